@@ -1,4 +1,4 @@
-from .api import BacktestEngine, BacktestConfig
+from .api import BacktestEngine, BacktestConfig, CancelToken, PaperTrader, Replayer
 from .strategy import Strategy
 from .analyzers import (
     compute_drawdown_segments,
@@ -12,14 +12,114 @@ from .analyzers import (
 
 # Import vectorized functions from Rust if available
 try:
-    from engine_rust import compute_sma, compute_rsi
+    from engine_rust import (
+        compute_sma,
+        compute_rsi,
+        compute_donchian,
+        compute_psar,
+        compute_rolling_std,
+        compute_rolling_var,
+        compute_rolling_min,
+        compute_rolling_max,
+        compute_zscore,
+        compute_rolling_corr,
+        compute_linreg,
+        compute_mfi,
+        compute_roc,
+        compute_momentum,
+        compute_ema,
+        compute_trix,
+        compute_wma,
+        compute_hma,
+        compute_kama,
+        compute_pivots,
+        compute_indicators,
+        compute_indicators_multi,
+        compute_sma_np,
+        compute_rsi_np,
+        compute_ema_np,
+        compute_wma_np,
+        compute_hma_np,
+        compute_roc_np,
+        compute_momentum_np,
+        compute_trix_np,
+        compute_rolling_max_np,
+        compute_rolling_min_np,
+        compute_rolling_std_np,
+        compute_zscore_np,
+        compute_kama_np,
+        compute_rolling_var_np,
+        compute_donchian_np,
+        compute_psar_np,
+        compute_rolling_corr_np,
+        compute_linreg_np,
+        compute_mfi_np,
+        compute_indicators_np,
+        rolling_apply,
+        compute_cross,
+        optimize_grid,
+        optimize_random,
+        purged_kfold_split,
+        combinatorial_purged_split,
+        combine_strategies,
+    )
 except ImportError:
     compute_sma = None
     compute_rsi = None
+    compute_donchian = None
+    compute_psar = None
+    compute_rolling_std = None
+    compute_rolling_var = None
+    compute_rolling_min = None
+    compute_rolling_max = None
+    compute_zscore = None
+    compute_rolling_corr = None
+    compute_linreg = None
+    compute_mfi = None
+    compute_roc = None
+    compute_momentum = None
+    compute_ema = None
+    compute_trix = None
+    compute_wma = None
+    compute_hma = None
+    compute_kama = None
+    compute_pivots = None
+    compute_indicators = None
+    compute_indicators_multi = None
+    compute_sma_np = None
+    compute_rsi_np = None
+    compute_ema_np = None
+    compute_wma_np = None
+    compute_hma_np = None
+    compute_roc_np = None
+    compute_momentum_np = None
+    compute_trix_np = None
+    compute_rolling_max_np = None
+    compute_rolling_min_np = None
+    compute_rolling_std_np = None
+    compute_zscore_np = None
+    compute_kama_np = None
+    compute_rolling_var_np = None
+    compute_donchian_np = None
+    compute_psar_np = None
+    compute_rolling_corr_np = None
+    compute_linreg_np = None
+    compute_mfi_np = None
+    compute_indicators_np = None
+    rolling_apply = None
+    compute_cross = None
+    optimize_grid = None
+    optimize_random = None
+    purged_kfold_split = None
+    combinatorial_purged_split = None
+    combine_strategies = None
 
 __all__ = [
     "BacktestEngine",
     "BacktestConfig",
+    "CancelToken",
+    "PaperTrader",
+    "Replayer",
     "Strategy",
     "compute_drawdown_segments",
     "round_trips_from_trades",
@@ -30,4 +130,51 @@ __all__ = [
     "generate_analysis_report",
     "compute_sma",
     "compute_rsi",
+    "compute_donchian",
+    "compute_psar",
+    "compute_rolling_std",
+    "compute_rolling_var",
+    "compute_rolling_min",
+    "compute_rolling_max",
+    "compute_zscore",
+    "compute_rolling_corr",
+    "compute_linreg",
+    "compute_mfi",
+    "compute_roc",
+    "compute_momentum",
+    "compute_ema",
+    "compute_trix",
+    "compute_wma",
+    "compute_hma",
+    "compute_kama",
+    "compute_pivots",
+    "compute_indicators",
+    "compute_indicators_multi",
+    "compute_sma_np",
+    "compute_rsi_np",
+    "compute_ema_np",
+    "compute_wma_np",
+    "compute_hma_np",
+    "compute_roc_np",
+    "compute_momentum_np",
+    "compute_trix_np",
+    "compute_rolling_max_np",
+    "compute_rolling_min_np",
+    "compute_rolling_std_np",
+    "compute_zscore_np",
+    "compute_kama_np",
+    "compute_rolling_var_np",
+    "compute_donchian_np",
+    "compute_psar_np",
+    "compute_rolling_corr_np",
+    "compute_linreg_np",
+    "compute_mfi_np",
+    "compute_indicators_np",
+    "rolling_apply",
+    "compute_cross",
+    "optimize_grid",
+    "optimize_random",
+    "purged_kfold_split",
+    "combinatorial_purged_split",
+    "combine_strategies",
 ] 
\ No newline at end of file
@@ -1,8 +1,8 @@
 from __future__ import annotations
-from typing import Any, Dict, List
+from typing import Any, Dict, List, Optional, Union
 
 try:
-    from engine_rust import BacktestEngine as _RustBacktestEngine, BacktestConfig  # type: ignore
+    from engine_rust import BacktestEngine as _RustBacktestEngine, BacktestConfig, CancelToken, PaperTrader, Replayer  # type: ignore
 except Exception as exc:  # pragma: no cover
     raise RuntimeError("engine_rust extension is not built. Run 'maturin develop' under rust/engine_rust'.") from exc
 
@@ -15,12 +15,128 @@ class BacktestEngine:
     def __init__(self, cfg: BacktestConfig) -> None:
         self._engine = _RustBacktestEngine(cfg)
 
-    def run(self, strategy: Any, bars: List[Dict[str, Any]]) -> Dict[str, Any]:
-        return self._engine.run(strategy, bars)  # type: ignore[no-any-return]
+    def run(
+        self,
+        strategy: Any,
+        bars: Any,
+        indicators: Optional[List[Dict[str, Any]]] = None,
+        progress_callback: Any = None,
+        progress_every: int = 0,
+        cancel_token: Optional[CancelToken] = None,
+        checkpoint_path: Optional[str] = None,
+        checkpoint_every: int = 0,
+        resume: bool = False,
+        timeframes: Optional[List[str]] = None,
+        benchmark: Any = None,
+        results_db: Optional[str] = None,
+        run_id: Optional[str] = None,
+    ) -> Dict[str, Any]:
+        """
+        Run single-feed backtest. `bars` is a list of bar dicts, a dict of columns
+        (numpy arrays or lists, `close` required), a pyarrow Table/RecordBatch or a pandas/polars
+        DataFrame (no `.to_dict("records")` needed). For out-of-core data, `bars` may also be
+        an iterator/generator (of bars or chunks), a DB-API cursor or a pyarrow RecordBatchReader;
+        the engine pulls one batch at a time (indicators and checkpoints are not supported then). `indicators` is an optional list of indicator specs
+        (same format as `compute_indicators`); the engine precomputes them in Rust and
+        injects each value into the bar dict, e.g. bar["sma_20"].
+        `progress_callback` is either `callback(done, total, elapsed)` or a tqdm-like object
+        with `update(n)`, invoked every `progress_every` bars (0 = about every 1%).
+        Ctrl+C or `cancel_token.cancel()` stops the run early and returns partial results
+        with result["cancelled"] = True.
+        With `checkpoint_path`, engine state is saved at least every `checkpoint_every` bars
+        (0 = every batch), on cancellation and at the end; `resume=True` continues from it.
+        `timeframes` (e.g. ["15m", "1d"]) makes the engine resample incrementally during the run;
+        each bar then carries bar["timeframes"]["15m"], the higher-timeframe bar formed so far
+        (with a `complete` flag on its last primary bar).
+        `benchmark` (bars in any non-streaming format above) adds result["benchmark"]: a buy-and-hold
+        equity curve, stats and excess return of the benchmark over the strategy's date range;
+        BacktestConfig(buy_and_hold=True) adds the same for `bars` as result["buy_and_hold"].
+        With `results_db` (a DuckDB path), a background thread writes the equity curve and trades
+        to its `backtest_equity` / `backtest_trades` tables in batches during the run, and the result
+        no longer carries the `equity_curve` / `trades` lists; result["results_db"] reports the path,
+        run_id and rows written. `run_id` labels the rows (default: generated from the current time;
+        earlier rows with the same run_id are replaced).
+        """
+        return self._engine.run(  # type: ignore[no-any-return]
+            strategy, bars, indicators, progress_callback, progress_every, cancel_token,
+            checkpoint_path, checkpoint_every, resume, timeframes, benchmark, results_db, run_id,
+        )
+
+    def run_from_db(
+        self,
+        strategy: Any,
+        db_path: str,
+        symbol: str,
+        period: str,
+        start: Optional[str] = None,
+        end: Optional[str] = None,
+        indicators: Optional[List[Dict[str, Any]]] = None,
+        progress_callback: Any = None,
+        progress_every: int = 0,
+        cancel_token: Optional[CancelToken] = None,
+        timeframes: Optional[List[str]] = None,
+        benchmark: Any = None,
+    ) -> Dict[str, Any]:
+        """
+        Backtest bars loaded from DuckDB in Rust (same query as `get_market_data`);
+        bars never materialize as a Python list. Other arguments work as in `run()`.
+        """
+        return self._engine.run_from_db(  # type: ignore[no-any-return]
+            strategy, db_path, symbol, period, start, end, indicators,
+            progress_callback, progress_every, cancel_token, timeframes, benchmark,
+        )
+
+    def run_many(
+        self,
+        strategies: Any,
+        bars: Any,
+        indicators: Optional[List[Dict[str, Any]]] = None,
+    ) -> Dict[str, Any]:
+        """
+        Backtest several strategies over the same bars, extracted once in Rust.
+        `strategies` is a dict {name: strategy} or a list (named by class name).
+        Returns {"results": {name: result}, "table": [{"name": name, **stats}, ...]}.
+        """
+        return self._engine.run_many(strategies, bars, indicators)  # type: ignore[no-any-return]
 
-    def run_multi(self, strategy: Any, feeds: Dict[str, List[Dict[str, Any]]]) -> Dict[str, Any]:
+    def run_signals(self, signals: List[Optional[float]], bars: Any, mode: str = "position") -> Dict[str, Any]:
         """
-        Run multi-asset/multi-feed backtest. Feeds is a dict: {feed_id: list[bar]}.
+        Vectorized backtest from a precomputed per-bar array of target positions
+        (mode="position") or target weights (mode="weight"); None keeps the current position.
+        The whole loop runs in Rust without Python callbacks.
+        """
+        return self._engine.run_signals(signals, bars, mode)  # type: ignore[no-any-return]
+
+    def run_rules(self, rules: str, bars: Any, size: float = 1.0, mode: str = "position") -> Dict[str, Any]:
+        """
+        Backtest a rule-DSL strategy, e.g. "BUY when sma(20) > sma(50); SELL when cross_below".
+        Rules are parsed and evaluated in Rust; no Python callbacks run during the backtest.
+        """
+        return self._engine.run_rules(rules, bars, size, mode)  # type: ignore[no-any-return]
+
+    def run_multi(
+        self,
+        strategy: Any,
+        feeds: Dict[str, Any],
+        progress_callback: Any = None,
+        progress_every: int = 0,
+        cancel_token: Optional[CancelToken] = None,
+        lot_size: Optional[Union[float, Dict[str, float]]] = None,
+        rebalance_band: float = 0.0,
+    ) -> Dict[str, Any]:
+        """
+        Run multi-asset/multi-feed backtest. Feeds is a dict: {feed_id: list[bar]};
+        each feed may also be a dict of columns, a pyarrow Table or a DataFrame, as in `run()`.
         Each bar should include at least: datetime, close; optional: open/high/low/volume/symbol.
+        Progress reporting and cancellation work as in `run()`; progress counts bars across all feeds.
+
+        next_multi() may return orders (each with a "symbol") or target weights: a {symbol: weight}
+        dict (only the listed symbols are adjusted) or a list/numpy array in `feeds` order.
+        Weights are converted to market orders at the current equity and latest prices.
+        `lot_size` rounds those order sizes toward zero: a number for every symbol or {symbol: lot}
+        (default: no rounding). Symbols whose weight is within `rebalance_band` of the target
+        (e.g. 0.02 = 2 percentage points) are not traded.
         """
-        return self._engine.run_multi(strategy, feeds)  # type: ignore[no-any-return] 
\ No newline at end of file
+        return self._engine.run_multi(  # type: ignore[no-any-return]
+            strategy, feeds, progress_callback, progress_every, cancel_token, lot_size, rebalance_band
+        ) 
\ No newline at end of file
@@ -189,7 +189,7 @@ class DBDataProvider:
         self,
         db_path: str,
         *,
-        fetch_fn: Callable[..., List[Mapping[str, object]]] = get_market_data_rust,
+        fetch_fn: Callable[..., Mapping[str, List[Mapping[str, object]]]] = get_market_data_rust,
         logger: Optional[logging.Logger] = None,
     ) -> None:
         self._db_path = db_path
@@ -202,15 +202,17 @@ class DBDataProvider:
         data: Dict[str, pd.DataFrame] = {}
         missing: List[MissingRange] = []
 
+        # One SQL query for all symbols; the Rust side returns {symbol: bars}
+        bars_by_symbol: Mapping[str, List[Mapping[str, object]]] = self._fetch_fn(
+            self._db_path,
+            list(request.symbols),
+            request.period,
+            request.start_time,
+            request.end_time,
+            request.count,
+        )
         for symbol in request.symbols:
-            bars: List[Mapping[str, object]] = self._fetch_fn(
-                self._db_path,
-                symbol,
-                request.period,
-                request.start_time,
-                request.end_time,
-                request.count,
-            )
+            bars = bars_by_symbol.get(symbol, [])
             df = _bars_to_dataframe(bars)
             data[symbol] = df
             missing.extend(
@@ -1,29 +1,22 @@
 from __future__ import annotations
-from typing import Any, Dict
 
+from engine_rust import Strategy as _RustStrategy  # type: ignore
 
-class Strategy:
-    def on_start(self, ctx: Any) -> None:
-        """回测开始时调用。"""
-        pass
 
-    def next(self, bar: Dict[str, Any]) -> str | Dict[str, Any] | None:
-        """
-        每根 bar 调用。返回以下之一：
-        - 字符串："BUY" 或 "SELL"（市价单、默认 size=1）
-        - 字典：{"action": "BUY"|"SELL", "type": "market"|"limit", "size": float, "price"?: float}
-        - None：不下单
-        """
-        return None
+class Strategy(_RustStrategy):
+    """
+    策略基类。所有钩子的默认空实现都由扩展中的 engine_rust.Strategy 提供，
+    引擎会跳过子类没有重写的钩子。next 每根 bar 调用，返回以下之一：
+    - 字符串："BUY" 或 "SELL"（市价单、默认 size=1）
+    - 字典：{"action": "BUY"|"SELL", "type": "market"|"limit", "size": float, "price"?: float}
+    - None：不下单
 
-    def on_order(self, event: Dict[str, Any]) -> None:
-        """订单事件：submitted/filled 等。"""
-        pass
-
-    def on_trade(self, event: Dict[str, Any]) -> None:
-        """成交事件。包含 order_id/side/price/size。"""
-        pass
-
-    def on_stop(self) -> None:
-        """回测结束时调用。"""
-        pass 
\ No newline at end of file
+    可选钩子（按需重写）：
+    - on_rebalance(ctx)：仅在 BacktestConfig 配置了 rebalance（daily/weekly/monthly/quarterly）
+      或 rebalance_dates 时，由引擎在调仓点（周期最后一根 bar，next 之后）调用；
+      返回值格式与 next 相同，多资产回测中也可以返回订单列表
+    - on_bar_end(ctx)：每根 bar 的成交全部处理完毕后调用（预热期除外），ctx 为成交后的账户快照
+    - on_session_end(ctx)：交易日最后一根 bar 处理完毕后调用（在 on_bar_end 之后），适合日终风控与盯市记录
+    - get_state() / set_state(state)：检查点保存与恢复策略状态；get_state 的返回值须可被 json.dumps 序列化，
+      set_state 在恢复时于 on_start 之后调用
+    """
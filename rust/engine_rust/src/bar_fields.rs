@@ -0,0 +1,113 @@
+//! 自定义 bar 字段模块
+//!
+//! 引擎提取 bar 时只保留 `datetime`/`open`/`high`/`low`/`close`/`volume`/`symbol`，
+//! 用户在数据里预先算好的特征（因子值、信号、行业代码等）会被丢掉，策略只能按 `bar_index`
+//! 回头去原始数据里查。这个模块保存这些额外字段，并在交给 `next()` 的 bar 字典中原样放回。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 提取 bar 时，标准字段以外的数值（整数、浮点数、布尔值）和字符串字段按出现顺序保存到 `BarData::extra`
+//! 2. 字段名在一次提取中只分配一次（`FieldNames` 缓存），每根 bar 只保存共享的名称和值
+//! 3. 构造 bar 字典时，标准字段之后写入额外字段，再写入引擎预计算的指标（同名时指标覆盖额外字段）
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! bars = [{"datetime": "2024-01-02", "close": 10.5, "momentum_20": 0.031, "sector": "bank"}, ...]
+//!
+//! class MyStrategy(Strategy):
+//!     def next(self, bar, ctx):
+//!         if bar["sector"] == "bank" and bar["momentum_20"] > 0.02:
+//!             return "BUY"
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 字典列表、逐根产出 bar 的迭代器、DB-API 游标、列式数据（列字典、pyarrow、DataFrame）和
+//!   `PaperTrader.push_bar()` 都会保留额外字段；从数据库读取的 bar（`run_from_db()` 等）只有标准字段
+//! - 只保留数值、布尔值和字符串，其他类型（列表、对象、`None` 等）的字段被忽略
+//! - 多资产回测中 `next_multi()` 收到的每个 bar 字典同样包含额外字段
+
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyLong, PyString};
+
+/// 引擎自身使用的字段（不作为额外字段保存）
+pub(crate) const STANDARD_FIELDS: [&str; 7] = ["datetime", "open", "high", "low", "close", "volume", "symbol"];
+
+/// 额外字段的值
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum FieldValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl FieldValue {
+    /// 从 Python 值提取；不是数值、布尔值或字符串时返回 `None`
+    pub(crate) fn extract(value: &PyAny) -> Option<Self> {
+        if let Ok(b) = value.downcast::<PyBool>() {
+            return Some(FieldValue::Bool(b.is_true()));
+        }
+        if value.is_instance_of::<PyLong>() {
+            return value.extract::<i64>().ok().map(FieldValue::Int);
+        }
+        if value.is_instance_of::<PyFloat>() {
+            return value.extract::<f64>().ok().map(FieldValue::Float);
+        }
+        if let Ok(s) = value.downcast::<PyString>() {
+            return s.to_str().ok().map(|s| FieldValue::Text(s.to_string()));
+        }
+        // numpy 标量等：能转换为浮点数即可
+        if value.hasattr("__float__").unwrap_or(false) {
+            return value.extract::<f64>().ok().map(FieldValue::Float);
+        }
+        None
+    }
+}
+
+impl ToPyObject for FieldValue {
+    fn to_object(&self, py: Python<'_>) -> PyObject {
+        match self {
+            FieldValue::Int(v) => v.to_object(py),
+            FieldValue::Float(v) => v.to_object(py),
+            FieldValue::Bool(v) => v.to_object(py),
+            FieldValue::Text(v) => v.to_object(py),
+        }
+    }
+}
+
+/// 一根 bar 上的额外字段（名称, 值），按出现顺序
+pub(crate) type ExtraFields = Vec<(Arc<str>, FieldValue)>;
+
+/// 把额外字段写入 bar 字典
+pub(crate) fn set_extra(dict: &Bound<'_, PyDict>, extra: &ExtraFields) -> PyResult<()> {
+    for (name, value) in extra {
+        dict.set_item(&**name, value)?;
+    }
+    Ok(())
+}
+
+/// 字段名缓存：同名字段在所有 bar 间共享一份字符串
+#[derive(Default)]
+pub(crate) struct FieldNames {
+    names: Vec<Arc<str>>,
+}
+
+impl FieldNames {
+    pub(crate) fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(existing) = self.names.iter().find(|n| &***n == name) {
+            return existing.clone();
+        }
+        let name: Arc<str> = Arc::from(name);
+        self.names.push(name.clone());
+        name
+    }
+}
+
+/// 是否为额外字段（不是引擎的标准字段）
+pub(crate) fn is_extra(name: &str) -> bool {
+    !STANDARD_FIELDS.contains(&name)
+}
@@ -0,0 +1,157 @@
+//! 买入持有基准模块
+//!
+//! 评价一个策略，第一个问题往往是"它跑赢了直接买入持有吗"。以前要回答这个问题，
+//! 用户需要在 Python 里自己用收盘价算一条买入持有曲线，再自己算统计指标，口径很容易和引擎不一致。
+//! 开启 `BacktestConfig(buy_and_hold=True)` 后，引擎在同一份数据上同时计算买入持有的净值曲线和统计指标；
+//! `run()` 传入 `benchmark` 数据时，还会对基准标的做同样的计算。
+//!
+//! ## 计算口径
+//!
+//! 1. 在策略净值曲线的第一根 bar（即预热期之后的第一根）以收盘价全仓买入，
+//!    买入价计入滑点（`slippage_bps`），并扣除手续费（`commission_rate`），之后一直持有
+//! 2. 每根 bar 的净值 = 持有数量 × 收盘价，与策略的 `equity_curve` 逐根对应
+//! 3. 基准标的只取策略净值曲线时间范围内的 bar，同样在第一根 bar 全仓买入
+//! 4. 统计指标与策略的 `stats` 使用完全相同的算法
+//!
+//! ## 结果字段
+//!
+//! - `buy_and_hold`: `{"equity_curve": [...], "stats": {...}, "excess_return": ...}`
+//! - `benchmark`: 仅在传入 `benchmark` 时存在，格式同上
+//!
+//! `excess_return` 为策略 `total_return` 减去基准 `total_return`。
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! cfg = BacktestConfig("2020-01-01", "2023-12-31", 100000.0, buy_and_hold=True)
+//! result = BacktestEngine(cfg).run(MyStrategy(), bars, benchmark=index_bars)
+//! print(result["buy_and_hold"]["excess_return"], result["benchmark"]["stats"]["sharpe"])
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 买入持有曲线不做分红、拆股调整，数据本身是否复权决定了结果口径
+//! - `run_multi` 不计算买入持有基准
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::database::parse_datetime;
+use crate::{BacktestConfig, BacktestEngine, BarData};
+
+/// 增量计算的买入持有净值曲线
+#[derive(Clone, Debug)]
+pub(crate) struct BuyAndHold {
+    cash: f64,
+    commission_rate: f64,
+    slippage: f64,
+    /// 持有数量（第一根 bar 买入后确定）
+    shares: Option<f64>,
+    curve: Vec<(Option<String>, f64)>,
+}
+
+impl BuyAndHold {
+    pub(crate) fn new(cfg: &BacktestConfig) -> Self {
+        Self {
+            cash: cfg.cash,
+            commission_rate: cfg.commission_rate,
+            slippage: cfg.slippage_bps / 10_000.0,
+            shares: None,
+            curve: Vec::new(),
+        }
+    }
+
+    /// 在一组 bar 上计算
+    pub(crate) fn from_bars(cfg: &BacktestConfig, bars: &[BarData]) -> Self {
+        let mut bh = Self::new(cfg);
+        for bar in bars {
+            bh.update(bar);
+        }
+        bh
+    }
+
+    /// 追加一根 bar；第一根 bar 以收盘价全仓买入
+    pub(crate) fn update(&mut self, bar: &BarData) {
+        let shares = *self.shares.get_or_insert_with(|| {
+            let cost_per_share = bar.close * (1.0 + self.slippage) * (1.0 + self.commission_rate);
+            if cost_per_share > 0.0 { self.cash / cost_per_share } else { 0.0 }
+        });
+        let equity = if shares > 0.0 { shares * bar.close } else { self.cash };
+        self.curve.push((bar.datetime.clone(), equity));
+    }
+}
+
+/// 净值曲线第一个和最后一个点的时间
+pub(crate) fn curve_range(curve: &[(Option<String>, f64)]) -> (Option<String>, Option<String>) {
+    (curve.first().and_then(|(dt, _)| dt.clone()), curve.last().and_then(|(dt, _)| dt.clone()))
+}
+
+/// 在结果字典中写入 `buy_and_hold` 与 `benchmark`
+///
+/// `range` 为策略净值曲线的时间范围，基准数据只取该范围内的 bar（无法解析的时间总是保留）。
+pub(crate) fn mark_result(
+    py: Python<'_>,
+    result: &PyObject,
+    cfg: &BacktestConfig,
+    buy_and_hold: Option<BuyAndHold>,
+    benchmark: Option<&[BarData]>,
+    range: (Option<String>, Option<String>),
+) -> PyResult<()> {
+    if buy_and_hold.is_none() && benchmark.is_none() {
+        return Ok(());
+    }
+    let result = result.downcast_bound::<PyDict>(py)?;
+    let strategy_return = total_return(result.get_item("stats")?)?;
+    if let Some(bh) = buy_and_hold {
+        result.set_item("buy_and_hold", baseline_dict(py, cfg, bh.curve, strategy_return)?)?;
+    }
+    if let Some(bars) = benchmark {
+        let first = range.0.as_deref().and_then(parse_datetime);
+        let last = range.1.as_deref().and_then(parse_datetime);
+        let mut bh = BuyAndHold::new(cfg);
+        for bar in bars {
+            let in_range = match bar.datetime.as_deref().and_then(parse_datetime) {
+                Some(dt) => first.is_none_or(|f| dt >= f) && last.is_none_or(|l| dt <= l),
+                None => true,
+            };
+            if in_range {
+                bh.update(bar);
+            }
+        }
+        result.set_item("benchmark", baseline_dict(py, cfg, bh.curve, strategy_return)?)?;
+    }
+    Ok(())
+}
+
+fn baseline_dict<'py>(
+    py: Python<'py>,
+    cfg: &BacktestConfig,
+    curve: Vec<(Option<String>, f64)>,
+    strategy_return: Option<f64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new_bound(py);
+    let eq_list = PyList::empty_bound(py);
+    for (dt, eq) in &curve {
+        let row = PyDict::new_bound(py);
+        row.set_item("datetime", dt)?;
+        row.set_item("equity", eq)?;
+        eq_list.append(row)?;
+    }
+    out.set_item("equity_curve", eq_list)?;
+    let stats = BacktestEngine::compute_enhanced_stats(py, &curve, &[], cfg.periods_per_year())?;
+    let baseline_return = total_return(Some(stats.bind(py).clone()))?;
+    out.set_item("stats", stats)?;
+    let excess = match (strategy_return, baseline_return) {
+        (Some(s), Some(b)) => Some(s - b),
+        _ => None,
+    };
+    out.set_item("excess_return", excess)?;
+    Ok(out)
+}
+
+fn total_return(stats: Option<Bound<'_, PyAny>>) -> PyResult<Option<f64>> {
+    match stats {
+        Some(stats) => stats.downcast::<PyDict>()?.get_item("total_return")?.map(|v| v.extract()).transpose(),
+        None => Ok(None),
+    }
+}
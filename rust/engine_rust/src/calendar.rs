@@ -0,0 +1,591 @@
+//! 交易日历模块
+//!
+//! 引擎原来只能从数据本身推断交易日：下一根 bar 的日期不同就认为"今天结束了"，
+//! 年化一律按 252 天，重采样和缺口检测也不知道哪些日子本来就休市。
+//! 这个模块内置了几个常用交易所的交易日历（节假日与提前收盘日），供 Python 直接查询，
+//! 并在重采样、缺口检测、年化和调仓计划中使用。
+//!
+//! ## 内置日历
+//!
+//! | 名称 | 别名 | 规则 | 年化天数 |
+//! |------|------|------|----------|
+//! | `SSE` | `XSHG` | 上交所：周末 + 国务院公布的节假日（内置 2019–2026 年），无提前收盘 | 242 |
+//! | `SZSE` | `XSHE` | 深交所：与上交所相同 | 242 |
+//! | `NYSE` | `XNYS` | 纽交所：周末 + 按规则推算的法定假日（含周末顺延）和临时休市；7 月 3 日、感恩节次日、平安夜 13:00 提前收盘 | 252 |
+//! | `CME` | `XCME`, `GLOBEX` | CME Globex（股指、利率期货口径）：元旦、耶稣受难日、圣诞休市；其他美国假日 12:00（芝加哥时间）提前收盘，纽交所提前收盘日 12:15 收盘 | 252 |
+//! | `CRYPTO` | `24/7` | 加密货币：每天都是交易日 | 365 |
+//! | `SHFE`、`INE` | `XSGE` | 上期所、上期能源：交易日同上交所，夜盘 21:00–02:30 | 242 |
+//! | `DCE`、`CZCE` | `XDCE`、`XZCE` | 大商所、郑商所：交易日同上交所，夜盘 21:00–23:00 | 242 |
+//! | `GFEX`、`CFFEX` | `XCFE` | 广期所、中金所：交易日同上交所，无夜盘 | 242 |
+//!
+//! ## 交易时段与夜盘
+//!
+//! 每个日历都带有交易所的交易时段定义（交易所当地时间，取该交易所所有品种的最大范围），
+//! 可通过 `sessions` 查询，格式与 `BacktestConfig(sessions=...)` 相同。
+//! 有夜盘的交易所（国内商品期货、CME Globex）按期货的惯例划分交易日：
+//! 日盘收盘之后的 bar 属于下一个交易日，周五夜盘属于下周一，节前最后一个夜盘属于节后第一个交易日，
+//! 凌晨的 bar（如 01:00）同样归入它所在夜盘的交易日。这样合成的日线与期货公司、交易所的日线一致。
+//!
+//! ## 在引擎中的使用
+//!
+//! - `BacktestConfig(calendar="SSE")`：年化收益、波动率、夏普、Calmar 使用日历的年化天数；
+//!   周期性调仓（`rebalance`）按日历判断周期末：数据缺少某些交易日时仍在日历上的最后一个交易日调仓，
+//!   数据在周期中间结束时最后一根 bar 不再被当作周期末
+//! - `resample_klines(bars, "1d", calendar="SSE")`：休市日的 bar 归入下一个交易日，
+//!   多日周期（如 `"5d"`）按交易日计数；使用期货交易所日历（如 `"SHFE"`）时夜盘 bar 归入下一个交易日
+//! - `find_gaps(..., calendar="SSE")`：按日历推算应有数据的交易日
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import TradingCalendar
+//!
+//! cal = TradingCalendar("SSE")
+//! cal.is_trading_day("2024-10-01")            # False（国庆）
+//! cal.next_trading_day("2024-09-30")          # "2024-10-08"
+//! len(cal.trading_days("2024-01-01", "2024-12-31"))  # 242
+//! TradingCalendar("NYSE").early_close("2024-11-29")  # "13:00"
+//!
+//! # 期货夜盘：周五 21:30 的 bar 属于下周一
+//! TradingCalendar("SHFE").trading_day("2024-01-05 21:30:00")  # "2024-01-08"
+//!
+//! # 补充内置数据没有的休市日（如更早年份或临时停市）
+//! cal = TradingCalendar("SSE", holidays=["2018-02-15", "2018-02-16"])
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 上交所/深交所（以及国内期货交易所）的节假日来自每年公布的安排，内置数据之外的年份只排除周末，可以用 `holidays` 补充
+//! - 交易时段按交易所汇总，不区分品种（如上期所只有部分品种交易到 02:30）；夜盘划分只依赖日盘收盘时间，不受影响
+//! - 提前收盘时间与交易时段均为交易所当地时间，bar 时间需要先转换到当地时间（见 `get_market_data(tz=...)`）
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+
+use crate::database::parse_datetime;
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 内置日历对应的交易所
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Exchange {
+    Sse,
+    Szse,
+    Nyse,
+    Cme,
+    Crypto,
+    Shfe,
+    Ine,
+    Dce,
+    Czce,
+    Gfex,
+    Cffex,
+}
+
+impl Exchange {
+    const ALL: [Exchange; 11] = [
+        Exchange::Sse,
+        Exchange::Szse,
+        Exchange::Nyse,
+        Exchange::Cme,
+        Exchange::Crypto,
+        Exchange::Shfe,
+        Exchange::Ine,
+        Exchange::Dce,
+        Exchange::Czce,
+        Exchange::Gfex,
+        Exchange::Cffex,
+    ];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_uppercase().as_str() {
+            "SSE" | "XSHG" => Some(Exchange::Sse),
+            "SZSE" | "XSHE" => Some(Exchange::Szse),
+            "NYSE" | "XNYS" => Some(Exchange::Nyse),
+            "CME" | "XCME" | "GLOBEX" => Some(Exchange::Cme),
+            "CRYPTO" | "24/7" => Some(Exchange::Crypto),
+            "SHFE" | "XSGE" => Some(Exchange::Shfe),
+            "INE" => Some(Exchange::Ine),
+            "DCE" | "XDCE" => Some(Exchange::Dce),
+            "CZCE" | "XZCE" => Some(Exchange::Czce),
+            "GFEX" => Some(Exchange::Gfex),
+            "CFFEX" | "XCFE" => Some(Exchange::Cffex),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Exchange::Sse => "SSE",
+            Exchange::Szse => "SZSE",
+            Exchange::Nyse => "NYSE",
+            Exchange::Cme => "CME",
+            Exchange::Crypto => "CRYPTO",
+            Exchange::Shfe => "SHFE",
+            Exchange::Ine => "INE",
+            Exchange::Dce => "DCE",
+            Exchange::Czce => "CZCE",
+            Exchange::Gfex => "GFEX",
+            Exchange::Cffex => "CFFEX",
+        }
+    }
+
+    /// 日盘交易时段 `(开始时, 开始分, 结束时, 结束分)`，按时间顺序排列
+    fn day_sessions(self) -> &'static [(u32, u32, u32, u32)] {
+        match self {
+            Exchange::Sse | Exchange::Szse => &[(9, 30, 11, 30), (13, 0, 15, 0)],
+            // 国债期货 09:15–15:15，股指期货 09:30–15:00
+            Exchange::Cffex => &[(9, 15, 11, 30), (13, 0, 15, 15)],
+            Exchange::Shfe | Exchange::Ine | Exchange::Dce | Exchange::Czce | Exchange::Gfex => {
+                &[(9, 0, 10, 15), (10, 30, 11, 30), (13, 30, 15, 0)]
+            }
+            Exchange::Nyse => &[(9, 30, 16, 0)],
+            // Globex 交易日的白天部分（芝加哥时间），前一晚 17:00 起的部分见夜盘
+            Exchange::Cme => &[(0, 0, 16, 0)],
+            Exchange::Crypto => &[(0, 0, 23, 59)],
+        }
+    }
+
+    /// 夜盘时段；开始时间晚于结束时间表示跨越午夜。夜盘属于下一个交易日
+    fn night_session(self) -> Option<(u32, u32, u32, u32)> {
+        match self {
+            Exchange::Shfe | Exchange::Ine => Some((21, 0, 2, 30)),
+            Exchange::Dce | Exchange::Czce => Some((21, 0, 23, 0)),
+            Exchange::Cme => Some((17, 0, 23, 59)),
+            _ => None,
+        }
+    }
+}
+
+fn hm(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap_or(NaiveTime::MIN)
+}
+
+/// 时间是否落在时段内（两端都包含；开始晚于结束表示跨越午夜）
+fn in_session(t: NaiveTime, (h1, m1, h2, m2): (u32, u32, u32, u32)) -> bool {
+    let (start, end) = (hm(h1, m1), hm(h2, m2));
+    if start <= end {
+        start <= t && t <= end
+    } else {
+        t >= start || t <= end
+    }
+}
+
+fn format_session((h1, m1, h2, m2): (u32, u32, u32, u32)) -> String {
+    format!("{:02}:{:02}-{:02}:{:02}", h1, m1, h2, m2)
+}
+
+/// 上交所/深交所休市的工作日（周末本来就休市，不在表内），按年份列出 (月, 日)
+const CN_HOLIDAYS: &[(i32, &[(u32, u32)])] = &[
+    (2019, &[(1, 1), (2, 4), (2, 5), (2, 6), (2, 7), (2, 8), (4, 5), (5, 1), (5, 2), (5, 3), (6, 7), (9, 13), (10, 1), (10, 2), (10, 3), (10, 4), (10, 7)]),
+    (2020, &[(1, 1), (1, 24), (1, 27), (1, 28), (1, 29), (1, 30), (1, 31), (4, 6), (5, 1), (5, 4), (5, 5), (6, 25), (6, 26), (10, 1), (10, 2), (10, 5), (10, 6), (10, 7), (10, 8)]),
+    (2021, &[(1, 1), (2, 11), (2, 12), (2, 15), (2, 16), (2, 17), (4, 5), (5, 3), (5, 4), (5, 5), (6, 14), (9, 20), (9, 21), (10, 1), (10, 4), (10, 5), (10, 6), (10, 7)]),
+    (2022, &[(1, 3), (1, 31), (2, 1), (2, 2), (2, 3), (2, 4), (4, 4), (4, 5), (5, 2), (5, 3), (5, 4), (6, 3), (9, 12), (10, 3), (10, 4), (10, 5), (10, 6), (10, 7)]),
+    (2023, &[(1, 2), (1, 23), (1, 24), (1, 25), (1, 26), (1, 27), (4, 5), (5, 1), (5, 2), (5, 3), (6, 22), (6, 23), (9, 29), (10, 2), (10, 3), (10, 4), (10, 5), (10, 6)]),
+    (2024, &[(1, 1), (2, 9), (2, 12), (2, 13), (2, 14), (2, 15), (2, 16), (4, 4), (4, 5), (5, 1), (5, 2), (5, 3), (6, 10), (9, 16), (9, 17), (10, 1), (10, 2), (10, 3), (10, 4), (10, 7)]),
+    (2025, &[(1, 1), (1, 28), (1, 29), (1, 30), (1, 31), (2, 3), (2, 4), (4, 4), (5, 1), (5, 2), (5, 5), (6, 2), (10, 1), (10, 2), (10, 3), (10, 6), (10, 7), (10, 8)]),
+    (2026, &[(1, 1), (1, 2), (2, 16), (2, 17), (2, 18), (2, 19), (2, 20), (2, 23), (4, 6), (5, 1), (5, 4), (5, 5), (6, 19), (9, 25), (10, 1), (10, 2), (10, 5), (10, 6), (10, 7)]),
+];
+
+/// 纽交所临时休市（按规则推算不出来的日子）
+const NYSE_SPECIAL_CLOSURES: &[(i32, u32, u32)] = &[
+    (2001, 9, 11),
+    (2001, 9, 12),
+    (2001, 9, 13),
+    (2001, 9, 14),
+    (2004, 6, 11),
+    (2007, 1, 2),
+    (2012, 10, 29),
+    (2012, 10, 30),
+    (2018, 12, 5),
+    (2025, 1, 9),
+];
+
+fn is_weekend(d: NaiveDate) -> bool {
+    matches!(d.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+fn is_cn_holiday(d: NaiveDate) -> bool {
+    CN_HOLIDAYS
+        .iter()
+        .find(|(year, _)| *year == d.year())
+        .is_some_and(|(_, days)| days.contains(&(d.month(), d.day())))
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap_or(NaiveDate::MIN)
+}
+
+/// 某月第 n 个星期几（n 为 5 时表示最后一个）
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+        .or_else(|| NaiveDate::from_weekday_of_month_opt(year, month, weekday, n - 1))
+        .unwrap_or(NaiveDate::MIN)
+}
+
+/// 复活节（公历，匿名算法）
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    ymd(year, month as u32, day as u32)
+}
+
+/// 固定日期的假日落在周末时的顺延：周六提前到周五，周日顺延到周一
+fn observed(d: NaiveDate) -> NaiveDate {
+    match d.weekday() {
+        Weekday::Sat => d - Duration::days(1),
+        Weekday::Sun => d + Duration::days(1),
+        _ => d,
+    }
+}
+
+/// 美国市场的法定假日（已按 NYSE 规则调整到工作日）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UsHoliday {
+    NewYear,
+    MartinLutherKing,
+    Presidents,
+    GoodFriday,
+    Memorial,
+    Juneteenth,
+    Independence,
+    Labor,
+    Thanksgiving,
+    Christmas,
+}
+
+/// 某一天是否为美国市场的法定假日
+fn us_holiday(d: NaiveDate) -> Option<UsHoliday> {
+    let year = d.year();
+    // 元旦落在周六时不在前一年的 12 月 31 日补休
+    let new_year = ymd(year, 1, 1);
+    let mut holidays = vec![
+        (UsHoliday::MartinLutherKing, nth_weekday(year, 1, Weekday::Mon, 3)),
+        (UsHoliday::Presidents, nth_weekday(year, 2, Weekday::Mon, 3)),
+        (UsHoliday::GoodFriday, easter(year) - Duration::days(2)),
+        (UsHoliday::Memorial, nth_weekday(year, 5, Weekday::Mon, 5)),
+        (UsHoliday::Independence, observed(ymd(year, 7, 4))),
+        (UsHoliday::Labor, nth_weekday(year, 9, Weekday::Mon, 1)),
+        (UsHoliday::Thanksgiving, nth_weekday(year, 11, Weekday::Thu, 4)),
+        (UsHoliday::Christmas, observed(ymd(year, 12, 25))),
+    ];
+    if new_year.weekday() != Weekday::Sat {
+        holidays.push((UsHoliday::NewYear, observed(new_year)));
+    }
+    if year >= 2022 {
+        holidays.push((UsHoliday::Juneteenth, observed(ymd(year, 6, 19))));
+    }
+    holidays.into_iter().find(|(_, date)| *date == d).map(|(h, _)| h)
+}
+
+fn is_nyse_special_closure(d: NaiveDate) -> bool {
+    NYSE_SPECIAL_CLOSURES.contains(&(d.year(), d.month(), d.day()))
+}
+
+/// 纽交所 13:00 提前收盘日：7 月 3 日（周一至周四）、感恩节次日、平安夜（周一至周四）
+fn is_nyse_half_day(d: NaiveDate) -> bool {
+    let weekday_before_holiday = |month, day| {
+        d == ymd(d.year(), month, day) && !matches!(d.weekday(), Weekday::Fri | Weekday::Sat | Weekday::Sun)
+    };
+    weekday_before_holiday(7, 3)
+        || d == nth_weekday(d.year(), 11, Weekday::Thu, 4) + Duration::days(1)
+        || weekday_before_holiday(12, 24)
+}
+
+/// 交易日历
+///
+/// 用交易所名称构造（`"SSE"`、`"SZSE"`、`"NYSE"`、`"CME"`、`"CRYPTO"`、国内期货交易所如 `"SHFE"` 及其别名，不区分大小写），
+/// 可以用 `holidays` 补充额外的休市日。所有日期参数接受 `"YYYY-MM-DD"` 或带时间的字符串（只取日期部分）。
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradingCalendar {
+    exchange: Exchange,
+    /// 额外的休市日（已排序、去重）
+    extra_holidays: Vec<NaiveDate>,
+}
+
+impl TradingCalendar {
+    /// 按名称解析内置日历，名称无法识别时返回错误信息
+    pub(crate) fn parse(name: &str) -> Result<Self, String> {
+        let exchange = Exchange::parse(name).ok_or_else(|| {
+            format!(
+                "Unknown trading calendar '{}': expected one of {}",
+                name,
+                Exchange::ALL.map(Exchange::name).join(", ")
+            )
+        })?;
+        Ok(Self { exchange, extra_holidays: Vec::new() })
+    }
+
+    /// 从 Python 参数获取日历：`TradingCalendar` 对象或日历名称
+    pub(crate) fn from_py(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        if let Ok(cal) = obj.extract::<TradingCalendar>() {
+            return Ok(cal);
+        }
+        let name = obj.downcast::<PyString>().map_err(|_| {
+            value_error("calendar must be a TradingCalendar or a calendar name such as 'SSE'".to_string())
+        })?;
+        Self::parse(&name.to_cow()?).map_err(value_error)
+    }
+
+    /// 是否为交易日
+    pub(crate) fn is_trading_day(&self, d: NaiveDate) -> bool {
+        if self.extra_holidays.binary_search(&d).is_ok() {
+            return false;
+        }
+        match self.exchange {
+            Exchange::Crypto => true,
+            Exchange::Nyse => !is_weekend(d) && us_holiday(d).is_none() && !is_nyse_special_closure(d),
+            Exchange::Cme => {
+                !is_weekend(d)
+                    && !matches!(
+                        us_holiday(d),
+                        Some(UsHoliday::NewYear | UsHoliday::GoodFriday | UsHoliday::Christmas)
+                    )
+                    && !is_nyse_special_closure(d)
+            }
+            _ => !is_weekend(d) && !is_cn_holiday(d),
+        }
+    }
+
+    /// 提前收盘的交易日返回收盘时间（交易所当地时间），其他日子为 `None`
+    pub(crate) fn early_close(&self, d: NaiveDate) -> Option<NaiveTime> {
+        if !self.is_trading_day(d) {
+            return None;
+        }
+        match self.exchange {
+            Exchange::Nyse if is_nyse_half_day(d) => NaiveTime::from_hms_opt(13, 0, 0),
+            // 其余美国假日 Globex 只交易到中午
+            Exchange::Cme if us_holiday(d).is_some() => NaiveTime::from_hms_opt(12, 0, 0),
+            Exchange::Cme if is_nyse_half_day(d) => NaiveTime::from_hms_opt(12, 15, 0),
+            _ => None,
+        }
+    }
+
+    /// `d` 之后（不含 `d`）的第一个交易日
+    pub(crate) fn next_trading_day(&self, d: NaiveDate) -> NaiveDate {
+        let mut next = d + Duration::days(1);
+        while !self.is_trading_day(next) && next < NaiveDate::MAX {
+            next += Duration::days(1);
+        }
+        next
+    }
+
+    /// `d` 之前（不含 `d`）的最后一个交易日
+    pub(crate) fn previous_trading_day(&self, d: NaiveDate) -> NaiveDate {
+        let mut prev = d - Duration::days(1);
+        while !self.is_trading_day(prev) && prev > NaiveDate::MIN {
+            prev -= Duration::days(1);
+        }
+        prev
+    }
+
+    /// `d` 所属的交易日：交易日为自身，休市日归入下一个交易日
+    pub(crate) fn session_date(&self, d: NaiveDate) -> NaiveDate {
+        if self.is_trading_day(d) { d } else { self.next_trading_day(d) }
+    }
+
+    /// bar 时间所属的交易日
+    ///
+    /// 有夜盘的交易所，日盘收盘之后的时间属于下一个交易日；凌晨（夜盘跨越午夜的部分）与
+    /// 日盘同样归入当天或之后的第一个交易日，因此周六凌晨的 bar 属于下周一。
+    pub(crate) fn trading_day(&self, dt: NaiveDateTime) -> NaiveDate {
+        let after_day_close = self.exchange.night_session().is_some()
+            && self.exchange.day_sessions().last().is_some_and(|&(_, _, h, m)| dt.time() > hm(h, m));
+        if after_day_close {
+            self.next_trading_day(dt.date())
+        } else {
+            self.session_date(dt.date())
+        }
+    }
+
+    /// 某个时间点交易所是否开市（在交易日的交易时段内）
+    ///
+    /// 夜盘按所属交易日判断：国内期货在下一个交易日紧接着的工作日晚上开夜盘（节前最后一晚没有夜盘，
+    /// 周五夜盘正常），CME 在下一个交易日的前一晚 17:00 开盘（周日晚开盘、周五晚不开）。
+    pub(crate) fn is_open(&self, dt: NaiveDateTime) -> bool {
+        let t = dt.time();
+        if self.is_trading_day(dt.date()) && self.exchange.day_sessions().iter().any(|&s| in_session(t, s)) {
+            return true;
+        }
+        let Some(night) = self.exchange.night_session().filter(|&n| in_session(t, n)) else { return false };
+        // 夜盘开始的那个晚上
+        let evening = if t >= hm(night.0, night.1) { dt.date() } else { dt.date() - Duration::days(1) };
+        if self.exchange == Exchange::Cme {
+            self.is_trading_day(evening + Duration::days(1))
+        } else {
+            let next_weekday = if evening.weekday() == Weekday::Fri { evening + Duration::days(3) } else { evening + Duration::days(1) };
+            self.is_trading_day(evening) && self.next_trading_day(evening) == next_weekday
+        }
+    }
+
+    /// `[start, end]` 内的交易日
+    pub(crate) fn trading_days(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        start.iter_days().take_while(|d| *d <= end).filter(|d| self.is_trading_day(*d)).collect()
+    }
+
+    /// 年化使用的每年交易日数
+    pub(crate) fn days_per_year(&self) -> f64 {
+        match self.exchange {
+            Exchange::Nyse | Exchange::Cme => 252.0,
+            Exchange::Crypto => 365.0,
+            _ => 242.0,
+        }
+    }
+}
+
+fn parse_date(date: &str) -> PyResult<NaiveDate> {
+    parse_datetime(date.trim())
+        .map(|dt| dt.date())
+        .ok_or_else(|| value_error(format!("Invalid date '{}': expected 'YYYY-MM-DD'", date)))
+}
+
+fn format_date(d: NaiveDate) -> String {
+    d.format("%Y-%m-%d").to_string()
+}
+
+#[pymethods]
+impl TradingCalendar {
+    /// 创建交易日历
+    ///
+    /// # 参数
+    ///
+    /// - `name`: 日历名称（`"SSE"`、`"SZSE"`、`"NYSE"`、`"CME"`、`"CRYPTO"`、`"SHFE"`、`"INE"`、`"DCE"`、`"CZCE"`、`"GFEX"`、`"CFFEX"` 或别名）
+    /// - `holidays`: 可选的额外休市日列表（"YYYY-MM-DD"）
+    #[new]
+    #[pyo3(signature = (name, holidays=None))]
+    fn py_new(name: &str, holidays: Option<Vec<String>>) -> PyResult<Self> {
+        let mut cal = Self::parse(name).map_err(value_error)?;
+        if let Some(days) = holidays {
+            cal.extra_holidays = days.iter().map(|d| parse_date(d)).collect::<PyResult<_>>()?;
+            cal.extra_holidays.sort_unstable();
+            cal.extra_holidays.dedup();
+        }
+        Ok(cal)
+    }
+
+    /// 所有内置日历的名称
+    #[staticmethod]
+    fn names() -> Vec<&'static str> {
+        Exchange::ALL.iter().map(|e| e.name()).collect()
+    }
+
+    /// 日历名称
+    #[getter]
+    fn name(&self) -> &'static str {
+        self.exchange.name()
+    }
+
+    /// 年化使用的每年交易日数
+    #[getter(days_per_year)]
+    fn py_days_per_year(&self) -> f64 {
+        self.days_per_year()
+    }
+
+    /// 交易时段列表（"HH:MM-HH:MM"，交易所当地时间），有夜盘时夜盘排在最后
+    ///
+    /// 可直接用作 `BacktestConfig(sessions=...)`。
+    #[getter]
+    fn sessions(&self) -> Vec<String> {
+        let day = self.exchange.day_sessions().iter().copied();
+        day.chain(self.exchange.night_session()).map(format_session).collect()
+    }
+
+    /// 夜盘时段（"HH:MM-HH:MM"）；没有夜盘时为 `None`
+    #[getter]
+    fn night_session(&self) -> Option<String> {
+        self.exchange.night_session().map(format_session)
+    }
+
+    /// bar 时间所属的交易日（"YYYY-MM-DD"）：有夜盘的交易所，日盘收盘后的 bar 属于下一个交易日
+    #[pyo3(name = "trading_day")]
+    fn py_trading_day(&self, datetime: &str) -> PyResult<String> {
+        let dt = parse_datetime(datetime.trim())
+            .ok_or_else(|| value_error(format!("Invalid datetime '{}': expected 'YYYY-MM-DD HH:MM:SS'", datetime)))?;
+        Ok(format_date(self.trading_day(dt)))
+    }
+
+    /// 某个时间点（交易所当地时间）是否开市：交易日的日盘时段，或属于下一个交易日的夜盘时段
+    #[pyo3(name = "is_open")]
+    fn py_is_open(&self, datetime: &str) -> PyResult<bool> {
+        let dt = parse_datetime(datetime.trim())
+            .ok_or_else(|| value_error(format!("Invalid datetime '{}': expected 'YYYY-MM-DD HH:MM:SS'", datetime)))?;
+        Ok(self.is_open(dt))
+    }
+
+    /// 是否为交易日
+    #[pyo3(name = "is_trading_day")]
+    fn py_is_trading_day(&self, date: &str) -> PyResult<bool> {
+        Ok(self.is_trading_day(parse_date(date)?))
+    }
+
+    /// 是否为提前收盘的交易日
+    fn is_half_day(&self, date: &str) -> PyResult<bool> {
+        Ok(self.early_close(parse_date(date)?).is_some())
+    }
+
+    /// 提前收盘的时间（"HH:MM"，交易所当地时间）；正常收盘或休市时为 `None`
+    #[pyo3(name = "early_close")]
+    fn py_early_close(&self, date: &str) -> PyResult<Option<String>> {
+        Ok(self.early_close(parse_date(date)?).map(|t| t.format("%H:%M").to_string()))
+    }
+
+    /// 之后（不含当天）的第一个交易日
+    #[pyo3(name = "next_trading_day")]
+    fn py_next_trading_day(&self, date: &str) -> PyResult<String> {
+        Ok(format_date(self.next_trading_day(parse_date(date)?)))
+    }
+
+    /// 之前（不含当天）的最后一个交易日
+    #[pyo3(name = "previous_trading_day")]
+    fn py_previous_trading_day(&self, date: &str) -> PyResult<String> {
+        Ok(format_date(self.previous_trading_day(parse_date(date)?)))
+    }
+
+    /// `[start, end]` 内的交易日列表
+    #[pyo3(name = "trading_days")]
+    fn py_trading_days(&self, start: &str, end: &str) -> PyResult<Vec<String>> {
+        Ok(self.trading_days(parse_date(start)?, parse_date(end)?).into_iter().map(format_date).collect())
+    }
+
+    /// `[start, end]` 内休市的工作日（周一至周五的节假日）
+    fn holidays(&self, start: &str, end: &str) -> PyResult<Vec<String>> {
+        let (start, end) = (parse_date(start)?, parse_date(end)?);
+        Ok(start
+            .iter_days()
+            .take_while(|d| *d <= end)
+            .filter(|d| !is_weekend(*d) && !self.is_trading_day(*d))
+            .map(format_date)
+            .collect())
+    }
+
+    /// `[start, end]` 内提前收盘的交易日
+    fn half_days(&self, start: &str, end: &str) -> PyResult<Vec<String>> {
+        let (start, end) = (parse_date(start)?, parse_date(end)?);
+        Ok(start
+            .iter_days()
+            .take_while(|d| *d <= end)
+            .filter(|d| self.early_close(*d).is_some())
+            .map(format_date)
+            .collect())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("TradingCalendar('{}')", self.exchange.name())
+    }
+}
@@ -0,0 +1,112 @@
+//! 回测取消模块
+//!
+//! 长时间运行的回测以前只能通过杀进程停止：Rust 主循环不检查信号，Ctrl+C 要么被延迟，
+//! 要么在策略回调中抛出 `KeyboardInterrupt` 后丢失全部已计算的结果。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! - 引擎在每个批次（`batch_size` 根 bar）开始时调用 `PyErr_CheckSignals`，并检查取消令牌
+//! - 策略回调中抛出的 `KeyboardInterrupt` 同样被视为取消
+//! - 取消后引擎停止推进，照常调用 `on_stop()` 并返回截至当前的部分结果，
+//!   结果中 `cancelled=True`、`cancel_reason` 为 `"keyboard_interrupt"` 或 `"token"`
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! token = CancelToken()
+//! threading.Timer(60, token.cancel).start()   # 最多跑 60 秒
+//! result = engine.run(strategy, bars, cancel_token=token)
+//! if result["cancelled"]:
+//!     print("partial result up to", result["equity_curve"][-1]["datetime"])
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use pyo3::exceptions::PyKeyboardInterrupt;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// 回测取消令牌
+///
+/// 可以在其他线程（如 GUI 按钮、定时器）中调用 `cancel()`，引擎会在下一个批次边界停止。
+/// 克隆的令牌共享同一个取消标志。
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+#[pymethods]
+impl CancelToken {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求取消回测
+    fn cancel(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// 清除取消标志，令牌可以复用于下一次回测
+    fn reset(&self) {
+        self.flag.store(false, Ordering::SeqCst);
+    }
+
+    /// 是否已请求取消
+    #[getter]
+    fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+}
+
+/// 取消原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CancelReason {
+    /// Ctrl+C（`KeyboardInterrupt`）
+    KeyboardInterrupt,
+    /// 取消令牌被触发
+    Token,
+}
+
+impl CancelReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            CancelReason::KeyboardInterrupt => "keyboard_interrupt",
+            CancelReason::Token => "token",
+        }
+    }
+}
+
+/// 检查取消令牌与 Python 信号（Ctrl+C）
+///
+/// 信号处理器抛出的非 `KeyboardInterrupt` 异常原样返回。
+pub(crate) fn check_cancel(py: Python<'_>, token: &CancelToken) -> PyResult<Option<CancelReason>> {
+    if token.is_cancelled() {
+        return Ok(Some(CancelReason::Token));
+    }
+    match py.check_signals() {
+        Ok(()) => Ok(None),
+        Err(err) => interrupt_reason(py, err).map(Some),
+    }
+}
+
+/// 把 `KeyboardInterrupt` 转换为取消原因，其他异常原样返回
+pub(crate) fn interrupt_reason(py: Python<'_>, err: PyErr) -> PyResult<CancelReason> {
+    if err.is_instance_of::<PyKeyboardInterrupt>(py) {
+        Ok(CancelReason::KeyboardInterrupt)
+    } else {
+        Err(err)
+    }
+}
+
+/// 在结果字典中记录是否被取消（`cancelled`）及原因（`cancel_reason`）
+pub(crate) fn mark_result(py: Python<'_>, result: &PyObject, reason: Option<CancelReason>) -> PyResult<()> {
+    let dict = result.downcast_bound::<PyDict>(py)?;
+    dict.set_item("cancelled", reason.is_some())?;
+    if let Some(reason) = reason {
+        dict.set_item("cancel_reason", reason.as_str())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! 回测检查点模块
+//!
+//! 在 tick 级数据上做多日参数扫描时，一次回测可能运行数小时；进程崩溃或机器重启后从头再来代价很高。
+//! 这个模块把单资产回测的引擎状态定期写入文件，之后可以从检查点继续运行。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 检查点包含：下一根待处理 bar 的序号、持仓/现金/成本/已实现盈亏、订单序号、
+//!    截至当前的净值曲线和成交记录（serde JSON 格式）
+//! 2. 引擎在批次边界（至少每 `checkpoint_every` 根 bar）、被取消时以及回测结束时写入检查点；
+//!    写入先落到临时文件再重命名，中途崩溃不会留下损坏的检查点
+//! 3. `resume=True` 且检查点文件存在时，引擎恢复状态并从下一根 bar 继续
+//!
+//! ## 策略状态
+//!
+//! 策略对象是 Python 对象，引擎无法直接序列化。策略可以实现两个可选方法：
+//! - `get_state()`: 返回可被 `json.dumps` 序列化的对象，随检查点一起保存
+//! - `set_state(state)`: 恢复时以 `json.loads` 的结果调用
+//!
+//! # 注意事项
+//!
+//! - 恢复时必须传入与写入检查点时相同的数据，引擎会校验 bar 数量和断点处的 datetime
+//! - Ctrl+C 打断在某根 bar 的中途时，该 bar 的状态可能不完整，因此不会覆盖检查点，
+//!   恢复时从最近一次完整的检查点继续
+//! - 目前仅 `run()` 支持检查点
+
+use std::fs;
+use std::path::PathBuf;
+
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::exposure::ExposureLimits;
+use crate::positions::PositionRow;
+use crate::profile::RunProfile;
+use crate::sizing::KellyStats;
+use crate::strategy::StrategyHooks;
+use crate::vol_target::{ReturnWindow, VolTarget};
+use crate::{BarData, Order, PositionState, TradeRecord};
+
+/// 检查点文件格式版本
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// 单资产回测的可变状态
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct RunState {
+    pub(crate) pos: PositionState,
+    pub(crate) order_seq: u64,
+    pub(crate) equity_curve: Vec<(Option<String>, f64)>,
+    pub(crate) trades: Vec<TradeRecord>,
+    /// 因交易时段顺延、尚未执行的订单
+    #[serde(default)]
+    pub(crate) deferred: Vec<Order>,
+    /// 每根 bar 的持仓快照（仅在开启 `record_positions` 时记录）
+    #[serde(default)]
+    pub(crate) positions_history: Vec<PositionRow>,
+    /// 平仓胜率与盈亏比（`sizing="kelly"` 使用）
+    #[serde(default)]
+    pub(crate) kelly: KellyStats,
+    /// 最近的收益率（`vol_target` 使用）
+    #[serde(default)]
+    pub(crate) vol_returns: ReturnWindow,
+    /// 耗时分析（仅在开启 `profile` 时存在，不写入检查点）
+    #[serde(skip)]
+    pub(crate) profile: Option<RunProfile>,
+    /// 组合敞口限制（回测开始时由配置解析一次，不写入检查点）
+    #[serde(skip)]
+    pub(crate) exposure_limits: ExposureLimits,
+    /// 波动率目标（同上，未配置时为 `None`）
+    #[serde(skip)]
+    pub(crate) vol_target: Option<VolTarget>,
+    /// 交易时段外的订单是否暂存到下一个时段（同上，否则拒绝）
+    #[serde(skip)]
+    pub(crate) defer_orders: bool,
+}
+
+impl RunState {
+    /// 已记录的 bar 数（预热期之后每根 bar 加一），用作挂单时长的时钟
+    pub(crate) fn bars_recorded(&self) -> usize {
+        self.equity_curve.len()
+    }
+}
+
+/// 检查点文件内容（写入时借用状态，读取时拥有状态）
+#[derive(Serialize, Deserialize)]
+struct CheckpointFile<S> {
+    version: u32,
+    /// 数据总 bar 数（恢复时校验）
+    n_bars: usize,
+    /// 下一根待处理 bar 的序号
+    next_bar: usize,
+    /// 最后一根已处理 bar 的 datetime（恢复时校验）
+    last_datetime: Option<String>,
+    state: S,
+    /// 策略 `get_state()` 的 JSON 文本
+    strategy_state: Option<String>,
+}
+
+/// 从检查点恢复的内容
+pub(crate) struct Resumed {
+    /// 下一根待处理 bar 的序号
+    pub(crate) next_bar: usize,
+    pub(crate) state: RunState,
+    strategy_state: Option<String>,
+}
+
+impl Resumed {
+    /// 恢复策略状态（策略实现了 `set_state` 且检查点中保存了状态时）
+    ///
+    /// 应在 `on_start()` 之后调用，避免策略在 `on_start()` 中的初始化覆盖恢复的状态。
+    pub(crate) fn restore_strategy(&self, py: Python<'_>, strategy: &PyObject, hooks: &StrategyHooks) -> PyResult<()> {
+        if let Some(state) = &self.strategy_state {
+            if hooks.set_state {
+                let obj = py.import_bound("json")?.call_method1("loads", (state,))?;
+                strategy.call_method1(py, "set_state", (obj,))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 检查点读写器
+pub(crate) struct Checkpointer {
+    path: PathBuf,
+    every: usize,
+    resume: bool,
+}
+
+impl Checkpointer {
+    /// 创建检查点读写器；未指定路径时返回 `None`（`resume=True` 却没有路径时报错）
+    pub(crate) fn new(path: Option<String>, every: usize, resume: bool) -> PyResult<Option<Self>> {
+        match path {
+            Some(path) => Ok(Some(Self { path: PathBuf::from(path), every, resume })),
+            None if resume => Err(value_error("resume=True requires checkpoint_path".to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// 自上次写入以来已处理 `since_last` 根 bar 时是否应写入检查点
+    pub(crate) fn due(&self, since_last: usize) -> bool {
+        since_last > 0 && since_last >= self.every
+    }
+
+    /// 读取检查点（仅在 `resume=True` 且文件存在时），并校验数据与检查点一致
+    pub(crate) fn load(&self, bars: &[BarData]) -> PyResult<Option<Resumed>> {
+        if !self.resume || !self.path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&self.path)
+            .map_err(|e| value_error(format!("Failed to read checkpoint {}: {}", self.path.display(), e)))?;
+        let cp: CheckpointFile<RunState> = serde_json::from_str(&text)
+            .map_err(|e| value_error(format!("Invalid checkpoint {}: {}", self.path.display(), e)))?;
+        if cp.version != CHECKPOINT_VERSION {
+            return Err(value_error(format!("Unsupported checkpoint version: {}", cp.version)));
+        }
+        if cp.n_bars != bars.len() || cp.next_bar > bars.len() {
+            return Err(value_error(format!(
+                "Checkpoint was written for {} bars, but {} bars were given",
+                cp.n_bars,
+                bars.len()
+            )));
+        }
+        let last_datetime = cp.next_bar.checked_sub(1).and_then(|i| bars[i].datetime.clone());
+        if last_datetime != cp.last_datetime {
+            return Err(value_error(format!(
+                "Checkpoint does not match data: expected bar {} at {:?}, found {:?}",
+                cp.next_bar, cp.last_datetime, last_datetime
+            )));
+        }
+        Ok(Some(Resumed { next_bar: cp.next_bar, state: cp.state, strategy_state: cp.strategy_state }))
+    }
+
+    /// 写入检查点：`next_bar` 之前的 bar 均已处理完毕
+    pub(crate) fn save(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        hooks: &StrategyHooks,
+        bars: &[BarData],
+        next_bar: usize,
+        state: &RunState,
+    ) -> PyResult<()> {
+        let strategy_state = if hooks.get_state {
+            let obj = strategy.call_method0(py, "get_state")?;
+            Some(py.import_bound("json")?.call_method1("dumps", (obj,))?.extract::<String>()?)
+        } else {
+            None
+        };
+        let cp = CheckpointFile {
+            version: CHECKPOINT_VERSION,
+            n_bars: bars.len(),
+            next_bar,
+            last_datetime: next_bar.checked_sub(1).and_then(|i| bars[i].datetime.clone()),
+            state,
+            strategy_state,
+        };
+        let text = serde_json::to_string(&cp).map_err(|e| value_error(format!("Failed to serialize checkpoint: {}", e)))?;
+
+        // 先写临时文件再重命名，避免中途崩溃留下损坏的检查点
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, text)
+            .and_then(|_| fs::rename(&tmp, &self.path))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Failed to write checkpoint {}: {}", self.path.display(), e)))
+    }
+}
@@ -0,0 +1,266 @@
+//! 列式数据输入模块
+//!
+//! `run()` 原本只接受"字典列表"形式的 K 线（每根 bar 一个 dict），`extract_bars_data`
+//! 需要逐行、逐字段地从 Python 字典中取值；数据量大时这一步会成为回测准备阶段的主要开销。
+//! 这个模块让引擎直接接受列式数据，按列整体读取。
+//!
+//! ## 支持的输入
+//!
+//! - **字典列表**：`[{"datetime": ..., "close": ...}, ...]`（原有格式，逐行提取）
+//! - **列字典**：`{"close": np.ndarray, "open": ..., "datetime": ...}`，值可以是 numpy 数组或列表
+//! - **pyarrow**：`pyarrow.Table` 或 `pyarrow.RecordBatch`
+//! - **DataFrame**：`pandas.DataFrame` 或 `polars.DataFrame`，无需先调用 `.to_dict("records")`
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 数值列通过 `numpy.ascontiguousarray(col, dtype=float64)` 统一为连续的 float64 数组，
+//!    已经是 float64 的 numpy 数组不会复制，然后整列拷贝进 Rust
+//! 2. `datetime` 列如果是 `datetime64`（包括 Arrow 时间戳列），在 numpy 中一次性格式化为
+//!    ISO 字符串（精确到秒），无效时间（NaT）视为缺失
+//! 3. DataFrame 通过 `df[列名]` 取列，再经 numpy 的 `__array__` 缓冲区接口整列读取，不做逐行迭代；
+//!    pandas DataFrame 没有 `datetime` 列但索引是 `DatetimeIndex` 时，使用索引作为 `datetime`
+//! 4. 缺失的列与字典列表格式一致：`open`/`high`/`low`/`close`/`volume` 默认为 0（`strict=True` 时报错），
+//!    `datetime`/`symbol` 默认为空
+//!
+//! # 注意事项
+//!
+//! - 所有列的长度必须与 `close` 列一致，否则返回 `ValueError`
+//! - 列字典必须包含 `close` 列
+//! - 带时区的 pandas 时间列会转换为带 UTC 偏移的字符串（如 `2024-01-02 09:30:00+08:00`）
+//! - 整数类型的 `datetime` 列视为 epoch 毫秒时间戳，格式化为 UTC 时间字符串，同时保留原始时间戳
+//! - 标准列以外的数值、布尔和字符串列作为额外字段交给策略（见 `bar_fields` 模块），其他类型的值被忽略
+
+use numpy::PyReadonlyArray1;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString};
+
+use crate::bar_fields::{self, FieldNames, FieldValue};
+use crate::database::format_epoch_ms;
+use crate::validation::ParsedFields;
+use crate::{extract_bars_data, BarData};
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 从任意支持的数据源提取 bar 数据
+///
+/// 字典列表走原有的逐行提取；其他输入按列式数据处理。
+pub(crate) fn extract_bars_any(data: &PyAny) -> PyResult<Vec<BarData>> {
+    if let Ok(list) = data.downcast::<PyList>() {
+        return extract_bars_data(list);
+    }
+    let close = get_column(data, "close")?
+        .ok_or_else(|| value_error("Columnar data must contain a 'close' column".to_string()))?;
+    let close = float_column(close)?;
+    let n = close.len();
+
+    // 存在的数值列整列记为已转换；缺失的列由严格模式报告
+    let mut parsed = ParsedFields::default();
+    parsed.mark("close");
+    let mut numeric = |name: &str| -> PyResult<Vec<f64>> {
+        match get_column(data, name)? {
+            Some(col) => {
+                let values = float_column(col)?;
+                check_len(name, values.len(), n)?;
+                parsed.mark(name);
+                Ok(values)
+            }
+            None => Ok(vec![0.0; n]),
+        }
+    };
+    let open = numeric("open")?;
+    let high = numeric("high")?;
+    let low = numeric("low")?;
+    let volume = numeric("volume")?;
+
+    let text = |name: &str| -> PyResult<Vec<Option<String>>> {
+        match get_column(data, name)? {
+            Some(col) => {
+                let values = string_column(col)?;
+                check_len(name, values.len(), n)?;
+                Ok(values)
+            }
+            None => Ok(vec![None; n]),
+        }
+    };
+    // 整数时间列视为 epoch 毫秒：保留时间戳，并格式化为 UTC 时间字符串
+    let mut timestamp: Vec<Option<i64>> = vec![None; n];
+    let mut datetime = match get_column(data, "datetime")? {
+        Some(col) => match epoch_column(col)? {
+            Some(ms) => {
+                check_len("datetime", ms.len(), n)?;
+                timestamp = ms.iter().map(|&m| Some(m)).collect();
+                ms.into_iter().map(format_epoch_ms).collect()
+            }
+            None => text("datetime")?,
+        },
+        None => vec![None; n],
+    };
+    if datetime.iter().all(Option::is_none) {
+        if let Some(index) = datetime_index(data)? {
+            check_len("index", index.len(), n)?;
+            datetime = index;
+        }
+    }
+    let symbol = text("symbol")?;
+
+    // 额外列：逐列转换为 Python 值列表后提取
+    let mut names = FieldNames::default();
+    let mut extra_columns = Vec::new();
+    for name in column_names(data)?.into_iter().filter(|n| bar_fields::is_extra(n)) {
+        if let Some(col) = get_column(data, &name)? {
+            let values = value_column(col)?;
+            check_len(&name, values.len(), n)?;
+            extra_columns.push((names.intern(&name), values));
+        }
+    }
+
+    Ok((0..n)
+        .map(|i| BarData {
+            datetime: datetime[i].clone(),
+            timestamp: timestamp[i],
+            open: open[i],
+            high: high[i],
+            low: low[i],
+            close: close[i],
+            volume: volume[i],
+            symbol: symbol[i].clone(),
+            extra: extra_columns
+                .iter()
+                .filter_map(|(name, values)| Some((name.clone(), values[i].clone()?)))
+                .collect(),
+            parsed,
+        })
+        .collect())
+}
+
+/// 全部列名（非字符串列名跳过）
+fn column_names(data: &PyAny) -> PyResult<Vec<String>> {
+    let names = if let Ok(dict) = data.downcast::<PyDict>() {
+        dict.keys().as_ref()
+    } else if data.hasattr("column_names")? {
+        data.getattr("column_names")?
+    } else {
+        data.getattr("columns")?
+    };
+    Ok(names.iter()?.filter_map(|name| name.ok()?.extract::<String>().ok()).collect())
+}
+
+/// 把一列转换为额外字段值（经 numpy 的 `tolist()` 得到 Python 标量后逐个提取）
+fn value_column(col: &PyAny) -> PyResult<Vec<Option<FieldValue>>> {
+    let np = col.py().import_bound("numpy")?;
+    let items = np.call_method1("asarray", (col,))?.call_method0("tolist")?;
+    let items: &PyList = items.into_gil_ref().downcast()?;
+    Ok(items.iter().map(FieldValue::extract).collect())
+}
+
+fn check_len(name: &str, len: usize, expected: usize) -> PyResult<()> {
+    if len != expected {
+        return Err(value_error(format!(
+            "Column '{}' has length {}, but 'close' has length {}",
+            name, len, expected
+        )));
+    }
+    Ok(())
+}
+
+/// 按列名取列；列不存在时为 `None`
+///
+/// - 列字典：按键取值
+/// - pyarrow Table/RecordBatch：按 `column_names` 判断，`column(name)` 取列
+/// - pandas/polars DataFrame：按 `columns` 判断，`df[name]` 取列
+fn get_column<'py>(data: &'py PyAny, name: &str) -> PyResult<Option<&'py PyAny>> {
+    if let Ok(dict) = data.downcast::<PyDict>() {
+        return dict.get_item(name);
+    }
+    if data.hasattr("column_names")? {
+        let names: Vec<String> = data.getattr("column_names")?.extract()?;
+        if names.iter().any(|n| n == name) {
+            return Ok(Some(data.call_method1("column", (name,))?));
+        }
+        return Ok(None);
+    }
+    if data.hasattr("columns")? {
+        // pandas 的 columns 是 Index，polars 的是 list；非字符串列名直接跳过
+        for col in data.getattr("columns")?.iter()? {
+            if col?.extract::<String>().is_ok_and(|c| c == name) {
+                return Ok(Some(data.get_item(name)?));
+            }
+        }
+        return Ok(None);
+    }
+    Err(value_error(format!(
+        "Unsupported data type '{}': expected a list of bar dicts, a dict of columns, a pyarrow Table or a pandas/polars DataFrame",
+        data.get_type().getattr("__name__")?
+    )))
+}
+
+/// pandas DataFrame 的 `DatetimeIndex`（没有 `datetime` 列时作为时间列）
+fn datetime_index(data: &PyAny) -> PyResult<Option<Vec<Option<String>>>> {
+    if data.is_instance_of::<PyDict>() || !data.hasattr("index")? {
+        return Ok(None);
+    }
+    let index = data.getattr("index")?;
+    // 其他对象的 index 可能是方法而不是索引
+    if index.is_callable() {
+        return Ok(None);
+    }
+    // 带时区的 DatetimeTZDtype 的 kind 同样是 "M"
+    let is_datetime = index
+        .getattr("dtype")
+        .and_then(|dtype| dtype.getattr("kind"))
+        .and_then(|kind| kind.extract::<String>())
+        .is_ok_and(|kind| kind == "M");
+    if !is_datetime {
+        return Ok(None);
+    }
+    string_column(index).map(Some)
+}
+
+/// 把一列转换为 float64 向量（经 numpy 统一为连续 float64 数组）
+fn float_column(col: &PyAny) -> PyResult<Vec<f64>> {
+    let np = col.py().import_bound("numpy")?;
+    let arr = np.call_method1("ascontiguousarray", (col, "float64"))?;
+    let arr: PyReadonlyArray1<f64> = arr.extract()?;
+    Ok(arr.as_array().iter().copied().collect())
+}
+
+/// 整数列（epoch 毫秒时间戳）转换为 i64 向量；不是整数列时返回 `None`
+fn epoch_column(col: &PyAny) -> PyResult<Option<Vec<i64>>> {
+    let np = col.py().import_bound("numpy")?;
+    let arr = np.call_method1("asarray", (col,))?;
+    let kind: String = arr.getattr("dtype")?.getattr("kind")?.extract()?;
+    if kind != "i" && kind != "u" {
+        return Ok(None);
+    }
+    let arr = np.call_method1("ascontiguousarray", (arr, "int64"))?;
+    let arr: PyReadonlyArray1<i64> = arr.extract()?;
+    Ok(Some(arr.as_array().iter().copied().collect()))
+}
+
+/// 把一列转换为可选字符串向量；`datetime64` 列先格式化为 ISO 字符串（精确到秒）
+fn string_column(col: &PyAny) -> PyResult<Vec<Option<String>>> {
+    let np = col.py().import_bound("numpy")?;
+    let mut arr = np.call_method1("asarray", (col,))?;
+    if arr.getattr("dtype")?.getattr("kind")?.extract::<String>()? == "M" {
+        let kwargs = PyDict::new_bound(col.py());
+        kwargs.set_item("unit", "s")?;
+        arr = np.call_method("datetime_as_string", (arr,), Some(&kwargs))?;
+    }
+    let items = arr.call_method0("tolist")?;
+    let items: &PyList = items.into_gil_ref().downcast()?;
+    items
+        .iter()
+        .map(|item| {
+            if item.is_none() {
+                Ok(None)
+            } else if let Ok(s) = item.downcast::<PyString>() {
+                let s = s.to_str()?;
+                Ok(if s == "NaT" { None } else { Some(s.to_string()) })
+            } else {
+                Ok(Some(item.str()?.to_string()))
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,396 @@
+//! 公司行为（分红、拆股）存储模块
+//!
+//! 股票数据在分红除息、拆股（送转）前后价格会出现跳空，回测和复权计算都需要知道这些事件发生在哪天、幅度多大。
+//! 这个模块在 DuckDB 中增加一张 `corporate_actions` 表，作为本地的权威数据来源，
+//! `get_market_data(adjust="qfq"/"hfq")` 据此在查询时计算前复权、后复权价格。
+//!
+//! ## 存储结构
+//!
+//! | 列 | 类型 | 说明 |
+//! |----|------|------|
+//! | `symbol` | VARCHAR | 交易标的 |
+//! | `ex_date` | DATE | 除权除息日 |
+//! | `action` | VARCHAR | `'dividend'` 或 `'split'` |
+//! | `value` | DOUBLE | 分红为每股现金（税前）；拆股为拆股后股数 / 拆股前股数 |
+//!
+//! (symbol, ex_date, action) 为主键，同一天既分红又送转时分别记录两行。
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import save_corporate_actions, get_corporate_actions
+//!
+//! save_corporate_actions("data/backtest.db", "600519.SH", [
+//!     {"ex_date": "2023-06-30", "action": "dividend", "value": 25.911},  # 每股派 25.911 元
+//!     {"ex_date": "2020-08-24", "action": "split", "value": 2.0},        # 1 股拆 2 股
+//!     {"ex_date": "2019-07-11", "action": "split", "value": 1.3},        # 10 送 3
+//! ])
+//! actions = get_corporate_actions("data/backtest.db", "600519.SH", start="2020-01-01")
+//! ```
+//!
+//! # 注意事项
+//!
+//! - A 股"10 派 X 元"换算为每股 `X / 10`，"10 送 Y 转 Z"换算为 `value = 1 + (Y + Z) / 10`
+//! - 重复写入同一 (标的, 日期, 类型) 时以新值覆盖
+//!
+//! ## 复权计算
+//!
+//! 每个除权除息日的价格因子 = `(前收盘 - 每股分红) / 前收盘 / 拆股比例`，前收盘取同一周期表中除权除息日之前的最后一根 K 线。
+//! 前复权（`qfq`）把 K 线价格乘以其后所有除权除息日因子的乘积，最新价格与实际成交价一致；
+//! 后复权（`hfq`）把 K 线价格除以截至当天（含）所有因子的乘积，最早价格与实际成交价一致。
+//! 成交量按拆股比例反向调整（前复权时拆股前的成交量乘以拆股比例）。
+//! 库中没有除息日之前的数据时无法得到前收盘，该次分红不参与复权（拆股不受影响）。
+
+use chrono::NaiveDate;
+use duckdb::Connection;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::database::{ensure_period_table, open_connection, parse_datetime, KlineBar};
+
+/// 公司行为表名
+const ACTIONS_TABLE: &str = "corporate_actions";
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 公司行为类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionKind {
+    /// 现金分红，`value` 为每股现金
+    Dividend,
+    /// 拆股 / 送转，`value` 为拆股后股数与拆股前股数之比
+    Split,
+}
+
+impl ActionKind {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "dividend" => Ok(ActionKind::Dividend),
+            "split" => Ok(ActionKind::Split),
+            _ => Err(format!("Unknown corporate action '{}': expected 'dividend' or 'split'", s)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ActionKind::Dividend => "dividend",
+            ActionKind::Split => "split",
+        }
+    }
+}
+
+/// 一条公司行为记录
+#[derive(Clone, Debug, PartialEq)]
+pub struct CorporateAction {
+    pub ex_date: NaiveDate,
+    pub kind: ActionKind,
+    pub value: f64,
+}
+
+fn ensure_actions_table(conn: &Connection) -> PyResult<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                symbol VARCHAR NOT NULL,
+                ex_date DATE NOT NULL,
+                action VARCHAR NOT NULL,
+                value DOUBLE NOT NULL,
+                PRIMARY KEY (symbol, ex_date, action)
+            )",
+            ACTIONS_TABLE
+        ),
+        [],
+    )
+    .map_err(|e| runtime_error(format!("Failed to ensure table {}: {}", ACTIONS_TABLE, e)))?;
+    Ok(())
+}
+
+/// 解析日期（`"YYYY-MM-DD"`，带时间时取日期部分）
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok().or_else(|| parse_datetime(s).map(|dt| dt.date()))
+}
+
+/// 读取公司行为（Rust 实现），按除权除息日升序排列
+///
+/// `start` / `end` 为可选的日期范围（包含两端）。
+pub fn load_corporate_actions_rust(
+    db_path: &str,
+    symbol: &str,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> PyResult<Vec<CorporateAction>> {
+    let conn = open_connection(db_path)?;
+    query_actions(&conn, symbol, start, end)
+}
+
+fn query_actions(
+    conn: &Connection,
+    symbol: &str,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> PyResult<Vec<CorporateAction>> {
+    ensure_actions_table(conn)?;
+
+    let mut where_parts = vec!["symbol = ?".to_string()];
+    let mut params: Vec<String> = vec![symbol.to_string()];
+    if let Some(s) = start {
+        where_parts.push("ex_date >= CAST(? AS DATE)".to_string());
+        params.push(s.to_string());
+    }
+    if let Some(e) = end {
+        where_parts.push("ex_date <= CAST(? AS DATE)".to_string());
+        params.push(e.to_string());
+    }
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT strftime(ex_date, '%Y-%m-%d'), action, value FROM {} WHERE {} ORDER BY ex_date, action",
+            ACTIONS_TABLE,
+            where_parts.join(" AND ")
+        ))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let rows = stmt
+        .query_map(duckdb::params_from_iter(params), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?))
+        })
+        .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?;
+
+    let mut actions = Vec::new();
+    for row in rows {
+        let (date, action, value) = row.map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+        let ex_date = parse_date(&date).ok_or_else(|| runtime_error(format!("Invalid ex_date in database: {}", date)))?;
+        let kind = ActionKind::parse(&action).map_err(runtime_error)?;
+        actions.push(CorporateAction { ex_date, kind, value });
+    }
+    Ok(actions)
+}
+
+/// 复权方式（`get_market_data(adjust=...)`）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Adjustment {
+    /// 前复权：最新价格不变，调整历史价格
+    Forward,
+    /// 后复权：最早价格不变，调整之后的价格
+    Backward,
+}
+
+impl Adjustment {
+    /// 解析 `adjust` 参数：`"qfq"`/`"forward"`、`"hfq"`/`"backward"`，`None` 表示不复权
+    pub(crate) fn parse(adjust: Option<&str>) -> PyResult<Option<Self>> {
+        let Some(adjust) = adjust else { return Ok(None) };
+        match adjust.to_lowercase().as_str() {
+            "qfq" | "forward" => Ok(Some(Adjustment::Forward)),
+            "hfq" | "backward" => Ok(Some(Adjustment::Backward)),
+            _ => Err(value_error(format!(
+                "Unknown adjust mode '{}': expected 'qfq', 'hfq' or None",
+                adjust
+            ))),
+        }
+    }
+}
+
+/// 单个除权除息日的复权因子
+struct ExFactor {
+    ex_date: NaiveDate,
+    /// 除权除息日之前的价格乘以该因子即与之后的价格可比
+    price: f64,
+    /// 除权除息日之前的成交量乘以该因子（拆股比例）
+    volume: f64,
+}
+
+/// 按除权除息日计算复权因子
+///
+/// 分红因子为 `(前收盘 - 每股分红) / 前收盘`，前收盘取该周期表中除权除息日之前的最后一根 K 线；
+/// 拆股因子为 `1 / 拆股比例`。同一天的多条记录合并为一个因子。
+fn ex_factors(conn: &Connection, table_name: &str, symbol: &str, actions: &[CorporateAction]) -> PyResult<Vec<ExFactor>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT close FROM {} WHERE symbol = ? AND datetime < CAST(? AS TIMESTAMP) ORDER BY datetime DESC LIMIT 1",
+            table_name
+        ))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+
+    let mut factors: Vec<ExFactor> = Vec::new();
+    for action in actions {
+        let (price, volume) = match action.kind {
+            ActionKind::Split => (1.0 / action.value, action.value),
+            ActionKind::Dividend => {
+                let prev_close = stmt
+                    .query_map(duckdb::params![symbol, action.ex_date.to_string()], |row| row.get::<_, f64>(0))
+                    .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?
+                    .next()
+                    .transpose()
+                    .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+                // 没有除息日之前的数据（或分红不小于前收盘）时无法计算分红因子，忽略该分红
+                match prev_close {
+                    Some(p) if p > action.value => ((p - action.value) / p, 1.0),
+                    _ => (1.0, 1.0),
+                }
+            }
+        };
+        match factors.last_mut() {
+            Some(last) if last.ex_date == action.ex_date => {
+                last.price *= price;
+                last.volume *= volume;
+            }
+            _ => factors.push(ExFactor { ex_date: action.ex_date, price, volume }),
+        }
+    }
+    Ok(factors)
+}
+
+/// 对 K 线做前复权或后复权（按标的分别计算，支持多标的混合的 K 线列表）
+///
+/// 复权因子使用库中该标的的全部公司行为，与查询的时间范围无关：前复权结果始终以库中最新价格为基准，
+/// 后复权结果始终以最早价格为基准。开高低收乘以价格因子，成交量按拆股比例反向调整。
+pub(crate) fn adjust_klines(db_path: &str, period: &str, bars: &mut [KlineBar], adjustment: Adjustment) -> PyResult<()> {
+    if bars.is_empty() {
+        return Ok(());
+    }
+    let conn = open_connection(db_path)?;
+    let table_name = ensure_period_table(&conn, period)?;
+
+    let mut symbols: Vec<String> = bars.iter().map(|b| b.symbol.clone()).collect();
+    symbols.sort();
+    symbols.dedup();
+    for symbol in symbols {
+        let actions = query_actions(&conn, &symbol, None, None)?;
+        if actions.is_empty() {
+            continue;
+        }
+        let factors = ex_factors(&conn, &table_name, &symbol, &actions)?;
+
+        // suffix[k] = 第 k 个及之后所有除权除息日因子的乘积
+        let mut suffix = vec![(1.0, 1.0); factors.len() + 1];
+        for k in (0..factors.len()).rev() {
+            suffix[k] = (suffix[k + 1].0 * factors[k].price, suffix[k + 1].1 * factors[k].volume);
+        }
+        let total = suffix[0];
+
+        for bar in bars.iter_mut().filter(|b| b.symbol == symbol) {
+            let Some(date) = parse_datetime(&bar.datetime).map(|dt| dt.date()) else { continue };
+            // 除权除息日不晚于 bar 日期的因子个数
+            let k = factors.partition_point(|f| f.ex_date <= date);
+            let (price, volume) = match adjustment {
+                Adjustment::Forward => suffix[k],
+                Adjustment::Backward => (suffix[k].0 / total.0, suffix[k].1 / total.1),
+            };
+            bar.open *= price;
+            bar.high *= price;
+            bar.low *= price;
+            bar.close *= price;
+            bar.volume *= volume;
+        }
+    }
+    Ok(())
+}
+
+/// 保存分红、拆股记录
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `actions`: 字典列表，每个字典包含 `ex_date`（除权除息日）、`action`（`"dividend"` 或 `"split"`）和 `value`
+///   （分红为每股现金；拆股为拆股后股数 / 拆股前股数，如 1 拆 2 为 `2.0`）
+/// - `replace`: 是否先删除该标的的全部旧记录，默认 `False`
+///
+/// # 返回值
+///
+/// 写入的记录条数
+///
+/// # 注意事项
+///
+/// - 日期无法解析、类型未知、分红为负或拆股比例不为正时返回 `ValueError`，此时不会写入任何数据
+/// - 已存在相同 (标的, 日期, 类型) 的记录时以新值覆盖
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, actions, replace=false))]
+pub fn save_corporate_actions(db_path: String, symbol: String, actions: &Bound<'_, PyList>, replace: bool) -> PyResult<usize> {
+    let mut parsed = Vec::with_capacity(actions.len());
+    for (i, item) in actions.iter().enumerate() {
+        let d = item.downcast::<PyDict>()?;
+        let ex_date = d
+            .get_item("ex_date")?
+            .and_then(|v| v.extract::<String>().ok())
+            .and_then(|s| parse_date(&s))
+            .ok_or_else(|| value_error(format!("Action {} has no valid ex_date (expected 'YYYY-MM-DD')", i)))?;
+        let kind = d
+            .get_item("action")?
+            .and_then(|v| v.extract::<String>().ok())
+            .ok_or_else(|| value_error(format!("Action {} has no action type", i)))
+            .and_then(|s| ActionKind::parse(&s).map_err(value_error))?;
+        let value: f64 = d
+            .get_item("value")?
+            .and_then(|v| v.extract().ok())
+            .ok_or_else(|| value_error(format!("Action {} has no numeric value", i)))?;
+        let valid = match kind {
+            ActionKind::Dividend => value.is_finite() && value >= 0.0,
+            ActionKind::Split => value.is_finite() && value > 0.0,
+        };
+        if !valid {
+            return Err(value_error(format!("Action {} has an invalid {} value: {}", i, kind.name(), value)));
+        }
+        parsed.push(CorporateAction { ex_date, kind, value });
+    }
+
+    let conn = open_connection(&db_path)?;
+    ensure_actions_table(&conn)?;
+    conn.execute("BEGIN TRANSACTION", []).map_err(|e| runtime_error(format!("Failed to begin transaction: {}", e)))?;
+    if replace {
+        conn.execute(&format!("DELETE FROM {} WHERE symbol = ?", ACTIONS_TABLE), duckdb::params![symbol])
+            .map_err(|e| runtime_error(format!("Failed to delete old data: {}", e)))?;
+    }
+    let insert = format!(
+        "INSERT INTO {} (symbol, ex_date, action, value) VALUES (?, CAST(? AS DATE), ?, ?)
+         ON CONFLICT (symbol, ex_date, action) DO UPDATE SET value = excluded.value",
+        ACTIONS_TABLE
+    );
+    for action in &parsed {
+        conn.execute(&insert, duckdb::params![symbol, action.ex_date.to_string(), action.kind.name(), action.value])
+            .map_err(|e| runtime_error(format!("Failed to save corporate action on {}: {}", action.ex_date, e)))?;
+    }
+    conn.execute("COMMIT", []).map_err(|e| runtime_error(format!("Failed to commit transaction: {}", e)))?;
+    Ok(parsed.len())
+}
+
+/// 查询分红、拆股记录
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `start` / `end`: 可选的日期范围（包含两端）
+///
+/// # 返回值
+///
+/// 按除权除息日升序排列的字典列表：`symbol`、`ex_date`、`action`、`value`
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, start=None, end=None))]
+pub fn get_corporate_actions(
+    py: Python<'_>,
+    db_path: String,
+    symbol: String,
+    start: Option<String>,
+    end: Option<String>,
+) -> PyResult<PyObject> {
+    let bound = |s: Option<&str>| {
+        s.map(|s| parse_date(s).ok_or_else(|| value_error(format!("Invalid date '{}': expected 'YYYY-MM-DD'", s))))
+            .transpose()
+    };
+    let actions = load_corporate_actions_rust(&db_path, &symbol, bound(start.as_deref())?, bound(end.as_deref())?)?;
+    let out = PyList::empty_bound(py);
+    for action in actions {
+        let d = PyDict::new_bound(py);
+        d.set_item("symbol", &symbol)?;
+        d.set_item("ex_date", action.ex_date.to_string())?;
+        d.set_item("action", action.kind.name())?;
+        d.set_item("value", action.value)?;
+        out.append(d)?;
+    }
+    Ok(out.into())
+}
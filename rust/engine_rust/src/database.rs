@@ -13,32 +13,44 @@
 //!
 //! # 使用方式
 //!
-//! 1. **数据导入**: 使用 `save_klines()` 或 `save_klines_from_csv()` 将数据导入 DuckDB
+//! 1. **数据导入**: 使用 `save_klines()`、`save_klines_from_csv()` 或 `save_klines_from_parquet()` 将数据导入 DuckDB
 //! 2. **数据查询**: 使用 `get_market_data()` 从数据库查询 K 线数据
 //! 3. **周期转换**: 使用 `resample_klines()` 将 K 线转换为目标周期
 //! 4. **数据合成**: 使用 `load_and_synthesize_klines()` 查询并自动转换周期
+//! 5. **数据导出**: 使用 `export_klines_to_parquet()` 把 K 线导出为 Parquet 文件，供其他工具使用
+//! 6. **数据发现**: 使用 `list_periods()` / `list_symbols()` 查看库中有哪些周期和标的，
+//!    `get_data_range()` 查看某个标的的起止时间与条数，`find_gaps()` 检测数据缺口
 //!
 //! # 性能优化策略
 //!
 //! - **直接 DuckDB 操作**: 绕过 Python 层，直接在 Rust 中操作数据库
-//! - **批量插入**: 使用临时表 + 批量 VALUES 插入，50k 记录/批
+//! - **批量插入**: 使用临时表 + DuckDB Appender 按列追加，不拼接 SQL 字符串
 //! - **CSV 直接读取**: `save_klines_from_csv()` 使用 DuckDB 的 `read_csv()` 函数，最快
+//! - **Parquet 直接读取**: `save_klines_from_parquet()` 使用 DuckDB 的 `read_parquet()` 函数
 //! - **事务处理**: 使用事务确保数据一致性，同时提升批量插入性能
 //! - **索引优化**: 自动创建 (symbol, datetime) 唯一索引，加速查询
 //!
 //! # 注意事项
 //!
 //! - 数据库文件路径必须可写，如果不存在会自动创建
-//! - CSV 文件必须包含表头：`datetime,open,high,low,close,volume`
+//! - CSV 文件必须包含表头：`datetime,open,high,low,close,volume`，Parquet 文件必须包含同名列
 //! - 周期字符串格式：`"1m"`, `"15m"`, `"1h"`, `"1d"`, `"1w"`, `"1mo"`, `"1y"` 等
-//! - 时间格式支持多种格式：ISO 8601、`"%Y-%m-%d %H:%M:%S"` 等
+//! - 时间格式支持多种格式：ISO 8601、`"%Y-%m-%d %H:%M:%S"` 等；带 UTC 偏移的时间写入时换算为 UTC
+//! - 写入函数的 `tz` 参数说明不带偏移的时间属于哪个时区，统一换算为 UTC 存储；
+//!   查询函数的 `tz` 参数把库中的 UTC 时间换算回该时区的本地时间
 //! - 批量插入时，如果数据量很大，会显示进度信息
 
-use chrono::{DateTime, NaiveDateTime, Timelike};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use chrono_tz::Tz;
 use duckdb::Connection;
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
+use pyo3::types::{PyDict, PyList, PyString};
 use std::path::Path;
+use std::sync::Mutex;
+
+use crate::calendar::TradingCalendar;
+use crate::corporate_actions::{adjust_klines, Adjustment};
+use crate::logger::{self, Level};
 
 /// K 线数据结构
 ///
@@ -89,7 +101,7 @@ pub struct KlineBar {
 ///
 /// 支持多种周期格式：m（分钟）、h（小时）、d（天）、w（周）、mo/M（月）、y（年）
 /// 例如："15m" → 15, "1h" → 60, "1d" → 1440
-fn period_to_minutes(period: &str) -> Option<i64> {
+pub(crate) fn period_to_minutes(period: &str) -> Option<i64> {
     let period_lower = period.to_lowercase();
 
     // 分钟周期：如 "15m" → 15
@@ -132,7 +144,39 @@ fn period_to_minutes(period: &str) -> Option<i64> {
     }
 }
 
-fn sanitize_period_identifier(period: &str) -> PyResult<String> {
+/// 进程内共享的内存数据库路径
+///
+/// 各数据库函数每次调用都会重新打开连接；对普通文件这没有问题，但 DuckDB 的 `:memory:` 每次打开都是一个新的空库。
+/// 因此 `db_path=":memory:"` 时所有函数共用同一个进程内的内存数据库（通过 `try_clone()` 获取连接），
+/// 数据在调用之间保留，直到 `clear_memory_db()` 或进程退出。
+pub(crate) const MEMORY_DB_PATH: &str = ":memory:";
+
+static MEMORY_DB: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// 打开数据库连接；`":memory:"` 返回进程内共享内存数据库的连接
+pub(crate) fn open_connection(db_path: &str) -> PyResult<Connection> {
+    let conn = if db_path == MEMORY_DB_PATH {
+        let mut root = MEMORY_DB.lock().unwrap_or_else(|e| e.into_inner());
+        match root.as_ref() {
+            Some(conn) => conn.try_clone(),
+            None => Connection::open_in_memory().and_then(|conn| {
+                let clone = conn.try_clone()?;
+                *root = Some(conn);
+                Ok(clone)
+            }),
+        }
+    } else {
+        Connection::open(Path::new(db_path))
+    };
+    conn.map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to connect to database: {}",
+            e
+        ))
+    })
+}
+
+pub(crate) fn sanitize_period_identifier(period: &str) -> PyResult<String> {
     let mut sanitized = String::with_capacity(period.len());
     for ch in period.chars() {
         if ch.is_ascii_alphanumeric() {
@@ -150,7 +194,7 @@ fn sanitize_period_identifier(period: &str) -> PyResult<String> {
     Ok(sanitized)
 }
 
-fn ensure_period_table(conn: &Connection, period: &str) -> PyResult<String> {
+pub(crate) fn ensure_period_table(conn: &Connection, period: &str) -> PyResult<String> {
     let sanitized_period = sanitize_period_identifier(period)?;
     let table_name = format!("klines_{}", sanitized_period);
 
@@ -194,37 +238,61 @@ fn ensure_period_table(conn: &Connection, period: &str) -> PyResult<String> {
     Ok(table_name)
 }
 
+/// 去掉 `strftime(..., '%f')` 输出中多余的小数秒（".000000" → ""，".500000" → ".5"）
+pub(crate) fn trim_fractional_seconds(mut datetime: String) -> String {
+    if let Some(stripped) = datetime.strip_suffix(".000000") {
+        datetime = stripped.to_string();
+    } else if datetime.contains('.') {
+        while datetime.ends_with('0') {
+            datetime.pop();
+        }
+        if datetime.ends_with('.') {
+            datetime.pop();
+        }
+    }
+    datetime
+}
+
 /// 解析时间字符串为 NaiveDateTime
 ///
 /// 支持多种时间格式，包括 ISO 8601、常见格式等。
 /// 如果解析失败返回 None。
-fn parse_datetime(dt_str: &str) -> Option<NaiveDateTime> {
-    // 优先尝试 ISO 格式（RFC3339）：包含 T、Z 或时区信息
-    if dt_str.contains('T') || dt_str.contains('Z') || dt_str.contains('+') {
-        // 尝试完整 RFC3339 格式（带时区）
-        if let Ok(dt) = DateTime::parse_from_rfc3339(dt_str) {
-            return Some(dt.naive_utc());
-        }
-        // 尝试不带时区的 ISO 格式：2020-01-01T09:30:00
-        if let Ok(dt) = NaiveDateTime::parse_from_str(dt_str, "%Y-%m-%dT%H:%M:%S") {
-            return Some(dt);
-        }
-        // 尝试带微秒的 ISO 格式：2020-01-01T09:30:00.123456
-        if let Ok(dt) = NaiveDateTime::parse_from_str(dt_str, "%Y-%m-%dT%H:%M:%S%.f") {
-            return Some(dt);
-        }
-    }
+///
+/// 带 UTC 偏移的时间（如 `"2024-01-02T09:30:00+08:00"`、`"2024-01-02 09:30:00+08"`）返回字符串上的
+/// 本地时间（09:30），与不带偏移的时间口径一致：引擎中的交易时段、回测区间、重采样都按墙上时间计算。
+/// 需要偏移本身时使用 `parse_datetime_with_offset()`。
+pub(crate) fn parse_datetime(dt_str: &str) -> Option<NaiveDateTime> {
+    parse_datetime_with_offset(dt_str).map(|(dt, _)| dt)
+}
 
-    // 尝试常见格式
+/// 解析时间字符串，同时返回其中的 UTC 偏移（没有偏移时为 `None`）
+///
+/// 返回的时间为字符串上的本地时间；减去偏移即为 UTC 时间。
+fn parse_datetime_with_offset(dt_str: &str) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
+    // 常见的不带偏移格式（绝大多数数据），`%.f` 可以匹配有或没有小数秒的时间
     let formats = [
-        "%Y-%m-%d %H:%M:%S",      // 标准格式：2020-01-01 09:30:00
-        "%Y-%m-%d %H:%M:%S%.f",   // 带微秒：2020-01-01 09:30:00.123456
-        "%Y-%m-%d",                // 仅日期：2020-01-01
+        "%Y-%m-%d %H:%M:%S%.f",   // 标准格式：2020-01-01 09:30:00 / 2020-01-01 09:30:00.123456
+        "%Y-%m-%dT%H:%M:%S%.f",   // ISO 格式：2020-01-01T09:30:00
     ];
-
     for fmt in &formats {
         if let Ok(dt) = NaiveDateTime::parse_from_str(dt_str, fmt) {
-            return Some(dt);
+            return Some((dt, None));
+        }
+    }
+
+    // 仅日期：2020-01-01（NaiveDateTime 无法直接解析不含时间的字符串，按当天 00:00:00 处理）
+    if let Ok(d) = NaiveDate::parse_from_str(dt_str, "%Y-%m-%d") {
+        return d.and_hms_opt(0, 0, 0).map(|dt| (dt, None));
+    }
+
+    // 带 UTC 偏移的格式：RFC3339（2020-01-01T09:30:00+08:00、...Z），
+    // 以及 DuckDB/pandas 常见的 2020-01-01 09:30:00+08、2020-01-01 09:30:00+0800
+    if let Ok(dt) = DateTime::parse_from_rfc3339(dt_str) {
+        return Some((dt.naive_local(), Some(*dt.offset())));
+    }
+    for fmt in ["%Y-%m-%d %H:%M:%S%.f%#z", "%Y-%m-%dT%H:%M:%S%.f%#z"] {
+        if let Ok(dt) = DateTime::parse_from_str(dt_str, fmt) {
+            return Some((dt.naive_local(), Some(*dt.offset())));
         }
     }
 
@@ -232,6 +300,83 @@ fn parse_datetime(dt_str: &str) -> Option<NaiveDateTime> {
     None
 }
 
+/// epoch 毫秒时间戳转换为 UTC 时间
+pub(crate) fn epoch_ms_to_datetime(ms: i64) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp_millis(ms).map(|dt| dt.naive_utc())
+}
+
+/// epoch 毫秒时间戳格式化为引擎使用的时间字符串（`"YYYY-MM-DD HH:MM:SS"`，有毫秒时带小数秒）
+pub(crate) fn format_epoch_ms(ms: i64) -> Option<String> {
+    epoch_ms_to_datetime(ms).map(|dt| dt.format("%Y-%m-%d %H:%M:%S%.f").to_string())
+}
+
+/// 时间字符串转换为 epoch 毫秒时间戳；不带偏移的时间按 UTC 计算（与字符串上的墙上时间一致）
+pub(crate) fn datetime_to_epoch_ms(dt_str: &str) -> Option<i64> {
+    parse_datetime(dt_str).map(|dt| dt.and_utc().timestamp_millis())
+}
+
+/// 解析时区名称（IANA 名称，如 `"Asia/Shanghai"`、`"America/New_York"`、`"UTC"`）
+pub(crate) fn parse_timezone(tz: Option<&str>) -> PyResult<Option<Tz>> {
+    tz.map(|name| {
+        name.parse::<Tz>().map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unknown time zone '{}': expected an IANA name such as 'Asia/Shanghai' or 'UTC'",
+                name
+            ))
+        })
+    })
+    .transpose()
+}
+
+/// 把时间字符串转换为 UTC 时间
+///
+/// 带偏移的时间按偏移换算；不带偏移的时间视为 `tz` 时区的本地时间，`tz` 为 `None` 时原样返回。
+/// 夏令时重叠的本地时间取较早的一个。
+///
+/// # 返回值
+///
+/// - `Ok(None)`：时间字符串无法解析
+/// - `Err`：本地时间在 `tz` 中不存在（夏令时跳过的时段）
+pub(crate) fn to_utc(dt_str: &str, tz: Option<Tz>) -> Result<Option<NaiveDateTime>, String> {
+    let Some((local, offset)) = parse_datetime_with_offset(dt_str) else { return Ok(None) };
+    if let Some(offset) = offset {
+        return Ok(Some(local - offset));
+    }
+    let Some(tz) = tz else { return Ok(Some(local)) };
+    tz.from_local_datetime(&local)
+        .earliest()
+        .map(|dt| Some(dt.naive_utc()))
+        .ok_or_else(|| format!("Local time '{}' does not exist in time zone {}", dt_str, tz.name()))
+}
+
+/// 把 UTC 时间转换为 `tz` 时区的本地时间
+fn from_utc(dt: NaiveDateTime, tz: Tz) -> NaiveDateTime {
+    tz.from_utc_datetime(&dt).naive_local()
+}
+
+/// 按时区把查询边界转换为 UTC（`tz` 为 `None` 时原样返回）
+fn utc_bounds(start: Option<String>, end: Option<String>, tz: Option<Tz>) -> PyResult<(Option<String>, Option<String>)> {
+    let Some(tz) = tz else { return Ok((start, end)) };
+    let convert = |bound: Option<String>| -> PyResult<Option<String>> {
+        let Some(bound) = bound else { return Ok(None) };
+        match to_utc(&bound, Some(tz)).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)? {
+            Some(dt) => Ok(Some(dt.format("%Y-%m-%d %H:%M:%S%.f").to_string())),
+            None => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid datetime: {}", bound))),
+        }
+    };
+    Ok((convert(start)?, convert(end)?))
+}
+
+/// 把查询结果中的 UTC 时间转换为 `tz` 时区的本地时间
+fn localize_bars(bars: &mut [KlineBar], tz: Option<Tz>) {
+    let Some(tz) = tz else { return };
+    for bar in bars {
+        if let Some(dt) = parse_datetime(&bar.datetime) {
+            bar.datetime = from_utc(dt, tz).format("%Y-%m-%d %H:%M:%S%.f").to_string();
+        }
+    }
+}
+
 /// 将时间向下取整到周期边界
 ///
 /// 用于 K 线重采样，将时间对齐到目标周期的起始点。
@@ -253,6 +398,75 @@ fn round_down_to_period(dt: NaiveDateTime, minutes: i64) -> NaiveDateTime {
     }
 }
 
+/// 按交易日历为日及以上周期分组
+///
+/// 每根 bar 先归入所属交易日（休市日的 bar 归入下一个交易日；期货交易所日历的夜盘 bar 归入下一个交易日），
+/// 再按周期取分组起点：
+/// - 日周期（如 `"1d"`、`"5d"`）：从第一根 bar 的交易日开始，每 N 个交易日一组
+/// - 周、月、年周期：按自然周（周一开始）、自然月、自然年分组
+///
+/// 分组时间为该组的第一个交易日。
+struct CalendarBuckets<'a> {
+    cal: &'a TradingCalendar,
+    minutes: i64,
+    /// 上一根 bar 的 (交易日, 交易日序号, 所在分组的第一个交易日)
+    last: Option<(NaiveDate, usize, NaiveDate)>,
+}
+
+impl<'a> CalendarBuckets<'a> {
+    fn new(cal: &'a TradingCalendar, minutes: i64) -> Self {
+        Self { cal, minutes, last: None }
+    }
+
+    fn group_start(&mut self, dt: NaiveDateTime) -> NaiveDateTime {
+        let session = self.cal.trading_day(dt);
+        let start = if self.minutes < 10080 {
+            let n = (self.minutes / 1440).max(1) as usize;
+            let (index, block) = match self.last {
+                None => (0, session),
+                Some((prev, index, block)) if session <= prev => (index, block),
+                Some((prev, mut index, mut block)) => {
+                    // 逐个交易日推进序号，数据缺失的交易日同样计数
+                    let mut d = prev;
+                    while d < session {
+                        d = self.cal.next_trading_day(d);
+                        index += 1;
+                        if index % n == 0 {
+                            block = d;
+                        }
+                    }
+                    (index, block)
+                }
+            };
+            self.last = Some((session, index, block));
+            block
+        } else {
+            self.cal.session_date(self.period_start(session))
+        };
+        start.and_hms_opt(0, 0, 0).unwrap_or(dt)
+    }
+
+    /// 周、月、年周期的自然起点
+    fn period_start(&self, d: NaiveDate) -> NaiveDate {
+        if self.minutes < 43200 {
+            let n = (self.minutes / 10080).max(1);
+            let monday = d - chrono::Duration::days(d.weekday().num_days_from_monday() as i64);
+            // 以 1970-01-05（周一）为基准按 N 周对齐
+            let anchor = NaiveDate::from_ymd_opt(1970, 1, 5).unwrap_or(monday);
+            let weeks = (monday - anchor).num_days().div_euclid(7);
+            anchor + chrono::Duration::weeks(weeks - weeks.rem_euclid(n))
+        } else if self.minutes < 525600 {
+            let n = (self.minutes / 43200).max(1) as i32;
+            let months = d.year() * 12 + d.month0() as i32;
+            let months = months - months.rem_euclid(n);
+            NaiveDate::from_ymd_opt(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1).unwrap_or(d)
+        } else {
+            let n = (self.minutes / 525600).max(1) as i32;
+            NaiveDate::from_ymd_opt(d.year() - d.year().rem_euclid(n), 1, 1).unwrap_or(d)
+        }
+    }
+}
+
 /// 将多根 K 线聚合成一根 K 线（OHLCV 聚合）
 ///
 /// 用于 K 线重采样，将同一时间段内的多根 K 线合并成一根。
@@ -326,11 +540,15 @@ fn aggregate_bars(bars: &[KlineBar], group_time: &NaiveDateTime) -> KlineBar {
 /// ```rust,ignore
 /// // 将 1 分钟数据转换为 15 分钟数据
 /// let bars_1m = vec![...]; // 1 分钟 K 线
-/// let bars_15m = resample_klines_rust(bars_1m, "15m")?;
+/// let bars_15m = resample_klines_rust(bars_1m, "15m", None)?;
 ///
 /// // 将日线数据转换为周线数据
 /// let bars_daily = vec![...]; // 日线 K 线
-/// let bars_weekly = resample_klines_rust(bars_daily, "1w")?;
+/// let bars_weekly = resample_klines_rust(bars_daily, "1w", None)?;
+///
+/// // 按上交所日历合成日线：休市日（如周末）的 bar 归入下一个交易日
+/// let cal = TradingCalendar::parse("SSE")?;
+/// let bars_daily = resample_klines_rust(bars_1m, "1d", Some(&cal))?;
 /// ```
 ///
 /// ## 支持的周期格式
@@ -346,6 +564,8 @@ fn aggregate_bars(bars: &[KlineBar], group_time: &NaiveDateTime) -> KlineBar {
 ///
 /// - `bars`: 原始 K 线数据列表，必须按时间顺序排列
 /// - `target_period`: 目标周期字符串（如 "15m", "1h", "1d"）
+/// - `calendar`: 可选的交易日历，仅对日及以上周期生效：bar 按所属交易日分组，
+///   多日周期按交易日计数，分组时间为组内第一个交易日；有夜盘的交易所（如 `"SHFE"`）日盘收盘后的 bar 属于下一个交易日
 ///
 /// # 返回值
 ///
@@ -362,7 +582,20 @@ fn aggregate_bars(bars: &[KlineBar], group_time: &NaiveDateTime) -> KlineBar {
 /// - 时间格式必须可解析，支持多种常见格式
 /// - 如果周期字符串无法识别，返回错误
 /// - 空数据返回空列表
-pub fn resample_klines_rust(bars: Vec<KlineBar>, target_period: &str) -> PyResult<Vec<KlineBar>> {
+pub fn resample_klines_rust(bars: Vec<KlineBar>, target_period: &str, calendar: Option<&TradingCalendar>) -> PyResult<Vec<KlineBar>> {
+    let groups = resample_groups(&bars, target_period, calendar)?;
+    Ok(groups.iter().map(|(group_time, range)| aggregate_bars(&bars[range.clone()], group_time)).collect())
+}
+
+/// 重采样分组：返回每个分组的时间（周期边界）和组内 bar 的下标范围
+///
+/// 连续且周期边界相同的 bar 为一组，与 `resample_klines_rust()` 的聚合一一对应，
+/// 供 Python 接口对额外列（VWAP、持仓量、自定义列）做同样分组的聚合。
+fn resample_groups(
+    bars: &[KlineBar],
+    target_period: &str,
+    calendar: Option<&TradingCalendar>,
+) -> PyResult<Vec<(NaiveDateTime, std::ops::Range<usize>)>> {
     if bars.is_empty() {
         return Ok(Vec::new());
     }
@@ -374,15 +607,14 @@ pub fn resample_klines_rust(bars: Vec<KlineBar>, target_period: &str) -> PyResul
         ))
     })?;
 
-    // 重采样结果容器
-    let mut resampled = Vec::new();
-    // 当前分组的 K 线列表
-    let mut current_group = Vec::new();
-    // 当前分组的时间（周期边界时间）
-    let mut current_group_time: Option<NaiveDateTime> = None;
+    // 日及以上周期按交易日历分组
+    let mut buckets = calendar.filter(|_| target_minutes >= 1440).map(|cal| CalendarBuckets::new(cal, target_minutes));
+
+    // 分组结果：(周期边界时间, 组内 bar 下标范围)
+    let mut groups: Vec<(NaiveDateTime, std::ops::Range<usize>)> = Vec::new();
 
     // 遍历所有 K 线，按时间分组
-    for bar in bars {
+    for (i, bar) in bars.iter().enumerate() {
         // 解析时间字符串
         let dt = parse_datetime(&bar.datetime).ok_or_else(|| {
             PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
@@ -392,44 +624,119 @@ pub fn resample_klines_rust(bars: Vec<KlineBar>, target_period: &str) -> PyResul
         })?;
 
         // 将时间向下取整到目标周期的边界
-        let group_time = round_down_to_period(dt, target_minutes);
+        let group_time = match buckets.as_mut() {
+            Some(b) => b.group_start(dt),
+            None => round_down_to_period(dt, target_minutes),
+        };
 
-        match current_group_time {
-            None => {
-                // 第一个 K 线：初始化分组
-                current_group.push(bar);
-                current_group_time = Some(group_time);
-            }
-            Some(ct) => {
-                if group_time != ct {
-                    // 时间边界变化：完成上一个分组，开始新分组
-                    if !current_group.is_empty() {
-                        // 将当前分组的所有 K 线聚合成一根
-                        resampled.push(aggregate_bars(&current_group, &ct));
-                    }
-                    // 开始新分组
-                    current_group = vec![bar];
-                    current_group_time = Some(group_time);
-                } else {
-                    // 仍在同一时间分组内：添加到当前分组
-                    current_group.push(bar);
-                }
-            }
+        match groups.last_mut() {
+            // 仍在同一时间分组内：扩展当前分组
+            Some((ct, range)) if *ct == group_time => range.end = i + 1,
+            // 第一根 K 线或时间边界变化：开始新分组
+            _ => groups.push((group_time, i..i + 1)),
+        }
+    }
+
+    Ok(groups)
+}
+
+/// 额外列的聚合方式（`resample_klines(agg=...)`）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColumnAgg {
+    First,
+    Last,
+    Max,
+    Min,
+    Sum,
+    Mean,
+    Count,
+}
+
+impl ColumnAgg {
+    fn parse(column: &str, how: &str) -> PyResult<Self> {
+        match how.to_lowercase().as_str() {
+            "first" => Ok(ColumnAgg::First),
+            "last" => Ok(ColumnAgg::Last),
+            "max" => Ok(ColumnAgg::Max),
+            "min" => Ok(ColumnAgg::Min),
+            "sum" => Ok(ColumnAgg::Sum),
+            "mean" => Ok(ColumnAgg::Mean),
+            "count" => Ok(ColumnAgg::Count),
+            other => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unsupported aggregation '{}' for column '{}': expected first/last/max/min/sum/mean/count",
+                other, column
+            ))),
         }
     }
 
-    // 处理最后一个分组（如果存在）
-    if !current_group.is_empty() {
-        if let Some(ct) = current_group_time {
-            resampled.push(aggregate_bars(&current_group, &ct));
+    /// 聚合一组值；缺失值（`None`）不参与计算，没有任何值时结果为 `None`（`count` 为 0）
+    fn apply(self, values: &[Option<f64>]) -> Option<f64> {
+        let mut present = values.iter().flatten().copied();
+        match self {
+            ColumnAgg::First => present.next(),
+            ColumnAgg::Last => present.last(),
+            ColumnAgg::Max => present.reduce(f64::max),
+            ColumnAgg::Min => present.reduce(f64::min),
+            ColumnAgg::Sum => present.reduce(|a, b| a + b),
+            ColumnAgg::Mean => {
+                let (sum, n) = present.fold((0.0, 0usize), |(s, n), v| (s + v, n + 1));
+                (n > 0).then(|| sum / n as f64)
+            }
+            ColumnAgg::Count => Some(present.count() as f64),
         }
     }
+}
 
-    Ok(resampled)
+/// 读取字典中的数值列（缺失或无法转换为数值时为 `None`）
+fn numeric_item(dict: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<f64>> {
+    Ok(dict.get_item(key)?.and_then(|v| v.extract().ok()))
+}
+
+// Convert Python dict to KlineBar（缺失的价格和成交量为 0，缺失的 symbol 为 "UNKNOWN"）
+pub(crate) fn kline_bar_from_pydict(bar_dict: &PyDict) -> PyResult<KlineBar> {
+    // 整数时间视为 epoch 毫秒（UTC）
+    let datetime: String = bar_dict
+        .get_item("datetime")?
+        .and_then(|v| crate::extract_datetime(v).0)
+        .unwrap_or_else(|| "".to_string());
+    let open: f64 = bar_dict
+        .get_item("open")?
+        .and_then(|v| v.extract().ok())
+        .unwrap_or(0.0);
+    let high: f64 = bar_dict
+        .get_item("high")?
+        .and_then(|v| v.extract().ok())
+        .unwrap_or(0.0);
+    let low: f64 = bar_dict
+        .get_item("low")?
+        .and_then(|v| v.extract().ok())
+        .unwrap_or(0.0);
+    let close: f64 = bar_dict
+        .get_item("close")?
+        .and_then(|v| v.extract().ok())
+        .unwrap_or(0.0);
+    let volume: f64 = bar_dict
+        .get_item("volume")?
+        .and_then(|v| v.extract().ok())
+        .unwrap_or(0.0);
+    let symbol: String = bar_dict
+        .get_item("symbol")?
+        .and_then(|v| v.extract().ok())
+        .unwrap_or_else(|| "UNKNOWN".to_string());
+
+    Ok(KlineBar {
+        datetime,
+        open,
+        high,
+        low,
+        close,
+        volume,
+        symbol,
+    })
 }
 
 // Convert KlineBar to Python dict
-fn kline_bar_to_pydict<'py>(py: Python<'py>, bar: &KlineBar) -> PyResult<Py<PyDict>> {
+pub(crate) fn kline_bar_to_pydict<'py>(py: Python<'py>, bar: &KlineBar) -> PyResult<Py<PyDict>> {
     let dict = PyDict::new(py);
     dict.set_item("datetime", &bar.datetime)?;
     dict.set_item("open", bar.open)?;
@@ -441,6 +748,51 @@ fn kline_bar_to_pydict<'py>(py: Python<'py>, bar: &KlineBar) -> PyResult<Py<PyDi
     Ok(dict.into())
 }
 
+/// K 线字典可选择的字段（`get_market_data(columns=...)`）
+const KLINE_COLUMNS: [&str; 7] = ["datetime", "open", "high", "low", "close", "volume", "symbol"];
+
+/// 校验 `columns` 参数，返回去重后的字段列表（保持传入顺序）
+fn parse_columns(columns: Option<Vec<String>>) -> PyResult<Option<Vec<String>>> {
+    let Some(columns) = columns else { return Ok(None) };
+    let mut selected: Vec<String> = Vec::with_capacity(columns.len());
+    for column in columns {
+        let column = column.trim().to_lowercase();
+        if !KLINE_COLUMNS.contains(&column.as_str()) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unknown column '{}': expected one of {}",
+                column,
+                KLINE_COLUMNS.join(", ")
+            )));
+        }
+        if !selected.contains(&column) {
+            selected.push(column);
+        }
+    }
+    if selected.is_empty() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "columns must contain at least one column",
+        ));
+    }
+    Ok(Some(selected))
+}
+
+/// 将 KlineBar 转换为只包含指定字段的 Python 字典
+fn kline_bar_to_pydict_columns<'py>(py: Python<'py>, bar: &KlineBar, columns: &[String]) -> PyResult<Py<PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for column in columns {
+        match column.as_str() {
+            "datetime" => dict.set_item("datetime", &bar.datetime)?,
+            "open" => dict.set_item("open", bar.open)?,
+            "high" => dict.set_item("high", bar.high)?,
+            "low" => dict.set_item("low", bar.low)?,
+            "close" => dict.set_item("close", bar.close)?,
+            "volume" => dict.set_item("volume", bar.volume)?,
+            _ => dict.set_item("symbol", &bar.symbol)?,
+        }
+    }
+    Ok(dict.unbind())
+}
+
 /// K 线重采样（Python 接口）
 ///
 /// 这是 `resample_klines_rust()` 的 Python 包装函数，用于从 Python 调用。
@@ -471,16 +823,33 @@ fn kline_bar_to_pydict<'py>(py: Python<'py>, bar: &KlineBar) -> PyResult<Py<PyDi
 ///
 /// # 转换为 15 分钟周期
 /// bars_15m = resample_klines(bars_1m, "15m")
+///
+/// # 附带 VWAP、bar 数，并自定义额外列的聚合方式
+/// bars_15m = resample_klines(bars_1m, "15m", vwap=True, count=True,
+///                            agg={"amount": "sum", "bid": "last", "spread": "mean"})
+///
+/// # 按上交所交易日合成日线（周末、节假日的 bar 归入下一个交易日）
+/// daily = resample_klines(bars_1m, "1d", calendar="SSE")
+///
+/// # 商品期货：夜盘 bar 归入下一个交易日，与期货公司的日线一致
+/// daily = resample_klines(rb_1m, "1d", calendar="SHFE")
 /// ```
 ///
 /// # 参数
 ///
 /// - `bars`: Python 列表，每个元素是包含 OHLCV 字段的字典
 /// - `target_period`: 目标周期字符串（如 "15m", "1h", "1d"）
+/// - `calendar`: 可选的交易日历（名称如 `"SSE"` 或 `TradingCalendar` 对象），仅对日及以上周期生效
+/// - `vwap`: 是否输出每个分组的成交量加权均价 `vwap`，默认 `False`。每根 bar 的价格优先取输入的 `vwap` 列，
+///   没有时用典型价格 `(high + low + close) / 3`；分组成交量为 0 时为 `None`
+/// - `count`: 是否输出每个分组包含的 bar 数 `bar_count`，默认 `False`；输入带 `trade_count` 列时同时输出其合计
+/// - `agg`: 可选的额外列聚合方式 `{列名: "first"|"last"|"max"|"min"|"sum"|"mean"|"count"}`，
+///   结果中以同名键输出；缺失或非数值的值不参与聚合
 ///
 /// # 返回值
 ///
-/// 返回 Python 列表，每个元素是重采样后的 K 线字典
+/// 返回 Python 列表，每个元素是重采样后的 K 线字典；输入带 `open_interest`（持仓量）列时，
+/// 输出每个分组最后一个持仓量
 ///
 /// # 性能说明
 ///
@@ -491,65 +860,274 @@ fn kline_bar_to_pydict<'py>(py: Python<'py>, bar: &KlineBar) -> PyResult<Py<PyDi
 /// - 输入数据必须按时间顺序排列
 /// - 每个字典必须包含 `datetime`, `open`, `high`, `low`, `close`, `volume` 字段
 /// - 可选字段：`symbol`（如果未提供，重采样后可能丢失）
+/// - `agg` 不能用于 `datetime`、OHLCV 和 `symbol` 等内置聚合的列，否则抛出 `ValueError`
 #[pyfunction]
-pub fn resample_klines(py: Python, bars: &PyList, target_period: String) -> PyResult<PyObject> {
+#[pyo3(signature = (bars, target_period, calendar=None, vwap=false, count=false, agg=None))]
+pub fn resample_klines(
+    py: Python,
+    bars: &Bound<'_, PyList>,
+    target_period: String,
+    calendar: Option<&Bound<'_, PyAny>>,
+    vwap: bool,
+    count: bool,
+    agg: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let calendar = calendar.map(TradingCalendar::from_py).transpose()?;
+    let mut extra_aggs: Vec<(String, ColumnAgg)> = Vec::new();
+    for (k, v) in agg.into_iter().flat_map(|d| d.iter()) {
+        let column: String = k.extract()?;
+        if ["datetime", "open", "high", "low", "close", "volume", "symbol"].contains(&column.as_str()) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Column '{}' is aggregated by resample_klines itself and cannot be listed in agg",
+                column
+            )));
+        }
+        let how: String = v.extract()?;
+        extra_aggs.push((column.clone(), ColumnAgg::parse(&column, &how)?));
+    }
+    // 额外输出列：(输出键, 聚合方式, 每根 bar 的值)
+    let mut extras: Vec<(String, ColumnAgg, Vec<Option<f64>>)> =
+        extra_aggs.into_iter().map(|(c, a)| (c, a, Vec::with_capacity(bars.len()))).collect();
+    let mut open_interest: Vec<Option<f64>> = Vec::with_capacity(bars.len());
+    let mut trade_count: Vec<Option<f64>> = Vec::with_capacity(bars.len());
+    // 每根 bar 的成交额（成交量 × 价格），用于计算 VWAP
+    let mut notional: Vec<Option<f64>> = Vec::with_capacity(if vwap { bars.len() } else { 0 });
     // Convert Python list of dicts to KlineBar
     let mut kline_bars = Vec::with_capacity(bars.len());
     for item in bars.iter() {
-        let bar_dict: &PyDict = item.downcast()?;
-        let datetime: String = bar_dict
-            .get_item("datetime")?
-            .and_then(|v| v.extract().ok())
-            .unwrap_or_else(|| "".to_string());
-        let open: f64 = bar_dict
-            .get_item("open")?
-            .and_then(|v| v.extract().ok())
-            .unwrap_or(0.0);
-        let high: f64 = bar_dict
-            .get_item("high")?
-            .and_then(|v| v.extract().ok())
-            .unwrap_or(0.0);
-        let low: f64 = bar_dict
-            .get_item("low")?
-            .and_then(|v| v.extract().ok())
-            .unwrap_or(0.0);
-        let close: f64 = bar_dict
-            .get_item("close")?
-            .and_then(|v| v.extract().ok())
-            .unwrap_or(0.0);
-        let volume: f64 = bar_dict
-            .get_item("volume")?
-            .and_then(|v| v.extract().ok())
-            .unwrap_or(0.0);
-        let symbol: String = bar_dict
-            .get_item("symbol")?
-            .and_then(|v| v.extract().ok())
-            .unwrap_or_else(|| "UNKNOWN".to_string());
+        let bar_dict = item.downcast::<PyDict>()?;
+        let bar = kline_bar_from_pydict(bar_dict.as_gil_ref())?;
+        let (high, low, close, volume) = (bar.high, bar.low, bar.close, bar.volume);
 
-        kline_bars.push(KlineBar {
-            datetime,
-            open,
-            high,
-            low,
-            close,
-            volume,
-            symbol,
-        });
+        for (column, _, values) in extras.iter_mut() {
+            values.push(numeric_item(bar_dict, column)?);
+        }
+        open_interest.push(numeric_item(bar_dict, "open_interest")?);
+        if count {
+            trade_count.push(numeric_item(bar_dict, "trade_count")?);
+        }
+        if vwap {
+            notional.push(Some(volume * numeric_item(bar_dict, "vwap")?.unwrap_or((high + low + close) / 3.0)));
+        }
+
+        kline_bars.push(bar);
     }
 
+    let has_open_interest = open_interest.iter().any(Option::is_some);
+    let has_trade_count = trade_count.iter().any(Option::is_some);
+    // 额外列按与 OHLCV 相同的分组聚合
+    let groups = if vwap || count || has_open_interest || !extras.is_empty() {
+        resample_groups(&kline_bars, &target_period, calendar.as_ref())?
+    } else {
+        Vec::new()
+    };
+
     // Resample using Rust (high performance)
-    let resampled = resample_klines_rust(kline_bars, &target_period)?;
+    let resampled = resample_klines_rust(kline_bars, &target_period, calendar.as_ref())?;
 
     // Convert back to Python list
-    let py_list = PyList::empty(py);
-    for bar in resampled {
-        let py_dict = kline_bar_to_pydict(py, &bar)?;
+    let py_list = PyList::empty_bound(py);
+    for (i, bar) in resampled.iter().enumerate() {
+        let py_dict = kline_bar_to_pydict(py, bar)?;
+        let Some((_, range)) = groups.get(i) else {
+            py_list.append(py_dict)?;
+            continue;
+        };
+        let d = py_dict.bind(py);
+        if vwap {
+            let total = ColumnAgg::Sum.apply(&notional[range.clone()]).unwrap_or(0.0);
+            d.set_item("vwap", (bar.volume > 0.0).then(|| total / bar.volume))?;
+        }
+        if count {
+            d.set_item("bar_count", range.len())?;
+            if has_trade_count {
+                d.set_item("trade_count", ColumnAgg::Sum.apply(&trade_count[range.clone()]))?;
+            }
+        }
+        if has_open_interest {
+            d.set_item("open_interest", ColumnAgg::Last.apply(&open_interest[range.clone()]))?;
+        }
+        for (column, how, values) in &extras {
+            d.set_item(column, how.apply(&values[range.clone()]))?;
+        }
         py_list.append(py_dict)?;
     }
 
     Ok(py_list.into())
 }
 
+/// K 线升采样（Rust 实现）
+///
+/// `resample_klines_rust()` 的反向操作：把粗周期的 K 线展开到更细的时间网格上。
+/// 常见用途是把变化很慢的序列（如日频因子、宏观数据）与日内 bar 对齐，让 `run_multi` 在每个日内时间点都能读到它。
+///
+/// ## 工作原理（简单理解）
+///
+/// 1. 原始 bar 按原样保留在自己的时间点上
+/// 2. 从原始 bar 之后的第一个网格点开始（网格与 `resample_klines` 的周期边界对齐，如 1 分钟网格为整分钟），
+///    到下一根原始 bar 之前（不含），每个网格点补一根 bar：开高低收都等于前一根原始 bar 的收盘价，成交量为 0
+/// 3. 最后一根原始 bar 之后补到 `end`（包含）；没有 `end` 时不再补
+/// 4. `is_open` 返回 `false` 的网格点（如休市时段）不补 bar
+///
+/// # 参数
+///
+/// - `bars`: 原始 K 线数据列表，必须按时间顺序排列
+/// - `target_period`: 目标周期字符串（分钟、小时或日周期，如 "1m", "5m", "1h", "1d"）
+/// - `end`: 可选的补齐终点（包含）
+/// - `is_open`: 网格点是否需要补 bar
+///
+/// # 返回值
+///
+/// `(bar, filled)` 列表，`filled` 为 `true` 表示这根 bar 是补出来的
+///
+/// # 注意事项
+///
+/// - 前向填充只是把已有的值向后延续，不会产生新信息；但如果粗周期 bar 的时间戳是周期开始时间
+///   （如日线标在 00:00），当天补出的 bar 会提前看到当天收盘价，使用前请确认时间戳代表"数据可用的时刻"
+/// - 周期无法识别或不是分钟、小时、日周期，以及时间无法解析或未按时间排序时返回错误
+pub fn upsample_klines_rust(
+    bars: Vec<KlineBar>,
+    target_period: &str,
+    end: Option<NaiveDateTime>,
+    is_open: impl Fn(NaiveDateTime) -> bool,
+) -> PyResult<Vec<(KlineBar, bool)>> {
+    let step = period_to_minutes(target_period).filter(|m| *m > 0 && *m < 10080).ok_or_else(|| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Unsupported upsampling period: {} (expected a minute, hour or day period)",
+            target_period
+        ))
+    })?;
+    let times = bars
+        .iter()
+        .map(|b| {
+            parse_datetime(&b.datetime).ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid datetime format: {}", b.datetime))
+            })
+        })
+        .collect::<PyResult<Vec<NaiveDateTime>>>()?;
+    if let Some(i) = (1..times.len()).find(|&i| times[i] < times[i - 1]) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Bars must be sorted by datetime: {} comes after {}",
+            bars[i].datetime,
+            bars[i - 1].datetime
+        )));
+    }
+
+    let delta = chrono::Duration::minutes(step);
+    let mut out = Vec::with_capacity(bars.len());
+    for (i, bar) in bars.iter().enumerate() {
+        out.push((bar.clone(), false));
+        // 下一根原始 bar 之前（不含），或最后一根之后到 end（包含）
+        let (until, inclusive) = match times.get(i + 1) {
+            Some(next) => (*next, false),
+            None => match end {
+                Some(e) => (e, true),
+                None => continue,
+            },
+        };
+        let mut t = round_down_to_period(times[i], step) + delta;
+        while t < until || (inclusive && t == until) {
+            if is_open(t) {
+                let filled = KlineBar {
+                    datetime: t.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    open: bar.close,
+                    high: bar.close,
+                    low: bar.close,
+                    close: bar.close,
+                    volume: 0.0,
+                    symbol: bar.symbol.clone(),
+                };
+                out.push((filled, true));
+            }
+            t += delta;
+        }
+    }
+    Ok(out)
+}
+
+/// K 线升采样（Python 接口）
+///
+/// 把粗周期 K 线展开到更细的时间网格：原始 bar 原样保留，中间的网格点用前一根 bar 的收盘价前向填充
+/// （开高低收相同、成交量为 0）。适合把日频因子等慢变量与日内 bar 对齐后一起放进 `run_multi`。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import upsample_klines
+///
+/// # 日频因子展开到 A 股交易时段内的每一分钟
+/// factor_1m = upsample_klines(factor_daily, "1m", sessions="SSE", end="2024-06-28 15:00:00")
+/// result = engine.run_multi(strategy, {"IF": if_1m, "factor": factor_1m})
+///
+/// # 只按时段过滤
+/// factor_5m = upsample_klines(factor_daily, "5m", sessions=["09:30-11:30", "13:00-15:00"])
+/// ```
+///
+/// # 参数
+///
+/// - `bars`: Python 列表，每个元素是包含 OHLCV 字段的字典，必须按时间顺序排列
+/// - `target_period`: 目标周期字符串（分钟、小时或日周期，如 "1m", "5m", "1h"）
+/// - `sessions`: 可选，只在开市时间补 bar：时段列表（如 `["09:30-11:30", "13:00-15:00"]`，只过滤时间），
+///   或交易日历名称 / `TradingCalendar`（同时排除休市日，夜盘按交易所规则判断）
+/// - `end`: 可选的补齐终点（包含），最后一根原始 bar 之后补到这里；默认不补
+///
+/// # 返回值
+///
+/// 返回 Python 列表，每个元素是 K 线字典，`filled` 字段表示该 bar 是否为补出来的
+///
+/// # 注意事项
+///
+/// - 原始 bar 的时间戳应当表示"数据可用的时刻"：日线标在 00:00 时，当天补出的 bar 会提前用到当天收盘价，
+///   可以先把时间改为收盘时间（如 "15:00:00"）再升采样
+/// - 原始 bar 只保留 OHLCV 与 `symbol`，其他字段不会复制到结果中
+/// - 周期不是分钟、小时、日周期，时段格式或日历名称无法识别，或数据未按时间排序时抛出 `ValueError`
+#[pyfunction]
+#[pyo3(signature = (bars, target_period, sessions=None, end=None))]
+pub fn upsample_klines(
+    py: Python,
+    bars: &Bound<'_, PyList>,
+    target_period: String,
+    sessions: Option<&Bound<'_, PyAny>>,
+    end: Option<String>,
+) -> PyResult<PyObject> {
+    let (windows, calendar) = match sessions {
+        Some(s) if s.is_instance_of::<PyString>() || s.is_instance_of::<TradingCalendar>() => {
+            (None, Some(TradingCalendar::from_py(s)?))
+        }
+        Some(s) => {
+            let windows: Vec<String> = s.extract()?;
+            let parsed = crate::sessions::TradingSessions::parse(&windows)
+                .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+            (Some(parsed), None)
+        }
+        None => (None, None),
+    };
+    let end = match end {
+        Some(e) => Some(parse_datetime(e.trim()).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid datetime: {}", e))
+        })?),
+        None => None,
+    };
+
+    let mut kline_bars = Vec::with_capacity(bars.len());
+    for item in bars.iter() {
+        kline_bars.push(kline_bar_from_pydict(item.downcast::<PyDict>()?.as_gil_ref())?);
+    }
+
+    let is_open = |t: NaiveDateTime| {
+        windows.as_ref().is_none_or(|w| w.contains(t.time())) && calendar.as_ref().is_none_or(|c| c.is_open(t))
+    };
+    let upsampled = upsample_klines_rust(kline_bars, &target_period, end, is_open)?;
+
+    let py_list = PyList::empty_bound(py);
+    for (bar, filled) in upsampled {
+        let py_dict = kline_bar_to_pydict(py, &bar)?;
+        py_dict.bind(py).set_item("filled", filled)?;
+        py_list.append(py_dict)?;
+    }
+    Ok(py_list.into())
+}
+
 // ============================================================================
 // Direct DuckDB Operations (High Performance - Eliminates Python Conversion)
 // ============================================================================
@@ -614,12 +1192,7 @@ pub fn load_klines_rust(
 ) -> PyResult<Vec<KlineBar>> {
 
     // Connect to database
-    let conn = Connection::open(Path::new(db_path)).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
-            "Failed to connect to database: {}",
-            e
-        ))
-    })?;
+    let conn = open_connection(db_path)?;
 
     // Ensure target table exists and retrieve its name
     let table_name = ensure_period_table(&conn, period)?;
@@ -657,18 +1230,7 @@ pub fn load_klines_rust(
 
     // Helper function to map row to KlineBar
     let map_row = |row: &duckdb::Row| -> duckdb::Result<KlineBar> {
-        let mut datetime: String = row.get(0)?;
-
-        if let Some(stripped) = datetime.strip_suffix(".000000") {
-            datetime = stripped.to_string();
-        } else if datetime.contains('.') {
-            while datetime.ends_with('0') {
-                datetime.pop();
-            }
-            if datetime.ends_with('.') {
-                datetime.pop();
-            }
-        }
+        let datetime = trim_fractional_seconds(row.get(0)?);
 
         Ok(KlineBar {
             datetime,
@@ -717,12 +1279,91 @@ pub fn load_klines_rust(
     Ok(bars)
 }
 
-/// 从 DuckDB 加载并合成 K 线数据（Rust 实现）
+/// 一条 SQL 查询多个标的的 K 线（Rust 内部使用）
 ///
-/// 这是 `load_klines_rust()` 的别名函数，用于保持 API 一致性。
-/// 直接调用 `load_klines_rust()` 执行查询，返回指定周期的 K 线数据。
-///
-/// # 参数
+/// 参数含义与 `load_klines_rust()` 相同；`count > 0` 时每个标的各取最近 `count` 条。
+/// 返回按 (symbol, datetime) 排序的 K 线。
+pub fn load_klines_multi_rust(
+    db_path: &str,
+    symbols: &[String],
+    period: &str,
+    start: Option<&str>,
+    end: Option<&str>,
+    count: i64,
+) -> PyResult<Vec<KlineBar>> {
+    if symbols.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Connect to database
+    let conn = open_connection(db_path)?;
+
+    let table_name = ensure_period_table(&conn, period)?;
+
+    // When count > 0, ignore start parameter (query most recent N bars per symbol)
+    let use_limit = count > 0;
+    let effective_start = if use_limit { None } else { start };
+
+    let placeholders = vec!["?"; symbols.len()].join(", ");
+    let mut where_parts = vec![format!("symbol IN ({})", placeholders)];
+    let mut params: Vec<String> = symbols.to_vec();
+    if let Some(s) = effective_start {
+        where_parts.push("datetime >= ?".to_string());
+        params.push(s.to_string());
+    }
+    if let Some(e) = end {
+        where_parts.push("datetime <= ?".to_string());
+        params.push(e.to_string());
+    }
+    let limit_clause = if use_limit {
+        format!(" QUALIFY ROW_NUMBER() OVER (PARTITION BY symbol ORDER BY datetime DESC) <= {}", count)
+    } else {
+        String::new()
+    };
+
+    let query = format!(
+        "SELECT symbol, strftime(datetime, '%Y-%m-%d %H:%M:%S.%f') AS datetime_str, open, high, low, close, volume
+         FROM {} WHERE {}{} ORDER BY symbol, datetime",
+        table_name,
+        where_parts.join(" AND "),
+        limit_clause
+    );
+
+    let mut stmt = conn.prepare(&query).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to prepare query: {}", e))
+    })?;
+    let rows = stmt
+        .query_map(duckdb::params_from_iter(params), |row| {
+            Ok(KlineBar {
+                symbol: row.get(0)?,
+                datetime: trim_fractional_seconds(row.get(1)?),
+                open: row.get::<_, f64>(2)?,
+                high: row.get::<_, f64>(3)?,
+                low: row.get::<_, f64>(4)?,
+                close: row.get::<_, f64>(5)?,
+                volume: row.get::<_, f64>(6)?,
+            })
+        })
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to execute query: {}", e))
+        })?;
+
+    // Collect results
+    let mut bars = Vec::new();
+    for row_result in rows {
+        bars.push(row_result.map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to read row: {}", e))
+        })?);
+    }
+    Ok(bars)
+}
+
+/// 从 DuckDB 加载并合成 K 线数据（Rust 实现）
+///
+/// 这是 `load_klines_rust()` 的别名函数，用于保持 API 一致性。
+/// 直接调用 `load_klines_rust()` 执行查询，返回指定周期的 K 线数据。
+///
+/// # 参数
 ///
 /// 与 `load_klines_rust()` 相同
 ///
@@ -771,20 +1412,46 @@ pub fn load_and_synthesize_klines_rust(
 ///     period="1d",
 ///     count=100
 /// )
+///
+/// # 一次查询多个标的（单条 SQL）：{"AAPL": [...], "MSFT": [...]}
+/// by_symbol = get_market_data("data/backtest.db", ["AAPL", "MSFT"], "1d", start="2020-01-01")
+///
+/// # 或者按时间排序的扁平列表，每根 bar 带 symbol 字段
+/// flat_bars = get_market_data("data/backtest.db", ["AAPL", "MSFT"], "1d", flat=True)
+///
+/// # 库中存的是 UTC 时间时，按交易所本地时间查询和返回
+/// local_bars = get_market_data("data/backtest.db", "600000.SH", "1m",
+///                              start="2024-01-02 09:30:00", tz="Asia/Shanghai")
+///
+/// # 前复权价格（根据 corporate_actions 表中的分红、拆股记录计算）
+/// qfq_bars = get_market_data("data/backtest.db", "600519.SH", "1d", adjust="qfq")
+///
+/// # 只取需要的字段，少构造字典键值
+/// closes = get_market_data("data/backtest.db", "AAPL", "1d", columns=["datetime", "close"])
 /// ```
 ///
 /// # 参数
 ///
 /// - `db_path`: 数据库文件路径
-/// - `symbol`: 交易标的代码
+/// - `symbol`: 交易标的代码，或标的代码列表（多个标的在一条 SQL 中查询）
 /// - `period`: 周期字符串（如 "1m", "1d"）
 /// - `start`: 开始时间（可选）
 /// - `end`: 结束时间（可选）
-/// - `count`: 查询数量，> 0 时查询最近 N 条，-1 表示查询所有
+/// - `count`: 查询数量，> 0 时查询最近 N 条（多个标的时每个标的各 N 条），-1 表示查询所有
+/// - `flat`: 仅对标的列表有效。默认 `False` 返回以标的代码为键的字典；
+///   为 `True` 时返回按 (datetime, symbol) 排序的扁平列表
+/// - `tz`: 可选的时区（IANA 名称，如 `"Asia/Shanghai"`）。指定后库中时间视为 UTC，
+///   返回该时区的本地时间；不带偏移的 `start`/`end` 也按该时区解释
+/// - `adjust`: 复权方式，`"qfq"` 前复权、`"hfq"` 后复权，默认 `None` 不复权。
+///   根据 `save_corporate_actions()` 写入的分红、拆股记录在查询时计算，开高低收按复权因子调整，
+///   成交量按拆股比例反向调整
+/// - `columns`: 可选的字段列表（`datetime`、`open`、`high`、`low`、`close`、`volume`、`symbol`），
+///   指定后每根 K 线的字典只包含这些字段，默认返回全部字段
 ///
 /// # 返回值
 ///
-/// 返回 Python 列表，每个元素是包含 OHLCV 字段的字典
+/// 单个标的时返回 Python 列表，每个元素是包含 OHLCV 字段的字典；
+/// 标的列表时返回 `{symbol: [bar, ...]}`（没有数据的标的对应空列表）或扁平列表
 ///
 /// # 性能说明
 ///
@@ -795,18 +1462,66 @@ pub fn load_and_synthesize_klines_rust(
 /// - 如果 `count > 0`，会忽略 `start` 参数
 /// - 数据库文件不存在时会自动创建
 /// - 表不存在时会自动创建
+/// - `tz` 只在写入时同样使用了 `tz`（或数据本身带 UTC 偏移）、库中存的是 UTC 时间时才有意义
+/// - 复权因子使用库中全部的公司行为，与 `start`/`end`/`count` 无关：前复权以库中最新价格为基准，
+///   后复权以最早价格为基准；`adjust` 不是 `"qfq"`/`"hfq"` 时返回 `ValueError`
+/// - `columns` 中有未知字段或为空列表时返回 `ValueError`；标的列表按标的分组时仍以 `symbol` 为键，
+///   与 `columns` 是否包含 `symbol` 无关
 #[pyfunction]
-#[pyo3(signature = (db_path, symbol, period, start=None, end=None, count=-1))]
+#[pyo3(signature = (db_path, symbol, period, start=None, end=None, count=-1, flat=false, tz=None, adjust=None, columns=None))]
+#[allow(clippy::too_many_arguments)]
 pub fn get_market_data(
     py: Python,
     db_path: String,
-    symbol: String,
+    symbol: &Bound<'_, PyAny>,
     period: String,
     start: Option<String>,
     end: Option<String>,
     count: i64,
+    flat: bool,
+    tz: Option<String>,
+    adjust: Option<String>,
+    columns: Option<Vec<String>>,
 ) -> PyResult<PyObject> {
-    let bars = load_klines_rust(
+    let tz = parse_timezone(tz.as_deref())?;
+    let (start, end) = utc_bounds(start, end, tz)?;
+    let adjustment = Adjustment::parse(adjust.as_deref())?;
+    let columns = parse_columns(columns)?;
+    let to_pydict = |bar: &KlineBar| match &columns {
+        Some(columns) => kline_bar_to_pydict_columns(py, bar, columns),
+        None => kline_bar_to_pydict(py, bar),
+    };
+
+    // 标的列表：一条 SQL 查询全部标的
+    if !symbol.is_instance_of::<pyo3::types::PyString>() {
+        let symbols: Vec<String> = symbol.extract()?;
+        let mut bars = load_klines_multi_rust(&db_path, &symbols, &period, start.as_deref(), end.as_deref(), count)?;
+        localize_bars(&mut bars, tz);
+        if let Some(adjustment) = adjustment {
+            adjust_klines(&db_path, &period, &mut bars, adjustment)?;
+        }
+        if flat {
+            bars.sort_by(|a, b| (&a.datetime, &a.symbol).cmp(&(&b.datetime, &b.symbol)));
+            let py_list = PyList::empty_bound(py);
+            for bar in &bars {
+                py_list.append(to_pydict(bar)?)?;
+            }
+            return Ok(py_list.into());
+        }
+        let by_symbol = PyDict::new_bound(py);
+        for s in &symbols {
+            by_symbol.set_item(s, PyList::empty_bound(py))?;
+        }
+        for bar in &bars {
+            if let Some(list) = by_symbol.get_item(&bar.symbol)? {
+                list.downcast::<PyList>()?.append(to_pydict(bar)?)?;
+            }
+        }
+        return Ok(by_symbol.into());
+    }
+
+    let symbol: String = symbol.extract()?;
+    let mut bars = load_klines_rust(
         &db_path,
         &symbol,
         &period,
@@ -814,10 +1529,14 @@ pub fn get_market_data(
         end.as_deref(),
         count,
     )?;
+    localize_bars(&mut bars, tz);
+    if let Some(adjustment) = adjustment {
+        adjust_klines(&db_path, &period, &mut bars, adjustment)?;
+    }
 
     let py_list = PyList::empty(py);
     for bar in bars {
-        let py_dict = kline_bar_to_pydict(py, &bar)?;
+        let py_dict = to_pydict(&bar)?;
         py_list.append(py_dict)?;
     }
 
@@ -831,13 +1550,14 @@ pub fn get_market_data(
 ///
 /// # 参数
 ///
-/// 与 `get_market_data()` 相同
+/// 与 `get_market_data()` 相同（不支持标的列表和 `flat`），包括可选的 `tz`
 ///
 /// # 返回值
 ///
 /// 返回 Python 列表，每个元素是包含 OHLCV 字段的字典
 #[pyfunction]
-#[pyo3(signature = (db_path, symbol, target_period, start=None, end=None, count=-1))]
+#[pyo3(signature = (db_path, symbol, target_period, start=None, end=None, count=-1, tz=None))]
+#[allow(clippy::too_many_arguments)]
 pub fn load_and_synthesize_klines(
     py: Python,
     db_path: String,
@@ -846,8 +1566,11 @@ pub fn load_and_synthesize_klines(
     start: Option<String>,
     end: Option<String>,
     count: i64,
+    tz: Option<String>,
 ) -> PyResult<PyObject> {
-    let bars = load_and_synthesize_klines_rust(
+    let tz = parse_timezone(tz.as_deref())?;
+    let (start, end) = utc_bounds(start, end, tz)?;
+    let mut bars = load_and_synthesize_klines_rust(
         &db_path,
         &symbol,
         &target_period,
@@ -855,6 +1578,7 @@ pub fn load_and_synthesize_klines(
         end.as_deref(),
         count,
     )?;
+    localize_bars(&mut bars, tz);
 
     // Convert to Python list (only once at the end)
     let py_list = PyList::empty(py);
@@ -866,6 +1590,217 @@ pub fn load_and_synthesize_klines(
     Ok(py_list.into())
 }
 
+/// 导入时的 OHLC 校验方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ValidationMode {
+    /// 只报告问题，数据照常写入
+    Flag,
+    /// 丢弃有问题的行，其余数据照常写入
+    Reject,
+}
+
+impl ValidationMode {
+    fn parse(mode: Option<&str>) -> PyResult<Option<Self>> {
+        match mode.map(|m| m.to_lowercase()).as_deref() {
+            None => Ok(None),
+            Some("flag") => Ok(Some(ValidationMode::Flag)),
+            Some("reject") => Ok(Some(ValidationMode::Reject)),
+            Some(other) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unsupported validate mode '{}': expected 'flag' or 'reject'",
+                other
+            ))),
+        }
+    }
+}
+
+/// 导入时同一时间重复的处理方式（`dedupe` 参数）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DedupePolicy {
+    /// 保留先出现的一根；与库中已有数据冲突时保留库中的数据
+    KeepFirst,
+    /// 保留后出现的一根；与库中已有数据冲突时覆盖库中的数据
+    KeepLast,
+}
+
+impl DedupePolicy {
+    fn parse(policy: Option<&str>) -> PyResult<Option<Self>> {
+        match policy.map(|p| p.to_lowercase().replace('-', "_")).as_deref() {
+            None => Ok(None),
+            Some("first" | "keep_first") => Ok(Some(DedupePolicy::KeepFirst)),
+            Some("last" | "keep_last") => Ok(Some(DedupePolicy::KeepLast)),
+            Some(other) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unsupported dedupe policy '{}': expected 'first' or 'last'",
+                other
+            ))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DedupePolicy::KeepFirst => "first",
+            DedupePolicy::KeepLast => "last",
+        }
+    }
+
+    /// 从临时表插入周期表时的冲突处理子句
+    fn conflict_clause(policy: Option<Self>) -> &'static str {
+        match policy {
+            Some(DedupePolicy::KeepLast) => {
+                "ON CONFLICT (symbol, datetime) DO UPDATE SET
+                 open = excluded.open, high = excluded.high, low = excluded.low,
+                 close = excluded.close, volume = excluded.volume"
+            }
+            _ => "ON CONFLICT (symbol, datetime) DO NOTHING",
+        }
+    }
+}
+
+/// 按时间稳定排序并去掉重复时间的 K 线，返回去掉的行数
+///
+/// 时间无法解析的行排在最前面且不参与去重（写入时报错）。
+fn sort_and_dedupe(bars: &mut Vec<KlineBar>, policy: DedupePolicy) -> usize {
+    let mut keyed: Vec<(Option<NaiveDateTime>, KlineBar)> =
+        bars.drain(..).map(|bar| (parse_datetime(&bar.datetime), bar)).collect();
+    keyed.sort_by_key(|(dt, _)| *dt);
+    let before = keyed.len();
+    // dedup_by 保留每组中的第一个，keep-last 时反转后去重再反转回来
+    if policy == DedupePolicy::KeepLast {
+        keyed.reverse();
+    }
+    keyed.dedup_by(|a, b| a.0.is_some() && a.0 == b.0);
+    if policy == DedupePolicy::KeepLast {
+        keyed.reverse();
+    }
+    let removed = before - keyed.len();
+    bars.extend(keyed.into_iter().map(|(_, bar)| bar));
+    removed
+}
+
+/// 统计临时表的行数，以及其中与周期表已有数据时间冲突的行数
+fn count_stored_conflicts(conn: &Connection, table_name: &str, temp_table: &str) -> PyResult<(usize, usize)> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT COUNT(*), COUNT(k.datetime) FROM {} t
+             LEFT JOIN {} k ON k.symbol = t.symbol AND k.datetime = t.datetime",
+            temp_table, table_name
+        ))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to prepare query: {}", e)))?;
+    let counts = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to count conflicting rows: {}", e))
+        })?
+        .next()
+        .transpose()
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to read row: {}", e)))?;
+    let (rows, conflicting) = counts.unwrap_or((0, 0));
+    Ok((rows as usize, conflicting as usize))
+}
+
+/// 排序去重导入的统计
+struct DedupeCounts {
+    policy: DedupePolicy,
+    /// 读取的行数
+    rows: usize,
+    /// 批次内重复、被去掉的行数
+    duplicates: usize,
+    /// 去重（及校验剔除）后写入周期表的行数
+    written: usize,
+    /// 其中与库中已有数据时间冲突的行数
+    conflicting: usize,
+}
+
+/// 构造排序去重导入的报告字典；同时做了校验时，校验报告放在 `validation` 键下
+fn dedupe_report(py: Python, counts: DedupeCounts, validation: Option<PyObject>) -> PyResult<PyObject> {
+    let keep_last = counts.policy == DedupePolicy::KeepLast;
+    let report = PyDict::new_bound(py);
+    report.set_item("policy", counts.policy.name())?;
+    report.set_item("rows", counts.rows)?;
+    report.set_item("inserted", counts.written - counts.conflicting)?;
+    report.set_item("updated", if keep_last { counts.conflicting } else { 0 })?;
+    report.set_item(
+        "skipped",
+        counts.duplicates + if keep_last { 0 } else { counts.conflicting },
+    )?;
+    report.set_item("duplicates", counts.duplicates)?;
+    report.set_item("conflicting", counts.conflicting)?;
+    if let Some(validation) = validation {
+        report.set_item("validation", validation)?;
+    }
+    Ok(report.into())
+}
+
+/// 一行 K 线的校验问题
+#[derive(Clone, Debug)]
+struct ValidationIssue {
+    /// 行号（从 0 开始，按导入顺序）
+    index: usize,
+    datetime: String,
+    reasons: Vec<&'static str>,
+}
+
+/// 逐行检查 OHLC 合理性与时间单调性
+///
+/// 检查项：`high` 低于 `open`/`close`（`high_below_open_close`）、`low` 高于 `open`/`close`
+/// （`low_above_open_close`）、价格不为正（`non_positive_price`）、时间无法解析（`invalid_datetime`）、
+/// 时间不晚于上一根有效 bar（`non_monotonic_datetime`，重复时间也算）。
+/// 时间倒退的行不会成为后续比较的基准，单根乱序的 bar 不会让之后的所有 bar 都被标记。
+fn validate_klines<'a>(rows: impl Iterator<Item = (&'a str, f64, f64, f64, f64)>) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut last_dt: Option<NaiveDateTime> = None;
+    for (index, (datetime, open, high, low, close)) in rows.enumerate() {
+        let mut reasons = Vec::new();
+        if high < open.max(close) {
+            reasons.push("high_below_open_close");
+        }
+        if low > open.min(close) {
+            reasons.push("low_above_open_close");
+        }
+        if open <= 0.0 || high <= 0.0 || low <= 0.0 || close <= 0.0 {
+            reasons.push("non_positive_price");
+        }
+        match parse_datetime(datetime) {
+            None => reasons.push("invalid_datetime"),
+            Some(dt) if last_dt.is_some_and(|last| dt <= last) => reasons.push("non_monotonic_datetime"),
+            Some(dt) => last_dt = Some(dt),
+        }
+        if !reasons.is_empty() {
+            issues.push(ValidationIssue { index, datetime: datetime.to_string(), reasons });
+        }
+    }
+    issues
+}
+
+/// 构建校验报告：`{"mode", "checked", "invalid", "rejected", "counts": {原因: 行数}, "issues": [...]}`
+fn validation_report(
+    py: Python,
+    mode: ValidationMode,
+    checked: usize,
+    issues: &[ValidationIssue],
+) -> PyResult<PyObject> {
+    let report = PyDict::new_bound(py);
+    report.set_item("mode", if mode == ValidationMode::Reject { "reject" } else { "flag" })?;
+    report.set_item("checked", checked)?;
+    report.set_item("invalid", issues.len())?;
+    report.set_item("rejected", if mode == ValidationMode::Reject { issues.len() } else { 0 })?;
+    let counts = PyDict::new_bound(py);
+    let issue_list = PyList::empty_bound(py);
+    for issue in issues {
+        for reason in &issue.reasons {
+            let n: usize = counts.get_item(reason)?.map(|v| v.extract()).transpose()?.unwrap_or(0);
+            counts.set_item(reason, n + 1)?;
+        }
+        let item = PyDict::new_bound(py);
+        item.set_item("index", issue.index)?;
+        item.set_item("datetime", &issue.datetime)?;
+        item.set_item("reasons", issue.reasons.clone())?;
+        issue_list.append(item)?;
+    }
+    report.set_item("counts", counts)?;
+    report.set_item("issues", issue_list)?;
+    Ok(report.into())
+}
+
 /// 将 K 线数据保存到 DuckDB（Rust 实现）
 ///
 /// 高性能的批量插入函数，使用临时表策略实现超高速数据写入。
@@ -883,7 +1818,7 @@ pub fn load_and_synthesize_klines(
 /// 1. **准备数据**：将 Python 列表转换为 Rust `KlineBar` 结构
 /// 2. **开始事务**：确保数据一致性
 /// 3. **创建临时表**：在内存中创建一个临时仓库
-/// 4. **批量打包**：将数据分成 50k 一批，批量插入临时表（不需要检查冲突）
+/// 4. **批量打包**：通过 DuckDB Appender 把数据逐行追加到临时表（不拼接 SQL，不需要检查冲突）
 /// 5. **一次性入库**：从临时表一次性插入到正式表（检查冲突，去重）
 /// 6. **清理临时表**：删除临时表
 /// 7. **提交事务**：所有操作原子性提交
@@ -918,7 +1853,7 @@ pub fn load_and_synthesize_klines(
 /// ## 性能优化策略
 ///
 /// - **临时表策略**：先插入临时表（无冲突检查），再一次性插入正式表
-/// - **批量插入**：50k 记录/批，减少 SQL 语句数量
+/// - **Appender 写入**：按列直接追加，省去 SQL 字符串拼接与解析
 /// - **事务处理**：使用事务确保原子性和性能
 /// - **进度显示**：每 50k 记录显示一次进度
 ///
@@ -929,10 +1864,36 @@ pub fn load_and_synthesize_klines(
 /// - `period`: 周期字符串（如 "1m", "1d"）
 /// - `bars`: Python 列表，每个元素是包含 OHLCV 字段的字典
 /// - `replace`: 是否替换现有数据（True=删除旧数据后插入，False=追加）
+/// - `validate`: 可选的 OHLC 校验方式。`"flag"` 只报告问题行、数据照常写入；
+///   `"reject"` 丢弃问题行、其余照常写入。检查 `high < max(open, close)`、`low > min(open, close)`、
+///   价格不为正、时间无法解析或不严格递增
+/// - `tz`: 可选的时区（IANA 名称，如 `"Asia/Shanghai"`、`"UTC"`），表示不带偏移的时间是哪个时区的本地时间。
+///   指定后所有时间换算为 UTC 存储
+/// - `dedupe`: 可选的排序去重策略。指定后先按时间稳定排序，同一时间的多根 K 线只保留一根：
+///   `"first"` 保留先出现的一根，与库中已有数据冲突时保留库中的数据；
+///   `"last"` 保留后出现的一根，与库中已有数据冲突时覆盖库中的数据
 ///
 /// # 返回值
 ///
-/// 成功返回 `Ok(())`，失败返回错误
+/// 不校验、不去重时返回 `None`；只校验时返回校验报告字典：`mode`、`checked`（检查行数）、`invalid`（问题行数）、
+/// `rejected`（丢弃行数）、`counts`（各原因的行数）和 `issues`（每个问题行的 `index`、`datetime`、`reasons`）
+///
+/// ```python
+/// report = save_klines("data/backtest.db", "AAPL", "1m", bars, False, validate="reject")
+/// if report["invalid"]:
+///     print(report["counts"])  # {"high_below_open_close": 3, "non_monotonic_datetime": 1}
+/// ```
+///
+/// 指定 `dedupe` 时返回写入报告字典：`policy`、`rows`（传入行数）、`inserted`（新增行数）、
+/// `updated`（覆盖库中已有数据的行数）、`skipped`（没有写入的行数：批次内重复的行，以及 `"first"` 时与库中冲突的行）、
+/// `duplicates`（批次内重复的行数）、`conflicting`（与库中已有数据时间冲突的行数）；
+/// 同时校验时校验报告放在 `validation` 键下
+///
+/// ```python
+/// report = save_klines("data/backtest.db", "AAPL", "1m", bars, False, dedupe="last")
+/// # {"policy": "last", "rows": 1000, "inserted": 950, "updated": 40, "skipped": 10,
+/// #  "duplicates": 10, "conflicting": 40}
+/// ```
 ///
 /// # 性能说明
 ///
@@ -942,26 +1903,34 @@ pub fn load_and_synthesize_klines(
 /// # 注意事项
 ///
 /// - 如果 `replace=True`，会先删除该 symbol 的所有旧数据
-/// - 重复数据会自动去重（基于 symbol + datetime 唯一索引）
+/// - 重复数据会自动去重（基于 symbol + datetime 唯一索引）；不指定 `dedupe` 时保留库中已有的数据，
+///   批次内的重复行保留哪一根不确定，也不报告数量
+/// - 指定 `dedupe` 时先排序去重再校验，乱序的输入不会被报告为时间不递增，校验报告中的 `index` 是排序去重后的行号
+/// - 带 UTC 偏移的时间（如 `"2020-01-01T09:30:00+08:00"`）无论是否指定 `tz` 都按偏移换算为 UTC 存储；
+///   指定 `tz` 时，夏令时跳过的本地时间返回 `ValueError`，重叠的本地时间取较早的一个
 /// - 大数据量时会显示进度信息
 /// - 数据库文件不存在时会自动创建
 /// - 表不存在时会自动创建
 #[pyfunction]
+#[pyo3(signature = (db_path, symbol, period, bars, replace, validate=None, tz=None, dedupe=None))]
+#[allow(clippy::too_many_arguments)]
 pub fn save_klines(
+    py: Python,
     db_path: String,
     symbol: String,
     period: String,
     bars: &PyList,
     replace: bool,
-) -> PyResult<()> {
+    validate: Option<String>,
+    tz: Option<String>,
+    dedupe: Option<String>,
+) -> PyResult<PyObject> {
+    let validate = ValidationMode::parse(validate.as_deref())?;
+    let tz = parse_timezone(tz.as_deref())?;
+    let dedupe = DedupePolicy::parse(dedupe.as_deref())?;
 
     // Connect to database
-    let conn = Connection::open(Path::new(&db_path)).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
-            "Failed to connect to database: {}",
-            e
-        ))
-    })?;
+    let conn = open_connection(&db_path)?;
 
     let table_name = ensure_period_table(&conn, &period)?;
 
@@ -983,9 +1952,10 @@ pub fn save_klines(
     let mut kline_bars = Vec::with_capacity(bars.len());
     for item in bars.iter() {
         let bar_dict: &PyDict = item.downcast()?;
+        // 整数时间视为 epoch 毫秒（UTC）
         let datetime: String = bar_dict
             .get_item("datetime")?
-            .and_then(|v| v.extract().ok())
+            .and_then(|v| crate::extract_datetime(v).0)
             .unwrap_or_else(|| "".to_string());
         let open: f64 = bar_dict
             .get_item("open")?
@@ -1019,6 +1989,36 @@ pub fn save_klines(
         });
     }
 
+    // 统一存储为 UTC：带偏移的时间按偏移换算，不带偏移的时间按 `tz` 换算；无法解析的时间留给校验/写入报错
+    for bar in kline_bars.iter_mut() {
+        let has_offset = matches!(parse_datetime_with_offset(&bar.datetime), Some((_, Some(_))));
+        if tz.is_none() && !has_offset {
+            continue;
+        }
+        if let Some(dt) = to_utc(&bar.datetime, tz).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)? {
+            bar.datetime = dt.format("%Y-%m-%d %H:%M:%S%.f").to_string();
+        }
+    }
+
+    // 可选的排序去重：在校验之前完成，乱序输入不会被报告为时间不递增
+    let rows_read = kline_bars.len();
+    let duplicates = dedupe.map_or(0, |policy| sort_and_dedupe(&mut kline_bars, policy));
+
+    // 可选的 OHLC 校验：在写入任何数据之前完成，reject 模式下丢弃问题行
+    let validation = validate.map(|mode| {
+        let issues = validate_klines(kline_bars.iter().map(|b| (b.datetime.as_str(), b.open, b.high, b.low, b.close)));
+        (mode, kline_bars.len(), issues)
+    });
+    if let Some((ValidationMode::Reject, _, issues)) = &validation {
+        let mut rejected = issues.iter().map(|issue| issue.index).peekable();
+        let mut index = 0;
+        kline_bars.retain(|_| {
+            let drop = rejected.next_if_eq(&index).is_some();
+            index += 1;
+            !drop
+        });
+    }
+
     // 开始事务：确保数据一致性，同时提升批量插入性能
     conn.execute("BEGIN TRANSACTION", []).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
@@ -1054,72 +2054,69 @@ pub fn save_klines(
         ))
     })?;
 
-    // SQL 字符串转义辅助函数：将单引号转义为两个单引号，防止 SQL 注入
-    fn escape_sql_string(s: &str) -> String {
-        s.replace("'", "''")
-    }
-
-    // 批量插入到临时表：每批 50k 条记录
+    // 通过 Appender 批量写入临时表：数据直接按列追加，不拼接 SQL 字符串，
+    // 既省去了 SQL 解析开销，也不需要为引号等特殊字符转义
     // 临时表插入不需要检查冲突，速度极快
-    const BATCH_SIZE: usize = 50000;
+    const PROGRESS_EVERY: usize = 50000;
     let total = kline_bars.len();
-    
-    for batch_start in (0..total).step_by(BATCH_SIZE) {
-        let batch_end = std::cmp::min(batch_start + BATCH_SIZE, total);
-        let batch = &kline_bars[batch_start..batch_end];
-        
-        // 预分配容量，减少内存重分配
-        let mut values_parts = Vec::with_capacity(batch.len());
-        for bar in batch.iter() {
-            // 转义字符串并格式化 SQL 值
-            let symbol_escaped = escape_sql_string(&bar.symbol);
-            let datetime_escaped = escape_sql_string(&bar.datetime);
-            // 构造 VALUES 子句的一部分：(symbol, datetime, open, high, low, close, volume)
-            values_parts.push(format!(
-                "('{}', '{}', {}, {}, {}, {}, {})",
-                symbol_escaped,
-                datetime_escaped,
-                bar.open,
-                bar.high,
-                bar.low,
-                bar.close,
-                bar.volume
-            ));
-        }
-        // 将所有 VALUES 部分用逗号连接
-        let values_clause = values_parts.join(", ");
-        
-        // 批量插入到临时表（不需要检查冲突，速度极快）
-        let insert_query = format!(
-            "INSERT INTO {} (symbol, datetime, open, high, low, close, volume) 
-             VALUES {}",
-            temp_table, values_clause
-        );
-        
-        conn.execute(&insert_query, []).map_err(|e| {
+    {
+        let mut appender = conn.appender(&temp_table).map_err(|e| {
             PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
-                "Failed to insert batch into temp table at index {}: {}",
-                batch_start, e
+                "Failed to create appender: {}",
+                e
             ))
         })?;
-        
-        // 每 50k 条记录或结束时显示进度
-        if batch_end % 50000 == 0 || batch_end == total {
-            println!("  Progress: {}/{} records prepared ({:.1}%)", 
-                batch_end, total, (batch_end as f64 / total as f64) * 100.0);
+        for (index, bar) in kline_bars.iter().enumerate() {
+            appender
+                .append_row(duckdb::params![
+                    bar.symbol,
+                    bar.datetime,
+                    bar.open,
+                    bar.high,
+                    bar.low,
+                    bar.close,
+                    bar.volume
+                ])
+                .map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                        "Failed to append row into temp table at index {}: {}",
+                        index, e
+                    ))
+                })?;
+
+            // 每 50k 条记录或结束时显示进度
+            let done = index + 1;
+            if done % PROGRESS_EVERY == 0 || done == total {
+                logger::log(Level::Info, format_args!("Progress: {}/{} records prepared ({:.1}%)",
+                    done, total, (done as f64 / total as f64) * 100.0));
+            }
         }
+        appender.flush().map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "Failed to flush appender: {}",
+                e
+            ))
+        })?;
     }
 
+    // 指定去重策略时，插入前统计与库中已有数据冲突的行数
+    let stored = match dedupe {
+        Some(_) => Some(count_stored_conflicts(&conn, &table_name, &temp_table)?),
+        None => None,
+    };
+
     // 从临时表一次性插入到正式表（带冲突检查和去重）
     // 这种方式比逐条插入快得多，因为只需要一次冲突检查操作
-    println!("  Inserting data into target table...");
+    logger::log(Level::Info, format_args!("Inserting data into target table..."));
     conn.execute(
         &format!(
             "INSERT INTO {} (symbol, datetime, open, high, low, close, volume)
              SELECT symbol, datetime, open, high, low, close, volume
              FROM {}
-             ON CONFLICT (symbol, datetime) DO NOTHING",
-            table_name, temp_table
+             {}",
+            table_name,
+            temp_table,
+            DedupePolicy::conflict_clause(dedupe)
         ),
         []
     ).map_err(|e| {
@@ -1145,170 +2142,1410 @@ pub fn save_klines(
         ))
     })?;
 
-    Ok(())
+    // 刷新以该周期为源周期的物化周期（从新数据中最早的时间开始；替换时整体重建）
+    crate::materialize::refresh_dependents(&conn, &symbol, &period, || {
+        Ok(match kline_bars.iter().filter_map(|b| parse_datetime(&b.datetime)).min() {
+            Some(dt) if !replace => crate::materialize::RefreshFrom::Since(dt),
+            _ => crate::materialize::RefreshFrom::Full,
+        })
+    })?;
+
+    let validation = match validation {
+        Some((mode, checked, issues)) => Some(validation_report(py, mode, checked, &issues)?),
+        None => None,
+    };
+    match (dedupe, stored) {
+        (Some(policy), Some((written, conflicting))) => dedupe_report(
+            py,
+            DedupeCounts { policy, rows: rows_read, duplicates, written, conflicting },
+            validation,
+        ),
+        _ => Ok(validation.unwrap_or_else(|| py.None())),
+    }
 }
 
-/// 直接从 CSV 文件保存 K 线数据到 DuckDB（超高速）
-///
-/// 这是最快的数据导入方式，因为 DuckDB 直接读取 CSV 文件，完全绕过了 Python 解析。
-/// 就像"直接从供应商仓库提货"，不需要经过中间环节，速度最快。
+/// 增量追加 K 线
 ///
-/// ## 为什么需要这个函数？
-///
-/// 传统方式需要：CSV → Python 解析 → Python 对象 → Rust 转换 → DuckDB
-/// 这个函数使用：CSV → DuckDB 直接读取 → DuckDB，最快
+/// 日常更新数据时，新下载的 K 线往往与库中已有的数据有重叠（例如每天重新拉取最近一周）。
+/// `save_klines(replace=False)` 需要把全部数据写入临时表再与正式表逐条比对去重；
+/// 这个函数先查出该标的已保存的最新时间，只写入更新的 K 线，不需要 `replace`，也不需要全量去重。
 ///
 /// ## 工作原理（简单理解）
 ///
-/// 想象你要把 CSV 文件的数据导入数据库：
-///
-/// 1. **DuckDB 直接读取 CSV**：使用 `read_csv()` 函数，DuckDB 原生支持
-/// 2. **创建临时表**：将 CSV 数据加载到临时表
-/// 3. **添加 symbol 字段**：为每条记录添加交易标的代码
-/// 4. **类型转换**：确保数据类型正确（datetime → TIMESTAMP, 价格 → DOUBLE）
-/// 5. **一次性插入**：从临时表插入到正式表（自动去重）
-/// 6. **清理和提交**：删除临时表，提交事务
+/// 1. 查询该标的在周期表中的最新时间（`MAX(datetime)`）
+/// 2. 只保留时间晚于最新时间的 K 线，按时间排序，同一时间只保留第一根
+/// 3. 在事务中通过 Appender 直接追加到正式表
 ///
 /// ## 实际使用场景
 ///
 /// ```python
-/// from engine_rust import save_klines_from_csv
-///
-/// # 直接从 CSV 文件导入（最快）
-/// save_klines_from_csv(
-///     db_path="data/backtest.db",
-///     csv_path="data/AAPL_1m.csv",
-///     symbol="AAPL",
-///     period="1m",
-///     replace=False
-/// )
-/// ```
-///
-/// ## CSV 格式要求
+/// from engine_rust import append_klines
 ///
-/// CSV 文件必须包含表头，格式如下：
-/// ```
-/// datetime,open,high,low,close,volume
-/// 2020-01-01 09:30:00,100.0,101.0,99.0,100.5,1000000
-/// 2020-01-01 09:31:00,100.5,102.0,100.0,101.5,1200000
-/// ...
+/// # 每天收盘后拉取最近 5 天的数据，重叠部分自动跳过
+/// added = append_klines("data/backtest.db", "AAPL", "1d", download("AAPL", days=5))
+/// print(f"appended {added} bars")
 /// ```
 ///
-/// ## 性能优势
-///
-/// 这是最快的数据导入方式：
-/// - **绕过 Python 解析**：DuckDB 直接读取 CSV，不需要 Python 的 csv 模块
-/// - **原生性能**：DuckDB 的 CSV 读取器是 C++ 实现，速度极快
-/// - **批量处理**：一次性处理整个文件，不需要逐行读取
-///
 /// # 参数
 ///
 /// - `db_path`: 数据库文件路径
-/// - `csv_path`: CSV 文件路径
-/// - `symbol`: 交易标的代码（会添加到每条记录）
+/// - `symbol`: 交易标的代码
 /// - `period`: 周期字符串（如 "1m", "1d"）
-/// - `replace`: 是否替换现有数据
+/// - `bars`: Python 列表，每个元素是包含 OHLCV 字段的字典（格式同 `save_klines()`）
+/// - `tz`: 可选的时区，含义同 `save_klines()`
 ///
 /// # 返回值
 ///
-/// 成功返回 `Ok(())`，失败返回错误
-///
-/// # 性能说明
-///
-/// 这是最快的数据导入方式，比 `save_klines()` 还要快 2-5 倍。
-/// 对于 100 万条记录，可能只需要几秒。
+/// 实际追加的 K 线条数
 ///
 /// # 注意事项
 ///
-/// - CSV 文件必须包含表头：`datetime,open,high,low,close,volume`
-/// - CSV 文件路径中的单引号会被自动转义
-/// - 如果 `replace=True`，会先删除该 symbol 的所有旧数据
-/// - 重复数据会自动去重
-/// - 数据库文件不存在时会自动创建
+/// - 不早于最新时间的 K 线一律跳过，即使它们填补的是历史缺口；补历史数据请用 `save_klines()`
+/// - 时间无法解析时返回 `ValueError`，此时不会写入任何数据
+/// - 表不存在时会自动创建
 #[pyfunction]
-pub fn save_klines_from_csv(
-    db_path: String,
-    csv_path: String,
-    symbol: String,
-    period: String,
-    replace: bool,
-) -> PyResult<()> {
+#[pyo3(signature = (db_path, symbol, period, bars, tz=None))]
+pub fn append_klines(db_path: String, symbol: String, period: String, bars: &Bound<'_, PyList>, tz: Option<String>) -> PyResult<usize> {
+    let tz = parse_timezone(tz.as_deref())?;
 
     // Connect to database
-    let conn = Connection::open(Path::new(&db_path)).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
-            "Failed to connect to database: {}",
-            e
-        ))
-    })?;
-
+    let conn = open_connection(&db_path)?;
     let table_name = ensure_period_table(&conn, &period)?;
 
-    // Delete old data if replace is true
-    if replace {
-        conn.execute(
-            &format!("DELETE FROM {} WHERE symbol = ?", table_name),
+    // 已保存的最新时间
+    let latest: Option<String> = conn
+        .query_row(
+            &format!(
+                "SELECT strftime(MAX(datetime), '%Y-%m-%d %H:%M:%S.%f') FROM {} WHERE symbol = ?",
+                table_name
+            ),
             duckdb::params![symbol],
+            |row| row.get(0),
         )
         .map_err(|e| {
-            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
-                "Failed to delete old data: {}",
-                e
-            ))
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to query latest datetime: {}", e))
         })?;
+    let latest = latest.as_deref().and_then(parse_datetime);
+
+    // 只保留更新的 K 线，统一换算为 UTC
+    let mut new_bars = Vec::new();
+    for (index, item) in bars.iter().enumerate() {
+        let mut bar = kline_bar_from_pydict(item.downcast::<PyDict>()?.as_gil_ref())?;
+        let dt = to_utc(&bar.datetime, tz)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?
+            .ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Bar {} has an invalid datetime: '{}'",
+                    index, bar.datetime
+                ))
+            })?;
+        if latest.is_some_and(|l| dt <= l) {
+            continue;
+        }
+        bar.symbol = symbol.clone();
+        bar.datetime = dt.format("%Y-%m-%d %H:%M:%S%.f").to_string();
+        new_bars.push((dt, bar));
+    }
+    // 稳定排序后去重：同一时间保留先出现的一根（与 `ON CONFLICT DO NOTHING` 一致）
+    new_bars.sort_by_key(|(dt, _)| *dt);
+    new_bars.dedup_by_key(|(dt, _)| *dt);
+    if new_bars.is_empty() {
+        return Ok(0);
     }
 
-    // Escape CSV path for SQL (handle single quotes)
-    let csv_path_escaped = csv_path.replace("'", "''");
+    let new_bars: Vec<KlineBar> = new_bars.into_iter().map(|(_, bar)| bar).collect();
 
-    // Use transaction
     conn.execute("BEGIN TRANSACTION", []).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
             "Failed to begin transaction: {}",
             e
         ))
     })?;
+    append_kline_rows(&conn, &table_name, &new_bars)?;
+    conn.execute("COMMIT", []).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to commit transaction: {}",
+            e
+        ))
+    })?;
 
-    // Create temporary table and load CSV directly
-    let temp_table = format!("temp_csv_import_{}", std::process::id());
-    
+    // 刷新以该周期为源周期的物化周期（从第一根新 K 线所在的目标周期开始）
+    let first = new_bars.iter().find_map(|b| parse_datetime(&b.datetime));
+    crate::materialize::refresh_dependents(&conn, &symbol, &period, || {
+        Ok(first.map_or(crate::materialize::RefreshFrom::Full, crate::materialize::RefreshFrom::Since))
+    })?;
+    Ok(new_bars.len())
+}
+
+/// 通过 Appender 把 K 线直接追加到周期表（不检查冲突，调用方需保证不会与已有数据重复）
+pub(crate) fn append_kline_rows(conn: &Connection, table_name: &str, bars: &[KlineBar]) -> PyResult<()> {
+    let mut appender = conn.appender(table_name).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to create appender: {}",
+            e
+        ))
+    })?;
+    for bar in bars {
+        appender
+            .append_row(duckdb::params![
+                bar.symbol,
+                bar.datetime,
+                bar.open,
+                bar.high,
+                bar.low,
+                bar.close,
+                bar.volume
+            ])
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                    "Failed to append bar at {}: {}",
+                    bar.datetime, e
+                ))
+            })?;
+    }
+    appender.flush().map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to flush appender: {}",
+            e
+        ))
+    })
+}
+
+/// 经临时表把 K 线写入周期表，与已有数据重复的 (symbol, datetime) 跳过
+///
+/// 与 `save_klines()` 的写入方式相同（Appender 写临时表，再 `ON CONFLICT DO NOTHING` 插入），
+/// 不开启事务，调用方负责事务；`bars` 内部不能有重复的时间。
+pub(crate) fn insert_kline_rows(conn: &Connection, table_name: &str, bars: &[KlineBar]) -> PyResult<()> {
+    let temp_table = format!("temp_insert_{}", std::process::id());
+    conn.execute(
+        &format!("CREATE TEMP TABLE {} AS SELECT * FROM {} LIMIT 0", temp_table, table_name),
+        [],
+    )
+    .map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to create temporary table: {}",
+            e
+        ))
+    })?;
+    append_kline_rows(conn, &temp_table, bars)?;
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (symbol, datetime, open, high, low, close, volume)
+             SELECT symbol, datetime, open, high, low, close, volume
+             FROM {}
+             ON CONFLICT (symbol, datetime) DO NOTHING",
+            table_name, temp_table
+        ),
+        [],
+    )
+    .map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to insert from temp table to target table: {}",
+            e
+        ))
+    })?;
+    conn.execute(&format!("DROP TABLE {}", temp_table), []).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to drop temporary table: {}",
+            e
+        ))
+    })?;
+    Ok(())
+}
+
+/// 直接从 CSV 文件保存 K 线数据到 DuckDB（超高速）
+///
+/// 这是最快的数据导入方式，因为 DuckDB 直接读取 CSV 文件，完全绕过了 Python 解析。
+/// 就像"直接从供应商仓库提货"，不需要经过中间环节，速度最快。
+///
+/// ## 为什么需要这个函数？
+///
+/// 传统方式需要：CSV → Python 解析 → Python 对象 → Rust 转换 → DuckDB
+/// 这个函数使用：CSV → DuckDB 直接读取 → DuckDB，最快
+///
+/// ## 工作原理（简单理解）
+///
+/// 想象你要把 CSV 文件的数据导入数据库：
+///
+/// 1. **DuckDB 直接读取 CSV**：使用 `read_csv()` 函数，DuckDB 原生支持
+/// 2. **创建临时表**：将 CSV 数据加载到临时表
+/// 3. **添加 symbol 字段**：为每条记录添加交易标的代码
+/// 4. **类型转换**：确保数据类型正确（datetime → TIMESTAMP, 价格 → DOUBLE）
+/// 5. **一次性插入**：从临时表插入到正式表（自动去重）
+/// 6. **清理和提交**：删除临时表，提交事务
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import save_klines_from_csv
+///
+/// # 直接从 CSV 文件导入（最快）
+/// save_klines_from_csv(
+///     db_path="data/backtest.db",
+///     csv_path="data/AAPL_1m.csv",
+///     symbol="AAPL",
+///     period="1m",
+///     replace=False
+/// )
+/// ```
+///
+/// ## CSV 格式要求
+///
+/// CSV 文件必须包含表头，格式如下：
+/// ```
+/// datetime,open,high,low,close,volume
+/// 2020-01-01 09:30:00,100.0,101.0,99.0,100.5,1000000
+/// 2020-01-01 09:31:00,100.5,102.0,100.0,101.5,1200000
+/// ...
+/// ```
+///
+/// ## 性能优势
+///
+/// 这是最快的数据导入方式：
+/// - **绕过 Python 解析**：DuckDB 直接读取 CSV，不需要 Python 的 csv 模块
+/// - **原生性能**：DuckDB 的 CSV 读取器是 C++ 实现，速度极快
+/// - **批量处理**：一次性处理整个文件，不需要逐行读取
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `csv_path`: CSV 文件路径
+/// - `symbol`: 交易标的代码（会添加到每条记录）
+/// - `period`: 周期字符串（如 "1m", "1d"）
+/// - `replace`: 是否替换现有数据
+/// - `validate`: 可选的 OHLC 校验方式（`"flag"` 或 `"reject"`，同 `save_klines()`）；
+///   行号按文件中的数据行计算（不含表头）
+/// - `tz`: 可选的时区，不带偏移的时间按该时区的本地时间换算为 UTC 存储（同 `save_klines()`）
+/// - `dedupe`: 可选的去重策略（`"first"` 或 `"last"`，同 `save_klines()`），先后按文件中的行序判断；
+///   指定后校验按时间顺序进行，行号按排序去重后的顺序计算
+///
+/// # 返回值
+///
+/// 不校验、不去重时返回 `None`；校验时返回校验报告字典，去重时返回写入报告字典（格式同 `save_klines()`）
+///
+/// # 性能说明
+///
+/// 这是最快的数据导入方式，比 `save_klines()` 还要快 2-5 倍。
+/// 对于 100 万条记录，可能只需要几秒。
+///
+/// # 注意事项
+///
+/// - CSV 文件必须包含表头：`datetime,open,high,low,close,volume`
+/// - CSV 文件路径中的单引号会被自动转义
+/// - 如果 `replace=True`，会先删除该 symbol 的所有旧数据
+/// - 重复数据会自动去重
+/// - 数据库文件不存在时会自动创建
+#[pyfunction]
+#[pyo3(signature = (db_path, csv_path, symbol, period, replace, validate=None, tz=None, dedupe=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn save_klines_from_csv(
+    py: Python,
+    db_path: String,
+    csv_path: String,
+    symbol: String,
+    period: String,
+    replace: bool,
+    validate: Option<String>,
+    tz: Option<String>,
+    dedupe: Option<String>,
+) -> PyResult<PyObject> {
+    // Escape CSV path for SQL (handle single quotes)
+    let csv_path_escaped = csv_path.replace("'", "''");
     // DuckDB can read CSV directly and infer schema
     // Expected CSV format: datetime,open,high,low,close,volume
+    let source = format!("read_csv('{}', header=true, auto_detect=true)", csv_path_escaped);
+    import_klines_from_source(py, &db_path, &source, "CSV", &symbol, &period, replace, validate.as_deref(), tz.as_deref(), dedupe.as_deref())
+}
+
+/// 批量导入一个目录下的全部 CSV 文件
+///
+/// 下载的历史数据通常是"一个标的一个文件"（如 `data/1m/AAPL.csv`、`data/1m/MSFT.csv`），
+/// 逐个调用 `save_klines_from_csv()` 既要在 Python 里遍历文件，又会为每个文件单独开事务。
+/// 这个函数用 DuckDB 的 `read_csv()` 一次读取所有匹配的文件，在一个事务中写入，
+/// 标的代码从文件名或文件中的某一列推断，最后返回每个标的的导入统计。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import save_klines_from_csv_dir
+///
+/// # 文件名即标的代码：data/1m/AAPL.csv -> "AAPL"
+/// report = save_klines_from_csv_dir("data/backtest.db", "data/1m/*.csv", "1m")
+///
+/// # 文件中有 symbol 列（一个文件可以包含多个标的）
+/// report = save_klines_from_csv_dir("data/backtest.db", "exports/**/*.csv", "1d", symbol_from="ticker")
+/// print(report["symbols"]["AAPL"])  # {"rows": 2516, "first": "2014-01-02 00:00:00", "last": "2023-12-29 00:00:00"}
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `dir_glob`: 文件通配符（如 `"data/1m/*.csv"`，支持 `**` 递归匹配）
+/// - `period`: 周期字符串（如 "1m", "1d"）
+/// - `symbol_from`: 标的代码来源。`"filename"`（默认）取文件名去掉扩展名；其他值视为 CSV 中的列名
+/// - `replace`: 是否先删除本次导入涉及的标的的旧数据，默认 `False`
+/// - `tz`: 可选的时区（同 `save_klines_from_csv()`）
+///
+/// # 返回值
+///
+/// 导入报告字典：`files`（文件数）、`rows`（读取的行数）、`inserted`（去重后实际写入的行数）、
+/// `symbols`（每个标的的 `rows`、`first`、`last`）
+///
+/// # 注意事项
+///
+/// - 所有文件必须包含表头 `datetime,open,high,low,close,volume`，列顺序可以不同，缺失的列读为空值并导致导入失败
+/// - 任何一个文件读取失败时整体回滚，不会只导入一部分
+/// - 没有匹配的文件时返回 `RuntimeError`
+#[pyfunction]
+#[pyo3(signature = (db_path, dir_glob, period, symbol_from="filename".to_string(), replace=false, tz=None))]
+pub fn save_klines_from_csv_dir(
+    py: Python,
+    db_path: String,
+    dir_glob: String,
+    period: String,
+    symbol_from: String,
+    replace: bool,
+    tz: Option<String>,
+) -> PyResult<PyObject> {
+    let tz = parse_timezone(tz.as_deref())?;
+    let source = format!(
+        "read_csv('{}', header=true, auto_detect=true, union_by_name=true, filename=true)",
+        dir_glob.replace("'", "''")
+    );
+    let symbol_expr = if symbol_from == "filename" {
+        "parse_filename(filename, true)".to_string()
+    } else {
+        format!("CAST(\"{}\" AS VARCHAR)", symbol_from.replace('"', "\"\""))
+    };
+
+    // Connect to database
+    let conn = open_connection(&db_path)?;
+
+    let table_name = ensure_period_table(&conn, &period)?;
+
+    conn.execute("BEGIN TRANSACTION", []).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to begin transaction: {}",
+            e
+        ))
+    })?;
+
+    let temp_table = format!("temp_csv_dir_import_{}", std::process::id());
+    logger::log(Level::Info, format_args!("Reading CSV files matching {} with DuckDB...", dir_glob));
+    conn.execute(
+        &format!(
+            "CREATE TEMP TABLE {} AS
+             SELECT
+                 {} as symbol,
+                 {} as datetime,
+                 CAST(open AS DOUBLE) as open,
+                 CAST(high AS DOUBLE) as high,
+                 CAST(low AS DOUBLE) as low,
+                 CAST(close AS DOUBLE) as close,
+                 CAST(volume AS DOUBLE) as volume,
+                 filename{}
+             FROM {}",
+            temp_table,
+            symbol_expr,
+            datetime_column_expr(&conn, &source),
+            if tz.is_some() { ",\n                 CAST(datetime AS VARCHAR) as datetime_raw" } else { "" },
+            source
+        ),
+        [],
+    )
+    .map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to read CSV files {}: {}. Make sure they have columns: datetime,open,high,low,close,volume",
+            dir_glob, e
+        ))
+    })?;
+
+    if tz.is_some() {
+        normalize_import_timezone(&conn, &temp_table, tz)?;
+    }
+
+    // 每个标的的行数与起止时间
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT symbol, COUNT(*),
+                    strftime(MIN(datetime), '%Y-%m-%d %H:%M:%S.%f'),
+                    strftime(MAX(datetime), '%Y-%m-%d %H:%M:%S.%f')
+             FROM {} GROUP BY symbol ORDER BY symbol",
+            temp_table
+        ))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to prepare query: {}", e))
+        })?;
+    let per_symbol = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?.map(trim_fractional_seconds),
+                row.get::<_, Option<String>>(3)?.map(trim_fractional_seconds),
+            ))
+        })
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to execute query: {}", e))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to read row: {}", e))
+        })?;
+    if per_symbol.iter().any(|(symbol, ..)| symbol.is_none()) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Some rows have no symbol in column '{}'",
+            symbol_from
+        )));
+    }
+    let files: i64 = conn
+        .query_row(&format!("SELECT COUNT(DISTINCT filename) FROM {}", temp_table), [], |row| row.get(0))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to execute query: {}", e))
+        })?;
+
+    // Delete old data of the imported symbols if replace is true
+    if replace {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE symbol IN (SELECT DISTINCT symbol FROM {})", table_name, temp_table),
+            [],
+        )
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "Failed to delete old data: {}",
+                e
+            ))
+        })?;
+    }
+
+    logger::log(Level::Info, format_args!("Inserting data into target table..."));
+    let inserted = conn
+        .execute(
+            &format!(
+                "INSERT INTO {} (symbol, datetime, open, high, low, close, volume)
+                 SELECT symbol, datetime, open, high, low, close, volume
+                 FROM {}
+                 ON CONFLICT (symbol, datetime) DO NOTHING",
+                table_name, temp_table
+            ),
+            [],
+        )
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "Failed to insert from temp table: {}",
+                e
+            ))
+        })?;
+
+    conn.execute("COMMIT", []).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to commit transaction: {}",
+            e
+        ))
+    })?;
+
+    let report = PyDict::new_bound(py);
+    let symbols = PyDict::new_bound(py);
+    let mut rows = 0;
+    for (symbol, count, first, last) in per_symbol {
+        let symbol = symbol.unwrap_or_default();
+        // 刷新以该周期为源周期的物化周期（替换时整体重建）
+        crate::materialize::refresh_dependents(&conn, &symbol, &period, || {
+            Ok(match first.as_deref().and_then(parse_datetime) {
+                Some(dt) if !replace => crate::materialize::RefreshFrom::Since(dt),
+                _ => crate::materialize::RefreshFrom::Full,
+            })
+        })?;
+        let info = PyDict::new_bound(py);
+        info.set_item("rows", count)?;
+        info.set_item("first", first)?;
+        info.set_item("last", last)?;
+        symbols.set_item(symbol, info)?;
+        rows += count;
+    }
+
+    // Drop temporary table
+    conn.execute(&format!("DROP TABLE {}", temp_table), []).ok();
+
+    report.set_item("files", files)?;
+    report.set_item("rows", rows)?;
+    report.set_item("inserted", inserted)?;
+    report.set_item("symbols", symbols)?;
+    Ok(report.into())
+}
+
+/// 直接从 Parquet 文件保存 K 线数据到 DuckDB（超高速）
+///
+/// 与 `save_klines_from_csv()` 相同的导入流程，只是数据源换成 DuckDB 原生的 `read_parquet()`。
+/// 大量历史数据通常以 Parquet 格式保存（体积小、自带列类型），这个函数让它们无需先转成 CSV
+/// 或经过 Python（pandas/pyarrow）就能直接导入数据库。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import save_klines_from_parquet
+///
+/// save_klines_from_parquet(
+///     db_path="data/backtest.db",
+///     parquet_path="data/AAPL_1m.parquet",
+///     symbol="AAPL",
+///     period="1m",
+///     replace=False
+/// )
+///
+/// # 按日期分片的目录也可以用通配符一次导入
+/// save_klines_from_parquet("data/backtest.db", "data/AAPL_1m/*.parquet", "AAPL", "1m", False)
+/// ```
+///
+/// ## Parquet 格式要求
+///
+/// 文件必须包含 `datetime`, `open`, `high`, `low`, `close`, `volume` 列（列名区分大小写，
+/// 多余的列会被忽略）。`datetime` 可以是时间戳类型，也可以是能转换为时间戳的字符串。
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `parquet_path`: Parquet 文件路径，支持 DuckDB 的通配符（如 `"data/*.parquet"`）
+/// - `symbol`: 交易标的代码（会添加到每条记录）
+/// - `period`: 周期字符串（如 "1m", "1d"）
+/// - `replace`: 是否替换现有数据
+/// - `validate`: 可选的 OHLC 校验方式（`"flag"` 或 `"reject"`，同 `save_klines()`）；
+///   行号按文件中的数据行计算（不含表头）
+/// - `tz`: 可选的时区，不带偏移的时间按该时区的本地时间换算为 UTC 存储（同 `save_klines()`）
+///
+/// # 返回值
+///
+/// 不校验时返回 `None`；校验时返回校验报告字典（格式同 `save_klines()`）
+///
+/// # 注意事项
+///
+/// - Parquet 文件路径中的单引号会被自动转义
+/// - 如果 `replace=True`，会先删除该 symbol 的所有旧数据
+/// - 重复数据会自动去重
+/// - 数据库文件不存在时会自动创建
+#[pyfunction]
+#[pyo3(signature = (db_path, parquet_path, symbol, period, replace, validate=None, tz=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn save_klines_from_parquet(
+    py: Python,
+    db_path: String,
+    parquet_path: String,
+    symbol: String,
+    period: String,
+    replace: bool,
+    validate: Option<String>,
+    tz: Option<String>,
+) -> PyResult<PyObject> {
+    let source = format!("read_parquet('{}')", parquet_path.replace("'", "''"));
+    import_klines_from_source(py, &db_path, &source, "Parquet", &symbol, &period, replace, validate.as_deref(), tz.as_deref(), None)
+}
+
+/// 把数据库中的 K 线导出为 Parquet 文件
+///
+/// 使用 DuckDB 的 `COPY ... TO ... (FORMAT PARQUET)` 在数据库内部直接写文件，数据不经过 Python。
+/// 整理好的数据可以直接交给 pandas/polars、Spark、R 等不依赖本项目的工具使用。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import export_klines_to_parquet
+///
+/// rows = export_klines_to_parquet(
+///     db_path="data/backtest.db",
+///     symbol="AAPL",
+///     period="1m",
+///     out_path="share/AAPL_1m_2020.parquet",
+///     start="2020-01-01",
+///     end="2020-12-31 23:59:59"
+/// )
+/// print(f"exported {rows} bars")
+/// ```
+///
+/// ## 输出格式
+///
+/// 列为 `symbol`, `datetime`（TIMESTAMP）, `open`, `high`, `low`, `close`, `volume`，按时间升序排列，
+/// 可以直接用 `save_klines_from_parquet()` 重新导入。
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `period`: 周期字符串（如 "1m", "1d"）
+/// - `out_path`: 输出的 Parquet 文件路径（已存在时覆盖）
+/// - `start`: 开始时间（可选，包含）
+/// - `end`: 结束时间（可选，包含）
+///
+/// # 返回值
+///
+/// 导出的 K 线条数
+///
+/// # 注意事项
+///
+/// - 输出目录必须已存在
+/// - 路径、标的代码和时间中的单引号会被自动转义
+/// - 没有匹配的数据时仍会写出只有表头（schema）的空文件
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, period, out_path, start=None, end=None))]
+pub fn export_klines_to_parquet(
+    db_path: String,
+    symbol: String,
+    period: String,
+    out_path: String,
+    start: Option<String>,
+    end: Option<String>,
+) -> PyResult<usize> {
+    export_klines(&db_path, &symbol, &period, &out_path, start.as_deref(), end.as_deref(), "FORMAT PARQUET", "Parquet")
+}
+
+/// 把 K 线导出为 CSV 文件
+///
+/// 与 `export_klines_to_parquet()` 相同，由 DuckDB 的 `COPY ... TO ... (FORMAT CSV, HEADER)` 直接写文件，
+/// 数据切片可以直接发给同事用 Excel、R 或任何脚本打开，不需要先经过 pandas。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import export_market_data_csv
+///
+/// rows = export_market_data_csv(
+///     db_path="data/backtest.db",
+///     symbol="AAPL",
+///     period="1d",
+///     out_path="share/AAPL_1d_2023.csv",
+///     start="2023-01-01",
+///     end="2023-12-31 23:59:59"
+/// )
+/// ```
+///
+/// ## 输出格式
+///
+/// 带表头的 CSV，列为 `symbol,datetime,open,high,low,close,volume`，按时间升序排列，
+/// 时间格式为 `YYYY-MM-DD HH:MM:SS`（有小数秒时保留），可以直接用 `save_klines_from_csv()` 重新导入。
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `period`: 周期字符串（如 "1m", "1d"）
+/// - `out_path`: 输出的 CSV 文件路径（已存在时覆盖）
+/// - `start`: 开始时间（可选，包含）
+/// - `end`: 结束时间（可选，包含）
+///
+/// # 返回值
+///
+/// 导出的 K 线条数
+///
+/// # 注意事项
+///
+/// - 输出目录必须已存在
+/// - 没有匹配的数据时仍会写出只有表头的文件
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, period, out_path, start=None, end=None))]
+pub fn export_market_data_csv(
+    db_path: String,
+    symbol: String,
+    period: String,
+    out_path: String,
+    start: Option<String>,
+    end: Option<String>,
+) -> PyResult<usize> {
+    export_klines(&db_path, &symbol, &period, &out_path, start.as_deref(), end.as_deref(), "FORMAT CSV, HEADER", "CSV")
+}
+
+/// 用 DuckDB `COPY ... TO` 导出一个标的的 K 线，`options` 为 COPY 的格式选项
+#[allow(clippy::too_many_arguments)]
+fn export_klines(
+    db_path: &str,
+    symbol: &str,
+    period: &str,
+    out_path: &str,
+    start: Option<&str>,
+    end: Option<&str>,
+    options: &str,
+    format_name: &str,
+) -> PyResult<usize> {
+    // Connect to database
+    let conn = open_connection(db_path)?;
+
+    let table_name = ensure_period_table(&conn, period)?;
+
+    // COPY does not accept prepared parameters, so literals are escaped inline
+    let escape = |v: &str| v.replace("'", "''");
+    let mut where_parts = vec![format!("symbol = '{}'", escape(symbol))];
+    if let Some(s) = start {
+        where_parts.push(format!("datetime >= CAST('{}' AS TIMESTAMP)", escape(s)));
+    }
+    if let Some(e) = end {
+        where_parts.push(format!("datetime <= CAST('{}' AS TIMESTAMP)", escape(e)));
+    }
+
+    let copy_sql = format!(
+        "COPY (
+             SELECT symbol, datetime, open, high, low, close, volume
+             FROM {}
+             WHERE {}
+             ORDER BY datetime
+         ) TO '{}' ({})",
+        table_name,
+        where_parts.join(" AND "),
+        escape(out_path),
+        options
+    );
+
+    conn.execute(&copy_sql, []).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to export klines to {}: {}",
+            format_name, e
+        ))
+    })
+}
+
+/// 把内存数据库写入磁盘文件
+///
+/// `db_path=":memory:"` 适合单元测试和临时研究，不会留下文件；研究到一半想保留结果时，
+/// 用这个函数把内存库中的全部表（K 线、盘口快照等）复制到一个 DuckDB 文件。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import save_klines, save_memory_db
+///
+/// save_klines(":memory:", "AAPL", "1d", bars, False)
+/// ...
+/// save_memory_db("data/research.db")
+/// ```
+///
+/// # 参数
+///
+/// - `path`: 目标数据库文件路径
+/// - `overwrite`: 目标文件已存在时是否覆盖，默认 `False`
+///
+/// # 注意事项
+///
+/// - 目标文件已存在且 `overwrite=False` 时返回 `ValueError`，避免误把数据合并进已有的库
+/// - 通过 DuckDB 的 `ATTACH` + `COPY FROM DATABASE` 完成，复制期间内存库保持可用
+#[pyfunction]
+#[pyo3(signature = (path, overwrite=false))]
+pub fn save_memory_db(path: String, overwrite: bool) -> PyResult<()> {
+    let target = Path::new(&path);
+    if target.exists() {
+        if !overwrite {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Database file '{}' already exists; pass overwrite=True to replace it",
+                path
+            )));
+        }
+        std::fs::remove_file(target).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "Failed to remove existing database file: {}",
+                e
+            ))
+        })?;
+        // 旧库残留的 WAL 文件会在打开时被重放，需要一并删除
+        let _ = std::fs::remove_file(format!("{}.wal", path));
+    }
+
+    let conn = open_connection(MEMORY_DB_PATH)?;
+    conn.execute_batch(&format!(
+        "ATTACH '{}' AS spill_target;
+         COPY FROM DATABASE memory TO spill_target;
+         DETACH spill_target;",
+        path.replace('\'', "''")
+    ))
+    .map_err(|e| {
+        let _ = conn.execute_batch("DETACH DATABASE IF EXISTS spill_target");
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to save in-memory database to {}: {}",
+            path, e
+        ))
+    })
+}
+
+/// 用磁盘上的数据库文件初始化内存数据库
+///
+/// 清空当前内存库，再把文件中的全部表复制进来。之后以 `db_path=":memory:"` 调用的函数都在副本上操作，
+/// 不会修改原文件；需要保存时调用 `save_memory_db()`。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import load_memory_db, get_market_data
+///
+/// load_memory_db("data/backtest.db")
+/// bars = get_market_data(":memory:", "AAPL", "1d")
+/// ```
+///
+/// # 参数
+///
+/// - `path`: 源数据库文件路径（只读打开）
+///
+/// # 注意事项
+///
+/// - 整个文件被复制进内存，请注意数据量
+/// - 文件不存在时返回 `ValueError`
+#[pyfunction]
+pub fn load_memory_db(path: String) -> PyResult<()> {
+    if !Path::new(&path).exists() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Database file '{}' does not exist",
+            path
+        )));
+    }
+    clear_memory_db();
+    let conn = open_connection(MEMORY_DB_PATH)?;
+    conn.execute_batch(&format!(
+        "ATTACH '{}' AS attach_source (READ_ONLY);
+         COPY FROM DATABASE attach_source TO memory;
+         DETACH attach_source;",
+        path.replace('\'', "''")
+    ))
+    .map_err(|e| {
+        let _ = conn.execute_batch("DETACH DATABASE IF EXISTS attach_source");
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to load {} into the in-memory database: {}",
+            path, e
+        ))
+    })
+}
+
+/// 清空进程内的内存数据库
+///
+/// 丢弃 `db_path=":memory:"` 下的全部数据，下次使用时从空库开始。单元测试可以在每个用例前调用，保证互不影响。
+#[pyfunction]
+pub fn clear_memory_db() {
+    *MEMORY_DB.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// 列出数据库中已有数据的周期
+///
+/// 扫描 `klines_*` 表，返回其中至少有一条记录的周期（即表名去掉 `klines_` 前缀，如 `"1m"`、`"1d"`），
+/// 按周期长短排序。工具和界面可以据此发现有哪些数据，而不需要手写 SQL。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import list_periods, list_symbols
+///
+/// for period in list_periods("data/backtest.db"):
+///     print(period, list_symbols("data/backtest.db", period))
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+///
+/// # 返回值
+///
+/// 周期字符串列表（小写，与表名一致）
+///
+/// # 注意事项
+///
+/// - 查询函数会按需创建空表，空表不会出现在结果中
+/// - 周期名来自表名，写入时的大写字母已被转换为小写（如 `"1M"` 存为 `klines_1m`）
+#[pyfunction]
+pub fn list_periods(db_path: String) -> PyResult<Vec<String>> {
+    // Connect to database
+    let conn = open_connection(&db_path)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT table_name FROM information_schema.tables
+             WHERE table_schema = 'main' AND table_name LIKE 'klines\\_%' ESCAPE '\\'",
+        )
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to prepare query: {}", e))
+        })?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to list tables: {}", e))
+        })?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to read row: {}", e))
+        })?;
+
+    let mut periods = Vec::with_capacity(tables.len());
+    for table in tables {
+        let has_rows: bool = conn
+            .query_row(&format!("SELECT EXISTS (SELECT 1 FROM \"{}\")", table), [], |row| row.get(0))
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                    "Failed to inspect table {}: {}",
+                    table, e
+                ))
+            })?;
+        if has_rows {
+            periods.push(table["klines_".len()..].to_string());
+        }
+    }
+    // 按周期长短排序，无法识别的周期排在最后
+    periods.sort_by_key(|p| (period_to_minutes(p).unwrap_or(i64::MAX), p.clone()));
+    Ok(periods)
+}
+
+/// 列出某个周期下已有数据的交易标的
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `period`: 周期字符串（如 "1m", "1d"）
+///
+/// # 返回值
+///
+/// 按字母顺序排列的标的代码列表；该周期的表不存在时返回空列表（不会创建表）
+#[pyfunction]
+pub fn list_symbols(db_path: String, period: String) -> PyResult<Vec<String>> {
+    // Connect to database
+    let conn = open_connection(&db_path)?;
+
+    let table_name = format!("klines_{}", sanitize_period_identifier(&period)?);
+    let exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM information_schema.tables WHERE table_schema = 'main' AND table_name = ?",
+            duckdb::params![table_name],
+            |row| row.get(0),
+        )
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to list tables: {}", e))
+        })?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn
+        .prepare(&format!("SELECT DISTINCT symbol FROM {} ORDER BY symbol", table_name))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to prepare query: {}", e))
+        })?;
+    let symbols = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to execute query: {}", e))
+        })?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to read row: {}", e))
+        })?;
+    Ok(symbols)
+}
+
+/// 查询某个标的在数据库中的数据覆盖范围
+///
+/// 返回第一根和最后一根 K 线的时间以及总条数，数据管道可以据此决定需要（重新）下载哪一段数据，
+/// 而不必先把全部 K 线查出来。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import get_data_range
+///
+/// info = get_data_range("data/backtest.db", "AAPL", "1d")
+/// # {"symbol": "AAPL", "period": "1d", "first": "2020-01-02 00:00:00", "last": "2024-06-28 00:00:00", "count": 1131}
+/// if info["count"] == 0 or info["last"] < "2024-12-31":
+///     download("AAPL", start=info["last"])
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `period`: 周期字符串（如 "1m", "1d"）
+///
+/// # 返回值
+///
+/// 字典：`symbol`、`period`、`first`、`last`（时间字符串，格式同 `get_market_data()`）和 `count`。
+/// 没有数据时 `count` 为 0，`first`/`last` 为 `None`
+///
+/// # 注意事项
+///
+/// - 只统计起止时间和条数，不检查区间内部是否有缺口
+/// - 该周期的表不存在时同样返回 `count=0`（不会创建表）
+#[pyfunction]
+pub fn get_data_range(py: Python, db_path: String, symbol: String, period: String) -> PyResult<PyObject> {
+    // Connect to database
+    let conn = open_connection(&db_path)?;
+
+    let table_name = format!("klines_{}", sanitize_period_identifier(&period)?);
+    let exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM information_schema.tables WHERE table_schema = 'main' AND table_name = ?",
+            duckdb::params![table_name],
+            |row| row.get(0),
+        )
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to list tables: {}", e))
+        })?;
+
+    let (first, last, count) = if exists {
+        conn.query_row(
+            &format!(
+                "SELECT strftime(MIN(datetime), '%Y-%m-%d %H:%M:%S.%f'),
+                        strftime(MAX(datetime), '%Y-%m-%d %H:%M:%S.%f'),
+                        COUNT(*)
+                 FROM {} WHERE symbol = ?",
+                table_name
+            ),
+            duckdb::params![symbol],
+            |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, i64>(2)?)),
+        )
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to execute query: {}", e))
+        })?
+    } else {
+        (None, None, 0)
+    };
+
+    let info = PyDict::new_bound(py);
+    info.set_item("symbol", &symbol)?;
+    info.set_item("period", &period)?;
+    info.set_item("first", first.map(trim_fractional_seconds))?;
+    info.set_item("last", last.map(trim_fractional_seconds))?;
+    info.set_item("count", count)?;
+    Ok(info.into())
+}
+
+/// 缺口判断规则：周期长度与可选的交易日历（`find_gaps()`、`data_quality_report()` 共用）
+pub(crate) struct GapRule {
+    /// 周期长度（分钟）
+    pub(crate) step: i64,
+    /// 相邻两根 bar 允许的最大间隔
+    max_delta: chrono::Duration,
+    /// 交易日列表（已排序、去重）；周线及更长的周期不使用
+    days: Option<Vec<NaiveDate>>,
+    /// 内置交易日历，交易日列表在读取数据后按首尾 bar 的日期生成
+    calendar: Option<TradingCalendar>,
+}
+
+impl GapRule {
+    /// 按周期和 `calendar` 参数（交易日列表、内置日历名称或 `TradingCalendar`）构造
+    pub(crate) fn new(period: &str, calendar: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let step = period_to_minutes(period).filter(|m| *m > 0).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Unsupported period: {}", period))
+        })?;
+        // 月、年周期的实际长度不固定，放宽 10%
+        let max_delta = chrono::Duration::minutes(if step >= 43200 { step + step / 10 } else { step });
+
+        let (days, trading_calendar) = match calendar {
+            Some(c) if c.is_instance_of::<PyString>() || c.is_instance_of::<TradingCalendar>() => {
+                (None, Some(TradingCalendar::from_py(c)?))
+            }
+            Some(c) => (Some(c.extract::<Vec<String>>()?), None),
+            None => (None, None),
+        };
+        let days = match days {
+            Some(days) if step <= 1440 => {
+                let mut parsed = days
+                    .iter()
+                    .map(|d| {
+                        NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").map_err(|_| {
+                            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                                "Invalid calendar date '{}': expected 'YYYY-MM-DD'",
+                                d
+                            ))
+                        })
+                    })
+                    .collect::<PyResult<Vec<NaiveDate>>>()?;
+                parsed.sort_unstable();
+                parsed.dedup();
+                Some(parsed)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            step,
+            max_delta,
+            days,
+            calendar: trading_calendar.filter(|_| step <= 1440),
+        })
+    }
+
+    /// 使用内置交易日历时，按首尾 bar 的日期生成交易日列表
+    pub(crate) fn resolve_days(&mut self, first: NaiveDate, last: NaiveDate) {
+        if let Some(cal) = &self.calendar {
+            self.days = Some(cal.trading_days(first, last));
+        }
+    }
+
+    /// 交易日列表（没有日历或周期不使用日历时为 `None`）
+    pub(crate) fn days(&self) -> Option<&[NaiveDate]> {
+        self.days.as_deref()
+    }
+
+    /// 相邻两根 bar 之间是否有缺口
+    pub(crate) fn is_gap(&self, prev: NaiveDateTime, next: NaiveDateTime) -> bool {
+        match &self.days {
+            None => next - prev > self.max_delta,
+            Some(_) if prev.date() == next.date() => self.step < 1440 && next - prev > self.max_delta,
+            Some(days) => {
+                // 两根 bar 之间是否夹着完整的交易日
+                let after_prev = days.partition_point(|d| *d <= prev.date());
+                days.get(after_prev).is_some_and(|d| *d < next.date())
+            }
+        }
+    }
+}
+
+/// 检测某个标的在数据库中的数据缺口
+///
+/// 逐根扫描已保存的 K 线，按周期推算相邻两根 bar 之间应有的间隔，间隔过大的地方即为缺口。
+/// 可选传入交易日历，跳过周末、节假日等本来就没有数据的日子。
+///
+/// ## 判断规则
+///
+/// - **不传日历**：相邻两根 bar 的时间差大于一个周期即视为缺口（月、年周期按自然月、自然年长度放宽 10%）。
+///   日内数据的隔夜、周末也会被报告为缺口，适合 7×24 交易的品种
+/// - **传入日历**（交易日列表，如 `["2024-01-02", "2024-01-03", ...]`，或内置交易日历名称 / `TradingCalendar`）：
+///   - 相邻两根 bar 之间夹着至少一个没有任何数据的交易日时视为缺口
+///   - 日内周期还会检查同一天内相邻 bar 的间隔是否大于一个周期
+///   - 周线及更长的周期忽略日历，按不传日历的规则判断
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import find_gaps
+///
+/// gaps = find_gaps("data/backtest.db", "000001.SZ", "1d", calendar=trading_days)
+/// gaps = find_gaps("data/backtest.db", "000001.SZ", "1d", calendar="SSE")
+/// for start, end in gaps:
+///     print(f"missing data between {start} and {end}")
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `period`: 周期字符串（如 "1m", "1d"）
+/// - `calendar`: 可选的交易日列表（"YYYY-MM-DD"，无需排序），或内置交易日历名称（如 `"SSE"`）/ `TradingCalendar`；
+///   使用交易日历时按日历推算首尾 bar 之间的交易日
+///
+/// # 返回值
+///
+/// 缺口列表，每个元素是 `(start, end)`：缺口前最后一根 bar 与缺口后第一根 bar 的时间（不含缺失的数据本身），
+/// 按时间升序排列
+///
+/// # 注意事项
+///
+/// - 只检查已有数据首尾之间的缺口，第一根 bar 之前、最后一根 bar 之后缺失的数据请用 `get_data_range()` 判断
+/// - 日内数据按日历判断时不知道每天的开收盘时间，当天开盘后或收盘前缺失的几根 bar 无法检测
+/// - 周期无法识别、日历日期无法解析时返回 `ValueError`
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, period, calendar=None))]
+pub fn find_gaps(
+    db_path: String,
+    symbol: String,
+    period: String,
+    calendar: Option<&Bound<'_, PyAny>>,
+) -> PyResult<Vec<(String, String)>> {
+    let mut rule = GapRule::new(&period, calendar)?;
+    let bars = load_klines_rust(&db_path, &symbol, &period, None, None, -1)?;
+    let times: Vec<(&str, NaiveDateTime)> = bars
+        .iter()
+        .filter_map(|b| parse_datetime(&b.datetime).map(|dt| (b.datetime.as_str(), dt)))
+        .collect();
+    if let (Some((_, first)), Some((_, last))) = (times.first(), times.last()) {
+        rule.resolve_days(first.date(), last.date());
+    }
+
+    let mut gaps = Vec::new();
+    for pair in times.windows(2) {
+        let (prev_str, prev) = pair[0];
+        let (next_str, next) = pair[1];
+        if rule.is_gap(prev, next) {
+            gaps.push((prev_str.to_string(), next_str.to_string()));
+        }
+    }
+    Ok(gaps)
+}
+
+/// 通过 DuckDB 表函数（`read_csv`/`read_parquet`）直接导入 K 线
+///
+/// 在事务中把数据源加载到临时表（补上 symbol 并统一列类型），可选地按时间去重、校验并剔除问题行，
+/// 再插入周期表（自动去重）。
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn import_klines_from_source(
+    py: Python,
+    db_path: &str,
+    source: &str,
+    format_name: &str,
+    symbol: &str,
+    period: &str,
+    replace: bool,
+    validate: Option<&str>,
+    tz: Option<&str>,
+    dedupe: Option<&str>,
+) -> PyResult<PyObject> {
+    let validate = ValidationMode::parse(validate)?;
+    let tz = parse_timezone(tz)?;
+    let dedupe = DedupePolicy::parse(dedupe)?;
+
+    // Connect to database
+    let conn = open_connection(db_path)?;
+
+    let table_name = ensure_period_table(&conn, period)?;
+
+    // Delete old data if replace is true
+    if replace {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE symbol = ?", table_name),
+            duckdb::params![symbol],
+        )
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "Failed to delete old data: {}",
+                e
+            ))
+        })?;
+    }
+
+    // Use transaction
+    conn.execute("BEGIN TRANSACTION", []).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "Failed to begin transaction: {}",
+            e
+        ))
+    })?;
+
+    // Create temporary table and load the file directly
+    let temp_table = format!("temp_{}_import_{}", format_name.to_lowercase(), std::process::id());
+
     // Escape symbol for SQL
     let symbol_escaped = symbol.replace("'", "''");
     let create_temp_sql = format!(
         "CREATE TEMP TABLE {} AS 
          SELECT 
              '{}' as symbol,
-             CAST(datetime AS TIMESTAMP) as datetime,
+             {} as datetime,
              CAST(open AS DOUBLE) as open,
              CAST(high AS DOUBLE) as high,
              CAST(low AS DOUBLE) as low,
              CAST(close AS DOUBLE) as close,
-             CAST(volume AS DOUBLE) as volume
-         FROM read_csv('{}', 
-             header=true,
-             auto_detect=true)",
-        temp_table, symbol_escaped, csv_path_escaped
+             CAST(volume AS DOUBLE) as volume{}
+         FROM {}",
+        temp_table,
+        symbol_escaped,
+        datetime_column_expr(&conn, source),
+        // 指定时区时保留原始时间字符串，换算为 UTC 后再写回 datetime
+        if tz.is_some() { ",\n             CAST(datetime AS VARCHAR) as datetime_raw" } else { "" },
+        source
     );
 
-    println!("  Reading CSV file directly with DuckDB...");
+    logger::log(Level::Info, format_args!("Reading {} file directly with DuckDB...", format_name));
     conn.execute(&create_temp_sql, []).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
-            "Failed to read CSV file: {}. Make sure CSV has headers: datetime,open,high,low,close,volume",
-            e
+            "Failed to read {} file: {}. Make sure it has columns: datetime,open,high,low,close,volume",
+            format_name, e
         ))
     })?;
 
+    if tz.is_some() {
+        normalize_import_timezone(&conn, &temp_table, tz)?;
+    }
+
+    // 可选的去重：同一时间只保留文件中先出现（first）或后出现（last）的一行
+    let deduped = match dedupe {
+        Some(policy) => {
+            let mut stmt = conn
+                .prepare(&format!("SELECT COUNT(*), COUNT(DISTINCT datetime) FROM {}", temp_table))
+                .map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to prepare query: {}", e))
+                })?;
+            let (rows, distinct) = stmt
+                .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+                .map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to count duplicate rows: {}", e))
+                })?
+                .next()
+                .transpose()
+                .map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to read row: {}", e))
+                })?
+                .unwrap_or((0, 0));
+            conn.execute(
+                &format!(
+                    "DELETE FROM {t} WHERE rowid NOT IN (SELECT {keep}(rowid) FROM {t} GROUP BY datetime)",
+                    t = temp_table,
+                    keep = if policy == DedupePolicy::KeepLast { "MAX" } else { "MIN" }
+                ),
+                [],
+            )
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to drop duplicate rows: {}", e))
+            })?;
+            Some((policy, rows as usize, (rows - distinct) as usize))
+        }
+        None => None,
+    };
+
+    // Optional OHLC validation: read rows back in file order (rowid) and check them in Rust
+    let validation = match validate {
+        Some(mode) => {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT rowid, strftime(datetime, '%Y-%m-%d %H:%M:%S.%f'), open, high, low, close FROM {} ORDER BY {}",
+                    temp_table,
+                    // 去重时按时间排序后校验，文件中的乱序不会被报告为时间不递增
+                    if deduped.is_some() { "datetime, rowid" } else { "rowid" }
+                ))
+                .map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to prepare query: {}", e))
+                })?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        trim_fractional_seconds(row.get(1)?),
+                        row.get::<_, f64>(2)?,
+                        row.get::<_, f64>(3)?,
+                        row.get::<_, f64>(4)?,
+                        row.get::<_, f64>(5)?,
+                    ))
+                })
+                .map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to execute query: {}", e))
+                })?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to read row: {}", e))
+                })?;
+            let issues = validate_klines(rows.iter().map(|(_, dt, o, h, l, c)| (dt.as_str(), *o, *h, *l, *c)));
+            if mode == ValidationMode::Reject {
+                for chunk in issues.chunks(10_000) {
+                    let rowids: Vec<String> = chunk.iter().map(|issue| rows[issue.index].0.to_string()).collect();
+                    conn.execute(
+                        &format!("DELETE FROM {} WHERE rowid IN ({})", temp_table, rowids.join(", ")),
+                        [],
+                    )
+                    .map_err(|e| {
+                        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                            "Failed to drop invalid rows: {}",
+                            e
+                        ))
+                    })?;
+                }
+            }
+            Some((mode, rows.len(), issues))
+        }
+        None => None,
+    };
+
+    // 指定去重策略时，插入前统计与库中已有数据冲突的行数
+    let stored = match deduped {
+        Some(_) => Some(count_stored_conflicts(&conn, &table_name, &temp_table)?),
+        None => None,
+    };
+
     // Insert from temp table to target table
-    println!("  Inserting data into target table...");
+    logger::log(Level::Info, format_args!("Inserting data into target table..."));
     conn.execute(
         &format!(
             "INSERT INTO {} (symbol, datetime, open, high, low, close, volume)
              SELECT symbol, datetime, open, high, low, close, volume
              FROM {}
-             ON CONFLICT (symbol, datetime) DO NOTHING",
-            table_name, temp_table
+             {}",
+            table_name,
+            temp_table,
+            DedupePolicy::conflict_clause(dedupe)
         ),
         []
     ).map_err(|e| {
@@ -1318,9 +3555,6 @@ pub fn save_klines_from_csv(
         ))
     })?;
 
-    // Drop temporary table
-    conn.execute(&format!("DROP TABLE {}", temp_table), []).ok();
-
     // Commit transaction
     conn.execute("COMMIT", []).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
@@ -1329,5 +3563,123 @@ pub fn save_klines_from_csv(
         ))
     })?;
 
+    // 刷新以该周期为源周期的物化周期（从本次导入数据中最早的时间开始；替换时整体重建）
+    crate::materialize::refresh_dependents(&conn, symbol, period, || {
+        if replace {
+            return Ok(crate::materialize::RefreshFrom::Full);
+        }
+        let earliest: Option<String> = conn
+            .query_row(
+                &format!("SELECT strftime(MIN(datetime), '%Y-%m-%d %H:%M:%S.%f') FROM {}", temp_table),
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to execute query: {}", e))
+            })?;
+        Ok(earliest
+            .as_deref()
+            .and_then(parse_datetime)
+            .map_or(crate::materialize::RefreshFrom::Full, crate::materialize::RefreshFrom::Since))
+    })?;
+
+    // Drop temporary table
+    conn.execute(&format!("DROP TABLE {}", temp_table), []).ok();
+
+    let validation = match validation {
+        Some((mode, checked, issues)) => Some(validation_report(py, mode, checked, &issues)?),
+        None => None,
+    };
+    match (deduped, stored) {
+        (Some((policy, rows, duplicates)), Some((written, conflicting))) => dedupe_report(
+            py,
+            DedupeCounts { policy, rows, duplicates, written, conflicting },
+            validation,
+        ),
+        _ => Ok(validation.unwrap_or_else(|| py.None())),
+    }
+}
+
+/// 把导入临时表中的时间按时区换算为 UTC
+///
+/// DuckDB 的 `TIMESTAMP` 不带时区，这里从 `datetime_raw` 读出原始时间字符串，在 Rust 中换算
+/// （带偏移的按偏移，不带偏移的按 `tz` 的本地时间），经映射表批量写回 `datetime`。
+fn normalize_import_timezone(conn: &Connection, temp_table: &str, tz: Option<Tz>) -> PyResult<()> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT rowid, datetime_raw FROM {}", temp_table))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to prepare query: {}", e))
+        })?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to execute query: {}", e))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to read row: {}", e))
+        })?;
+
+    let map_table = format!("{}_utc", temp_table);
+    conn.execute(&format!("CREATE TEMP TABLE {} (rid BIGINT, datetime TIMESTAMP)", map_table), [])
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "Failed to create temporary table: {}",
+                e
+            ))
+        })?;
+    {
+        let mut appender = conn.appender(&map_table).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to create appender: {}", e))
+        })?;
+        for (rowid, raw) in &rows {
+            // 整数 epoch 时间戳本身就是 UTC，保持不变（无法解析的字符串在 CAST 阶段已经报错）
+            let Some(utc) = to_utc(raw, tz).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)? else {
+                continue;
+            };
+            appender
+                .append_row(duckdb::params![rowid, utc.format("%Y-%m-%d %H:%M:%S%.f").to_string()])
+                .map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                        "Failed to convert time zone: {}",
+                        e
+                    ))
+                })?;
+        }
+        appender.flush().map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to convert time zone: {}", e))
+        })?;
+    }
+    conn.execute(
+        &format!(
+            "UPDATE {t} SET datetime = m.datetime FROM {m} m WHERE {t}.rowid = m.rid",
+            t = temp_table,
+            m = map_table
+        ),
+        [],
+    )
+    .map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Failed to convert time zone: {}", e))
+    })?;
+    conn.execute(&format!("DROP TABLE {}", map_table), []).ok();
     Ok(())
 }
+
+/// 导入时 `datetime` 列的转换表达式
+///
+/// 整数列视为 epoch 毫秒时间戳（`epoch_ms()`），其他类型（字符串、时间戳）直接转换为 `TIMESTAMP`。
+/// 无法探测列类型时按后者处理，真正的错误由随后的导入语句报告。
+pub(crate) fn datetime_column_expr(conn: &Connection, source: &str) -> &'static str {
+    const INTEGER_TYPES: [&str; 8] = ["TINYINT", "SMALLINT", "INTEGER", "BIGINT", "UTINYINT", "USMALLINT", "UINTEGER", "UBIGINT"];
+    let column_type = conn
+        .prepare(&format!("DESCRIBE SELECT datetime FROM {}", source))
+        .ok()
+        .and_then(|mut stmt| {
+            let rows = stmt.query_map([], |row| row.get::<_, String>(1)).ok()?;
+            rows.flatten().next()
+        });
+    match column_type {
+        Some(t) if INTEGER_TYPES.contains(&t.as_str()) => "epoch_ms(CAST(datetime AS BIGINT))",
+        _ => "CAST(datetime AS TIMESTAMP)",
+    }
+}
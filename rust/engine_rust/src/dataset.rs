@@ -0,0 +1,359 @@
+//! 分区 Parquet 数据集导出/导入模块
+//!
+//! `export_klines_to_parquet()` 一次只导出一个标的、一个周期的单个文件。和 Spark、Polars 等工具交换整库数据时，
+//! 更通用的是 Hive 风格的分区目录：按周期、标的、年份分目录存放，读取方可以按目录裁剪只读需要的分区。
+//! 这个模块用 DuckDB 的 `COPY ... (PARTITION_BY ...)` 把整个库导出为这样的数据集，
+//! 也可以把同样布局的数据集（包括其他工具写出的）读回库中。
+//!
+//! ## 目录布局
+//!
+//! ```text
+//! dataset/
+//! ├── period=1d/
+//! │   ├── symbol=AAPL/
+//! │   │   ├── year=2022/data_0.parquet
+//! │   │   └── year=2023/data_0.parquet
+//! │   └── symbol=MSFT/...
+//! └── period=1m/...
+//! ```
+//!
+//! 文件中的列为 `datetime`（TIMESTAMP）、`open`、`high`、`low`、`close`、`volume`，
+//! `period`、`symbol`、`year` 由目录名给出（Hive 分区）。
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import export_parquet_dataset, import_parquet_dataset
+//!
+//! export_parquet_dataset("data/backtest.db", "share/dataset")          # {"1m": 5234100, "1d": 25160}
+//! # Polars: pl.scan_parquet("share/dataset/period=1d/**/*.parquet", hive_partitioning=True)
+//! # Spark:  spark.read.parquet("share/dataset")
+//!
+//! import_parquet_dataset("data/other.db", "share/dataset")             # 按 period 分区写入各周期表
+//! import_parquet_dataset("data/other.db", "spark_out/bars", period="1m")  # 只有 symbol/year 分区的数据集
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 分区值按字符串读取（不做类型推断），`000001.SZ` 之类的代码不会被误转换为数字
+//! - 导入时重复的 (symbol, datetime) 会被跳过（同 `save_klines()`），写入后刷新依赖的物化周期
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use duckdb::Connection;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::database::{datetime_column_expr, ensure_period_table, list_periods, open_connection, parse_datetime};
+use crate::materialize::{refresh_dependents, RefreshFrom};
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// SQL 字符串字面量转义
+fn escape(v: &str) -> String {
+    v.replace('\'', "''")
+}
+
+/// 执行返回单个整数的查询（没有结果时为 0）
+fn query_count(conn: &Connection, sql: &str, params: &[&str]) -> PyResult<usize> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let count = stmt
+        .query_map(duckdb::params_from_iter(params), |row| row.get::<_, i64>(0))
+        .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?
+        .next()
+        .transpose()
+        .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+    Ok(count.unwrap_or(0) as usize)
+}
+
+/// 把整个库导出为按周期、标的、年份分区的 Parquet 数据集
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import export_parquet_dataset
+///
+/// rows = export_parquet_dataset("data/backtest.db", "share/dataset", periods=["1d"], symbols=["AAPL", "MSFT"])
+/// # {"1d": 5032}
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `out_dir`: 数据集根目录（不存在时自动创建）
+/// - `periods`: 可选的周期列表，默认导出库中所有有数据的周期
+/// - `symbols`: 可选的标的列表，默认导出全部标的
+/// - `overwrite`: 周期目录已存在时是否覆盖其中的同名文件，默认 `False`
+///
+/// # 返回值
+///
+/// 字典 `{周期: 导出行数}`
+///
+/// # 注意事项
+///
+/// - `overwrite=False` 且周期目录已有文件时返回 `RuntimeError`，避免与旧数据混在一起；
+///   `overwrite=True` 只覆盖同名文件，旧数据集中多出的分区需要自行删除
+/// - 每个分区内按时间升序排列
+#[pyfunction]
+#[pyo3(signature = (db_path, out_dir, periods=None, symbols=None, overwrite=false))]
+pub fn export_parquet_dataset(
+    db_path: String,
+    out_dir: String,
+    periods: Option<Vec<String>>,
+    symbols: Option<Vec<String>>,
+    overwrite: bool,
+) -> PyResult<BTreeMap<String, usize>> {
+    let periods = match periods {
+        Some(periods) => periods,
+        None => list_periods(db_path.clone())?,
+    };
+    std::fs::create_dir_all(&out_dir)
+        .map_err(|e| runtime_error(format!("Failed to create directory {}: {}", out_dir, e)))?;
+
+    let conn = open_connection(&db_path)?;
+    let symbol_filter = match &symbols {
+        Some(symbols) if symbols.is_empty() => return Err(value_error("symbols must not be empty".to_string())),
+        Some(symbols) => format!(
+            "WHERE symbol IN ({})",
+            symbols.iter().map(|s| format!("'{}'", escape(s))).collect::<Vec<_>>().join(", ")
+        ),
+        None => String::new(),
+    };
+
+    let mut exported = BTreeMap::new();
+    for period in periods {
+        let table_name = ensure_period_table(&conn, &period)?;
+        let period_name = &table_name["klines_".len()..];
+        let target = Path::new(&out_dir).join(format!("period={}", period_name));
+        // COPY does not accept prepared parameters, so literals are escaped inline
+        let rows = conn
+            .execute(
+                &format!(
+                    "COPY (
+                         SELECT symbol, CAST(year(datetime) AS VARCHAR) AS year, datetime, open, high, low, close, volume
+                         FROM {} {}
+                         ORDER BY symbol, datetime
+                     ) TO '{}' (FORMAT PARQUET, PARTITION_BY (symbol, year){})",
+                    table_name,
+                    symbol_filter,
+                    escape(&target.to_string_lossy()),
+                    if overwrite { ", OVERWRITE_OR_IGNORE" } else { "" }
+                ),
+                [],
+            )
+            .map_err(|e| runtime_error(format!("Failed to export period {} to {}: {}", period_name, target.display(), e)))?;
+        exported.insert(period_name.to_string(), rows);
+    }
+    Ok(exported)
+}
+
+/// 从 Hive 分区的 Parquet 数据集导入 K 线
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import import_parquet_dataset
+///
+/// report = import_parquet_dataset("data/backtest.db", "share/dataset")
+/// # {"1d": {"rows": 5032, "inserted": 5032}, "1m": {...}}
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `path`: 数据集根目录，或 Parquet 文件通配符（如 `"share/dataset/period=1d/symbol=AAPL/**/*.parquet"`）
+/// - `period`: 可选的周期。数据集有 `period` 分区时只导入该周期；没有 `period` 分区时必须指定，全部数据写入该周期
+/// - `replace`: 是否先删除数据集中出现的标的在对应周期的旧数据，默认 `False`
+///
+/// # 返回值
+///
+/// 字典 `{周期: {"rows": 读取行数, "inserted": 实际写入行数}}`
+///
+/// # 注意事项
+///
+/// - 数据必须包含 `symbol`（列或分区）、`datetime`、`open`、`high`、`low`、`close`、`volume`，否则返回 `RuntimeError`
+/// - 没有 `period` 分区且未指定 `period` 时返回 `ValueError`
+/// - 所有周期在一个事务中写入，任何一步失败时整体回滚
+#[pyfunction]
+#[pyo3(signature = (db_path, path, period=None, replace=false))]
+pub fn import_parquet_dataset(
+    py: Python<'_>,
+    db_path: String,
+    path: String,
+    period: Option<String>,
+    replace: bool,
+) -> PyResult<PyObject> {
+    let glob = if Path::new(&path).is_dir() {
+        Path::new(&path).join("**").join("*.parquet").to_string_lossy().to_string()
+    } else {
+        path.clone()
+    };
+    let source = format!(
+        "read_parquet('{}', hive_partitioning = true, hive_types_autocast = false, union_by_name = true)",
+        escape(&glob)
+    );
+
+    let conn = open_connection(&db_path)?;
+    let mut stmt = conn
+        .prepare(&format!("DESCRIBE SELECT * FROM {}", source))
+        .map_err(|e| runtime_error(format!("Failed to read Parquet dataset {}: {}", path, e)))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| runtime_error(format!("Failed to read Parquet dataset {}: {}", path, e)))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+    let has_period = columns.iter().any(|c| c == "period");
+    let (period_expr, period_filter) = match (&period, has_period) {
+        (Some(p), true) => (format!("'{}'", escape(p)), format!("WHERE CAST(period AS VARCHAR) = '{}'", escape(p))),
+        (Some(p), false) => (format!("'{}'", escape(p)), String::new()),
+        (None, true) => ("CAST(period AS VARCHAR)".to_string(), String::new()),
+        (None, false) => {
+            return Err(value_error(format!(
+                "Dataset {} has no period partition; pass period=",
+                path
+            )))
+        }
+    };
+
+    let temp_table = format!("temp_dataset_import_{}", std::process::id());
+    conn.execute(
+        &format!(
+            "CREATE TEMP TABLE {} AS
+             SELECT
+                 {} AS period,
+                 CAST(symbol AS VARCHAR) AS symbol,
+                 {} AS datetime,
+                 CAST(open AS DOUBLE) AS open,
+                 CAST(high AS DOUBLE) AS high,
+                 CAST(low AS DOUBLE) AS low,
+                 CAST(close AS DOUBLE) AS close,
+                 CAST(volume AS DOUBLE) AS volume
+             FROM {} {}",
+            temp_table,
+            period_expr,
+            datetime_column_expr(&conn, &source),
+            source,
+            period_filter
+        ),
+        [],
+    )
+    .map_err(|e| {
+        runtime_error(format!(
+            "Failed to read Parquet dataset {}: {}. Make sure it has columns: symbol,datetime,open,high,low,close,volume",
+            path, e
+        ))
+    })?;
+
+    let result = import_periods(&conn, &temp_table, replace);
+    conn.execute(&format!("DROP TABLE IF EXISTS {}", temp_table), []).ok();
+    let imported = result?;
+
+    let report = PyDict::new_bound(py);
+    for (period, (rows, inserted)) in imported {
+        let item = PyDict::new_bound(py);
+        item.set_item("rows", rows)?;
+        item.set_item("inserted", inserted)?;
+        report.set_item(period, item)?;
+    }
+    Ok(report.into())
+}
+
+/// 把临时表中的数据按周期写入各周期表，返回 `{周期: (读取行数, 写入行数)}`
+fn import_periods(conn: &Connection, temp_table: &str, replace: bool) -> PyResult<BTreeMap<String, (usize, usize)>> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT DISTINCT period FROM {} ORDER BY period", temp_table))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let periods = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| runtime_error(format!("Failed to list periods: {}", e)))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+    let tables = periods
+        .iter()
+        .map(|p| ensure_period_table(conn, p).map(|t| (p.clone(), t)))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    conn.execute("BEGIN TRANSACTION", [])
+        .map_err(|e| runtime_error(format!("Failed to begin transaction: {}", e)))?;
+    let written = tables
+        .iter()
+        .map(|(period, table)| import_period(conn, temp_table, period, table, replace).map(|n| (period.clone(), n)))
+        .collect::<PyResult<BTreeMap<_, _>>>();
+    let written = match written {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = conn.execute("ROLLBACK", []);
+            return Err(e);
+        }
+    };
+    conn.execute("COMMIT", []).map_err(|e| runtime_error(format!("Failed to commit transaction: {}", e)))?;
+
+    // 刷新以这些周期为源周期的物化周期（每个标的从本次导入的最早时间开始；替换时整体重建）
+    for (period, _) in &tables {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT symbol, strftime(MIN(datetime), '%Y-%m-%d %H:%M:%S.%f') FROM {} WHERE period = ? GROUP BY symbol",
+                temp_table
+            ))
+            .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+        let earliest = stmt
+            .query_map(duckdb::params![period], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+            .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+        for (symbol, first) in earliest {
+            refresh_dependents(conn, &symbol, period, || {
+                Ok(match first.as_deref().and_then(parse_datetime) {
+                    Some(dt) if !replace => RefreshFrom::Since(dt),
+                    _ => RefreshFrom::Full,
+                })
+            })?;
+        }
+    }
+    Ok(written)
+}
+
+/// 把一个周期的数据写入周期表，返回 (读取行数, 写入行数)
+fn import_period(
+    conn: &Connection,
+    temp_table: &str,
+    period: &str,
+    table: &str,
+    replace: bool,
+) -> PyResult<(usize, usize)> {
+    if replace {
+        conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE symbol IN (SELECT DISTINCT symbol FROM {} WHERE period = ?)",
+                table, temp_table
+            ),
+            duckdb::params![period],
+        )
+        .map_err(|e| runtime_error(format!("Failed to delete old data: {}", e)))?;
+    }
+    let rows = query_count(conn, &format!("SELECT COUNT(*) FROM {} WHERE period = ?", temp_table), &[period])?;
+    let before = query_count(conn, &format!("SELECT COUNT(*) FROM {}", table), &[])?;
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (symbol, datetime, open, high, low, close, volume)
+             SELECT symbol, datetime, open, high, low, close, volume
+             FROM {}
+             WHERE period = ?
+             ON CONFLICT (symbol, datetime) DO NOTHING",
+            table, temp_table
+        ),
+        duckdb::params![period],
+    )
+    .map_err(|e| runtime_error(format!("Failed to insert period {}: {}", period, e)))?;
+    let after = query_count(conn, &format!("SELECT COUNT(*) FROM {}", table), &[])?;
+    Ok((rows, after.saturating_sub(before)))
+}
@@ -0,0 +1,130 @@
+//! 回测区间模块
+//!
+//! `BacktestConfig` 一直有 `start`/`end` 两个字段，但引擎从未使用它们：传入多少数据就回测多少，
+//! 想只测某一段行情必须在 Python 里先切好数据。这个模块按配置的区间截取数据，
+//! 并在结果中报告实际回测的时间范围。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. `start`/`end` 可以是日期（`"2020-01-01"`）或日期时间（`"2020-01-01 09:30:00"`），两端都包含；
+//!    只有日期的 `end` 包含当天的全部 bar，空字符串表示该端不限制
+//! 2. 指标先在完整数据上预计算，再与 bar 一起截取，因此区间开头的指标不会因为缺少历史而为空
+//! 3. `run_from_db` 把区间直接下推到 DuckDB 查询，区间外的数据不会被读出
+//! 4. 结果中的 `date_range` 记录实际回测的第一根和最后一根 bar 的时间（`start`/`end`）以及 bar 数（`bars`）；
+//!    `run_multi` 的 `bars` 为各 feed 截取后的 bar 数之和
+//!
+//! # 注意事项
+//!
+//! - 没有 `datetime` 或 `datetime` 无法解析的 bar 无法判断所属区间，总是保留
+//! - 截取后没有任何 bar 时返回 `ValueError`，避免配置区间与数据不符时静默得到全零结果
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::database::parse_datetime;
+use crate::BarData;
+
+/// 回测区间（两端都包含，`None` 表示不限制）
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct DateWindow {
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+}
+
+impl DateWindow {
+    /// 解析配置中的 `start`/`end`；空字符串表示不限制
+    pub(crate) fn parse(start: &str, end: &str) -> Result<Self, String> {
+        let window = Self { start: parse_bound(start, false)?, end: parse_bound(end, true)? };
+        if let (Some(s), Some(e)) = (window.start, window.end) {
+            if s > e {
+                return Err(format!("start ({}) must not be later than end ({})", start, end));
+            }
+        }
+        Ok(window)
+    }
+
+    /// 与另一组边界取交集（如 `run_from_db` 的 `start`/`end` 参数）
+    pub(crate) fn narrow(self, start: Option<&str>, end: Option<&str>) -> Result<Self, String> {
+        let other = Self::parse(start.unwrap_or(""), end.unwrap_or(""))?;
+        Ok(Self {
+            start: self.start.max(other.start),
+            end: match (self.end, other.end) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        })
+    }
+
+    /// bar 时间是否在区间内；没有或无法解析的时间视为在区间内
+    pub(crate) fn contains(&self, datetime: Option<&str>) -> bool {
+        let Some(dt) = datetime.and_then(parse_datetime) else { return true };
+        self.start.is_none_or(|s| dt >= s) && self.end.is_none_or(|e| dt <= e)
+    }
+
+    /// 区间内 bar 的下标；全部在区间内时返回 `None`（无需截取）
+    pub(crate) fn select(&self, bars: &[BarData]) -> Option<Vec<usize>> {
+        if self.start.is_none() && self.end.is_none() {
+            return None;
+        }
+        let keep: Vec<usize> = (0..bars.len()).filter(|&i| self.contains(bars[i].datetime.as_deref())).collect();
+        (keep.len() < bars.len()).then_some(keep)
+    }
+
+    /// 截取 bar 数据；截取后为空时返回 `ValueError`
+    pub(crate) fn slice_bars(&self, bars: Vec<BarData>, keep: Option<&[usize]>) -> PyResult<Vec<BarData>> {
+        let Some(keep) = keep else { return Ok(bars) };
+        if keep.is_empty() && !bars.is_empty() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "No bars between {} and {} (data covers {} to {})",
+                self.start.map_or("-".to_string(), |s| s.format("%Y-%m-%d %H:%M:%S").to_string()),
+                self.end.map_or("-".to_string(), |e| e.format("%Y-%m-%d %H:%M:%S").to_string()),
+                bars[0].datetime.as_deref().unwrap_or("-"),
+                bars[bars.len() - 1].datetime.as_deref().unwrap_or("-"),
+            )));
+        }
+        Ok(select_values(&bars, keep))
+    }
+
+    /// DuckDB 查询使用的边界字符串
+    pub(crate) fn sql_bounds(&self) -> (Option<String>, Option<String>) {
+        let fmt = |dt: NaiveDateTime| dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+        (self.start.map(fmt), self.end.map(fmt))
+    }
+}
+
+/// 按下标截取与 bar 对齐的数据（指标列、信号等）
+pub(crate) fn select_values<T: Clone>(values: &[T], keep: &[usize]) -> Vec<T> {
+    keep.iter().map(|&i| values[i].clone()).collect()
+}
+
+/// 在结果字典中记录实际回测的时间范围（`date_range`）
+pub(crate) fn mark_result(
+    py: Python<'_>,
+    result: &PyObject,
+    first: Option<&str>,
+    last: Option<&str>,
+    bars: usize,
+) -> PyResult<()> {
+    let range = PyDict::new_bound(py);
+    range.set_item("start", first)?;
+    range.set_item("end", last)?;
+    range.set_item("bars", bars)?;
+    result.downcast_bound::<PyDict>(py)?.set_item("date_range", range)?;
+    Ok(())
+}
+
+/// 解析区间边界；只有日期的结束边界包含当天全部时间
+fn parse_bound(value: &str, is_end: bool) -> Result<Option<NaiveDateTime>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if is_end { NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999) } else { NaiveTime::from_hms_opt(0, 0, 0) };
+        return Ok(time.map(|t| date.and_time(t)));
+    }
+    parse_datetime(value)
+        .map(Some)
+        .ok_or_else(|| format!("Invalid date '{}': expected 'YYYY-MM-DD' or 'YYYY-MM-DD HH:MM:SS'", value))
+}
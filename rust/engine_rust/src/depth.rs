@@ -0,0 +1,374 @@
+//! Level-2 盘口快照存储模块
+//!
+//! K 线只记录了每个周期的开高低收，做微观结构研究（买卖价差、盘口失衡、冲击成本）
+//! 或者更真实的成交模型，都需要逐笔时刻的买卖盘口。这个模块在 DuckDB 中增加一张盘口快照表，
+//! 按 (标的, 时间) 保存任意档位的买卖盘，并提供与 K 线相同风格的保存/读取函数。
+//!
+//! ## 存储结构
+//!
+//! 所有标的共用一张表 `depth_snapshots`，每个档位一行（长表），档位数可以不固定：
+//!
+//! | 列 | 类型 | 说明 |
+//! |----|------|------|
+//! | `symbol` | VARCHAR | 交易标的 |
+//! | `datetime` | TIMESTAMP | 快照时间 |
+//! | `side` | VARCHAR | `'bid'` 或 `'ask'` |
+//! | `level` | INTEGER | 档位，1 为最优价 |
+//! | `price` | DOUBLE | 价格 |
+//! | `size` | DOUBLE | 挂单量 |
+//!
+//! (symbol, datetime, side, level) 上有唯一索引，重复写入同一快照时自动去重（保留先写入的数据）。
+//! 长表便于直接用 SQL 做分析，例如 `SELECT datetime, price FROM depth_snapshots WHERE side = 'ask' AND level = 1`。
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import save_depth, load_depth
+//!
+//! snapshots = [
+//!     {"datetime": "2024-01-02 09:30:00.500",
+//!      "bids": [[10.01, 500], [10.00, 1200]],      # 买一、买二……（价格, 数量），最优价在前
+//!      "asks": [[10.02, 300], [10.03, 800]]},
+//!     ...
+//! ]
+//! save_depth("data/backtest.db", "000001.SZ", snapshots)
+//!
+//! book = load_depth("data/backtest.db", "000001.SZ", start="2024-01-02 09:30:00", levels=5)
+//! spread = book[0]["asks"][0][0] - book[0]["bids"][0][0]
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 档位按列表顺序编号，调用方需保证最优价在前
+//! - 快照时间支持毫秒等小数秒；带 UTC 偏移的时间换算为 UTC 存储，整数时间视为 epoch 毫秒（UTC）
+//! - 某一侧没有挂单时传空列表即可，读取时该侧同样为空列表
+
+use duckdb::Connection;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::database::{open_connection, to_utc, trim_fractional_seconds};
+
+/// 盘口快照表名
+const DEPTH_TABLE: &str = "depth_snapshots";
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+/// 一个时刻的盘口快照
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepthSnapshot {
+    /// 交易标的
+    pub symbol: String,
+    /// 快照时间
+    pub datetime: String,
+    /// 买盘 `(价格, 数量)`，买一在前
+    pub bids: Vec<(f64, f64)>,
+    /// 卖盘 `(价格, 数量)`，卖一在前
+    pub asks: Vec<(f64, f64)>,
+}
+
+/// 确保盘口快照表及唯一索引存在
+fn ensure_depth_table(conn: &Connection) -> PyResult<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                symbol VARCHAR NOT NULL,
+                datetime TIMESTAMP NOT NULL,
+                side VARCHAR NOT NULL,
+                level INTEGER NOT NULL,
+                price DOUBLE NOT NULL,
+                size DOUBLE NOT NULL
+            )",
+            DEPTH_TABLE
+        ),
+        [],
+    )
+    .map_err(|e| runtime_error(format!("Failed to ensure table {}: {}", DEPTH_TABLE, e)))?;
+    conn.execute(
+        &format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_{}_key ON {} (symbol, datetime, side, level)",
+            DEPTH_TABLE, DEPTH_TABLE
+        ),
+        [],
+    )
+    .map_err(|e| runtime_error(format!("Failed to ensure index for {}: {}", DEPTH_TABLE, e)))?;
+    Ok(())
+}
+
+/// 保存盘口快照（Rust 实现）
+///
+/// 在事务中先用 Appender 把所有档位写入临时表，再一次性插入正式表（`ON CONFLICT DO NOTHING` 去重）。
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码（覆盖快照中的 `symbol`）
+/// - `snapshots`: 盘口快照列表
+/// - `replace`: 是否先删除该标的的全部旧快照
+///
+/// # 返回值
+///
+/// 写入的档位行数（去重前）
+pub fn save_depth_rust(db_path: &str, symbol: &str, snapshots: &[DepthSnapshot], replace: bool) -> PyResult<usize> {
+    // 统一时间格式：带偏移的时间换算为 UTC
+    let mut datetimes = Vec::with_capacity(snapshots.len());
+    for (i, snap) in snapshots.iter().enumerate() {
+        let dt = to_utc(&snap.datetime, None)
+            .map_err(value_error)?
+            .ok_or_else(|| value_error(format!("Snapshot {} has an invalid datetime: '{}'", i, snap.datetime)))?;
+        datetimes.push(dt.format("%Y-%m-%d %H:%M:%S%.f").to_string());
+    }
+
+    let conn = open_connection(db_path)?;
+    ensure_depth_table(&conn)?;
+    if replace {
+        conn.execute(&format!("DELETE FROM {} WHERE symbol = ?", DEPTH_TABLE), duckdb::params![symbol])
+            .map_err(|e| runtime_error(format!("Failed to delete old data: {}", e)))?;
+    }
+
+    conn.execute("BEGIN TRANSACTION", []).map_err(|e| runtime_error(format!("Failed to begin transaction: {}", e)))?;
+    let temp_table = format!("temp_depth_{}", std::process::id());
+    conn.execute(
+        &format!(
+            "CREATE TEMP TABLE {} (
+                symbol VARCHAR NOT NULL,
+                datetime TIMESTAMP NOT NULL,
+                side VARCHAR NOT NULL,
+                level INTEGER NOT NULL,
+                price DOUBLE NOT NULL,
+                size DOUBLE NOT NULL
+            )",
+            temp_table
+        ),
+        [],
+    )
+    .map_err(|e| runtime_error(format!("Failed to create temporary table: {}", e)))?;
+
+    let mut rows = 0usize;
+    {
+        let mut appender =
+            conn.appender(&temp_table).map_err(|e| runtime_error(format!("Failed to create appender: {}", e)))?;
+        for (snap, datetime) in snapshots.iter().zip(&datetimes) {
+            for (side, levels) in [("bid", &snap.bids), ("ask", &snap.asks)] {
+                for (i, &(price, size)) in levels.iter().enumerate() {
+                    let level = (i + 1) as i32;
+                    appender
+                        .append_row(duckdb::params![symbol, datetime.as_str(), side, level, price, size])
+                        .map_err(|e| runtime_error(format!("Failed to append depth row at {}: {}", datetime, e)))?;
+                    rows += 1;
+                }
+            }
+        }
+        appender.flush().map_err(|e| runtime_error(format!("Failed to flush appender: {}", e)))?;
+    }
+
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (symbol, datetime, side, level, price, size)
+             SELECT symbol, datetime, side, level, price, size FROM {}
+             ON CONFLICT (symbol, datetime, side, level) DO NOTHING",
+            DEPTH_TABLE, temp_table
+        ),
+        [],
+    )
+    .map_err(|e| runtime_error(format!("Failed to insert from temp table to target table: {}", e)))?;
+    conn.execute(&format!("DROP TABLE {}", temp_table), [])
+        .map_err(|e| runtime_error(format!("Failed to drop temporary table: {}", e)))?;
+    conn.execute("COMMIT", []).map_err(|e| runtime_error(format!("Failed to commit transaction: {}", e)))?;
+    Ok(rows)
+}
+
+/// 读取盘口快照（Rust 实现）
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `start` / `end`: 可选的时间范围（包含两端）
+/// - `levels`: 可选的最大档位数，只读取前 N 档
+///
+/// # 返回值
+///
+/// 按时间升序排列的快照列表
+pub fn load_depth_rust(
+    db_path: &str,
+    symbol: &str,
+    start: Option<&str>,
+    end: Option<&str>,
+    levels: Option<usize>,
+) -> PyResult<Vec<DepthSnapshot>> {
+    let conn = open_connection(db_path)?;
+    ensure_depth_table(&conn)?;
+
+    let mut where_parts = vec!["symbol = ?".to_string()];
+    let mut params: Vec<String> = vec![symbol.to_string()];
+    if let Some(s) = start {
+        where_parts.push("datetime >= ?".to_string());
+        params.push(s.to_string());
+    }
+    if let Some(e) = end {
+        where_parts.push("datetime <= ?".to_string());
+        params.push(e.to_string());
+    }
+    if let Some(n) = levels {
+        where_parts.push(format!("level <= {}", n));
+    }
+    let query = format!(
+        "SELECT strftime(datetime, '%Y-%m-%d %H:%M:%S.%f') AS datetime_str, side, level, price, size
+         FROM {} WHERE {} ORDER BY datetime, side, level",
+        DEPTH_TABLE,
+        where_parts.join(" AND ")
+    );
+
+    let mut stmt = conn.prepare(&query).map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let rows = stmt
+        .query_map(duckdb::params_from_iter(params), |row| {
+            Ok((
+                trim_fractional_seconds(row.get(0)?),
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+            ))
+        })
+        .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?;
+
+    // 同一时间的行属于同一快照；按 level 排序，直接追加即为档位顺序
+    let mut snapshots: Vec<DepthSnapshot> = Vec::new();
+    for row in rows {
+        let (datetime, side, price, size) = row.map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+        if snapshots.last().is_none_or(|s| s.datetime != datetime) {
+            snapshots.push(DepthSnapshot { symbol: symbol.to_string(), datetime, bids: Vec::new(), asks: Vec::new() });
+        }
+        if let Some(snap) = snapshots.last_mut() {
+            match side.as_str() {
+                "bid" => snap.bids.push((price, size)),
+                _ => snap.asks.push((price, size)),
+            }
+        }
+    }
+    Ok(snapshots)
+}
+
+/// 读取一侧的档位列表（`[[price, size], ...]`）
+fn extract_levels(snapshot: &Bound<'_, PyDict>, key: &str, index: usize) -> PyResult<Vec<(f64, f64)>> {
+    let Some(levels) = snapshot.get_item(key)? else { return Ok(Vec::new()) };
+    if levels.is_none() {
+        return Ok(Vec::new());
+    }
+    let mut out = Vec::new();
+    for (j, level) in levels.iter()?.enumerate() {
+        match level?.extract::<Vec<f64>>().ok().as_deref() {
+            Some(&[price, size]) => out.push((price, size)),
+            _ => {
+                return Err(value_error(format!(
+                    "Snapshot {} has an invalid {} level {}: expected [price, size]",
+                    index,
+                    key,
+                    j + 1
+                )))
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// 保存 Level-2 盘口快照
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import save_depth
+///
+/// save_depth("data/backtest.db", "000001.SZ", [
+///     {"datetime": "2024-01-02 09:30:00.500",
+///      "bids": [[10.01, 500], [10.00, 1200]],
+///      "asks": [[10.02, 300], [10.03, 800]]},
+/// ])
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `snapshots`: 快照字典列表，每个字典包含 `datetime`、`bids`、`asks`；
+///   `bids`/`asks` 为 `[价格, 数量]` 列表（元组亦可），最优价在前，缺失时视为空
+/// - `replace`: 是否先删除该标的的全部旧快照，默认 `False`
+///
+/// # 返回值
+///
+/// 写入的档位行数（重复快照去重前的行数）
+///
+/// # 注意事项
+///
+/// - 时间无法解析或档位不是 `[价格, 数量]` 时返回 `ValueError`，此时不会写入任何数据
+/// - 相同 (标的, 时间, 方向, 档位) 的行已存在时保留旧数据
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, snapshots, replace=false))]
+pub fn save_depth(db_path: String, symbol: String, snapshots: &Bound<'_, PyList>, replace: bool) -> PyResult<usize> {
+    let mut parsed = Vec::with_capacity(snapshots.len());
+    for (i, item) in snapshots.iter().enumerate() {
+        let snap = item.downcast::<PyDict>()?;
+        let datetime = snap
+            .get_item("datetime")?
+            .and_then(|v| crate::extract_datetime(v.as_gil_ref()).0)
+            .ok_or_else(|| value_error(format!("Snapshot {} has no datetime", i)))?;
+        parsed.push(DepthSnapshot {
+            symbol: symbol.clone(),
+            datetime,
+            bids: extract_levels(snap, "bids", i)?,
+            asks: extract_levels(snap, "asks", i)?,
+        });
+    }
+    save_depth_rust(&db_path, &symbol, &parsed, replace)
+}
+
+/// 读取 Level-2 盘口快照
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import load_depth
+///
+/// book = load_depth("data/backtest.db", "000001.SZ", start="2024-01-02", end="2024-01-02 23:59:59", levels=5)
+/// for snap in book:
+///     best_bid, best_ask = snap["bids"][0][0], snap["asks"][0][0]
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `start` / `end`: 可选的时间范围（包含两端）
+/// - `levels`: 可选，只读取前 N 档
+///
+/// # 返回值
+///
+/// 按时间升序排列的快照字典列表：`symbol`、`datetime`、`bids`、`asks`（`(价格, 数量)` 元组列表，最优价在前）
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, start=None, end=None, levels=None))]
+pub fn load_depth(
+    py: Python<'_>,
+    db_path: String,
+    symbol: String,
+    start: Option<String>,
+    end: Option<String>,
+    levels: Option<usize>,
+) -> PyResult<PyObject> {
+    let snapshots = load_depth_rust(&db_path, &symbol, start.as_deref(), end.as_deref(), levels)?;
+    let out = PyList::empty_bound(py);
+    for snap in snapshots {
+        let d = PyDict::new_bound(py);
+        d.set_item("symbol", &snap.symbol)?;
+        d.set_item("datetime", &snap.datetime)?;
+        d.set_item("bids", snap.bids)?;
+        d.set_item("asks", snap.asks)?;
+        out.append(d)?;
+    }
+    Ok(out.into())
+}
@@ -0,0 +1,135 @@
+//! 提前终止模块
+//!
+//! 参数扫描或压力测试时，很多回测在中途就已经没有继续跑下去的意义：账户已经爆仓、
+//! 已经达到目标收益，或者只想用前 N 根 bar 快速验证策略。以前只能跑完全部数据再事后筛选。
+//! 配置提前终止条件后，引擎在满足条件的那根 bar 处理完后立即停止，并在结果中说明终止原因。
+//!
+//! ## 终止条件
+//!
+//! - `max_bars`: 处理完第 `max_bars` 根 bar（包括预热 bar）后停止
+//! - `target_equity`: 账户净值 **大于等于** 目标值时停止（止盈）
+//! - `ruin_equity`: 账户净值 **小于等于** 破产阈值时停止（爆仓）
+//!
+//! 净值条件只在预热期之后检查，净值按该 bar 处理完（包括调仓成交）后的收盘价估算。
+//! 同一根 bar 同时满足多个条件时，优先级为 `ruin` > `target_equity` > `max_bars`。
+//!
+//! ## 结果字段
+//!
+//! 配置了任一终止条件时，结果字典中附带 `stop_reason`：
+//! `"ruin"`、`"target_equity"`、`"max_bars"`，正常跑完时为 `"completed"`，被取消时为 `"cancelled"`。
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! cfg = BacktestConfig("2020-01-01", "2023-12-31", 100000.0, ruin_equity=50000.0)
+//! result = engine.run(strategy, bars)
+//! if result["stop_reason"] == "ruin":
+//!     print("blown up at", result["equity_curve"][-1]["datetime"])
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 提前终止后照常调用 `on_stop()`，持仓不会被自动平掉
+//! - 统计指标只基于终止前的净值曲线计算
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// 提前终止原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StopReason {
+    /// 净值跌破破产阈值
+    Ruin,
+    /// 净值达到目标值
+    TargetEquity,
+    /// 达到最大 bar 数
+    MaxBars,
+}
+
+impl StopReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            StopReason::Ruin => "ruin",
+            StopReason::TargetEquity => "target_equity",
+            StopReason::MaxBars => "max_bars",
+        }
+    }
+}
+
+/// 提前终止条件
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct EarlyStop {
+    max_bars: Option<usize>,
+    target_equity: Option<f64>,
+    ruin_equity: Option<f64>,
+}
+
+impl EarlyStop {
+    /// 从配置创建并校验终止条件
+    pub(crate) fn from_config(
+        max_bars: Option<usize>,
+        target_equity: Option<f64>,
+        ruin_equity: Option<f64>,
+    ) -> Result<Self, String> {
+        if max_bars == Some(0) {
+            return Err("max_bars must be greater than 0".to_string());
+        }
+        for (name, value) in [("target_equity", target_equity), ("ruin_equity", ruin_equity)] {
+            if value.is_some_and(|v| !v.is_finite()) {
+                return Err(format!("{} must be a finite number", name));
+            }
+        }
+        if let (Some(target), Some(ruin)) = (target_equity, ruin_equity) {
+            if ruin >= target {
+                return Err(format!(
+                    "ruin_equity ({}) must be less than target_equity ({})",
+                    ruin, target
+                ));
+            }
+        }
+        Ok(Self { max_bars, target_equity, ruin_equity })
+    }
+
+    /// 是否配置了任一终止条件
+    pub(crate) fn is_active(&self) -> bool {
+        self.max_bars.is_some() || self.target_equity.is_some() || self.ruin_equity.is_some()
+    }
+
+    /// 处理完一根 bar 后检查终止条件
+    ///
+    /// `bars_done` 为已处理的 bar 数（含预热），`equity` 为该 bar 处理后的净值（预热期为 `None`）。
+    pub(crate) fn check(&self, bars_done: usize, equity: Option<f64>) -> Option<StopReason> {
+        if let Some(equity) = equity {
+            if self.ruin_equity.is_some_and(|ruin| equity <= ruin) {
+                return Some(StopReason::Ruin);
+            }
+            if self.target_equity.is_some_and(|target| equity >= target) {
+                return Some(StopReason::TargetEquity);
+            }
+        }
+        if self.max_bars.is_some_and(|max| bars_done >= max) {
+            return Some(StopReason::MaxBars);
+        }
+        None
+    }
+
+    /// 在结果字典中记录终止原因（`stop_reason`）；未配置终止条件时不写入
+    pub(crate) fn mark_result(
+        &self,
+        py: Python<'_>,
+        result: &PyObject,
+        reason: Option<StopReason>,
+        cancelled: bool,
+    ) -> PyResult<()> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        let reason = match reason {
+            Some(reason) => reason.as_str(),
+            None if cancelled => "cancelled",
+            None => "completed",
+        };
+        result.downcast_bound::<PyDict>(py)?.set_item("stop_reason", reason)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+//! 组合敞口限制模块
+//!
+//! 策略的下单数量通常按信号算出，遇到连续加仓、多标的同时开仓或做空时，很容易在不知不觉中把
+//! 组合杠杆推到不现实的水平，回测收益也随之失真。`BacktestConfig` 的 `max_gross_exposure`（总敞口）
+//! 和 `max_net_exposure`（净敞口）以净值的倍数限制组合敞口，引擎在每笔订单成交前检查，
+//! 超限的订单按 `exposure_policy` 截断或拒绝，并通过 `on_order` 告知策略。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 成交前按最新价格计算组合净值（现金 + 各持仓市值）和成交后的敞口：
+//!    总敞口 = Σ|持仓市值|，净敞口 = |Σ持仓市值|（多头为正、空头为负）
+//! 2. 成交后两项敞口都不超过 `上限 × 净值` 时照常成交
+//! 3. 否则求出不超限的最大成交数量：`"clip"`（默认）按该数量成交，`"reject"` 整笔拒绝；
+//!    原数量为整数时截断后的数量向下取整，截断到 0 时同样拒绝
+//! 4. 已经超限（例如价格变动导致）时，不会增加敞口的订单（减仓、对冲）总是允许成交
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! cfg = BacktestConfig("2020-01-01", "2024-12-31", 1_000_000, max_gross_exposure=1.5, max_net_exposure=1.0)
+//!
+//! class MyStrategy(Strategy):
+//!     def on_order(self, event):
+//!         if event.get("reason") == "exposure_limit":
+//!             # event: "clipped"（size 为实际成交数量，requested_size 为原数量）或 "rejected"
+//!             print(event["message"])
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 敞口按撮合时的最新价格估值，不考虑手续费和滑点
+//! - 适用于 `run()`（含 `PaperTrader`、`Replayer`）和 `run_multi()`；单资产回测只有一个持仓，
+//!   总敞口与净敞口相同
+//! - `run_multi()` 的订单按提交顺序依次检查，前面的成交会计入后面订单的敞口
+
+use crate::OrderSide;
+
+/// 超限订单的处理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ExposurePolicy {
+    /// 截断到不超限的最大数量
+    #[default]
+    Clip,
+    /// 整笔拒绝
+    Reject,
+}
+
+impl ExposurePolicy {
+    pub(crate) fn parse(policy: &str) -> Result<Self, String> {
+        match policy.to_lowercase().as_str() {
+            "clip" => Ok(ExposurePolicy::Clip),
+            "reject" => Ok(ExposurePolicy::Reject),
+            other => Err(format!("Unsupported exposure_policy '{}': expected 'clip' or 'reject'", other)),
+        }
+    }
+}
+
+/// 敞口类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExposureKind {
+    /// 总敞口：Σ|持仓市值|
+    Gross,
+    /// 净敞口：|Σ持仓市值|
+    Net,
+}
+
+impl ExposureKind {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ExposureKind::Gross => "gross",
+            ExposureKind::Net => "net",
+        }
+    }
+}
+
+/// 订单触及的敞口限制（用于订单事件）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ExposureBreach {
+    /// 触及的限制
+    pub(crate) kind: ExposureKind,
+    /// 限制（净值的倍数）
+    pub(crate) limit: f64,
+    /// 原订单数量
+    pub(crate) requested: f64,
+    /// 允许成交的数量（拒绝时为 0）
+    pub(crate) allowed: f64,
+}
+
+/// 检查结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ExposureCheck {
+    /// 不超限，按原数量成交
+    Pass,
+    /// 截断为 `breach.allowed` 成交
+    Clip(ExposureBreach),
+    /// 拒绝
+    Reject(ExposureBreach),
+}
+
+/// 下单前的组合状态（按最新价格估值）
+pub(crate) struct Book {
+    /// 组合净值
+    pub(crate) equity: f64,
+    /// 订单标的当前持仓
+    pub(crate) position: f64,
+    /// 其他标的的 Σ|市值|
+    pub(crate) other_gross: f64,
+    /// 其他标的的 Σ市值
+    pub(crate) other_net: f64,
+}
+
+/// 组合敞口限制（默认不限制）
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ExposureLimits {
+    gross: Option<f64>,
+    net: Option<f64>,
+    policy: ExposurePolicy,
+}
+
+impl ExposureLimits {
+    /// 由配置构造；限制必须是正数
+    pub(crate) fn from_config(gross: Option<f64>, net: Option<f64>, policy: &str) -> Result<Self, String> {
+        for (name, limit) in [("max_gross_exposure", gross), ("max_net_exposure", net)] {
+            if let Some(limit) = limit {
+                if !(limit.is_finite() && limit > 0.0) {
+                    return Err(format!("{} must be a positive number, got {}", name, limit));
+                }
+            }
+        }
+        Ok(Self { gross, net, policy: ExposurePolicy::parse(policy)? })
+    }
+
+    /// 是否配置了任一限制
+    pub(crate) fn is_active(&self) -> bool {
+        self.gross.is_some() || self.net.is_some()
+    }
+
+    /// 检查以 `price` 成交 `size` 后的组合敞口
+    pub(crate) fn check(&self, side: OrderSide, size: f64, price: f64, book: &Book) -> ExposureCheck {
+        if !(price > 0.0 && size > 0.0) {
+            return ExposureCheck::Pass;
+        }
+        let step = match side { OrderSide::Buy => price, OrderSide::Sell => -price };
+        let value = book.position * price;
+
+        // 每项限制下敞口都形如 c + |a + step·t|（t 为成交数量），求不超限的最大 t
+        let mut allowed = size;
+        let mut binding = None;
+        for (kind, limit, c, a) in [
+            (ExposureKind::Gross, self.gross, book.other_gross, value),
+            (ExposureKind::Net, self.net, 0.0, book.other_net + value),
+        ] {
+            let Some(limit) = limit else { continue };
+            let exposure = |t: f64| c + (a + step * t).abs();
+            let cap = limit * book.equity;
+            if exposure(size) <= cap + 1e-9 * cap.abs().max(1.0) {
+                continue;
+            }
+            // 已经超限时以当前敞口为上限：不增加敞口的部分仍可成交
+            let room = cap.max(exposure(0.0)) - c;
+            let upper = ((room - a) / step).max((-room - a) / step).clamp(0.0, size);
+            if upper < allowed {
+                allowed = upper;
+                binding = Some((kind, limit));
+            }
+        }
+
+        let Some((kind, limit)) = binding.filter(|_| allowed < size - 1e-9) else { return ExposureCheck::Pass };
+        if size.fract() == 0.0 {
+            allowed = (allowed + 1e-9).floor();
+        }
+        let breach = ExposureBreach { kind, limit, requested: size, allowed };
+        if self.policy == ExposurePolicy::Reject || allowed <= 1e-9 {
+            ExposureCheck::Reject(ExposureBreach { allowed: 0.0, ..breach })
+        } else {
+            ExposureCheck::Clip(breach)
+        }
+    }
+}
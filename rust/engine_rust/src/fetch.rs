@@ -0,0 +1,363 @@
+//! 远程行情获取模块
+//!
+//! 本地库的数据以前需要另写 Python 脚本下载、整理，再调用 `save_klines()` 写入。
+//! 这个模块把"下载 → 写入 DuckDB"合并为一次调用 `fetch_klines()`，数据源可插拔：
+//!
+//! | `source` | 说明 |
+//! |----------|------|
+//! | `"binance"` | 币安现货 REST 接口 `/api/v3/klines`，按 1000 根一页自动翻页 |
+//! | `"csv"` | 从 `url` 下载 CSV 文件（表头 `datetime,open,high,low,close,volume`），由 DuckDB 解析导入 |
+//! | 可调用对象 | `source(symbol, period, start, end)` 返回 K 线字典列表（格式同 `save_klines()`），接入任意数据源 |
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 下载期间释放 GIL；HTTP 请求使用 30 秒超时
+//! 2. 下载结果按时间排序去重后，在一个事务中经临时表写入周期表，与已有数据重复的 K 线跳过
+//! 3. 写入后刷新依赖该周期的物化周期（同 `save_klines()`）
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import fetch_klines
+//!
+//! # 币安 BTCUSDT 1 小时线
+//! fetch_klines("data/backtest.db", "binance", "BTCUSDT", "1h", start="2024-01-01", end="2024-06-30")
+//!
+//! # 公开的 CSV 文件
+//! fetch_klines("data/backtest.db", "csv", "SPY", "1d", url="https://example.com/spy_daily.csv")
+//!
+//! # 自定义数据源
+//! def my_source(symbol, period, start, end):
+//!     return [{"datetime": ..., "open": ..., "high": ..., "low": ..., "close": ..., "volume": ...}, ...]
+//! fetch_klines("data/backtest.db", my_source, "600000.SH", "1d", start="2024-01-01", tz="Asia/Shanghai")
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 币安返回的时间为 UTC（K 线开盘时间），`tz` 对 `"binance"` 无效
+//! - 币安接口不传 `start` 时只返回最近 1000 根 K 线
+//! - `"csv"` 不支持 `start` / `end`，整个文件写入
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use duckdb::Connection;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+
+use crate::database::{
+    datetime_to_epoch_ms, ensure_period_table, format_epoch_ms, import_klines_from_source, insert_kline_rows,
+    kline_bar_from_pydict, open_connection, parse_datetime, parse_timezone, to_utc, KlineBar,
+};
+
+/// 币安 REST 接口默认地址
+const BINANCE_DEFAULT_URL: &str = "https://api.binance.com";
+/// 币安 `/api/v3/klines` 单页最大条数
+const BINANCE_PAGE_LIMIT: usize = 1000;
+/// 币安支持的 K 线周期
+const BINANCE_INTERVALS: [&str; 15] =
+    ["1m", "3m", "5m", "15m", "30m", "1h", "2h", "4h", "6h", "8h", "12h", "1d", "3d", "1w", "1M"];
+/// HTTP 请求超时
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+fn http_agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).user_agent("pyrust-bt").build()
+}
+
+/// 发送 GET 请求；非 2xx 状态码时错误信息中带上响应内容
+fn http_get(agent: &ureq::Agent, url: &str) -> Result<ureq::Response, String> {
+    match agent.get(url).call() {
+        Ok(response) => Ok(response),
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            Err(format!("HTTP {} from {}: {}", code, url, body.trim()))
+        }
+        Err(e) => Err(format!("Request failed: {}", e)),
+    }
+}
+
+/// 币安 K 线中的数值字段（价格、成交量为字符串，时间为整数）
+fn json_f64(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::String(s) => s.parse().ok(),
+        other => other.as_f64(),
+    }
+}
+
+/// 从币安分页下载 K 线（`start_ms` / `end_ms` 为开盘时间的 epoch 毫秒，包含两端）
+fn fetch_binance(
+    base_url: &str,
+    symbol: &str,
+    period: &str,
+    start_ms: Option<i64>,
+    end_ms: Option<i64>,
+) -> Result<Vec<KlineBar>, String> {
+    let agent = http_agent();
+    let mut bars = Vec::new();
+    let mut cursor = start_ms;
+    loop {
+        let mut url = format!(
+            "{}/api/v3/klines?symbol={}&interval={}&limit={}",
+            base_url.trim_end_matches('/'),
+            symbol,
+            period,
+            BINANCE_PAGE_LIMIT
+        );
+        if let Some(start) = cursor {
+            url.push_str(&format!("&startTime={}", start));
+        }
+        if let Some(end) = end_ms {
+            url.push_str(&format!("&endTime={}", end));
+        }
+        let body = http_get(&agent, &url)?
+            .into_string()
+            .map_err(|e| format!("Failed to read response from {}: {}", url, e))?;
+        let rows: Vec<Vec<serde_json::Value>> =
+            serde_json::from_str(&body).map_err(|e| format!("Unexpected response from {}: {}", url, e))?;
+
+        let page_len = rows.len();
+        let mut last_open = None;
+        for row in rows {
+            let parsed = (|| {
+                let open_ms = row.first()?.as_i64()?;
+                Some((
+                    open_ms,
+                    KlineBar {
+                        datetime: format_epoch_ms(open_ms)?,
+                        open: json_f64(row.get(1)?)?,
+                        high: json_f64(row.get(2)?)?,
+                        low: json_f64(row.get(3)?)?,
+                        close: json_f64(row.get(4)?)?,
+                        volume: json_f64(row.get(5)?)?,
+                        symbol: symbol.to_string(),
+                    },
+                ))
+            })();
+            let (open_ms, bar) = parsed.ok_or_else(|| format!("Malformed kline row from {}: {:?}", url, row))?;
+            bars.push(bar);
+            last_open = Some(open_ms);
+        }
+        // 不足一页说明已经取完
+        match last_open {
+            Some(open_ms) if page_len == BINANCE_PAGE_LIMIT => cursor = Some(open_ms + 1),
+            _ => break,
+        }
+    }
+    Ok(bars)
+}
+
+/// 下载结果
+enum Downloaded {
+    /// 已解析的 K 线（币安、自定义数据源）
+    Bars(Vec<KlineBar>),
+    /// 下载到本地的 CSV 临时文件
+    CsvFile(PathBuf),
+}
+
+/// 下载文件到本地路径，返回字节数
+fn download_to_file(url: &str, path: &Path) -> Result<u64, String> {
+    let response = http_get(&http_agent(), url)?;
+    let mut file = std::fs::File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    std::io::copy(&mut response.into_reader(), &mut file)
+        .map_err(|e| format!("Failed to download {}: {}", url, e))
+}
+
+/// 该标的在周期表中的行数
+fn symbol_row_count(conn: &Connection, table_name: &str, symbol: &str) -> PyResult<usize> {
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM {} WHERE symbol = ?", table_name),
+        duckdb::params![symbol],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n as usize)
+    .map_err(|e| runtime_error(format!("Failed to count rows in {}: {}", table_name, e)))
+}
+
+/// 在一个事务中写入下载的 K 线，并刷新依赖该周期的物化周期
+fn write_bars(conn: &Connection, table_name: &str, symbol: &str, period: &str, mut bars: Vec<KlineBar>, replace: bool) -> PyResult<()> {
+    bars.sort_by_cached_key(|b| parse_datetime(&b.datetime));
+    bars.dedup_by(|a, b| a.datetime == b.datetime);
+
+    conn.execute("BEGIN TRANSACTION", [])
+        .map_err(|e| runtime_error(format!("Failed to begin transaction: {}", e)))?;
+    if replace {
+        conn.execute(&format!("DELETE FROM {} WHERE symbol = ?", table_name), duckdb::params![symbol])
+            .map_err(|e| runtime_error(format!("Failed to delete old data: {}", e)))?;
+    }
+    insert_kline_rows(conn, table_name, &bars)?;
+    conn.execute("COMMIT", []).map_err(|e| runtime_error(format!("Failed to commit transaction: {}", e)))?;
+
+    crate::materialize::refresh_dependents(conn, symbol, period, || {
+        Ok(match bars.first().and_then(|b| parse_datetime(&b.datetime)) {
+            Some(dt) if !replace => crate::materialize::RefreshFrom::Since(dt),
+            _ => crate::materialize::RefreshFrom::Full,
+        })
+    })
+}
+
+/// 从远程数据源下载 K 线并写入 DuckDB
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import fetch_klines
+///
+/// report = fetch_klines("data/backtest.db", "binance", "ETHUSDT", "1d", start="2020-01-01")
+/// print(report)  # {"fetched": 1650, "inserted": 1650}
+///
+/// # 每天增量更新：重叠部分自动跳过
+/// fetch_klines("data/backtest.db", "binance", "ETHUSDT", "1d", start="2024-06-01")
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `source`: 数据源：`"binance"`、`"csv"`，或可调用对象 `source(symbol, period, start, end)`，
+///   返回 K 线字典列表（格式同 `save_klines()`）
+/// - `symbol`: 交易标的代码（币安为交易对，如 `"BTCUSDT"`），写入库中时使用同一代码
+/// - `period`: 周期字符串（如 "1m", "1d"）；币安只支持其接口提供的周期
+/// - `start` / `end`: 可选的时间范围（包含两端），格式 "YYYY-MM-DD" 或 "YYYY-MM-DD HH:MM:SS"；
+///   币安按 UTC 解释，可调用对象原样收到这两个参数
+/// - `url`: `"csv"` 必填，为 CSV 文件地址；`"binance"` 可选，替换默认的接口地址（如币安美国站）
+/// - `replace`: 是否先删除该标的在该周期的旧数据，默认 `False`
+/// - `tz`: 可选的时区（同 `save_klines()`），适用于 `"csv"` 和可调用对象
+///
+/// # 返回值
+///
+/// 字典：`fetched`（下载的 K 线条数）、`inserted`（去重后实际写入的条数）
+///
+/// # 注意事项
+///
+/// - 未知的数据源、币安不支持的周期、`"csv"` 缺少 `url` 或传入了 `start`/`end` 时返回 `ValueError`
+/// - 网络错误或接口返回错误时返回 `RuntimeError`，此时不会写入任何数据
+#[pyfunction]
+#[pyo3(signature = (db_path, source, symbol, period, start=None, end=None, url=None, replace=false, tz=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn fetch_klines(
+    py: Python<'_>,
+    db_path: String,
+    source: &Bound<'_, PyAny>,
+    symbol: String,
+    period: String,
+    start: Option<String>,
+    end: Option<String>,
+    url: Option<String>,
+    replace: bool,
+    tz: Option<String>,
+) -> PyResult<PyObject> {
+    let tz_name = tz;
+    let tz = parse_timezone(tz_name.as_deref())?;
+
+    // 先下载（不占用数据库连接），CSV 落到临时文件，其他数据源得到 K 线列表
+    let downloaded = if source.is_instance_of::<PyString>() {
+        match source.extract::<String>()?.to_lowercase().as_str() {
+            "binance" => {
+                if !BINANCE_INTERVALS.contains(&period.as_str()) {
+                    return Err(value_error(format!(
+                        "Period '{}' is not supported by Binance: expected one of {}",
+                        period,
+                        BINANCE_INTERVALS.join(", ")
+                    )));
+                }
+                if tz.is_some() {
+                    return Err(value_error("tz is not supported for Binance (timestamps are UTC)".to_string()));
+                }
+                let epoch_ms = |bound: &Option<String>| -> PyResult<Option<i64>> {
+                    bound
+                        .as_deref()
+                        .map(|s| datetime_to_epoch_ms(s).ok_or_else(|| value_error(format!("Invalid datetime: {}", s))))
+                        .transpose()
+                };
+                let (start_ms, end_ms) = (epoch_ms(&start)?, epoch_ms(&end)?);
+                let base_url = url.as_deref().unwrap_or(BINANCE_DEFAULT_URL);
+                let bars = py
+                    .allow_threads(|| fetch_binance(base_url, &symbol, &period, start_ms, end_ms))
+                    .map_err(runtime_error)?;
+                Downloaded::Bars(bars)
+            }
+            "csv" => {
+                let Some(url) = url else {
+                    return Err(value_error("source='csv' requires url".to_string()));
+                };
+                if start.is_some() || end.is_some() {
+                    return Err(value_error("start/end are not supported for source='csv'".to_string()));
+                }
+                let path = std::env::temp_dir().join(format!(
+                    "pyrust_bt_fetch_{}_{}.csv",
+                    std::process::id(),
+                    symbol.replace(['/', '\\'], "_")
+                ));
+                if let Err(e) = py.allow_threads(|| download_to_file(&url, &path)) {
+                    let _ = std::fs::remove_file(&path);
+                    return Err(runtime_error(e));
+                }
+                Downloaded::CsvFile(path)
+            }
+            other => {
+                return Err(value_error(format!(
+                    "Unknown source '{}': expected 'binance', 'csv' or a callable",
+                    other
+                )))
+            }
+        }
+    } else if source.is_callable() {
+        let result = source.call1((&symbol, &period, start.as_deref(), end.as_deref()))?;
+        let mut bars = Vec::new();
+        for item in result.iter()? {
+            let item = item?;
+            let mut bar = kline_bar_from_pydict(item.downcast::<PyDict>()?.as_gil_ref())?;
+            bar.symbol = symbol.clone();
+            if let Some(dt) = to_utc(&bar.datetime, tz).map_err(value_error)? {
+                bar.datetime = dt.format("%Y-%m-%d %H:%M:%S%.f").to_string();
+            }
+            bars.push(bar);
+        }
+        Downloaded::Bars(bars)
+    } else {
+        return Err(value_error("source must be 'binance', 'csv' or a callable".to_string()));
+    };
+
+    let mut conn = open_connection(&db_path)?;
+    let table_name = ensure_period_table(&conn, &period)?;
+    let before = if replace { 0 } else { symbol_row_count(&conn, &table_name, &symbol)? };
+    let fetched = match downloaded {
+        Downloaded::Bars(bars) => {
+            let fetched = bars.len();
+            write_bars(&conn, &table_name, &symbol, &period, bars, replace)?;
+            fetched
+        }
+        Downloaded::CsvFile(path) => {
+            let source = format!(
+                "read_csv('{}', header=true, auto_detect=true)",
+                path.to_string_lossy().replace('\'', "''")
+            );
+            let counted = conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", source), [], |row| row.get::<_, i64>(0))
+                .map(|n| n as usize)
+                .map_err(|e| runtime_error(format!("Failed to read downloaded CSV: {}", e)));
+            // 导入时会重新打开连接，先释放当前连接
+            drop(conn);
+            let imported = counted.and_then(|fetched| {
+                import_klines_from_source(py, &db_path, &source, "CSV", &symbol, &period, replace, None, tz_name.as_deref(), None)
+                    .map(|_| fetched)
+            });
+            let _ = std::fs::remove_file(&path);
+            conn = open_connection(&db_path)?;
+            imported?
+        }
+    };
+
+    let after = symbol_row_count(&conn, &table_name, &symbol)?;
+    let report = PyDict::new_bound(py);
+    report.set_item("fetched", fetched)?;
+    report.set_item("inserted", after.saturating_sub(before))?;
+    Ok(report.into())
+}
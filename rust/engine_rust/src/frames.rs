@@ -0,0 +1,377 @@
+//! 回测结果表格化与 JSON 导出模块
+//!
+//! 回测结果中的 `equity_curve` / `trades` 是字典列表，`pd.DataFrame(result["trades"])` 需要
+//! 逐个字典、逐个键地推断列，百万级净值点时往往比回测本身还慢。这个模块让 `run()` / `run_multi()`
+//! 返回的结果（`BacktestResult`，`dict` 的子类，原有的取值方式不变）额外保留 Rust 侧的净值与成交数据，
+//! 需要表格时按列一次性构建 Arrow 表，再转换为 pandas / polars DataFrame；需要存档或发给 Web 看板时，
+//! `to_json()` 用 serde_json 直接输出固定结构的 JSON 文本。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. abi3 模式下 Rust 类不能继承 `dict`，因此 `BacktestResult` 是模块初始化时定义的一个很小的 Python 类，
+//!    两个方法直接转调本模块的 Rust 函数
+//! 2. 引擎构建结果时把净值曲线、成交记录的 Rust 数据放进 `ResultFrames`，作为结果对象的属性（不是字典的键，
+//!    不影响 `json.dumps` 等对结果字典的处理）
+//! 3. `equity_curve_df()` / `trades_df()` 直接从 Rust 数据逐列生成 Python 列表（不创建中间字典），
+//!    交给 `pyarrow.table()` 构建列式表
+//! 4. 按 `backend` 返回 pandas DataFrame（`Table.to_pandas()`）、polars DataFrame（`polars.from_arrow()`）
+//!    或 Arrow 表本身；未安装 pyarrow 时改用 `pandas.DataFrame` / `polars.DataFrame` 按列构造
+//! 5. `to_json()` 同样优先使用 Rust 侧数据序列化净值与成交，其余字段（`stats`、`positions` 等）按 JSON 类型转换
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! result = engine.run(MyStrategy(), bars)
+//! curve = result.equity_curve_df()                  # pandas: datetime, equity（run_multi 另有 cash）
+//! trades = result.trades_df(backend="polars")       # polars: order_id, side, price, size, datetime, symbol, ...
+//! table = result.trades_df(backend="arrow")         # pyarrow.Table
+//! text = result.to_json()                           # 存档 / 发给看板
+//! ```
+//!
+//! ## JSON 结构
+//!
+//! 顶层键按字母排序，以下键始终存在（数据缺失时为 `null` 或空列表），结果中的其他键（`positions`、
+//! `buy_and_hold`、`cancelled` 等）按原样附加：
+//!
+//! ```json
+//! {"avg_cost": 0.0, "cash": 100523.5, "equity": 100523.5, "position": 0.0, "realized_pnl": 523.5,
+//!  "equity_curve": [{"datetime": "2024-01-02", "equity": 100000.0}, ...],
+//!  "trades": [{"order_id": 1, "side": "BUY", "price": 10.01, "size": 100.0, "datetime": "2024-01-02",
+//!              "symbol": "DEFAULT", "commission": 0.5, "slippage": 0.2, "position": 100.0, "cash": 98998.5}, ...],
+//!  "stats": {"total_return": 0.0052, "sharpe": 1.3, ...},
+//!  "schema_version": 1}
+//! ```
+//!
+//! # 注意事项
+//!
+//! - `datetime` 列保留引擎中的原始字符串，需要时间类型时再用 `pd.to_datetime()` 等转换
+//! - 经过 pickle、从 `load_backtest_result()` 读出的结果没有 Rust 侧数据，改为从结果中的列表按列提取
+//! - 使用 `results_db` 的回测结果中没有净值与成交列表，返回的表为空（数据在库中）
+//! - JSON 中的 `NaN` / `inf` 写为 `null`（JSON 不支持这些值）；无法转换为 JSON 的值（如自定义对象）返回 `ValueError`
+
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{PyDict, PyList, PyType};
+
+use serde_json::{Map, Value};
+
+use crate::positions::POSITION_COLUMNS;
+use crate::TradeRecord;
+
+/// 净值表的列（与结果中 `equity_curve` 元素的键相同）
+const EQUITY_COLUMNS: [&str; 2] = ["datetime", "equity"];
+/// 带逐行现金的净值表的列（`run_multi()` 的结果）
+const EQUITY_CASH_COLUMNS: [&str; 3] = ["datetime", "equity", "cash"];
+/// 成交表的列（与结果中 `trades` 元素的键相同）
+const TRADE_COLUMNS: [&str; 10] =
+    ["order_id", "side", "price", "size", "datetime", "symbol", "commission", "slippage", "position", "cash"];
+/// 结果对象上保存 Rust 侧数据的属性名
+const FRAMES_ATTR: &str = "_frames";
+/// `to_json()` 输出结构的版本号（字段含义变化时递增）
+const JSON_SCHEMA_VERSION: u32 = 1;
+/// `to_json()` 中始终存在的键
+const JSON_REQUIRED_KEYS: [&str; 8] =
+    ["cash", "position", "avg_cost", "equity", "realized_pnl", "stats", "equity_curve", "trades"];
+
+/// `BacktestResult` 的定义：方法转调 Rust 函数；pickle 时只保存字典内容
+const RESULT_CLASS_CODE: &str = r#"
+class BacktestResult(dict):
+    """Backtest result dict with columnar DataFrame helpers."""
+
+    def equity_curve_df(self, backend="pandas"):
+        """Equity curve as a pandas/polars DataFrame or pyarrow Table (columns: datetime, equity)."""
+        return _equity_curve_df(self, backend)
+
+    def trades_df(self, backend="pandas"):
+        """Trades as a pandas/polars DataFrame or pyarrow Table (one column per trade field)."""
+        return _trades_df(self, backend)
+
+    def positions_df(self, backend="pandas"):
+        """Positions history (needs record_positions=True) as a DataFrame: datetime, symbol, position, price, market_value."""
+        return _positions_df(self, backend)
+
+    def to_json(self, indent=None):
+        """Whole result (equity curve, trades, stats, ...) as a JSON string with a stable schema."""
+        return _result_to_json(self, indent)
+
+    def __reduce__(self):
+        return (BacktestResult, (dict(self),))
+"#;
+
+static RESULT_CLASS: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+
+/// 结果对应的 Rust 侧净值与成交数据
+#[pyclass]
+struct ResultFrames {
+    equity_curve: Vec<(Option<String>, f64)>,
+    /// 与净值曲线逐行对应的现金（仅 `run_multi()` 的结果）
+    cash: Option<Vec<f64>>,
+    trades: Vec<TradeRecord>,
+}
+
+/// 定义 `BacktestResult` 并加入模块（在模块初始化时调用）
+pub(crate) fn register(py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let code = PyModule::from_code_bound(py, RESULT_CLASS_CODE, "engine_rust/frames.py", "engine_rust.frames")?;
+    code.add_function(wrap_pyfunction!(_equity_curve_df, &code)?)?;
+    code.add_function(wrap_pyfunction!(_trades_df, &code)?)?;
+    code.add_function(wrap_pyfunction!(_positions_df, &code)?)?;
+    code.add_function(wrap_pyfunction!(_result_to_json, &code)?)?;
+    let class = code.getattr("BacktestResult")?.downcast_into::<PyType>()?;
+    class.setattr("__module__", "engine_rust")?;
+    m.add("BacktestResult", &class)?;
+    let _ = RESULT_CLASS.set(py, class.unbind());
+    Ok(())
+}
+
+/// 创建结果对象（此时字典为空），引擎随后像普通字典一样写入各字段
+pub(crate) fn new_result(
+    py: Python<'_>,
+    equity_curve: Vec<(Option<String>, f64)>,
+    trades: Vec<TradeRecord>,
+) -> PyResult<Bound<'_, PyDict>> {
+    let class = RESULT_CLASS
+        .get(py)
+        .ok_or_else(|| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("BacktestResult is not initialized"))?;
+    let result = class.bind(py).call0()?;
+    result.setattr(FRAMES_ATTR, Py::new(py, ResultFrames { equity_curve, cash: None, trades })?)?;
+    Ok(result.downcast_into::<PyDict>()?)
+}
+
+/// 记录与净值曲线逐行对应的现金（净值表与 JSON 中的净值行随之多出 `cash`）
+pub(crate) fn set_cash_curve(result: &Bound<'_, PyDict>, cash: Vec<f64>) -> PyResult<()> {
+    if let Some(frames) = frames_of(result) {
+        frames.borrow_mut().cash = Some(cash);
+    }
+    Ok(())
+}
+
+/// 结果对象上的 Rust 侧数据（经过 pickle 等途径得到的结果没有）
+fn frames_of<'py>(result: &Bound<'py, PyDict>) -> Option<Bound<'py, ResultFrames>> {
+    result.getattr(FRAMES_ATTR).ok()?.downcast_into::<ResultFrames>().ok()
+}
+
+/// 净值曲线表（列：`datetime`、`equity`；`run_multi()` 的结果另有 `cash`）
+#[pyfunction]
+fn _equity_curve_df(py: Python<'_>, result: &Bound<'_, PyDict>, backend: &str) -> PyResult<PyObject> {
+    match frames_of(result) {
+        Some(frames) => {
+            let frames = frames.borrow();
+            let curve = &frames.equity_curve;
+            let mut columns = vec![
+                PyList::new_bound(py, curve.iter().map(|(dt, _)| dt.as_deref())),
+                PyList::new_bound(py, curve.iter().map(|(_, eq)| *eq)),
+            ];
+            match &frames.cash {
+                Some(cash) => {
+                    columns.push(PyList::new_bound(py, cash));
+                    to_frame(py, &EQUITY_CASH_COLUMNS, columns, backend)
+                }
+                None => to_frame(py, &EQUITY_COLUMNS, columns, backend),
+            }
+        }
+        None => {
+            // 字典中的净值行带 cash 时保留该列
+            let has_cash = match result.get_item("equity_curve")?.filter(|v| !v.is_none()) {
+                Some(rows) => rows.iter()?.next().transpose()?.map_or(Ok(false), |row| {
+                    row.downcast::<PyDict>().map_or(Ok(false), |row| row.contains("cash"))
+                })?,
+                None => false,
+            };
+            let names: &[&str] = if has_cash { &EQUITY_CASH_COLUMNS } else { &EQUITY_COLUMNS };
+            to_frame(py, names, columns_from_list(result, "equity_curve", names)?, backend)
+        }
+    }
+}
+
+/// 成交记录表（列与 `trades` 元素的键相同）
+#[pyfunction]
+fn _trades_df(py: Python<'_>, result: &Bound<'_, PyDict>, backend: &str) -> PyResult<PyObject> {
+    let columns = match frames_of(result) {
+        Some(frames) => {
+            let frames = frames.borrow();
+            let t = &frames.trades;
+            vec![
+                PyList::new_bound(py, t.iter().map(|r| r.order_id)),
+                PyList::new_bound(py, t.iter().map(|r| r.side.as_str())),
+                PyList::new_bound(py, t.iter().map(|r| r.price)),
+                PyList::new_bound(py, t.iter().map(|r| r.size)),
+                PyList::new_bound(py, t.iter().map(|r| r.datetime.as_deref())),
+                PyList::new_bound(py, t.iter().map(|r| r.symbol.as_str())),
+                PyList::new_bound(py, t.iter().map(|r| r.commission)),
+                PyList::new_bound(py, t.iter().map(|r| r.slippage)),
+                PyList::new_bound(py, t.iter().map(|r| r.position)),
+                PyList::new_bound(py, t.iter().map(|r| r.cash)),
+            ]
+        }
+        None => columns_from_list(result, "trades", &TRADE_COLUMNS)?,
+    };
+    to_frame(py, &TRADE_COLUMNS, columns, backend)
+}
+
+/// 持仓历史表（列与 `positions_history` 元素的键相同；没有记录持仓时为空表）
+#[pyfunction]
+fn _positions_df(py: Python<'_>, result: &Bound<'_, PyDict>, backend: &str) -> PyResult<PyObject> {
+    let columns = columns_from_list(result, "positions_history", &POSITION_COLUMNS)?;
+    to_frame(py, &POSITION_COLUMNS, columns, backend)
+}
+
+/// 从结果字典中的列表按列提取（没有 Rust 侧数据时使用；缺少的键为 `None`）
+fn columns_from_list<'py>(
+    result: &Bound<'py, PyDict>,
+    key: &str,
+    columns: &[&str],
+) -> PyResult<Vec<Bound<'py, PyList>>> {
+    let out: Vec<Bound<'py, PyList>> = columns.iter().map(|_| PyList::empty_bound(result.py())).collect();
+    if let Some(rows) = result.get_item(key)?.filter(|v| !v.is_none()) {
+        for row in rows.iter()? {
+            let row = row?;
+            let row = row.downcast::<PyDict>()?;
+            for (column, values) in columns.iter().zip(&out) {
+                values.append(row.get_item(column)?)?;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// 按列构建 Arrow 表并转换为指定的 DataFrame
+fn to_frame(py: Python<'_>, names: &[&str], columns: Vec<Bound<'_, PyList>>, backend: &str) -> PyResult<PyObject> {
+    let backend = backend.trim().to_ascii_lowercase();
+    if !matches!(backend.as_str(), "pandas" | "polars" | "arrow") {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Unsupported backend: {} (expected pandas/polars/arrow)",
+            backend
+        )));
+    }
+    let data = PyDict::new_bound(py);
+    for (name, values) in names.iter().zip(columns) {
+        data.set_item(name, values)?;
+    }
+
+    let table = match py.import_bound("pyarrow") {
+        Ok(pa) => Some(pa.call_method1("table", (&data,))?),
+        Err(err) if backend == "arrow" => return Err(err),
+        Err(_) => None,
+    };
+    let frame = match (backend.as_str(), table) {
+        ("arrow", Some(table)) => table,
+        ("pandas", Some(table)) => table.call_method0("to_pandas")?,
+        ("pandas", None) => py.import_bound("pandas")?.call_method1("DataFrame", (&data,))?,
+        (_, Some(table)) => py.import_bound("polars")?.call_method1("from_arrow", (table,))?,
+        (_, None) => py.import_bound("polars")?.call_method1("DataFrame", (&data,))?,
+    };
+    Ok(frame.into())
+}
+
+/// 整个结果的 JSON 文本（结构见模块文档）；`indent` 为缩进空格数，`None` 时输出紧凑格式
+#[pyfunction]
+fn _result_to_json(result: &Bound<'_, PyDict>, indent: Option<usize>) -> PyResult<String> {
+    let mut root = Map::new();
+    for key in JSON_REQUIRED_KEYS {
+        root.insert(key.to_string(), Value::Null);
+    }
+    root.insert("equity_curve".to_string(), Value::Array(Vec::new()));
+    root.insert("trades".to_string(), Value::Array(Vec::new()));
+
+    // 有 Rust 侧数据时直接序列化（与字典中的列表内容相同），字典中的这两个列表不再逐项转换
+    let frames = frames_of(result);
+    let frames = frames.as_ref().map(|f| f.borrow());
+    if let Some(frames) = &frames {
+        if !frames.equity_curve.is_empty() {
+            let curve = frames
+                .equity_curve
+                .iter()
+                .enumerate()
+                .map(|(i, (dt, eq))| match frames.cash.as_ref().and_then(|c| c.get(i)) {
+                    Some(cash) => serde_json::json!({"datetime": dt, "equity": eq, "cash": cash}),
+                    None => serde_json::json!({"datetime": dt, "equity": eq}),
+                })
+                .collect();
+            root.insert("equity_curve".to_string(), Value::Array(curve));
+        }
+        if !frames.trades.is_empty() {
+            root.insert("trades".to_string(), serde_json::to_value(&frames.trades).map_err(json_error)?);
+        }
+    }
+    for (key, value) in result.iter() {
+        let key: String = key.extract()?;
+        // 已由 Rust 数据填充，或为 None（保持空列表）的净值与成交
+        let skip = match (key.as_str(), &frames) {
+            ("equity_curve", Some(f)) if !f.equity_curve.is_empty() => true,
+            ("trades", Some(f)) if !f.trades.is_empty() => true,
+            ("equity_curve" | "trades", _) => value.is_none(),
+            _ => false,
+        };
+        if !skip {
+            let value = to_json_value(&value, &key)?;
+            root.insert(key, value);
+        }
+    }
+    root.insert("schema_version".to_string(), Value::from(JSON_SCHEMA_VERSION));
+
+    let value = Value::Object(root);
+    match indent {
+        None => serde_json::to_string(&value).map_err(json_error),
+        Some(width) => {
+            let indent = " ".repeat(width);
+            let mut out = Vec::new();
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut ser = serde_json::Serializer::with_formatter(&mut out, formatter);
+            serde::Serialize::serialize(&value, &mut ser).map_err(json_error)?;
+            String::from_utf8(out).map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+        }
+    }
+}
+
+/// 把 Python 值转换为 JSON（`path` 用于错误信息）
+fn to_json_value(value: &Bound<'_, PyAny>, path: &str) -> PyResult<Value> {
+    if value.is_none() {
+        return Ok(Value::Null);
+    }
+    if let Ok(b) = value.downcast::<pyo3::types::PyBool>() {
+        return Ok(Value::Bool(b.is_true()));
+    }
+    if value.is_instance_of::<pyo3::types::PyLong>() {
+        if let Ok(i) = value.extract::<i64>() {
+            return Ok(Value::from(i));
+        }
+        if let Ok(u) = value.extract::<u64>() {
+            return Ok(Value::from(u));
+        }
+    }
+    if let Ok(s) = value.downcast::<pyo3::types::PyString>() {
+        return Ok(Value::String(s.extract()?));
+    }
+    if let Ok(f) = value.extract::<f64>() {
+        // NaN / inf 写为 null
+        return Ok(serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number));
+    }
+    if let Ok(d) = value.downcast::<PyDict>() {
+        let mut map = Map::new();
+        for (k, v) in d.iter() {
+            let key: String = match k.downcast::<pyo3::types::PyString>() {
+                Ok(s) => s.extract()?,
+                Err(_) => k.str()?.extract()?,
+            };
+            let child = to_json_value(&v, &format!("{}.{}", path, key))?;
+            map.insert(key, child);
+        }
+        return Ok(Value::Object(map));
+    }
+    if value.is_instance_of::<PyList>() || value.is_instance_of::<pyo3::types::PyTuple>() {
+        let mut items = Vec::new();
+        for (i, item) in value.iter()?.enumerate() {
+            items.push(to_json_value(&item?, &format!("{}[{}]", path, i))?);
+        }
+        return Ok(Value::Array(items));
+    }
+    Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+        "Result field {} is not JSON serializable: {}",
+        path,
+        value.get_type().name()?
+    )))
+}
+
+fn json_error(err: serde_json::Error) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to serialize result: {}", err))
+}
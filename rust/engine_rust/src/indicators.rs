@@ -0,0 +1,2454 @@
+//! 技术指标计算模块
+//!
+//! 本模块集中实现了常用技术指标的向量化计算，作为 `compute_sma`、`compute_rsi` 的补充。
+//! 所有指标都在 Rust 中单次遍历完成，避免了 pandas 滚动窗口的解释器开销。
+//!
+//! # 核心概念
+//!
+//! - **向量化指标**: `vectorized_*` 函数接收价格切片，返回与输入等长的结果向量
+//! - **预热区间**: 数据不足以计算指标时返回 `None`，与 `vectorized_sma` 保持一致
+//! - **Python 接口**: `compute_*` 函数是对应的 PyO3 包装，供 Python 直接调用
+//!
+//! # 使用方式
+//!
+//! 1. **Rust 内部**: 直接调用 `vectorized_*` 函数，传入切片
+//! 2. **Python**: `from engine_rust import compute_donchian` 等
+//!
+//! # 注意事项
+//!
+//! - 多序列输入（如 `highs`/`lows`）的长度必须一致，否则返回 `ValueError`
+//! - 所有价格使用 `f64` 类型，注意浮点数精度问题
+
+use chrono::NaiveDate;
+use numpy::{IntoPyArray, PyArray1, PyReadonlyArray1};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use crate::database::parse_datetime;
+use crate::extract_bars_data;
+
+/// 通道类指标的上下轨：`(upper, lower)`
+pub type ChannelSeries = (Vec<Option<f64>>, Vec<Option<f64>>);
+
+/// 通道类指标上下轨的 numpy 数组：`(upper, lower)`
+pub type ChannelArrays<'py> = (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>);
+
+/// 批量指标计算的输出：按规格顺序排列的 `(输出名称, 数值序列)`
+pub type IndicatorOutputs = Vec<(String, Vec<Option<f64>>)>;
+
+/// 校验多个输入序列长度一致
+fn ensure_same_len(lens: &[usize]) -> PyResult<()> {
+    if lens.windows(2).any(|w| w[0] != w[1]) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Input series must have the same length, got {:?}",
+            lens
+        )));
+    }
+    Ok(())
+}
+
+/// 指标预热区间（以及其他缺失值）的填充方式
+///
+/// 所有指标函数都通过 `fill` 参数选择：
+/// - `"none"`（默认）：保持 `None`
+/// - `"nan"`：填充为 `NaN`，便于直接转成 numpy/pandas 的浮点数组
+/// - `"zero"`：填充为 `0.0`
+/// - `"ffill"`：用前一个有效值填充；第一个有效值之前的预热区间没有前值，保持 `None`
+///   （numpy 版本中为 NaN），不会用之后才出现的值回填，避免引入未来信息
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WarmupFill {
+    #[default]
+    None,
+    Nan,
+    Zero,
+    Ffill,
+}
+
+impl WarmupFill {
+    /// 从字符串解析填充方式（不区分大小写）
+    pub fn parse(s: &str) -> PyResult<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(WarmupFill::None),
+            "nan" => Ok(WarmupFill::Nan),
+            "zero" => Ok(WarmupFill::Zero),
+            "ffill" => Ok(WarmupFill::Ffill),
+            _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unsupported fill policy: {} (expected none/nan/zero/ffill)",
+                s
+            ))),
+        }
+    }
+
+    /// 按填充方式处理序列中的 `None`
+    pub fn apply(self, mut values: Vec<Option<f64>>) -> Vec<Option<f64>> {
+        match self {
+            WarmupFill::None => {}
+            WarmupFill::Nan => values.iter_mut().for_each(|v| *v = Some(v.unwrap_or(f64::NAN))),
+            WarmupFill::Zero => values.iter_mut().for_each(|v| *v = Some(v.unwrap_or(0.0))),
+            WarmupFill::Ffill => {
+                let mut last = None;
+                for v in values.iter_mut() {
+                    match v {
+                        Some(x) => last = Some(*x),
+                        None => *v = last,
+                    }
+                }
+            }
+        }
+        values
+    }
+}
+
+/// 单调双端队列实现的滚动极值
+///
+/// 队列中保存候选元素的索引，并保持对应值单调（求最大值时递减，求最小值时递增），
+/// 队首始终是窗口内的极值。新元素入队时弹出所有被它"支配"的旧元素，队首过期时出队。
+/// 每个元素最多入队、出队各一次，整体复杂度为 O(n)，与窗口大小无关。
+fn rolling_extreme(values: &[f64], window: usize, is_max: bool) -> Vec<Option<f64>> {
+    if values.is_empty() || window == 0 {
+        return vec![None; values.len()];
+    }
+
+    let mut result = Vec::with_capacity(values.len());
+    let mut q: VecDeque<usize> = VecDeque::with_capacity(window);
+
+    for i in 0..values.len() {
+        // 弹出被新值支配的候选
+        while let Some(&j) = q.back() {
+            let dominated = if is_max { values[j] <= values[i] } else { values[j] >= values[i] };
+            if dominated { q.pop_back(); } else { break; }
+        }
+        q.push_back(i);
+
+        // 移除已滑出窗口的队首
+        if let Some(&j) = q.front() {
+            if j + window <= i { q.pop_front(); }
+        }
+
+        if i + 1 < window {
+            result.push(None);
+        } else {
+            result.push(q.front().map(|&j| values[j]));
+        }
+    }
+
+    result
+}
+
+/// 计算滚动最大值
+///
+/// 对每个位置返回最近 `window` 个值中的最大值，使用单调双端队列实现，时间复杂度 O(n)。
+/// 相比朴素实现的 O(n×w)，在大窗口（如 250 日新高）下优势明显。
+/// 唐奇安通道、威廉指标、随机指标等都可以在此基础上构建。
+///
+/// ```rust,ignore
+/// let values = vec![3.0, 1.0, 4.0, 1.0, 5.0];
+/// let max = vectorized_rolling_max(&values, 3);
+/// // 结果: [None, None, Some(4.0), Some(4.0), Some(5.0)]
+/// ```
+///
+/// # 参数
+///
+/// - `values`: 数值序列切片，按时间顺序排列
+/// - `window`: 窗口大小，必须大于 0
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同，前 `window-1` 个元素为 `None`
+///
+/// # 注意事项
+///
+/// - 如果 `values` 为空或 `window` 为 0，返回全 `None` 向量
+/// - 序列中包含 NaN 时结果未定义，请先清洗数据
+pub fn vectorized_rolling_max(values: &[f64], window: usize) -> Vec<Option<f64>> {
+    rolling_extreme(values, window, true)
+}
+
+/// 计算滚动最小值
+///
+/// 与 `vectorized_rolling_max()` 对称，返回最近 `window` 个值中的最小值。
+pub fn vectorized_rolling_min(values: &[f64], window: usize) -> Vec<Option<f64>> {
+    rolling_extreme(values, window, false)
+}
+
+/// 滚动最大值（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_rolling_max
+///
+/// high_250 = compute_rolling_max(highs, 250)  # 250 日新高
+/// ```
+#[pyfunction]
+#[pyo3(signature = (values, window, fill="none"))]
+pub fn compute_rolling_max(values: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_rolling_max(&values, window)))
+}
+
+/// 滚动最小值（Python 接口）
+///
+/// 参数与 `compute_rolling_max` 相同。
+#[pyfunction]
+#[pyo3(signature = (values, window, fill="none"))]
+pub fn compute_rolling_min(values: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_rolling_min(&values, window)))
+}
+
+/// 计算唐奇安通道（Donchian Channels）
+///
+/// 唐奇安通道由最近 N 根 K 线的最高价和最低价构成，是海龟交易法等突破系统的基础。
+/// 价格突破上轨视为做多信号，跌破下轨视为做空信号。
+///
+/// 上轨是 `highs` 的滚动最大值，下轨是 `lows` 的滚动最小值，
+/// 均基于 `vectorized_rolling_max()` / `vectorized_rolling_min()` 的单调队列实现，复杂度 O(n)。
+///
+/// ```rust,ignore
+/// let highs = vec![10.0, 11.0, 12.0, 11.5, 10.5];
+/// let lows = vec![9.0, 10.0, 11.0, 10.5, 9.5];
+/// let (upper, lower) = vectorized_donchian(&highs, &lows, 3);
+/// // upper: [None, None, Some(12.0), Some(12.0), Some(12.0)]
+/// // lower: [None, None, Some(9.0), Some(10.0), Some(9.5)]
+/// ```
+///
+/// # 参数
+///
+/// - `highs`: 最高价序列，按时间顺序排列
+/// - `lows`: 最低价序列，与 `highs` 一一对应
+/// - `window`: 通道窗口大小，常用 20（突破系统）或 55（长期趋势）
+///
+/// # 返回值
+///
+/// 返回 `(upper, lower)` 元组，长度与输入相同：
+/// - 前 `window-1` 个元素为 `None`（数据不足）
+/// - 之后为窗口内最高价 / 最低价
+///
+/// # 注意事项
+///
+/// - 如果输入为空或 `window` 为 0，返回全 `None` 向量
+/// - 如果 `highs` 与 `lows` 长度不同，按较短的长度计算
+pub fn vectorized_donchian(highs: &[f64], lows: &[f64], window: usize) -> ChannelSeries {
+    let n = highs.len().min(lows.len());
+    (
+        vectorized_rolling_max(&highs[..n], window),
+        vectorized_rolling_min(&lows[..n], window),
+    )
+}
+
+/// 唐奇安通道（Python 接口）
+///
+/// 返回 `(upper, lower)` 两个列表，详见 `vectorized_donchian()`。
+///
+/// ```python
+/// from engine_rust import compute_donchian
+///
+/// upper, lower = compute_donchian(highs, lows, 20)
+/// breakout = [c > u for c, u in zip(closes[1:], upper[:-1]) if u is not None]
+/// ```
+#[pyfunction]
+#[pyo3(signature = (highs, lows, window, fill="none"))]
+pub fn compute_donchian(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    window: usize,
+    fill: &str,
+) -> PyResult<ChannelSeries> {
+    let fill = WarmupFill::parse(fill)?;
+    ensure_same_len(&[highs.len(), lows.len()])?;
+    let (upper, lower) = vectorized_donchian(&highs, &lows, window);
+    Ok((fill.apply(upper), fill.apply(lower)))
+}
+
+/// 计算抛物线转向指标（Parabolic SAR）
+///
+/// SAR（Stop And Reverse）是 Wilder 提出的趋势跟踪止损指标：上升趋势中 SAR 位于价格下方并逐步上移，
+/// 下降趋势中位于价格上方并逐步下移，价格穿越 SAR 时趋势反转。
+///
+/// ## 为什么需要这个函数？
+///
+/// SAR 的每一步都依赖上一步的状态（趋势方向、极值点、加速因子），无法用 pandas 的向量化操作表达，
+/// 只能逐行循环。Python 循环在大数据量下非常慢，而 Rust 循环几乎没有额外开销。
+///
+/// ## 工作原理（简单理解）
+///
+/// 引擎维护一个小型状态机：
+///
+/// 1. **初始化**：根据前两根 K 线的中价判断初始趋势，SAR 取第一根的低点（上升）或高点（下降）
+/// 2. **推进 SAR**：`SAR = SAR + AF × (EP - SAR)`，其中 EP 是当前趋势的极值点
+/// 3. **约束 SAR**：上升趋势中 SAR 不能高于前两根 K 线的最低价（下降趋势对称）
+/// 4. **反转检查**：价格穿越 SAR 时反转趋势，SAR 重置为原 EP，AF 重置为初始值
+/// 5. **加速**：趋势延续且创出新极值时，AF 增加 `af_step`，上限为 `af_max`
+///
+/// ```rust,ignore
+/// let sar = vectorized_psar(&highs, &lows, 0.02, 0.02, 0.2);
+/// // sar[0] 为 None，之后每根 K 线都有值
+/// ```
+///
+/// # 参数
+///
+/// - `highs`: 最高价序列，按时间顺序排列
+/// - `lows`: 最低价序列，与 `highs` 一一对应
+/// - `af_start`: 初始加速因子，通常为 0.02
+/// - `af_step`: 加速因子步长，通常为 0.02
+/// - `af_max`: 加速因子上限，通常为 0.2
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同：
+/// - 第一个元素为 `None`（没有前一根 K 线）
+/// - 之后每个元素为当根 K 线的 SAR 值
+///
+/// # 注意事项
+///
+/// - 如果输入长度小于 2，返回全 `None` 向量
+/// - 价格低于 SAR 表示处于下降趋势，高于 SAR 表示处于上升趋势
+pub fn vectorized_psar(highs: &[f64], lows: &[f64], af_start: f64, af_step: f64, af_max: f64) -> Vec<Option<f64>> {
+    let n = highs.len().min(lows.len());
+    if n < 2 {
+        return vec![None; n];
+    }
+
+    let mut result = Vec::with_capacity(n);
+    result.push(None);
+
+    // 初始趋势：比较前两根 K 线的中价
+    let mut up = highs[1] + lows[1] >= highs[0] + lows[0];
+    let mut sar = if up { lows[0] } else { highs[0] };
+    let mut ep = if up { highs[0] } else { lows[0] };
+    let mut af = af_start;
+
+    for i in 1..n {
+        if i > 1 {
+            sar += af * (ep - sar);
+            // SAR 不能进入前两根 K 线的价格区间
+            if up {
+                sar = sar.min(lows[i - 1]).min(lows[i - 2]);
+            } else {
+                sar = sar.max(highs[i - 1]).max(highs[i - 2]);
+            }
+        }
+
+        if up && lows[i] < sar {
+            // 上升趋势被跌破：反转为下降趋势
+            up = false;
+            sar = ep;
+            ep = lows[i];
+            af = af_start;
+        } else if !up && highs[i] > sar {
+            // 下降趋势被突破：反转为上升趋势
+            up = true;
+            sar = ep;
+            ep = highs[i];
+            af = af_start;
+        } else if up && highs[i] > ep {
+            ep = highs[i];
+            af = (af + af_step).min(af_max);
+        } else if !up && lows[i] < ep {
+            ep = lows[i];
+            af = (af + af_step).min(af_max);
+        }
+
+        result.push(Some(sar));
+    }
+
+    result
+}
+
+/// 抛物线转向指标（Python 接口）
+///
+/// 详见 `vectorized_psar()`。
+///
+/// ```python
+/// from engine_rust import compute_psar
+///
+/// sar = compute_psar(highs, lows)                      # 默认 0.02 / 0.02 / 0.2
+/// sar = compute_psar(highs, lows, 0.01, 0.01, 0.1)     # 更平滑
+/// ```
+#[pyfunction]
+#[pyo3(signature = (highs, lows, af_start=0.02, af_step=0.02, af_max=0.2, fill="none"))]
+pub fn compute_psar(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    af_start: f64,
+    af_step: f64,
+    af_max: f64,
+    fill: &str,
+) -> PyResult<Vec<Option<f64>>> {
+    let fill = WarmupFill::parse(fill)?;
+    ensure_same_len(&[highs.len(), lows.len()])?;
+    Ok(fill.apply(vectorized_psar(&highs, &lows, af_start, af_step, af_max)))
+}
+
+/// 滑动窗口矩统计（均值与离差平方和）
+///
+/// 使用 Welford 在线算法的滑动窗口版本，支持 O(1) 加入和移除样本。
+/// 相比直接维护"和"与"平方和"，Welford 算法避免了大数相减带来的精度损失。
+#[derive(Default, Clone, Copy, Debug)]
+struct RollingMoments {
+    count: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl RollingMoments {
+    #[inline]
+    fn push(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    #[inline]
+    fn pop(&mut self, x: f64) {
+        if self.count <= 1 {
+            *self = Self::default();
+            return;
+        }
+        self.count -= 1;
+        let delta = x - self.mean;
+        self.mean -= delta / self.count as f64;
+        self.m2 -= delta * (x - self.mean);
+        // 浮点误差可能使 m2 略小于 0
+        if self.m2 < 0.0 { self.m2 = 0.0; }
+    }
+
+    #[inline]
+    fn variance(&self, ddof: usize) -> Option<f64> {
+        if self.count > ddof { Some(self.m2 / (self.count - ddof) as f64) } else { None }
+    }
+}
+
+/// 计算滚动方差
+///
+/// 对每个位置计算最近 `window` 个价格的方差，是布林带、Z-Score、波动率目标等计算的基础。
+///
+/// ## 工作原理（简单理解）
+///
+/// 使用滑动窗口版 Welford 算法：新价格进入窗口时更新均值和离差平方和，
+/// 最旧的价格离开窗口时做逆向更新。每一步都是 O(1)，且数值稳定。
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: 窗口大小，必须大于 `ddof`
+/// - `ddof`: 自由度修正，1 表示样本方差（与 pandas 默认一致），0 表示总体方差
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同，前 `window-1` 个元素为 `None`
+///
+/// # 注意事项
+///
+/// - 如果 `prices` 为空、`window` 为 0 或 `window <= ddof`，返回全 `None` 向量
+pub fn vectorized_rolling_var(prices: &[f64], window: usize, ddof: usize) -> Vec<Option<f64>> {
+    if prices.is_empty() || window == 0 || window <= ddof {
+        return vec![None; prices.len()];
+    }
+
+    let mut result = Vec::with_capacity(prices.len());
+    let mut moments = RollingMoments::default();
+
+    for i in 0..prices.len() {
+        moments.push(prices[i]);
+        if i >= window {
+            moments.pop(prices[i - window]);
+        }
+        if i + 1 < window {
+            result.push(None);
+        } else {
+            result.push(moments.variance(ddof));
+        }
+    }
+
+    result
+}
+
+/// 计算滚动标准差
+///
+/// 滚动方差的平方根，详见 `vectorized_rolling_var()`。
+///
+/// ```rust,ignore
+/// let std = vectorized_rolling_std(&prices, 20, 1);
+/// // 布林带：upper = sma + 2 * std，lower = sma - 2 * std
+/// ```
+pub fn vectorized_rolling_std(prices: &[f64], window: usize, ddof: usize) -> Vec<Option<f64>> {
+    vectorized_rolling_var(prices, window, ddof)
+        .into_iter()
+        .map(|v| v.map(f64::sqrt))
+        .collect()
+}
+
+/// 滚动标准差（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_rolling_std
+///
+/// std_20 = compute_rolling_std(closes, 20)          # 样本标准差（ddof=1）
+/// std_20 = compute_rolling_std(closes, 20, ddof=0)  # 总体标准差
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, window, ddof=1, fill="none"))]
+pub fn compute_rolling_std(prices: Vec<f64>, window: usize, ddof: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_rolling_std(&prices, window, ddof)))
+}
+
+/// 滚动方差（Python 接口）
+///
+/// 参数与 `compute_rolling_std` 相同。
+#[pyfunction]
+#[pyo3(signature = (prices, window, ddof=1, fill="none"))]
+pub fn compute_rolling_var(prices: Vec<f64>, window: usize, ddof: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_rolling_var(&prices, window, ddof)))
+}
+
+/// 计算滚动 Z-Score
+///
+/// Z-Score 衡量当前价格偏离近期均值多少个标准差：`z = (price - 滚动均值) / 滚动标准差`。
+/// 是均值回归策略最常用的信号：z > 2 视为显著偏高，z < -2 视为显著偏低。
+///
+/// ## 工作原理
+///
+/// 与 `vectorized_rolling_var()` 共用滑动窗口 Welford 统计，单次遍历同时得到均值和标准差，
+/// 不需要分别计算 SMA 和滚动标准差再组合。
+///
+/// ```rust,ignore
+/// let z = vectorized_zscore(&closes, 20, 1);
+/// // z[i] > 2.0 → 做空信号；z[i] < -2.0 → 做多信号
+/// ```
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: 窗口大小，必须大于 `ddof`
+/// - `ddof`: 标准差的自由度修正，默认 1（样本标准差）
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同，前 `window-1` 个元素为 `None`
+///
+/// # 注意事项
+///
+/// - 窗口内价格完全相同（标准差为 0）时，价格必然等于均值，返回 `Some(0.0)`
+/// - 如果 `prices` 为空、`window` 为 0 或 `window <= ddof`，返回全 `None` 向量
+pub fn vectorized_zscore(prices: &[f64], window: usize, ddof: usize) -> Vec<Option<f64>> {
+    if prices.is_empty() || window == 0 || window <= ddof {
+        return vec![None; prices.len()];
+    }
+
+    let mut result = Vec::with_capacity(prices.len());
+    let mut moments = RollingMoments::default();
+
+    for i in 0..prices.len() {
+        moments.push(prices[i]);
+        if i >= window {
+            moments.pop(prices[i - window]);
+        }
+        if i + 1 < window {
+            result.push(None);
+            continue;
+        }
+        let z = moments.variance(ddof).map(|var| {
+            let std = var.sqrt();
+            if std > 0.0 { (prices[i] - moments.mean) / std } else { 0.0 }
+        });
+        result.push(z);
+    }
+
+    result
+}
+
+/// 滚动 Z-Score（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_zscore
+///
+/// z = compute_zscore(closes, 20)
+/// signal = ["SELL" if v is not None and v > 2 else "BUY" if v is not None and v < -2 else None for v in z]
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, window, ddof=1, fill="none"))]
+pub fn compute_zscore(prices: Vec<f64>, window: usize, ddof: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_zscore(&prices, window, ddof)))
+}
+
+/// 双序列滑动窗口协矩统计
+///
+/// `RollingMoments` 的双变量版本，同时维护两个序列的均值、离差平方和以及交叉离差积，
+/// 支持 O(1) 加入和移除样本对。
+#[derive(Default, Clone, Copy, Debug)]
+struct RollingCoMoments {
+    count: usize,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    c_xy: f64,
+}
+
+impl RollingCoMoments {
+    #[inline]
+    fn push(&mut self, x: f64, y: f64) {
+        self.count += 1;
+        let n = self.count as f64;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += dx / n;
+        self.mean_y += dy / n;
+        self.m2_x += dx * (x - self.mean_x);
+        self.m2_y += dy * (y - self.mean_y);
+        self.c_xy += dx * (y - self.mean_y);
+    }
+
+    #[inline]
+    fn pop(&mut self, x: f64, y: f64) {
+        if self.count <= 1 {
+            *self = Self::default();
+            return;
+        }
+        self.count -= 1;
+        let n = self.count as f64;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x -= dx / n;
+        self.mean_y -= dy / n;
+        self.m2_x -= dx * (x - self.mean_x);
+        self.m2_y -= dy * (y - self.mean_y);
+        self.c_xy -= (x - self.mean_x) * dy;
+        if self.m2_x < 0.0 { self.m2_x = 0.0; }
+        if self.m2_y < 0.0 { self.m2_y = 0.0; }
+    }
+
+    #[inline]
+    fn covariance(&self, ddof: usize) -> Option<f64> {
+        if self.count > ddof { Some(self.c_xy / (self.count - ddof) as f64) } else { None }
+    }
+
+    #[inline]
+    fn correlation(&self) -> Option<f64> {
+        let denom = (self.m2_x * self.m2_y).sqrt();
+        if denom > 0.0 { Some((self.c_xy / denom).clamp(-1.0, 1.0)) } else { None }
+    }
+}
+
+/// 计算两个序列的滚动相关系数与协方差
+///
+/// 对每个位置计算最近 `window` 个样本对的 Pearson 相关系数和样本协方差。
+/// 配对交易需要监控价差两腿的相关性，Beta 对冲需要滚动协方差 / 方差，都可以基于此函数完成。
+///
+/// ## 工作原理
+///
+/// 使用滑动窗口版双变量 Welford 算法，同时维护两个序列的均值、离差平方和与交叉离差积，
+/// 每一步 O(1) 更新，单次遍历同时得到相关系数与协方差。
+///
+/// ```rust,ignore
+/// let (corr, cov) = vectorized_rolling_corr(&stock_returns, &index_returns, 60);
+/// // 滚动 Beta = cov / var(index_returns)
+/// ```
+///
+/// # 参数
+///
+/// - `a`: 第一个序列，按时间顺序排列
+/// - `b`: 第二个序列，与 `a` 一一对应
+/// - `window`: 窗口大小，必须大于 1
+///
+/// # 返回值
+///
+/// 返回 `(corr, cov)` 元组，长度与输入相同，前 `window-1` 个元素为 `None`：
+/// - `corr`: Pearson 相关系数，范围 -1 到 1；任一序列在窗口内为常数时为 `None`
+/// - `cov`: 样本协方差（除以 `window - 1`，与 pandas 一致）
+///
+/// # 注意事项
+///
+/// - 如果 `a` 与 `b` 长度不同，按较短的长度计算
+/// - 如果 `window < 2`，返回全 `None` 向量
+pub fn vectorized_rolling_corr(a: &[f64], b: &[f64], window: usize) -> (Vec<Option<f64>>, Vec<Option<f64>>) {
+    let n = a.len().min(b.len());
+    if n == 0 || window < 2 {
+        return (vec![None; n], vec![None; n]);
+    }
+
+    let mut corr = Vec::with_capacity(n);
+    let mut cov = Vec::with_capacity(n);
+    let mut moments = RollingCoMoments::default();
+
+    for i in 0..n {
+        moments.push(a[i], b[i]);
+        if i >= window {
+            moments.pop(a[i - window], b[i - window]);
+        }
+        if i + 1 < window {
+            corr.push(None);
+            cov.push(None);
+        } else {
+            corr.push(moments.correlation());
+            cov.push(moments.covariance(1));
+        }
+    }
+
+    (corr, cov)
+}
+
+/// 滚动相关系数（Python 接口）
+///
+/// 默认只返回相关系数列表；`with_cov=True` 时返回 `(corr, cov)` 元组。
+///
+/// ```python
+/// from engine_rust import compute_rolling_corr
+///
+/// corr = compute_rolling_corr(a_closes, b_closes, 60)
+/// corr, cov = compute_rolling_corr(stock_rets, index_rets, 60, with_cov=True)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (a, b, window, with_cov=false, fill="none"))]
+pub fn compute_rolling_corr(
+    py: Python<'_>,
+    a: Vec<f64>,
+    b: Vec<f64>,
+    window: usize,
+    with_cov: bool,
+    fill: &str,
+) -> PyResult<PyObject> {
+    let fill = WarmupFill::parse(fill)?;
+    ensure_same_len(&[a.len(), b.len()])?;
+    let (corr, cov) = vectorized_rolling_corr(&a, &b, window);
+    if with_cov {
+        Ok((fill.apply(corr), fill.apply(cov)).into_py(py))
+    } else {
+        Ok(fill.apply(corr).into_py(py))
+    }
+}
+
+/// 滚动线性回归结果：(斜率, 截距, R²)
+pub type LinRegSeries = (Vec<Option<f64>>, Vec<Option<f64>>, Vec<Option<f64>>);
+
+/// 滚动线性回归结果的 numpy 数组：(斜率, 截距, R²)
+pub type LinRegArrays<'py> = (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>);
+
+/// 计算滚动线性回归（斜率、截距、R²）
+///
+/// 对每个位置，用最近 `window` 个价格对时间做最小二乘回归 `price = intercept + slope × x`，
+/// 其中 `x = 0` 对应窗口内最旧的 K 线，`x = window - 1` 对应当前 K 线。
+/// 斜率衡量趋势方向与速度，R² 衡量趋势的"干净"程度，常用作趋势过滤器。
+///
+/// ## 工作原理（简单理解）
+///
+/// 窗口内的 x 固定为 `0..window`，因此 Σx、Σx² 是常数，只需维护 Σy、Σy²、Σxy：
+///
+/// 1. **加入新价格**：新价格位于 `x = window - 1`
+/// 2. **滑动窗口**：整体左移一格时 `Σxy' = Σxy - (Σy - y_out) + (window - 1) × y_in`
+/// 3. **求解**：用闭式公式计算斜率、截距和 R²
+///
+/// 每一步都是 O(1)，与窗口大小无关。为了减小大价格下的舍入误差，内部以首个价格为基准做了平移。
+///
+/// ```rust,ignore
+/// let (slope, intercept, r2) = vectorized_linreg(&closes, 20);
+/// // 当前 K 线的拟合值 = intercept + slope * (20 - 1)
+/// // 趋势过滤：slope > 0 且 r2 > 0.6 时才允许做多
+/// ```
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: 回归窗口大小，必须 >= 2
+///
+/// # 返回值
+///
+/// 返回 `(slope, intercept, r2)` 元组，每个序列长度与输入相同，前 `window-1` 个元素为 `None`
+///
+/// # 注意事项
+///
+/// - 如果 `window < 2`，返回全 `None` 向量
+/// - 窗口内价格完全相同时，斜率为 0，R² 记为 0（不存在可解释的趋势）
+pub fn vectorized_linreg(prices: &[f64], window: usize) -> LinRegSeries {
+    let n = prices.len();
+    if n == 0 || window < 2 {
+        return (vec![None; n], vec![None; n], vec![None; n]);
+    }
+
+    let mut slopes = Vec::with_capacity(n);
+    let mut intercepts = Vec::with_capacity(n);
+    let mut r2s = Vec::with_capacity(n);
+
+    // x 为 0..window，Σx 与 Σx² 为常数
+    let w = window as f64;
+    let sum_x = w * (w - 1.0) / 2.0;
+    let sum_x2 = (w - 1.0) * w * (2.0 * w - 1.0) / 6.0;
+    let denom_x = w * sum_x2 - sum_x * sum_x;
+
+    // 以首个价格为基准平移，减少大数相减的精度损失（斜率和 R² 不受平移影响）
+    let offset = prices[0];
+    let mut sum_y = 0.0;
+    let mut sum_y2 = 0.0;
+    let mut sum_xy = 0.0;
+
+    for i in 0..n {
+        let y_in = prices[i] - offset;
+        if i < window {
+            // 窗口未满：新价格位于 x = i
+            sum_xy += i as f64 * y_in;
+        } else {
+            // 窗口滑动：所有 x 减 1，移出最旧价格，新价格位于 x = window - 1
+            let y_out = prices[i - window] - offset;
+            sum_xy = sum_xy - (sum_y - y_out) + (w - 1.0) * y_in;
+            sum_y -= y_out;
+            sum_y2 -= y_out * y_out;
+        }
+        sum_y += y_in;
+        sum_y2 += y_in * y_in;
+
+        if i + 1 < window {
+            slopes.push(None);
+            intercepts.push(None);
+            r2s.push(None);
+            continue;
+        }
+
+        let cov_xy = w * sum_xy - sum_x * sum_y;
+        let var_y = w * sum_y2 - sum_y * sum_y;
+        let slope = cov_xy / denom_x;
+        let intercept = (sum_y - slope * sum_x) / w + offset;
+        let r2 = if var_y > 0.0 { ((cov_xy * cov_xy) / (denom_x * var_y)).clamp(0.0, 1.0) } else { 0.0 };
+
+        slopes.push(Some(slope));
+        intercepts.push(Some(intercept));
+        r2s.push(Some(r2));
+    }
+
+    (slopes, intercepts, r2s)
+}
+
+/// 滚动线性回归（Python 接口）
+///
+/// 返回 `(slope, intercept, r2)` 三个列表，详见 `vectorized_linreg()`。
+///
+/// ```python
+/// from engine_rust import compute_linreg
+///
+/// slope, intercept, r2 = compute_linreg(closes, 20)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, window, fill="none"))]
+pub fn compute_linreg(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<LinRegSeries> {
+    let fill = WarmupFill::parse(fill)?;
+    let (slope, intercept, r2) = vectorized_linreg(&prices, window);
+    Ok((fill.apply(slope), fill.apply(intercept), fill.apply(r2)))
+}
+
+/// 计算资金流量指标（Money Flow Index, MFI）
+///
+/// MFI 是成交量加权的 RSI：用典型价格 × 成交量衡量每根 K 线的"资金流"，
+/// 再比较窗口内流入与流出的资金量。MFI > 80 通常视为超买，< 20 视为超卖。
+///
+/// ## 工作原理（简单理解）
+///
+/// 1. **典型价格**：`TP = (high + low + close) / 3`
+/// 2. **原始资金流**：`MF = TP × volume`
+/// 3. **流向判断**：TP 高于前一根记为流入，低于前一根记为流出，持平不计
+/// 4. **资金比率**：`MR = 窗口内流入之和 / 窗口内流出之和`
+/// 5. **MFI**：`100 - 100 / (1 + MR)`
+///
+/// 流入、流出之和使用滑动窗口维护，每一步 O(1)。
+///
+/// # 参数
+///
+/// - `highs` / `lows` / `closes` / `volumes`: 等长的 OHLCV 序列，按时间顺序排列
+/// - `window`: 计算窗口，通常为 14
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同：
+/// - 前 `window` 个元素为 `None`（第一根 K 线没有资金流向，需要 `window` 个流向）
+/// - 之后为 0-100 之间的 MFI 值
+///
+/// # 注意事项
+///
+/// - 窗口内没有流出时返回 100；既无流入也无流出（如成交量为 0）时返回 50
+/// - 如果输入长度不一致，按最短的长度计算
+pub fn vectorized_mfi(highs: &[f64], lows: &[f64], closes: &[f64], volumes: &[f64], window: usize) -> Vec<Option<f64>> {
+    let n = highs.len().min(lows.len()).min(closes.len()).min(volumes.len());
+    if n < 2 || window == 0 {
+        return vec![None; n];
+    }
+
+    let tp: Vec<f64> = (0..n).map(|i| (highs[i] + lows[i] + closes[i]) / 3.0).collect();
+
+    // 每根 K 线的流入 / 流出资金（第一根没有流向）
+    let mut pos_flow = vec![0.0; n];
+    let mut neg_flow = vec![0.0; n];
+    for i in 1..n {
+        let mf = tp[i] * volumes[i];
+        if tp[i] > tp[i - 1] {
+            pos_flow[i] = mf;
+        } else if tp[i] < tp[i - 1] {
+            neg_flow[i] = mf;
+        }
+    }
+
+    let mut result = Vec::with_capacity(n);
+    result.push(None);
+    let mut pos_sum = 0.0;
+    let mut neg_sum = 0.0;
+
+    for i in 1..n {
+        pos_sum += pos_flow[i];
+        neg_sum += neg_flow[i];
+        if i > window {
+            pos_sum -= pos_flow[i - window];
+            neg_sum -= neg_flow[i - window];
+        }
+        if i < window {
+            result.push(None);
+            continue;
+        }
+        let mfi = if neg_sum <= 0.0 {
+            if pos_sum <= 0.0 { 50.0 } else { 100.0 }
+        } else {
+            100.0 - 100.0 / (1.0 + pos_sum / neg_sum)
+        };
+        result.push(Some(mfi));
+    }
+
+    result
+}
+
+/// 资金流量指标（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_mfi
+///
+/// mfi = compute_mfi(highs, lows, closes, volumes, 14)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (highs, lows, closes, volumes, window=14, fill="none"))]
+pub fn compute_mfi(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    closes: Vec<f64>,
+    volumes: Vec<f64>,
+    window: usize,
+    fill: &str,
+) -> PyResult<Vec<Option<f64>>> {
+    let fill = WarmupFill::parse(fill)?;
+    ensure_same_len(&[highs.len(), lows.len(), closes.len(), volumes.len()])?;
+    Ok(fill.apply(vectorized_mfi(&highs, &lows, &closes, &volumes, window)))
+}
+
+/// 计算变动率（Rate of Change, ROC）
+///
+/// `ROC = price[i] / price[i - window] - 1`，即最近 `window` 根 K 线的区间收益率。
+/// 是动量因子研究中最基础的构件（如 20 日动量、12-1 月动量等）。
+///
+/// ```rust,ignore
+/// let prices = vec![100.0, 102.0, 105.0, 103.0];
+/// let roc = vectorized_roc(&prices, 2);
+/// // 结果: [None, None, Some(0.05), Some(0.00980...)]
+/// ```
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: 回看期数，必须大于 0
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同：
+/// - 前 `window` 个元素为 `None`（没有足够的历史价格）
+/// - 之后为小数形式的收益率（0.05 表示 5%），与回测统计中 `total_return` 的口径一致
+///
+/// # 注意事项
+///
+/// - 基准价格为 0 时对应位置返回 `None`
+pub fn vectorized_roc(prices: &[f64], window: usize) -> Vec<Option<f64>> {
+    if prices.is_empty() || window == 0 {
+        return vec![None; prices.len()];
+    }
+    (0..prices.len())
+        .map(|i| {
+            if i < window {
+                return None;
+            }
+            let base = prices[i - window];
+            if base != 0.0 { Some(prices[i] / base - 1.0) } else { None }
+        })
+        .collect()
+}
+
+/// 计算动量（Momentum）
+///
+/// `Momentum = price[i] - price[i - window]`，即最近 `window` 根 K 线的价格变化量。
+/// 与 `vectorized_roc()` 的区别是返回绝对价差而不是收益率。
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: 回看期数，必须大于 0
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同，前 `window` 个元素为 `None`
+pub fn vectorized_momentum(prices: &[f64], window: usize) -> Vec<Option<f64>> {
+    if prices.is_empty() || window == 0 {
+        return vec![None; prices.len()];
+    }
+    (0..prices.len())
+        .map(|i| if i < window { None } else { Some(prices[i] - prices[i - window]) })
+        .collect()
+}
+
+/// 变动率（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_roc
+///
+/// mom_20 = compute_roc(closes, 20)  # 20 日收益率
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, window, fill="none"))]
+pub fn compute_roc(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_roc(&prices, window)))
+}
+
+/// 动量（Python 接口）
+///
+/// 参数与 `compute_roc` 相同，返回价格差而不是收益率。
+#[pyfunction]
+#[pyo3(signature = (prices, window, fill="none"))]
+pub fn compute_momentum(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_momentum(&prices, window)))
+}
+
+/// 对可能含有预热 `None` 的序列计算 EMA
+///
+/// 从第一个有效值开始累积，前 `window` 个有效值的简单平均作为 EMA 种子，
+/// 之后按 `alpha = 2 / (window + 1)` 递推。用于 EMA 的多重嵌套（如 TRIX）。
+fn ema_of_series(values: &[Option<f64>], window: usize) -> Vec<Option<f64>> {
+    let mut result = Vec::with_capacity(values.len());
+    if window == 0 {
+        result.resize(values.len(), None);
+        return result;
+    }
+
+    let alpha = 2.0 / (window as f64 + 1.0);
+    let mut seen = 0usize;
+    let mut seed_sum = 0.0;
+    let mut ema: Option<f64> = None;
+
+    for v in values {
+        match (*v, ema) {
+            (None, _) => result.push(None),
+            (Some(x), None) => {
+                // 种子阶段：累积前 window 个有效值
+                seen += 1;
+                seed_sum += x;
+                if seen == window {
+                    ema = Some(seed_sum / window as f64);
+                }
+                result.push(ema);
+            }
+            (Some(x), Some(prev)) => {
+                let next = prev + alpha * (x - prev);
+                ema = Some(next);
+                result.push(ema);
+            }
+        }
+    }
+
+    result
+}
+
+/// 计算指数移动平均线（EMA）
+///
+/// EMA 对近期价格赋予更高权重：`EMA[i] = EMA[i-1] + α × (price[i] - EMA[i-1])`，
+/// 其中 `α = 2 / (window + 1)`。种子值取前 `window` 个价格的简单平均。
+/// EMA 是 MACD、TRIX 等指标的基础构件。
+///
+/// ```rust,ignore
+/// let ema = vectorized_ema(&prices, 12);
+/// ```
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: EMA 周期，必须大于 0
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同，前 `window-1` 个元素为 `None`
+pub fn vectorized_ema(prices: &[f64], window: usize) -> Vec<Option<f64>> {
+    let values: Vec<Option<f64>> = prices.iter().map(|&p| Some(p)).collect();
+    ema_of_series(&values, window)
+}
+
+/// 计算 TRIX 指标（三重指数平滑变动率）
+///
+/// TRIX 对价格做三次 EMA 平滑，再计算相邻两期的变动率，能过滤掉短期噪音，
+/// 只保留较长周期的趋势信息。TRIX 上穿 0 轴视为看多，下穿视为看空。
+///
+/// ## 工作原理
+///
+/// 1. `EMA1 = EMA(price, window)`
+/// 2. `EMA2 = EMA(EMA1, window)`
+/// 3. `EMA3 = EMA(EMA2, window)`
+/// 4. `TRIX = EMA3[i] / EMA3[i-1] - 1`
+///
+/// 三次平滑都复用 EMA 原语，每次从上一层第一个有效值开始计算。
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: EMA 周期，通常为 12-15
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同：
+/// - 前 `3 × (window - 1) + 1` 个元素为 `None`（三层 EMA 的预热期加一期变动率）
+/// - 之后为小数形式的变动率，与 `vectorized_roc()` 口径一致
+///
+/// # 注意事项
+///
+/// - 如果 `window` 为 0，返回全 `None` 向量
+pub fn vectorized_trix(prices: &[f64], window: usize) -> Vec<Option<f64>> {
+    if prices.is_empty() || window == 0 {
+        return vec![None; prices.len()];
+    }
+
+    let ema1 = vectorized_ema(prices, window);
+    let ema2 = ema_of_series(&ema1, window);
+    let ema3 = ema_of_series(&ema2, window);
+
+    let mut result = Vec::with_capacity(prices.len());
+    result.push(None);
+    for i in 1..ema3.len() {
+        let trix = match (ema3[i - 1], ema3[i]) {
+            (Some(prev), Some(cur)) if prev != 0.0 => Some(cur / prev - 1.0),
+            _ => None,
+        };
+        result.push(trix);
+    }
+
+    result
+}
+
+/// 指数移动平均线（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_ema
+///
+/// ema_12 = compute_ema(closes, 12)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, window, fill="none"))]
+pub fn compute_ema(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_ema(&prices, window)))
+}
+
+/// TRIX 指标（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_trix
+///
+/// trix = compute_trix(closes, 15)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, window, fill="none"))]
+pub fn compute_trix(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_trix(&prices, window)))
+}
+
+/// 计算加权移动平均线（WMA）
+///
+/// 窗口内价格按线性权重加权，最新价格权重为 `window`，最旧价格权重为 1，
+/// 比 SMA 对近期价格更敏感。
+///
+/// ## 工作原理（简单理解）
+///
+/// 维护窗口内的普通和 `S` 与加权和 `WS`，窗口滑动时所有权重减 1：
+///
+/// - `WS' = WS - S + window × 新价格`
+/// - `S' = S - 最旧价格 + 新价格`
+///
+/// 每一步 O(1)，不需要每次重新计算加权和。
+///
+/// ```rust,ignore
+/// let prices = vec![1.0, 2.0, 3.0, 4.0];
+/// let wma = vectorized_wma(&prices, 3);
+/// // 结果: [None, None, Some(2.333...), Some(3.333...)]
+/// ```
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: 窗口大小，必须大于 0
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同，前 `window-1` 个元素为 `None`
+pub fn vectorized_wma(prices: &[f64], window: usize) -> Vec<Option<f64>> {
+    if prices.is_empty() || window == 0 {
+        return vec![None; prices.len()];
+    }
+
+    let w = window as f64;
+    let denom = w * (w + 1.0) / 2.0;
+    let mut result = Vec::with_capacity(prices.len());
+    let mut sum = 0.0;
+    let mut wsum = 0.0;
+
+    for i in 0..prices.len() {
+        if i < window {
+            sum += prices[i];
+            wsum += (i + 1) as f64 * prices[i];
+        } else {
+            // 滑动窗口：所有权重减 1（最旧价格权重降为 0），新价格权重为 window
+            wsum = wsum - sum + w * prices[i];
+            sum = sum - prices[i - window] + prices[i];
+        }
+        if i + 1 < window { result.push(None); } else { result.push(Some(wsum / denom)); }
+    }
+
+    result
+}
+
+/// 对含预热 `None` 的序列计算 WMA（从第一个有效值开始）
+fn wma_of_series(values: &[Option<f64>], window: usize) -> Vec<Option<f64>> {
+    let start = match values.iter().position(|v| v.is_some()) {
+        Some(s) => s,
+        None => return vec![None; values.len()],
+    };
+    let tail: Vec<f64> = values[start..].iter().map(|v| v.unwrap_or(f64::NAN)).collect();
+    let mut result = vec![None; start];
+    result.extend(vectorized_wma(&tail, window));
+    result
+}
+
+/// 计算赫尔移动平均线（Hull Moving Average, HMA）
+///
+/// HMA 由 Alan Hull 提出，在保持平滑的同时显著降低了均线的滞后：
+/// `HMA(n) = WMA(2 × WMA(price, n/2) - WMA(price, n), √n)`。
+///
+/// ## 为什么需要这个函数？
+///
+/// HMA 需要三次 WMA 嵌套，在 pandas 中要写多次 `rolling().apply()` 并手动对齐预热区间，
+/// 既繁琐又慢。这里在 Rust 中一次性完成，所有 WMA 都是 O(n) 的增量实现。
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `window`: HMA 周期，常用 9、16、20 等
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同：
+/// - 前 `window + ⌊√window⌋ - 2` 个元素为 `None`（两层 WMA 的预热期）
+///
+/// # 注意事项
+///
+/// - `window/2` 与 `√window` 均向下取整，且至少为 1
+pub fn vectorized_hma(prices: &[f64], window: usize) -> Vec<Option<f64>> {
+    if prices.is_empty() || window == 0 {
+        return vec![None; prices.len()];
+    }
+
+    let half = (window / 2).max(1);
+    let sqrt_n = ((window as f64).sqrt() as usize).max(1);
+
+    let wma_half = vectorized_wma(prices, half);
+    let wma_full = vectorized_wma(prices, window);
+    let raw: Vec<Option<f64>> = wma_half
+        .iter()
+        .zip(wma_full.iter())
+        .map(|(h, f)| match (h, f) {
+            (Some(h), Some(f)) => Some(2.0 * h - f),
+            _ => None,
+        })
+        .collect();
+
+    wma_of_series(&raw, sqrt_n)
+}
+
+/// 加权移动平均线（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_wma
+///
+/// wma_10 = compute_wma(closes, 10)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, window, fill="none"))]
+pub fn compute_wma(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_wma(&prices, window)))
+}
+
+/// 赫尔移动平均线（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_hma
+///
+/// hma_20 = compute_hma(closes, 20)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, window, fill="none"))]
+pub fn compute_hma(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_hma(&prices, window)))
+}
+
+/// 计算考夫曼自适应移动平均线（KAMA）
+///
+/// KAMA 根据市场的"效率"自动调整平滑速度：趋势明确时接近快速 EMA，震荡时接近慢速 EMA，
+/// 从而在趋势中快速跟随、在震荡中减少假信号。
+///
+/// ## 工作原理（简单理解）
+///
+/// 1. **效率比 ER**：`ER = |price[i] - price[i-n]| / Σ|price[j] - price[j-1]|`
+///    - 单边行情时 ER 接近 1，来回震荡时 ER 接近 0
+/// 2. **平滑常数**：`SC = (ER × (fast_sc - slow_sc) + slow_sc)²`，
+///    其中 `fast_sc = 2/(fast+1)`，`slow_sc = 2/(slow+1)`
+/// 3. **递推**：`KAMA[i] = KAMA[i-1] + SC × (price[i] - KAMA[i-1])`
+///
+/// 每一步的平滑常数都依赖当前 ER，只能顺序计算，非常适合 Rust 循环。
+/// 分母中的波动总和使用滑动窗口维护，每一步 O(1)。
+///
+/// # 参数
+///
+/// - `prices`: 价格序列切片，按时间顺序排列
+/// - `er_window`: 效率比窗口，通常为 10
+/// - `fast`: 快速 EMA 周期，通常为 2
+/// - `slow`: 慢速 EMA 周期，通常为 30
+///
+/// # 返回值
+///
+/// 返回 `Vec<Option<f64>>`，长度与输入相同：
+/// - 前 `er_window` 个元素为 `None`（KAMA 以第 `er_window` 根 K 线的价格为种子）
+///
+/// # 注意事项
+///
+/// - 窗口内价格完全不变时 ER 记为 0，KAMA 按慢速常数平滑
+/// - 如果 `er_window`、`fast` 或 `slow` 为 0，返回全 `None` 向量
+pub fn vectorized_kama(prices: &[f64], er_window: usize, fast: usize, slow: usize) -> Vec<Option<f64>> {
+    let n = prices.len();
+    if n <= er_window || er_window == 0 || fast == 0 || slow == 0 {
+        return vec![None; n];
+    }
+
+    let fast_sc = 2.0 / (fast as f64 + 1.0);
+    let slow_sc = 2.0 / (slow as f64 + 1.0);
+
+    let mut result = vec![None; er_window];
+    result.reserve(n - er_window);
+
+    // 初始波动总和：前 er_window 个价格变化的绝对值之和
+    let mut volatility: f64 = (1..=er_window).map(|j| (prices[j] - prices[j - 1]).abs()).sum();
+    let mut kama = prices[er_window - 1];
+
+    for i in er_window..n {
+        if i > er_window {
+            volatility += (prices[i] - prices[i - 1]).abs();
+            volatility -= (prices[i - er_window] - prices[i - er_window - 1]).abs();
+        }
+        let direction = (prices[i] - prices[i - er_window]).abs();
+        let er = if volatility > 0.0 { (direction / volatility).min(1.0) } else { 0.0 };
+        let sc = (er * (fast_sc - slow_sc) + slow_sc).powi(2);
+        kama += sc * (prices[i] - kama);
+        result.push(Some(kama));
+    }
+
+    result
+}
+
+/// 考夫曼自适应移动平均线（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_kama
+///
+/// kama = compute_kama(closes)            # 默认 10 / 2 / 30
+/// kama = compute_kama(closes, 20, 2, 50)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (prices, er_window=10, fast=2, slow=30, fill="none"))]
+pub fn compute_kama(prices: Vec<f64>, er_window: usize, fast: usize, slow: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(WarmupFill::parse(fill)?.apply(vectorized_kama(&prices, er_window, fast, slow)))
+}
+
+/// 枢轴点计算方法
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PivotMethod {
+    /// 经典枢轴点（Floor Pivots）
+    Classic,
+    /// 斐波那契枢轴点
+    Fibonacci,
+    /// Camarilla 枢轴点
+    Camarilla,
+}
+
+impl PivotMethod {
+    /// 从字符串解析计算方法（不区分大小写），支持 "classic"、"fibonacci"/"fib"、"camarilla"
+    pub fn parse(method: &str) -> Option<Self> {
+        match method.to_lowercase().as_str() {
+            "classic" | "standard" | "floor" => Some(PivotMethod::Classic),
+            "fibonacci" | "fib" => Some(PivotMethod::Fibonacci),
+            "camarilla" => Some(PivotMethod::Camarilla),
+            _ => None,
+        }
+    }
+
+    /// 该方法输出的价位名称（按输出顺序）
+    pub fn level_names(&self) -> &'static [&'static str] {
+        match self {
+            PivotMethod::Camarilla => &["pp", "r1", "r2", "r3", "r4", "s1", "s2", "s3", "s4"],
+            _ => &["pp", "r1", "r2", "r3", "s1", "s2", "s3"],
+        }
+    }
+}
+
+/// 根据一个交易时段的最高价、最低价、收盘价计算枢轴点价位
+///
+/// 返回值顺序与 `PivotMethod::level_names()` 一致。
+///
+/// - **Classic**: `P = (H+L+C)/3`，`R1 = 2P-L`，`S1 = 2P-H`，`R2 = P+(H-L)`，`S2 = P-(H-L)`，
+///   `R3 = H+2(P-L)`，`S3 = L-2(H-P)`
+/// - **Fibonacci**: `P` 同上，`R1/R2/R3 = P + 0.382/0.618/1.0 × (H-L)`，支撑位对称
+/// - **Camarilla**: `R1..R4 = C + (H-L) × 1.1/12, 1.1/6, 1.1/4, 1.1/2`，支撑位对称
+pub fn pivot_levels(high: f64, low: f64, close: f64, method: PivotMethod) -> Vec<f64> {
+    let pp = (high + low + close) / 3.0;
+    let range = high - low;
+    match method {
+        PivotMethod::Classic => vec![
+            pp,
+            2.0 * pp - low,
+            pp + range,
+            high + 2.0 * (pp - low),
+            2.0 * pp - high,
+            pp - range,
+            low - 2.0 * (high - pp),
+        ],
+        PivotMethod::Fibonacci => vec![
+            pp,
+            pp + 0.382 * range,
+            pp + 0.618 * range,
+            pp + range,
+            pp - 0.382 * range,
+            pp - 0.618 * range,
+            pp - range,
+        ],
+        PivotMethod::Camarilla => vec![
+            pp,
+            close + range * 1.1 / 12.0,
+            close + range * 1.1 / 6.0,
+            close + range * 1.1 / 4.0,
+            close + range * 1.1 / 2.0,
+            close - range * 1.1 / 12.0,
+            close - range * 1.1 / 6.0,
+            close - range * 1.1 / 4.0,
+            close - range * 1.1 / 2.0,
+        ],
+    }
+}
+
+/// 计算枢轴点价位（Pivot Points）
+///
+/// 枢轴点是日内交易中最常用的支撑 / 阻力参考位，由**前一交易时段**的最高价、最低价和收盘价计算，
+/// 在当前交易时段内保持不变。
+///
+/// ## 为什么需要这个函数？
+///
+/// 日内策略需要把"昨日"的枢轴点对齐到"今日"的每一根分钟 K 线上。用 pandas 实现需要先按日重采样、
+/// 平移一天、再按日期 merge 回分钟数据，步骤多且容易错位。这个函数一次性完成分组、计算和对齐。
+///
+/// ## 工作原理（简单理解）
+///
+/// 1. **按日分组**：将 `daily_bars` 按日期分组，每组取最高价、最低价和最后一根的收盘价
+///    （因此传入日线或分钟线都可以）
+/// 2. **计算价位**：对每个交易日，按 `method` 计算枢轴点价位
+/// 3. **对齐输出**：对目标序列中的每根 K 线，取**日期严格早于它**的最近一个交易日的价位
+///    - 目标序列默认为 `daily_bars` 本身，传入 `intraday_bars` 时对齐到日内 K 线
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import compute_pivots
+///
+/// # 用日线计算，对齐到 1 分钟 K 线
+/// levels = compute_pivots(daily_bars, "camarilla", intraday_bars=bars_1m)
+/// for bar, r3, s3 in zip(bars_1m, levels["r3"], levels["s3"]):
+///     if r3 is not None and bar["close"] > r3:
+///         ...
+/// ```
+///
+/// # 参数
+///
+/// - `daily_bars`: K 线字典列表，至少包含 `datetime`、`high`、`low`、`close`，按时间顺序排列
+/// - `method`: 计算方法，`"classic"`（默认）、`"fibonacci"` 或 `"camarilla"`
+/// - `intraday_bars`: 可选，需要对齐的日内 K 线列表
+///
+/// # 返回值
+///
+/// 返回字典 `{价位名称: 列表}`，列表长度与目标序列相同：
+/// - Classic / Fibonacci: `pp`, `r1`-`r3`, `s1`-`s3`
+/// - Camarilla: `pp`, `r1`-`r4`, `s1`-`s4`
+/// - 第一个交易日（没有前一交易时段）对应的元素为 `None`
+///
+/// # 注意事项
+///
+/// - 无法解析 `datetime` 的 K 线会被跳过分组，对应输出为 `None`
+/// - 不支持的 `method` 会返回 `ValueError`
+#[pyfunction]
+#[pyo3(signature = (daily_bars, method="classic", intraday_bars=None, fill="none"))]
+pub fn compute_pivots(
+    py: Python<'_>,
+    daily_bars: &Bound<'_, PyList>,
+    method: &str,
+    intraday_bars: Option<&Bound<'_, PyList>>,
+    fill: &str,
+) -> PyResult<PyObject> {
+    let fill = WarmupFill::parse(fill)?;
+    let pivot_method = PivotMethod::parse(method).ok_or_else(|| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Unsupported pivot method: {} (expected classic/fibonacci/camarilla)",
+            method
+        ))
+    })?;
+
+    let source = extract_bars_data(daily_bars.as_gil_ref())?;
+    let session_of = |dt: &Option<String>| -> Option<NaiveDate> {
+        dt.as_deref().and_then(parse_datetime).map(|d| d.date())
+    };
+
+    // 按日期分组：(日期, 最高价, 最低价, 收盘价)
+    let mut sessions: Vec<(NaiveDate, f64, f64, f64)> = Vec::new();
+    for bar in &source {
+        let date = match session_of(&bar.datetime) { Some(d) => d, None => continue };
+        match sessions.last_mut() {
+            Some(last) if last.0 == date => {
+                last.1 = last.1.max(bar.high);
+                last.2 = last.2.min(bar.low);
+                last.3 = bar.close;
+            }
+            _ => sessions.push((date, bar.high, bar.low, bar.close)),
+        }
+    }
+    let session_levels: Vec<Vec<f64>> = sessions
+        .iter()
+        .map(|&(_, h, l, c)| pivot_levels(h, l, c, pivot_method))
+        .collect();
+
+    // 目标序列：默认对齐到 daily_bars 本身
+    let target_dates: Vec<Option<NaiveDate>> = match intraday_bars {
+        Some(list) => extract_bars_data(list.as_gil_ref())?.iter().map(|b| session_of(&b.datetime)).collect(),
+        None => source.iter().map(|b| session_of(&b.datetime)).collect(),
+    };
+
+    let names = pivot_method.level_names();
+    let mut columns: Vec<Vec<Option<f64>>> = vec![Vec::with_capacity(target_dates.len()); names.len()];
+    // 指向"日期严格早于当前 K 线"的交易日数量（目标序列按时间排序，指针单调前进）
+    let mut ptr = 0usize;
+    for date in &target_dates {
+        let levels = match date {
+            Some(d) => {
+                while ptr < sessions.len() && sessions[ptr].0 < *d { ptr += 1; }
+                if ptr > 0 { Some(&session_levels[ptr - 1]) } else { None }
+            }
+            None => None,
+        };
+        for (k, col) in columns.iter_mut().enumerate() {
+            col.push(levels.map(|lv| lv[k]));
+        }
+    }
+
+    let out = PyDict::new_bound(py);
+    for (name, col) in names.iter().zip(columns) {
+        out.set_item(*name, fill.apply(col))?;
+    }
+    Ok(out.into())
+}
+
+// ============================================================================
+// Batch Indicator Computation (compute_indicators)
+// ============================================================================
+
+/// 按列存储的 OHLCV 数据
+///
+/// 批量指标计算的输入：一次性从 bar 列表中提取各字段，所有指标共享同一份列数据，
+/// 避免每个指标都单独做一次 Python → Rust 转换。
+#[derive(Clone, Debug, Default)]
+pub struct OhlcvColumns {
+    pub open: Vec<f64>,
+    pub high: Vec<f64>,
+    pub low: Vec<f64>,
+    pub close: Vec<f64>,
+    pub volume: Vec<f64>,
+}
+
+impl OhlcvColumns {
+    /// 从预提取的 bar 数据构建列
+    pub(crate) fn from_bars(bars: &[crate::BarData]) -> Self {
+        let mut cols = OhlcvColumns {
+            open: Vec::with_capacity(bars.len()),
+            high: Vec::with_capacity(bars.len()),
+            low: Vec::with_capacity(bars.len()),
+            close: Vec::with_capacity(bars.len()),
+            volume: Vec::with_capacity(bars.len()),
+        };
+        for b in bars {
+            cols.open.push(b.open);
+            cols.high.push(b.high);
+            cols.low.push(b.low);
+            cols.close.push(b.close);
+            cols.volume.push(b.volume);
+        }
+        cols
+    }
+
+    /// 按字段名取列（"open"/"high"/"low"/"close"/"volume"）
+    fn field(&self, name: &str) -> Option<&[f64]> {
+        match name {
+            "open" => Some(&self.open),
+            "high" => Some(&self.high),
+            "low" => Some(&self.low),
+            "close" => Some(&self.close),
+            "volume" => Some(&self.volume),
+            _ => None,
+        }
+    }
+}
+
+/// 指标类型及其参数
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndicatorKind {
+    Sma { window: usize },
+    Ema { window: usize },
+    Wma { window: usize },
+    Hma { window: usize },
+    Rsi { window: usize },
+    Std { window: usize, ddof: usize },
+    Var { window: usize, ddof: usize },
+    Zscore { window: usize, ddof: usize },
+    Roc { window: usize },
+    Momentum { window: usize },
+    Trix { window: usize },
+    Kama { er_window: usize, fast: usize, slow: usize },
+    RollingMax { window: usize },
+    RollingMin { window: usize },
+    Linreg { window: usize },
+    Donchian { window: usize },
+    Psar { af_start: f64, af_step: f64, af_max: f64 },
+    Mfi { window: usize },
+}
+
+/// 单个指标规格：指标类型 + 输出名称 + 输入字段
+///
+/// 对应 Python 端的字典，例如 `{"kind": "sma", "window": 20}`、
+/// `{"kind": "zscore", "window": 60, "source": "volume", "name": "vol_z"}`。
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndicatorSpec {
+    /// 指标类型及参数
+    pub kind: IndicatorKind,
+    /// 输出名称（多输出指标会在此基础上追加后缀）
+    pub name: String,
+    /// 单序列指标使用的输入字段，默认 "close"
+    pub source: String,
+    /// 预热区间填充方式
+    pub fill: WarmupFill,
+}
+
+fn spec_usize(d: &Bound<'_, PyDict>, key: &str, default: Option<usize>) -> PyResult<usize> {
+    match d.get_item(key)?.and_then(|v| v.extract::<usize>().ok()).or(default) {
+        Some(v) => Ok(v),
+        None => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Indicator spec is missing required integer field '{}'",
+            key
+        ))),
+    }
+}
+
+fn spec_f64(d: &Bound<'_, PyDict>, key: &str, default: f64) -> PyResult<f64> {
+    Ok(d.get_item(key)?.and_then(|v| v.extract::<f64>().ok()).unwrap_or(default))
+}
+
+impl IndicatorSpec {
+    /// 从 Python 字典解析指标规格
+    ///
+    /// 必需字段 `kind`；大多数指标需要 `window`；可选字段 `name`、`source`、`fill` 以及各指标的专有参数
+    /// （如 `ddof`、`er_window`/`fast`/`slow`、`af_start`/`af_step`/`af_max`）。
+    /// 规格中没有 `fill` 时使用 `default_fill`。
+    pub fn from_pydict(d: &Bound<'_, PyDict>, default_fill: WarmupFill) -> PyResult<Self> {
+        let kind_str: String = d
+            .get_item("kind")?
+            .and_then(|v| v.extract().ok())
+            .ok_or_else(|| PyErr::new::<pyo3::exceptions::PyValueError, _>("Indicator spec is missing 'kind'"))?;
+        let kind_lower = kind_str.to_lowercase();
+
+        let (kind, default_name) = match kind_lower.as_str() {
+            "sma" | "ema" | "wma" | "hma" | "rsi" | "roc" | "momentum" | "trix" | "rolling_max" | "rolling_min"
+            | "linreg" | "donchian" | "mfi" => {
+                let window = spec_usize(d, "window", None)?;
+                let kind = match kind_lower.as_str() {
+                    "sma" => IndicatorKind::Sma { window },
+                    "ema" => IndicatorKind::Ema { window },
+                    "wma" => IndicatorKind::Wma { window },
+                    "hma" => IndicatorKind::Hma { window },
+                    "rsi" => IndicatorKind::Rsi { window },
+                    "roc" => IndicatorKind::Roc { window },
+                    "momentum" => IndicatorKind::Momentum { window },
+                    "trix" => IndicatorKind::Trix { window },
+                    "rolling_max" => IndicatorKind::RollingMax { window },
+                    "rolling_min" => IndicatorKind::RollingMin { window },
+                    "linreg" => IndicatorKind::Linreg { window },
+                    "donchian" => IndicatorKind::Donchian { window },
+                    _ => IndicatorKind::Mfi { window },
+                };
+                (kind, format!("{}_{}", kind_lower, window))
+            }
+            "std" | "var" | "zscore" => {
+                let window = spec_usize(d, "window", None)?;
+                let ddof = spec_usize(d, "ddof", Some(1))?;
+                let kind = match kind_lower.as_str() {
+                    "std" => IndicatorKind::Std { window, ddof },
+                    "var" => IndicatorKind::Var { window, ddof },
+                    _ => IndicatorKind::Zscore { window, ddof },
+                };
+                (kind, format!("{}_{}", kind_lower, window))
+            }
+            "kama" => {
+                let er_window = spec_usize(d, "er_window", Some(10))?;
+                let er_window = spec_usize(d, "window", Some(er_window))?;
+                let fast = spec_usize(d, "fast", Some(2))?;
+                let slow = spec_usize(d, "slow", Some(30))?;
+                (IndicatorKind::Kama { er_window, fast, slow }, format!("kama_{}", er_window))
+            }
+            "psar" => {
+                let af_start = spec_f64(d, "af_start", 0.02)?;
+                let af_step = spec_f64(d, "af_step", 0.02)?;
+                let af_max = spec_f64(d, "af_max", 0.2)?;
+                (IndicatorKind::Psar { af_start, af_step, af_max }, "psar".to_string())
+            }
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unsupported indicator kind: {}",
+                    kind_str
+                )))
+            }
+        };
+
+        let name = d.get_item("name")?.and_then(|v| v.extract::<String>().ok()).unwrap_or(default_name);
+        let source = d
+            .get_item("source")?
+            .and_then(|v| v.extract::<String>().ok())
+            .unwrap_or_else(|| "close".to_string());
+        if OhlcvColumns::default().field(&source).is_none() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unsupported indicator source field: {} (expected open/high/low/close/volume)",
+                source
+            )));
+        }
+
+        let fill = match d.get_item("fill")?.and_then(|v| v.extract::<String>().ok()) {
+            Some(f) => WarmupFill::parse(&f)?,
+            None => default_fill,
+        };
+
+        Ok(IndicatorSpec { kind, name, source, fill })
+    }
+
+    /// 在列数据上计算该指标，返回 `(输出名称, 数值序列)` 列表
+    ///
+    /// 单输出指标返回一项；唐奇安通道返回 `{name}_upper`/`{name}_lower`，
+    /// 线性回归返回 `{name}_slope`/`{name}_intercept`/`{name}_r2`。
+    pub fn compute(&self, cols: &OhlcvColumns) -> IndicatorOutputs {
+        let mut outputs = self.compute_raw(cols);
+        for (_, values) in outputs.iter_mut() {
+            *values = self.fill.apply(std::mem::take(values));
+        }
+        outputs
+    }
+
+    fn compute_raw(&self, cols: &OhlcvColumns) -> IndicatorOutputs {
+        let src = cols.field(&self.source).unwrap_or(&cols.close);
+        let single = |values: Vec<Option<f64>>| vec![(self.name.clone(), values)];
+        match self.kind {
+            IndicatorKind::Sma { window } => single(crate::vectorized_sma(src, window)),
+            IndicatorKind::Ema { window } => single(vectorized_ema(src, window)),
+            IndicatorKind::Wma { window } => single(vectorized_wma(src, window)),
+            IndicatorKind::Hma { window } => single(vectorized_hma(src, window)),
+            IndicatorKind::Rsi { window } => single(crate::vectorized_rsi(src, window)),
+            IndicatorKind::Std { window, ddof } => single(vectorized_rolling_std(src, window, ddof)),
+            IndicatorKind::Var { window, ddof } => single(vectorized_rolling_var(src, window, ddof)),
+            IndicatorKind::Zscore { window, ddof } => single(vectorized_zscore(src, window, ddof)),
+            IndicatorKind::Roc { window } => single(vectorized_roc(src, window)),
+            IndicatorKind::Momentum { window } => single(vectorized_momentum(src, window)),
+            IndicatorKind::Trix { window } => single(vectorized_trix(src, window)),
+            IndicatorKind::Kama { er_window, fast, slow } => single(vectorized_kama(src, er_window, fast, slow)),
+            IndicatorKind::RollingMax { window } => single(vectorized_rolling_max(src, window)),
+            IndicatorKind::RollingMin { window } => single(vectorized_rolling_min(src, window)),
+            IndicatorKind::Linreg { window } => {
+                let (slope, intercept, r2) = vectorized_linreg(src, window);
+                vec![
+                    (format!("{}_slope", self.name), slope),
+                    (format!("{}_intercept", self.name), intercept),
+                    (format!("{}_r2", self.name), r2),
+                ]
+            }
+            IndicatorKind::Donchian { window } => {
+                let (upper, lower) = vectorized_donchian(&cols.high, &cols.low, window);
+                vec![(format!("{}_upper", self.name), upper), (format!("{}_lower", self.name), lower)]
+            }
+            IndicatorKind::Psar { af_start, af_step, af_max } => {
+                single(vectorized_psar(&cols.high, &cols.low, af_start, af_step, af_max))
+            }
+            IndicatorKind::Mfi { window } => {
+                single(vectorized_mfi(&cols.high, &cols.low, &cols.close, &cols.volume, window))
+            }
+        }
+    }
+}
+
+/// 从 Python 列表解析一组指标规格
+pub fn parse_indicator_specs(specs: &Bound<'_, PyList>, default_fill: WarmupFill) -> PyResult<Vec<IndicatorSpec>> {
+    let mut out = Vec::with_capacity(specs.len());
+    for item in specs.iter() {
+        out.push(IndicatorSpec::from_pydict(item.downcast::<PyDict>()?, default_fill)?);
+    }
+    Ok(out)
+}
+
+/// 在列数据上批量计算一组指标
+///
+/// 按规格顺序返回所有输出序列；输出名称重复时，后面的指标覆盖前面的结果。
+pub fn compute_indicator_batch(cols: &OhlcvColumns, specs: &[IndicatorSpec]) -> IndicatorOutputs {
+    specs.iter().flat_map(|spec| spec.compute(cols)).collect()
+}
+
+/// 批量计算多个指标（Python 接口）
+///
+/// 一次调用计算任意多个指标，返回 `{名称: 数值列表}` 字典。
+///
+/// ## 为什么需要这个函数？
+///
+/// 使用多个指标的策略如果逐个调用 `compute_sma`、`compute_rsi`……，每次调用都要把价格列表从 Python
+/// 转换到 Rust，再把结果转换回来，K 个指标就是 K 次转换和 K 次 Python 调用。
+/// 这个函数只遍历一次 bar 列表提取 OHLCV 列，所有指标共享同一份列数据在 Rust 中完成计算。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import compute_indicators
+///
+/// ind = compute_indicators(bars, [
+///     {"kind": "sma", "window": 20},
+///     {"kind": "sma", "window": 60},
+///     {"kind": "rsi", "window": 14},
+///     {"kind": "zscore", "window": 20, "source": "volume", "name": "vol_z"},
+///     {"kind": "donchian", "window": 55},
+/// ])
+/// ind["sma_20"], ind["rsi_14"], ind["vol_z"], ind["donchian_55_upper"]
+/// ```
+///
+/// ## 规格字段
+///
+/// - `kind`（必需）：`sma`/`ema`/`wma`/`hma`/`rsi`/`std`/`var`/`zscore`/`roc`/`momentum`/`trix`/`kama`/
+///   `rolling_max`/`rolling_min`/`linreg`/`donchian`/`psar`/`mfi`
+/// - `window`：窗口大小（`psar` 不需要，`kama` 可用 `er_window`）
+/// - `name`：输出名称，默认 `"{kind}_{window}"`（`psar` 默认为 `"psar"`）
+/// - `source`：单序列指标的输入字段，默认 `"close"`
+/// - 专有参数：`ddof`（std/var/zscore）、`fast`/`slow`（kama）、`af_start`/`af_step`/`af_max`（psar）
+/// - `fill`：该指标的预热区间填充方式，覆盖函数级的 `fill` 参数
+///
+/// # 参数
+///
+/// - `bars`: K 线字典列表，包含 `open`/`high`/`low`/`close`/`volume`
+/// - `specs`: 指标规格字典列表
+/// - `fill`: 默认的预热区间填充方式（`none`/`nan`/`zero`/`ffill`），默认 `none`
+///
+/// # 返回值
+///
+/// 返回字典 `{名称: 列表}`，每个列表长度与 `bars` 相同，预热区间为 `None`
+///
+/// # 注意事项
+///
+/// - 不支持的 `kind` 或 `source`、缺少 `window` 时返回 `ValueError`
+/// - 唐奇安通道、PSAR、MFI 固定使用 high/low/close/volume，忽略 `source`
+#[pyfunction]
+#[pyo3(signature = (bars, specs, fill="none"))]
+pub fn compute_indicators(py: Python<'_>, bars: &Bound<'_, PyList>, specs: &Bound<'_, PyList>, fill: &str) -> PyResult<PyObject> {
+    let specs = parse_indicator_specs(specs, WarmupFill::parse(fill)?)?;
+    let cols = OhlcvColumns::from_bars(&extract_bars_data(bars.as_gil_ref())?);
+    let out = PyDict::new_bound(py);
+    for (name, values) in compute_indicator_batch(&cols, &specs) {
+        out.set_item(name, values)?;
+    }
+    Ok(out.into())
+}
+
+/// 多标的并行批量计算指标（Python 接口）
+///
+/// 对成百上千个标的同时计算同一组指标，返回嵌套字典 `{标的: {名称: 数值列表}}`，
+/// 用于横截面因子构建。
+///
+/// ## 工作原理（简单理解）
+///
+/// 1. 持有 GIL 时把每个标的的数据提取为 Rust 列数据
+/// 2. 释放 GIL，用 rayon 在线程池中按标的并行计算所有指标
+/// 3. 重新获取 GIL，把结果组装为 Python 字典
+///
+/// 计算阶段不接触任何 Python 对象，因此可以充分利用多核。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import compute_indicators_multi
+///
+/// closes = {"000001.SZ": [10.1, 10.3, ...], "600000.SH": [7.2, 7.1, ...]}
+/// out = compute_indicators_multi(closes, [
+///     {"kind": "roc", "window": 20},
+///     {"kind": "zscore", "window": 60},
+/// ])
+/// mom = {sym: ind["roc_20"][-1] for sym, ind in out.items()}
+/// ```
+///
+/// # 参数
+///
+/// - `symbol_to_prices`: 字典 `{标的: 数据}`，数据可以是收盘价列表，也可以是 K 线字典列表
+/// - `specs`: 指标规格字典列表（格式同 `compute_indicators`）
+/// - `fill`: 默认的预热区间填充方式，默认 `none`
+///
+/// # 返回值
+///
+/// 返回嵌套字典 `{标的: {名称: 列表}}`
+///
+/// # 注意事项
+///
+/// - 传入收盘价列表时，open/high/low/close 都视为该价格、volume 视为 0，
+///   因此依赖 high/low/volume 的指标（donchian、psar、mfi）需要传入 K 线字典列表
+/// - 各标的长度可以不同
+#[pyfunction]
+#[pyo3(signature = (symbol_to_prices, specs, fill="none"))]
+pub fn compute_indicators_multi(
+    py: Python<'_>,
+    symbol_to_prices: &Bound<'_, PyDict>,
+    specs: &Bound<'_, PyList>,
+    fill: &str,
+) -> PyResult<PyObject> {
+    let specs = parse_indicator_specs(specs, WarmupFill::parse(fill)?)?;
+
+    // 持有 GIL 时提取所有标的的数据
+    let mut inputs: Vec<(String, OhlcvColumns)> = Vec::with_capacity(symbol_to_prices.len());
+    for (key, value) in symbol_to_prices.iter() {
+        let symbol: String = key.extract()?;
+        let list = value.downcast::<PyList>()?;
+        let is_bars = !list.is_empty() && list.get_item(0)?.downcast::<PyDict>().is_ok();
+        let cols = if is_bars {
+            OhlcvColumns::from_bars(&extract_bars_data(list.as_gil_ref())?)
+        } else {
+            let prices: Vec<f64> = list.extract()?;
+            OhlcvColumns {
+                open: prices.clone(),
+                high: prices.clone(),
+                low: prices.clone(),
+                volume: vec![0.0; prices.len()],
+                close: prices,
+            }
+        };
+        inputs.push((symbol, cols));
+    }
+
+    // 释放 GIL 并行计算
+    let results: Vec<(String, IndicatorOutputs)> = py.allow_threads(|| {
+        inputs
+            .par_iter()
+            .map(|(symbol, cols)| (symbol.clone(), compute_indicator_batch(cols, &specs)))
+            .collect()
+    });
+
+    let out = PyDict::new_bound(py);
+    for (symbol, outputs) in results {
+        let inner = PyDict::new_bound(py);
+        for (name, values) in outputs {
+            inner.set_item(name, values)?;
+        }
+        out.set_item(symbol, inner)?;
+    }
+    Ok(out.into())
+}
+
+// ============================================================================
+// NumPy Interface (zero-copy input, NaN for missing values)
+// ============================================================================
+//
+// 每个数值序列指标都有对应的 `*_np` 版本。`compute_pivots` 按 bar 的 `datetime` 分组到交易日，
+// 输入是 K 线字典而不是数值数组，因此没有 numpy 版本；`rolling_apply` 每个窗口都要回调 Python，
+// 数组转换不是瓶颈，同样不提供。
+
+/// 借用 numpy 数组的底层缓冲区
+///
+/// C 连续数组直接借用内存（零拷贝）；切片视图等非连续数组才复制一份。
+fn readonly_slice<'a>(arr: &'a PyReadonlyArray1<'_, f64>) -> Cow<'a, [f64]> {
+    match arr.as_slice() {
+        Ok(s) => Cow::Borrowed(s),
+        Err(_) => Cow::Owned(arr.as_array().iter().copied().collect()),
+    }
+}
+
+/// 把 `Option` 序列转换为以 NaN 表示缺失值的序列
+fn option_to_nan(values: Vec<Option<f64>>) -> Vec<f64> {
+    values.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect()
+}
+
+/// 为 `(prices, window)` 形式的指标生成 numpy 版本的 Python 接口
+///
+/// numpy 版本的 `fill` 默认为 `"nan"`（`"none"` 同样输出 NaN）。
+macro_rules! numpy_window_indicator {
+    ($(#[$doc:meta])* $name:ident => $f:path) => {
+        $(#[$doc])*
+        #[pyfunction]
+        #[pyo3(signature = (prices, window, fill="nan"))]
+        pub fn $name<'py>(
+            py: Python<'py>,
+            prices: PyReadonlyArray1<'py, f64>,
+            window: usize,
+            fill: &str,
+        ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+            let values = WarmupFill::parse(fill)?.apply($f(&readonly_slice(&prices), window));
+            Ok(option_to_nan(values).into_pyarray_bound(py))
+        }
+    };
+}
+
+numpy_window_indicator!(
+    /// 简单移动平均（numpy 版本）：输入 `float64` 数组，返回 `float64` 数组，预热区间为 NaN
+    compute_sma_np => crate::vectorized_sma
+);
+numpy_window_indicator!(
+    /// RSI（numpy 版本）：输入 `float64` 数组，返回 `float64` 数组，预热区间为 NaN
+    compute_rsi_np => crate::vectorized_rsi
+);
+numpy_window_indicator!(
+    /// 指数移动平均（numpy 版本）
+    compute_ema_np => vectorized_ema
+);
+numpy_window_indicator!(
+    /// 加权移动平均（numpy 版本）
+    compute_wma_np => vectorized_wma
+);
+numpy_window_indicator!(
+    /// Hull 移动平均（numpy 版本）
+    compute_hma_np => vectorized_hma
+);
+numpy_window_indicator!(
+    /// 变化率（numpy 版本）
+    compute_roc_np => vectorized_roc
+);
+numpy_window_indicator!(
+    /// 动量（numpy 版本）
+    compute_momentum_np => vectorized_momentum
+);
+numpy_window_indicator!(
+    /// TRIX（numpy 版本）
+    compute_trix_np => vectorized_trix
+);
+numpy_window_indicator!(
+    /// 滚动最大值（numpy 版本）
+    compute_rolling_max_np => vectorized_rolling_max
+);
+numpy_window_indicator!(
+    /// 滚动最小值（numpy 版本）
+    compute_rolling_min_np => vectorized_rolling_min
+);
+
+/// 滚动标准差（numpy 版本）
+#[pyfunction]
+#[pyo3(signature = (prices, window, ddof=1, fill="nan"))]
+pub fn compute_rolling_std_np<'py>(
+    py: Python<'py>,
+    prices: PyReadonlyArray1<'py, f64>,
+    window: usize,
+    ddof: usize,
+    fill: &str,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let values = WarmupFill::parse(fill)?.apply(vectorized_rolling_std(&readonly_slice(&prices), window, ddof));
+    Ok(option_to_nan(values).into_pyarray_bound(py))
+}
+
+/// 滚动 Z 分数（numpy 版本）
+#[pyfunction]
+#[pyo3(signature = (prices, window, ddof=1, fill="nan"))]
+pub fn compute_zscore_np<'py>(
+    py: Python<'py>,
+    prices: PyReadonlyArray1<'py, f64>,
+    window: usize,
+    ddof: usize,
+    fill: &str,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let values = WarmupFill::parse(fill)?.apply(vectorized_zscore(&readonly_slice(&prices), window, ddof));
+    Ok(option_to_nan(values).into_pyarray_bound(py))
+}
+
+/// 考夫曼自适应移动平均（numpy 版本）
+#[pyfunction]
+#[pyo3(signature = (prices, er_window=10, fast=2, slow=30, fill="nan"))]
+pub fn compute_kama_np<'py>(
+    py: Python<'py>,
+    prices: PyReadonlyArray1<'py, f64>,
+    er_window: usize,
+    fast: usize,
+    slow: usize,
+    fill: &str,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let values = WarmupFill::parse(fill)?.apply(vectorized_kama(&readonly_slice(&prices), er_window, fast, slow));
+    Ok(option_to_nan(values).into_pyarray_bound(py))
+}
+
+/// 滚动方差（numpy 版本）
+#[pyfunction]
+#[pyo3(signature = (prices, window, ddof=1, fill="nan"))]
+pub fn compute_rolling_var_np<'py>(
+    py: Python<'py>,
+    prices: PyReadonlyArray1<'py, f64>,
+    window: usize,
+    ddof: usize,
+    fill: &str,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let values = WarmupFill::parse(fill)?.apply(vectorized_rolling_var(&readonly_slice(&prices), window, ddof));
+    Ok(option_to_nan(values).into_pyarray_bound(py))
+}
+
+/// 唐奇安通道（numpy 版本）：返回 `(upper, lower)` 两个数组
+#[pyfunction]
+#[pyo3(signature = (highs, lows, window, fill="nan"))]
+pub fn compute_donchian_np<'py>(
+    py: Python<'py>,
+    highs: PyReadonlyArray1<'py, f64>,
+    lows: PyReadonlyArray1<'py, f64>,
+    window: usize,
+    fill: &str,
+) -> PyResult<ChannelArrays<'py>> {
+    let fill = WarmupFill::parse(fill)?;
+    let (highs, lows) = (readonly_slice(&highs), readonly_slice(&lows));
+    ensure_same_len(&[highs.len(), lows.len()])?;
+    let (upper, lower) = vectorized_donchian(&highs, &lows, window);
+    Ok((
+        option_to_nan(fill.apply(upper)).into_pyarray_bound(py),
+        option_to_nan(fill.apply(lower)).into_pyarray_bound(py),
+    ))
+}
+
+/// 抛物线转向指标（numpy 版本）
+#[pyfunction]
+#[pyo3(signature = (highs, lows, af_start=0.02, af_step=0.02, af_max=0.2, fill="nan"))]
+pub fn compute_psar_np<'py>(
+    py: Python<'py>,
+    highs: PyReadonlyArray1<'py, f64>,
+    lows: PyReadonlyArray1<'py, f64>,
+    af_start: f64,
+    af_step: f64,
+    af_max: f64,
+    fill: &str,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let fill = WarmupFill::parse(fill)?;
+    let (highs, lows) = (readonly_slice(&highs), readonly_slice(&lows));
+    ensure_same_len(&[highs.len(), lows.len()])?;
+    let values = fill.apply(vectorized_psar(&highs, &lows, af_start, af_step, af_max));
+    Ok(option_to_nan(values).into_pyarray_bound(py))
+}
+
+/// 滚动相关系数（numpy 版本）：`with_cov=True` 时返回 `(corr, cov)` 两个数组
+#[pyfunction]
+#[pyo3(signature = (a, b, window, with_cov=false, fill="nan"))]
+pub fn compute_rolling_corr_np<'py>(
+    py: Python<'py>,
+    a: PyReadonlyArray1<'py, f64>,
+    b: PyReadonlyArray1<'py, f64>,
+    window: usize,
+    with_cov: bool,
+    fill: &str,
+) -> PyResult<PyObject> {
+    let fill = WarmupFill::parse(fill)?;
+    let (a, b) = (readonly_slice(&a), readonly_slice(&b));
+    ensure_same_len(&[a.len(), b.len()])?;
+    let (corr, cov) = vectorized_rolling_corr(&a, &b, window);
+    let corr = option_to_nan(fill.apply(corr)).into_pyarray_bound(py);
+    if with_cov {
+        Ok((corr, option_to_nan(fill.apply(cov)).into_pyarray_bound(py)).into_py(py))
+    } else {
+        Ok(corr.into_py(py))
+    }
+}
+
+/// 滚动线性回归（numpy 版本）：返回 `(slope, intercept, r2)` 三个数组
+#[pyfunction]
+#[pyo3(signature = (prices, window, fill="nan"))]
+pub fn compute_linreg_np<'py>(
+    py: Python<'py>,
+    prices: PyReadonlyArray1<'py, f64>,
+    window: usize,
+    fill: &str,
+) -> PyResult<LinRegArrays<'py>> {
+    let fill = WarmupFill::parse(fill)?;
+    let (slope, intercept, r2) = vectorized_linreg(&readonly_slice(&prices), window);
+    Ok((
+        option_to_nan(fill.apply(slope)).into_pyarray_bound(py),
+        option_to_nan(fill.apply(intercept)).into_pyarray_bound(py),
+        option_to_nan(fill.apply(r2)).into_pyarray_bound(py),
+    ))
+}
+
+/// 资金流量指标（numpy 版本）
+#[pyfunction]
+#[pyo3(signature = (highs, lows, closes, volumes, window=14, fill="nan"))]
+pub fn compute_mfi_np<'py>(
+    py: Python<'py>,
+    highs: PyReadonlyArray1<'py, f64>,
+    lows: PyReadonlyArray1<'py, f64>,
+    closes: PyReadonlyArray1<'py, f64>,
+    volumes: PyReadonlyArray1<'py, f64>,
+    window: usize,
+    fill: &str,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let fill = WarmupFill::parse(fill)?;
+    let (highs, lows) = (readonly_slice(&highs), readonly_slice(&lows));
+    let (closes, volumes) = (readonly_slice(&closes), readonly_slice(&volumes));
+    ensure_same_len(&[highs.len(), lows.len(), closes.len(), volumes.len()])?;
+    let values = fill.apply(vectorized_mfi(&highs, &lows, &closes, &volumes, window));
+    Ok(option_to_nan(values).into_pyarray_bound(py))
+}
+
+/// 批量计算多个指标（numpy 版本）
+///
+/// 与 `compute_indicators` 使用相同的规格格式，但输入输出都是 numpy 数组。
+///
+/// ## 为什么需要这个函数？
+///
+/// 在大数组上，指标计算本身只占很小一部分时间，大部分时间花在 Python 列表与
+/// `Vec<Option<f64>>` 之间的逐元素转换上。numpy 版本按整块内存读取输入数组，
+/// 输出的 `Vec<f64>` 直接交给 numpy 持有，缺失值用 NaN 表示，不再逐个创建 Python 对象。
+/// （单指标的 `compute_*_np` 函数对 C 连续数组完全零拷贝地借用输入。）
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// import numpy as np
+/// from engine_rust import compute_indicators_np
+///
+/// out = compute_indicators_np(
+///     df["close"].to_numpy(np.float64),
+///     [{"kind": "sma", "window": 20}, {"kind": "donchian", "window": 55}],
+///     high=df["high"].to_numpy(np.float64),
+///     low=df["low"].to_numpy(np.float64),
+/// )
+/// df["sma_20"] = out["sma_20"]
+/// ```
+///
+/// # 参数
+///
+/// - `close`: 收盘价数组
+/// - `specs`: 指标规格字典列表（格式同 `compute_indicators`）
+/// - `open`/`high`/`low`: 可选，缺省时使用 `close`
+/// - `volume`: 可选，缺省时为 0
+/// - `fill`: 默认的预热区间填充方式，默认 `nan`
+///
+/// # 返回值
+///
+/// 返回字典 `{名称: numpy 数组}`，预热区间为 NaN
+///
+/// # 注意事项
+///
+/// - 所有数组长度必须一致，否则返回 `ValueError`
+/// - 输入数组本身不应包含 NaN，指标计算不会跳过缺失值
+#[pyfunction]
+#[pyo3(signature = (close, specs, open=None, high=None, low=None, volume=None, fill="nan"))]
+#[allow(clippy::too_many_arguments)]
+pub fn compute_indicators_np<'py>(
+    py: Python<'py>,
+    close: PyReadonlyArray1<'py, f64>,
+    specs: &Bound<'py, PyList>,
+    open: Option<PyReadonlyArray1<'py, f64>>,
+    high: Option<PyReadonlyArray1<'py, f64>>,
+    low: Option<PyReadonlyArray1<'py, f64>>,
+    volume: Option<PyReadonlyArray1<'py, f64>>,
+    fill: &str,
+) -> PyResult<PyObject> {
+    let specs = parse_indicator_specs(specs, WarmupFill::parse(fill)?)?;
+    let close = readonly_slice(&close).into_owned();
+    let column = |arr: &Option<PyReadonlyArray1<'py, f64>>, default: &[f64]| match arr {
+        Some(a) => readonly_slice(a).into_owned(),
+        None => default.to_vec(),
+    };
+    let cols = OhlcvColumns {
+        open: column(&open, &close),
+        high: column(&high, &close),
+        low: column(&low, &close),
+        volume: column(&volume, &vec![0.0; close.len()]),
+        close,
+    };
+    ensure_same_len(&[cols.close.len(), cols.open.len(), cols.high.len(), cols.low.len(), cols.volume.len()])?;
+
+    let out = PyDict::new_bound(py);
+    for (name, values) in compute_indicator_batch(&cols, &specs) {
+        out.set_item(name, option_to_nan(values).into_pyarray_bound(py))?;
+    }
+    Ok(out.into())
+}
+
+// ============================================================================
+// Rolling Apply (user-defined Python function)
+// ============================================================================
+
+/// 滚动窗口上应用自定义 Python 函数
+///
+/// 滑动窗口在 Rust 中维护，每个窗口调用一次 Python 函数；用作内置指标不够用时的兜底方案。
+///
+/// ## 为什么需要这个函数？
+///
+/// 内置指标无法覆盖所有自定义需求。在 Python 中手写滚动循环需要反复切片列表，
+/// 这个函数只负责把窗口切好交给 Python，并支持批量模式：一次把多个窗口交给函数，
+/// 大幅减少 Python 调用次数（例如函数内部用 numpy 向量化处理整批窗口）。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import rolling_apply
+///
+/// # 逐窗口调用：func(window: list[float]) -> float | None
+/// rng = rolling_apply(closes, 20, lambda w: max(w) - min(w))
+///
+/// # 批量调用：func(windows: list[list[float]]) -> list[float | None]
+/// import numpy as np
+/// med = rolling_apply(closes, 20, lambda ws: np.median(np.array(ws), axis=1).tolist(), batch_size=512)
+/// ```
+///
+/// # 参数
+///
+/// - `prices`: 输入序列
+/// - `window`: 窗口大小
+/// - `func`: Python 可调用对象
+/// - `batch_size`: 批量大小；0（默认）表示逐窗口调用，大于 0 时每次传入最多 `batch_size` 个窗口
+/// - `fill`: 预热区间填充方式，默认 `none`
+///
+/// # 返回值
+///
+/// 返回与 `prices` 等长的列表，前 `window-1` 个值为 `None`；函数返回 `None` 时对应位置也为 `None`
+///
+/// # 注意事项
+///
+/// - 批量模式下函数返回的列表长度必须等于传入的窗口数，否则返回 `ValueError`
+/// - `window` 为 0 时返回 `ValueError`
+/// - 函数抛出的异常会原样传回 Python
+#[pyfunction]
+#[pyo3(signature = (prices, window, func, batch_size=0, fill="none"))]
+pub fn rolling_apply(
+    py: Python<'_>,
+    prices: Vec<f64>,
+    window: usize,
+    func: PyObject,
+    batch_size: usize,
+    fill: &str,
+) -> PyResult<Vec<Option<f64>>> {
+    let fill = WarmupFill::parse(fill)?;
+    if window == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>("window must be positive"));
+    }
+    let n = prices.len();
+    let mut out: Vec<Option<f64>> = vec![None; n];
+    if n < window {
+        return Ok(fill.apply(out));
+    }
+
+    // 第 i 个窗口对应 prices[i+1-window..=i]，结果写入 out[i]
+    let first = window - 1;
+    if batch_size == 0 {
+        for i in first..n {
+            let win = PyList::new_bound(py, &prices[i + 1 - window..=i]);
+            out[i] = func.call1(py, (win,))?.extract::<Option<f64>>(py)?;
+        }
+        return Ok(fill.apply(out));
+    }
+
+    let mut start = first;
+    while start < n {
+        let end = (start + batch_size).min(n);
+        let windows = PyList::empty_bound(py);
+        for i in start..end {
+            windows.append(PyList::new_bound(py, &prices[i + 1 - window..=i]))?;
+        }
+        let results: Vec<Option<f64>> = func.call1(py, (windows,))?.extract(py)?;
+        if results.len() != end - start {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "rolling_apply batch function returned {} values for {} windows",
+                results.len(),
+                end - start
+            )));
+        }
+        out[start..end].copy_from_slice(&results);
+        start = end;
+    }
+    Ok(fill.apply(out))
+}
+
+// ============================================================================
+// Crossover Signals
+// ============================================================================
+
+/// 两条序列的交叉信号（向量化计算）
+///
+/// 逐 bar 返回：`1` 表示上穿（前一根 `a <= b`，当前 `a > b`），`-1` 表示下穿
+/// （前一根 `a >= b`，当前 `a < b`），其余为 `0`。
+///
+/// ## 为什么需要这个函数？
+///
+/// "快线上穿慢线"、"RSI 下穿 70" 这类判断几乎出现在每个策略里，
+/// 手写时很容易在相等、缺失值的边界上出错。
+///
+/// # 参数
+///
+/// - `a`: 第一条序列，`None` 表示缺失（如指标预热区间）
+/// - `b`: 第二条序列，长度与 `a` 相同
+///
+/// # 返回值
+///
+/// 与 `a` 等长的信号序列；第一个 bar 以及当前或前一个 bar 存在缺失值时为 `0`
+pub fn vectorized_cross(a: &[Option<f64>], b: &[Option<f64>]) -> Vec<i8> {
+    let n = a.len().min(b.len());
+    let mut out = vec![0i8; a.len()];
+    for i in 1..n {
+        if let (Some(pa), Some(pb), Some(ca), Some(cb)) = (a[i - 1], b[i - 1], a[i], b[i]) {
+            if pa <= pb && ca > cb {
+                out[i] = 1;
+            } else if pa >= pb && ca < cb {
+                out[i] = -1;
+            }
+        }
+    }
+    out
+}
+
+/// 计算交叉信号（Python 接口）
+///
+/// ```python
+/// from engine_rust import compute_sma, compute_rsi, compute_cross
+///
+/// fast, slow = compute_sma(closes, 10), compute_sma(closes, 30)
+/// golden = compute_cross(fast, slow)          # 1 = 金叉，-1 = 死叉
+/// rsi_exit = compute_cross(compute_rsi(closes, 14), 70.0)  # 与常数比较
+/// ```
+///
+/// # 参数
+///
+/// - `a`: 序列（可包含 `None`）
+/// - `b`: 与 `a` 等长的序列（可包含 `None`），或一个常数
+///
+/// # 返回值
+///
+/// 由 `1`/`-1`/`0` 组成的列表
+///
+/// # 注意事项
+///
+/// - `b` 为序列且长度与 `a` 不同时返回 `ValueError`
+#[pyfunction]
+pub fn compute_cross(a: Vec<Option<f64>>, b: &Bound<'_, PyAny>) -> PyResult<Vec<i8>> {
+    let b: Vec<Option<f64>> = match b.extract::<f64>() {
+        Ok(c) => vec![Some(c); a.len()],
+        Err(_) => b.extract()?,
+    };
+    ensure_same_len(&[a.len(), b.len()])?;
+    Ok(vectorized_cross(&a, &b))
+}
@@ -0,0 +1,181 @@
+//! 标的元数据模块
+//!
+//! K 线表只记录价格，标的本身的属性（名称、交易所、计价货币、最小变动价位、每手数量、合约乘数）
+//! 以前只能散落在 Python 脚本里。这个模块在 DuckDB 中增加一张 `instruments` 表，
+//! 与行情数据放在同一个库中统一管理，需要合约规格的功能可以通过 `load_instrument_rust()` 直接读取。
+//!
+//! ## 存储结构
+//!
+//! | 列 | 类型 | 说明 |
+//! |----|------|------|
+//! | `symbol` | VARCHAR | 交易标的（主键） |
+//! | `name` | VARCHAR | 名称 |
+//! | `exchange` | VARCHAR | 交易所 |
+//! | `currency` | VARCHAR | 计价货币 |
+//! | `tick_size` | DOUBLE | 最小变动价位 |
+//! | `lot_size` | DOUBLE | 每手数量（最小交易单位） |
+//! | `multiplier` | DOUBLE | 合约乘数（股票为 1） |
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import upsert_instrument, get_instrument
+//!
+//! upsert_instrument("data/backtest.db", "IF2406.CFFEX", name="沪深300股指期货", exchange="CFFEX",
+//!                   currency="CNY", tick_size=0.2, lot_size=1, multiplier=300)
+//! upsert_instrument("data/backtest.db", "IF2406.CFFEX", tick_size=0.2)  # 只更新传入的字段
+//! spec = get_instrument("data/backtest.db", "IF2406.CFFEX")
+//! ```
+//!
+//! # 注意事项
+//!
+//! - `upsert_instrument()` 只覆盖传入的字段，未传入（`None`）的字段保留原值
+//! - 查询不存在的标的返回 `None`
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::database::open_connection;
+
+/// 标的元数据表名
+const INSTRUMENTS_TABLE: &str = "instruments";
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+/// 一个标的的元数据
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Instrument {
+    pub symbol: String,
+    pub name: Option<String>,
+    pub exchange: Option<String>,
+    pub currency: Option<String>,
+    pub tick_size: Option<f64>,
+    pub lot_size: Option<f64>,
+    pub multiplier: Option<f64>,
+}
+
+fn ensure_instruments_table(conn: &duckdb::Connection) -> PyResult<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                symbol VARCHAR PRIMARY KEY,
+                name VARCHAR,
+                exchange VARCHAR,
+                currency VARCHAR,
+                tick_size DOUBLE,
+                lot_size DOUBLE,
+                multiplier DOUBLE
+            )",
+            INSTRUMENTS_TABLE
+        ),
+        [],
+    )
+    .map_err(|e| runtime_error(format!("Failed to ensure table {}: {}", INSTRUMENTS_TABLE, e)))?;
+    Ok(())
+}
+
+/// 读取标的元数据（Rust 实现），标的不存在时返回 `None`
+pub fn load_instrument_rust(db_path: &str, symbol: &str) -> PyResult<Option<Instrument>> {
+    let conn = open_connection(db_path)?;
+    ensure_instruments_table(&conn)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT symbol, name, exchange, currency, tick_size, lot_size, multiplier FROM {} WHERE symbol = ?",
+            INSTRUMENTS_TABLE
+        ))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let mut rows = stmt
+        .query_map(duckdb::params![symbol], |row| {
+            Ok(Instrument {
+                symbol: row.get(0)?,
+                name: row.get(1)?,
+                exchange: row.get(2)?,
+                currency: row.get(3)?,
+                tick_size: row.get(4)?,
+                lot_size: row.get(5)?,
+                multiplier: row.get(6)?,
+            })
+        })
+        .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?;
+    rows.next().transpose().map_err(|e| runtime_error(format!("Failed to read row: {}", e)))
+}
+
+/// 新增或更新标的元数据
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `name` / `exchange` / `currency`: 可选的名称、交易所、计价货币
+/// - `tick_size` / `lot_size` / `multiplier`: 可选的最小变动价位、每手数量、合约乘数，必须为正数
+///
+/// # 注意事项
+///
+/// - 标的已存在时只覆盖传入的字段，未传入的字段保留原值
+/// - `tick_size`、`lot_size`、`multiplier` 不为正数时返回 `ValueError`
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, name=None, exchange=None, currency=None, tick_size=None, lot_size=None, multiplier=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn upsert_instrument(
+    db_path: String,
+    symbol: String,
+    name: Option<String>,
+    exchange: Option<String>,
+    currency: Option<String>,
+    tick_size: Option<f64>,
+    lot_size: Option<f64>,
+    multiplier: Option<f64>,
+) -> PyResult<()> {
+    for (field, value) in [("tick_size", tick_size), ("lot_size", lot_size), ("multiplier", multiplier)] {
+        if let Some(v) = value {
+            if !(v.is_finite() && v > 0.0) {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "{} must be a positive number, got {}",
+                    field, v
+                )));
+            }
+        }
+    }
+
+    let conn = open_connection(&db_path)?;
+    ensure_instruments_table(&conn)?;
+    conn.execute(
+        &format!(
+            "INSERT INTO {t} (symbol, name, exchange, currency, tick_size, lot_size, multiplier)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (symbol) DO UPDATE SET
+                 name = COALESCE(excluded.name, {t}.name),
+                 exchange = COALESCE(excluded.exchange, {t}.exchange),
+                 currency = COALESCE(excluded.currency, {t}.currency),
+                 tick_size = COALESCE(excluded.tick_size, {t}.tick_size),
+                 lot_size = COALESCE(excluded.lot_size, {t}.lot_size),
+                 multiplier = COALESCE(excluded.multiplier, {t}.multiplier)",
+            t = INSTRUMENTS_TABLE
+        ),
+        duckdb::params![symbol, name, exchange, currency, tick_size, lot_size, multiplier],
+    )
+    .map_err(|e| runtime_error(format!("Failed to upsert instrument {}: {}", symbol, e)))?;
+    Ok(())
+}
+
+/// 查询标的元数据
+///
+/// # 返回值
+///
+/// 字典：`symbol`、`name`、`exchange`、`currency`、`tick_size`、`lot_size`、`multiplier`（未设置的字段为 `None`）；
+/// 标的不存在时返回 `None`
+#[pyfunction]
+pub fn get_instrument(py: Python<'_>, db_path: String, symbol: String) -> PyResult<Option<PyObject>> {
+    let Some(inst) = load_instrument_rust(&db_path, &symbol)? else { return Ok(None) };
+    let d = PyDict::new_bound(py);
+    d.set_item("symbol", inst.symbol)?;
+    d.set_item("name", inst.name)?;
+    d.set_item("exchange", inst.exchange)?;
+    d.set_item("currency", inst.currency)?;
+    d.set_item("tick_size", inst.tick_size)?;
+    d.set_item("lot_size", inst.lot_size)?;
+    d.set_item("multiplier", inst.multiplier)?;
+    Ok(Some(d.into()))
+}
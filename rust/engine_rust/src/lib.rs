@@ -35,26 +35,253 @@
 //! - 建议使用较大的 `batch_size`（1000-5000）以获得最佳性能
 //! - 所有价格和金额使用 `f64` 类型，注意浮点数精度问题
 
+use pyo3::exceptions::PyKeyboardInterrupt;
 use pyo3::prelude::*;
 use pyo3::types::{PyAny, PyDict, PyList};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Instant;
 
 // Database module for high-performance K-line operations
 mod database;
-pub use database::{get_market_data, resample_klines, save_klines, save_klines_from_csv};
+pub use database::{append_klines, clear_memory_db, export_klines_to_parquet, export_market_data_csv, find_gaps, get_data_range, get_market_data, list_periods, list_symbols, load_memory_db, resample_klines, save_klines, save_klines_from_csv, save_klines_from_csv_dir, save_klines_from_parquet, save_memory_db, upsample_klines};
+
+// Technical indicators module (vectorized, single-pass)
+mod indicators;
+pub use indicators::{
+    ChannelSeries, IndicatorKind, IndicatorOutputs, IndicatorSpec, OhlcvColumns, PivotMethod,
+    WarmupFill, compute_cross, compute_donchian, compute_donchian_np, compute_ema, compute_ema_np,
+    compute_hma, compute_hma_np, compute_indicator_batch, compute_indicators,
+    compute_indicators_multi, compute_indicators_np, compute_kama, compute_kama_np, compute_linreg,
+    compute_linreg_np, compute_mfi, compute_mfi_np, compute_momentum, compute_momentum_np,
+    compute_pivots, compute_psar, compute_psar_np, compute_roc, compute_roc_np,
+    compute_rolling_corr, compute_rolling_corr_np, compute_rolling_max, compute_rolling_max_np,
+    compute_rolling_min, compute_rolling_min_np, compute_rolling_std, compute_rolling_std_np,
+    compute_rolling_var, compute_rolling_var_np, compute_rsi_np, compute_sma_np, compute_trix,
+    compute_trix_np, compute_wma, compute_wma_np, compute_zscore, compute_zscore_np,
+    parse_indicator_specs, pivot_levels, rolling_apply, vectorized_cross, vectorized_donchian,
+    vectorized_ema, vectorized_hma, vectorized_kama, vectorized_linreg, vectorized_mfi,
+    vectorized_momentum, vectorized_psar, vectorized_roc, vectorized_rolling_corr,
+    vectorized_rolling_max, vectorized_rolling_min, vectorized_rolling_std, vectorized_rolling_var,
+    vectorized_trix, vectorized_wma, vectorized_zscore,
+};
+
+// Rule DSL strategies (parsed and evaluated in Rust)
+mod rules;
+pub use rules::RuleProgram;
+
+// Parameter optimization module (grid / random search)
+mod schedule;
+pub use schedule::{session_end_flags, RebalanceFreq, RebalanceSchedule};
+
+mod cancel;
+pub use cancel::CancelToken;
+use cancel::CancelReason;
+
+mod checkpoint;
+use checkpoint::{Checkpointer, RunState};
+
+// 回测结果后台写入 DuckDB（净值曲线、成交）
+mod result_writer;
+use result_writer::ResultWriter;
+
+mod progress;
+use progress::ProgressReporter;
+
+mod profile;
+use profile::RunProfile;
+
+// 提前终止条件（最大 bar 数、目标净值、破产阈值）
+mod early_stop;
+use early_stop::{EarlyStop, StopReason};
+
+// 单数据源回测中的多周期增量重采样
+mod timeframes;
+use timeframes::Timeframes;
+
+// 交易时段过滤（时段外订单拒绝或顺延）
+mod sessions;
+use sessions::{SessionPolicy, TradingSessions};
+
+// 按配置的 start/end 截取回测区间
+mod date_window;
+use date_window::DateWindow;
+
+// 买入持有基准（同数据与可选基准标的）
+mod baseline;
+use baseline::BuyAndHold;
+
+// 交易所交易日历（节假日、提前收盘日、年化天数）
+mod calendar;
+pub use calendar::TradingCalendar;
+
+// 派生周期物化（DuckDB 中按源周期增量维护粗周期表）
+mod materialize;
+pub use materialize::materialize_period;
+
+// 数据保留策略与数据库压缩
+mod retention;
+pub use retention::{compact_database, prune_klines};
+
+// 标的元数据（名称、交易所、合约规格）
+mod instruments;
+pub use instruments::{get_instrument, upsert_instrument};
+
+// 公司行为（分红、拆股）存储
+mod corporate_actions;
+pub use corporate_actions::{get_corporate_actions, save_corporate_actions};
+
+// Level-2 盘口快照存储（DuckDB）
+mod depth;
+pub use depth::{load_depth, save_depth};
+
+// 数据库结构版本与原地迁移
+mod migrations;
+pub use migrations::migrate_db;
+
+// 远程行情获取（币安 REST、CSV 地址、自定义数据源）写入 DuckDB
+mod fetch;
+pub use fetch::fetch_klines;
+
+// 分块流式读取 K 线（配合引擎的流式输入模式）
+mod market_iter;
+pub use market_iter::{iter_market_data, MarketDataIter};
+
+// 回测结果持久化（runs / run_equity / run_trades）
+mod run_store;
+pub use run_store::{load_backtest_result, save_backtest_result};
+
+// 数据质量统计（缺失 bar、零成交量、重复时间戳、价格跳变）
+mod quality;
+pub use quality::data_quality_report;
+
+// 分区 Parquet 数据集导出/导入（period/symbol/year）
+mod dataset;
+pub use dataset::{export_parquet_dataset, import_parquet_dataset};
+
+// 日志输出（级别、Python logging 转发、静默模式）
+mod logger;
+pub use logger::configure_logging;
+
+// 回测结果（dict 子类）按列构建 pandas / polars DataFrame
+mod frames;
+
+// 自定义 bar 字段（标准字段以外的数值/字符串，原样交给策略）
+mod bar_fields;
+use bar_fields::{ExtraFields, FieldNames, FieldValue};
+
+// 严格模式下的输入 bar 校验（OHLCV、时间格式与顺序，错误信息带行号）
+mod validation;
+use validation::{BarValidator, ParsedFields};
+
+// 未成交订单事件的原因代码（rejected / expired / cancelled）
+mod order_events;
+use order_events::OrderReason;
+
+// 多资产目标权重（换算为订单，整手取整与调仓容差）
+mod weights;
+use weights::{LotSizes, WeightSizer};
+
+// 持仓历史（每根 bar 的持仓快照）
+mod positions;
+use positions::PositionRow;
+
+// 组合敞口限制（总敞口 / 净敞口，超限订单截断或拒绝）
+mod exposure;
+use exposure::{Book, ExposureCheck, ExposureLimits};
+
+// 订单数量换算（净值比例 / 固定风险，整手与最小变动价位取整）
+mod sizing;
+use sizing::{KellyStats, OrderSizing, SizingBasis};
+
+// 波动率目标（按近期收益率估计的组合波动率缩放目标持仓）
+mod vol_target;
+use vol_target::{ReturnWindow, VolTarget};
+
+// 策略基类（默认空钩子，跳过未重写的钩子）
+mod strategy;
+pub use strategy::Strategy;
+use strategy::StrategyHooks;
+
+mod portfolio;
+pub use portfolio::combine_strategies;
+
+// Columnar inputs (dict of arrays / pyarrow) for run()
+mod columnar;
+use columnar::extract_bars_any;
+
+// Streaming (iterator / cursor) inputs for run()
+mod stream;
+use stream::BarStream;
+
+// Live paper trading (bars pushed one at a time)
+mod live;
+pub use live::PaperTrader;
+
+// Step-by-step replay of a backtest
+mod replay;
+pub use replay::Replayer;
+
+mod optimize;
+pub use optimize::{
+    combinatorial_purged, combinatorial_purged_split, optimize_grid, optimize_random, purged_kfold,
+    purged_kfold_split, CvSplit,
+};
 
 // 预提取的bar数据结构
 #[derive(Clone, Debug)]
 struct BarData {
     datetime: Option<String>,
+    /// 输入为整数 epoch 毫秒时的原始时间戳（此时 `datetime` 为由它格式化的 UTC 时间字符串）
+    timestamp: Option<i64>,
     open: f64,
     high: f64,
     low: f64,
     close: f64,
     volume: f64,
     symbol: Option<String>,
+    /// 标准字段以外的数值与字符串字段（原样放回交给策略的 bar 字典）
+    extra: ExtraFields,
+    /// 提取时成功转换为数值的 OHLCV 字段（严格模式据此报告缺失字段）
+    parsed: ParsedFields,
+}
+
+impl From<database::KlineBar> for BarData {
+    fn from(bar: database::KlineBar) -> Self {
+        BarData {
+            datetime: Some(bar.datetime),
+            timestamp: None,
+            open: bar.open,
+            high: bar.high,
+            low: bar.low,
+            close: bar.close,
+            volume: bar.volume,
+            symbol: Some(bar.symbol),
+            extra: Vec::new(),
+            parsed: ParsedFields::ALL,
+        }
+    }
+}
+
+impl BarData {
+    /// 时间的 epoch 毫秒：整数时间直接使用，字符串时间解析一次（没有或无法解析时为 `None`）
+    fn epoch_ms(&self) -> Option<i64> {
+        self.timestamp.or_else(|| self.datetime.as_deref().and_then(database::datetime_to_epoch_ms))
+    }
+}
+
+/// 单根 bar 的日历标记
+#[derive(Clone, Copy, Debug, Default)]
+struct BarFlags {
+    /// 需要调用 `on_rebalance`
+    rebalance: bool,
+    /// 交易日最后一根 bar，且需要调用 `on_session_end`
+    session_end: bool,
+    /// bar 在配置的交易时段之外
+    outside_session: bool,
 }
 
 /// 回测配置结构体
@@ -64,26 +291,74 @@ struct BarData {
 ///
 /// # 字段说明
 ///
-/// - `start`: 回测开始日期，格式为 "YYYY-MM-DD" 字符串
-/// - `end`: 回测结束日期，格式为 "YYYY-MM-DD" 字符串
+/// - `start`: 回测开始日期，格式为 "YYYY-MM-DD"（或 "YYYY-MM-DD HH:MM:SS"）字符串，空字符串表示不限制。
+///   引擎只回测区间内的 bar，结果中的 `date_range` 记录实际回测的时间范围
+/// - `end`: 回测结束日期（包含当天），格式同 `start`
 /// - `cash`: 初始资金，单位与价格单位一致（如人民币元）
 /// - `commission_rate`: 手续费率，例如 0.0005 表示 0.05%（万五）
 /// - `slippage_bps`: 滑点，单位为基点（basis points），例如 2.0 表示 2 个基点（0.02%）
 /// - `batch_size`: 批处理大小，用于减少 Python GIL 争用，建议设置为 1000-5000
+/// - `warmup_bars`: 预热 bar 数量，默认 0。前 `warmup_bars` 根 bar 只用于"热身"：
+///   指标照常计算、`next()` 照常调用（可通过 `warmup_call_next` 关闭），但不执行任何订单，
+///   也不计入净值曲线和统计指标
+/// - `warmup_call_next`: 预热期间是否调用策略的 `next()`，默认 `True`；
+///   为 `False` 时预热 bar 完全跳过策略回调
+/// - `rebalance`: 调仓频率，`"daily"`/`"weekly"`/`"monthly"`/`"quarterly"` 之一，默认 `None`。
+///   配置后引擎会在每个周期的最后一根 bar（在 `next()` 之后）调用策略的 `on_rebalance(ctx)`
+/// - `rebalance_dates`: 自定义调仓日期列表（如 `["2020-03-20", "2020-06-19"]`），默认 `None`。
+///   日期落在非交易日时顺延到之后的第一根 bar；可与 `rebalance` 同时使用
+/// - `seed`: 随机种子，默认 `None`。引擎中的随机组件（如未单独指定 `seed` 的 `optimize_random`
+///   参数采样，以及概率成交/冲击等随机执行模型）都从这个种子创建确定性的随机数生成器，
+///   保证相同配置下的回测结果完全可复现；为 `None` 时使用系统熵
+/// - `profile`: 是否开启耗时分析，默认 `False`。开启后 `run()` 等方法的结果中附带 `profile` 字典，
+///   拆分数据转换、Python 策略回调与 Rust 引擎（撮合、持仓）各自的耗时
+/// - `max_bars`: 最多处理的 bar 数（包括预热 bar），默认 `None` 表示不限制
+/// - `target_equity`: 目标净值，默认 `None`。净值达到该值后提前结束回测
+/// - `ruin_equity`: 破产阈值，默认 `None`。净值跌到该值及以下时提前结束回测；
+///   配置了任一提前终止条件时，结果中附带 `stop_reason` 说明回测为何结束
+/// - `sessions`: 允许交易的时段列表（如 `["09:30-11:30", "13:00-15:00"]`），默认 `None` 表示不限制
+/// - `session_policy`: 时段外订单的处理方式，`"reject"`（默认，拒绝）或 `"defer"`（顺延到下一个时段开盘）
+/// - `buy_and_hold`: 是否同时计算买入持有基准，默认 `False`。开启后结果中附带 `buy_and_hold`
+///   （同一份数据上全仓买入持有的净值曲线、统计指标和策略的超额收益）
+/// - `calendar`: 交易日历名称（`"SSE"`/`"SZSE"`/`"NYSE"`/`"CME"`/`"CRYPTO"`，或国内期货交易所如 `"SHFE"`），默认 `None`。
+///   配置后年化收益、波动率、夏普和 Calmar 使用该日历的年化天数（A 股 242、美股 252、加密货币 365），
+///   周期性调仓按日历上的下一个交易日判断周期末
+/// - `strict`: 严格模式，默认 `False`。开启后回测开始前逐根校验输入 bar（OHLCV 字段缺失或无效、`high`/`low`
+///   不包含开收盘价、时间无法解析、时间没有严格递增），第一处问题即返回带行号的 `ValueError`
+/// - `record_positions`: 是否记录持仓历史，默认 `False`。开启后结果中附带 `positions_history`
+///   （每根 bar 每个非零持仓一行：`datetime`、`symbol`、`position`、`price`、`market_value`），可用 `positions_df()` 转为表格
+/// - `max_gross_exposure`: 总敞口上限（Σ|持仓市值| 与净值之比），默认 `None` 表示不限制
+/// - `max_net_exposure`: 净敞口上限（|Σ持仓市值| 与净值之比），默认 `None` 表示不限制
+/// - `exposure_policy`: 成交后超出敞口上限的订单的处理方式，`"clip"`（默认，截断到不超限的最大数量）或 `"reject"`（拒绝），
+///   两种情况都触发带 `reason="exposure_limit"` 的 `on_order` 事件
+/// - `vol_target`: 年化波动率目标（如 `0.10`），默认 `None` 表示不开启。开启后引擎按最近 `vol_window` 根 bar
+///   的收益率估计目标持仓的组合波动率，把目标持仓缩放到该波动率（见 `vol_target` 模块说明）
+/// - `vol_window`: 估计波动率使用的收益率个数，默认 20，至少为 2
+/// - `vol_max_scale`: 缩放系数上限，默认 2.0（低波动时最多把目标持仓放大到 2 倍）
 ///
 /// # 使用示例
 ///
-/// ```rust,ignore
-/// use engine_rust::BacktestConfig;
-///
-/// let config = BacktestConfig::new(
-///     "2020-01-01".to_string(),
-///     "2020-12-31".to_string(),
-///     100000.0,      // 初始资金 10 万
-///     0.0005,        // 手续费率 0.05%
-///     2.0,           // 滑点 2 个基点
-///     1000,          // 批处理大小
-/// );
+/// 前三个参数（`start`、`end`、`cash`）按位置传入，其余参数都有默认值，建议按关键字传入：
+///
+/// ```python
+/// from engine_rust import BacktestConfig
+///
+/// cfg = BacktestConfig(
+///     "2020-01-01",
+///     "2020-12-31",
+///     100000.0,                 # 初始资金 10 万
+///     commission_rate=0.0005,   # 手续费率 0.05%
+///     slippage_bps=2.0,         # 滑点 2 个基点
+///     rebalance="monthly",      # 月末调仓
+///     seed=42,                  # 随机种子
+///     ruin_equity=50000.0,      # 净值跌破 5 万时停止
+///     sessions=["09:30-11:30", "13:00-15:00"],  # A 股交易时段
+///     session_policy="defer",   # 时段外订单顺延到下一个时段开盘
+///     buy_and_hold=True,        # 同时计算买入持有基准
+///     calendar="SSE",           # 上交所交易日历
+///     max_gross_exposure=1.0,   # 总敞口不超过净值
+///     vol_target=0.10,          # 年化波动率目标 10%
+/// )
 /// ```
 ///
 /// # 性能优化建议
@@ -96,11 +371,33 @@ struct BarData {
 ///
 /// # 注意事项
 ///
-/// - 日期格式必须为 "YYYY-MM-DD"，否则可能导致解析错误
+/// - 日期格式必须为 "YYYY-MM-DD" 或 "YYYY-MM-DD HH:MM:SS"，否则构造时抛出 `ValueError`
+/// - 数据中没有任何 bar 落在 `start`/`end` 区间内时，回测返回 `ValueError`
 /// - 手续费率是每次交易的费率，买入和卖出都会收取
 /// - 滑点会在成交价格上应用，买入时加滑点，卖出时减滑点
-#[pyclass]
-#[derive(Clone)]
+/// - 调仓频率和调仓日期在构造时校验，无法识别时抛出 `ValueError`
+/// - `ruin_equity` 必须小于 `target_equity`，`max_bars` 必须大于 0，否则抛出 `ValueError`
+/// - 交易时段格式无法解析或 `session_policy` 无法识别时抛出 `ValueError`
+/// - 买入持有基准按与策略相同的手续费率和滑点买入，`run_multi` 不计算该基准
+/// - 交易日历名称无法识别时抛出 `ValueError`；未配置日历时年化按 252 天
+/// - 敞口上限必须是正数，`exposure_policy` 无法识别时抛出 `ValueError`
+/// - `vol_target`、`vol_max_scale` 必须是正数，`vol_window` 小于 2 时抛出 `ValueError`
+///
+/// # 从文件加载配置
+///
+/// 配置可以保存在 JSON/TOML 文件中，键名与构造参数相同，未给出的键取构造函数的默认值；
+/// `to_dict()` 导出全部字段，可以随回测结果一起保存，便于复现：
+///
+/// ```python
+/// cfg = BacktestConfig.from_toml(open("configs/ma_cross.toml").read())
+/// cfg = BacktestConfig.from_dict({"start": "2020-01-01", "end": "2020-12-31", "cash": 100000, "calendar": "SSE"})
+/// save_backtest_result("data/runs.db", "ma_cross_v2", result, params={"config": cfg.to_dict()})
+/// ```
+///
+/// 配置支持 `==` 比较和 `pickle`（可以直接传给 `multiprocessing` 进程池），`repr()` 只列出与默认值不同的参数。
+#[pyclass(module = "engine_rust")]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BacktestConfig {
     /// 回测开始日期
     #[pyo3(get)]
@@ -113,20 +410,136 @@ pub struct BacktestConfig {
     pub cash: f64,
     /// 手续费率（例如 0.0005 表示 0.05%）
     #[pyo3(get)]
+    #[serde(default)]
     pub commission_rate: f64,
     /// 滑点（基点，例如 2.0 表示 2 个基点 = 0.02%）
     #[pyo3(get)]
+    #[serde(default)]
     pub slippage_bps: f64,
     /// 批处理大小，用于减少 Python GIL 争用（建议 1000-5000）
     #[pyo3(get)]
+    #[serde(default = "default_batch_size")]
     pub batch_size: usize,
+    /// 预热 bar 数量（不执行订单，不计入净值和统计）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub warmup_bars: usize,
+    /// 预热期间是否调用策略的 `next()`
+    #[pyo3(get)]
+    #[serde(default = "default_true")]
+    pub warmup_call_next: bool,
+    /// 调仓频率（daily/weekly/monthly/quarterly）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub rebalance: Option<String>,
+    /// 自定义调仓日期
+    #[pyo3(get)]
+    #[serde(default)]
+    pub rebalance_dates: Option<Vec<String>>,
+    /// 随机种子（随机执行模型与随机搜索共用；`None` 表示使用系统熵）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// 是否在结果中附带耗时分析（`profile`）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub profile: bool,
+    /// 最多处理的 bar 数（`None` 表示不限制）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub max_bars: Option<usize>,
+    /// 目标净值，达到后提前结束
+    #[pyo3(get)]
+    #[serde(default)]
+    pub target_equity: Option<f64>,
+    /// 破产阈值，净值跌到该值及以下时提前结束
+    #[pyo3(get)]
+    #[serde(default)]
+    pub ruin_equity: Option<f64>,
+    /// 允许交易的时段（如 "09:30-11:30"）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub sessions: Option<Vec<String>>,
+    /// 时段外订单的处理方式（reject/defer）
+    #[pyo3(get)]
+    #[serde(default = "default_session_policy")]
+    pub session_policy: String,
+    /// 是否同时计算买入持有基准
+    #[pyo3(get)]
+    #[serde(default)]
+    pub buy_and_hold: bool,
+    /// 交易日历名称（如 "SSE"）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub calendar: Option<String>,
+    /// 严格模式：回测前校验输入 bar（OHLCV、时间格式与顺序），有问题时报错
+    #[pyo3(get)]
+    #[serde(default)]
+    pub strict: bool,
+    /// 是否在结果中记录每根 bar 的持仓快照（`positions_history`）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub record_positions: bool,
+    /// 总敞口上限（净值的倍数）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub max_gross_exposure: Option<f64>,
+    /// 净敞口上限（净值的倍数）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub max_net_exposure: Option<f64>,
+    /// 超出敞口上限的订单的处理方式（clip/reject）
+    #[pyo3(get)]
+    #[serde(default = "default_exposure_policy")]
+    pub exposure_policy: String,
+    /// 年化波动率目标（`None` 表示不开启）
+    #[pyo3(get)]
+    #[serde(default)]
+    pub vol_target: Option<f64>,
+    /// 估计波动率使用的收益率个数
+    #[pyo3(get)]
+    #[serde(default = "default_vol_window")]
+    pub vol_window: usize,
+    /// 波动率目标的缩放系数上限
+    #[pyo3(get)]
+    #[serde(default = "default_vol_max_scale")]
+    pub vol_max_scale: f64,
 }
 
 #[pymethods]
 impl BacktestConfig {
     #[new]
-    #[pyo3(signature = (start, end, cash, commission_rate=0.0, slippage_bps=0.0, batch_size=1000))]
-    fn new(start: String, end: String, cash: f64, commission_rate: f64, slippage_bps: f64, batch_size: usize) -> Self {
+    #[pyo3(signature = (start, end, cash, commission_rate=0.0, slippage_bps=0.0, batch_size=1000, warmup_bars=0, warmup_call_next=true, rebalance=None, rebalance_dates=None, seed=None, profile=false, max_bars=None, target_equity=None, ruin_equity=None, sessions=None, session_policy="reject".to_string(), buy_and_hold=false, calendar=None, strict=false, record_positions=false, max_gross_exposure=None, max_net_exposure=None, exposure_policy="clip".to_string(), vol_target=None, vol_window=20, vol_max_scale=2.0))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        start: String,
+        end: String,
+        cash: f64,
+        commission_rate: f64,
+        slippage_bps: f64,
+        batch_size: usize,
+        warmup_bars: usize,
+        warmup_call_next: bool,
+        rebalance: Option<String>,
+        rebalance_dates: Option<Vec<String>>,
+        seed: Option<u64>,
+        profile: bool,
+        max_bars: Option<usize>,
+        target_equity: Option<f64>,
+        ruin_equity: Option<f64>,
+        sessions: Option<Vec<String>>,
+        session_policy: String,
+        buy_and_hold: bool,
+        calendar: Option<String>,
+        strict: bool,
+        record_positions: bool,
+        max_gross_exposure: Option<f64>,
+        max_net_exposure: Option<f64>,
+        exposure_policy: String,
+        vol_target: Option<f64>,
+        vol_window: usize,
+        vol_max_scale: f64,
+    ) -> PyResult<Self> {
         Self {
             start,
             end,
@@ -134,34 +547,322 @@ impl BacktestConfig {
             commission_rate,
             slippage_bps,
             batch_size,
+            warmup_bars,
+            warmup_call_next,
+            rebalance,
+            rebalance_dates,
+            seed,
+            profile,
+            max_bars,
+            target_equity,
+            ruin_equity,
+            sessions,
+            session_policy,
+            buy_and_hold,
+            calendar,
+            strict,
+            record_positions,
+            max_gross_exposure,
+            max_net_exposure,
+            exposure_policy,
+            vol_target,
+            vol_window,
+            vol_max_scale,
+        }
+        .validated()
+    }
+
+    /// 从字典构造配置（键名与构造参数相同，未给出的键取默认值）
+    ///
+    /// 未知的键、类型不符或校验失败时返回 `ValueError`。
+    #[staticmethod]
+    fn from_dict(py: Python<'_>, config: &Bound<'_, PyDict>) -> PyResult<Self> {
+        from_pydict::<Self>(py, config, "BacktestConfig")?.validated()
+    }
+
+    /// 从 JSON 文本构造配置（顶层为对象，键名与构造参数相同）
+    #[staticmethod]
+    fn from_json(text: &str) -> PyResult<Self> {
+        serde_json::from_str::<Self>(text)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid BacktestConfig: {}", e)))?
+            .validated()
+    }
+
+    /// 从 TOML 文本构造配置（顶层键名与构造参数相同）
+    #[staticmethod]
+    fn from_toml(text: &str) -> PyResult<Self> {
+        toml::from_str::<Self>(text)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid BacktestConfig: {}", e)))?
+            .validated()
+    }
+
+    /// 导出全部字段为字典（未设置的可选字段为 `None`），可以直接传给 `from_dict()`
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_pyobject(py, self, "BacktestConfig")
+    }
+
+    /// 只列出必填参数和与默认值不同的参数
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let defaults = serde_json::from_value::<Self>(serde_json::json!({
+            "start": self.start,
+            "end": self.end,
+            "cash": self.cash,
+        }))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid BacktestConfig: {}", e)))?;
+        let state = self.to_dict(py)?;
+        let defaults = defaults.to_dict(py)?;
+        let (state, defaults) = (state.downcast_bound::<PyDict>(py)?, defaults.downcast_bound::<PyDict>(py)?);
+        let mut parts = Vec::new();
+        for (key, value) in state.iter() {
+            let name: String = key.extract()?;
+            let changed = match defaults.get_item(&key)? {
+                Some(default) => !value.eq(default)?,
+                None => true,
+            };
+            if matches!(name.as_str(), "start" | "end" | "cash") || changed {
+                parts.push(format!("{}={}", name, value.repr()?));
+            }
         }
+        Ok(format!("BacktestConfig({})", parts.join(", ")))
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    /// pickle 支持：构造参数 + 全部字段
+    fn __getnewargs__(&self) -> (String, String, f64) {
+        (self.start.clone(), self.end.clone(), self.cash)
+    }
+
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.to_dict(py)
+    }
+
+    fn __setstate__(&mut self, py: Python<'_>, state: &Bound<'_, PyDict>) -> PyResult<()> {
+        *self = Self::from_dict(py, state)?;
+        Ok(())
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+/// 把 serde 可序列化的值转换为 Python 对象（经 JSON 中转，字段顺序与结构体定义一致）
+fn to_pyobject<T: Serialize>(py: Python<'_>, value: &T, what: &str) -> PyResult<PyObject> {
+    let text = serde_json::to_string(value)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to serialize {}: {}", what, e)))?;
+    Ok(py.import_bound("json")?.call_method1("loads", (text,))?.into())
+}
+
+/// 从 Python 字典反序列化（经 JSON 中转），字段不符时返回 `ValueError`
+fn from_pydict<T: serde::de::DeserializeOwned>(py: Python<'_>, dict: &Bound<'_, PyDict>, what: &str) -> PyResult<T> {
+    let text: String = py.import_bound("json")?.call_method1("dumps", (dict,))?.extract()?;
+    serde_json::from_str::<T>(&text)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid {}: {}", what, e)))
+}
+
+fn default_batch_size() -> usize {
+    1000
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_session_policy() -> String {
+    "reject".to_string()
+}
+
+fn default_exposure_policy() -> String {
+    "clip".to_string()
+}
+
+fn default_vol_window() -> usize {
+    20
+}
+
+fn default_vol_max_scale() -> f64 {
+    2.0
+}
+
+impl BacktestConfig {
+    /// 校验各项配置（构造函数与 `from_*` 共用），无效时返回 `ValueError`
+    fn validated(self) -> PyResult<Self> {
+        self.date_window()?;
+        self.rebalance_schedule()?;
+        self.early_stop()?;
+        self.trading_sessions()?;
+        self.session_policy()?;
+        self.trading_calendar()?;
+        self.exposure_limits()?;
+        self.vol_targeting()?;
+        Ok(self)
+    }
+
+    /// 解析回测区间（`start`/`end`）
+    pub(crate) fn date_window(&self) -> PyResult<DateWindow> {
+        DateWindow::parse(&self.start, &self.end).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// 解析调仓计划（频率与自定义日期）
+    fn rebalance_schedule(&self) -> PyResult<RebalanceSchedule> {
+        RebalanceSchedule::from_config(self.rebalance.as_deref(), self.rebalance_dates.as_deref(), self.trading_calendar()?)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// 解析提前终止条件
+    fn early_stop(&self) -> PyResult<EarlyStop> {
+        EarlyStop::from_config(self.max_bars, self.target_equity, self.ruin_equity)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// 解析交易时段；未配置时返回 `None`
+    pub(crate) fn trading_sessions(&self) -> PyResult<Option<TradingSessions>> {
+        self.sessions
+            .as_deref()
+            .map(TradingSessions::parse)
+            .transpose()
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// 解析时段外订单的处理方式
+    fn session_policy(&self) -> PyResult<SessionPolicy> {
+        SessionPolicy::parse(&self.session_policy).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// 交易时段外的订单是否暂存到下一个时段（`session_policy="defer"`），否则拒绝
+    pub(crate) fn defers_orders(&self) -> PyResult<bool> {
+        Ok(self.session_policy()? == SessionPolicy::Defer)
+    }
+
+    /// 解析组合敞口限制
+    pub(crate) fn exposure_limits(&self) -> PyResult<ExposureLimits> {
+        ExposureLimits::from_config(self.max_gross_exposure, self.max_net_exposure, &self.exposure_policy)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// 解析波动率目标；未配置 `vol_target` 时返回 `None`
+    pub(crate) fn vol_targeting(&self) -> PyResult<Option<VolTarget>> {
+        VolTarget::from_config(self.vol_target, self.vol_window, self.vol_max_scale, self.periods_per_year())
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// 解析交易日历；未配置时返回 `None`
+    pub(crate) fn trading_calendar(&self) -> PyResult<Option<TradingCalendar>> {
+        self.calendar
+            .as_deref()
+            .map(TradingCalendar::parse)
+            .transpose()
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// 年化使用的每年周期数：配置了交易日历时取日历的年化天数，否则为 252
+    pub(crate) fn periods_per_year(&self) -> f64 {
+        self.trading_calendar().ok().flatten().map_or(252.0, |cal| cal.days_per_year())
+    }
+
+    /// 严格模式下校验输入 bar（未开启时不做任何检查）
+    pub(crate) fn check_bars(&self, bars: &[BarData]) -> PyResult<()> {
+        if self.strict {
+            BarValidator::default().check_all(bars)?;
+        }
+        Ok(())
+    }
+
+    /// 创建随机数生成器：配置了 `seed` 时每次都从同一种子开始（结果可复现），否则使用系统熵
+    pub(crate) fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum OrderSide {
     Buy,
     Sell,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum OrderType {
     Market,
     Limit,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct Order {
     id: u64,
     side: OrderSide,
     otype: OrderType,
     size: f64,
     limit_price: Option<f64>,
+    #[serde(skip)]
     status: &'static str,
     symbol: String,
+    /// 提交时已记录的 bar 数（单资产回测中由 `submit_order` 设置，顺延订单据此计算挂单时长）
+    #[serde(default)]
+    submitted_bar: usize,
+}
+
+/// 策略通过 `ctx.buy()` / `ctx.sell()` / `ctx.order_target_percent()` / `ctx.close()` 排队的订单
+///
+/// 目标仓位与平仓在执行时才按最新持仓换算成数量（同一次回调中前面的订单成交后再计算）。
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum QueuedOrder {
+    /// 买入/卖出指定数量；`limit_price` 为 `Some` 时为限价单
+    Submit { side: OrderSide, size: f64, limit_price: Option<f64> },
+    /// 调整持仓市值到净值的指定比例（负数为空头）
+    TargetPercent(f64),
+    /// 平掉全部持仓
+    Close,
+}
+
+/// 上下文上的订单队列（不参与上下文的比较与序列化）
+#[derive(Clone, Debug, Default)]
+struct OrderQueue {
+    /// 只有传给 `next()` / `on_rebalance()` 的上下文（以及用户自己构造的上下文）可以下单
+    accepting: bool,
+    orders: Vec<QueuedOrder>,
+}
+
+impl PartialEq for OrderQueue {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// 尚未成交、仍在等待的订单（目前为交易时段外顺延的订单），通过 `ctx.open_orders` 提供给策略
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct OpenOrder {
+    order_id: u64,
+    symbol: String,
+    side: OrderSide,
+    otype: OrderType,
+    limit_price: Option<f64>,
+    /// 剩余数量（不支持部分成交，即订单数量）
+    size: f64,
+    /// 提交后经过的 bar 数（提交所在的 bar 为 0）
+    age: usize,
+}
+
+impl OpenOrder {
+    /// 按当前的 bar 时钟（`RunState::bars_recorded()`）列出等待中的订单
+    fn list(orders: &[Order], clock: usize) -> Vec<Self> {
+        orders
+            .iter()
+            .map(|o| OpenOrder {
+                order_id: o.id,
+                symbol: o.symbol.clone(),
+                side: o.side,
+                otype: o.otype,
+                limit_price: o.limit_price,
+                size: o.size,
+                age: clock.saturating_sub(o.submitted_bar),
+            })
+            .collect()
+    }
 }
 
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 struct PositionState {
     position: f64,
     avg_cost: f64,
@@ -180,6 +881,80 @@ impl PositionState {
     }
 }
 
+/// 一笔成交的完整记录
+///
+/// 旧版本检查点中的成交只有前四个字段（`[order_id, side, price, size]`），恢复时其余字段取默认值。
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct TradeRecord {
+    order_id: u64,
+    /// `"BUY"` 或 `"SELL"`
+    side: String,
+    /// 成交价（含滑点）
+    price: f64,
+    size: f64,
+    /// 成交所在 bar 的时间
+    #[serde(default)]
+    datetime: Option<String>,
+    #[serde(default)]
+    symbol: String,
+    /// 手续费
+    #[serde(default)]
+    commission: f64,
+    /// 滑点成本：成交价与撮合价之差 × 数量（始终为非负数）
+    #[serde(default)]
+    slippage: f64,
+    /// 成交后该标的的持仓
+    #[serde(default)]
+    position: f64,
+    /// 成交后的现金
+    #[serde(default)]
+    cash: f64,
+}
+
+impl TradeRecord {
+    /// 由订单和撮合结果构造；`position`、`cash` 为成交后的值
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        order: &Order,
+        fill_price: f64,
+        exec_price: f64,
+        fill_size: f64,
+        commission: f64,
+        datetime: Option<&str>,
+        position: f64,
+        cash: f64,
+    ) -> Self {
+        Self {
+            order_id: order.id,
+            side: match order.side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" }.to_string(),
+            price: exec_price,
+            size: fill_size,
+            datetime: datetime.map(str::to_string),
+            symbol: order.symbol.clone(),
+            commission,
+            slippage: (exec_price - fill_price).abs() * fill_size,
+            position,
+            cash,
+        }
+    }
+
+    /// 结果和回调中使用的成交字典
+    fn to_pydict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let t = PyDict::new_bound(py);
+        t.set_item("order_id", self.order_id)?;
+        t.set_item("side", &self.side)?;
+        t.set_item("price", self.price)?;
+        t.set_item("size", self.size)?;
+        t.set_item("datetime", &self.datetime)?;
+        t.set_item("symbol", &self.symbol)?;
+        t.set_item("commission", self.commission)?;
+        t.set_item("slippage", self.slippage)?;
+        t.set_item("position", self.position)?;
+        t.set_item("cash", self.cash)?;
+        Ok(t)
+    }
+}
+
 /// 计算简单移动平均线（SMA）
 ///
 /// 使用滑动窗口优化算法，实现 O(1) 时间复杂度的移动平均计算。
@@ -252,10 +1027,10 @@ pub fn vectorized_sma(prices: &[f64], window: usize) -> Vec<Option<f64>> {
     let mut sum = 0.0;
     
     for i in 0..prices.len() {
-        if i < window {
+        if i + 1 < window {
             sum += prices[i];
             result.push(None);
-        } else if i == window {
+        } else if i + 1 == window {
             sum += prices[i];
             result.push(Some(sum / window as f64));
         } else {
@@ -395,48 +1170,77 @@ pub fn vectorized_rsi(prices: &[f64], window: usize) -> Vec<Option<f64>> {
 }
 
 #[pyfunction]
-fn compute_sma(prices: Vec<f64>, window: usize) -> Vec<Option<f64>> {
-    vectorized_sma(&prices, window)
+#[pyo3(signature = (prices, window, fill="none"))]
+fn compute_sma(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(indicators::WarmupFill::parse(fill)?.apply(vectorized_sma(&prices, window)))
 }
 
 #[pyfunction]
-fn compute_rsi(prices: Vec<f64>, window: usize) -> Vec<Option<f64>> {
-    vectorized_rsi(&prices, window)
+#[pyo3(signature = (prices, window, fill="none"))]
+fn compute_rsi(prices: Vec<f64>, window: usize, fill: &str) -> PyResult<Vec<Option<f64>>> {
+    Ok(indicators::WarmupFill::parse(fill)?.apply(vectorized_rsi(&prices, window)))
 }
 
 // 批量提取bar数据，减少Python调用
 fn extract_bars_data(bars: &PyList) -> PyResult<Vec<BarData>> {
     let mut bars_data = Vec::with_capacity(bars.len());
+    let mut names = FieldNames::default();
     
     for item in bars.iter() {
-        let bar: &PyDict = item.downcast()?;
-        
-        let datetime = match bar.get_item("datetime")? {
-            Some(v) => v.extract::<String>().ok(),
-            None => None,
-        };
-        
-        let open = bar.get_item("open")?.and_then(|v| v.extract::<f64>().ok()).unwrap_or(0.0);
-        let high = bar.get_item("high")?.and_then(|v| v.extract::<f64>().ok()).unwrap_or(0.0);
-        let low = bar.get_item("low")?.and_then(|v| v.extract::<f64>().ok()).unwrap_or(0.0);
-        let close = bar.get_item("close")?.and_then(|v| v.extract::<f64>().ok()).unwrap_or(0.0);
-        let volume = bar.get_item("volume")?.and_then(|v| v.extract::<f64>().ok()).unwrap_or(0.0);
-        let symbol = bar.get_item("symbol")?.and_then(|v| v.extract::<String>().ok());
-        
-        bars_data.push(BarData {
-            datetime,
-            open,
-            high,
-            low,
-            close,
-            volume,
-            symbol,
-        });
+        bars_data.push(extract_bar(item.downcast()?, &mut names)?);
     }
     
     Ok(bars_data)
 }
 
+// 提取时间值：字符串原样保留；整数视为 epoch 毫秒，同时保留时间戳并格式化为 UTC 时间字符串
+pub(crate) fn extract_datetime(value: &PyAny) -> (Option<String>, Option<i64>) {
+    if let Ok(s) = value.downcast::<pyo3::types::PyString>() {
+        return (s.to_str().ok().map(str::to_string), None);
+    }
+    if value.is_instance_of::<pyo3::types::PyLong>() && !value.is_instance_of::<pyo3::types::PyBool>() {
+        if let Ok(ms) = value.extract::<i64>() {
+            return (database::format_epoch_ms(ms), Some(ms));
+        }
+    }
+    (None, None)
+}
+
+// 提取单根bar；缺失或无法转换的价格字段为 0，其他数值/字符串字段保存为额外字段
+fn extract_bar(bar: &PyDict, names: &mut FieldNames) -> PyResult<BarData> {
+    let mut out = BarData {
+        datetime: None,
+        timestamp: None,
+        open: 0.0,
+        high: 0.0,
+        low: 0.0,
+        close: 0.0,
+        volume: 0.0,
+        symbol: None,
+        extra: Vec::new(),
+        parsed: ParsedFields::default(),
+    };
+    for (key, value) in bar.iter() {
+        let Ok(key) = key.downcast::<pyo3::types::PyString>() else { continue };
+        let key = key.to_str()?;
+        match key {
+            "datetime" => (out.datetime, out.timestamp) = extract_datetime(value),
+            "open" => out.open = out.parsed.number(key, value),
+            "high" => out.high = out.parsed.number(key, value),
+            "low" => out.low = out.parsed.number(key, value),
+            "close" => out.close = out.parsed.number(key, value),
+            "volume" => out.volume = out.parsed.number(key, value),
+            "symbol" => out.symbol = value.extract::<String>().ok(),
+            _ => {
+                if let Some(v) = FieldValue::extract(value) {
+                    out.extra.push((names.intern(key), v));
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
 /// 策略执行上下文
 ///
 /// 在策略执行过程中，引擎会为每个 bar 创建一个上下文快照，提供给策略使用。
@@ -449,6 +1253,23 @@ fn extract_bars_data(bars: &PyList) -> PyResult<Vec<BarData>> {
 /// - `cash`: 当前现金余额
 /// - `equity`: 当前账户净值（现金 + 持仓市值）
 /// - `bar_index`: 当前处理的 bar 索引（从 0 开始）
+/// - `datetime`: 当前 bar 的时间（bar 中没有 `datetime` 或尚未开始处理 bar 时为 `None`）
+/// - `unrealized_pnl`: 浮动盈亏，`position * (close - avg_cost)`
+/// - `position_value`: 持仓市值，`position * close`（空头为负数）
+///
+/// # 下单方法
+///
+/// 除了从 `next()` 返回订单字典，也可以在 `next()` / `on_rebalance()` 中直接通过上下文下单，
+/// 回调返回后引擎按调用顺序依次执行（先于回调的返回值）：
+///
+/// - `ctx.buy(size=1.0, price=None)` / `ctx.sell(size=1.0, price=None)`: 市价单；传入 `price` 时为限价单
+/// - `ctx.order_target_percent(pct)`: 把持仓市值调整到净值的 `pct`（如 `0.5`；负数为空头，`0` 等同于平仓）
+/// - `ctx.close()`: 平掉全部持仓
+/// - `ctx.pending_orders`: 已排队、尚未执行的订单（字典列表），便于单独测试策略
+///
+/// `ctx.open_orders` 列出已经提交、仍在等待成交的订单（`order_id`、`symbol`、`side`、`type`、`price`、`size`、`age`）。
+/// 限价单在提交的 bar 上撮合，没有触及限价即失效，不会留在这里；目前等待中的只有交易时段外被顺延的订单
+/// （`session_policy="defer"`），它们在下一个交易时段的第一根 bar 开盘时撮合。
 ///
 /// # 使用场景
 ///
@@ -467,9 +1288,9 @@ fn extract_bars_data(bars: &PyList) -> PyResult<Vec<BarData>> {
 ///             # 净值过低，停止交易
 ///             return None
 ///         
-///         # 根据平均成本判断盈亏
-///         if bar["close"] > ctx.avg_cost * 1.1:
-///             # 盈利超过 10%，可以考虑止盈
+///         # 根据浮动盈亏判断是否止盈
+///         if ctx.position > 0 and ctx.unrealized_pnl > 0.1 * ctx.position_value:
+///             # 浮盈超过持仓市值的 10%，可以考虑止盈
 ///             return {"action": "SELL", "type": "market", "size": ctx.position}
 /// ```
 ///
@@ -479,8 +1300,13 @@ fn extract_bars_data(bars: &PyList) -> PyResult<Vec<BarData>> {
 /// - 在 `next()` 方法中修改上下文不会影响实际账户状态
 /// - `equity` 是计算值：`equity = cash + position * current_price`
 /// - `bar_index` 可以用于判断回测进度或实现基于索引的逻辑
-#[pyclass]
-#[derive(Clone)]
+/// - `on_start()` 中还没有当前价格，`unrealized_pnl` 与 `position_value` 为 0（从检查点恢复时按断点处的收盘价计算）
+/// - 下单方法只能在 `next()` / `on_rebalance()` 的上下文上调用，其他钩子中调用返回 `RuntimeError`；
+///   目标仓位与平仓在执行时按最新持仓和当前收盘价换算数量；多资产回测（`run_multi()`）的上下文是字典，不支持这些方法
+/// - 上下文支持 `==` 比较和 `pickle`，可以保存下来或发送到其他进程做离线分析；
+///   也可以用关键字参数直接构造（如 `EngineContext(position=1.0, cash=900.0, equity=1000.0)`），便于单独测试策略
+#[pyclass(module = "engine_rust")]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct EngineContext {
     /// 当前持仓数量（正数=多头，负数=空头，0=空仓）
     #[pyo3(get)]
@@ -497,48 +1323,257 @@ pub struct EngineContext {
     /// 当前处理的 bar 索引（从 0 开始）
     #[pyo3(get)]
     pub bar_index: usize,
+    /// 当前 bar 的时间
+    #[pyo3(get)]
+    pub datetime: Option<String>,
+    /// 浮动盈亏（按当前价格计算）
+    #[pyo3(get)]
+    pub unrealized_pnl: f64,
+    /// 持仓市值（按当前价格计算）
+    #[pyo3(get)]
+    pub position_value: f64,
+    /// 尚未成交、仍在等待的订单
+    #[serde(default)]
+    open_orders: Vec<OpenOrder>,
+    /// 通过下单方法排队的订单
+    #[serde(skip)]
+    queue: OrderQueue,
 }
 
-/// 回测引擎核心结构体
-///
-/// 这是整个回测系统的核心，负责执行策略回测、订单撮合、持仓管理、统计计算等所有关键功能。
-/// 就像一台"回测机器"，它会按照时间顺序处理每一根 K 线，调用策略生成订单，执行交易，并记录结果。
-///
-/// # 核心方法
-///
-/// - `new()`: 创建回测引擎实例，需要传入 `BacktestConfig` 配置
-/// - `run()`: 执行单资产回测，处理单个数据源的 K 线数据
-/// - `run_multi()`: 执行多资产/多周期回测，支持联合时间线推进
-///
-/// # 工作原理
-///
-/// 回测引擎的工作流程就像"时间机器"：
-///
-/// 1. **初始化**: 创建引擎，设置初始资金和配置参数
-/// 2. **数据准备**: 预提取所有 K 线数据到 Rust 结构，减少 Python 调用
-/// 3. **策略启动**: 调用策略的 `on_start()` 方法，传入初始上下文
-/// 4. **循环处理**: 按时间顺序处理每根 K 线：
-///    - 构造当前 bar 和上下文
-///    - 调用策略的 `next()` 方法获取交易信号
-///    - 解析订单动作（字符串或字典格式）
-///    - 执行订单撮合（市价/限价）
-///    - 更新持仓和账户状态
-///    - 触发订单和成交回调
-///    - 记录净值曲线
-/// 5. **策略结束**: 调用策略的 `on_stop()` 方法
-/// 6. **结果构建**: 计算统计指标，构建返回结果
-///
-/// # 性能优化
-///
-/// - **预提取数据**: 一次性提取所有 bar 数据，避免重复的 Python↔Rust 转换
-/// - **批量处理**: 通过 `batch_size` 配置批量处理策略调用，减少 GIL 争用
-/// - **预分配容器**: 使用 `Vec::with_capacity()` 预分配内存，减少重分配
-/// - **内联热点函数**: 对订单撮合、持仓更新等热点路径使用 `#[inline]` 优化
-///
-/// # 使用示例
-///
-/// ```python
-/// from engine_rust import BacktestEngine, BacktestConfig
+impl EngineContext {
+    /// 按当前价格构造单资产上下文快照；`price` 为 `None`（尚无行情）时净值按现金估算
+    fn snapshot(pos: &PositionState, price: Option<f64>, datetime: Option<&str>, bar_index: usize) -> Self {
+        let (position_value, unrealized_pnl) = match price {
+            Some(p) => (pos.position * p, pos.position * (p - pos.avg_cost)),
+            None => (0.0, 0.0),
+        };
+        Self {
+            position: pos.position,
+            avg_cost: pos.avg_cost,
+            cash: pos.cash,
+            equity: pos.cash + position_value,
+            bar_index,
+            datetime: datetime.map(str::to_string),
+            unrealized_pnl,
+            position_value,
+            open_orders: Vec::new(),
+            queue: OrderQueue::default(),
+        }
+    }
+
+    /// 附上等待中的订单（`clock` 为本 bar 开始时的 `RunState::bars_recorded()`）
+    fn with_open_orders(mut self, orders: &[Order], clock: usize) -> Self {
+        self.open_orders = OpenOrder::list(orders, clock);
+        self
+    }
+
+    /// 允许通过下单方法排队订单（传给 `next()` / `on_rebalance()` 的上下文）
+    fn accepting_orders(mut self) -> Self {
+        self.queue.accepting = true;
+        self
+    }
+
+    fn enqueue(&mut self, method: &str, order: QueuedOrder) -> PyResult<()> {
+        if !self.queue.accepting {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "ctx.{}() is only available in next() / on_rebalance()",
+                method
+            )));
+        }
+        self.queue.orders.push(order);
+        Ok(())
+    }
+
+    fn submit(&mut self, method: &str, side: OrderSide, size: f64, price: Option<f64>) -> PyResult<()> {
+        if !(size.is_finite() && size > 0.0) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "size must be a positive number, got {}",
+                size
+            )));
+        }
+        if let Some(p) = price.filter(|p| !(p.is_finite() && *p > 0.0)) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "price must be a positive number, got {}",
+                p
+            )));
+        }
+        self.enqueue(method, QueuedOrder::Submit { side, size, limit_price: price })
+    }
+}
+
+#[pymethods]
+impl EngineContext {
+    #[new]
+    #[pyo3(signature = (position=0.0, avg_cost=0.0, cash=0.0, equity=0.0, bar_index=0, datetime=None, unrealized_pnl=0.0, position_value=0.0))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        position: f64,
+        avg_cost: f64,
+        cash: f64,
+        equity: f64,
+        bar_index: usize,
+        datetime: Option<String>,
+        unrealized_pnl: f64,
+        position_value: f64,
+    ) -> Self {
+        Self {
+            position,
+            avg_cost,
+            cash,
+            equity,
+            bar_index,
+            datetime,
+            unrealized_pnl,
+            position_value,
+            open_orders: Vec::new(),
+            queue: OrderQueue { accepting: true, orders: Vec::new() },
+        }
+    }
+
+    /// 买入 `size`（市价单；传入 `price` 时为限价单）
+    #[pyo3(signature = (size=1.0, price=None))]
+    fn buy(&mut self, size: f64, price: Option<f64>) -> PyResult<()> {
+        self.submit("buy", OrderSide::Buy, size, price)
+    }
+
+    /// 卖出 `size`（市价单；传入 `price` 时为限价单）
+    #[pyo3(signature = (size=1.0, price=None))]
+    fn sell(&mut self, size: f64, price: Option<f64>) -> PyResult<()> {
+        self.submit("sell", OrderSide::Sell, size, price)
+    }
+
+    /// 把持仓市值调整到净值的 `pct`（负数为空头）
+    fn order_target_percent(&mut self, pct: f64) -> PyResult<()> {
+        if !pct.is_finite() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "pct must be a finite number, got {}",
+                pct
+            )));
+        }
+        self.enqueue("order_target_percent", QueuedOrder::TargetPercent(pct))
+    }
+
+    /// 平掉全部持仓
+    fn close(&mut self) -> PyResult<()> {
+        self.enqueue("close", QueuedOrder::Close)
+    }
+
+    /// 已提交、仍在等待成交的订单（字典列表）：`order_id`、`symbol`、`side`、`type`、`price`（限价，市价单为 `None`）、
+    /// `size`（剩余数量）、`age`（提交后经过的 bar 数）
+    #[getter]
+    fn open_orders(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let list = PyList::empty_bound(py);
+        for order in &self.open_orders {
+            let d = PyDict::new_bound(py);
+            d.set_item("order_id", order.order_id)?;
+            d.set_item("symbol", &order.symbol)?;
+            d.set_item("side", match order.side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" })?;
+            d.set_item("type", match order.otype { OrderType::Market => "market", OrderType::Limit => "limit" })?;
+            d.set_item("price", order.limit_price)?;
+            d.set_item("size", order.size)?;
+            d.set_item("age", order.age)?;
+            list.append(d)?;
+        }
+        Ok(list.into())
+    }
+
+    /// 已排队、尚未执行的订单
+    #[getter]
+    fn pending_orders(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let list = PyList::empty_bound(py);
+        for order in &self.queue.orders {
+            let d = PyDict::new_bound(py);
+            match *order {
+                QueuedOrder::Submit { side, size, limit_price } => {
+                    d.set_item("action", match side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" })?;
+                    d.set_item("type", if limit_price.is_some() { "limit" } else { "market" })?;
+                    d.set_item("size", size)?;
+                    if let Some(p) = limit_price {
+                        d.set_item("price", p)?;
+                    }
+                }
+                QueuedOrder::TargetPercent(pct) => {
+                    d.set_item("action", "TARGET_PERCENT")?;
+                    d.set_item("percent", pct)?;
+                }
+                QueuedOrder::Close => d.set_item("action", "CLOSE")?,
+            }
+            list.append(d)?;
+        }
+        Ok(list.into())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "EngineContext(bar_index={}, datetime={}, position={:?}, avg_cost={:?}, cash={:?}, equity={:?}, unrealized_pnl={:?}, position_value={:?})",
+            self.bar_index,
+            self.datetime.as_ref().map_or("None".to_string(), |dt| format!("'{}'", dt)),
+            self.position,
+            self.avg_cost,
+            self.cash,
+            self.equity,
+            self.unrealized_pnl,
+            self.position_value
+        )
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_pyobject(py, self, "EngineContext")
+    }
+
+    fn __setstate__(&mut self, py: Python<'_>, state: &Bound<'_, PyDict>) -> PyResult<()> {
+        // 订单队列不随状态保存，保留新建对象上的（可下单、为空）
+        let queue = std::mem::take(&mut self.queue);
+        *self = from_pydict(py, state, "EngineContext")?;
+        self.queue = queue;
+        Ok(())
+    }
+}
+
+/// 回测引擎核心结构体
+///
+/// 这是整个回测系统的核心，负责执行策略回测、订单撮合、持仓管理、统计计算等所有关键功能。
+/// 就像一台"回测机器"，它会按照时间顺序处理每一根 K 线，调用策略生成订单，执行交易，并记录结果。
+///
+/// # 核心方法
+///
+/// - `new()`: 创建回测引擎实例，需要传入 `BacktestConfig` 配置
+/// - `run()`: 执行单资产回测，处理单个数据源的 K 线数据
+/// - `run_multi()`: 执行多资产/多周期回测，支持联合时间线推进
+///
+/// # 工作原理
+///
+/// 回测引擎的工作流程就像"时间机器"：
+///
+/// 1. **初始化**: 创建引擎，设置初始资金和配置参数
+/// 2. **数据准备**: 预提取所有 K 线数据到 Rust 结构，减少 Python 调用
+/// 3. **策略启动**: 调用策略的 `on_start()` 方法，传入初始上下文
+/// 4. **循环处理**: 按时间顺序处理每根 K 线：
+///    - 构造当前 bar 和上下文
+///    - 调用策略的 `next()` 方法获取交易信号
+///    - 解析订单动作（字符串或字典格式）
+///    - 执行订单撮合（市价/限价）
+///    - 更新持仓和账户状态
+///    - 触发订单和成交回调
+///    - 记录净值曲线
+/// 5. **策略结束**: 调用策略的 `on_stop()` 方法
+/// 6. **结果构建**: 计算统计指标，构建返回结果
+///
+/// # 性能优化
+///
+/// - **预提取数据**: 一次性提取所有 bar 数据，避免重复的 Python↔Rust 转换
+/// - **批量处理**: 通过 `batch_size` 配置批量处理策略调用，减少 GIL 争用
+/// - **预分配容器**: 使用 `Vec::with_capacity()` 预分配内存，减少重分配
+/// - **内联热点函数**: 对订单撮合、持仓更新等热点路径使用 `#[inline]` 优化
+///
+/// # 使用示例
+///
+/// ```python
+/// from engine_rust import BacktestEngine, BacktestConfig
 /// from pyrust_bt.strategy import Strategy
 ///
 /// # 创建配置
@@ -608,7 +1643,10 @@ impl BacktestEngine {
     ///    - 如果有订单，立即撮合成交（简化模型：同 bar 内立即成交）
     ///    - 更新账户（持仓、现金、成本等）
     ///    - 告诉策略"订单已提交"和"订单已成交"（回调）
+    ///    - 如果配置了 `rebalance`/`rebalance_dates` 且当前 bar 是调仓点，调用 `on_rebalance(ctx)` 并执行其返回的订单
     ///    - 记录当前净值
+    ///    - 调用可选钩子 `on_bar_end(ctx)`（本 bar 成交全部处理完毕后）；
+    ///      交易日最后一根 bar 再调用 `on_session_end(ctx)`，适合做盯市记录和日终风控检查
     /// 5. **结束策略**：告诉策略"回测结束了"（调用 `on_stop()`）
     /// 6. **计算结果**：统计总收益、夏普比率、最大回撤等指标
     ///
@@ -686,7 +1724,29 @@ impl BacktestEngine {
     /// # 参数
     ///
     /// - `strategy`: Python 策略对象，必须实现 `Strategy` trait
-    /// - `data`: K 线数据列表，每个元素是包含 `datetime`, `open`, `high`, `low`, `close`, `volume` 的字典
+    /// - `data`: K 线数据列表，每个元素是包含 `datetime`, `open`, `high`, `low`, `close`, `volume` 的字典；
+    ///   也可以是列式数据：列字典（值为 numpy 数组或列表，必须包含 `close`）、pyarrow `Table`/`RecordBatch`
+    ///   或 pandas/polars DataFrame；还可以是流式数据源（Python 迭代器/生成器、DB-API 游标、
+    ///   pyarrow `RecordBatchReader`），引擎按 `batch_size` 分批拉取，适合超过内存的数据集
+    ///   （此时不支持 `indicators` 与检查点）
+    /// - `indicators`: 可选的指标规格列表（格式同 `compute_indicators`），引擎会在回测开始前
+    ///   一次性在 Rust 中算好所有指标，并把每根 bar 上的值注入 `bar` 字典（如 `bar["sma_20"]`），
+    ///   预热区间的值为 `None`
+    /// - `progress_callback`: 可选的进度回调，`callback(done, total, elapsed)` 或 tqdm 风格对象（有 `update(n)` 方法）
+    /// - `progress_every`: 每处理多少根 bar 汇报一次进度，默认 0 表示自动（约每 1%）
+    /// - `cancel_token`: 可选的 `CancelToken`，在其他线程调用 `token.cancel()` 可提前结束回测；
+    ///   不传时 Ctrl+C 同样会在批次边界（或策略回调中）停止回测并返回部分结果
+    /// - `checkpoint_path`: 可选的检查点文件路径；设置后引擎在批次边界、取消时和结束时写入检查点
+    /// - `checkpoint_every`: 两次检查点之间至少间隔的 bar 数，默认 0 表示每个批次都写
+    /// - `resume`: 为 `True` 且检查点文件存在时从检查点继续；策略可实现 `get_state()`/`set_state(state)`
+    ///   （JSON 可序列化）以随检查点保存自身状态
+    /// - `timeframes`: 可选的高周期列表（如 `["15m", "1d"]`）；引擎在主循环中增量重采样，
+    ///   策略通过 `bar["timeframes"]["15m"]` 读取截至当前 bar 的高周期 K 线（含 `complete` 标记）
+    /// - `benchmark`: 可选的基准标的 K 线（格式同 `data`，不支持流式数据源）；传入后结果中附带
+    ///   `benchmark`：基准在策略回测区间内买入持有的净值曲线、统计指标和策略的超额收益
+    /// - `results_db`: 可选的 DuckDB 路径；设置后回测过程中由后台线程把净值曲线和成交分批写入
+    ///   `backtest_equity` / `backtest_trades` 表，结果字典中不再包含 `equity_curve` 和 `trades` 列表
+    /// - `run_id`: 写入 `results_db` 时的运行标识，默认以当前时间生成；同一 `run_id` 的旧结果会被替换
     ///
     /// # 返回值
     ///
@@ -697,8 +1757,16 @@ impl BacktestEngine {
     /// - `equity`: 最终账户净值
     /// - `realized_pnl`: 已实现盈亏
     /// - `equity_curve`: 净值曲线列表（每个元素包含 `datetime` 和 `equity`）
-    /// - `trades`: 交易列表（每个元素包含 `order_id`, `side`, `price`, `size`）
+    /// - `trades`: 交易列表（每个元素包含 `order_id`, `side`, `price`, `size`，以及成交时间 `datetime`、
+    ///   `symbol`、手续费 `commission`、滑点成本 `slippage`、成交后的持仓 `position` 和现金 `cash`）
     /// - `stats`: 统计指标字典（包含总收益、年化收益、夏普比率、最大回撤等）
+    /// - `cancelled`: 是否被取消（Ctrl+C 或取消令牌）；为 `True` 时以上字段均为截至取消时的部分结果，
+    ///   并附带 `cancel_reason`（`"keyboard_interrupt"` 或 `"token"`）
+    /// - `profile`: 仅在 `BacktestConfig(profile=True)` 时存在，耗时分解（数据转换、指标、策略回调、引擎、结果构建）
+    /// - `buy_and_hold`: 仅在 `BacktestConfig(buy_and_hold=True)` 时存在，同一份数据上买入持有的
+    ///   `equity_curve`、`stats` 和策略相对它的 `excess_return`
+    /// - `benchmark`: 仅在传入 `benchmark` 时存在，格式同 `buy_and_hold`
+    /// - `results_db`: 仅在传入 `results_db` 时存在，包含 `path`、`run_id`、`equity_rows`、`trades`（写入的行数）
     ///
     /// # 示例
     ///
@@ -707,230 +1775,1479 @@ impl BacktestEngine {
     /// print(result["stats"]["total_return"])  # 总收益率
     /// print(result["stats"]["sharpe"])        # 夏普比率
     /// print(result["equity_curve"])           # 净值曲线
+    ///
+    /// # 超过内存的数据：逐批从数据库游标读取
+    /// cursor = duckdb.connect("data/backtest.db").execute("SELECT datetime, open, high, low, close, volume FROM klines_1m")
+    /// result = engine.run(MyStrategy(), cursor)
+    ///
+    /// # 长回测显示进度
+    /// from tqdm import tqdm
+    /// result = engine.run(MyStrategy(), bars, progress_callback=tqdm())
+    ///
+    /// # 长回测定期写检查点，中断后以 resume=True 继续
+    /// result = engine.run(MyStrategy(), ticks, checkpoint_path="run.ckpt",
+    ///                     checkpoint_every=1_000_000, resume=True)
+    ///
+    /// # 引擎预计算指标，策略中直接读取
+    /// result = engine.run(MyStrategy(), bars, indicators=[
+    ///     {"kind": "sma", "window": 20},
+    ///     {"kind": "rsi", "window": 14},
+    /// ])
+    /// # MyStrategy.next 中：bar["sma_20"], bar["rsi_14"]
+    ///
+    /// # 1 分钟数据上同时维护 15 分钟与日线 K 线
+    /// result = engine.run(MyStrategy(), bars_1m, timeframes=["15m", "1d"])
+    /// # MyStrategy.next 中：bar["timeframes"]["1d"]["open"]
+    ///
+    /// # 与指数买入持有对比（配合 BacktestConfig(buy_and_hold=True) 还会得到本标的的买入持有基准）
+    /// result = engine.run(MyStrategy(), bars, benchmark=index_bars)
+    /// print(result["benchmark"]["excess_return"])
+    ///
+    /// # 百万级 bar：净值曲线和成交在回测过程中写入 DuckDB，不转换为 Python 列表
+    /// result = engine.run(MyStrategy(), ticks, results_db="data/results.db", run_id="ma_cross_v2")
     /// ```
-    fn run<'py>(&self, py: Python<'py>, strategy: PyObject, data: &'py PyAny) -> PyResult<PyObject> {
-        let bars: &PyList = data.downcast()?;
-        let n_bars = bars.len();
+    #[pyo3(signature = (strategy, data, indicators=None, progress_callback=None, progress_every=0, cancel_token=None, checkpoint_path=None, checkpoint_every=0, resume=false, timeframes=None, benchmark=None, results_db=None, run_id=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn run<'py>(
+        &self,
+        py: Python<'py>,
+        strategy: PyObject,
+        data: &Bound<'py, PyAny>,
+        indicators: Option<&Bound<'py, PyList>>,
+        progress_callback: Option<PyObject>,
+        progress_every: usize,
+        cancel_token: Option<CancelToken>,
+        checkpoint_path: Option<String>,
+        checkpoint_every: usize,
+        resume: bool,
+        timeframes: Option<Vec<String>>,
+        benchmark: Option<&Bound<'py, PyAny>>,
+        results_db: Option<String>,
+        run_id: Option<String>,
+    ) -> PyResult<PyObject> {
+        // 未传入令牌时仍响应 Ctrl+C
+        let cancel_token = cancel_token.unwrap_or_default();
+        let timeframes = timeframes.as_deref().map(Timeframes::new).transpose()?;
+        let benchmark = benchmark.map(|b| extract_bars_any(b.as_gil_ref())).transpose()?;
+        let writer = ResultWriter::new(results_db, run_id)?;
 
-        // 预提取所有bar数据到Rust结构中
-        let bars_data = extract_bars_data(bars)?;
-        
-        // 初始上下文（无价格时以现金估算净值）
-        let init_ctx = Py::new(py, EngineContext {
-            position: 0.0,
-            avg_cost: 0.0,
-            cash: self.cfg.cash,
-            equity: self.cfg.cash,
-            bar_index: 0,
+        // 迭代器/游标：按批次拉取数据，不一次性提取
+        if let Some(mut stream) = BarStream::detect(data, self.cfg.batch_size)? {
+            if indicators.is_some() || checkpoint_path.is_some() {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    "indicators and checkpoint_path are not supported with streaming data",
+                ));
+            }
+            let every = if progress_every == 0 { self.cfg.batch_size.max(1) } else { progress_every };
+            let mut progress = ProgressReporter::new(py, progress_callback, every, 0)?;
+            return self.run_stream(py, &strategy, &mut stream, timeframes, progress.as_mut(), &cancel_token, benchmark.as_deref(), writer);
+        }
+
+        // 预提取所有bar数据到Rust结构中（字典列表或列式数据）
+        let started = Instant::now();
+        let bars_data = extract_bars_any(data.as_gil_ref())?;
+        self.cfg.check_bars(&bars_data)?;
+        let extraction = started.elapsed();
+
+        // 预计算指标（一次性完成，逐 bar 只做注入）
+        let started = Instant::now();
+        let indicator_columns = match indicators {
+            Some(specs) => {
+                let specs = indicators::parse_indicator_specs(specs, indicators::WarmupFill::None)?;
+                indicators::compute_indicator_batch(&indicators::OhlcvColumns::from_bars(&bars_data), &specs)
+            }
+            None => Vec::new(),
+        };
+        let (bars_data, indicator_columns) = self.apply_date_window(bars_data, indicator_columns)?;
+        let profile = self.cfg.profile.then(|| RunProfile { extraction, indicators: started.elapsed(), ..Default::default() });
+
+        let mut progress = ProgressReporter::new(py, progress_callback, progress_every, bars_data.len())?;
+        let checkpoint = Checkpointer::new(checkpoint_path, checkpoint_every, resume)?;
+        self.run_bars(py, &strategy, &bars_data, &indicator_columns, timeframes, progress.as_mut(), Some(&cancel_token), checkpoint.as_ref(), profile, benchmark.as_deref(), writer)
+    }
+
+    /// 直接从 DuckDB 回测
+    ///
+    /// 用 `load_klines_rust` 在 Rust 中查询 K 线并直接送入回测循环，数据全程不经过 Python 列表。
+    ///
+    /// ## 为什么需要这个方法？
+    ///
+    /// `get_market_data()` + `run()` 的组合需要先把查询结果转换成 Python 字典列表，
+    /// `run()` 再把它提取回 Rust 结构，两次转换都是纯开销。这个方法让数据从数据库到回测循环
+    /// 始终停留在 Rust 中；查询期间释放 GIL。
+    ///
+    /// ## 实际使用场景
+    ///
+    /// ```python
+    /// result = engine.run_from_db(MyStrategy(), "data/backtest.db", "AAPL", "1d",
+    ///                             start="2020-01-01", end="2020-12-31")
+    /// ```
+    ///
+    /// # 参数
+    ///
+    /// - `strategy`: Python 策略对象（同 `run()`）
+    /// - `db_path`: 数据库文件路径
+    /// - `symbol`: 交易标的代码
+    /// - `period`: 周期字符串（如 "1m", "1d"）
+    /// - `start` / `end`: 可选的时间范围，格式 "YYYY-MM-DD" 或 "YYYY-MM-DD HH:MM:SS"；不传表示不限制。
+    ///   与 `BacktestConfig` 的 `start`/`end` 取交集后下推到查询
+    /// - `indicators`、`progress_callback`、`progress_every`、`cancel_token`、`timeframes`、`benchmark`、
+    ///   `results_db`、`run_id`: 同 `run()`
+    ///
+    /// # 返回值
+    ///
+    /// 与 `run()` 相同；每根 bar 的 `symbol` 为查询的标的代码
+    ///
+    /// # 注意事项
+    ///
+    /// - 查询结果为空时返回 `ValueError`，避免在空数据上静默得到全零结果
+    #[pyo3(signature = (strategy, db_path, symbol, period, start=None, end=None, indicators=None, progress_callback=None, progress_every=0, cancel_token=None, timeframes=None, benchmark=None, results_db=None, run_id=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn run_from_db<'py>(
+        &self,
+        py: Python<'py>,
+        strategy: PyObject,
+        db_path: &str,
+        symbol: &str,
+        period: &str,
+        start: Option<&str>,
+        end: Option<&str>,
+        indicators: Option<&Bound<'py, PyList>>,
+        progress_callback: Option<PyObject>,
+        progress_every: usize,
+        cancel_token: Option<CancelToken>,
+        timeframes: Option<Vec<String>>,
+        benchmark: Option<&Bound<'py, PyAny>>,
+        results_db: Option<String>,
+        run_id: Option<String>,
+    ) -> PyResult<PyObject> {
+        let timeframes = timeframes.as_deref().map(Timeframes::new).transpose()?;
+        let benchmark = benchmark.map(|b| extract_bars_any(b.as_gil_ref())).transpose()?;
+        // 配置区间与参数区间取交集，直接下推到查询
+        let window = self.cfg.date_window()?.narrow(start, end).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        let (query_start, query_end) = window.sql_bounds();
+        let started = Instant::now();
+        let klines = py.allow_threads(|| {
+            database::load_klines_rust(db_path, symbol, period, query_start.as_deref(), query_end.as_deref(), -1)
         })?;
-        let _ = strategy.call_method1(py, "on_start", (init_ctx.as_ref(py),));
+        if klines.is_empty() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "No {} bars found for {} in {} (start={}, end={})",
+                period,
+                symbol,
+                db_path,
+                start.unwrap_or("-"),
+                end.unwrap_or("-")
+            )));
+        }
+        let bars_data: Vec<BarData> = klines.into_iter().map(BarData::from).collect();
+        self.cfg.check_bars(&bars_data)?;
+        let extraction = started.elapsed();
 
-        let mut pos = PositionState::new(self.cfg.cash);
-        let mut order_seq: u64 = 1;
+        let started = Instant::now();
+        let indicator_columns = match indicators {
+            Some(specs) => {
+                let specs = indicators::parse_indicator_specs(specs, indicators::WarmupFill::None)?;
+                indicators::compute_indicator_batch(&indicators::OhlcvColumns::from_bars(&bars_data), &specs)
+            }
+            None => Vec::new(),
+        };
+        let profile = self.cfg.profile.then(|| RunProfile { extraction, indicators: started.elapsed(), ..Default::default() });
 
-        // 预分配容量
-        let mut equity_curve: Vec<(Option<String>, f64)> = Vec::with_capacity(n_bars);
-        let mut trades: Vec<(u64, String, f64, f64)> = Vec::with_capacity(n_bars / 100);
+        let mut progress = ProgressReporter::new(py, progress_callback, progress_every, bars_data.len())?;
+        let cancel_token = cancel_token.unwrap_or_default();
+        let writer = ResultWriter::new(results_db, run_id)?;
+        self.run_bars(py, &strategy, &bars_data, &indicator_columns, timeframes, progress.as_mut(), Some(&cancel_token), None, profile, benchmark.as_deref(), writer)
+    }
 
-        // 批量处理策略调用，减少Python GIL争用
-        let batch_size = self.cfg.batch_size.min(n_bars);
-        
-        for chunk_start in (0..n_bars).step_by(batch_size) {
-            let chunk_end = (chunk_start + batch_size).min(n_bars);
-            
-            // 处理当前批次
-            for i in chunk_start..chunk_end {
-                let bar_data = &bars_data[i];
-                let last_price = bar_data.close;
+    /// 多策略对比回测
+    ///
+    /// 在同一份数据上同时回测多个策略，返回每个策略的完整结果和一张统计指标对比表。
+    ///
+    /// ## 为什么需要这个方法？
+    ///
+    /// 在 Python 中逐个调用 `run()` 对比策略时，每次都要重新把 bar 列表转换为 Rust 结构、
+    /// 重新计算指标。`run_many` 只提取一次数据和指标，所有策略共享同一份 Rust 数据。
+    ///
+    /// ## 工作原理（简单理解）
+    ///
+    /// 1. 一次性提取 bar 数据、预计算 `indicators`
+    /// 2. 在 rayon 线程池中并行回测各个策略：撮合、持仓更新与统计指标等纯 Rust 计算释放 GIL 并行执行，
+    ///    只有策略调用与订单回调需要获取 GIL
+    /// 3. 汇总每个策略的 `stats`，生成对比表
+    ///
+    /// ## 实际使用场景
+    ///
+    /// ```python
+    /// report = engine.run_many({"ma_cross": MaCross(), "breakout": Breakout()}, bars)
+    /// for row in report["table"]:
+    ///     print(row["name"], row["total_return"], row["sharpe"], row["max_drawdown"])
+    /// best = report["results"]["ma_cross"]["equity_curve"]
+    /// ```
+    ///
+    /// # 参数
+    ///
+    /// - `strategies`: 策略字典 `{名称: 策略对象}`，或策略列表（以类名命名，重名时追加 `_2`、`_3`...）
+    /// - `data`: K 线数据列表（同 `run()`）
+    /// - `indicators`: 可选的指标规格列表（同 `run()`），所有策略共享
+    ///
+    /// # 返回值
+    ///
+    /// 返回字典：
+    /// - `results`: `{名称: run() 的结果字典}`
+    /// - `table`: 对比表，按输入顺序每个策略一行：`{"name": 名称, **stats}`
+    ///
+    /// # 注意事项
+    ///
+    /// - 每个策略对象只应出现一次：各策略的回测并行执行，回调会交错调用，同一个对象被多个回测共用时内部状态会互相干扰
+    /// - 任一策略抛出异常时整个调用失败并返回该异常
+    #[pyo3(signature = (strategies, data, indicators=None))]
+    fn run_many<'py>(
+        &self,
+        py: Python<'py>,
+        strategies: &Bound<'py, PyAny>,
+        data: &Bound<'py, PyAny>,
+        indicators: Option<&Bound<'py, PyList>>,
+    ) -> PyResult<PyObject> {
+        let named = Self::named_strategies(strategies)?;
+        let bars_data = extract_bars_any(data.as_gil_ref())?;
+        self.cfg.check_bars(&bars_data)?;
+        let indicator_columns = match indicators {
+            Some(specs) => {
+                let specs = indicators::parse_indicator_specs(specs, indicators::WarmupFill::None)?;
+                indicators::compute_indicator_batch(&indicators::OhlcvColumns::from_bars(&bars_data), &specs)
+            }
+            None => Vec::new(),
+        };
+        let (bars_data, indicator_columns) = self.apply_date_window(bars_data, indicator_columns)?;
+
+        // 所有策略共享同一份数据并行回测，结果保持输入顺序
+        let results: Vec<PyObject> = py.allow_threads(|| {
+            named
+                .par_iter()
+                .map(|(_, strategy)| Python::with_gil(|py| self.run_detached(py, strategy, &bars_data, &indicator_columns)))
+                .collect::<PyResult<Vec<_>>>()
+        })?;
+
+        let results_dict = PyDict::new_bound(py);
+        let table = PyList::empty_bound(py);
+        for ((name, _), result) in named.iter().zip(results) {
+            let row = PyDict::new_bound(py);
+            row.set_item("name", name)?;
+            if let Ok(stats) = result.bind(py).get_item("stats") {
+                row.update(stats.downcast::<pyo3::types::PyMapping>()?)?;
+            }
+            table.append(row)?;
+            results_dict.set_item(name, result)?;
+        }
+        let out = PyDict::new_bound(py);
+        out.set_item("results", results_dict)?;
+        out.set_item("table", table)?;
+        Ok(out.into())
+    }
+
+    /// 信号数组回测（全向量化模式）
+    ///
+    /// 策略以预先算好的"每根 bar 的目标仓位/权重"数组表示，整个回测循环（成交、成本、净值）
+    /// 都在 Rust 中完成，不回调任何 Python 代码。
+    ///
+    /// ## 为什么需要这个方法？
+    ///
+    /// 很多规则型策略（均线交叉、阈值突破等）可以先用 `compute_*` 指标一次性算出信号，
+    /// 这时逐 bar 调用 Python `next()` 纯属浪费。`run_signals` 去掉了所有 Python 回调，
+    /// 对这类策略通常有 10-100 倍的提速。
+    ///
+    /// ## 工作原理（简单理解）
+    ///
+    /// 每根 bar 上：
+    /// 1. 读取目标值：`position` 模式下为目标持仓数量；`weight` 模式下为目标权重，
+    ///    换算为 `权重 × 当前净值 / 收盘价`
+    /// 2. 目标与当前持仓之差即为市价单数量，以收盘价加滑点成交并扣除手续费
+    /// 3. 记录成交与净值
+    ///
+    /// ## 实际使用场景
+    ///
+    /// ```python
+    /// from engine_rust import compute_sma
+    ///
+    /// closes = [b["close"] for b in bars]
+    /// fast, slow = compute_sma(closes, 10), compute_sma(closes, 30)
+    /// signals = [None if f is None or s is None else (1.0 if f > s else 0.0) for f, s in zip(fast, slow)]
+    /// result = engine.run_signals(signals, bars, mode="weight")
+    /// ```
+    ///
+    /// # 参数
+    ///
+    /// - `signals`: 与 `data` 等长的目标值列表；`None` 表示保持当前持仓不变
+    /// - `data`: K 线数据列表（同 `run()`）
+    /// - `mode`: `"position"`（默认，目标持仓数量）或 `"weight"`（目标持仓市值占净值的比例）
+    ///
+    /// # 返回值
+    ///
+    /// 与 `run()` 相同的结果字典
+    ///
+    /// # 注意事项
+    ///
+    /// - 信号在当根 bar 的收盘价成交；如果信号本身用到了当根收盘价，请先把信号整体后移一根，
+    ///   避免前视偏差
+    /// - 负的目标值表示做空
+    /// - `signals` 与 `data` 长度不一致或 `mode` 不支持时返回 `ValueError`
+    /// - `signals` 与完整的 `data` 逐根对应，引擎按配置的 `start`/`end` 同时截取两者
+    #[pyo3(signature = (signals, data, mode="position"))]
+    fn run_signals<'py>(
+        &self,
+        py: Python<'py>,
+        signals: Vec<Option<f64>>,
+        data: &Bound<'py, PyAny>,
+        mode: &str,
+    ) -> PyResult<PyObject> {
+        let by_weight = match mode {
+            "position" => false,
+            "weight" => true,
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unsupported signal mode: {} (expected position/weight)",
+                    mode
+                )))
+            }
+        };
+        let bars_data = extract_bars_any(data.as_gil_ref())?;
+        self.cfg.check_bars(&bars_data)?;
+        if signals.len() != bars_data.len() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "signals length ({}) does not match bars length ({})",
+                signals.len(),
+                bars_data.len()
+            )));
+        }
+        let window = self.cfg.date_window()?;
+        let keep = window.select(&bars_data);
+        let signals = match &keep {
+            Some(keep) => date_window::select_values(&signals, keep),
+            None => signals,
+        };
+        let bars_data = window.slice_bars(bars_data, keep.as_deref())?;
+
+        // 整个循环不涉及 Python 对象，释放 GIL 执行
+        let (pos, equity_curve, trades) = py.allow_threads(|| self.simulate_targets(&bars_data, &signals, by_weight));
+        let range = baseline::curve_range(&equity_curve);
+        let buy_and_hold = self.cfg.buy_and_hold.then(|| BuyAndHold::from_bars(&self.cfg, self.equity_bars(&bars_data, equity_curve.len())));
+        let result = self.build_result(py, pos, equity_curve, trades)?;
+        baseline::mark_result(py, &result, &self.cfg, buy_and_hold, None, range)?;
+        Self::mark_date_range(py, &result, &bars_data)?;
+        Ok(result)
+    }
+
+    /// 规则策略回测（完全在 Rust 中执行）
+    ///
+    /// 用一段规则文本描述策略，例如 `"BUY when sma(20) > sma(50); SELL when cross_below"`，
+    /// 引擎在 Rust 中解析规则、计算所需指标并逐 bar 求值，回测期间不调用任何 Python 代码。
+    /// 语法详见 `rules` 模块文档。
+    ///
+    /// ## 实际使用场景
+    ///
+    /// ```python
+    /// # 均线金叉买入、死叉卖出
+    /// result = engine.run_rules("BUY when sma(20) crosses_above sma(50); SELL when sma(20) crosses_below sma(50)", bars)
+    ///
+    /// # RSI 超卖买入、超买或跌破 20 日低点卖出，满仓运行
+    /// result = engine.run_rules(
+    ///     "BUY when rsi(14) < 30\nSELL when rsi(14) > 70 or close < lowest(20)",
+    ///     bars, size=1.0, mode="weight",
+    /// )
+    /// ```
+    ///
+    /// # 参数
+    ///
+    /// - `rules`: 规则文本
+    /// - `data`: K 线数据列表（同 `run()`）
+    /// - `size`: BUY 时的目标值，默认 1.0
+    /// - `mode`: `"position"`（`size` 为持仓数量，默认）或 `"weight"`（`size` 为净值权重）
+    ///
+    /// # 返回值
+    ///
+    /// 与 `run()` 相同的结果字典
+    ///
+    /// # 注意事项
+    ///
+    /// - 规则只做多：空仓时 BUY 建仓，持仓时 SELL 全部平仓
+    /// - 条件在当根 bar 收盘后求值并以收盘价成交，与 `run_signals()` 一致
+    /// - 规则语法错误时返回 `ValueError`
+    /// - 规则在完整数据上求值后再截取配置的 `start`/`end` 区间，区间开头的指标不缺历史
+    #[pyo3(signature = (rules, data, size=1.0, mode="position"))]
+    fn run_rules<'py>(&self, py: Python<'py>, rules: &str, data: &Bound<'py, PyAny>, size: f64, mode: &str) -> PyResult<PyObject> {
+        let by_weight = match mode {
+            "position" => false,
+            "weight" => true,
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unsupported signal mode: {} (expected position/weight)",
+                    mode
+                )))
+            }
+        };
+        let program = RuleProgram::parse(rules)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid rule program: {}", e)))?;
+        let bars_data = extract_bars_any(data.as_gil_ref())?;
+        self.cfg.check_bars(&bars_data)?;
+        let window = self.cfg.date_window()?;
+        let keep = window.select(&bars_data);
+
+        // 指标计算、规则求值与撮合都不涉及 Python 对象，释放 GIL 执行；
+        // 规则在完整数据上求值，再截取回测区间，区间开头的指标不缺历史
+        let bars_data = py.allow_threads(|| -> PyResult<_> {
+            let targets = program.targets(&indicators::OhlcvColumns::from_bars(&bars_data), size, self.cfg.warmup_bars);
+            let targets = match &keep {
+                Some(keep) => date_window::select_values(&targets, keep),
+                None => targets,
+            };
+            let bars_data = window.slice_bars(bars_data, keep.as_deref())?;
+            Ok((self.simulate_targets(&bars_data, &targets, by_weight), bars_data))
+        })?;
+        let ((pos, equity_curve, trades), bars_data) = bars_data;
+        let range = baseline::curve_range(&equity_curve);
+        let buy_and_hold = self.cfg.buy_and_hold.then(|| BuyAndHold::from_bars(&self.cfg, self.equity_bars(&bars_data, equity_curve.len())));
+        let result = self.build_result(py, pos, equity_curve, trades)?;
+        baseline::mark_result(py, &result, &self.cfg, buy_and_hold, None, range)?;
+        Self::mark_date_range(py, &result, &bars_data)?;
+        Ok(result)
+    }
+
+    /// 执行多资产/多周期回测
+    ///
+    /// 这个方法支持同时回测多个资产或多个时间周期，就像同时观察多个"时间机器"的运行。
+    /// 它会将所有数据源的时间线合并，按照统一的时间顺序推进，让策略能够同时看到多个资产的状态。
+    ///
+    /// ## 为什么需要这个方法？
+    ///
+    /// 在实际交易中，我们经常需要：
+    /// - 同时交易多个股票（多资产策略）
+    /// - 使用不同周期的数据（如日线和分钟线结合）
+    /// - 进行资产配置和再平衡
+    ///
+    /// 单资产回测无法满足这些需求，因此需要多资产回测功能。
+    ///
+    /// ## 工作原理（简单理解）
+    ///
+    /// 想象你在同时观察多个股票的走势图：
+    ///
+    /// 1. **准备多个数据源**：每个数据源（feed）包含一个资产或周期的 K 线数据
+    /// 2. **合并时间线**：找到所有数据源中最早的时间点，作为当前处理时间
+    /// 3. **构造更新切片**：将所有在当前时间有更新的数据源打包成一个字典
+    /// 4. **调用策略**：策略可以看到所有资产的当前状态，做出交易决策
+    /// 5. **执行订单**：订单可以指定不同的 symbol，分别更新对应资产的持仓
+    /// 6. **计算组合净值**：汇总所有资产的持仓市值和现金，得到组合总净值
+    ///
+    /// ## 实际使用场景
+    ///
+    /// 适用于多资产组合策略、资产配置、再平衡等场景：
+    ///
+    /// ```python
+    /// # 准备多个资产的数据
+    /// feeds = {
+    ///     "AAPL": aapl_bars,      # 苹果股票
+    ///     "GOOGL": googl_bars,     # 谷歌股票
+    ///     "SPY": spy_bars,         # 标普500 ETF
+    /// }
+    ///
+    /// # 实现多资产策略
+    /// class MultiAssetStrategy(Strategy):
+    ///     def next_multi(self, update_slice, ctx):
+    ///         # update_slice 包含所有在当前时间有更新的资产
+    ///         # ctx.positions 包含所有资产的持仓信息
+    ///         # ctx.last_prices 包含所有资产的最新价格
+    ///
+    ///         # 等权重配置策略
+    ///         target_weight = 1.0 / len(ctx.positions)
+    ///         orders = []
+    ///
+    ///         for symbol in ["AAPL", "GOOGL", "SPY"]:
+    ///             if symbol in update_slice:
+    ///                 current_price = ctx.last_prices.get(symbol, 0)
+    ///                 current_pos = ctx.positions.get(symbol, {}).get("position", 0)
+    ///                 target_value = ctx.equity * target_weight
+    ///                 target_pos = target_value / current_price if current_price > 0 else 0
+    ///
+    ///                 if target_pos > current_pos:
+    ///                     orders.append({
+    ///                         "action": "BUY",
+    ///                         "type": "market",
+    ///                         "size": target_pos - current_pos,
+    ///                         "symbol": symbol
+    ///                     })
+    ///
+    ///         return orders if orders else None
+    ///
+    /// # 运行多资产回测
+    /// result = engine.run_multi(MultiAssetStrategy(), feeds)
+    /// ```
+    ///
+    /// ## 策略接口
+    ///
+    /// 策略可以实现两种方法：
+    /// - `next_multi(update_slice, ctx)`: 多资产专用方法（推荐）
+    /// - `next(bar, ctx)`: 单资产方法（回退，使用第一个 feed 的数据）
+    ///
+    /// `update_slice` 是一个字典：`{feed_id: bar_dict}`，包含所有在当前时间有更新的资产。
+    /// `ctx` 包含组合级别的信息：`positions`（各资产持仓）、`last_prices`（最新价格映射）、`equity`（组合净值）。
+    ///
+    /// 配置了 `rebalance`/`rebalance_dates` 时，引擎还会在联合时间线的调仓点调用 `on_rebalance(ctx)`，
+    /// 返回值格式与 `next_multi()` 相同（单个动作或动作列表），无需在策略里自己判断月末。
+    ///
+    /// 可选钩子 `on_bar_end(ctx)` 在每个时间点的成交处理完毕后调用，
+    /// `on_session_end(ctx)` 在联合时间线上每个交易日的最后一个时间点调用；两者的返回值会被忽略。
+    ///
+    /// ## 可能遇到的问题
+    ///
+    /// ### 时间对齐
+    ///
+    /// 不同资产的数据可能时间不完全一致（如不同交易所的交易时间）。
+    /// 引擎会按照联合时间线推进，如果某个资产在某个时间点没有数据，则不会出现在 `update_slice` 中。
+    /// 联合时间线按时间值（epoch 毫秒）对齐，而不是比较字符串：`"2024-01-02"` 与 `"2024-01-02 00:00:00"`、
+    /// 整数时间戳与等价的时间字符串都视为同一时间点。每根 bar 的时间在回测开始前解析一次，
+    /// 没有 `datetime` 或无法解析的 bar 会返回 `ValueError`。
+    ///
+    /// ### 订单格式
+    ///
+    /// 多资产回测的订单必须包含 `symbol` 字段，指定交易哪个资产。
+    /// 可以返回单个订单或订单列表。
+    ///
+    /// ### 目标权重
+    ///
+    /// 也可以返回目标权重：`{symbol: 权重}` 字典（只调整列出的标的），或与 `feeds` 顺序一致的权重列表 / numpy 数组。
+    /// 引擎按当前净值和最新价格换算为市价单，按 `lot_size` 向零取整，偏离不超过 `rebalance_band` 的标的不调仓：
+    ///
+    /// ```python
+    /// def next_multi(self, update_slice, ctx):
+    ///     return {"AAPL": 0.5, "GOOGL": 0.3}
+    /// ```
+    ///
+    /// # 参数
+    ///
+    /// - `strategy`: Python 策略对象，建议实现 `next_multi()` 方法
+    /// - `feeds`: 数据源字典，格式为 `{feed_id: list[bar]}`，每个 bar 至少包含 `datetime` 和 `close`；
+    ///   `datetime` 可以是时间字符串或整数 epoch 毫秒时间戳
+    /// - `progress_callback`: 可选的进度回调（同 `run()`），`total` 为所有 feed 的 bar 总数
+    /// - `progress_every`: 每处理多少根 bar 汇报一次进度，默认 0 表示自动（约每 1%）
+    /// - `cancel_token`: 可选的取消令牌（同 `run()`），Ctrl+C 也会停止回测并返回部分结果
+    /// - `lot_size`: 目标权重换算数量时的每手数量，数值（所有标的）或 `{symbol: 数值}`，默认不取整
+    /// - `rebalance_band`: 目标权重的调仓容差（如 `0.02` 表示当前权重与目标相差不超过 2 个百分点时不交易），默认 0
+    ///
+    /// # 返回值
+    ///
+    /// 返回格式与 `run()` 相同，但 `position` 和 `avg_cost` 为 0；期末各资产的持仓在 `positions` 中：
+    /// `{symbol: {"position", "avg_cost", "last_price", "market_value", "unrealized_pnl"}}`，
+    /// 市值与浮动盈亏按该资产的最新价格计算（从未收到过价格时这三项为 `None`），已平仓的资产持仓为 0。
+    ///
+    /// # 示例
+    ///
+    /// ```python
+    /// feeds = {"AAPL": aapl_bars, "GOOGL": googl_bars}
+    /// result = engine.run_multi(MyStrategy(), feeds)
+    /// ```
+    #[pyo3(signature = (strategy, feeds, progress_callback=None, progress_every=0, cancel_token=None, lot_size=None, rebalance_band=0.0))]
+    #[allow(clippy::too_many_arguments)]
+    fn run_multi<'py>(
+        &self,
+        py: Python<'py>,
+        strategy: PyObject,
+        feeds: &Bound<'py, PyAny>,
+        progress_callback: Option<PyObject>,
+        progress_every: usize,
+        cancel_token: Option<CancelToken>,
+        lot_size: Option<&Bound<'py, PyAny>>,
+        rebalance_band: f64,
+    ) -> PyResult<PyObject> {
+        let sizer = WeightSizer::new(LotSizes::parse(lot_size)?, rebalance_band)?;
+        self._run_multi_impl(py, strategy, feeds, progress_callback, progress_every, cancel_token, sizer)
+    }
+}
+
+/// `simulate_targets()` 的结果：(期末持仓, 净值曲线, 成交记录)
+type SimulatedRun = (PositionState, Vec<(Option<String>, f64)>, Vec<TradeRecord>);
+
+impl BacktestEngine {
+    /// 按目标仓位序列执行回测（纯 Rust，不涉及 Python）
+    ///
+    /// `run_signals()` 和规则策略共用的核心循环：每根 bar 以收盘价（加滑点）把持仓调整到目标值，
+    /// `None` 表示保持不变；`by_weight` 为 `true` 时目标值按净值权重换算为数量。
+    fn simulate_targets(
+        &self,
+        bars_data: &[BarData],
+        targets: &[Option<f64>],
+        by_weight: bool,
+    ) -> SimulatedRun {
+        let mut pos = PositionState::new(self.cfg.cash);
+        let mut order_seq: u64 = 1;
+        let mut equity_curve: Vec<(Option<String>, f64)> = Vec::with_capacity(bars_data.len());
+        let mut trades: Vec<TradeRecord> = Vec::new();
+        let slip = self.cfg.slippage_bps / 10_000.0;
+
+        for (bar_data, target) in bars_data.iter().zip(targets).skip(self.cfg.warmup_bars) {
+            let last_price = bar_data.close;
+            if let Some(target_value) = *target {
+                let target = if by_weight {
+                    let equity = pos.cash + pos.position * last_price;
+                    if last_price > 0.0 { target_value * equity / last_price } else { pos.position }
+                } else {
+                    target_value
+                };
+                let delta = target - pos.position;
+                if delta.abs() > 1e-12 {
+                    let side = if delta > 0.0 { OrderSide::Buy } else { OrderSide::Sell };
+                    let order = Order {
+                        id: order_seq,
+                        side,
+                        otype: OrderType::Market,
+                        size: delta.abs(),
+                        limit_price: None,
+                        status: "filled",
+                        symbol: bar_data.symbol.clone().unwrap_or_else(|| "DEFAULT".to_string()),
+                        submitted_bar: 0,
+                    };
+                    order_seq += 1;
+                    let sign = match side { OrderSide::Buy => 1.0, OrderSide::Sell => -1.0 };
+                    let exec_price = last_price * (1.0 + sign * slip);
+                    let commission = exec_price * order.size * self.cfg.commission_rate;
+                    self.update_position(&mut pos, &order, exec_price, order.size, commission);
+                    trades.push(TradeRecord::new(&order, last_price, exec_price, order.size, commission, bar_data.datetime.as_deref(), pos.position, pos.cash));
+                }
+            }
+            equity_curve.push((bar_data.datetime.clone(), pos.cash + pos.position * last_price));
+        }
+
+        (pos, equity_curve, trades)
+    }
+
+    /// 把 `run_many()` 的策略参数规范为 `(名称, 策略)` 列表
+    ///
+    /// 字典按键命名；列表按类名命名，重名时追加序号。
+    fn named_strategies(strategies: &Bound<'_, PyAny>) -> PyResult<Vec<(String, PyObject)>> {
+        if let Ok(dict) = strategies.downcast::<PyDict>() {
+            return dict
+                .iter()
+                .map(|(k, v)| Ok((k.str()?.to_string(), v.into_py(strategies.py()))))
+                .collect();
+        }
+        let mut named: Vec<(String, PyObject)> = Vec::new();
+        for strategy in strategies.iter()? {
+            let strategy = strategy?;
+            let base: String = strategy.get_type().getattr("__name__")?.extract()?;
+            let mut name = base.clone();
+            let mut k = 1;
+            while named.iter().any(|(n, _)| *n == name) {
+                k += 1;
+                name = format!("{}_{}", base, k);
+            }
+            named.push((name, strategy.into_py(strategies.py())));
+        }
+        Ok(named)
+    }
+
+    /// 在预提取的 bar 数据上执行单资产回测
+    ///
+    /// `run()` 的核心循环：调用方负责提取 bar 数据和预计算指标，
+    /// 参数优化等需要在同一份数据上反复回测的场景可以直接复用，避免重复转换。
+    /// `profile` 携带调用方的数据提取与指标耗时；为 `None` 但配置开启了 `profile` 时从零开始计时。
+    /// 传入 `writer` 时净值曲线和成交在批次边界写入数据库，结果中不构建对应的 Python 列表。
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn run_bars(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        bars_data: &[BarData],
+        indicator_columns: &[(String, Vec<Option<f64>>)],
+        mut timeframes: Option<Timeframes>,
+        mut progress: Option<&mut ProgressReporter>,
+        cancel: Option<&CancelToken>,
+        checkpoint: Option<&Checkpointer>,
+        profile: Option<RunProfile>,
+        benchmark: Option<&[BarData]>,
+        mut writer: Option<ResultWriter>,
+    ) -> PyResult<PyObject> {
+        let n_bars = bars_data.len();
+
+        // 从检查点恢复（如果配置了 resume），否则从头开始
+        let mut resumed = match checkpoint {
+            Some(cp) => cp.load(bars_data)?,
+            None => None,
+        };
+        let start_bar = resumed.as_ref().map_or(0, |r| r.next_bar);
+        let mut st = match resumed.as_mut() {
+            // 由配置解析的设置不写入检查点，恢复时重新解析
+            Some(r) => RunState {
+                exposure_limits: self.cfg.exposure_limits()?,
+                vol_target: self.cfg.vol_targeting()?,
+                defer_orders: self.cfg.defers_orders()?,
+                ..std::mem::take(&mut r.state)
+            },
+            None => self.new_run_state(n_bars)?,
+        };
+        st.profile = profile.or_else(|| self.cfg.profile.then(RunProfile::default));
+
+        // 初始上下文（无价格时以现金估算净值；恢复时按断点处的收盘价估算）
+        let last_bar = start_bar.checked_sub(1).and_then(|k| bars_data.get(k));
+        let init_ctx = Py::new(
+            py,
+            EngineContext::snapshot(&st.pos, last_bar.map(|b| b.close), last_bar.and_then(|b| b.datetime.as_deref()), start_bar),
+        )?;
+        let schedule = self.cfg.rebalance_schedule()?;
+        let hooks = StrategyHooks::detect(py, strategy)?;
+        if hooks.on_start {
+            let _ = strategy.call_method1(py, "on_start", (init_ctx.bind(py),));
+        }
+        if let Some(r) = &resumed {
+            r.restore_strategy(py, strategy, &hooks)?;
+        }
+
+        // 调仓日历预计算（未配置时全部为 false）
+        let bar_dates = schedule::bar_dates(bars_data.iter().map(|b| b.datetime.as_deref()));
+        let rebalance_flags = schedule.flags(&bar_dates);
+        let session_flags = if hooks.on_session_end { session_end_flags(&bar_dates) } else { Vec::new() };
+        let early_stop = self.cfg.early_stop()?;
+        let sessions = self.cfg.trading_sessions()?;
+        let outside_flags: Vec<bool> = match &sessions {
+            Some(s) => bars_data.iter().map(|b| s.outside(b.datetime.as_deref())).collect(),
+            None => vec![false; n_bars],
+        };
+
+        // 批量处理策略调用，减少Python GIL争用
+        let batch_size = self.cfg.batch_size.min(n_bars).max(1);
+
+        // 从检查点恢复时，用已处理的 bar 重建高周期 K 线
+        if let Some(tf) = timeframes.as_mut() {
+            for i in 0..start_bar {
+                tf.update(&bars_data[i], bars_data.get(i + 1))?;
+            }
+        }
+
+        // 单根 bar 的处理逻辑：策略回调中抛出的 KeyboardInterrupt 由外层循环转换为取消
+        let process_bar = |i: usize, st: &mut RunState, timeframes: &mut Option<Timeframes>| -> PyResult<()> {
+            let flags = BarFlags {
+                rebalance: rebalance_flags[i],
+                session_end: hooks.on_session_end && session_flags[i],
+                outside_session: outside_flags[i],
+            };
+            if let Some(tf) = timeframes.as_mut() {
+                tf.update(&bars_data[i], bars_data.get(i + 1))?;
+            }
+            self.process_bar(py, strategy, &hooks, st, i, &bars_data[i], indicator_columns, timeframes.as_ref(), flags)
+        };
+
+        let mut cancelled: Option<CancelReason> = None;
+        let mut stopped: Option<StopReason> = None;
+        let mut last_checkpoint = start_bar;
+        let loop_started = Instant::now();
+        let mut processed = 0usize;
+        'run: for chunk_start in (start_bar..n_bars).step_by(batch_size) {
+            let chunk_end = (chunk_start + batch_size).min(n_bars);
+
+            // 批次边界：之前的 bar 均已完整处理，按间隔写入检查点
+            if let Some(w) = writer.as_mut() {
+                w.flush(py, &st);
+            }
+            if let Some(cp) = checkpoint {
+                if cp.due(chunk_start - last_checkpoint) {
+                    cp.save(py, strategy, &hooks, bars_data, chunk_start, &st)?;
+                    last_checkpoint = chunk_start;
+                }
+            }
+
+            // 每个批次开始时检查 Ctrl+C 与取消令牌
+            if let Some(token) = cancel {
+                cancelled = cancel::check_cancel(py, token)?;
+                if cancelled.is_some() {
+                    if let Some(cp) = checkpoint {
+                        cp.save(py, strategy, &hooks, bars_data, chunk_start, &st)?;
+                    }
+                    break;
+                }
+            }
+
+            // 处理当前批次
+            for i in chunk_start..chunk_end {
+                let step = match progress.as_deref_mut() {
+                    Some(p) => p.tick(py, i).and_then(|_| process_bar(i, &mut st, &mut timeframes)),
+                    None => process_bar(i, &mut st, &mut timeframes),
+                };
+                if let Err(err) = step {
+                    match cancel {
+                        Some(_) => {
+                            cancelled = Some(cancel::interrupt_reason(py, err)?);
+                            break 'run;
+                        }
+                        None => return Err(err),
+                    }
+                }
+                processed += 1;
+                stopped = early_stop.check(i + 1, self.bar_equity(&st, i));
+                if stopped.is_some() {
+                    break 'run;
+                }
+            }
+        }
+        if let Some(p) = st.profile.as_mut() {
+            p.main_loop += loop_started.elapsed();
+            p.bars += processed;
+        }
+
+        if cancelled.is_none() {
+            if let Some(p) = progress {
+                p.finish(py)?;
+            }
+            // 完整跑完（或提前终止）也写入检查点，之后以 resume 再次运行会直接返回结果
+            if let Some(cp) = checkpoint {
+                cp.save(py, strategy, &hooks, bars_data, n_bars, &st)?;
+            }
+            self.cancel_deferred(py, strategy, &hooks, &mut st)?;
+        }
+
+        if hooks.on_stop {
+            let _ = strategy.call_method0(py, "on_stop");
+        }
+
+        // 构建结果（优化版）；被取消时为截至当前的部分结果
+        let profile = st.profile.take();
+        let result_started = Instant::now();
+        let range = baseline::curve_range(&st.equity_curve);
+        let buy_and_hold = self.cfg.buy_and_hold.then(|| BuyAndHold::from_bars(&self.cfg, self.equity_bars(bars_data, st.equity_curve.len())));
+        let written = writer.map(|w| w.finish(py, &st)).transpose()?;
+        let result = self.build_result_with(py, st.pos, st.equity_curve, st.trades, written.is_none())?;
+        if self.cfg.record_positions {
+            positions::mark_result(py, &result, &st.positions_history)?;
+        }
+        result_writer::mark_result(py, &result, written)?;
+        baseline::mark_result(py, &result, &self.cfg, buy_and_hold, benchmark, range)?;
+        if let Some(mut p) = profile {
+            p.result += result_started.elapsed();
+            p.write_to(py, &result)?;
+        }
+        if cancel.is_some() {
+            cancel::mark_result(py, &result, cancelled)?;
+        }
+        early_stop.mark_result(py, &result, stopped, cancelled.is_some())?;
+        Self::mark_date_range(py, &result, bars_data)?;
+        Ok(result)
+    }
+
+    /// 新回测的初始状态；`n_bars` 为已知的 bar 数，用于预分配容量（流式数据时为 0）
+    fn new_run_state(&self, n_bars: usize) -> PyResult<RunState> {
+        Ok(RunState {
+            pos: PositionState::new(self.cfg.cash),
+            order_seq: 1,
+            equity_curve: Vec::with_capacity(n_bars),
+            trades: Vec::with_capacity(n_bars / 100),
+            deferred: Vec::new(),
+            positions_history: Vec::new(),
+            kelly: KellyStats::default(),
+            vol_returns: ReturnWindow::default(),
+            profile: None,
+            exposure_limits: self.cfg.exposure_limits()?,
+            vol_target: self.cfg.vol_targeting()?,
+            defer_orders: self.cfg.defers_orders()?,
+        })
+    }
+
+    /// 在预提取的 bar 数据上执行单资产回测，回测期间只在策略回调时持有 GIL
+    ///
+    /// `run_many()` 与参数优化在 rayon 线程池中为每个策略调用它，结果与 `run_bars`（不带进度、取消、
+    /// 检查点和结果写库时）一致。逐 bar 循环释放 GIL，每根 bar 只在构造 bar 字典、调用策略与订单回调时
+    /// 重新获取一次；统计指标、净值汇总与买入持有基准等纯 Rust 计算不持有 GIL，
+    /// 多个回测的这部分工作在线程池中并行执行。
+    pub(crate) fn run_detached(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        bars_data: &[BarData],
+        indicator_columns: &[(String, Vec<Option<f64>>)],
+    ) -> PyResult<PyObject> {
+        let mut st = RunState { profile: self.cfg.profile.then(RunProfile::default), ..self.new_run_state(bars_data.len())? };
+        let hooks = StrategyHooks::detect(py, strategy)?;
+        if hooks.on_start {
+            let init_ctx = Bound::new(py, EngineContext::snapshot(&st.pos, None, None, 0))?;
+            let _ = strategy.call_method1(py, "on_start", (init_ctx,));
+        }
+        let schedule = self.cfg.rebalance_schedule()?;
+        let early_stop = self.cfg.early_stop()?;
+        let sessions = self.cfg.trading_sessions()?;
+
+        let stopped = py.allow_threads(|| -> PyResult<Option<StopReason>> {
+            let bar_dates = schedule::bar_dates(bars_data.iter().map(|b| b.datetime.as_deref()));
+            let rebalance_flags = schedule.flags(&bar_dates);
+            let session_flags = if hooks.on_session_end { session_end_flags(&bar_dates) } else { Vec::new() };
+            let loop_started = Instant::now();
+            let mut stopped = None;
+            let mut processed = 0usize;
+            for (i, bar) in bars_data.iter().enumerate() {
+                let flags = BarFlags {
+                    rebalance: rebalance_flags[i],
+                    session_end: hooks.on_session_end && session_flags[i],
+                    outside_session: sessions.as_ref().is_some_and(|s| s.outside(bar.datetime.as_deref())),
+                };
+                Python::with_gil(|py| self.process_bar(py, strategy, &hooks, &mut st, i, bar, indicator_columns, None, flags))?;
+                processed += 1;
+                stopped = early_stop.check(i + 1, self.bar_equity(&st, i));
+                if stopped.is_some() {
+                    break;
+                }
+            }
+            if let Some(p) = st.profile.as_mut() {
+                p.main_loop += loop_started.elapsed();
+                p.bars += processed;
+            }
+            Ok(stopped)
+        })?;
+
+        self.cancel_deferred(py, strategy, &hooks, &mut st)?;
+        if hooks.on_stop {
+            let _ = strategy.call_method0(py, "on_stop");
+        }
+
+        // 统计指标与基准在释放 GIL 时计算，之后只构建 Python 结果
+        let profile = st.profile.take();
+        let result_started = Instant::now();
+        let (stats, range, buy_and_hold) = py.allow_threads(|| {
+            let stats = EnhancedStats::compute(&st.equity_curve, &st.trades, self.cfg.periods_per_year());
+            let buy_and_hold = self.cfg.buy_and_hold.then(|| BuyAndHold::from_bars(&self.cfg, self.equity_bars(bars_data, st.equity_curve.len())));
+            (stats, baseline::curve_range(&st.equity_curve), buy_and_hold)
+        });
+        let stats = EnhancedStats::to_py(py, stats.as_ref())?;
+        let result = self.result_dict(py, st.pos, st.equity_curve, st.trades, true, stats)?;
+        if self.cfg.record_positions {
+            positions::mark_result(py, &result, &st.positions_history)?;
+        }
+        baseline::mark_result(py, &result, &self.cfg, buy_and_hold, None, range)?;
+        if let Some(mut p) = profile {
+            p.result += result_started.elapsed();
+            p.write_to(py, &result)?;
+        }
+        early_stop.mark_result(py, &result, stopped, false)?;
+        Self::mark_date_range(py, &result, bars_data)?;
+        Ok(result)
+    }
+
+    /// 流式数据的单资产回测循环
+    ///
+    /// 与 `run_bars` 的逻辑一致，区别在于数据按批次从 `stream` 拉取：处理当前批次时预读下一批，
+    /// 用于判断批次末尾 bar 的调仓与交易日结束。每个批次开始时检查取消。
+    #[allow(clippy::too_many_arguments)]
+    fn run_stream(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        stream: &mut BarStream,
+        mut timeframes: Option<Timeframes>,
+        mut progress: Option<&mut ProgressReporter>,
+        cancel: &CancelToken,
+        benchmark: Option<&[BarData]>,
+        mut writer: Option<ResultWriter>,
+    ) -> PyResult<PyObject> {
+        let mut st = RunState { profile: self.cfg.profile.then(RunProfile::default), ..self.new_run_state(0)? };
+
+        let schedule = self.cfg.rebalance_schedule()?;
+        let hooks = StrategyHooks::detect(py, strategy)?;
+        let init_ctx = Py::new(py, EngineContext::snapshot(&st.pos, None, None, 0))?;
+        if hooks.on_start {
+            let _ = strategy.call_method1(py, "on_start", (init_ctx.bind(py),));
+        }
+        let mut schedule_cursor = 0usize;
+        let early_stop = self.cfg.early_stop()?;
+        let sessions = self.cfg.trading_sessions()?;
+        let window = self.cfg.date_window()?;
+        let mut first_dt: Option<String> = None;
+        let mut last_dt: Option<String> = None;
+        // 流式数据不保留 bar，买入持有基准逐根增量计算
+        let mut buy_and_hold = self.cfg.buy_and_hold.then(|| BuyAndHold::new(&self.cfg));
+
+        let mut cancelled: Option<CancelReason> = None;
+        let mut stopped: Option<StopReason> = None;
+        let mut done = 0usize;
+        // 拉取数据的耗时计入数据转换，而不是主循环
+        let loop_started = Instant::now();
+        let mut fetching = std::time::Duration::ZERO;
+        // 区间外的 bar 在拉取时丢弃；整批都在区间外时继续拉取下一批
+        // 严格模式下跨批次连续编号、比较时间顺序
+        let mut validator = self.cfg.strict.then(BarValidator::default);
+        let mut fetch = |stream: &mut BarStream| -> PyResult<Option<Vec<BarData>>> {
+            let started = Instant::now();
+            let chunk = loop {
+                match stream.next_chunk(py)? {
+                    Some(mut bars) => {
+                        if let Some(validator) = validator.as_mut() {
+                            validator.check_all(&bars)?;
+                        }
+                        bars.retain(|b| window.contains(b.datetime.as_deref()));
+                        if !bars.is_empty() {
+                            break Some(bars);
+                        }
+                    }
+                    None => break None,
+                }
+            };
+            fetching += started.elapsed();
+            Ok(chunk)
+        };
+        let mut chunk = fetch(stream)?;
+        'run: while let Some(bars) = chunk.take() {
+            if let Some(w) = writer.as_mut() {
+                w.flush(py, &st);
+            }
+            cancelled = cancel::check_cancel(py, cancel)?;
+            if cancelled.is_some() {
+                break;
+            }
+            if first_dt.is_none() {
+                first_dt = bars[0].datetime.clone();
+            }
+            last_dt = bars[bars.len() - 1].datetime.clone();
+
+            // 预读下一批，取其第一个有效日期作为本批次最后一根 bar 的"下一根日期"
+            let next_chunk = fetch(stream)?;
+            let lookahead = next_chunk
+                .as_ref()
+                .and_then(|next| schedule::bar_dates(next.iter().map(|b| b.datetime.as_deref())).into_iter().flatten().next());
+            let dates = schedule::bar_dates(bars.iter().map(|b| b.datetime.as_deref()));
+            let next_dates = schedule::next_dates(&dates, lookahead);
+
+            for (j, bar_data) in bars.iter().enumerate() {
+                let flags = BarFlags {
+                    rebalance: hooks.on_rebalance && schedule.is_due(dates[j], next_dates[j], &mut schedule_cursor),
+                    session_end: hooks.on_session_end
+                        && dates[j].is_some_and(|d| RebalanceFreq::Daily.is_period_end(d, next_dates[j])),
+                    outside_session: sessions.as_ref().is_some_and(|s| s.outside(bar_data.datetime.as_deref())),
+                };
+                let i = done + j;
+                if let Some(tf) = timeframes.as_mut() {
+                    let next = bars.get(j + 1).or_else(|| next_chunk.as_ref().and_then(|c| c.first()));
+                    tf.update(bar_data, next)?;
+                }
+                let step = match progress.as_deref_mut() {
+                    Some(p) => p.tick(py, i).and_then(|_| self.process_bar(py, strategy, &hooks, &mut st, i, bar_data, &[], timeframes.as_ref(), flags)),
+                    None => self.process_bar(py, strategy, &hooks, &mut st, i, bar_data, &[], timeframes.as_ref(), flags),
+                };
+                if let Err(err) = step {
+                    cancelled = Some(cancel::interrupt_reason(py, err)?);
+                    done += j;
+                    break 'run;
+                }
+                if let Some(bh) = buy_and_hold.as_mut().filter(|_| i >= self.cfg.warmup_bars) {
+                    bh.update(bar_data);
+                }
+                stopped = early_stop.check(i + 1, self.bar_equity(&st, i));
+                if stopped.is_some() {
+                    done += j + 1;
+                    break 'run;
+                }
+            }
+            done += bars.len();
+            chunk = next_chunk;
+        }
+        if let Some(p) = st.profile.as_mut() {
+            p.extraction += fetching;
+            p.main_loop += loop_started.elapsed().saturating_sub(fetching);
+            p.bars += done;
+        }
+
+        if cancelled.is_none() {
+            if let Some(p) = progress {
+                p.set_total(py, done);
+                p.finish(py)?;
+            }
+            self.cancel_deferred(py, strategy, &hooks, &mut st)?;
+        }
+
+        if hooks.on_stop {
+            let _ = strategy.call_method0(py, "on_stop");
+        }
+
+        let profile = st.profile.take();
+        let result_started = Instant::now();
+        let range = baseline::curve_range(&st.equity_curve);
+        let written = writer.map(|w| w.finish(py, &st)).transpose()?;
+        let result = self.build_result_with(py, st.pos, st.equity_curve, st.trades, written.is_none())?;
+        if self.cfg.record_positions {
+            positions::mark_result(py, &result, &st.positions_history)?;
+        }
+        result_writer::mark_result(py, &result, written)?;
+        baseline::mark_result(py, &result, &self.cfg, buy_and_hold, benchmark, range)?;
+        if let Some(mut p) = profile {
+            p.result += result_started.elapsed();
+            p.write_to(py, &result)?;
+        }
+        cancel::mark_result(py, &result, cancelled)?;
+        early_stop.mark_result(py, &result, stopped, cancelled.is_some())?;
+        date_window::mark_result(py, &result, first_dt.as_deref(), last_dt.as_deref(), done)?;
+        Ok(result)
+    }
+
+    /// 按配置的回测区间截取 bar 与预计算的指标列（指标已在完整数据上算好）
+    pub(crate) fn apply_date_window(
+        &self,
+        bars: Vec<BarData>,
+        indicator_columns: indicators::IndicatorOutputs,
+    ) -> PyResult<(Vec<BarData>, indicators::IndicatorOutputs)> {
+        let window = self.cfg.date_window()?;
+        let Some(keep) = window.select(&bars) else { return Ok((bars, indicator_columns)) };
+        let indicator_columns = indicator_columns
+            .into_iter()
+            .map(|(name, values)| (name, date_window::select_values(&values, &keep)))
+            .collect();
+        Ok((window.slice_bars(bars, Some(&keep))?, indicator_columns))
+    }
+
+    /// 与净值曲线逐根对应的 bar（预热期之后的前 `len` 根）
+    pub(crate) fn equity_bars<'a>(&self, bars: &'a [BarData], len: usize) -> &'a [BarData] {
+        let from = self.cfg.warmup_bars.min(bars.len());
+        &bars[from..(from + len).min(bars.len())]
+    }
+
+    /// 在结果中记录 `bars` 覆盖的时间范围（`date_range`）
+    pub(crate) fn mark_date_range(py: Python<'_>, result: &PyObject, bars: &[BarData]) -> PyResult<()> {
+        let first = bars.first().and_then(|b| b.datetime.as_deref());
+        let last = bars.last().and_then(|b| b.datetime.as_deref());
+        date_window::mark_result(py, result, first, last, bars.len())
+    }
+
+    /// 第 `i` 根 bar 处理完后的净值（预热期不记录净值，返回 `None`）
+    fn bar_equity(&self, st: &RunState, i: usize) -> Option<f64> {
+        if i < self.cfg.warmup_bars {
+            return None;
+        }
+        st.equity_curve.last().map(|(_, equity)| *equity)
+    }
+
+    /// 处理单根 bar：调用 `next()`、执行订单、触发调仓与生命周期钩子、记录净值
+    ///
+    /// `run()` 的批量循环与流式数据循环共用这套逻辑。`i` 是 bar 在整个回测中的序号，
+    /// `indicator_columns` 按 `i` 取值（流式数据时为空）；`timeframes` 为已用本 bar 更新过的高周期 K 线。
+    #[allow(clippy::too_many_arguments)]
+    fn process_bar(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        hooks: &StrategyHooks,
+        st: &mut RunState,
+        i: usize,
+        bar_data: &BarData,
+        indicator_columns: &[(String, Vec<Option<f64>>)],
+        timeframes: Option<&Timeframes>,
+        flags: BarFlags,
+    ) -> PyResult<()> {
+        let last_price = bar_data.close;
+        // 波动率目标：记录收益率（预热 bar 同样计入）
+        if self.cfg.vol_target.is_some() {
+            let symbol = bar_data.symbol.as_deref().unwrap_or("DEFAULT");
+            st.vol_returns.push(symbol, last_price, self.cfg.vol_window);
+        }
+        // 预热期：可选地调用 next()，但不执行订单、不记录净值
+        let in_warmup = i < self.cfg.warmup_bars;
+        if in_warmup && !self.cfg.warmup_call_next {
+            return Ok(());
+        }
+
+        // 顺延的订单：在交易时段内的第一根 bar 开始时按开盘价撮合（缺少开盘价时用收盘价）
+        if !in_warmup && !flags.outside_session && !st.deferred.is_empty() {
+            let open_price = if bar_data.open > 0.0 { bar_data.open } else { last_price };
+            for order in std::mem::take(&mut st.deferred) {
+                self.fill_order(py, strategy, hooks, &order, open_price, bar_data.datetime.as_deref(), st)?;
+            }
+        }
+
+        // 重新构造PyDict给策略（只在需要时），上下文快照一并传入
+        let pos = st.pos.clone();
+        let clock = st.bars_recorded();
+        let open_orders = st.deferred.clone();
+        let (bar_dict, ctx) = RunProfile::conversion(&mut st.profile, || -> PyResult<_> {
+            let bar_dict = PyDict::new_bound(py);
+            if let Some(ref dt) = bar_data.datetime {
+                bar_dict.set_item("datetime", dt)?;
+            }
+            bar_dict.set_item("open", bar_data.open)?;
+            bar_dict.set_item("high", bar_data.high)?;
+            bar_dict.set_item("low", bar_data.low)?;
+            bar_dict.set_item("close", bar_data.close)?;
+            bar_dict.set_item("volume", bar_data.volume)?;
+            bar_fields::set_extra(&bar_dict, &bar_data.extra)?;
+            for (name, values) in indicator_columns {
+                bar_dict.set_item(name, values[i])?;
+            }
+            if let Some(tf) = timeframes {
+                bar_dict.set_item("timeframes", tf.to_py(py)?)?;
+            }
+
+            let ctx = EngineContext::snapshot(&pos, Some(last_price), bar_data.datetime.as_deref(), i)
+                .with_open_orders(&open_orders, clock)
+                .accepting_orders();
+            Ok((bar_dict, Py::new(py, ctx)?))
+        })?;
+
+        // 优先使用 next(bar, ctx)，若失败则回退到 next(bar)；继承 Strategy 且未重写 next 时不调用
+        let action_obj = if hooks.next {
+            RunProfile::strategy_call(&mut st.profile, || {
+                match strategy.call_method1(py, "next", (bar_dict.as_any(), ctx.bind(py))) {
+                    Ok(obj) => Ok(obj),
+                    Err(err) if err.is_instance_of::<PyKeyboardInterrupt>(py) => Err(err),
+                    Err(_) => strategy.call_method1(py, "next", (bar_dict.as_any(),)),
+                }
+            })?
+        } else {
+            py.None()
+        };
+        if in_warmup {
+            return Ok(());
+        }
+
+        self.execute_queued(py, strategy, hooks, &ctx, bar_data, st, flags.outside_session)?;
+        self.execute_action(py, strategy, hooks, action_obj.bind(py), bar_data, st, flags.outside_session)?;
+
+        // 日历调仓：周期末（或自定义日期）在 next() 之后调用 on_rebalance(ctx)
+        if flags.rebalance && hooks.on_rebalance {
+            let ctx = EngineContext::snapshot(&st.pos, Some(last_price), bar_data.datetime.as_deref(), i)
+                .with_open_orders(&st.deferred, clock)
+                .accepting_orders();
+            let ctx = Py::new(py, ctx)?;
+            let rebalance_obj = RunProfile::strategy_call(&mut st.profile, || strategy.call_method1(py, "on_rebalance", (ctx.bind(py),)))?;
+            self.execute_queued(py, strategy, hooks, &ctx, bar_data, st, flags.outside_session)?;
+            self.execute_action(py, strategy, hooks, rebalance_obj.bind(py), bar_data, st, flags.outside_session)?;
+        }
+
+        let equity = st.pos.cash + st.pos.position * last_price;
+        st.equity_curve.push((bar_data.datetime.clone(), equity));
+        if self.cfg.record_positions && st.pos.position != 0.0 {
+            st.positions_history.push(PositionRow {
+                datetime: bar_data.datetime.clone(),
+                symbol: bar_data.symbol.clone().unwrap_or_else(|| "DEFAULT".to_string()),
+                position: st.pos.position,
+                price: last_price,
+            });
+        }
+
+        // 本 bar 的所有成交处理完毕后：on_bar_end(ctx)，交易日最后一根再调用 on_session_end(ctx)
+        if hooks.on_bar_end || flags.session_end {
+            let ctx = EngineContext::snapshot(&st.pos, Some(last_price), bar_data.datetime.as_deref(), i)
+                .with_open_orders(&st.deferred, clock);
+            let ctx = Py::new(py, ctx)?;
+            if hooks.on_bar_end {
+                RunProfile::strategy_call(&mut st.profile, || strategy.call_method1(py, "on_bar_end", (ctx.bind(py),)))?;
+            }
+            if flags.session_end {
+                RunProfile::strategy_call(&mut st.profile, || strategy.call_method1(py, "on_session_end", (ctx.bind(py),)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 解析并执行单资产回测中策略返回的一个动作
+    ///
+    /// `next()` 与 `on_rebalance()` 的返回值共用这套逻辑：解析订单、触发 `on_order`/`on_trade`
+    /// 回调、按当前 bar 收盘价撮合并更新持仓。`outside_session` 为真时按 `session_policy`
+    /// 拒绝订单或暂存到下一个交易时段。
+    #[allow(clippy::too_many_arguments)]
+    fn execute_action(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        hooks: &StrategyHooks,
+        action_obj: &Bound<'_, PyAny>,
+        bar_data: &BarData,
+        st: &mut RunState,
+        outside_session: bool,
+    ) -> PyResult<()> {
+        let default_symbol = bar_data.symbol.as_deref().unwrap_or("DEFAULT");
+        let basis = SizingBasis { equity: st.pos.cash + st.pos.position * bar_data.close, lots: None, kelly: &st.kelly };
+        match self.parse_action_fast(action_obj.as_gil_ref(), &mut st.order_seq, bar_data.close, default_symbol, &basis)? {
+            Some(order) => self.submit_order(py, strategy, hooks, order, bar_data, st, outside_session),
+            None => Ok(()),
+        }
+    }
+
+    /// 执行策略通过 `ctx.buy()` 等方法排队的订单（按调用顺序，每笔订单按执行前的最新持仓换算数量）
+    #[allow(clippy::too_many_arguments)]
+    fn execute_queued(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        hooks: &StrategyHooks,
+        ctx: &Py<EngineContext>,
+        bar_data: &BarData,
+        st: &mut RunState,
+        outside_session: bool,
+    ) -> PyResult<()> {
+        let queued = std::mem::take(&mut ctx.borrow_mut(py).queue.orders);
+        let symbol = bar_data.symbol.as_deref().unwrap_or("DEFAULT");
+        for q in queued {
+            if let Some(order) = Self::queued_order(q, &st.pos, bar_data.close, &mut st.order_seq, symbol) {
+                self.submit_order(py, strategy, hooks, order, bar_data, st, outside_session)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 把排队的订单换算为具体订单；目标仓位与当前持仓一致（或没有有效价格）时返回 `None`
+    fn queued_order(q: QueuedOrder, pos: &PositionState, last_price: f64, order_seq: &mut u64, symbol: &str) -> Option<Order> {
+        // 调整到目标持仓数量所需的市价单
+        let towards = |target: f64| {
+            let delta = target - pos.position;
+            (delta.abs() >= f64::EPSILON)
+                .then(|| (if delta > 0.0 { OrderSide::Buy } else { OrderSide::Sell }, delta.abs(), None))
+        };
+        let (side, size, limit_price) = match q {
+            QueuedOrder::Submit { side, size, limit_price } => (side, size, limit_price),
+            QueuedOrder::TargetPercent(_) if last_price <= 0.0 => return None,
+            QueuedOrder::TargetPercent(pct) => towards(pct * (pos.cash + pos.position * last_price) / last_price)?,
+            QueuedOrder::Close => towards(0.0)?,
+        };
+        let id = *order_seq;
+        *order_seq += 1;
+        let otype = if limit_price.is_some() { OrderType::Limit } else { OrderType::Market };
+        Some(Order { id, side, otype, size, limit_price, status: "submitted", symbol: symbol.to_string(), submitted_bar: 0 })
+    }
+
+    /// 提交单资产订单：触发 `submitted` 回调，交易时段外按 `session_policy` 拒绝或暂存，否则按当前收盘价撮合
+    #[allow(clippy::too_many_arguments)]
+    fn submit_order(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        hooks: &StrategyHooks,
+        mut order: Order,
+        bar_data: &BarData,
+        st: &mut RunState,
+        outside_session: bool,
+    ) -> PyResult<()> {
+        let last_price = bar_data.close;
+        order.submitted_bar = st.bars_recorded();
+        // 订单提交回调
+        if hooks.on_order {
+            let evt = PyDict::new_bound(py);
+            evt.set_item("event", "submitted")?;
+            evt.set_item("order_id", order.id)?;
+            evt.set_item("side", match order.side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" })?;
+            evt.set_item("type", match order.otype { OrderType::Market => "market", OrderType::Limit => "limit" })?;
+            evt.set_item("size", order.size)?;
+            evt.set_item("symbol", &order.symbol)?;
+            if let Some(lp) = order.limit_price { evt.set_item("limit_price", lp)?; }
+            let _ = RunProfile::strategy_call(&mut st.profile, || strategy.call_method1(py, "on_order", (evt.as_any(),)));
+        }
+
+        // 数量无效：拒绝
+        if !(order.size.is_finite() && order.size > 0.0) {
+            self.order_unfilled(py, strategy, hooks, st, "rejected", &order, OrderReason::InvalidSize, Some(last_price))?;
+            return Ok(());
+        }
 
-                // 重新构造PyDict给策略（只在需要时）
-                let bar_dict = PyDict::new_bound(py);
-                if let Some(ref dt) = bar_data.datetime {
-                    bar_dict.set_item("datetime", dt)?;
-                }
-                bar_dict.set_item("open", bar_data.open)?;
-                bar_dict.set_item("high", bar_data.high)?;
-                bar_dict.set_item("low", bar_data.low)?;
-                bar_dict.set_item("close", bar_data.close)?;
-                bar_dict.set_item("volume", bar_data.volume)?;
-
-                // 上下文快照传入策略（优先使用 next(bar, ctx)，若失败则回退到 next(bar)）
-                let equity_snapshot = pos.cash + pos.position * last_price;
-                let ctx = Py::new(py, EngineContext {
-                    position: pos.position,
-                    avg_cost: pos.avg_cost,
-                    cash: pos.cash,
-                    equity: equity_snapshot,
-                    bar_index: i,
-                })?;
-                let action_obj = match strategy.call_method1(py, "next", (bar_dict.as_any(), ctx.as_ref(py))) {
-                    Ok(obj) => obj,
-                    Err(_) => strategy.call_method1(py, "next", (bar_dict.as_any(),))?,
-                };
+        // 波动率目标：按缩放后的目标持仓调整数量
+        if let Some(vt) = &st.vol_target {
+            let symbol = bar_data.symbol.as_deref().unwrap_or("DEFAULT");
+            let equity = st.pos.cash + st.pos.position * last_price;
+            let size = vt.adjust_order(&st.vol_returns, order.side, order.size, symbol, last_price, st.pos.position, equity, &[]);
+            if size <= 0.0 {
+                self.order_unfilled(py, strategy, hooks, st, "rejected", &order, OrderReason::VolTarget, Some(last_price))?;
+                return Ok(());
+            }
+            order.size = size;
+        }
 
-                // 快速订单处理
-                let default_symbol = bar_data.symbol.as_deref().unwrap_or("DEFAULT");
-                if let Some(order) = self.parse_action_fast(action_obj.as_ref(py), &mut order_seq, last_price, default_symbol)? {
-                    // 订单提交回调
-                    let evt = PyDict::new_bound(py);
-                    evt.set_item("event", "submitted")?;
-                    evt.set_item("order_id", order.id)?;
-                    evt.set_item("side", match order.side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" })?;
-                    evt.set_item("type", match order.otype { OrderType::Market => "market", OrderType::Limit => "limit" })?;
-                    evt.set_item("size", order.size)?;
-                    evt.set_item("symbol", &order.symbol)?;
-                    if let Some(lp) = order.limit_price { evt.set_item("limit_price", lp)?; }
-                    let _ = strategy.call_method1(py, "on_order", (evt.as_any(),));
-
-                    if let Some((fill_price, fill_size)) = self.try_match(&order, last_price) {
-                        let slip = self.cfg.slippage_bps / 10_000.0;
-                        let sign = match order.side { OrderSide::Buy => 1.0, OrderSide::Sell => -1.0 };
-                        let exec_price = fill_price * (1.0 + sign * slip);
-                        let commission = exec_price * fill_size * self.cfg.commission_rate;
-
-                        // 快速持仓更新
-                        self.update_position(&mut pos, &order, exec_price, fill_size, commission);
-                        trades.push((order.id, match order.side { OrderSide::Buy => "BUY".to_string(), OrderSide::Sell => "SELL".to_string() }, exec_price, fill_size));
-
-                        // 成交回调
-                        let trade_evt = PyDict::new_bound(py);
-                        trade_evt.set_item("order_id", order.id)?;
-                        trade_evt.set_item("side", match order.side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" })?;
-                        trade_evt.set_item("price", exec_price)?;
-                        trade_evt.set_item("size", fill_size)?;
-                        trade_evt.set_item("symbol", &order.symbol)?;
-                        let _ = strategy.call_method1(py, "on_trade", (trade_evt.as_any(),));
+        // 交易时段外：拒绝或暂存
+        if outside_session {
+            let defer = st.defer_orders;
+            let event = if defer { "deferred" } else { "rejected" };
+            self.order_unfilled(py, strategy, hooks, st, event, &order, OrderReason::OutsideSession, Some(last_price))?;
+            if defer {
+                st.deferred.push(order);
+            }
+            return Ok(());
+        }
+        self.fill_order(py, strategy, hooks, &order, last_price, bar_data.datetime.as_deref(), st)
+    }
 
-                        // 订单完成回调
-                        let evt2 = PyDict::new_bound(py);
-                        evt2.set_item("event", "filled")?;
-                        evt2.set_item("order_id", order.id)?;
-                        let _ = strategy.call_method1(py, "on_order", (evt2.as_any(),));
+    /// 按给定价格撮合单资产订单：检查敞口限制，更新持仓、记录成交并触发 `on_trade` 与 `filled` 回调
+    #[allow(clippy::too_many_arguments)]
+    fn fill_order(&self, py: Python<'_>, strategy: &PyObject, hooks: &StrategyHooks, order: &Order, last_price: f64, datetime: Option<&str>, st: &mut RunState) -> PyResult<()> {
+        if let Some((fill_price, mut fill_size)) = self.try_match(order, last_price) {
+            // 组合敞口限制：超限时截断或拒绝
+            let limits = st.exposure_limits;
+            if limits.is_active() {
+                let book = Book {
+                    equity: st.pos.cash + st.pos.position * last_price,
+                    position: st.pos.position,
+                    other_gross: 0.0,
+                    other_net: 0.0,
+                };
+                match limits.check(order.side, fill_size, last_price, &book) {
+                    ExposureCheck::Pass => {}
+                    ExposureCheck::Clip(breach) => {
+                        self.order_unfilled(py, strategy, hooks, st, "clipped", order, OrderReason::ExposureLimit(breach), Some(last_price))?;
+                        fill_size = breach.allowed;
+                    }
+                    ExposureCheck::Reject(breach) => {
+                        self.order_unfilled(py, strategy, hooks, st, "rejected", order, OrderReason::ExposureLimit(breach), Some(last_price))?;
+                        return Ok(());
                     }
                 }
+            }
+            let slip = self.cfg.slippage_bps / 10_000.0;
+            let sign = match order.side { OrderSide::Buy => 1.0, OrderSide::Sell => -1.0 };
+            let exec_price = fill_price * (1.0 + sign * slip);
+            let commission = exec_price * fill_size * self.cfg.commission_rate;
 
-                let equity = pos.cash + pos.position * last_price;
-                equity_curve.push((bar_data.datetime.clone(), equity));
+            // 平仓成交计入 Kelly 胜率与盈亏比
+            if order.side == OrderSide::Sell && st.pos.position > 0.0 {
+                st.kelly.record(exec_price, st.pos.avg_cost);
             }
-        }
+            // 快速持仓更新
+            self.update_position(&mut st.pos, order, exec_price, fill_size, commission);
+            let trade = TradeRecord::new(order, fill_price, exec_price, fill_size, commission, datetime, st.pos.position, st.pos.cash);
 
-        let _ = strategy.call_method0(py, "on_stop");
+            // 成交回调
+            if hooks.on_trade {
+                let trade_evt = trade.to_pydict(py)?;
+                let _ = RunProfile::strategy_call(&mut st.profile, || strategy.call_method1(py, "on_trade", (trade_evt.as_any(),)));
+            }
+            st.trades.push(trade);
 
-        // 构建结果（优化版）
-        self.build_result(py, pos, equity_curve, trades)
+            // 订单完成回调
+            if hooks.on_order {
+                let evt2 = PyDict::new_bound(py);
+                evt2.set_item("event", "filled")?;
+                evt2.set_item("order_id", order.id)?;
+                let _ = RunProfile::strategy_call(&mut st.profile, || strategy.call_method1(py, "on_order", (evt2.as_any(),)));
+            }
+        } else {
+            // 限价单未触及限价：随本 bar 失效
+            self.order_unfilled(py, strategy, hooks, st, "expired", order, OrderReason::LimitNotReached, Some(last_price))?;
+        }
+        Ok(())
     }
 
-    /// 执行多资产/多周期回测
-    ///
-    /// 这个方法支持同时回测多个资产或多个时间周期，就像同时观察多个"时间机器"的运行。
-    /// 它会将所有数据源的时间线合并，按照统一的时间顺序推进，让策略能够同时看到多个资产的状态。
-    ///
-    /// ## 为什么需要这个方法？
-    ///
-    /// 在实际交易中，我们经常需要：
-    /// - 同时交易多个股票（多资产策略）
-    /// - 使用不同周期的数据（如日线和分钟线结合）
-    /// - 进行资产配置和再平衡
-    ///
-    /// 单资产回测无法满足这些需求，因此需要多资产回测功能。
-    ///
-    /// ## 工作原理（简单理解）
-    ///
-    /// 想象你在同时观察多个股票的走势图：
-    ///
-    /// 1. **准备多个数据源**：每个数据源（feed）包含一个资产或周期的 K 线数据
-    /// 2. **合并时间线**：找到所有数据源中最早的时间点，作为当前处理时间
-    /// 3. **构造更新切片**：将所有在当前时间有更新的数据源打包成一个字典
-    /// 4. **调用策略**：策略可以看到所有资产的当前状态，做出交易决策
-    /// 5. **执行订单**：订单可以指定不同的 symbol，分别更新对应资产的持仓
-    /// 6. **计算组合净值**：汇总所有资产的持仓市值和现金，得到组合总净值
-    ///
-    /// ## 实际使用场景
-    ///
-    /// 适用于多资产组合策略、资产配置、再平衡等场景：
-    ///
-    /// ```python
-    /// # 准备多个资产的数据
-    /// feeds = {
-    ///     "AAPL": aapl_bars,      # 苹果股票
-    ///     "GOOGL": googl_bars,     # 谷歌股票
-    ///     "SPY": spy_bars,         # 标普500 ETF
-    /// }
-    ///
-    /// # 实现多资产策略
-    /// class MultiAssetStrategy(Strategy):
-    ///     def next_multi(self, update_slice, ctx):
-    ///         # update_slice 包含所有在当前时间有更新的资产
-    ///         # ctx.positions 包含所有资产的持仓信息
-    ///         # ctx.last_prices 包含所有资产的最新价格
-    ///
-    ///         # 等权重配置策略
-    ///         target_weight = 1.0 / len(ctx.positions)
-    ///         orders = []
-    ///
-    ///         for symbol in ["AAPL", "GOOGL", "SPY"]:
-    ///             if symbol in update_slice:
-    ///                 current_price = ctx.last_prices.get(symbol, 0)
-    ///                 current_pos = ctx.positions.get(symbol, {}).get("position", 0)
-    ///                 target_value = ctx.equity * target_weight
-    ///                 target_pos = target_value / current_price if current_price > 0 else 0
-    ///
-    ///                 if target_pos > current_pos:
-    ///                     orders.append({
-    ///                         "action": "BUY",
-    ///                         "type": "market",
-    ///                         "size": target_pos - current_pos,
-    ///                         "symbol": symbol
-    ///                     })
-    ///
-    ///         return orders if orders else None
-    ///
-    /// # 运行多资产回测
-    /// result = engine.run_multi(MultiAssetStrategy(), feeds)
-    /// ```
-    ///
-    /// ## 策略接口
-    ///
-    /// 策略可以实现两种方法：
-    /// - `next_multi(update_slice, ctx)`: 多资产专用方法（推荐）
-    /// - `next(bar, ctx)`: 单资产方法（回退，使用第一个 feed 的数据）
-    ///
-    /// `update_slice` 是一个字典：`{feed_id: bar_dict}`，包含所有在当前时间有更新的资产。
-    /// `ctx` 包含组合级别的信息：`positions`（各资产持仓）、`last_prices`（最新价格映射）、`equity`（组合净值）。
-    ///
-    /// ## 可能遇到的问题
-    ///
-    /// ### 时间对齐
-    ///
-    /// 不同资产的数据可能时间不完全一致（如不同交易所的交易时间）。
-    /// 引擎会按照联合时间线推进，如果某个资产在某个时间点没有数据，则不会出现在 `update_slice` 中。
-    ///
-    /// ### 订单格式
-    ///
-    /// 多资产回测的订单必须包含 `symbol` 字段，指定交易哪个资产。
-    /// 可以返回单个订单或订单列表。
-    ///
-    /// # 参数
-    ///
-    /// - `strategy`: Python 策略对象，建议实现 `next_multi()` 方法
-    /// - `feeds`: 数据源字典，格式为 `{feed_id: list[bar]}`，每个 bar 至少包含 `datetime` 和 `close`
-    ///
-    /// # 返回值
-    ///
-    /// 返回格式与 `run()` 相同，但 `position` 和 `avg_cost` 为 0（多资产场景使用 `ctx.positions` 获取详细持仓）。
-    ///
-    /// # 示例
-    ///
-    /// ```python
-    /// feeds = {"AAPL": aapl_bars, "GOOGL": googl_bars}
-    /// result = engine.run_multi(MyStrategy(), feeds)
-    /// ```
-    fn run_multi<'py>(&self, py: Python<'py>, strategy: PyObject, feeds: &'py PyAny) -> PyResult<PyObject> {
-        self._run_multi_impl(py, strategy, feeds)
+    /// 触发未成交订单的 `on_order` 事件（带 `reason` 与 `message`）
+    #[allow(clippy::too_many_arguments)]
+    fn order_unfilled(
+        &self,
+        py: Python<'_>,
+        strategy: &PyObject,
+        hooks: &StrategyHooks,
+        st: &mut RunState,
+        event: &str,
+        order: &Order,
+        reason: OrderReason,
+        price: Option<f64>,
+    ) -> PyResult<()> {
+        if hooks.on_order {
+            let evt = order_events::reason_event(py, event, order, reason, price)?;
+            let _ = RunProfile::strategy_call(&mut st.profile, || strategy.call_method1(py, "on_order", (evt.as_any(),)));
+        }
+        Ok(())
+    }
+
+    /// 回测结束：仍在等待的顺延订单全部取消（`event="cancelled"`、`reason="run_ended"`）
+    fn cancel_deferred(&self, py: Python<'_>, strategy: &PyObject, hooks: &StrategyHooks, st: &mut RunState) -> PyResult<()> {
+        for order in std::mem::take(&mut st.deferred) {
+            self.order_unfilled(py, strategy, hooks, st, "cancelled", &order, OrderReason::RunEnded, None)?;
+        }
+        Ok(())
     }
-}
 
-impl BacktestEngine {
     /// 快速解析策略返回的订单动作
     ///
     /// 将策略返回的动作（字符串或字典）解析为内部订单结构。
@@ -942,6 +3259,7 @@ impl BacktestEngine {
     /// - `order_seq`: 订单序列号（可变引用，会自动递增）
     /// - `last_price`: 当前价格（用于限价单的默认价格）
     /// - `default_symbol`: 默认交易标的（如果动作中未指定）
+    /// - `basis`: 下单时的净值等（订单字典给出 `sizing` 时用于换算数量）
     ///
     /// # 返回值
     ///
@@ -953,6 +3271,7 @@ impl BacktestEngine {
         order_seq: &mut u64,
         last_price: f64,
         default_symbol: &str,
+        basis: &SizingBasis<'_>,
     ) -> PyResult<Option<Order>> {
         // 快速路径：尝试解析为字符串（"BUY" 或 "SELL"）
         // 这是最常见的简单订单格式，优先处理以提升性能
@@ -962,7 +3281,7 @@ impl BacktestEngine {
                 let side = if act.as_bytes()[0] == b'B' { OrderSide::Buy } else { OrderSide::Sell };
                 let id = *order_seq; *order_seq += 1;
                 // 字符串格式默认为市价单，数量为 1.0
-                return Ok(Some(Order { id, side, otype: OrderType::Market, size: 1.0, limit_price: None, status: "submitted", symbol: default_symbol.to_string() }));
+                return Ok(Some(Order { id, side, otype: OrderType::Market, size: 1.0, limit_price: None, status: "submitted", symbol: default_symbol.to_string(), submitted_bar: 0 }));
             }
         }
 
@@ -985,9 +3304,17 @@ impl BacktestEngine {
             let symbol = d.get_item("symbol")?.and_then(|v| v.extract::<String>().ok()).unwrap_or_else(|| default_symbol.to_string());
             
             let id = *order_seq; *order_seq += 1;
-            // 限价单：如果未指定价格，使用当前价格作为限价
-            let limit_price = if otype == OrderType::Limit { price.or(Some(last_price)) } else { None };
-            return Ok(Some(Order { id, side, otype, size, limit_price, status: "submitted", symbol }));
+            // 限价单：如果未指定价格，使用当前价格作为限价；给出 tick 时按最小变动价位取整
+            let mut limit_price = if otype == OrderType::Limit { price.or(Some(last_price)) } else { None };
+            if let (Some(lp), Some(tick)) = (limit_price, sizing::tick_size(d)?) {
+                limit_price = Some(sizing::round_to_tick(lp, tick, side));
+            }
+            // 仓位意图（sizing）：按净值与价格换算数量
+            let size = match OrderSizing::parse(d)? {
+                Some(sizing) => sizing.size(&symbol, limit_price.unwrap_or(last_price), basis),
+                None => size,
+            };
+            return Ok(Some(Order { id, side, otype, size, limit_price, status: "submitted", symbol, submitted_bar: 0 }));
         }
 
         // 无法解析：返回 None（策略返回 None 或无效格式）
@@ -1005,6 +3332,7 @@ impl BacktestEngine {
     /// - `order_seq`: 订单序列号（可变引用）
     /// - `last_price_map`: 各资产的最新价格映射
     /// - `default_symbol`: 默认交易标的
+    /// - `basis`: 组合净值与每手数量（用于 `sizing` 换算）
     ///
     /// # 返回值
     ///
@@ -1016,6 +3344,7 @@ impl BacktestEngine {
         order_seq: &mut u64,
         last_price_map: &HashMap<String, f64>,
         default_symbol: &str,
+        basis: &SizingBasis<'_>,
     ) -> PyResult<Vec<Order>> {
         // 尝试解析为列表格式（多订单）
         if let Ok(seq) = action_obj.downcast::<pyo3::types::PyList>() {
@@ -1031,13 +3360,19 @@ impl BacktestEngine {
                 // 获取该资产的最新价格，如果不存在则使用 0.0
                 let lp = *last_price_map.get(&sym).unwrap_or(&0.0);
                 // 解析单个订单动作
-                if let Some(o) = self.parse_action_fast(item, order_seq, lp, &sym)? { out.push(o); }
+                if let Some(o) = self.parse_action_fast(item, order_seq, lp, &sym, basis)? { out.push(o); }
             }
             return Ok(out);
         }
-        // 单个订单：解析后包装成列表
-        let lp = *last_price_map.get(default_symbol).unwrap_or(&0.0);
-        if let Some(o) = self.parse_action_fast(action_obj, order_seq, lp, default_symbol)? { return Ok(vec![o]); }
+        // 单个订单：解析后包装成列表（同样按订单的 symbol 取最新价格）
+        let sym = action_obj
+            .downcast::<PyDict>()
+            .ok()
+            .and_then(|d| d.get_item("symbol").ok().flatten())
+            .and_then(|v| v.extract::<String>().ok())
+            .unwrap_or_else(|| default_symbol.to_string());
+        let lp = *last_price_map.get(&sym).unwrap_or(&0.0);
+        if let Some(o) = self.parse_action_fast(action_obj, order_seq, lp, default_symbol, basis)? { return Ok(vec![o]); }
         // 无法解析：返回空列表
         Ok(Vec::new())
     }
@@ -1133,48 +3468,86 @@ impl BacktestEngine {
         }
     }
 
-    fn build_result<'py>(&self, py: Python<'py>, pos: PositionState, equity_curve: Vec<(Option<String>, f64)>, trades: Vec<(u64, String, f64, f64)>) -> PyResult<PyObject> {
-        let result = PyDict::new_bound(py);
+    fn build_result<'py>(&self, py: Python<'py>, pos: PositionState, equity_curve: Vec<(Option<String>, f64)>, trades: Vec<TradeRecord>) -> PyResult<PyObject> {
+        self.build_result_with(py, pos, equity_curve, trades, true)
+    }
+
+    /// 构建结果字典；`include_lists` 为 `false` 时（结果已写入数据库）不构建 `equity_curve` 和 `trades` 列表
+    fn build_result_with<'py>(&self, py: Python<'py>, pos: PositionState, equity_curve: Vec<(Option<String>, f64)>, trades: Vec<TradeRecord>, include_lists: bool) -> PyResult<PyObject> {
+        // 增强的统计分析
+        let stats = Self::compute_enhanced_stats(py, &equity_curve, &trades, self.cfg.periods_per_year())?;
+        self.result_dict(py, pos, equity_curve, trades, include_lists, stats)
+    }
+
+    /// 用已计算好的 `stats` 构建结果字典（`include_lists` 同 `build_result_with`）
+    fn result_dict(&self, py: Python<'_>, pos: PositionState, equity_curve: Vec<(Option<String>, f64)>, trades: Vec<TradeRecord>, include_lists: bool, stats: PyObject) -> PyResult<PyObject> {
+        // 结果对象同时保留 Rust 侧数据，供 equity_curve_df() / trades_df() 按列构建表格
+        let result = if include_lists {
+            frames::new_result(py, equity_curve.clone(), trades.clone())?
+        } else {
+            frames::new_result(py, Vec::new(), Vec::new())?
+        };
         result.set_item("cash", pos.cash)?;
         result.set_item("position", pos.position)?;
         result.set_item("avg_cost", pos.avg_cost)?;
-        result.set_item("equity", pos.cash + pos.position * equity_curve.last().map_or(0.0, |(_, eq)| *eq))?;
+        result.set_item("equity", equity_curve.last().map_or(pos.cash, |(_, eq)| *eq))?;
         result.set_item("realized_pnl", pos.realized_pnl)?;
 
-        // 高效构建净值曲线
-        let eq_list = PyList::empty_bound(py);
-        for (dt, eq) in &equity_curve {
-            let row = PyDict::new_bound(py);
-            if let Some(d) = dt { row.set_item("datetime", d)?; } else { row.set_item("datetime", py.None())?; }
-            row.set_item("equity", eq)?;
-            eq_list.append(row)?;
-        }
-        result.set_item("equity_curve", eq_list)?;
+        if include_lists {
+            // 高效构建净值曲线
+            let eq_list = PyList::empty_bound(py);
+            for (dt, eq) in &equity_curve {
+                let row = PyDict::new_bound(py);
+                if let Some(d) = dt { row.set_item("datetime", d)?; } else { row.set_item("datetime", py.None())?; }
+                row.set_item("equity", eq)?;
+                eq_list.append(row)?;
+            }
+            result.set_item("equity_curve", eq_list)?;
 
-        // 高效构建交易列表
-        let tr_list = PyList::empty_bound(py);
-        for (oid, side, price, size) in &trades {
-            let t = PyDict::new_bound(py);
-            t.set_item("order_id", oid)?;
-            t.set_item("side", side)?;
-            t.set_item("price", price)?;
-            t.set_item("size", size)?;
-            tr_list.append(t)?;
+            // 高效构建交易列表
+            let tr_list = PyList::empty_bound(py);
+            for trade in &trades {
+                tr_list.append(trade.to_pydict(py)?)?;
+            }
+            result.set_item("trades", tr_list)?;
         }
-        result.set_item("trades", tr_list)?;
-
-        // 增强的统计分析
-        let stats = self.compute_enhanced_stats(py, &equity_curve, &trades)?;
         result.set_item("stats", stats)?;
 
         Ok(result.into())
     }
 
-    fn compute_enhanced_stats<'py>(&self, py: Python<'py>, equity_curve: &[(Option<String>, f64)], trades: &[(u64, String, f64, f64)]) -> PyResult<PyObject> {
+    /// 计算统计指标；`periods_per_year` 为年化因子（每年的 bar 周期数，日线即每年交易日数）
+    pub(crate) fn compute_enhanced_stats<'py>(py: Python<'py>, equity_curve: &[(Option<String>, f64)], trades: &[TradeRecord], periods_per_year: f64) -> PyResult<PyObject> {
+        EnhancedStats::to_py(py, EnhancedStats::compute(equity_curve, trades, periods_per_year).as_ref())
+    }
+}
+
+/// 统计指标（纯 Rust 计算，不需要 GIL；转换为 Python 字典见 `to_py`）
+#[derive(Clone, Debug)]
+pub(crate) struct EnhancedStats {
+    start_equity: f64,
+    end_equity: f64,
+    total_return: f64,
+    annualized_return: f64,
+    volatility: f64,
+    sharpe: f64,
+    calmar: f64,
+    max_drawdown: f64,
+    max_dd_duration: usize,
+    total_trades: usize,
+    winning_trades: usize,
+    losing_trades: usize,
+    win_rate: f64,
+    total_pnl: f64,
+}
+
+impl EnhancedStats {
+    /// 计算统计指标；`periods_per_year` 为年化因子，净值曲线为空时没有指标
+    pub(crate) fn compute(equity_curve: &[(Option<String>, f64)], trades: &[TradeRecord], periods_per_year: f64) -> Option<Self> {
         if equity_curve.is_empty() {
-            return Ok(PyDict::new_bound(py).into());
+            return None;
         }
-        
+
         // 基础统计：起始和结束净值
         let start_equity = equity_curve.first().unwrap().1;
         let end_equity = equity_curve.last().unwrap().1;
@@ -1201,9 +3574,9 @@ impl BacktestEngine {
         } else { 0.0 };
         // 标准差 = 方差的平方根
         let std = var.sqrt();
-        // 夏普比率 = (平均收益率 × √252) / 标准差
-        // 252 是年化因子（假设一年 252 个交易日）
-        let sharpe = if std > 0.0 { (mean_return * 252.0_f64.sqrt()) / std } else { 0.0 };
+        // 夏普比率 = (平均收益率 × √N) / 标准差
+        // N 是年化因子（默认一年 252 个交易日，配置交易日历时取日历的年化天数）
+        let sharpe = if std > 0.0 { (mean_return * periods_per_year.sqrt()) / std } else { 0.0 };
 
         // 高效最大回撤计算：单次遍历，O(n) 时间复杂度
         // 回撤 = (峰值 - 当前值) / 峰值
@@ -1241,9 +3614,9 @@ impl BacktestEngine {
             // 简化计算：比较相邻两次交易的价格差
             // 注意：这是简化模型，实际应该按订单配对计算
             for i in 0..trades.len() {
-                let (_, side, price, size) = &trades[i];
+                let TradeRecord { side, price, size, .. } = &trades[i];
                 if i > 0 {
-                    let prev_price = trades[i-1].2;
+                    let prev_price = trades[i-1].price;
                     // 计算本次交易的盈亏（简化：买入看涨，卖出看跌）
                     let profit = if side == "BUY" { (price - prev_price) * size } else { (prev_price - price) * size };
                     pnl += profit;
@@ -1254,29 +3627,98 @@ impl BacktestEngine {
         };
 
         let win_rate = if total_trades > 0 { winning_trades as f64 / total_trades as f64 } else { 0.0 };
-        let calmar = if max_dd > 0.0 { (mean_return * 252.0) / max_dd } else { 0.0 };
-
-        let stats = PyDict::new_bound(py);
-        stats.set_item("start_equity", start_equity)?;
-        stats.set_item("end_equity", end_equity)?;
-        stats.set_item("total_return", total_return)?;
-        stats.set_item("annualized_return", mean_return * 252.0)?;
-        stats.set_item("volatility", std * (252.0_f64.sqrt()))?;
-        stats.set_item("sharpe", sharpe)?;
-        stats.set_item("calmar", calmar)?;
-        stats.set_item("max_drawdown", max_dd)?;
-        stats.set_item("max_dd_duration", max_dd_duration)?;
-        stats.set_item("total_trades", total_trades)?;
-        stats.set_item("winning_trades", winning_trades)?;
-        stats.set_item("losing_trades", losing_trades)?;
-        stats.set_item("win_rate", win_rate)?;
-        stats.set_item("total_pnl", total_pnl)?;
-        
-        Ok(stats.into())
+        let calmar = if max_dd > 0.0 { (mean_return * periods_per_year) / max_dd } else { 0.0 };
+
+        Some(Self {
+            start_equity,
+            end_equity,
+            total_return,
+            annualized_return: mean_return * periods_per_year,
+            volatility: std * periods_per_year.sqrt(),
+            sharpe,
+            calmar,
+            max_drawdown: max_dd,
+            max_dd_duration,
+            total_trades,
+            winning_trades,
+            losing_trades,
+            win_rate,
+            total_pnl,
+        })
+    }
+
+    /// 转换为结果中的 `stats` 字典（没有指标时为空字典）
+    pub(crate) fn to_py(py: Python<'_>, stats: Option<&Self>) -> PyResult<PyObject> {
+        let out = PyDict::new_bound(py);
+        let Some(stats) = stats else { return Ok(out.into()) };
+        out.set_item("start_equity", stats.start_equity)?;
+        out.set_item("end_equity", stats.end_equity)?;
+        out.set_item("total_return", stats.total_return)?;
+        out.set_item("annualized_return", stats.annualized_return)?;
+        out.set_item("volatility", stats.volatility)?;
+        out.set_item("sharpe", stats.sharpe)?;
+        out.set_item("calmar", stats.calmar)?;
+        out.set_item("max_drawdown", stats.max_drawdown)?;
+        out.set_item("max_dd_duration", stats.max_dd_duration)?;
+        out.set_item("total_trades", stats.total_trades)?;
+        out.set_item("winning_trades", stats.winning_trades)?;
+        out.set_item("losing_trades", stats.losing_trades)?;
+        out.set_item("win_rate", stats.win_rate)?;
+        out.set_item("total_pnl", stats.total_pnl)?;
+        Ok(out.into())
     }
 }
 
 impl BacktestEngine {
+    /// 所有 feed 中下一根未处理 bar 的最小时间戳（epoch 毫秒）及其所在 feed（全部处理完时为 `None`）
+    fn next_min_timestamp(feed_ts: &[Vec<i64>], idxs: &[usize]) -> Option<(i64, usize)> {
+        let mut min: Option<(i64, usize)> = None;
+        for (f, (ts, &idx)) in feed_ts.iter().zip(idxs).enumerate() {
+            if let Some(&t) = ts.get(idx) {
+                if min.is_none_or(|(cur, _)| t < cur) {
+                    min = Some((t, f));
+                }
+            }
+        }
+        min
+    }
+
+    /// 构造多资产回测的策略上下文（Python dict）
+    ///
+    /// 包含 `positions`（逐 symbol 的持仓与成本）、`cash`、`equity`（按最新价估值）、
+    /// `bar_index` 和 `last_prices`。
+    fn multi_ctx<'py>(
+        py: Python<'py>,
+        positions: &HashMap<String, (f64, f64)>,
+        cash: f64,
+        last_price_map: &HashMap<String, f64>,
+        step: usize,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let ctx = PyDict::new_bound(py);
+        let pos_dict = PyDict::new_bound(py);
+        for (sym, (p, ac)) in positions.iter() {
+            let pd = PyDict::new_bound(py);
+            pd.set_item("position", *p)?;
+            pd.set_item("avg_cost", *ac)?;
+            pos_dict.set_item(sym, pd)?;
+        }
+        // 汇总净值
+        let mut equity: f64 = cash;
+        for (sym, (p, _)) in positions.iter() {
+            if let Some(lp) = last_price_map.get(sym) { equity += p * lp; }
+        }
+        ctx.set_item("positions", pos_dict)?;
+        ctx.set_item("cash", cash)?;
+        ctx.set_item("equity", equity)?;
+        ctx.set_item("bar_index", step)?;
+        ctx.set_item("last_prices", {
+            let lp = PyDict::new_bound(py);
+            for (k, v) in last_price_map.iter() { lp.set_item(k, v)?; }
+            lp
+        })?;
+        Ok(ctx)
+    }
+
     /// 多资产/多周期回测的核心实现
     ///
     /// 这是 `run_multi()` 的内部实现，负责联合时间线推进、多资产持仓管理、组合净值计算等核心逻辑。
@@ -1316,6 +3758,9 @@ impl BacktestEngine {
     /// 方法会优先调用 `next_multi(update_slice, ctx)`，如果策略没有实现，则回退到 `next(bar, ctx)`。
     /// 回退时会使用第一个 feed 的最新快照作为主 bar。
     ///
+    /// 调仓点由联合时间线决定：当前时间与下一个时间点（所有 feed 中最早的下一根 bar）
+    /// 落在不同周期时视为周期末，此时在 `next_multi()` 之后调用 `on_rebalance(ctx)`，两者返回的订单一并执行。
+    ///
     /// ## 性能考虑
     ///
     /// - 预提取所有 feed 的数据，减少 Python 调用
@@ -1326,37 +3771,95 @@ impl BacktestEngine {
     ///
     /// - `strategy`: Python 策略对象
     /// - `feeds`: 数据源字典，格式为 `{feed_id: list[bar]}`
+    /// - `progress_callback` / `progress_every`: 可选的进度回调及汇报间隔（见 `progress` 模块）
+    /// - `cancel_token`: 可选的取消令牌；未传入时仍响应 Ctrl+C（见 `cancel` 模块）
     ///
     /// # 返回值
     ///
-    /// 返回格式与 `run()` 相同，但 `position` 和 `avg_cost` 为 0。
+    /// 返回格式与 `run()` 相同，但 `position` 和 `avg_cost` 为 0，`equity_curve` 的每一行另有该时间点的 `cash`。
     /// 详细的各资产持仓信息可以通过策略的 `on_trade` 回调或上下文中的 `positions` 获取。
-    fn _run_multi_impl<'py>(&self, py: Python<'py>, strategy: PyObject, feeds: &'py PyAny) -> PyResult<PyObject> {
-        let feeds_dict: &PyDict = feeds.downcast()?;
+    #[allow(clippy::too_many_arguments)]
+    fn _run_multi_impl<'py>(
+        &self,
+        py: Python<'py>,
+        strategy: PyObject,
+        feeds: &Bound<'py, PyAny>,
+        progress_callback: Option<PyObject>,
+        progress_every: usize,
+        cancel_token: Option<CancelToken>,
+        sizer: WeightSizer,
+    ) -> PyResult<PyObject> {
+        let cancel_token = cancel_token.unwrap_or_default();
+        let feeds_dict = feeds.downcast::<PyDict>()?;
         // 预提取每个 feed 的数据
         let mut feed_ids: Vec<String> = Vec::with_capacity(feeds_dict.len());
         let mut feed_bars: Vec<Vec<BarData>> = Vec::with_capacity(feeds_dict.len());
+        let window = self.cfg.date_window()?;
         for (k, v) in feeds_dict.iter() {
             let fid: String = k.extract()?;
-            let blist: &PyList = v.downcast()?;
-            let bars_vec = extract_bars_data(blist)?;
+            let bars_vec = extract_bars_any(v.as_gil_ref())?;
+            if self.cfg.strict {
+                BarValidator::for_feed(&fid).check_all(&bars_vec)?;
+            }
+            // 各 feed 分别截取回测区间；单个 feed 在区间内没有数据时保留为空
+            let bars_vec = match window.select(&bars_vec) {
+                Some(keep) => date_window::select_values(&bars_vec, &keep),
+                None => bars_vec,
+            };
             feed_ids.push(fid);
             feed_bars.push(bars_vec);
         }
+        // 权重列表按位置对应的标的：feed 中 bar 的 symbol，没有时用 feed 名称
+        let feed_symbols: Vec<String> = feed_ids
+            .iter()
+            .zip(&feed_bars)
+            .map(|(fid, bars)| bars.iter().find_map(|b| b.symbol.clone()).unwrap_or_else(|| fid.clone()))
+            .collect();
+        // 每根 bar 的时间只解析一次，联合时间线按整数时间戳推进
+        let feed_ts = feed_ids
+            .iter()
+            .zip(&feed_bars)
+            .map(|(fid, bars)| {
+                bars.iter()
+                    .enumerate()
+                    .map(|(i, b)| {
+                        b.epoch_ms().ok_or_else(|| {
+                            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                                "run_multi requires a parseable datetime on every bar: feed '{}' bar {} has {:?}",
+                                fid, i, b.datetime
+                            ))
+                        })
+                    })
+                    .collect::<PyResult<Vec<i64>>>()
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        if feed_bars.iter().all(Vec::is_empty) && !feeds_dict.is_empty() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "No bars between {} and {} in any feed",
+                self.cfg.start, self.cfg.end
+            )));
+        }
 
         let n_feeds = feed_ids.len();
         let mut idxs: Vec<usize> = vec![0; n_feeds];
+        let total_bars: usize = feed_bars.iter().map(Vec::len).sum();
+        let mut progress = ProgressReporter::new(py, progress_callback, progress_every, total_bars)?;
         let mut last_snapshot: Vec<Option<BarData>> = vec![None; n_feeds];
 
         // 投资组合状态
         let mut cash: f64 = self.cfg.cash;
         let mut realized_pnl: f64 = 0.0;
+        let mut kelly = KellyStats::default();
         let mut positions: HashMap<String, (f64, f64)> = HashMap::new(); // symbol -> (position, avg_cost)
         let mut last_price_map: HashMap<String, f64> = HashMap::new();
 
         // 结果容器
         let mut equity_curve: Vec<(Option<String>, f64)> = Vec::new();
-        let mut trades: Vec<(u64, String, f64, f64)> = Vec::new();
+        // 与净值曲线逐行对应的现金
+        let mut cash_curve: Vec<f64> = Vec::new();
+        // 每个时间点的持仓快照（开启 record_positions 时）
+        let mut positions_history: Vec<PositionRow> = Vec::new();
+        let mut trades: Vec<TradeRecord> = Vec::new();
         let mut order_seq: u64 = 1;
 
         // on_start 传入汇总 ctx（Python dict）
@@ -1365,34 +3868,48 @@ impl BacktestEngine {
         start_ctx.set_item("equity", cash)?;
         start_ctx.set_item("positions", PyDict::new_bound(py))?;
         start_ctx.set_item("bar_index", 0usize)?;
-        let _ = strategy.call_method1(py, "on_start", (start_ctx.as_any(),));
+        let hooks = StrategyHooks::detect(py, &strategy)?;
+        if hooks.on_start {
+            let _ = strategy.call_method1(py, "on_start", (start_ctx.as_any(),));
+        }
+
+        // 调仓日历（联合时间线上逐步判断）
+        let schedule = self.cfg.rebalance_schedule()?;
+        let has_on_rebalance = schedule.is_active() && hooks.on_rebalance;
+        let mut rebalance_cursor: usize = 0;
+
+        // 交易时段：时段外的订单拒绝或暂存到下一个时段
+        let sessions = self.cfg.trading_sessions()?;
+        let defer_orders = self.cfg.defers_orders()?;
+        let mut deferred: Vec<Order> = Vec::new();
+        let exposure_limits = self.cfg.exposure_limits()?;
+        let vol_target = self.cfg.vol_targeting()?;
+        let mut vol_returns = ReturnWindow::default();
 
         let mut step: usize = 0;
-        loop {
+        // 推进联合时间线一步，全部 feed 处理完时返回 false；
+        // 策略回调中抛出的 KeyboardInterrupt 由外层循环转换为取消
+        let mut advance = || -> PyResult<bool> {
             // 找到下一个最小的 datetime
-            let mut min_dt: Option<String> = None;
-            for f in 0..n_feeds {
-                if idxs[f] < feed_bars[f].len() {
-                    if let Some(dt) = &feed_bars[f][idxs[f]].datetime {
-                        match &min_dt {
-                            None => min_dt = Some(dt.clone()),
-                            Some(cur) => { if dt < cur { min_dt = Some(dt.clone()); } }
-                        }
-                    }
-                }
+            if let Some(p) = progress.as_mut() {
+                p.tick(py, idxs.iter().sum())?;
             }
-            if min_dt.is_none() { break; }
-            let cur_dt = min_dt.unwrap();
+            let Some((cur_ts, min_feed)) = Self::next_min_timestamp(&feed_ts, &idxs) else { return Ok(false) };
+            let cur_dt = feed_bars[min_feed][idxs[min_feed]].datetime.clone().unwrap_or_default();
 
             // 本步更新的 bars 切片
             let update_slice = PyDict::new_bound(py);
+            let mut open_price_map: HashMap<String, f64> = HashMap::new();
             for f in 0..n_feeds {
                 if idxs[f] < feed_bars[f].len() {
-                    if feed_bars[f][idxs[f]].datetime.as_ref() == Some(&cur_dt) {
+                    if feed_ts[f][idxs[f]] == cur_ts {
                         let b = &feed_bars[f][idxs[f]];
                         // 更新 last
                         last_snapshot[f] = Some(b.clone());
-                        if let Some(sym) = &b.symbol { last_price_map.insert(sym.clone(), b.close); }
+                        if let Some(sym) = &b.symbol {
+                            last_price_map.insert(sym.clone(), b.close);
+                            if b.open > 0.0 { open_price_map.insert(sym.clone(), b.open); }
+                        }
                         // 构造 bar dict
                         let bd = PyDict::new_bound(py);
                         if let Some(dt) = &b.datetime { bd.set_item("datetime", dt)?; }
@@ -1402,39 +3919,34 @@ impl BacktestEngine {
                         bd.set_item("low", b.low)?;
                         bd.set_item("close", b.close)?;
                         bd.set_item("volume", b.volume)?;
+                        bar_fields::set_extra(&bd, &b.extra)?;
                         update_slice.set_item(&feed_ids[f], bd)?;
                         idxs[f] += 1;
                     }
                 }
             }
 
+            // 波动率目标：记录各标的本时间点的收益率（价格未更新的标的为 0）
+            if let Some(vt) = &vol_target {
+                for (sym, price) in last_price_map.iter() {
+                    vol_returns.push(sym, *price, vt.window());
+                }
+            }
+
             // 构造 ctx：汇总 + 头寸 + last_prices
-            let ctx = PyDict::new_bound(py);
-            let pos_dict = PyDict::new_bound(py);
-            for (sym, (p, ac)) in positions.iter() {
-                let pd = PyDict::new_bound(py);
-                pd.set_item("position", *p)?;
-                pd.set_item("avg_cost", *ac)?;
-                pos_dict.set_item(sym, pd)?;
-            }
-            // 汇总净值
-            let mut equity: f64 = cash;
-            for (sym, (p, _)) in positions.iter() {
-                if let Some(lp) = last_price_map.get(sym) { equity += p * lp; }
-            }
-            ctx.set_item("positions", pos_dict)?;
-            ctx.set_item("cash", cash)?;
-            ctx.set_item("equity", equity)?;
-            ctx.set_item("bar_index", step)?;
-            ctx.set_item("last_prices", {
-                let lp = PyDict::new_bound(py);
-                for (k, v) in last_price_map.iter() { lp.set_item(k, v)?; }
-                lp
-            })?;
+            let ctx = Self::multi_ctx(py, &positions, cash, &last_price_map, step)?;
+
+            // 预热期：可选地调用策略，但不执行订单、不记录净值
+            let in_warmup = step < self.cfg.warmup_bars;
+            if in_warmup && !self.cfg.warmup_call_next {
+                step += 1;
+                return Ok(true);
+            }
 
             // 调用策略：next_multi(update_slice, ctx) 优先
             let action_obj = match strategy.call_method1(py, "next_multi", (update_slice.as_any(), ctx.as_any())) {
                 Ok(obj) => obj,
+                Err(err) if err.is_instance_of::<PyKeyboardInterrupt>(py) => return Err(err),
                 Err(_) => {
                     // 回退：若存在主 bar，则取第一个 feed 的最新快照
                     let primary_bar = if let Some(Some(b)) = last_snapshot.get(0) {
@@ -1446,21 +3958,156 @@ impl BacktestEngine {
                         bd.set_item("low", b.low)?;
                         bd.set_item("close", b.close)?;
                         bd.set_item("volume", b.volume)?;
+                        bar_fields::set_extra(&bd, &b.extra)?;
                         Some(bd)
                     } else { None };
-                    if let Some(pb) = primary_bar { strategy.call_method1(py, "next", (pb.as_any(), ctx.as_any()))? } else { py.None() }
+                    match primary_bar.filter(|_| hooks.next) {
+                        Some(pb) => strategy.call_method1(py, "next", (pb.as_any(), ctx.as_any()))?,
+                        None => py.None(),
+                    }
                 }
             };
+            if in_warmup {
+                step += 1;
+                return Ok(true);
+            }
 
             // 解析并执行指令（支持 list）
-            let default_symbol = if let Some(Some(b)) = last_snapshot.get(0) {
+            let default_symbol = if let Some(Some(b)) = last_snapshot.first() {
                 b.symbol.clone().unwrap_or_else(|| "DEFAULT".to_string())
             } else { "DEFAULT".to_string() };
-            let orders = self.parse_actions_any(py, action_obj.as_ref(py), &mut order_seq, &last_price_map, &default_symbol)?;
-            for order in orders {
-                // 获取该 symbol 的 last_price
-                let lp = *last_price_map.get(&order.symbol).unwrap_or(&0.0);
-                if let Some((fill_price, fill_size)) = self.try_match(&order, lp) {
+            let mut actions = vec![action_obj];
+
+            // 当前与下一个时间点的日期（仅在需要日历判断时解析）
+            let (cur_date, next_date) = if has_on_rebalance || hooks.on_session_end {
+                let next_date = Self::next_min_timestamp(&feed_ts, &idxs)
+                    .and_then(|(ts, _)| database::epoch_ms_to_datetime(ts))
+                    .map(|d| d.date());
+                (database::epoch_ms_to_datetime(cur_ts).map(|d| d.date()), next_date)
+            } else {
+                (None, None)
+            };
+
+            // 日历调仓：联合时间线上的周期末（或自定义日期）在 next_multi() 之后调用 on_rebalance(ctx)
+            if has_on_rebalance && schedule.is_due(cur_date, next_date, &mut rebalance_cursor) {
+                let rebalance_ctx = Self::multi_ctx(py, &positions, cash, &last_price_map, step)?;
+                actions.push(strategy.call_method1(py, "on_rebalance", (rebalance_ctx.as_any(),))?);
+            }
+
+            // 目标权重（同一时间点的多组合并）由引擎换算为订单，其余动作按订单解析
+            let mut orders = Vec::new();
+            let mut weights: Option<Vec<(String, f64)>> = None;
+            let mut equity_now = cash;
+            for (sym, (p, _)) in positions.iter() {
+                if let Some(lp) = last_price_map.get(sym) { equity_now += p * lp; }
+            }
+            let basis = SizingBasis { equity: equity_now, lots: Some(sizer.lots()), kelly: &kelly };
+            for action in &actions {
+                match weights::parse_weights(action.bind(py), &feed_symbols)? {
+                    Some(more) => weights::merge_weights(weights.get_or_insert_with(Vec::new), more),
+                    None => orders.extend(self.parse_actions_any(py, action.bind(py).as_gil_ref(), &mut order_seq, &last_price_map, &default_symbol, &basis)?),
+                }
+            }
+            // 数量不是正数的订单（如 sizing 换算后不足一手）忽略
+            orders.retain(|o| o.size > 0.0);
+
+            // 波动率目标：按缩放后的目标持仓调整订单数量，目标权重整体缩放
+            if let Some(vt) = &vol_target {
+                let weight_of = |sym: &str, p: f64| last_price_map.get(sym).map_or(0.0, |lp| p * lp / equity_now);
+                let mut kept = Vec::with_capacity(orders.len());
+                for mut order in orders {
+                    let others: Vec<(&str, f64)> = positions
+                        .iter()
+                        .filter(|(sym, _)| **sym != order.symbol)
+                        .map(|(sym, (p, _))| (sym.as_str(), weight_of(sym, *p)))
+                        .collect();
+                    let position = positions.get(&order.symbol).map_or(0.0, |(p, _)| *p);
+                    let price = last_price_map.get(&order.symbol).copied().unwrap_or(0.0);
+                    let size = vt.adjust_order(&vol_returns, order.side, order.size, &order.symbol, price, position, equity_now, &others);
+                    if size <= 0.0 {
+                        if hooks.on_order {
+                            order_events::notify(py, &strategy, "rejected", &order, OrderReason::VolTarget, Some(price))?;
+                        }
+                        continue;
+                    }
+                    order.size = size;
+                    kept.push(order);
+                }
+                orders = kept;
+                if let Some(targets) = weights.as_mut() {
+                    // 组合 = 目标权重 + 未列出标的的当前权重
+                    let mut portfolio: Vec<(&str, f64)> = targets.iter().map(|(s, w)| (s.as_str(), *w)).collect();
+                    for (sym, (p, _)) in positions.iter() {
+                        if !targets.iter().any(|(s, _)| s == sym) {
+                            portfolio.push((sym.as_str(), weight_of(sym, *p)));
+                        }
+                    }
+                    if let Some(scale) = vt.scale(&vol_returns, &portfolio) {
+                        for (_, w) in targets.iter_mut() {
+                            *w *= scale;
+                        }
+                    }
+                }
+            }
+            if let Some(targets) = &weights {
+                orders.extend(sizer.orders(targets, cash, &positions, &last_price_map, &mut order_seq));
+            }
+
+            // 交易时段外：拒绝（丢弃）或暂存；回到时段内时，暂存的订单先按开盘价撮合
+            let mut fills: Vec<(Order, f64)> = Vec::with_capacity(orders.len());
+            if sessions.as_ref().is_some_and(|s| s.outside(Some(&cur_dt))) {
+                let event = if defer_orders { "deferred" } else { "rejected" };
+                for order in orders {
+                    if hooks.on_order {
+                        let price = last_price_map.get(&order.symbol).copied();
+                        order_events::notify(py, &strategy, event, &order, OrderReason::OutsideSession, price)?;
+                    }
+                    if defer_orders {
+                        deferred.push(order);
+                    }
+                }
+            } else {
+                for order in deferred.drain(..) {
+                    let price = open_price_map.get(&order.symbol).or_else(|| last_price_map.get(&order.symbol));
+                    let price = price.copied().unwrap_or(0.0);
+                    fills.push((order, price));
+                }
+                for order in orders {
+                    // 获取该 symbol 的 last_price
+                    let lp = *last_price_map.get(&order.symbol).unwrap_or(&0.0);
+                    fills.push((order, lp));
+                }
+            }
+            for (order, lp) in fills {
+                if let Some((fill_price, mut fill_size)) = self.try_match(&order, lp) {
+                    // 组合敞口限制：按本时间点的最新价格估值，前面的成交已计入
+                    if exposure_limits.is_active() {
+                        let mut book = Book { equity: cash, position: 0.0, other_gross: 0.0, other_net: 0.0 };
+                        for (sym, (p, _)) in positions.iter() {
+                            let price = if *sym == order.symbol { lp } else { last_price_map.get(sym).copied().unwrap_or(0.0) };
+                            book.equity += p * price;
+                            if *sym == order.symbol {
+                                book.position = *p;
+                            } else {
+                                book.other_gross += (p * price).abs();
+                                book.other_net += p * price;
+                            }
+                        }
+                        let (event, breach) = match exposure_limits.check(order.side, fill_size, lp, &book) {
+                            ExposureCheck::Pass => ("", None),
+                            ExposureCheck::Clip(breach) => ("clipped", Some(breach)),
+                            ExposureCheck::Reject(breach) => ("rejected", Some(breach)),
+                        };
+                        if let Some(breach) = breach {
+                            if hooks.on_order {
+                                order_events::notify(py, &strategy, event, &order, OrderReason::ExposureLimit(breach), Some(lp))?;
+                            }
+                            if event == "rejected" {
+                                continue;
+                            }
+                            fill_size = breach.allowed;
+                        }
+                    }
                     let slip = self.cfg.slippage_bps / 10_000.0;
                     let sign = match order.side { OrderSide::Buy => 1.0, OrderSide::Sell => -1.0 };
                     let exec_price = fill_price * (1.0 + sign * slip);
@@ -1485,6 +4132,7 @@ impl BacktestEngine {
                             if sp.0 > 0.0 {
                                 let closing = fill_size.min(sp.0);
                                 realized_pnl += (exec_price - sp.1) * closing;
+                                kelly.record(exec_price, sp.1);
                             }
                             sp.0 -= fill_size;
                             if sp.0.abs() < f64::EPSILON { sp.1 = 0.0; }
@@ -1493,14 +4141,15 @@ impl BacktestEngine {
                     }
 
                     // 记录交易与回调
-                    trades.push((order.id, match order.side { OrderSide::Buy => "BUY".to_string(), OrderSide::Sell => "SELL".to_string() }, exec_price, fill_size));
-                    let trade_evt = PyDict::new_bound(py);
-                    trade_evt.set_item("order_id", order.id)?;
-                    trade_evt.set_item("side", match order.side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" })?;
-                    trade_evt.set_item("price", exec_price)?;
-                    trade_evt.set_item("size", fill_size)?;
-                    trade_evt.set_item("symbol", &order.symbol)?;
-                    let _ = strategy.call_method1(py, "on_trade", (trade_evt.as_any(),));
+                    let trade = TradeRecord::new(&order, fill_price, exec_price, fill_size, commission, Some(&cur_dt), sp.0, cash);
+                    if hooks.on_trade {
+                        let trade_evt = trade.to_pydict(py)?;
+                        let _ = strategy.call_method1(py, "on_trade", (trade_evt.as_any(),));
+                    }
+                    trades.push(trade);
+                } else if hooks.on_order {
+                    // 限价单未触及限价：随本时间点失效
+                    order_events::notify(py, &strategy, "expired", &order, OrderReason::LimitNotReached, Some(lp))?;
                 }
             }
 
@@ -1510,45 +4159,128 @@ impl BacktestEngine {
                 if let Some(lp) = last_price_map.get(sym) { equity_step += p * lp; }
             }
             equity_curve.push((Some(cur_dt.clone()), equity_step));
+            cash_curve.push(cash);
+            if self.cfg.record_positions {
+                let mut held: Vec<(&String, f64, f64)> = positions
+                    .iter()
+                    .filter(|(_, (p, _))| *p != 0.0)
+                    .filter_map(|(sym, (p, _))| last_price_map.get(sym).map(|lp| (sym, *p, *lp)))
+                    .collect();
+                held.sort_by(|a, b| a.0.cmp(b.0));
+                positions_history.extend(held.into_iter().map(|(sym, position, price)| PositionRow {
+                    datetime: Some(cur_dt.clone()),
+                    symbol: sym.clone(),
+                    position,
+                    price,
+                }));
+            }
+
+            // 本时间点的所有成交处理完毕后：on_bar_end(ctx)，交易日最后一个时间点再调用 on_session_end(ctx)
+            let session_end = hooks.on_session_end
+                && cur_date.is_some_and(|d| RebalanceFreq::Daily.is_period_end(d, next_date));
+            if hooks.on_bar_end || session_end {
+                let end_ctx = Self::multi_ctx(py, &positions, cash, &last_price_map, step)?;
+                if hooks.on_bar_end {
+                    strategy.call_method1(py, "on_bar_end", (end_ctx.as_any(),))?;
+                }
+                if session_end {
+                    strategy.call_method1(py, "on_session_end", (end_ctx.as_any(),))?;
+                }
+            }
             step += 1;
+            Ok(true)
+        };
+
+        let check_every = self.cfg.batch_size.max(1);
+        let mut cancelled: Option<CancelReason> = None;
+        let mut n_steps: usize = 0;
+        loop {
+            // 每 batch_size 步检查一次 Ctrl+C 与取消令牌
+            if n_steps.is_multiple_of(check_every) {
+                cancelled = cancel::check_cancel(py, &cancel_token)?;
+                if cancelled.is_some() {
+                    break;
+                }
+            }
+            match advance() {
+                Ok(true) => n_steps += 1,
+                Ok(false) => break,
+                Err(err) => {
+                    cancelled = Some(cancel::interrupt_reason(py, err)?);
+                    break;
+                }
+            }
+        }
+
+        if cancelled.is_none() {
+            if let Some(p) = progress.as_mut() {
+                p.finish(py)?;
+            }
+            // 回测结束时仍在等待的顺延订单全部取消
+            for order in deferred.drain(..) {
+                if hooks.on_order {
+                    order_events::notify(py, &strategy, "cancelled", &order, OrderReason::RunEnded, None)?;
+                }
+            }
         }
 
-        let _ = strategy.call_method0(py, "on_stop");
+        if hooks.on_stop {
+            let _ = strategy.call_method0(py, "on_stop");
+        }
 
         // 构建结果
-        let result = PyDict::new_bound(py);
-        // 汇总头寸（简化：不返回逐 symbol 持仓，用户可在 on_trade / ctx 中获取）
+        let stats = Self::compute_enhanced_stats(py, &equity_curve, &trades, self.cfg.periods_per_year())?;
+        let result = frames::new_result(py, equity_curve.clone(), trades.clone())?;
+        frames::set_cash_curve(&result, cash_curve.clone())?;
+        // 组合层面没有单一的持仓/成本，逐 symbol 的期末持仓放在 positions 中
         result.set_item("cash", cash)?;
         result.set_item("position", 0.0_f64)?;
         result.set_item("avg_cost", 0.0_f64)?;
+        let final_positions = PyDict::new_bound(py);
+        let mut symbols: Vec<&String> = positions.keys().collect();
+        symbols.sort();
+        for sym in symbols {
+            let (p, ac) = positions[sym];
+            let lp = last_price_map.get(sym).copied();
+            let pd = PyDict::new_bound(py);
+            pd.set_item("position", p)?;
+            pd.set_item("avg_cost", ac)?;
+            pd.set_item("last_price", lp)?;
+            pd.set_item("market_value", lp.map(|lp| p * lp))?;
+            pd.set_item("unrealized_pnl", lp.map(|lp| p * (lp - ac)))?;
+            final_positions.set_item(sym, pd)?;
+        }
+        result.set_item("positions", final_positions)?;
         let last_eq = equity_curve.last().map(|(_, e)| *e).unwrap_or(cash);
         result.set_item("equity", last_eq)?;
         result.set_item("realized_pnl", realized_pnl)?;
 
         let eq_list = PyList::empty_bound(py);
-        for (dt, eq) in &equity_curve {
+        for ((dt, eq), c) in equity_curve.iter().zip(&cash_curve) {
             let row = PyDict::new_bound(py);
             if let Some(d) = dt { row.set_item("datetime", d)?; } else { row.set_item("datetime", py.None())?; }
             row.set_item("equity", eq)?;
+            row.set_item("cash", c)?;
             eq_list.append(row)?;
         }
         result.set_item("equity_curve", eq_list)?;
 
         let tr_list = PyList::empty_bound(py);
-        for (oid, side, price, size) in &trades {
-            let t = PyDict::new_bound(py);
-            t.set_item("order_id", oid)?;
-            t.set_item("side", side)?;
-            t.set_item("price", price)?;
-            t.set_item("size", size)?;
-            tr_list.append(t)?;
+        for trade in &trades {
+            tr_list.append(trade.to_pydict(py)?)?;
         }
         result.set_item("trades", tr_list)?;
-
-        let stats = self.compute_enhanced_stats(py, &equity_curve, &trades)?;
         result.set_item("stats", stats)?;
 
-        Ok(result.into())
+        let result: PyObject = result.into();
+        if self.cfg.record_positions {
+            positions::mark_result(py, &result, &positions_history)?;
+        }
+        cancel::mark_result(py, &result, cancelled)?;
+        let first_dt = feed_bars.iter().filter_map(|bars| bars.first()?.datetime.as_deref()).min_by_key(|dt| database::parse_datetime(dt));
+        let last_dt = feed_bars.iter().filter_map(|bars| bars.last()?.datetime.as_deref()).max_by_key(|dt| database::parse_datetime(dt));
+        date_window::mark_result(py, &result, first_dt, last_dt, total_bars)?;
+        Ok(result)
     }
 }
 
@@ -1777,17 +4509,105 @@ fn factor_backtest_fast(py: Python<'_>, closes: Vec<f64>, factors: Vec<f64>, qua
 }
 
 #[pymodule]
-fn engine_rust(_py: Python, m: &PyModule) -> PyResult<()> {
+fn engine_rust(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<BacktestConfig>()?;
     m.add_class::<BacktestEngine>()?;
     m.add_class::<EngineContext>()?;
+    m.add_class::<CancelToken>()?;
+    m.add_class::<PaperTrader>()?;
+    m.add_class::<Replayer>()?;
+    m.add_class::<TradingCalendar>()?;
+    m.add_class::<MarketDataIter>()?;
+    m.add_class::<Strategy>()?;
     m.add_function(wrap_pyfunction!(compute_sma, m)?)?;
     m.add_function(wrap_pyfunction!(compute_rsi, m)?)?;
     m.add_function(wrap_pyfunction!(factor_backtest_fast, m)?)?;
+    // Indicator functions
+    m.add_function(wrap_pyfunction!(indicators::compute_donchian, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_psar, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_std, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_var, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_max, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_min, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_zscore, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_corr, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_linreg, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_mfi, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_roc, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_momentum, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_ema, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_trix, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_wma, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_hma, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_kama, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_pivots, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_indicators, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_indicators_multi, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_sma_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rsi_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_ema_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_wma_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_hma_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_roc_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_momentum_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_trix_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_max_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_min_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_std_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_zscore_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_kama_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_var_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_donchian_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_psar_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_rolling_corr_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_linreg_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_mfi_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_indicators_np, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::rolling_apply, m)?)?;
+    m.add_function(wrap_pyfunction!(indicators::compute_cross, m)?)?;
+    // Optimization functions
+    m.add_function(wrap_pyfunction!(optimize::optimize_grid, m)?)?;
+    m.add_function(wrap_pyfunction!(optimize::optimize_random, m)?)?;
+    m.add_function(wrap_pyfunction!(optimize::purged_kfold_split, m)?)?;
+    m.add_function(wrap_pyfunction!(optimize::combinatorial_purged_split, m)?)?;
+    // Portfolio functions
+    m.add_function(wrap_pyfunction!(portfolio::combine_strategies, m)?)?;
     // Database functions
     m.add_function(wrap_pyfunction!(database::get_market_data, m)?)?;
     m.add_function(wrap_pyfunction!(database::resample_klines, m)?)?;
+    m.add_function(wrap_pyfunction!(database::upsample_klines, m)?)?;
+    m.add_function(wrap_pyfunction!(database::save_memory_db, m)?)?;
+    m.add_function(wrap_pyfunction!(database::append_klines, m)?)?;
+    m.add_function(wrap_pyfunction!(database::export_market_data_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(database::save_klines_from_csv_dir, m)?)?;
+    m.add_function(wrap_pyfunction!(materialize::materialize_period, m)?)?;
+    m.add_function(wrap_pyfunction!(retention::prune_klines, m)?)?;
+    m.add_function(wrap_pyfunction!(retention::compact_database, m)?)?;
+    m.add_function(wrap_pyfunction!(database::load_memory_db, m)?)?;
+    m.add_function(wrap_pyfunction!(database::clear_memory_db, m)?)?;
+    m.add_function(wrap_pyfunction!(instruments::upsert_instrument, m)?)?;
+    m.add_function(wrap_pyfunction!(instruments::get_instrument, m)?)?;
+    m.add_function(wrap_pyfunction!(corporate_actions::save_corporate_actions, m)?)?;
+    m.add_function(wrap_pyfunction!(corporate_actions::get_corporate_actions, m)?)?;
+    m.add_function(wrap_pyfunction!(depth::save_depth, m)?)?;
+    m.add_function(wrap_pyfunction!(depth::load_depth, m)?)?;
+    m.add_function(wrap_pyfunction!(migrations::migrate_db, m)?)?;
+    m.add_function(wrap_pyfunction!(fetch::fetch_klines, m)?)?;
+    m.add_function(wrap_pyfunction!(market_iter::iter_market_data, m)?)?;
+    m.add_function(wrap_pyfunction!(run_store::save_backtest_result, m)?)?;
+    m.add_function(wrap_pyfunction!(run_store::load_backtest_result, m)?)?;
+    m.add_function(wrap_pyfunction!(quality::data_quality_report, m)?)?;
+    m.add_function(wrap_pyfunction!(dataset::export_parquet_dataset, m)?)?;
+    m.add_function(wrap_pyfunction!(dataset::import_parquet_dataset, m)?)?;
+    m.add_function(wrap_pyfunction!(logger::configure_logging, m)?)?;
     m.add_function(wrap_pyfunction!(database::save_klines, m)?)?;
     m.add_function(wrap_pyfunction!(database::save_klines_from_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(database::save_klines_from_parquet, m)?)?;
+    m.add_function(wrap_pyfunction!(database::export_klines_to_parquet, m)?)?;
+    m.add_function(wrap_pyfunction!(database::list_periods, m)?)?;
+    m.add_function(wrap_pyfunction!(database::list_symbols, m)?)?;
+    m.add_function(wrap_pyfunction!(database::get_data_range, m)?)?;
+    m.add_function(wrap_pyfunction!(database::find_gaps, m)?)?;
+    frames::register(py, m)?;
     Ok(())
 } 
\ No newline at end of file
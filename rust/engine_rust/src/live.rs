@@ -0,0 +1,241 @@
+//! 实时模拟交易（paper trading）模块
+//!
+//! 回测通过后，下一步通常是用实时行情做模拟交易。如果模拟交易需要另写一套下单、持仓逻辑，
+//! 两边的行为很容易不一致。`PaperTrader` 复用回测引擎的单资产状态机：
+//! 同样的订单解析、撮合、手续费与滑点、持仓更新和策略回调，策略代码无需任何修改。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 创建 `PaperTrader` 时调用策略的 `on_start(ctx)`
+//! 2. 每收到一根实时 bar，调用 `push_bar(bar)`：引擎调用 `next(bar, ctx)`、撮合订单、
+//!    触发 `on_order`/`on_trade`/`on_bar_end`，返回本根 bar 产生的成交
+//! 3. 调仓（`on_rebalance`）和交易日结束（`on_session_end`）需要知道下一根 bar 的日期，
+//!    因此在下一根 bar 到达（或调用 `stop()`）时才触发，按上一根 bar 的收盘价撮合
+//! 4. `stop()` 调用 `on_stop()` 并返回与 `run()` 相同格式的结果
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! trader = PaperTrader(MyStrategy(), config)
+//! for bar in live_feed():          # 行情回调、websocket、轮询……
+//!     for trade in trader.push_bar(bar):
+//!         print("filled", trade)
+//!     print(trader.context().equity)
+//! result = trader.stop()
+//! ```
+//!
+//! # 注意事项
+//!
+//! - `on_rebalance`/`on_session_end` 相比回测晚一步触发（在下一根 bar 的 `next()` 之前）
+//! - 不支持 `indicators` 预计算，策略需自行维护指标（或使用增量指标）
+
+use chrono::NaiveDate;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::bar_fields::FieldNames;
+use crate::baseline::{self, BuyAndHold};
+use crate::checkpoint::RunState;
+use crate::positions;
+use crate::schedule::{self, RebalanceFreq, RebalanceSchedule};
+use crate::sessions::TradingSessions;
+use crate::sizing::KellyStats;
+use crate::strategy::StrategyHooks;
+use crate::validation::BarValidator;
+use crate::vol_target::ReturnWindow;
+use crate::{extract_bar, BacktestConfig, BacktestEngine, BarData, BarFlags, EngineContext, PositionState};
+
+/// 实时模拟交易引擎
+///
+/// 逐根接收实时 bar，使用与回测完全相同的订单与持仓逻辑驱动策略。
+#[pyclass]
+pub struct PaperTrader {
+    engine: BacktestEngine,
+    strategy: PyObject,
+    state: RunState,
+    hooks: StrategyHooks,
+    schedule: RebalanceSchedule,
+    schedule_cursor: usize,
+    sessions: Option<TradingSessions>,
+    /// 买入持有基准（配置开启时随推送的 bar 增量计算）
+    buy_and_hold: Option<BuyAndHold>,
+    /// 已处理的 bar 数
+    bar_count: usize,
+    /// 上一根 bar（及其日期），用于延后判断调仓与交易日结束
+    last_bar: Option<(BarData, Option<NaiveDate>)>,
+    /// 严格模式下的 bar 校验器（与上一根推送的 bar 比较时间顺序）
+    validator: Option<BarValidator>,
+    stopped: bool,
+}
+
+fn value_error(msg: &str) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg.to_string())
+}
+
+#[pymethods]
+impl PaperTrader {
+    /// 创建模拟交易引擎并调用策略的 `on_start(ctx)`
+    ///
+    /// # 参数
+    ///
+    /// - `strategy`: Python 策略对象（与 `run()` 使用的完全相同）
+    /// - `cfg`: 回测配置；使用其中的初始资金、手续费、滑点、预热与调仓设置
+    #[new]
+    fn new(py: Python<'_>, strategy: PyObject, cfg: BacktestConfig) -> PyResult<Self> {
+        let schedule = cfg.rebalance_schedule()?;
+        let hooks = StrategyHooks::detect(py, &strategy)?;
+        let sessions = cfg.trading_sessions()?;
+        let buy_and_hold = cfg.buy_and_hold.then(|| BuyAndHold::new(&cfg));
+        let validator = cfg.strict.then(BarValidator::default);
+        let state = RunState { pos: PositionState::new(cfg.cash), order_seq: 1, equity_curve: Vec::new(), trades: Vec::new(), deferred: Vec::new(), positions_history: Vec::new(), kelly: KellyStats::default(), vol_returns: ReturnWindow::default(), profile: None, exposure_limits: cfg.exposure_limits()?, vol_target: cfg.vol_targeting()?, defer_orders: cfg.defers_orders()? };
+        let trader = Self {
+            engine: BacktestEngine { cfg },
+            strategy,
+            state,
+            hooks,
+            schedule,
+            schedule_cursor: 0,
+            sessions,
+            buy_and_hold,
+            bar_count: 0,
+            last_bar: None,
+            validator,
+            stopped: false,
+        };
+        let ctx = Py::new(py, trader.context())?;
+        if trader.hooks.on_start {
+            let _ = trader.strategy.call_method1(py, "on_start", (ctx.bind(py),));
+        }
+        Ok(trader)
+    }
+
+    /// 推送一根实时 bar
+    ///
+    /// # 参数
+    ///
+    /// - `bar`: bar 字典，格式与 `run()` 的数据相同（至少包含 `close`，建议包含 `datetime`）；
+    ///   配置了 `strict=True` 时无效的 bar 返回 `ValueError`，行号为推送的序号
+    ///
+    /// # 返回值
+    ///
+    /// 本次推送产生的成交列表（每个元素与 `run()` 结果中 `trades` 的元素格式相同），
+    /// 包括上一根 bar 延后触发的调仓成交
+    fn push_bar(&mut self, py: Python<'_>, bar: &Bound<'_, PyDict>) -> PyResult<PyObject> {
+        if self.stopped {
+            return Err(value_error("PaperTrader has been stopped"));
+        }
+        let bar_data = extract_bar(bar.as_gil_ref(), &mut FieldNames::default())?;
+        // 严格模式下无效的 bar 直接报错，不影响账户状态
+        if let Some(validator) = self.validator.as_mut() {
+            validator.check(&bar_data)?;
+        }
+        let date = schedule::bar_dates([bar_data.datetime.as_deref()])[0];
+        let trades_before = self.state.trades.len();
+
+        // 新 bar 的日期确定后，上一根 bar 是否为周期末/交易日末才能判断
+        if date.is_some() {
+            self.flush_period_end(py, date)?;
+        }
+
+        let i = self.bar_count;
+        let flags = BarFlags { outside_session: self.outside_session(&bar_data), ..BarFlags::default() };
+        self.engine.process_bar(py, &self.strategy, &self.hooks, &mut self.state, i, &bar_data, &[], None, flags)?;
+        if let Some(bh) = self.buy_and_hold.as_mut().filter(|_| i >= self.engine.cfg.warmup_bars) {
+            bh.update(&bar_data);
+        }
+        self.bar_count += 1;
+        self.last_bar = Some((bar_data, date));
+        self.trades_since(py, trades_before)
+    }
+
+    /// 当前账户状态快照（以最近一根 bar 的收盘价估算净值）
+    fn context(&self) -> EngineContext {
+        let last_bar = self.last_bar.as_ref().map(|(b, _)| b);
+        EngineContext::snapshot(
+            &self.state.pos,
+            last_bar.map(|b| b.close),
+            last_bar.and_then(|b| b.datetime.as_deref()),
+            self.bar_count.saturating_sub(1),
+        )
+        .with_open_orders(&self.state.deferred, self.state.bars_recorded().saturating_sub(1))
+    }
+
+    /// 已处理的 bar 数
+    #[getter]
+    fn bar_count(&self) -> usize {
+        self.bar_count
+    }
+
+    /// 截至当前的结果（格式同 `run()`），不结束模拟交易
+    fn result(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let st = self.state.clone();
+        let range = baseline::curve_range(&st.equity_curve);
+        let result = self.engine.build_result(py, st.pos, st.equity_curve, st.trades)?;
+        if self.engine.cfg.record_positions {
+            positions::mark_result(py, &result, &st.positions_history)?;
+        }
+        baseline::mark_result(py, &result, &self.engine.cfg, self.buy_and_hold.clone(), None, range)?;
+        Ok(result)
+    }
+
+    /// 结束模拟交易：触发最后一根 bar 的周期末回调，调用 `on_stop()` 并返回最终结果
+    ///
+    /// 重复调用时直接返回结果。
+    fn stop(&mut self, py: Python<'_>) -> PyResult<PyObject> {
+        if !self.stopped {
+            self.flush_period_end(py, None)?;
+            self.stopped = true;
+            self.engine.cancel_deferred(py, &self.strategy, &self.hooks, &mut self.state)?;
+            if self.hooks.on_stop {
+                let _ = self.strategy.call_method0(py, "on_stop");
+            }
+        }
+        self.result(py)
+    }
+}
+
+impl PaperTrader {
+    /// 对上一根 bar 补发调仓与交易日结束回调；`next` 为下一根 bar 的日期（`None` 表示已结束）
+    fn flush_period_end(&mut self, py: Python<'_>, next: Option<NaiveDate>) -> PyResult<()> {
+        let Some((bar, Some(cur))) = self.last_bar.clone() else { return Ok(()) };
+        let i = self.bar_count - 1;
+        // 预热期的 bar 不执行订单，也不触发调仓
+        if i < self.engine.cfg.warmup_bars {
+            return Ok(());
+        }
+        let rebalance = self.hooks.on_rebalance && self.schedule.is_due(Some(cur), next, &mut self.schedule_cursor);
+        let session_end = self.hooks.on_session_end && RebalanceFreq::Daily.is_period_end(cur, next);
+
+        if rebalance {
+            let ctx = Py::new(py, self.context().accepting_orders())?;
+            let action = self.strategy.call_method1(py, "on_rebalance", (ctx.bind(py),))?;
+            let outside_session = self.outside_session(&bar);
+            let st = &mut self.state;
+            self.engine.execute_queued(py, &self.strategy, &self.hooks, &ctx, &bar, st, outside_session)?;
+            self.engine.execute_action(py, &self.strategy, &self.hooks, action.bind(py), &bar, st, outside_session)?;
+            // 调仓成交后更新该 bar 的净值
+            if let Some(point) = st.equity_curve.last_mut() {
+                point.1 = st.pos.cash + st.pos.position * bar.close;
+            }
+        }
+        if session_end {
+            let ctx = Py::new(py, self.context())?;
+            self.strategy.call_method1(py, "on_session_end", (ctx.bind(py),))?;
+        }
+        Ok(())
+    }
+
+    /// bar 是否在配置的交易时段之外
+    fn outside_session(&self, bar: &BarData) -> bool {
+        self.sessions.as_ref().is_some_and(|s| s.outside(bar.datetime.as_deref()))
+    }
+
+    /// 把 `from` 之后新增的成交转换为 Python 列表
+    fn trades_since(&self, py: Python<'_>, from: usize) -> PyResult<PyObject> {
+        let list = PyList::empty_bound(py);
+        for trade in &self.state.trades[from..] {
+            list.append(trade.to_pydict(py)?)?;
+        }
+        Ok(list.into())
+    }
+}
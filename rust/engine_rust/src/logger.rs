@@ -0,0 +1,152 @@
+//! 日志输出模块
+//!
+//! 数据导入过程中的进度信息（读取文件、已准备的记录数、写入目标表）原先直接 `println!` 到标准输出，
+//! 在 Web 服务、Jupyter 或定时任务里调用时会刷屏，也无法接入应用自己的日志系统。
+//! 这个模块统一管理这些输出：可以调整日志级别、转发到 Python 的 `logging`，或者完全静默。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 日志配置保存在进程级的原子变量中，所有线程共享，修改后立即生效
+//! 2. 低于当前级别的消息在格式化之前就被丢弃，不产生额外开销
+//! 3. 默认输出到标准输出（与之前的行为一致）；开启转发后交给名为 `engine_rust` 的 Python logger，
+//!    级别编号与 `logging.DEBUG/INFO/WARNING/ERROR` 相同
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! import logging
+//! from engine_rust import configure_logging
+//!
+//! configure_logging(quiet=True)                              # Notebook 中不输出导入进度
+//! configure_logging(level="warning")                         # 只输出警告和错误
+//! logging.basicConfig(level=logging.INFO)
+//! configure_logging(level="info", python_logging=True, quiet=False)  # 交给服务自己的日志系统
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 配置是全局的，对同一进程中的所有调用生效
+//! - 转发到 Python 时需要获取 GIL；转发失败（如 logger 的处理器抛出异常）时该条消息被丢弃
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// 日志级别（数值与 Python `logging` 的级别编号相同）
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+    Debug = 10,
+    Info = 20,
+    Warning = 30,
+    Error = 40,
+}
+
+impl Level {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "debug" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warning" | "warn" => Ok(Level::Warning),
+            "error" => Ok(Level::Error),
+            other => Err(format!("Unsupported log level: {} (expected debug/info/warning/error)", other)),
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            10 => Level::Debug,
+            30 => Level::Warning,
+            40 => Level::Error,
+            _ => Level::Info,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// 当前日志级别
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+/// 是否转发到 Python `logging`
+static PYTHON_LOGGING: AtomicBool = AtomicBool::new(false);
+/// 静默模式：丢弃全部日志
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// 输出一条日志（低于当前级别或处于静默模式时直接丢弃）
+pub(crate) fn log(level: Level, args: fmt::Arguments<'_>) {
+    if QUIET.load(Ordering::Relaxed) || (level as u8) < LEVEL.load(Ordering::Relaxed) {
+        return;
+    }
+    if PYTHON_LOGGING.load(Ordering::Relaxed) {
+        let message = args.to_string();
+        Python::with_gil(|py| {
+            let _ = py
+                .import_bound("logging")
+                .and_then(|logging| logging.call_method1("getLogger", ("engine_rust",)))
+                .and_then(|logger| logger.call_method1("log", (level as u8, message)));
+        });
+    } else {
+        println!("  {}", args);
+    }
+}
+
+/// 配置日志输出
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import configure_logging
+///
+/// configure_logging(quiet=True)                          # 静默
+/// configure_logging(level="debug", python_logging=True)  # 转发到 logging.getLogger("engine_rust")
+/// print(configure_logging())                             # {"level": "debug", "python_logging": True, "quiet": True}
+/// ```
+///
+/// # 参数
+///
+/// - `level`: 日志级别（`"debug"`/`"info"`/`"warning"`/`"error"`），默认 `"info"`
+/// - `python_logging`: 是否转发到 Python 的 `logging`（logger 名为 `engine_rust`），默认 `False`（输出到标准输出）
+/// - `quiet`: 是否静默，默认 `False`；静默时保留级别等其他设置，关闭静默后恢复输出
+///
+/// 未传入（`None`）的参数保持当前设置不变。
+///
+/// # 返回值
+///
+/// 修改后的配置：`{"level", "python_logging", "quiet"}`
+///
+/// # 注意事项
+///
+/// - 级别名称无法识别时返回 `ValueError`，此时不修改任何设置
+#[pyfunction]
+#[pyo3(signature = (level=None, python_logging=None, quiet=None))]
+pub fn configure_logging(
+    py: Python<'_>,
+    level: Option<&str>,
+    python_logging: Option<bool>,
+    quiet: Option<bool>,
+) -> PyResult<PyObject> {
+    if let Some(name) = level {
+        let level = Level::parse(name).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        LEVEL.store(level as u8, Ordering::Relaxed);
+    }
+    if let Some(enabled) = python_logging {
+        PYTHON_LOGGING.store(enabled, Ordering::Relaxed);
+    }
+    if let Some(enabled) = quiet {
+        QUIET.store(enabled, Ordering::Relaxed);
+    }
+
+    let config = PyDict::new_bound(py);
+    config.set_item("level", Level::from_u8(LEVEL.load(Ordering::Relaxed)).name())?;
+    config.set_item("python_logging", PYTHON_LOGGING.load(Ordering::Relaxed))?;
+    config.set_item("quiet", QUIET.load(Ordering::Relaxed))?;
+    Ok(config.into())
+}
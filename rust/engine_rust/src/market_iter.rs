@@ -0,0 +1,186 @@
+//! 分块流式读取模块
+//!
+//! `get_market_data()` 一次把查询结果全部转换成 Python 列表，分钟线、tick 级别的多年数据
+//! 动辄上千万行，全部放进内存并不现实。这个模块提供 `iter_market_data()`，按固定行数
+//! 分块从 DuckDB 读取 K 线，返回的迭代器每次产出一块（bar 字典列表），
+//! 可以直接交给引擎的流式输入模式（`engine.run(strategy, iterator)`）。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 迭代器只记录查询条件和上一块最后一根 bar 的时间（游标），不持有数据库连接
+//! 2. 每次取下一块时打开连接，按 `datetime > 游标 ORDER BY datetime LIMIT chunk_size` 查询
+//!    （键集分页，不使用 OFFSET，越往后读也不会变慢）
+//! 3. 查询结果不足 `chunk_size` 行时说明已读到末尾，之后迭代结束
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import BacktestEngine, iter_market_data
+//!
+//! chunks = iter_market_data("data/backtest.db", "BTCUSDT", "1m", chunk_size=50_000, start="2020-01-01")
+//! result = engine.run(MyStrategy(), chunks)  # 流式输入，内存中同时只有一块数据
+//!
+//! for chunk in iter_market_data("data/backtest.db", "BTCUSDT", "1m"):
+//!     process(chunk)
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 迭代过程中不持有连接，其他进程可以同时写入；新写入的、时间晚于游标的 bar 会在之后的块中读到
+//! - 返回的时间与库中存储的一致（不做时区换算、不复权）
+
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+
+use crate::database::{ensure_period_table, kline_bar_to_pydict, open_connection, trim_fractional_seconds, KlineBar};
+
+/// 默认每块的行数
+const DEFAULT_CHUNK_SIZE: usize = 10_000;
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+/// 按块读取 K 线的迭代器（由 `iter_market_data()` 创建）
+#[pyclass]
+pub struct MarketDataIter {
+    db_path: String,
+    symbol: String,
+    period: String,
+    chunk_size: usize,
+    start: Option<String>,
+    end: Option<String>,
+    /// 上一块最后一根 bar 的时间，`None` 表示还没有读过
+    cursor: Option<String>,
+    /// 已读到末尾
+    exhausted: bool,
+}
+
+impl MarketDataIter {
+    /// 读取游标之后的下一块
+    fn next_chunk(&self) -> PyResult<Vec<KlineBar>> {
+        let conn = open_connection(&self.db_path)?;
+        let table_name = ensure_period_table(&conn, &self.period)?;
+
+        let mut where_parts = vec!["symbol = ?"];
+        let mut params: Vec<&str> = vec![&self.symbol];
+        if let Some(cursor) = &self.cursor {
+            where_parts.push("datetime > ?");
+            params.push(cursor);
+        } else if let Some(start) = &self.start {
+            where_parts.push("datetime >= ?");
+            params.push(start);
+        }
+        if let Some(end) = &self.end {
+            where_parts.push("datetime <= ?");
+            params.push(end);
+        }
+
+        let query = format!(
+            "SELECT strftime(datetime, '%Y-%m-%d %H:%M:%S.%f') AS datetime_str, open, high, low, close, volume FROM {} WHERE {} ORDER BY datetime LIMIT {}",
+            table_name,
+            where_parts.join(" AND "),
+            self.chunk_size
+        );
+        let mut stmt = conn
+            .prepare(&query)
+            .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+        let rows = stmt
+            .query_map(duckdb::params_from_iter(params), |row| {
+                Ok(KlineBar {
+                    datetime: trim_fractional_seconds(row.get(0)?),
+                    open: row.get(1)?,
+                    high: row.get(2)?,
+                    low: row.get(3)?,
+                    close: row.get(4)?,
+                    volume: row.get(5)?,
+                    symbol: self.symbol.clone(),
+                })
+            })
+            .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))
+    }
+}
+
+#[pymethods]
+impl MarketDataIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// 返回下一块（bar 字典列表），读完后结束迭代
+    fn __next__(mut slf: PyRefMut<'_, Self>, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        if slf.exhausted {
+            return Ok(None);
+        }
+        let bars = {
+            let this = &*slf;
+            py.allow_threads(|| this.next_chunk())?
+        };
+        if bars.len() < slf.chunk_size {
+            slf.exhausted = true;
+        }
+        let Some(last) = bars.last() else { return Ok(None) };
+        slf.cursor = Some(last.datetime.clone());
+
+        let chunk = PyList::empty_bound(py);
+        for bar in &bars {
+            chunk.append(kline_bar_to_pydict(py, bar)?)?;
+        }
+        Ok(Some(chunk.into()))
+    }
+}
+
+/// 按块流式读取 K 线
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import iter_market_data
+///
+/// # 每块 5 万根 1 分钟线，直接作为引擎的流式输入
+/// chunks = iter_market_data("data/backtest.db", "BTCUSDT", "1m", chunk_size=50_000)
+/// result = engine.run(MyStrategy(), chunks)
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `period`: 周期字符串（如 "1m", "1d"）
+/// - `chunk_size`: 每块的行数，默认 10000
+/// - `start` / `end`: 可选的时间范围（闭区间）
+///
+/// # 返回值
+///
+/// 迭代器，每次产出一个列表，元素是与 `get_market_data()` 相同格式的 bar 字典；最后一块可能不足 `chunk_size` 行
+///
+/// # 注意事项
+///
+/// - `chunk_size` 为 0 时返回 `ValueError`
+/// - 查询在每次取下一块时执行，数据库错误在迭代过程中以 `RuntimeError` 报告
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, period, chunk_size=DEFAULT_CHUNK_SIZE, start=None, end=None))]
+pub fn iter_market_data(
+    db_path: String,
+    symbol: String,
+    period: String,
+    chunk_size: usize,
+    start: Option<String>,
+    end: Option<String>,
+) -> PyResult<MarketDataIter> {
+    if chunk_size == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>("chunk_size must be positive"));
+    }
+    Ok(MarketDataIter {
+        db_path,
+        symbol,
+        period,
+        chunk_size,
+        start,
+        end,
+        cursor: None,
+        exhausted: false,
+    })
+}
@@ -0,0 +1,284 @@
+//! 派生周期物化模块
+//!
+//! 库里通常只保存 1 分钟数据，需要 5 分钟、1 小时、日线时由 `load_and_synthesize_klines()` 现场重采样；
+//! 数据量一大，每次查询粗周期都要读出全部 1 分钟数据再合成。这个模块把重采样结果直接写入 DuckDB 的
+//! 目标周期表（如 `klines_15m`），之后 `get_market_data(..., "15m")` 直接读表即可；源周期写入新数据时，
+//! 已物化的目标周期会自动增量更新。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. `materialize_period()` 在登记表 `materialized_periods` 中记录 (标的, 源周期, 目标周期)
+//! 2. 刷新时找到受影响的第一个目标周期桶（可能尚未走完的最后一根，或新数据落入的那一根），
+//!    删除目标表中从该桶开始的 K 线，读出源周期从该桶开始的数据重新合成并追加
+//! 3. `save_klines()`、`append_klines()`、`save_klines_from_csv()` / `save_klines_from_parquet()`
+//!    提交后，对依赖该周期的目标周期做同样的刷新；目标周期本身也被物化时（如 1m → 5m → 1h）逐级刷新
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import materialize_period, append_klines, get_market_data
+//!
+//! for period in ["5m", "15m", "1h", "1d"]:
+//!     materialize_period("data/backtest.db", "AAPL", "1m", period)
+//!
+//! append_klines("data/backtest.db", "AAPL", "1m", todays_bars)   # 5m/15m/1h/1d 同步更新
+//! bars_1h = get_market_data("data/backtest.db", "AAPL", "1h")     # 直接读表，无需重采样
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 重采样规则与 `resample_klines()` 相同（不传交易日历，日线按自然日分组）
+//! - 目标周期表由物化结果维护，不要再向其中直接写入同一标的的数据，否则下一次刷新时会被覆盖
+//! - 替换源周期数据（`replace=True`）时，目标周期从源周期最早的数据开始整体重建；
+//!   更早的目标周期 K 线（例如源周期已被 `prune_klines()` 清理的部分）继续保留
+
+use chrono::NaiveDateTime;
+use duckdb::Connection;
+use pyo3::prelude::*;
+
+use crate::database::{
+    append_kline_rows, ensure_period_table, open_connection, parse_datetime, period_to_minutes,
+    resample_klines_rust, sanitize_period_identifier, trim_fractional_seconds, KlineBar,
+};
+
+/// 物化登记表
+const REGISTRY_TABLE: &str = "materialized_periods";
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+/// 目标周期从哪里开始刷新
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum RefreshFrom {
+    /// 从目标周期已有的最后一根 K 线开始（它可能尚未走完）
+    Latest,
+    /// 从包含该时间的目标周期桶开始（源周期在该时间之后写入了数据）
+    Since(NaiveDateTime),
+    /// 整体重建
+    Full,
+}
+
+fn ensure_registry(conn: &Connection) -> PyResult<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                symbol VARCHAR NOT NULL,
+                source_table VARCHAR NOT NULL,
+                target_period VARCHAR NOT NULL,
+                PRIMARY KEY (symbol, source_table, target_period)
+            )",
+            REGISTRY_TABLE
+        ),
+        [],
+    )
+    .map_err(|e| runtime_error(format!("Failed to ensure table {}: {}", REGISTRY_TABLE, e)))?;
+    Ok(())
+}
+
+/// 以 `period` 为源周期物化的目标周期；没有登记表时返回空列表（不会创建登记表）
+fn dependents(conn: &Connection, symbol: &str, period: &str) -> PyResult<Vec<String>> {
+    let source_table = format!("klines_{}", sanitize_period_identifier(period)?);
+    let mut stmt = conn
+        .prepare("SELECT table_name FROM information_schema.tables WHERE table_schema = 'main' AND table_name = ?")
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let registered = stmt
+        .query_map(duckdb::params![REGISTRY_TABLE], |row| row.get::<_, String>(0))
+        .map_err(|e| runtime_error(format!("Failed to list tables: {}", e)))?
+        .next()
+        .is_some();
+    if !registered {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT target_period FROM {} WHERE symbol = ? AND source_table = ? ORDER BY target_period",
+            REGISTRY_TABLE
+        ))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let targets = stmt
+        .query_map(duckdb::params![symbol, source_table], |row| row.get::<_, String>(0))
+        .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+    Ok(targets)
+}
+
+/// 源周期写入数据后刷新依赖它的目标周期（逐级向下传递）
+///
+/// `from` 只在确实存在依赖时才会被调用，调用方可以把计算刷新起点的查询放在里面。
+pub(crate) fn refresh_dependents(
+    conn: &Connection,
+    symbol: &str,
+    period: &str,
+    from: impl FnOnce() -> PyResult<RefreshFrom>,
+) -> PyResult<()> {
+    let targets = dependents(conn, symbol, period)?;
+    if targets.is_empty() {
+        return Ok(());
+    }
+    refresh_targets(conn, symbol, period, targets, from()?)
+}
+
+/// 依次刷新目标周期，再把各自的刷新起点传给以它们为源周期的物化周期
+fn refresh_targets(conn: &Connection, symbol: &str, period: &str, targets: Vec<String>, from: RefreshFrom) -> PyResult<()> {
+    for target in targets {
+        let (_, start) = refresh(conn, symbol, period, &target, from)?;
+        let next = start.map_or(RefreshFrom::Full, RefreshFrom::Since);
+        refresh_targets(conn, symbol, &target, dependents(conn, symbol, &target)?, next)?;
+    }
+    Ok(())
+}
+
+/// 重新合成目标周期中从刷新起点开始的 K 线
+///
+/// # 返回值
+///
+/// `(写入的 K 线条数, 实际刷新起点)`；起点为 `None` 表示整体重建
+fn refresh(
+    conn: &Connection,
+    symbol: &str,
+    from_period: &str,
+    to_period: &str,
+    from: RefreshFrom,
+) -> PyResult<(usize, Option<NaiveDateTime>)> {
+    let source_table = ensure_period_table(conn, from_period)?;
+    let target_table = ensure_period_table(conn, to_period)?;
+
+    // 刷新起点：目标周期中最后一根（或包含 `since` 的那一根）K 线的时间
+    let start = match from {
+        RefreshFrom::Full => None,
+        RefreshFrom::Latest | RefreshFrom::Since(_) => {
+            let bound = match from {
+                RefreshFrom::Since(dt) => Some(dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()),
+                _ => None,
+            };
+            let query = format!(
+                "SELECT strftime(MAX(datetime), '%Y-%m-%d %H:%M:%S.%f') FROM {} WHERE symbol = ?{}",
+                target_table,
+                if bound.is_some() { " AND datetime <= ?" } else { "" }
+            );
+            let latest: Option<String> = match &bound {
+                Some(b) => conn.query_row(&query, duckdb::params![symbol, b], |row| row.get(0)),
+                None => conn.query_row(&query, duckdb::params![symbol], |row| row.get(0)),
+            }
+            .map_err(|e| runtime_error(format!("Failed to query latest datetime: {}", e)))?;
+            latest.as_deref().and_then(parse_datetime)
+        }
+    };
+    let start_str = start.map(|dt| dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string());
+
+    conn.execute("BEGIN TRANSACTION", [])
+        .map_err(|e| runtime_error(format!("Failed to begin transaction: {}", e)))?;
+
+    let range_clause = if start_str.is_some() { " AND datetime >= ?" } else { "" };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT strftime(datetime, '%Y-%m-%d %H:%M:%S.%f'), open, high, low, close, volume
+             FROM {} WHERE symbol = ?{} ORDER BY datetime",
+            source_table, range_clause
+        ))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let map_row = |row: &duckdb::Row| -> duckdb::Result<KlineBar> {
+        Ok(KlineBar {
+            datetime: trim_fractional_seconds(row.get(0)?),
+            open: row.get(1)?,
+            high: row.get(2)?,
+            low: row.get(3)?,
+            close: row.get(4)?,
+            volume: row.get(5)?,
+            symbol: symbol.to_string(),
+        })
+    };
+    let source = match &start_str {
+        Some(s) => stmt.query_map(duckdb::params![symbol, s], map_row),
+        None => stmt.query_map(duckdb::params![symbol], map_row),
+    }
+    .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?
+    .collect::<Result<Vec<KlineBar>, _>>()
+    .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+
+    let mut bars = resample_klines_rust(source, to_period, None)?;
+    for bar in bars.iter_mut() {
+        bar.symbol = symbol.to_string();
+    }
+
+    // 删除将被重算的目标周期 K 线；整体重建时只删除源周期数据覆盖的部分，
+    // 源周期已按保留策略清理掉的更早数据，其目标周期 K 线继续保留
+    let delete_from = start_str.or_else(|| bars.first().map(|b| b.datetime.clone()));
+    if let Some(from) = delete_from {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE symbol = ? AND datetime >= ?", target_table),
+            duckdb::params![symbol, from],
+        )
+        .map_err(|e| runtime_error(format!("Failed to delete stale bars: {}", e)))?;
+    }
+    append_kline_rows(conn, &target_table, &bars)?;
+
+    conn.execute("COMMIT", []).map_err(|e| runtime_error(format!("Failed to commit transaction: {}", e)))?;
+    Ok((bars.len(), start))
+}
+
+/// 把源周期数据物化为更粗的目标周期表，并在源周期更新时自动增量刷新
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import materialize_period, get_market_data
+///
+/// materialize_period("data/backtest.db", "AAPL", "1m", "15m")
+/// bars_15m = get_market_data("data/backtest.db", "AAPL", "15m")
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `from_period`: 源周期（如 `"1m"`）
+/// - `to_period`: 目标周期（如 `"5m"`、`"1h"`、`"1d"`），必须比源周期更长
+///
+/// # 返回值
+///
+/// 本次写入目标周期表的 K 线条数。第一次调用时整体重建（目标表中与合成结果重叠的原有数据被替换），
+/// 之后重复调用只重算最后一根（可能尚未走完的）目标周期 K 线及其后的数据
+///
+/// # 注意事项
+///
+/// - 登记后，写入源周期的函数会在提交后自动刷新目标周期，通常不需要再次调用
+/// - 周期无法识别，或目标周期不比源周期长时返回 `ValueError`
+#[pyfunction]
+pub fn materialize_period(db_path: String, symbol: String, from_period: String, to_period: String) -> PyResult<usize> {
+    let minutes = |period: &str| {
+        period_to_minutes(period).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Unsupported period: {}", period))
+        })
+    };
+    if minutes(&to_period)? <= minutes(&from_period)? {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Target period '{}' must be longer than source period '{}'",
+            to_period, from_period
+        )));
+    }
+
+    let conn = open_connection(&db_path)?;
+    ensure_registry(&conn)?;
+    let source_table = format!("klines_{}", sanitize_period_identifier(&from_period)?);
+    let newly_registered = conn
+        .execute(
+            &format!(
+                "INSERT INTO {} (symbol, source_table, target_period) VALUES (?, ?, ?) ON CONFLICT DO NOTHING",
+                REGISTRY_TABLE
+            ),
+            duckdb::params![symbol, source_table, to_period],
+        )
+        .map_err(|e| runtime_error(format!("Failed to register materialized period: {}", e)))?
+        > 0;
+
+    // 第一次物化时整体重建，目标表中已有的同一标的数据会被替换
+    let from = if newly_registered { RefreshFrom::Full } else { RefreshFrom::Latest };
+    let (written, start) = refresh(&conn, &symbol, &from_period, &to_period, from)?;
+    let next = start.map_or(RefreshFrom::Full, RefreshFrom::Since);
+    refresh_targets(&conn, &symbol, &to_period, dependents(&conn, &symbol, &to_period)?, next)?;
+    Ok(written)
+}
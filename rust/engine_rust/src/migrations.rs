@@ -0,0 +1,188 @@
+//! 数据库结构版本与迁移模块
+//!
+//! K 线库往往积累了多年的数据，结构调整（新增列、新增表、补建索引）时不能要求用户删库重导。
+//! 这个模块在库中记录结构版本，并按顺序执行尚未应用的迁移，原地升级已有的数据库。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. `schema_version` 表记录已应用的迁移（版本号、说明、应用时间），库的版本即其中的最大版本号；
+//!    没有该表的旧库视为版本 0
+//! 2. `MIGRATIONS` 按版本号升序列出全部迁移，`migrate_db()` 依次执行版本号大于当前版本的迁移
+//! 3. 每个迁移与写入 `schema_version` 在同一个事务中完成，失败时该迁移整体回滚，之前的迁移保留
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import migrate_db
+//!
+//! report = migrate_db("data/backtest.db")
+//! print(report["from_version"], "->", report["to_version"], report["applied"])
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 迁移是幂等的：已是最新版本的库再次调用不做任何修改
+//! - 库的版本高于当前 engine_rust 支持的版本时返回 `ValueError`，避免旧版本误改新结构
+//! - 新增迁移时只能在 `MIGRATIONS` 末尾追加，已发布的迁移不要修改
+
+use duckdb::Connection;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::database::open_connection;
+
+/// 结构版本表名
+const VERSION_TABLE: &str = "schema_version";
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+/// 一个结构迁移
+struct Migration {
+    version: i32,
+    description: &'static str,
+    apply: fn(&Connection) -> PyResult<()>,
+}
+
+/// 全部迁移，按版本号升序排列
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "unique (symbol, datetime) index on every klines table",
+    apply: ensure_kline_indexes,
+}];
+
+/// 当前 engine_rust 支持的最新结构版本
+fn latest_version() -> i32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+fn ensure_version_table(conn: &Connection) -> PyResult<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                version INTEGER PRIMARY KEY,
+                description VARCHAR NOT NULL,
+                applied_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+            )",
+            VERSION_TABLE
+        ),
+        [],
+    )
+    .map_err(|e| runtime_error(format!("Failed to ensure table {}: {}", VERSION_TABLE, e)))?;
+    Ok(())
+}
+
+/// 库的当前结构版本（没有应用过任何迁移时为 0）
+fn current_version(conn: &Connection) -> PyResult<i32> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT COALESCE(MAX(version), 0) FROM {}", VERSION_TABLE))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let version = stmt
+        .query_map([], |row| row.get::<_, i32>(0))
+        .map_err(|e| runtime_error(format!("Failed to read schema version: {}", e)))?
+        .next()
+        .transpose()
+        .map_err(|e| runtime_error(format!("Failed to read schema version: {}", e)))?;
+    Ok(version.unwrap_or(0))
+}
+
+/// 迁移 1：为所有 `klines_*` 表补建 (symbol, datetime) 唯一索引
+///
+/// 早期写入或外部工具建的表可能缺少该索引，`INSERT ... ON CONFLICT` 依赖它去重。
+/// 表中已有重复的 (symbol, datetime) 时建索引会失败，需要先手工清理。
+fn ensure_kline_indexes(conn: &Connection) -> PyResult<()> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT table_name FROM information_schema.tables
+             WHERE table_schema = 'main' AND table_name LIKE 'klines\\_%' ESCAPE '\\'",
+        )
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| runtime_error(format!("Failed to list tables: {}", e)))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+
+    for table in tables {
+        conn.execute(
+            &format!(
+                "CREATE UNIQUE INDEX IF NOT EXISTS idx_{t}_symbol_datetime ON \"{t}\" (symbol, datetime)",
+                t = table
+            ),
+            [],
+        )
+        .map_err(|e| runtime_error(format!("Failed to ensure index for {}: {}", table, e)))?;
+    }
+    Ok(())
+}
+
+/// 把数据库升级到当前 engine_rust 支持的最新结构版本
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import migrate_db
+///
+/// # 升级 engine_rust 后先升级库结构
+/// report = migrate_db("data/backtest.db")
+/// # {"from_version": 0, "to_version": 1,
+/// #  "applied": [{"version": 1, "description": "unique (symbol, datetime) index on every klines table"}]}
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+///
+/// # 返回值
+///
+/// 字典：`from_version`、`to_version`（迁移前后的版本）、`applied`（本次应用的迁移列表，
+/// 每项包含 `version` 和 `description`；已是最新版本时为空列表）
+///
+/// # 注意事项
+///
+/// - 库的版本高于支持的最新版本时返回 `ValueError`
+/// - 某个迁移失败时返回 `RuntimeError`，该迁移回滚，之前已应用的迁移保留
+#[pyfunction]
+pub fn migrate_db(py: Python<'_>, db_path: String) -> PyResult<PyObject> {
+    let conn = open_connection(&db_path)?;
+    ensure_version_table(&conn)?;
+    let from_version = current_version(&conn)?;
+    if from_version > latest_version() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Database schema version {} is newer than the supported version {}; upgrade engine_rust",
+            from_version,
+            latest_version()
+        )));
+    }
+
+    let applied = PyList::empty_bound(py);
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from_version) {
+        conn.execute("BEGIN TRANSACTION", [])
+            .map_err(|e| runtime_error(format!("Failed to begin transaction: {}", e)))?;
+        let result = (migration.apply)(&conn).and_then(|_| {
+            conn.execute(
+                &format!("INSERT INTO {} (version, description) VALUES (?, ?)", VERSION_TABLE),
+                duckdb::params![migration.version, migration.description],
+            )
+            .map(|_| ())
+            .map_err(|e| runtime_error(format!("Failed to record schema version: {}", e)))
+        });
+        if let Err(e) = result {
+            let _ = conn.execute("ROLLBACK", []);
+            return Err(runtime_error(format!("Migration {} failed: {}", migration.version, e)));
+        }
+        conn.execute("COMMIT", []).map_err(|e| runtime_error(format!("Failed to commit transaction: {}", e)))?;
+
+        let item = PyDict::new_bound(py);
+        item.set_item("version", migration.version)?;
+        item.set_item("description", migration.description)?;
+        applied.append(item)?;
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("from_version", from_version)?;
+    report.set_item("to_version", from_version.max(latest_version()))?;
+    report.set_item("applied", applied)?;
+    Ok(report.into())
+}
@@ -0,0 +1,598 @@
+//! 参数优化模块
+//!
+//! 这个模块提供策略参数的批量回测与寻优：在同一份 K 线数据上，用不同参数组合构造策略并回测，
+//! 按指定的统计指标排序，返回"参数 → 统计指标"表。
+//!
+//! ## 为什么需要这个模块？
+//!
+//! 在 Python 中手写参数循环时，每组参数都要重新把整个 bar 列表转换为 Rust 结构，
+//! 结果也要逐个收集、排序。这个模块只提取一次数据（以及预计算一次指标），
+//! 所有试验共享同一份 Rust 数据，在 rayon 线程池中并行回测后统一排序。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. **展开参数空间**：网格搜索对 `param_grid` 做笛卡尔积；随机搜索按分布逐批采样
+//! 2. **一次性准备数据**：提取 bar 数据、预计算指标
+//! 3. **并行执行试验**：每个试验调用 `strategy_factory(**params)` 构造策略并回测，
+//!    撮合、持仓更新与统计指标等纯 Rust 计算释放 GIL 并行执行
+//! 4. **排序**：按 `metric` 对所有试验排序（随机搜索还可以在指标长期不再提升时提前停止）
+//!
+//! ## 注意事项
+//!
+//! 构造 bar 字典、调用策略、触发 `on_order`/`on_trade` 仍需要 GIL：每根 bar 只在这些回调前后
+//! 获取一次 GIL，其余时间释放给其他试验。Python 策略本身不会并行执行，策略越轻量、
+//! Rust 侧的撮合与统计占比越高，并行的收益越明显。
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::indicators::{compute_indicator_batch, parse_indicator_specs, OhlcvColumns, WarmupFill};
+use crate::{extract_bars_data, BacktestConfig, BacktestEngine, BarData};
+
+/// 单次试验的结果
+pub(crate) struct Trial {
+    /// 参数字典（传给 `strategy_factory` 的关键字参数）
+    pub params: Py<PyDict>,
+    /// 回测统计指标字典（`result["stats"]`）
+    pub stats: PyObject,
+    /// 排序用的指标值；缺失或无法转换为浮点数时为 NaN
+    pub score: f64,
+}
+
+/// 一次优化共享的回测环境：引擎、预提取的数据和预计算的指标
+pub(crate) struct TrialRunner {
+    engine: BacktestEngine,
+    strategy_factory: PyObject,
+    bars_data: Vec<BarData>,
+    indicator_columns: Vec<(String, Vec<Option<f64>>)>,
+    metric: String,
+}
+
+impl TrialRunner {
+    pub(crate) fn new(
+        cfg: &BacktestConfig,
+        strategy_factory: PyObject,
+        bars: &Bound<'_, PyList>,
+        indicators: Option<&Bound<'_, PyList>>,
+        metric: &str,
+    ) -> PyResult<Self> {
+        let bars_data = extract_bars_data(bars.as_gil_ref())?;
+        cfg.check_bars(&bars_data)?;
+        let indicator_columns = match indicators {
+            Some(specs) => {
+                let specs = parse_indicator_specs(specs, WarmupFill::None)?;
+                compute_indicator_batch(&OhlcvColumns::from_bars(&bars_data), &specs)
+            }
+            None => Vec::new(),
+        };
+        let engine = BacktestEngine { cfg: cfg.clone() };
+        let (bars_data, indicator_columns) = engine.apply_date_window(bars_data, indicator_columns)?;
+        Ok(Self {
+            engine,
+            strategy_factory,
+            bars_data,
+            indicator_columns,
+            metric: metric.to_string(),
+        })
+    }
+
+    /// 执行单个试验：构造策略、回测、提取统计指标
+    ///
+    /// 自行获取 GIL（已持有时可重入），因此既可以在当前线程调用，也可以在 rayon 工作线程中调用。
+    fn run_one(&self, keys: &[String], values: &[PyObject]) -> PyResult<Trial> {
+        Python::with_gil(|py| {
+            let params = PyDict::new_bound(py);
+            for (k, v) in keys.iter().zip(values) {
+                params.set_item(k, v)?;
+            }
+            let strategy = self.strategy_factory.call_bound(py, (), Some(&params))?;
+            let result = self.engine.run_detached(py, &strategy, &self.bars_data, &self.indicator_columns)?;
+            let stats = result.bind(py).get_item("stats")?;
+            let score = stats
+                .get_item(self.metric.as_str())
+                .ok()
+                .and_then(|v| v.extract::<f64>().ok())
+                .unwrap_or(f64::NAN);
+            Ok(Trial { params: params.unbind(), stats: stats.unbind(), score })
+        })
+    }
+
+    /// 并行执行一批试验，结果顺序与输入一致
+    pub(crate) fn run_batch(&self, py: Python<'_>, keys: &[String], batch: &[Vec<PyObject>]) -> PyResult<Vec<Trial>> {
+        py.allow_threads(|| batch.par_iter().map(|values| self.run_one(keys, values)).collect())
+    }
+}
+
+/// 按指标排序试验：默认降序，NaN 始终排在最后
+pub(crate) fn sort_trials(trials: &mut [Trial], ascending: bool) {
+    trials.sort_by(|a, b| match (a.score.is_nan(), b.score.is_nan()) {
+        (true, true) => std::cmp::Ordering::Equal,
+        (true, false) => std::cmp::Ordering::Greater,
+        (false, true) => std::cmp::Ordering::Less,
+        (false, false) => {
+            let ord = a.score.partial_cmp(&b.score).unwrap_or(std::cmp::Ordering::Equal);
+            if ascending { ord } else { ord.reverse() }
+        }
+    });
+}
+
+/// 把单个试验转换为字典：`{"params": ..., "stats": ..., "score": ...}`
+fn trial_to_pydict<'py>(py: Python<'py>, t: &Trial) -> PyResult<Bound<'py, PyDict>> {
+    let row = PyDict::new_bound(py);
+    row.set_item("params", t.params.bind(py))?;
+    row.set_item("stats", t.stats.bind(py))?;
+    row.set_item("score", t.score)?;
+    Ok(row)
+}
+
+/// 把试验列表转换为 Python 列表
+pub(crate) fn trials_to_pylist(py: Python<'_>, trials: &[Trial]) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for t in trials {
+        out.append(trial_to_pydict(py, t)?)?;
+    }
+    Ok(out.into())
+}
+
+/// `a` 是否优于 `b`（NaN 永远不优于任何值）
+fn is_better(a: f64, b: f64, ascending: bool, min_delta: f64) -> bool {
+    if a.is_nan() {
+        return false;
+    }
+    if b.is_nan() {
+        return true;
+    }
+    if ascending { a < b - min_delta } else { a > b + min_delta }
+}
+
+/// 读取参数空间：`{参数名: 候选值列表}`，保持字典顺序
+fn read_param_grid(param_grid: &Bound<'_, PyDict>) -> PyResult<(Vec<String>, Vec<Vec<PyObject>>)> {
+    let mut keys = Vec::with_capacity(param_grid.len());
+    let mut choices = Vec::with_capacity(param_grid.len());
+    for (k, v) in param_grid.iter() {
+        let name: String = k.extract()?;
+        let values: Vec<PyObject> = v.iter()?.map(|x| x.map(|x| x.into())).collect::<PyResult<_>>()?;
+        if values.is_empty() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Parameter '{}' has no candidate values",
+                name
+            )));
+        }
+        keys.push(name);
+        choices.push(values);
+    }
+    Ok((keys, choices))
+}
+
+/// 参数网格的笛卡尔积（最后一个参数变化最快）
+fn cartesian_product(py: Python<'_>, choices: &[Vec<PyObject>]) -> Vec<Vec<PyObject>> {
+    let mut combos: Vec<Vec<PyObject>> = vec![Vec::new()];
+    for values in choices {
+        let mut next = Vec::with_capacity(combos.len() * values.len());
+        for combo in &combos {
+            for v in values {
+                let mut c: Vec<PyObject> = combo.iter().map(|x| x.clone_ref(py)).collect();
+                c.push(v.clone_ref(py));
+                next.push(c);
+            }
+        }
+        combos = next;
+    }
+    combos
+}
+
+/// 网格搜索参数优化
+///
+/// 对参数网格的每个组合构造策略并回测，返回按指定指标排序的结果表。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import BacktestConfig, optimize_grid
+///
+/// cfg = BacktestConfig("2020-01-01", "2023-12-31", 100000.0, 0.0005, 2.0)
+/// table = optimize_grid(
+///     cfg,
+///     lambda fast, slow: SmaCross(fast=fast, slow=slow),
+///     bars,
+///     {"fast": [5, 10, 20], "slow": [30, 60, 120]},
+///     metric="sharpe",
+/// )
+/// best = table[0]
+/// print(best["params"], best["stats"]["sharpe"])
+/// ```
+///
+/// # 参数
+///
+/// - `engine_cfg`: 回测配置（所有试验共用）
+/// - `strategy_factory`: 可调用对象，以参数作为关键字参数调用，返回策略实例
+/// - `bars`: K 线字典列表（只提取一次）
+/// - `param_grid`: 字典 `{参数名: 候选值列表}`
+/// - `metric`: 排序使用的统计指标名（`result["stats"]` 中的键），默认 `"sharpe"`
+/// - `ascending`: 是否升序排序，默认 `False`（指标越大越好）
+/// - `indicators`: 可选的指标规格列表，预计算一次后注入每个试验的 bar 字典（同 `run()`）
+///
+/// # 返回值
+///
+/// 返回列表，每个元素为 `{"params": 参数字典, "stats": 统计指标字典, "score": 指标值}`，
+/// 按 `score` 排序；指标缺失的试验 `score` 为 NaN，排在最后
+///
+/// # 注意事项
+///
+/// - 组合数量是各参数候选值数量的乘积，注意控制网格大小
+/// - 任一试验抛出异常时整个优化以该异常结束
+/// - 试验并行执行，各试验的回调会交错调用；每个试验都会得到一个全新的策略实例，
+///   不要在 factory 外共享可变状态
+#[pyfunction]
+#[pyo3(signature = (engine_cfg, strategy_factory, bars, param_grid, metric="sharpe", ascending=false, indicators=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn optimize_grid(
+    py: Python<'_>,
+    engine_cfg: BacktestConfig,
+    strategy_factory: PyObject,
+    bars: &Bound<'_, PyList>,
+    param_grid: &Bound<'_, PyDict>,
+    metric: &str,
+    ascending: bool,
+    indicators: Option<&Bound<'_, PyList>>,
+) -> PyResult<PyObject> {
+    let (keys, choices) = read_param_grid(param_grid)?;
+    let combos = cartesian_product(py, &choices);
+    let runner = TrialRunner::new(&engine_cfg, strategy_factory, bars, indicators, metric)?;
+
+    let mut trials = runner.run_batch(py, &keys, &combos)?;
+    sort_trials(&mut trials, ascending);
+    trials_to_pylist(py, &trials)
+}
+
+/// 随机搜索中单个参数的取值分布
+enum ParamDist {
+    /// 从候选值中等概率选取
+    Choice(Vec<PyObject>),
+    /// 闭区间 `[low, high]` 内的均匀整数
+    IntRange(i64, i64),
+    /// 区间 `[low, high)` 内的均匀浮点数
+    FloatRange(f64, f64),
+}
+
+impl ParamDist {
+    /// 解析参数分布：列表表示候选值，二元组 `(low, high)` 表示区间
+    fn from_py(name: &str, v: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let invalid = || {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Parameter '{}' must be a non-empty list of choices or a (low, high) tuple",
+                name
+            ))
+        };
+        if let Ok(list) = v.downcast::<PyList>() {
+            if list.is_empty() {
+                return Err(invalid());
+            }
+            return Ok(ParamDist::Choice(list.iter().map(|x| x.into()).collect()));
+        }
+        if let Ok((low, high)) = v.extract::<(i64, i64)>() {
+            if low > high {
+                return Err(invalid());
+            }
+            return Ok(ParamDist::IntRange(low, high));
+        }
+        if let Ok((low, high)) = v.extract::<(f64, f64)>() {
+            if low.is_nan() || high.is_nan() || low > high {
+                return Err(invalid());
+            }
+            return Ok(ParamDist::FloatRange(low, high));
+        }
+        Err(invalid())
+    }
+
+    fn sample(&self, py: Python<'_>, rng: &mut StdRng) -> PyObject {
+        match self {
+            ParamDist::Choice(values) => values[rng.gen_range(0..values.len())].clone_ref(py),
+            ParamDist::IntRange(low, high) => rng.gen_range(*low..=*high).into_py(py),
+            ParamDist::FloatRange(low, high) => {
+                if low == high { low.into_py(py) } else { rng.gen_range(*low..*high).into_py(py) }
+            }
+        }
+    }
+}
+
+/// 随机搜索参数优化（支持预算与提前停止）
+///
+/// 从参数空间中随机采样至多 `n_trials` 组参数并回测；可选在指标连续 `patience` 次
+/// 没有提升时提前停止。返回完整的试验历史，便于事后分析参数敏感性。
+///
+/// ## 为什么需要这个函数？
+///
+/// 参数较多时网格的组合数呈指数增长，随机搜索用固定的预算就能较好地覆盖参数空间；
+/// 提前停止则避免在指标已经进入平台期后继续浪费时间。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import optimize_random
+///
+/// res = optimize_random(
+///     cfg,
+///     lambda fast, slow, stop: SmaCross(fast=fast, slow=slow, stop=stop),
+///     bars,
+///     {"fast": (5, 30), "slow": [60, 120, 250], "stop": (0.01, 0.05)},
+///     n_trials=200,
+///     seed=42,
+///     patience=40,
+/// )
+/// print(res["best"]["params"], len(res["trials"]), res["stopped_early"])
+/// ```
+///
+/// # 参数
+///
+/// - `engine_cfg`/`strategy_factory`/`bars`/`metric`/`ascending`/`indicators`: 同 `optimize_grid`
+/// - `param_space`: 字典 `{参数名: 分布}`；分布为候选值列表，或 `(low, high)` 区间
+///   （两端都是整数时在闭区间内取整数，否则取均匀浮点数）
+/// - `n_trials`: 试验预算（最多回测的次数）
+/// - `seed`: 随机种子；指定后采样结果可复现，未指定时使用 `engine_cfg.seed`
+/// - `patience`: 可选，指标连续这么多次试验没有提升时停止
+/// - `min_delta`: 判定"提升"所需的最小改进量，默认 0
+///
+/// # 返回值
+///
+/// 返回字典：
+/// - `trials`: 按执行顺序排列的全部试验，每个元素包含 `trial`（序号）、`params`、`stats`、`score`
+/// - `best`: 指标最优的试验（全部试验的指标都缺失时为 `None`）
+/// - `stopped_early`: 是否因提前停止而未用完预算
+///
+/// # 注意事项
+///
+/// - 试验逐个执行，提前停止后不会再采样或回测新的参数组合
+/// - 随机采样可能产生重复的参数组合
+#[pyfunction]
+#[pyo3(signature = (
+    engine_cfg, strategy_factory, bars, param_space, n_trials,
+    metric="sharpe", ascending=false, seed=None, patience=None, min_delta=0.0, indicators=None
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn optimize_random(
+    py: Python<'_>,
+    engine_cfg: BacktestConfig,
+    strategy_factory: PyObject,
+    bars: &Bound<'_, PyList>,
+    param_space: &Bound<'_, PyDict>,
+    n_trials: usize,
+    metric: &str,
+    ascending: bool,
+    seed: Option<u64>,
+    patience: Option<usize>,
+    min_delta: f64,
+    indicators: Option<&Bound<'_, PyList>>,
+) -> PyResult<PyObject> {
+    let mut keys = Vec::with_capacity(param_space.len());
+    let mut dists = Vec::with_capacity(param_space.len());
+    for (k, v) in param_space.iter() {
+        let name: String = k.extract()?;
+        dists.push(ParamDist::from_py(&name, &v)?);
+        keys.push(name);
+    }
+    let runner = TrialRunner::new(&engine_cfg, strategy_factory, bars, indicators, metric)?;
+    // 未单独指定 seed 时沿用引擎配置的种子
+    let mut rng = match seed {
+        Some(s) => StdRng::seed_from_u64(s),
+        None => engine_cfg.rng(),
+    };
+
+    let mut history: Vec<Trial> = Vec::with_capacity(n_trials);
+    let mut best_idx: Option<usize> = None;
+    let mut plateau_best = f64::NAN;
+    let mut since_improve = 0usize;
+    let mut stopped_early = false;
+
+    while history.len() < n_trials {
+        let values: Vec<PyObject> = dists.iter().map(|d| d.sample(py, &mut rng)).collect();
+        let trial = runner.run_one(&keys, &values)?;
+        if best_idx.map_or(!trial.score.is_nan(), |b| is_better(trial.score, history[b].score, ascending, 0.0)) {
+            best_idx = Some(history.len());
+        }
+        if is_better(trial.score, plateau_best, ascending, min_delta) {
+            plateau_best = trial.score;
+            since_improve = 0;
+        } else {
+            since_improve += 1;
+        }
+        history.push(trial);
+        if patience.is_some_and(|p| since_improve >= p) && history.len() < n_trials {
+            stopped_early = true;
+            break;
+        }
+    }
+
+    let trials = PyList::empty_bound(py);
+    for (i, t) in history.iter().enumerate() {
+        let row = trial_to_pydict(py, t)?;
+        row.set_item("trial", i)?;
+        trials.append(row)?;
+    }
+    let out = PyDict::new_bound(py);
+    out.set_item("trials", trials)?;
+    match best_idx {
+        Some(b) => out.set_item("best", trial_to_pydict(py, &history[b])?)?,
+        None => out.set_item("best", py.None())?,
+    }
+    out.set_item("stopped_early", stopped_early)?;
+    Ok(out.into())
+}
+
+// ============================================================================
+// Cross-Validation Splitters (purging + embargo)
+// ============================================================================
+
+/// 一次交叉验证划分：`(训练集 bar 索引, 测试集 bar 索引)`
+pub type CvSplit = (Vec<usize>, Vec<usize>);
+
+/// 把 `[0, n_bars)` 均分为 `n_groups` 个连续区间，前面的区间多分余数
+fn contiguous_groups(n_bars: usize, n_groups: usize) -> Vec<(usize, usize)> {
+    let base = n_bars / n_groups;
+    let rem = n_bars % n_groups;
+    let mut groups = Vec::with_capacity(n_groups);
+    let mut start = 0;
+    for g in 0..n_groups {
+        let len = base + usize::from(g < rem);
+        groups.push((start, start + len));
+        start += len;
+    }
+    groups
+}
+
+/// 给定测试区间，生成清洗（purge）与禁区（embargo）后的训练/测试索引
+///
+/// 每个测试区间 `[s, e)` 之前的 `purge` 根 bar 和之后的 `embargo` 根 bar 都不进入训练集。
+fn purged_split(n_bars: usize, test_blocks: &[(usize, usize)], purge: usize, embargo: usize) -> CvSplit {
+    let mut is_test = vec![false; n_bars];
+    let mut excluded = vec![false; n_bars];
+    for &(s, e) in test_blocks {
+        is_test[s..e].iter_mut().for_each(|x| *x = true);
+        excluded[s.saturating_sub(purge)..(e + embargo).min(n_bars)].iter_mut().for_each(|x| *x = true);
+    }
+    let train = (0..n_bars).filter(|&i| !excluded[i]).collect();
+    let test = (0..n_bars).filter(|&i| is_test[i]).collect();
+    (train, test)
+}
+
+fn check_cv_args(n_bars: usize, n_groups: usize) -> PyResult<()> {
+    if n_groups < 2 || n_groups > n_bars {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Number of folds must be between 2 and n_bars ({}), got {}",
+            n_bars, n_groups
+        )));
+    }
+    Ok(())
+}
+
+/// 清洗 K 折划分（Purged K-Fold）
+///
+/// 把 bar 序列按时间切成 `n_splits` 个连续的测试折，每折的训练集为其余 bar，
+/// 并去掉测试折前 `purge` 根、后 `embargo` 根 bar。
+///
+/// ## 为什么需要清洗和禁区？
+///
+/// 金融时间序列的标签通常依赖未来若干根 bar（如"持有 N 天后的收益"），
+/// 普通 K 折会让紧挨测试集的训练样本"看到"测试期的价格，造成信息泄漏：
+/// - **purge**：去掉测试集之前、标签窗口与测试期重叠的训练样本
+/// - **embargo**：去掉测试集之后的一段样本，避免序列相关性把测试期信息带回训练集
+pub fn purged_kfold(n_bars: usize, n_splits: usize, purge: usize, embargo: usize) -> Vec<CvSplit> {
+    contiguous_groups(n_bars, n_splits)
+        .into_iter()
+        .map(|block| purged_split(n_bars, &[block], purge, embargo))
+        .collect()
+}
+
+/// 组合清洗交叉验证划分（Combinatorial Purged CV）
+///
+/// 把 bar 序列切成 `n_groups` 个连续组，每次取其中 `n_test_groups` 个组作为测试集，
+/// 遍历所有 C(n_groups, n_test_groups) 种组合；训练集同样做清洗和禁区处理。
+/// 相比 K 折能得到更多条回测路径，用于评估参数的稳定性。
+pub fn combinatorial_purged(
+    n_bars: usize,
+    n_groups: usize,
+    n_test_groups: usize,
+    purge: usize,
+    embargo: usize,
+) -> Vec<CvSplit> {
+    let groups = contiguous_groups(n_bars, n_groups);
+    let mut splits = Vec::new();
+    // 按字典序枚举组合
+    let mut idx: Vec<usize> = (0..n_test_groups).collect();
+    loop {
+        let blocks: Vec<(usize, usize)> = idx.iter().map(|&g| groups[g]).collect();
+        splits.push(purged_split(n_bars, &blocks, purge, embargo));
+        let mut k = n_test_groups;
+        while k > 0 && idx[k - 1] == n_groups - n_test_groups + k - 1 {
+            k -= 1;
+        }
+        if k == 0 {
+            break;
+        }
+        idx[k - 1] += 1;
+        for j in k..n_test_groups {
+            idx[j] = idx[j - 1] + 1;
+        }
+    }
+    splits
+}
+
+/// 清洗 K 折划分（Python 接口）
+///
+/// ```python
+/// from engine_rust import purged_kfold_split, optimize_grid
+///
+/// for train_idx, test_idx in purged_kfold_split(len(bars), n_splits=5, purge=10, embargo=5):
+///     train_bars = [bars[i] for i in train_idx]
+///     table = optimize_grid(cfg, factory, train_bars, grid)
+///     ...  # 用 test_idx 对应的 bar 评估 table[0]["params"]
+/// ```
+///
+/// # 参数
+///
+/// - `n_bars`: bar 总数
+/// - `n_splits`: 折数，默认 5
+/// - `purge`: 每个测试折之前从训练集中去掉的 bar 数，通常取标签的前瞻长度
+/// - `embargo`: 每个测试折之后从训练集中去掉的 bar 数
+///
+/// # 返回值
+///
+/// 返回 `[(train_indices, test_indices), ...]`，索引均为升序
+///
+/// # 注意事项
+///
+/// - 训练集由测试折两侧的 bar 拼接而成，中间不连续；对依赖连续序列的策略，
+///   可以只取测试折之前的部分做前向验证
+/// - `n_splits` 必须在 2 到 `n_bars` 之间，否则返回 `ValueError`
+#[pyfunction]
+#[pyo3(signature = (n_bars, n_splits=5, purge=0, embargo=0))]
+pub fn purged_kfold_split(n_bars: usize, n_splits: usize, purge: usize, embargo: usize) -> PyResult<Vec<CvSplit>> {
+    check_cv_args(n_bars, n_splits)?;
+    Ok(purged_kfold(n_bars, n_splits, purge, embargo))
+}
+
+/// 组合清洗交叉验证划分（Python 接口）
+///
+/// ```python
+/// from engine_rust import combinatorial_purged_split
+///
+/// # 6 组取 2 组做测试：共 C(6, 2) = 15 种划分
+/// splits = combinatorial_purged_split(len(bars), n_groups=6, n_test_groups=2, purge=10, embargo=5)
+/// ```
+///
+/// # 参数
+///
+/// - `n_bars`: bar 总数
+/// - `n_groups`: 分组数，默认 6
+/// - `n_test_groups`: 每次作为测试集的组数，默认 2
+/// - `purge` / `embargo`: 同 `purged_kfold_split`
+///
+/// # 返回值
+///
+/// 返回 `[(train_indices, test_indices), ...]`，按测试组合的字典序排列
+///
+/// # 注意事项
+///
+/// - `n_test_groups` 必须在 1 到 `n_groups - 1` 之间，否则返回 `ValueError`
+#[pyfunction]
+#[pyo3(signature = (n_bars, n_groups=6, n_test_groups=2, purge=0, embargo=0))]
+pub fn combinatorial_purged_split(
+    n_bars: usize,
+    n_groups: usize,
+    n_test_groups: usize,
+    purge: usize,
+    embargo: usize,
+) -> PyResult<Vec<CvSplit>> {
+    check_cv_args(n_bars, n_groups)?;
+    if n_test_groups == 0 || n_test_groups >= n_groups {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "n_test_groups must be between 1 and n_groups - 1 ({}), got {}",
+            n_groups - 1,
+            n_test_groups
+        )));
+    }
+    Ok(combinatorial_purged(n_bars, n_groups, n_test_groups, purge, embargo))
+}
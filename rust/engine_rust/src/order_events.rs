@@ -0,0 +1,150 @@
+//! 订单事件原因模块
+//!
+//! 订单没有成交时，策略原先只能从 `event` 猜原因：时段外被拒绝的事件带了 `reason`，限价单没有触及
+//! 限价则不产生任何事件，回测结束时没来得及执行的顺延订单也被悄悄丢弃。这个模块统一定义这些情况的
+//! 原因代码，所有未成交的订单事件都带上机器可读的 `reason` 和一句说明 `message`。
+//!
+//! ## 事件与原因
+//!
+//! | `event` | `reason` | 场景 |
+//! |---------|----------|------|
+//! | `rejected` | `outside_session` | 交易时段外下单，`session_policy="reject"` |
+//! | `deferred` | `outside_session` | 交易时段外下单，`session_policy="defer"`，顺延到下一个时段 |
+//! | `rejected` | `invalid_size` | 订单数量不是正数（0、负数或 NaN） |
+//! | `expired` | `limit_not_reached` | 限价单在当前 bar 没有触及限价，随 bar 结束失效 |
+//! | `cancelled` | `run_ended` | 回测结束时仍在等待的顺延订单 |
+//! | `clipped` | `exposure_limit` | 成交后超出敞口限制，截断为 `size` 成交（原数量 `requested_size`） |
+//! | `rejected` | `exposure_limit` | 成交后超出敞口限制，`exposure_policy="reject"` 或截断后数量为 0 |
+//! | `rejected` | `vol_target` | 波动率目标缩放后当前持仓已达到目标，订单不再需要（或不足 1 股） |
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! class MyStrategy(Strategy):
+//!     def on_order(self, event):
+//!         if event["event"] in ("rejected", "expired", "cancelled"):
+//!             self.missed[event["reason"]] += 1
+//!             print(event["message"])   # "order 12 rejected: bar is outside the trading sessions"
+//! ```
+//!
+//! # 注意事项
+//!
+//! - `reason` 是稳定的代码，适合程序判断；`message` 只用于日志展示，措辞可能调整
+//! - `exposure_limit` 事件另带 `limit`（`"gross"`/`"net"`）、`max_exposure`（净值的倍数）、`requested_size` 和 `size`
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::exposure::ExposureBreach;
+use crate::{Order, OrderSide};
+
+/// 订单未成交（或未全部成交）的原因
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum OrderReason {
+    /// 交易时段外（拒绝或顺延）
+    OutsideSession,
+    /// 订单数量不是正数
+    InvalidSize,
+    /// 限价单在当前 bar 没有触及限价
+    LimitNotReached,
+    /// 回测结束时仍未执行的顺延订单
+    RunEnded,
+    /// 成交后超出组合敞口限制（截断或拒绝）
+    ExposureLimit(ExposureBreach),
+    /// 波动率目标缩放后不再需要该订单
+    VolTarget,
+}
+
+impl OrderReason {
+    /// 机器可读的原因代码
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            OrderReason::OutsideSession => "outside_session",
+            OrderReason::InvalidSize => "invalid_size",
+            OrderReason::LimitNotReached => "limit_not_reached",
+            OrderReason::RunEnded => "run_ended",
+            OrderReason::ExposureLimit(_) => "exposure_limit",
+            OrderReason::VolTarget => "vol_target",
+        }
+    }
+
+    /// 说明文字；`price` 为判断时的价格（限价单未触及限价时使用）
+    fn message(self, event: &str, order: &Order, price: Option<f64>) -> String {
+        match self {
+            OrderReason::OutsideSession if event == "deferred" => {
+                format!("order {} deferred to the first bar inside the trading sessions", order.id)
+            }
+            OrderReason::OutsideSession => {
+                format!("order {} rejected: bar is outside the trading sessions", order.id)
+            }
+            OrderReason::InvalidSize => {
+                format!("order {} rejected: size must be a positive number, got {}", order.id, order.size)
+            }
+            OrderReason::LimitNotReached => format!(
+                "limit {} order {} at {} expired: price {} did not reach the limit",
+                match order.side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" },
+                order.id,
+                order.limit_price.or(price).unwrap_or(f64::NAN),
+                price.unwrap_or(f64::NAN)
+            ),
+            OrderReason::RunEnded => {
+                format!("deferred order {} cancelled: the run ended before the next trading session", order.id)
+            }
+            OrderReason::ExposureLimit(b) if event == "clipped" => format!(
+                "order {} clipped from {} to {}: {} exposure would exceed {}x equity",
+                order.id,
+                b.requested,
+                b.allowed,
+                b.kind.name(),
+                b.limit
+            ),
+            OrderReason::ExposureLimit(b) => format!(
+                "order {} rejected: {} exposure would exceed {}x equity",
+                order.id,
+                b.kind.name(),
+                b.limit
+            ),
+            OrderReason::VolTarget => format!(
+                "order {} rejected: the position already meets the volatility-targeted size",
+                order.id
+            ),
+        }
+    }
+}
+
+/// 构造未成交的订单事件：`event`、`order_id`、`symbol`、`reason`、`message`
+pub(crate) fn reason_event<'py>(
+    py: Python<'py>,
+    event: &str,
+    order: &Order,
+    reason: OrderReason,
+    price: Option<f64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let evt = PyDict::new_bound(py);
+    evt.set_item("event", event)?;
+    evt.set_item("order_id", order.id)?;
+    evt.set_item("symbol", &order.symbol)?;
+    evt.set_item("reason", reason.code())?;
+    evt.set_item("message", reason.message(event, order, price))?;
+    if let OrderReason::ExposureLimit(b) = reason {
+        evt.set_item("limit", b.kind.name())?;
+        evt.set_item("max_exposure", b.limit)?;
+        evt.set_item("requested_size", b.requested)?;
+        evt.set_item("size", b.allowed)?;
+    }
+    Ok(evt)
+}
+
+/// 构造未成交事件并调用策略的 `on_order`（多资产回测使用；与其他回调一样忽略 `on_order` 抛出的异常）
+pub(crate) fn notify(
+    py: Python<'_>,
+    strategy: &PyObject,
+    event: &str,
+    order: &Order,
+    reason: OrderReason,
+    price: Option<f64>,
+) -> PyResult<()> {
+    let evt = reason_event(py, event, order, reason, price)?;
+    let _ = strategy.call_method1(py, "on_order", (evt,));
+    Ok(())
+}
@@ -0,0 +1,204 @@
+//! 策略组合模块（fund-of-strategies）
+//!
+//! 把多个子回测（通常是不同策略）的净值曲线按资金分配权重合成为一个组合，
+//! 并计算组合层面的统计指标，相当于"策略的基金"视角。
+//!
+//! ## 为什么需要这个模块？
+//!
+//! 单个策略的夏普比率和回撤并不能说明把几个策略放在一起之后的表现：
+//! 低相关的策略组合后波动和回撤往往明显下降。在 Python 中对齐多条净值曲线、
+//! 按权重加总、处理再平衡既繁琐又容易出错（比如直接对净值加权平均会忽略权重漂移）。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 把所有子回测的净值曲线按 `datetime` 对齐到联合时间线
+//! 2. 每个策略是组合中的一个"子账户"，初始资金 = 总资金 × 权重
+//! 3. 每个时间点，子账户按该策略当期收益率增长；策略在该时间点没有数据（或尚未开始）时收益为 0
+//! 4. 如果配置了再平衡频率，在每个周期末把各子账户重新调整回目标权重；
+//!    否则权重随各策略表现自然漂移（买入持有）
+//! 5. 组合净值 = 各子账户之和，并用与 `run()` 相同的方法计算统计指标
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::calendar::TradingCalendar;
+use crate::database::parse_datetime;
+use crate::schedule::RebalanceFreq;
+use crate::BacktestEngine;
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 读取一条净值曲线：接受 `run()` 的结果字典（取 `equity_curve`）或净值曲线列表本身
+fn extract_equity_curve(name: &str, obj: &Bound<'_, PyAny>) -> PyResult<Vec<(String, f64)>> {
+    let curve = match obj.downcast::<PyDict>() {
+        Ok(d) => d
+            .get_item("equity_curve")?
+            .ok_or_else(|| value_error(format!("Result for '{}' has no equity_curve", name)))?
+            .downcast_into::<PyList>()?,
+        Err(_) => obj.downcast::<PyList>()?.clone(),
+    };
+    let mut out = Vec::with_capacity(curve.len());
+    for row in curve.iter() {
+        let dt: Option<String> = row.get_item("datetime")?.extract()?;
+        let dt = dt.ok_or_else(|| value_error(format!("Equity curve of '{}' has a row without datetime", name)))?;
+        let eq: f64 = row.get_item("equity")?.extract()?;
+        out.push((dt, eq));
+    }
+    Ok(out)
+}
+
+/// 合成策略组合
+///
+/// 按资金分配权重把多个子回测合成为一个组合，可选地定期再平衡，返回组合净值曲线和统计指标。
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// report = engine.run_many({"trend": Trend(), "meanrev": MeanRev()}, bars)
+/// combo = combine_strategies(
+///     report["results"],
+///     weights={"trend": 0.6, "meanrev": 0.4},
+///     rebalance="monthly",
+/// )
+/// print(combo["stats"]["sharpe"], combo["final_weights"])
+/// ```
+///
+/// # 参数
+///
+/// - `results`: 字典 `{名称: run() 结果字典或净值曲线列表}`；净值曲线每行需包含 `datetime` 与 `equity`
+/// - `weights`: 可选的资金分配权重 `{名称: 权重}`，会被归一化；默认等权
+/// - `rebalance`: 可选的再平衡频率 `"daily"`/`"weekly"`/`"monthly"`/`"quarterly"`；默认不再平衡
+/// - `initial_capital`: 组合初始资金，默认 1.0（即输出单位净值）
+/// - `calendar`: 可选的交易日历（名称或 `TradingCalendar`），统计指标按该日历的年化天数年化；默认 252
+///
+/// # 返回值
+///
+/// 返回字典：
+/// - `equity_curve`: 组合净值曲线（每个元素包含 `datetime` 和 `equity`）
+/// - `stats`: 组合统计指标（与 `run()` 的 `stats` 相同的字段，交易相关字段为 0）
+/// - `final_weights`: 期末各策略占组合的实际权重
+/// - `rebalance_count`: 实际执行的再平衡次数
+///
+/// # 注意事项
+///
+/// - 对齐按 `datetime` 字符串进行，各子回测的时间格式需一致
+/// - 权重必须非负且总和大于 0；`weights` 中的名称必须都出现在 `results` 中，
+///   `results` 中未出现在 `weights` 里的策略权重为 0
+/// - 再平衡假设子账户之间可以无成本划转资金
+#[pyfunction]
+#[pyo3(signature = (results, weights=None, rebalance=None, initial_capital=1.0, calendar=None))]
+pub fn combine_strategies(
+    py: Python<'_>,
+    results: &Bound<'_, PyDict>,
+    weights: Option<&Bound<'_, PyDict>>,
+    rebalance: Option<&str>,
+    initial_capital: f64,
+    calendar: Option<&Bound<'_, PyAny>>,
+) -> PyResult<PyObject> {
+    if results.is_empty() {
+        return Err(value_error("results must not be empty".to_string()));
+    }
+    let freq = match rebalance {
+        Some(f) => Some(RebalanceFreq::parse(f).ok_or_else(|| {
+            value_error(format!("Unsupported rebalance frequency: {} (expected daily/weekly/monthly/quarterly)", f))
+        })?),
+        None => None,
+    };
+    let periods_per_year = calendar.map(TradingCalendar::from_py).transpose()?.map_or(252.0, |cal| cal.days_per_year());
+
+    let mut names: Vec<String> = Vec::with_capacity(results.len());
+    let mut curves: Vec<Vec<(String, f64)>> = Vec::with_capacity(results.len());
+    for (k, v) in results.iter() {
+        let name: String = k.extract()?;
+        curves.push(extract_equity_curve(&name, &v)?);
+        names.push(name);
+    }
+
+    // 目标权重：默认等权，归一化到总和为 1
+    let mut target = vec![1.0; names.len()];
+    if let Some(w) = weights {
+        target = vec![0.0; names.len()];
+        for (k, v) in w.iter() {
+            let name: String = k.extract()?;
+            let idx = names
+                .iter()
+                .position(|n| *n == name)
+                .ok_or_else(|| value_error(format!("Weight given for unknown strategy '{}'", name)))?;
+            let weight: f64 = v.extract()?;
+            if weight.is_nan() || weight < 0.0 {
+                return Err(value_error(format!("Weight for '{}' must be non-negative, got {}", name, weight)));
+            }
+            target[idx] = weight;
+        }
+    }
+    let total_weight: f64 = target.iter().sum();
+    if total_weight.is_nan() || total_weight <= 0.0 {
+        return Err(value_error("Weights must sum to a positive value".to_string()));
+    }
+    target.iter_mut().for_each(|w| *w /= total_weight);
+
+    // 联合时间线（与 run_multi 相同，按 datetime 字符串排序）
+    let mut timeline: Vec<&str> = curves.iter().flat_map(|c| c.iter().map(|(dt, _)| dt.as_str())).collect();
+    timeline.sort_unstable();
+    timeline.dedup();
+    let lookups: Vec<HashMap<&str, f64>> = curves
+        .iter()
+        .map(|c| c.iter().map(|(dt, eq)| (dt.as_str(), *eq)).collect())
+        .collect();
+    let dates: Vec<Option<NaiveDate>> = timeline.iter().map(|dt| parse_datetime(dt).map(|d| d.date())).collect();
+
+    let mut sleeves: Vec<f64> = target.iter().map(|w| w * initial_capital).collect();
+    let mut last_equity: Vec<Option<f64>> = vec![None; names.len()];
+    let mut equity_curve: Vec<(Option<String>, f64)> = Vec::with_capacity(timeline.len());
+    let mut rebalance_count = 0usize;
+
+    for (t, dt) in timeline.iter().enumerate() {
+        for (i, lookup) in lookups.iter().enumerate() {
+            if let Some(&eq) = lookup.get(dt) {
+                if let Some(prev) = last_equity[i] {
+                    if prev != 0.0 {
+                        sleeves[i] *= eq / prev;
+                    }
+                }
+                last_equity[i] = Some(eq);
+            }
+        }
+        let total: f64 = sleeves.iter().sum();
+        equity_curve.push((Some(dt.to_string()), total));
+
+        // 周期末再平衡回目标权重（最后一个时间点不需要）
+        if let (Some(freq), Some(cur)) = (freq, dates[t]) {
+            let next = dates[t + 1..].iter().flatten().next().copied();
+            if next.is_some() && freq.is_period_end(cur, next) {
+                for (s, w) in sleeves.iter_mut().zip(&target) {
+                    *s = total * w;
+                }
+                rebalance_count += 1;
+            }
+        }
+    }
+
+    let out = PyDict::new_bound(py);
+    let eq_list = PyList::empty_bound(py);
+    for (dt, eq) in &equity_curve {
+        let row = PyDict::new_bound(py);
+        row.set_item("datetime", dt)?;
+        row.set_item("equity", eq)?;
+        eq_list.append(row)?;
+    }
+    out.set_item("equity_curve", eq_list)?;
+    out.set_item("stats", BacktestEngine::compute_enhanced_stats(py, &equity_curve, &[], periods_per_year)?)?;
+    let total: f64 = sleeves.iter().sum();
+    let final_weights = PyDict::new_bound(py);
+    for (name, s) in names.iter().zip(&sleeves) {
+        final_weights.set_item(name, if total != 0.0 { s / total } else { 0.0 })?;
+    }
+    out.set_item("final_weights", final_weights)?;
+    out.set_item("rebalance_count", rebalance_count)?;
+    Ok(out.into())
+}
@@ -0,0 +1,68 @@
+//! 持仓历史模块
+//!
+//! 回测结果只保留每根 bar 的净值和逐笔成交：多资产回测结束后，想知道"某一天各标的各占多少敞口"，
+//! 只能从成交记录一笔笔重放持仓，再配上当时的价格。`BacktestConfig(record_positions=True)` 开启后，
+//! 引擎在每根 bar（多资产回测为联合时间线的每个时间点）结束时记录一份持仓快照，放在结果的
+//! `positions_history` 中。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 每根 bar 的订单全部处理完、净值记录之后，对每个持仓不为 0 的标的记录一行：
+//!    `datetime`、`symbol`、`position`、`price`（估值所用的最新价格）、`market_value`（`position * price`，空头为负）
+//! 2. 空仓的时间点没有行，按时间透视后缺失值即为 0
+//! 3. `result.positions_df()` 按列构建表格（与 `equity_curve_df()` 相同的 `backend` 参数）
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! cfg = BacktestConfig("2020-01-01", "2024-12-31", 1_000_000, record_positions=True)
+//! result = BacktestEngine(cfg).run_multi(MyStrategy(), feeds)
+//!
+//! exposure = result.positions_df().pivot(index="datetime", columns="symbol", values="market_value").fillna(0)
+//! gross = exposure.abs().sum(axis=1)
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 默认不记录：持仓很多、bar 很多时行数为二者之积
+//! - 适用于 `run()`、`run_from_db()`、`run_multi()`、`PaperTrader` 和 `Replayer`；单资产的 `symbol` 取 bar 中的
+//!   `symbol`，没有时为 `"DEFAULT"`（与订单一致）
+//! - 预热期不记录（与净值曲线一致）
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde::{Deserialize, Serialize};
+
+/// 持仓表的列（与 `positions_history` 元素的键相同）
+pub(crate) const POSITION_COLUMNS: [&str; 5] = ["datetime", "symbol", "position", "price", "market_value"];
+
+/// 某个时间点一个标的的持仓
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PositionRow {
+    pub(crate) datetime: Option<String>,
+    pub(crate) symbol: String,
+    pub(crate) position: f64,
+    pub(crate) price: f64,
+}
+
+impl PositionRow {
+    fn to_pydict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let row = PyDict::new_bound(py);
+        row.set_item("datetime", self.datetime.as_deref())?;
+        row.set_item("symbol", &self.symbol)?;
+        row.set_item("position", self.position)?;
+        row.set_item("price", self.price)?;
+        row.set_item("market_value", self.position * self.price)?;
+        Ok(row)
+    }
+}
+
+/// 把持仓历史写入结果（`positions_history`）
+pub(crate) fn mark_result(py: Python<'_>, result: &PyObject, rows: &[PositionRow]) -> PyResult<()> {
+    let list = PyList::empty_bound(py);
+    for row in rows {
+        list.append(row.to_pydict(py)?)?;
+    }
+    result.downcast_bound::<PyDict>(py)?.set_item("positions_history", list)?;
+    Ok(())
+}
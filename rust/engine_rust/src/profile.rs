@@ -0,0 +1,96 @@
+//! 回测耗时分析模块
+//!
+//! 回测慢的时候，用户很难判断瓶颈是自己的策略代码还是引擎本身：Python 的 `cProfile`
+//! 只能看到一次 `run()` 调用，看不到 Rust 内部的时间分布。
+//! 开启 `BacktestConfig(profile=True)` 后，`run()` 的结果中会附带 `profile` 字典。
+//!
+//! ## 统计口径
+//!
+//! - `data_conversion`: 数据转换，包括输入数据提取到 Rust，以及逐 bar 构造传给策略的 bar 字典与上下文
+//! - `indicators`: `indicators` 参数的指标预计算
+//! - `strategy`: Python 策略回调（`next`、`on_order`、`on_trade`、`on_rebalance`、`on_bar_end`、
+//!   `on_session_end`）内部耗时
+//! - `engine`: 主循环中除以上两项外的时间（订单解析、撮合、持仓与净值更新）
+//! - `result`: 构建结果字典与统计指标
+//! - `total`: 以上各项之和
+//!
+//! 时间单位均为秒；另附 `strategy_calls`（策略回调次数）、`bars`（处理的 bar 数）
+//! 和 `bottleneck`（`data_conversion`/`strategy`/`engine` 中耗时最多的一项）。
+//!
+//! # 注意事项
+//!
+//! - 计时本身有少量开销（每次回调两次 `Instant::now()`），默认关闭
+//! - 策略回调中的时间包含回调内部再调用的 Rust 函数（如 `compute_sma`）
+
+use std::time::{Duration, Instant};
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// 一次回测的耗时累计
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RunProfile {
+    pub(crate) extraction: Duration,
+    pub(crate) indicators: Duration,
+    /// 逐 bar 的 Rust → Python 转换
+    pub(crate) conversion: Duration,
+    pub(crate) strategy: Duration,
+    pub(crate) strategy_calls: u64,
+    /// 主循环总耗时（包含 `conversion` 与 `strategy`）
+    pub(crate) main_loop: Duration,
+    pub(crate) result: Duration,
+    pub(crate) bars: usize,
+}
+
+impl RunProfile {
+    /// 计时一次 Python 策略回调
+    pub(crate) fn strategy_call<T>(profile: &mut Option<RunProfile>, f: impl FnOnce() -> T) -> T {
+        match profile {
+            Some(p) => {
+                let started = Instant::now();
+                let out = f();
+                p.strategy += started.elapsed();
+                p.strategy_calls += 1;
+                out
+            }
+            None => f(),
+        }
+    }
+
+    /// 计时一段数据转换
+    pub(crate) fn conversion<T>(profile: &mut Option<RunProfile>, f: impl FnOnce() -> T) -> T {
+        match profile {
+            Some(p) => {
+                let started = Instant::now();
+                let out = f();
+                p.conversion += started.elapsed();
+                out
+            }
+            None => f(),
+        }
+    }
+
+    /// 写入结果字典的 `profile` 字段
+    pub(crate) fn write_to(&self, py: Python<'_>, result: &PyObject) -> PyResult<()> {
+        let engine = self.main_loop.saturating_sub(self.conversion + self.strategy);
+        let data_conversion = self.extraction + self.conversion;
+        let total = data_conversion + self.indicators + self.strategy + engine + self.result;
+
+        let d = PyDict::new_bound(py);
+        d.set_item("data_conversion", data_conversion.as_secs_f64())?;
+        d.set_item("indicators", self.indicators.as_secs_f64())?;
+        d.set_item("strategy", self.strategy.as_secs_f64())?;
+        d.set_item("engine", engine.as_secs_f64())?;
+        d.set_item("result", self.result.as_secs_f64())?;
+        d.set_item("total", total.as_secs_f64())?;
+        d.set_item("strategy_calls", self.strategy_calls)?;
+        d.set_item("bars", self.bars)?;
+        let bottleneck = [("data_conversion", data_conversion), ("strategy", self.strategy), ("engine", engine)]
+            .into_iter()
+            .max_by_key(|(_, t)| *t)
+            .map(|(name, _)| name);
+        d.set_item("bottleneck", bottleneck)?;
+        result.downcast_bound::<PyDict>(py)?.set_item("profile", d)?;
+        Ok(())
+    }
+}
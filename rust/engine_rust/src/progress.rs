@@ -0,0 +1,96 @@
+//! 回测进度回调模块
+//!
+//! 百万级 bar 的回测可能要跑几分钟，期间 Python 端没有任何输出，看起来就像"卡住了"。
+//! 这个模块让 `run()`/`run_multi()` 每处理 N 根 bar 向 Python 汇报一次进度。
+//!
+//! ## 支持两种回调形式
+//!
+//! - **普通函数**：`progress_callback(done, total, elapsed)`，`elapsed` 为已用秒数
+//! - **tqdm 风格对象**：不可调用但有 `update(n)` 方法的对象（如 `tqdm.tqdm()`），
+//!   引擎会设置其 `total` 属性并按增量调用 `update(n)`
+//!
+//! ```python
+//! from tqdm import tqdm
+//!
+//! engine.run(strategy, bars, progress_callback=lambda d, t, s: print(f"{d}/{t} {s:.1f}s"))
+//! engine.run(strategy, bars, progress_callback=tqdm(), progress_every=10_000)
+//! ```
+
+use std::time::Instant;
+
+use pyo3::prelude::*;
+
+/// 进度汇报器
+///
+/// 由调用方在主循环中调用 `tick(done)`，达到汇报间隔时才真正回调 Python，
+/// 因此未达到间隔时的开销只是一次整数比较。
+pub(crate) struct ProgressReporter {
+    callback: PyObject,
+    /// 是否为 tqdm 风格对象（调用 `update(n)` 而不是 `callback(done, total, elapsed)`）
+    tqdm_like: bool,
+    every: usize,
+    total: usize,
+    reported: usize,
+    started: Instant,
+}
+
+impl ProgressReporter {
+    /// 创建进度汇报器；`callback` 为 `None` 时返回 `None`
+    ///
+    /// `every` 为 0 时自动取总量的 1%（至少 1 根 bar）。`total` 为 0 表示总量未知（流式数据），
+    /// 此时调用方应显式指定 `every`，并在结束时通过 `set_total` 补上总量。
+    pub(crate) fn new(py: Python<'_>, callback: Option<PyObject>, every: usize, total: usize) -> PyResult<Option<Self>> {
+        let Some(callback) = callback else { return Ok(None) };
+        let obj = callback.bind(py);
+        let tqdm_like = if obj.is_callable() {
+            false
+        } else if obj.hasattr("update")? {
+            // tqdm 的 total 可写；其他对象不支持时忽略（总量未知时不设置）
+            if total > 0 {
+                let _ = obj.setattr("total", total);
+            }
+            true
+        } else {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "progress_callback must be callable(done, total, elapsed) or have an update(n) method",
+            ));
+        };
+        let every = if every == 0 { (total / 100).max(1) } else { every };
+        Ok(Some(Self { callback, tqdm_like, every, total, reported: 0, started: Instant::now() }))
+    }
+
+    /// 已处理 `done` 根 bar；距上次汇报不少于 `every` 根时回调 Python
+    pub(crate) fn tick(&mut self, py: Python<'_>, done: usize) -> PyResult<()> {
+        if done >= self.reported + self.every {
+            self.report(py, done)?;
+        }
+        Ok(())
+    }
+
+    /// 设置总量（流式数据在数据源耗尽后才知道总 bar 数）
+    pub(crate) fn set_total(&mut self, py: Python<'_>, total: usize) {
+        self.total = total;
+        if self.tqdm_like {
+            let _ = self.callback.bind(py).setattr("total", total);
+        }
+    }
+
+    /// 回测结束时汇报最终进度（若尚未汇报到总量）
+    pub(crate) fn finish(&mut self, py: Python<'_>) -> PyResult<()> {
+        if self.reported < self.total {
+            self.report(py, self.total)?;
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, py: Python<'_>, done: usize) -> PyResult<()> {
+        if self.tqdm_like {
+            self.callback.call_method1(py, "update", (done - self.reported,))?;
+        } else {
+            let elapsed = self.started.elapsed().as_secs_f64();
+            self.callback.call1(py, (done, self.total, elapsed))?;
+        }
+        self.reported = done;
+        Ok(())
+    }
+}
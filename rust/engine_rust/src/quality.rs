@@ -0,0 +1,165 @@
+//! 数据质量统计模块
+//!
+//! 回测之前需要先筛掉数据有问题的标的：缺了很多 bar、大段零成交量、重复时间戳、
+//! 明显错误的价格跳变都会让回测结果失真。`find_gaps()` 只列出缺口位置，
+//! 这个模块在一次扫描中汇总各类问题的数量，便于批量比较、筛选标的。
+//!
+//! ## 统计项
+//!
+//! | 字段 | 说明 |
+//! |------|------|
+//! | `gaps` / `missing_bars` | 缺口数量与估算的缺失 bar 数（规则同 `find_gaps()`） |
+//! | `missing_days` | 传入交易日历时，首尾之间没有任何数据的交易日数 |
+//! | `zero_volume` | 成交量为 0 的 bar 数 |
+//! | `duplicates` | 重复时间戳的行数（缺少唯一索引的旧表才可能出现） |
+//! | `price_jumps` / `jumps` | 相邻收盘价变动幅度超过 `max_jump` 的次数及明细 |
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import data_quality_report
+//!
+//! for symbol in symbols:
+//!     q = data_quality_report("data/backtest.db", symbol, "1d", calendar="SSE", max_jump=0.11)
+//!     if q["missing_days"] > 5 or q["price_jumps"] > 0:
+//!         print(symbol, q["missing_days"], q["jumps"][:3])
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 价格跳变按原始价格计算，未复权数据在除权日会出现跳变，可以结合 `get_corporate_actions()` 排除
+//! - 统计需要读出该标的全部数据，数据量很大时耗时与 `get_market_data()` 相当
+
+use chrono::NaiveDateTime;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::database::{load_klines_rust, parse_datetime, GapRule};
+
+/// 缺口中估算的缺失 bar 数（至少为 1）
+fn missing_in_gap(prev: NaiveDateTime, next: NaiveDateTime, step: i64) -> usize {
+    let periods = ((next - prev).num_minutes() as f64 / step as f64).round() as usize;
+    periods.saturating_sub(1).max(1)
+}
+
+/// 统计某个标的的数据质量
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import data_quality_report
+///
+/// q = data_quality_report("data/backtest.db", "000001.SZ", "1d", calendar="SSE")
+/// # {"symbol": "000001.SZ", "period": "1d", "rows": 2430, "first": "2014-01-02 00:00:00",
+/// #  "last": "2023-12-29 00:00:00", "gaps": 2, "missing_bars": 3, "missing_days": 3, "zero_volume": 41, "duplicates": 0,
+/// #  "price_jumps": 1, "jumps": [{"datetime": "2015-07-09 00:00:00", "prev_close": 9.8, "close": 12.1, "change": 0.2347}]}
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `symbol`: 交易标的代码
+/// - `period`: 周期字符串（如 "1m", "1d"）
+/// - `calendar`: 可选的交易日列表、内置交易日历名称或 `TradingCalendar`（同 `find_gaps()`）
+/// - `max_jump`: 价格跳变阈值，相邻收盘价的变动幅度（`|close / prev_close - 1|`）超过该值时计为跳变，默认 0.2
+///
+/// # 返回值
+///
+/// 字典：`symbol`、`period`、`rows`、`first`、`last`、`gaps`、`missing_bars`、`missing_days`、`zero_volume`、
+/// `duplicates`、`price_jumps` 和 `jumps`（每次跳变的 `datetime`、`prev_close`、`close`、`change`）
+///
+/// # 注意事项
+///
+/// - 不传日历时 `missing_bars` 按时间差除以周期估算，日内数据的隔夜、周末也会计入；`missing_days` 为 `None`
+/// - 传入日历时，日线的 `missing_bars` 等于 `missing_days`；日内周期的 `missing_bars` 只统计交易日内的缺失，
+///   整天缺失的数据计入 `missing_days`
+/// - 周期无法识别、日历日期无法解析或 `max_jump` 不为正数时返回 `ValueError`
+#[pyfunction]
+#[pyo3(signature = (db_path, symbol, period, calendar=None, max_jump=0.2))]
+pub fn data_quality_report(
+    py: Python<'_>,
+    db_path: String,
+    symbol: String,
+    period: String,
+    calendar: Option<&Bound<'_, PyAny>>,
+    max_jump: f64,
+) -> PyResult<PyObject> {
+    if !(max_jump.is_finite() && max_jump > 0.0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "max_jump must be a positive number, got {}",
+            max_jump
+        )));
+    }
+    let mut rule = GapRule::new(&period, calendar)?;
+    let bars = load_klines_rust(&db_path, &symbol, &period, None, None, -1)?;
+    let times: Vec<NaiveDateTime> = bars.iter().filter_map(|b| parse_datetime(&b.datetime)).collect();
+    if let (Some(first), Some(last)) = (times.first(), times.last()) {
+        rule.resolve_days(first.date(), last.date());
+    }
+
+    let mut gaps = 0;
+    let mut missing_bars = 0;
+    let mut duplicates = 0;
+    for pair in times.windows(2) {
+        let (prev, next) = (pair[0], pair[1]);
+        if next == prev {
+            duplicates += 1;
+        } else if rule.is_gap(prev, next) {
+            gaps += 1;
+            // 有日历时跨日的缺口按缺失的交易日统计（日线即缺失的 bar 数）
+            if rule.days().is_none() || prev.date() == next.date() {
+                missing_bars += missing_in_gap(prev, next, rule.step);
+            }
+        }
+    }
+
+    // 首尾之间没有任何数据的交易日
+    let missing_days = rule.days().map(|days| {
+        let mut dates: Vec<_> = times.iter().map(|dt| dt.date()).collect();
+        dates.dedup();
+        match (dates.first(), dates.last()) {
+            (Some(first), Some(last)) => days
+                .iter()
+                .filter(|d| *d >= first && *d <= last && dates.binary_search(d).is_err())
+                .count(),
+            _ => 0,
+        }
+    });
+    if rule.step >= 1440 {
+        if let Some(days) = missing_days {
+            missing_bars = days;
+        }
+    }
+
+    let jumps = PyList::empty_bound(py);
+    for pair in bars.windows(2) {
+        let (prev, bar) = (&pair[0], &pair[1]);
+        if prev.close <= 0.0 {
+            continue;
+        }
+        let change = bar.close / prev.close - 1.0;
+        if change.abs() > max_jump {
+            let jump = PyDict::new_bound(py);
+            jump.set_item("datetime", &bar.datetime)?;
+            jump.set_item("prev_close", prev.close)?;
+            jump.set_item("close", bar.close)?;
+            jump.set_item("change", change)?;
+            jumps.append(jump)?;
+        }
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("symbol", &symbol)?;
+    report.set_item("period", &period)?;
+    report.set_item("rows", bars.len())?;
+    report.set_item("first", bars.first().map(|b| b.datetime.as_str()))?;
+    report.set_item("last", bars.last().map(|b| b.datetime.as_str()))?;
+    report.set_item("gaps", gaps)?;
+    report.set_item("missing_bars", missing_bars)?;
+    report.set_item("missing_days", missing_days)?;
+    report.set_item("zero_volume", bars.iter().filter(|b| b.volume == 0.0).count())?;
+    report.set_item("duplicates", duplicates)?;
+    report.set_item("price_jumps", jumps.len())?;
+    report.set_item("jumps", jumps)?;
+    Ok(report.into())
+}
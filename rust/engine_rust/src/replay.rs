@@ -0,0 +1,313 @@
+//! 回测回放模块
+//!
+//! `run()` 一次性跑完整个回测，只能看到最终结果；想知道"第 1234 根 bar 时策略为什么开仓"，
+//! 通常只能在策略里加打印再重跑。`Replayer` 按需逐根（或逐笔成交）推进回测，
+//! 每一步都可以查看引擎状态，适合调试策略和制作可视化回放工具。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 用与 `run()` 相同的数据、配置和指标构造 `Replayer`，此时调用策略的 `on_start(ctx)`
+//! 2. 每次 `step()` 都使用与 `run()` 相同的单根 bar 处理逻辑（撮合、调仓、生命周期钩子），
+//!    因此逐步回放的结果与 `run()` 完全一致
+//! 3. `step_trade()` 一直推进到产生下一笔成交为止；`seek(i)` 推进到指定 bar
+//! 4. 每一步返回状态快照：当前 bar、持仓、现金、净值、已实现盈亏及本步产生的成交
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! rp = Replayer(MyStrategy(), bars, config)
+//! while not rp.done:
+//!     snap = rp.step_trade()
+//!     print(snap["datetime"], snap["position"], snap["trades"])
+//! result = rp.finish()   # 与 engine.run() 的结果相同
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 回放只能向前推进；需要从头开始时请用新的策略实例重新构造 `Replayer`
+//! - 策略对象会被真实调用，回放过程中策略内部状态照常变化
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::bar_fields;
+use crate::baseline::{self, BuyAndHold};
+use crate::checkpoint::RunState;
+use crate::columnar::extract_bars_any;
+use crate::positions;
+use crate::schedule::{self, session_end_flags};
+use crate::sizing::KellyStats;
+use crate::strategy::StrategyHooks;
+use crate::timeframes::Timeframes;
+use crate::vol_target::ReturnWindow;
+use crate::{indicators, BacktestConfig, BacktestEngine, BarData, BarFlags, EngineContext, PositionState};
+
+/// 逐步回放的回测
+#[pyclass]
+pub struct Replayer {
+    engine: BacktestEngine,
+    strategy: PyObject,
+    bars: Vec<BarData>,
+    indicator_columns: Vec<(String, Vec<Option<f64>>)>,
+    timeframes: Option<Timeframes>,
+    hooks: StrategyHooks,
+    rebalance_flags: Vec<bool>,
+    session_flags: Vec<bool>,
+    outside_flags: Vec<bool>,
+    state: RunState,
+    /// 下一根待处理 bar 的序号
+    next_bar: usize,
+    /// 最近一步产生的成交在 `state.trades` 中的起始位置
+    step_trades_from: usize,
+    stopped: bool,
+}
+
+#[pymethods]
+impl Replayer {
+    /// 创建回放器并调用策略的 `on_start(ctx)`
+    ///
+    /// # 参数
+    ///
+    /// - `strategy`: Python 策略对象
+    /// - `data`: K 线数据（格式同 `run()`，不支持流式数据源）
+    /// - `cfg`: 回测配置
+    /// - `indicators`: 可选的指标规格列表（同 `run()`）
+    /// - `timeframes`: 可选的高周期列表（同 `run()`）
+    #[new]
+    #[pyo3(signature = (strategy, data, cfg, indicators=None, timeframes=None))]
+    fn new(
+        py: Python<'_>,
+        strategy: PyObject,
+        data: &Bound<'_, PyAny>,
+        cfg: BacktestConfig,
+        indicators: Option<&Bound<'_, PyList>>,
+        timeframes: Option<Vec<String>>,
+    ) -> PyResult<Self> {
+        let timeframes = timeframes.as_deref().map(Timeframes::new).transpose()?;
+        let bars = extract_bars_any(data.as_gil_ref())?;
+        cfg.check_bars(&bars)?;
+        let indicator_columns = match indicators {
+            Some(specs) => {
+                let specs = indicators::parse_indicator_specs(specs, indicators::WarmupFill::None)?;
+                indicators::compute_indicator_batch(&indicators::OhlcvColumns::from_bars(&bars), &specs)
+            }
+            None => Vec::new(),
+        };
+        let engine = BacktestEngine { cfg };
+        let (bars, indicator_columns) = engine.apply_date_window(bars, indicator_columns)?;
+        let cfg = &engine.cfg;
+
+        let schedule = cfg.rebalance_schedule()?;
+        let bar_dates = schedule::bar_dates(bars.iter().map(|b| b.datetime.as_deref()));
+        let rebalance_flags = schedule.flags(&bar_dates);
+        let hooks = StrategyHooks::detect(py, &strategy)?;
+        let session_flags = if hooks.on_session_end { session_end_flags(&bar_dates) } else { vec![false; bars.len()] };
+        let outside_flags = match cfg.trading_sessions()? {
+            Some(sessions) => bars.iter().map(|b| sessions.outside(b.datetime.as_deref())).collect(),
+            None => vec![false; bars.len()],
+        };
+
+        let state = RunState {
+            pos: PositionState::new(cfg.cash),
+            order_seq: 1,
+            equity_curve: Vec::with_capacity(bars.len()),
+            trades: Vec::new(),
+            deferred: Vec::new(),
+            positions_history: Vec::new(),
+            kelly: KellyStats::default(),
+            vol_returns: ReturnWindow::default(),
+            profile: None,
+            exposure_limits: cfg.exposure_limits()?,
+            vol_target: cfg.vol_targeting()?,
+            defer_orders: cfg.defers_orders()?,
+        };
+        let replayer = Self {
+            engine,
+            strategy,
+            bars,
+            indicator_columns,
+            timeframes,
+            hooks,
+            rebalance_flags,
+            session_flags,
+            outside_flags,
+            state,
+            next_bar: 0,
+            step_trades_from: 0,
+            stopped: false,
+        };
+        let ctx = Py::new(py, EngineContext::snapshot(&replayer.state.pos, None, None, 0))?;
+        if replayer.hooks.on_start {
+            let _ = replayer.strategy.call_method1(py, "on_start", (ctx.bind(py),));
+        }
+        Ok(replayer)
+    }
+
+    /// 推进 `n` 根 bar，返回推进后的状态快照（`trades` 为这 `n` 根 bar 产生的全部成交）
+    ///
+    /// 已回放到末尾时返回 `None`。
+    #[pyo3(signature = (n=1))]
+    fn step(&mut self, py: Python<'_>, n: usize) -> PyResult<Option<PyObject>> {
+        if self.next_bar >= self.bars.len() {
+            return Ok(None);
+        }
+        self.step_trades_from = self.state.trades.len();
+        for _ in 0..n.max(1) {
+            if self.next_bar >= self.bars.len() {
+                break;
+            }
+            self.advance(py)?;
+        }
+        self.snapshot(py).map(Some)
+    }
+
+    /// 推进到产生下一笔成交的 bar（或回放结束），返回该 bar 处理后的状态快照
+    ///
+    /// 已回放到末尾时返回 `None`。
+    fn step_trade(&mut self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        if self.next_bar >= self.bars.len() {
+            return Ok(None);
+        }
+        self.step_trades_from = self.state.trades.len();
+        while self.next_bar < self.bars.len() {
+            self.advance(py)?;
+            if self.state.trades.len() > self.step_trades_from {
+                break;
+            }
+        }
+        self.snapshot(py).map(Some)
+    }
+
+    /// 推进到第 `bar_index` 根 bar 处理完毕（只能向前），返回状态快照
+    fn seek(&mut self, py: Python<'_>, bar_index: usize) -> PyResult<PyObject> {
+        if bar_index + 1 < self.next_bar {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Cannot seek backwards to bar {} (already at bar {}); create a new Replayer to restart",
+                bar_index,
+                self.next_bar.saturating_sub(1)
+            )));
+        }
+        self.step_trades_from = self.state.trades.len();
+        let target = (bar_index + 1).min(self.bars.len());
+        while self.next_bar < target {
+            self.advance(py)?;
+        }
+        self.snapshot(py)
+    }
+
+    /// 当前状态快照（`trades` 为最近一次推进产生的成交）
+    ///
+    /// 快照字典包含：`bar_index`（最近处理的 bar 序号，尚未推进时为 -1）、`datetime`、`bar`（OHLCV）、
+    /// `position`、`avg_cost`、`cash`、`equity`、`realized_pnl`、`trades`、`trade_count`（累计成交数）
+    fn state(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.snapshot(py)
+    }
+
+    /// 下一根待处理 bar 的序号
+    #[getter]
+    fn next_index(&self) -> usize {
+        self.next_bar
+    }
+
+    /// 数据总 bar 数
+    #[getter]
+    fn total(&self) -> usize {
+        self.bars.len()
+    }
+
+    /// 是否已回放到末尾
+    #[getter]
+    fn done(&self) -> bool {
+        self.next_bar >= self.bars.len()
+    }
+
+    /// 截至当前的结果（格式同 `run()`）
+    fn result(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let st = self.state.clone();
+        let cfg = &self.engine.cfg;
+        let range = baseline::curve_range(&st.equity_curve);
+        let buy_and_hold = cfg.buy_and_hold.then(|| BuyAndHold::from_bars(cfg, self.engine.equity_bars(&self.bars, st.equity_curve.len())));
+        let result = self.engine.build_result(py, st.pos, st.equity_curve, st.trades)?;
+        if cfg.record_positions {
+            positions::mark_result(py, &result, &st.positions_history)?;
+        }
+        baseline::mark_result(py, &result, cfg, buy_and_hold, None, range)?;
+        BacktestEngine::mark_date_range(py, &result, &self.bars)?;
+        Ok(result)
+    }
+
+    /// 回放剩余全部 bar，调用 `on_stop()` 并返回最终结果（与 `run()` 相同）
+    fn finish(&mut self, py: Python<'_>) -> PyResult<PyObject> {
+        while self.next_bar < self.bars.len() {
+            self.advance(py)?;
+        }
+        if !self.stopped {
+            self.stopped = true;
+            self.engine.cancel_deferred(py, &self.strategy, &self.hooks, &mut self.state)?;
+            if self.hooks.on_stop {
+                let _ = self.strategy.call_method0(py, "on_stop");
+            }
+        }
+        self.result(py)
+    }
+}
+
+impl Replayer {
+    /// 处理下一根 bar
+    fn advance(&mut self, py: Python<'_>) -> PyResult<()> {
+        let i = self.next_bar;
+        let flags = BarFlags {
+            rebalance: self.rebalance_flags[i],
+            session_end: self.session_flags[i],
+            outside_session: self.outside_flags[i],
+        };
+        if let Some(tf) = self.timeframes.as_mut() {
+            tf.update(&self.bars[i], self.bars.get(i + 1))?;
+        }
+        let timeframes = self.timeframes.as_ref();
+        self.engine.process_bar(py, &self.strategy, &self.hooks, &mut self.state, i, &self.bars[i], &self.indicator_columns, timeframes, flags)?;
+        self.next_bar += 1;
+        Ok(())
+    }
+
+    fn snapshot(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let snap = PyDict::new_bound(py);
+        let pos = &self.state.pos;
+        match self.next_bar.checked_sub(1) {
+            Some(i) => {
+                let bar = &self.bars[i];
+                snap.set_item("bar_index", i)?;
+                snap.set_item("datetime", &bar.datetime)?;
+                let bar_dict = PyDict::new_bound(py);
+                bar_dict.set_item("open", bar.open)?;
+                bar_dict.set_item("high", bar.high)?;
+                bar_dict.set_item("low", bar.low)?;
+                bar_dict.set_item("close", bar.close)?;
+                bar_dict.set_item("volume", bar.volume)?;
+                bar_fields::set_extra(&bar_dict, &bar.extra)?;
+                for (name, values) in &self.indicator_columns {
+                    bar_dict.set_item(name, values[i])?;
+                }
+                snap.set_item("bar", bar_dict)?;
+                snap.set_item("equity", pos.cash + pos.position * bar.close)?;
+            }
+            None => {
+                snap.set_item("bar_index", -1)?;
+                snap.set_item("datetime", py.None())?;
+                snap.set_item("bar", py.None())?;
+                snap.set_item("equity", pos.cash)?;
+            }
+        }
+        snap.set_item("position", pos.position)?;
+        snap.set_item("avg_cost", pos.avg_cost)?;
+        snap.set_item("cash", pos.cash)?;
+        snap.set_item("realized_pnl", pos.realized_pnl)?;
+        let trades = PyList::empty_bound(py);
+        for trade in &self.state.trades[self.step_trades_from..] {
+            trades.append(trade.to_pydict(py)?)?;
+        }
+        snap.set_item("trades", trades)?;
+        snap.set_item("trade_count", self.state.trades.len())?;
+        Ok(snap.into())
+    }
+}
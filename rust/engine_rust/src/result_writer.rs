@@ -0,0 +1,234 @@
+//! 回测结果后台写入模块
+//!
+//! 百万级 bar 的回测结束时，把净值曲线和成交记录逐条转换成 Python 字典往往比回测本身还慢，
+//! 生成的 Python 对象占用的内存也是 Rust 侧数据的数倍。这个模块在回测过程中把结果
+//! 分批交给后台线程写入 DuckDB，回测结果中不再构建 `equity_curve` / `trades` 列表。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 回测开始前在主线程中建表，并删除同一 `run_id` 的旧结果（路径错误在回测开始前就会报出）
+//! 2. 每个批次边界把新增的净值点和成交通过有界通道发给后台线程，后台线程用 Appender 追加写入；
+//!    写入跟不上时主循环等待（释放 GIL），积压的批次数有上限
+//! 3. 回测结束时发送最后一批并等待后台线程写完，写入过程中的错误在此时报告
+//!
+//! ## 存储结构
+//!
+//! - `backtest_equity(run_id, bar_index, datetime, equity)`：`bar_index` 为净值点的序号
+//! - `backtest_trades(run_id, order_id, side, price, size, datetime, symbol, commission, slippage, position, cash)`：
+//!   与结果中的 `trades` 字段一致；早期版本建的表缺少后六列，写入前会自动补齐
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! result = engine.run(MyStrategy(), ticks, results_db="data/results.db", run_id="ma_cross_v2")
+//! print(result["stats"]["sharpe"], result["results_db"]["equity_rows"])
+//!
+//! import duckdb
+//! curve = duckdb.connect("data/results.db").execute(
+//!     "SELECT datetime, equity FROM backtest_equity WHERE run_id = 'ma_cross_v2' ORDER BY bar_index"
+//! ).df()
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 统计指标仍在 Rust 侧基于完整的净值曲线计算，结果中的 `stats` 与不写库时完全相同
+//! - 回测因策略异常中止时，已写入的部分结果保留在库中
+//! - `datetime` 按 bar 中的原始字符串保存（VARCHAR），缺失时为 NULL
+
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread::JoinHandle;
+
+use duckdb::Connection;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::checkpoint::RunState;
+use crate::database::open_connection;
+use crate::TradeRecord;
+
+/// 净值曲线表名
+const EQUITY_TABLE: &str = "backtest_equity";
+/// 成交记录表名
+const TRADES_TABLE: &str = "backtest_trades";
+/// 通道中最多积压的批次数
+const MAX_PENDING_BATCHES: usize = 4;
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+/// 发给后台线程的一批结果
+struct ResultBatch {
+    /// 本批第一个净值点的序号
+    first_bar: usize,
+    equity: Vec<(Option<String>, f64)>,
+    trades: Vec<TradeRecord>,
+}
+
+/// 回测结果的后台写入器
+pub(crate) struct ResultWriter {
+    path: String,
+    run_id: String,
+    sender: Option<SyncSender<ResultBatch>>,
+    handle: Option<JoinHandle<Result<(), String>>>,
+    equity_sent: usize,
+    trades_sent: usize,
+}
+
+impl ResultWriter {
+    /// 创建写入器并启动后台线程；未指定 `results_db` 时返回 `None`
+    ///
+    /// 未指定 `run_id` 时以当前本地时间生成（如 `"20240102-093000.123"`）。
+    pub(crate) fn new(path: Option<String>, run_id: Option<String>) -> PyResult<Option<Self>> {
+        let Some(path) = path else {
+            if run_id.is_some() {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>("run_id requires results_db"));
+            }
+            return Ok(None);
+        };
+        let run_id = run_id.unwrap_or_else(|| chrono::Local::now().format("%Y%m%d-%H%M%S%.3f").to_string());
+
+        let conn = open_connection(&path)?;
+        prepare_tables(&conn, &run_id)?;
+
+        let (sender, receiver) = sync_channel::<ResultBatch>(MAX_PENDING_BATCHES);
+        let thread_run_id = run_id.clone();
+        let handle = std::thread::spawn(move || {
+            // 出错后继续接收（丢弃）剩余批次，避免主循环在发送时阻塞
+            let mut error: Option<String> = None;
+            for batch in receiver {
+                if error.is_none() {
+                    error = write_batch(&conn, &thread_run_id, batch).err();
+                }
+            }
+            error.map_or(Ok(()), Err)
+        });
+
+        Ok(Some(Self {
+            path,
+            run_id,
+            sender: Some(sender),
+            handle: Some(handle),
+            equity_sent: 0,
+            trades_sent: 0,
+        }))
+    }
+
+    /// 把上次发送之后新增的净值点和成交发给后台线程（在批次边界调用）
+    pub(crate) fn flush(&mut self, py: Python<'_>, st: &RunState) {
+        if st.equity_curve.len() == self.equity_sent && st.trades.len() == self.trades_sent {
+            return;
+        }
+        let batch = ResultBatch {
+            first_bar: self.equity_sent,
+            equity: st.equity_curve[self.equity_sent..].to_vec(),
+            trades: st.trades[self.trades_sent..].to_vec(),
+        };
+        self.equity_sent = st.equity_curve.len();
+        self.trades_sent = st.trades.len();
+        if let Some(sender) = &self.sender {
+            // 后台线程异常退出时发送失败，错误在 finish() 中报告
+            let _ = py.allow_threads(|| sender.send(batch));
+        }
+    }
+
+    /// 发送剩余结果并等待后台线程写完，写入过程中的错误在此时返回
+    pub(crate) fn finish(mut self, py: Python<'_>, st: &RunState) -> PyResult<WrittenResults> {
+        self.flush(py, st);
+        self.sender = None;
+        if let Some(handle) = self.handle.take() {
+            py.allow_threads(|| handle.join())
+                .map_err(|_| runtime_error("Result writer thread panicked".to_string()))?
+                .map_err(runtime_error)?;
+        }
+        Ok(WrittenResults {
+            path: self.path,
+            run_id: self.run_id,
+            equity_rows: self.equity_sent,
+            trades: self.trades_sent,
+        })
+    }
+}
+
+/// 已写入数据库的结果概要
+pub(crate) struct WrittenResults {
+    path: String,
+    run_id: String,
+    equity_rows: usize,
+    trades: usize,
+}
+
+/// 在结果字典中记录 `results_db`（`path`、`run_id`、`equity_rows`、`trades`）
+pub(crate) fn mark_result(py: Python<'_>, result: &PyObject, written: Option<WrittenResults>) -> PyResult<()> {
+    let Some(written) = written else { return Ok(()) };
+    let info = PyDict::new_bound(py);
+    info.set_item("path", written.path)?;
+    info.set_item("run_id", written.run_id)?;
+    info.set_item("equity_rows", written.equity_rows)?;
+    info.set_item("trades", written.trades)?;
+    result.downcast_bound::<PyDict>(py)?.set_item("results_db", info)?;
+    Ok(())
+}
+
+/// 建表并删除同一 `run_id` 的旧结果
+fn prepare_tables(conn: &Connection, run_id: &str) -> PyResult<()> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {0} (
+             run_id VARCHAR NOT NULL,
+             bar_index BIGINT NOT NULL,
+             datetime VARCHAR,
+             equity DOUBLE NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS {1} (
+             run_id VARCHAR NOT NULL,
+             order_id UBIGINT NOT NULL,
+             side VARCHAR NOT NULL,
+             price DOUBLE NOT NULL,
+             size DOUBLE NOT NULL,
+             datetime VARCHAR,
+             symbol VARCHAR,
+             commission DOUBLE,
+             slippage DOUBLE,
+             position DOUBLE,
+             cash DOUBLE
+         );
+         ALTER TABLE {1} ADD COLUMN IF NOT EXISTS datetime VARCHAR;
+         ALTER TABLE {1} ADD COLUMN IF NOT EXISTS symbol VARCHAR;
+         ALTER TABLE {1} ADD COLUMN IF NOT EXISTS commission DOUBLE;
+         ALTER TABLE {1} ADD COLUMN IF NOT EXISTS slippage DOUBLE;
+         ALTER TABLE {1} ADD COLUMN IF NOT EXISTS position DOUBLE;
+         ALTER TABLE {1} ADD COLUMN IF NOT EXISTS cash DOUBLE;",
+        EQUITY_TABLE, TRADES_TABLE
+    ))
+    .map_err(|e| runtime_error(format!("Failed to create result tables: {}", e)))?;
+    for table in [EQUITY_TABLE, TRADES_TABLE] {
+        conn.execute(&format!("DELETE FROM {} WHERE run_id = ?", table), duckdb::params![run_id])
+            .map_err(|e| runtime_error(format!("Failed to clear previous results in {}: {}", table, e)))?;
+    }
+    Ok(())
+}
+
+/// 后台线程中追加写入一批结果
+fn write_batch(conn: &Connection, run_id: &str, batch: ResultBatch) -> Result<(), String> {
+    let mut appender = conn
+        .appender(EQUITY_TABLE)
+        .map_err(|e| format!("Failed to create appender for {}: {}", EQUITY_TABLE, e))?;
+    for (k, (datetime, equity)) in batch.equity.iter().enumerate() {
+        appender
+            .append_row(duckdb::params![run_id, (batch.first_bar + k) as i64, datetime, equity])
+            .map_err(|e| format!("Failed to write equity curve: {}", e))?;
+    }
+    appender.flush().map_err(|e| format!("Failed to write equity curve: {}", e))?;
+
+    let mut appender = conn
+        .appender(TRADES_TABLE)
+        .map_err(|e| format!("Failed to create appender for {}: {}", TRADES_TABLE, e))?;
+    for t in &batch.trades {
+        appender
+            .append_row(duckdb::params![
+                run_id, t.order_id, t.side, t.price, t.size, t.datetime, t.symbol, t.commission, t.slippage, t.position, t.cash
+            ])
+            .map_err(|e| format!("Failed to write trades: {}", e))?;
+    }
+    appender.flush().map_err(|e| format!("Failed to write trades: {}", e))
+}
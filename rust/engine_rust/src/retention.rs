@@ -0,0 +1,178 @@
+//! 数据保留与压缩模块
+//!
+//! 长期运行的 tick / 分钟级数据库会不断变大：研究通常只需要最近一段时间的细粒度数据，
+//! 更早的历史看日线、小时线就够了。这个模块按标的清理过旧的细粒度 K 线（粗周期不受影响），
+//! 并提供数据库压缩，把删除后留下的空间真正还给文件系统。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. `prune_klines()` 以每个标的最新一根 K 线所在的日期为准，向前保留 `keep_days` 天，
+//!    更早的数据从该周期表中删除；只处理指定的周期，其他周期（如日线）原样保留
+//! 2. DuckDB 删除数据后不会缩小文件，`compact_database()` 先做一次 `CHECKPOINT`，
+//!    再把整个库复制到新文件并替换原文件，文件大小随之缩小
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import materialize_period, prune_klines, compact_database
+//!
+//! materialize_period("data/backtest.db", "AAPL", "1m", "1d")   # 先保证日线完整
+//! deleted = prune_klines("data/backtest.db", "1m", keep_days=90)  # 1 分钟数据只留 90 天
+//! print(compact_database("data/backtest.db"))                     # {"before": ..., "after": ...}
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 清理按标的各自的最新时间计算，停止更新的标的不会因为"太旧"被整体删除
+//! - 已物化的粗周期在源周期被清理后保留原有的 K 线，之后的刷新不会删除它们
+//! - 压缩期间需要与原库大小相当的额外磁盘空间，且不能有其他进程打开该数据库
+
+use std::path::Path;
+
+use duckdb::Connection;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::database::{open_connection, sanitize_period_identifier, MEMORY_DB_PATH};
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 表是否存在（不会创建表）
+fn table_exists(conn: &Connection, table_name: &str) -> PyResult<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM information_schema.tables WHERE table_schema = 'main' AND table_name = ?",
+        duckdb::params![table_name],
+        |row| row.get(0),
+    )
+    .map_err(|e| runtime_error(format!("Failed to list tables: {}", e)))
+}
+
+/// 清理细粒度周期中过旧的 K 线
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import prune_klines
+///
+/// # 所有标的的 1 分钟数据都只保留最近 30 天
+/// prune_klines("data/backtest.db", "1m", keep_days=30)
+/// # 只清理一个标的
+/// prune_klines("data/backtest.db", "1m", keep_days=30, symbol="AAPL")
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `period`: 要清理的周期（如 `"1m"`），其他周期不受影响
+/// - `keep_days`: 保留天数。每个标的保留其最新一根 K 线所在日期及之前 `keep_days` 天（按日期零点截断）
+/// - `symbol`: 可选，只清理该标的；默认清理该周期的全部标的
+///
+/// # 返回值
+///
+/// 删除的 K 线条数
+///
+/// # 注意事项
+///
+/// - 截断点按日期对齐，不会把某一天的数据删掉一半
+/// - 该周期的表不存在时返回 0（不会创建表）
+#[pyfunction]
+#[pyo3(signature = (db_path, period, keep_days, symbol=None))]
+pub fn prune_klines(db_path: String, period: String, keep_days: u32, symbol: Option<String>) -> PyResult<usize> {
+    let conn = open_connection(&db_path)?;
+    let table_name = format!("klines_{}", sanitize_period_identifier(&period)?);
+    if !table_exists(&conn, &table_name)? {
+        return Ok(0);
+    }
+
+    // 每个标的的截断点：最新一根 K 线所在日期的零点向前 keep_days 天
+    let query = format!(
+        "DELETE FROM {t} USING (
+             SELECT symbol, date_trunc('day', MAX(datetime)) - INTERVAL {days} DAY AS cutoff
+             FROM {t}{filter}
+             GROUP BY symbol
+         ) AS c
+         WHERE {t}.symbol = c.symbol AND {t}.datetime < c.cutoff",
+        t = table_name,
+        days = keep_days,
+        filter = if symbol.is_some() { " WHERE symbol = ?" } else { "" }
+    );
+    match &symbol {
+        Some(s) => conn.execute(&query, duckdb::params![s]),
+        None => conn.execute(&query, []),
+    }
+    .map_err(|e| runtime_error(format!("Failed to prune {}: {}", table_name, e)))
+}
+
+/// 压缩数据库文件
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import compact_database
+///
+/// sizes = compact_database("data/backtest.db")
+/// print(f"{sizes['before'] / 1e6:.1f} MB -> {sizes['after'] / 1e6:.1f} MB")
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+///
+/// # 返回值
+///
+/// 字典：`before`、`after`（压缩前后的文件字节数）
+///
+/// # 注意事项
+///
+/// - 内存数据库（`":memory:"`）或文件不存在时返回 `ValueError`
+/// - 先写入临时文件 `<db_path>.compact`，成功后才替换原文件；失败时原文件保持不变
+#[pyfunction]
+pub fn compact_database(py: Python<'_>, db_path: String) -> PyResult<PyObject> {
+    if db_path == MEMORY_DB_PATH {
+        return Err(value_error("The in-memory database cannot be compacted".to_string()));
+    }
+    let path = Path::new(&db_path);
+    if !path.exists() {
+        return Err(value_error(format!("Database file '{}' does not exist", db_path)));
+    }
+    let file_size = |p: &Path| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+    let before = file_size(path);
+
+    let compact_path = format!("{}.compact", db_path);
+    if Path::new(&compact_path).exists() {
+        std::fs::remove_file(&compact_path)
+            .map_err(|e| runtime_error(format!("Failed to remove stale compaction file: {}", e)))?;
+    }
+    {
+        let conn = open_connection(&db_path)?;
+        conn.execute_batch("CHECKPOINT")
+            .map_err(|e| runtime_error(format!("Failed to checkpoint database: {}", e)))?;
+        let catalog: String = conn
+            .query_row("SELECT current_database()", [], |row| row.get(0))
+            .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?;
+        conn.execute_batch(&format!(
+            "ATTACH '{}' AS compact_target;
+             COPY FROM DATABASE \"{}\" TO compact_target;
+             DETACH compact_target;",
+            compact_path.replace('\'', "''"),
+            catalog.replace('"', "\"\"")
+        ))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&compact_path);
+            runtime_error(format!("Failed to compact database: {}", e))
+        })?;
+    }
+    std::fs::rename(&compact_path, path)
+        .map_err(|e| runtime_error(format!("Failed to replace database file: {}", e)))?;
+
+    let sizes = PyDict::new_bound(py);
+    sizes.set_item("before", before)?;
+    sizes.set_item("after", file_size(path))?;
+    Ok(sizes.into())
+}
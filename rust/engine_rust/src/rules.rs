@@ -0,0 +1,434 @@
+//! 规则策略模块（Rule DSL）
+//!
+//! 这个模块实现了一个小型的策略表达式语言：把常见的指标交叉、阈值类策略写成一行文本，
+//! 在 Rust 中解析并逐 bar 求值，整个回测过程不需要调用 Python。
+//!
+//! ## 为什么需要这个模块？
+//!
+//! 大量策略只是"均线金叉买入、死叉卖出"、"RSI 低于 30 买入、高于 70 卖出"这类规则，
+//! 用 Python 类实现时每根 bar 都要回调一次 `next()`。规则策略把这些逻辑交给 Rust，
+//! 既免去了 Python 回调开销，也方便把策略保存为配置文本、批量生成。
+//!
+//! ## 语法
+//!
+//! ```text
+//! 程序   := 规则 (';' 或换行 规则)*
+//! 规则   := (BUY | SELL) WHEN 条件
+//! 条件   := 条件 OR 条件 | 条件 AND 条件 | NOT 条件 | '(' 条件 ')' | 比较 | 交叉
+//! 比较   := 值 (> | < | >= | <= | == | !=) 值
+//! 交叉   := 值 crosses_above 值 | 值 crosses_below 值 | cross_above | cross_below
+//! 值     := 数字 | open | high | low | close | volume | 指标 '(' 窗口 [',' 字段] ')'
+//! ```
+//!
+//! 支持的指标：`sma`、`ema`、`wma`、`hma`、`rsi`、`roc`、`momentum`、`trix`、`std`、`var`、
+//! `zscore`、`kama`、`rolling_max`（别名 `highest`）、`rolling_min`（别名 `lowest`）、`mfi`、`psar`。
+//! 关键字不区分大小写。
+//!
+//! 不带参数的 `cross_above` / `cross_below` 是简写，表示程序中第一个比较式两侧的交叉，例如：
+//!
+//! ```text
+//! BUY when sma(20) > sma(50); SELL when cross_below
+//! ```
+//!
+//! 等价于 `SELL when sma(20) crosses_below sma(50)`。
+//!
+//! ## 执行语义
+//!
+//! 规则策略只做多：空仓时 BUY 条件成立则建仓到目标数量（或权重），持仓时 SELL 条件成立则全部平仓。
+//! 多条同类规则之间是"或"的关系。指标处于预热期（值缺失）时，相关条件视为不成立。
+
+use crate::indicators::{IndicatorKind, IndicatorSpec, OhlcvColumns, WarmupFill};
+
+/// 比较运算符
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CmpOp {
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    Eq,
+    Ne,
+}
+
+/// 表达式中的值：常数或序列表中的某条序列
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value {
+    Const(f64),
+    Series(usize),
+}
+
+/// 布尔条件表达式
+#[derive(Clone, Debug)]
+enum Cond {
+    And(Box<Cond>, Box<Cond>),
+    Or(Box<Cond>, Box<Cond>),
+    Not(Box<Cond>),
+    Cmp(Value, CmpOp, Value),
+    Cross { a: Value, b: Value, above: bool },
+}
+
+/// 序列定义：行情字段或指标
+#[derive(Clone, Debug, PartialEq)]
+enum SeriesDef {
+    Field(String),
+    Indicator(IndicatorSpec),
+}
+
+/// 解析后的规则程序
+#[derive(Clone, Debug)]
+pub struct RuleProgram {
+    buy: Vec<Cond>,
+    sell: Vec<Cond>,
+    series: Vec<SeriesDef>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Op(CmpOp),
+    LParen,
+    RParen,
+    Comma,
+    Sep,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == ';' || c == '\n' {
+            tokens.push(Token::Sep);
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else if "<>=!".contains(c) {
+            let two = chars.get(i + 1) == Some(&'=');
+            let op = match (c, two) {
+                ('>', true) => CmpOp::Ge,
+                ('>', false) => CmpOp::Gt,
+                ('<', true) => CmpOp::Le,
+                ('<', false) => CmpOp::Lt,
+                ('=', true) => CmpOp::Eq,
+                ('!', true) => CmpOp::Ne,
+                _ => return Err(format!("unexpected character '{}' at position {}", c, i)),
+            };
+            tokens.push(Token::Op(op));
+            i += if two { 2 } else { 1 };
+        } else if c.is_ascii_digit() || c == '.' || c == '-' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let n = text.parse::<f64>().map_err(|_| format!("invalid number '{}' at position {}", text, start))?;
+            tokens.push(Token::Number(n));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect::<String>().to_lowercase()));
+        } else {
+            return Err(format!("unexpected character '{}' at position {}", c, i));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    series: Vec<SeriesDef>,
+    /// 程序中第一个比较式的两侧，供 `cross_above`/`cross_below` 简写使用
+    first_cmp: Option<(Value, Value)>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn peek_ident(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(w)) if w == word)
+    }
+
+    fn expect(&mut self, tok: Token) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == tok => Ok(()),
+            other => Err(format!("expected {:?}, found {:?}", tok, other)),
+        }
+    }
+
+    fn series_index(&mut self, def: SeriesDef) -> usize {
+        match self.series.iter().position(|d| *d == def) {
+            Some(i) => i,
+            None => {
+                self.series.push(def);
+                self.series.len() - 1
+            }
+        }
+    }
+
+    fn parse_program(mut self) -> Result<RuleProgram, String> {
+        let mut buy = Vec::new();
+        let mut sell = Vec::new();
+        loop {
+            while self.peek() == Some(&Token::Sep) {
+                self.pos += 1;
+            }
+            let action = match self.next() {
+                None => break,
+                Some(Token::Ident(w)) if w == "buy" || w == "sell" => w,
+                other => return Err(format!("expected BUY or SELL, found {:?}", other)),
+            };
+            match self.next() {
+                Some(Token::Ident(w)) if w == "when" => {}
+                other => return Err(format!("expected WHEN after {}, found {:?}", action.to_uppercase(), other)),
+            }
+            let cond = self.parse_or()?;
+            match self.peek() {
+                None | Some(Token::Sep) => {}
+                Some(t) => return Err(format!("unexpected token {:?} after rule", t)),
+            }
+            if action == "buy" { buy.push(cond) } else { sell.push(cond) }
+        }
+        if buy.is_empty() {
+            return Err("rule program must contain at least one BUY rule".to_string());
+        }
+        Ok(RuleProgram { buy, sell, series: self.series })
+    }
+
+    fn parse_or(&mut self) -> Result<Cond, String> {
+        let mut left = self.parse_and()?;
+        while self.peek_ident("or") {
+            self.pos += 1;
+            left = Cond::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Cond, String> {
+        let mut left = self.parse_not()?;
+        while self.peek_ident("and") {
+            self.pos += 1;
+            left = Cond::And(Box::new(left), Box::new(self.parse_not()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Cond, String> {
+        if self.peek_ident("not") {
+            self.pos += 1;
+            return Ok(Cond::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Cond, String> {
+        if self.peek_ident("cross_above") || self.peek_ident("cross_below") {
+            let above = self.peek_ident("cross_above");
+            self.pos += 1;
+            let (a, b) = self
+                .first_cmp
+                .ok_or_else(|| "cross_above/cross_below shorthand needs an earlier comparison".to_string())?;
+            return Ok(Cond::Cross { a, b, above });
+        }
+        // 括号既可能包住条件，也可能包住值；先按条件尝试，失败再回退
+        if self.peek() == Some(&Token::LParen) {
+            let save = self.pos;
+            self.pos += 1;
+            if let Ok(cond) = self.parse_or() {
+                if self.peek() == Some(&Token::RParen) {
+                    self.pos += 1;
+                    return Ok(cond);
+                }
+            }
+            self.pos = save;
+        }
+        let a = self.parse_value()?;
+        match self.next() {
+            Some(Token::Op(op)) => {
+                let b = self.parse_value()?;
+                if self.first_cmp.is_none() {
+                    self.first_cmp = Some((a, b));
+                }
+                Ok(Cond::Cmp(a, op, b))
+            }
+            Some(Token::Ident(w)) if w == "crosses_above" || w == "crosses_below" => {
+                let b = self.parse_value()?;
+                Ok(Cond::Cross { a, b, above: w == "crosses_above" })
+            }
+            other => Err(format!("expected comparison operator, found {:?}", other)),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Value::Const(n)),
+            Some(Token::Ident(name)) => {
+                if matches!(name.as_str(), "open" | "high" | "low" | "close" | "volume") {
+                    return Ok(Value::Series(self.series_index(SeriesDef::Field(name))));
+                }
+                let mut args: Vec<Token> = Vec::new();
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    while self.peek() != Some(&Token::RParen) {
+                        match self.next() {
+                            Some(Token::Comma) => {}
+                            Some(t @ (Token::Number(_) | Token::Ident(_))) => args.push(t),
+                            other => return Err(format!("invalid argument {:?} to {}", other, name)),
+                        }
+                    }
+                    self.expect(Token::RParen)?;
+                }
+                let spec = indicator_spec(&name, &args)?;
+                Ok(Value::Series(self.series_index(SeriesDef::Indicator(spec))))
+            }
+            other => Err(format!("expected a value, found {:?}", other)),
+        }
+    }
+}
+
+/// 把 `指标(窗口[, 字段])` 转换为指标规格
+fn indicator_spec(name: &str, args: &[Token]) -> Result<IndicatorSpec, String> {
+    let window = match args.first() {
+        Some(Token::Number(n)) if *n >= 1.0 && n.fract() == 0.0 => Some(*n as usize),
+        Some(other) => return Err(format!("invalid window {:?} for {}", other, name)),
+        None => None,
+    };
+    let source = match args.get(1) {
+        Some(Token::Ident(f)) if matches!(f.as_str(), "open" | "high" | "low" | "close" | "volume") => f.clone(),
+        Some(other) => return Err(format!("invalid source field {:?} for {}", other, name)),
+        None => "close".to_string(),
+    };
+    let need = |w: Option<usize>| w.ok_or_else(|| format!("{} requires a window, e.g. {}(20)", name, name));
+    let kind = match name {
+        "sma" => IndicatorKind::Sma { window: need(window)? },
+        "ema" => IndicatorKind::Ema { window: need(window)? },
+        "wma" => IndicatorKind::Wma { window: need(window)? },
+        "hma" => IndicatorKind::Hma { window: need(window)? },
+        "rsi" => IndicatorKind::Rsi { window: window.unwrap_or(14) },
+        "roc" => IndicatorKind::Roc { window: need(window)? },
+        "momentum" => IndicatorKind::Momentum { window: need(window)? },
+        "trix" => IndicatorKind::Trix { window: need(window)? },
+        "std" => IndicatorKind::Std { window: need(window)?, ddof: 1 },
+        "var" => IndicatorKind::Var { window: need(window)?, ddof: 1 },
+        "zscore" => IndicatorKind::Zscore { window: need(window)?, ddof: 1 },
+        "kama" => IndicatorKind::Kama { er_window: window.unwrap_or(10), fast: 2, slow: 30 },
+        "rolling_max" | "highest" => IndicatorKind::RollingMax { window: need(window)? },
+        "rolling_min" | "lowest" => IndicatorKind::RollingMin { window: need(window)? },
+        "mfi" => IndicatorKind::Mfi { window: window.unwrap_or(14) },
+        "psar" => IndicatorKind::Psar { af_start: 0.02, af_step: 0.02, af_max: 0.2 },
+        _ => return Err(format!("unknown indicator '{}'", name)),
+    };
+    Ok(IndicatorSpec { kind, name: name.to_string(), source, fill: WarmupFill::None })
+}
+
+impl RuleProgram {
+    /// 解析规则文本，语法错误时返回描述信息
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let parser = Parser { tokens: tokenize(src)?, pos: 0, series: Vec::new(), first_cmp: None };
+        parser.parse_program()
+    }
+
+    /// 计算每根 bar 的目标仓位
+    ///
+    /// 空仓时 BUY 条件成立返回 `Some(size)`，持仓时 SELL 条件成立返回 `Some(0.0)`，其余为 `None`（保持不变）。
+    /// 前 `warmup` 根 bar 不求值规则（指标仍使用全部数据计算）。
+    pub fn targets(&self, cols: &OhlcvColumns, size: f64, warmup: usize) -> Vec<Option<f64>> {
+        let series: Vec<Vec<Option<f64>>> = self
+            .series
+            .iter()
+            .map(|def| match def {
+                SeriesDef::Field(f) => {
+                    let col = match f.as_str() {
+                        "open" => &cols.open,
+                        "high" => &cols.high,
+                        "low" => &cols.low,
+                        "volume" => &cols.volume,
+                        _ => &cols.close,
+                    };
+                    col.iter().map(|&v| Some(v)).collect()
+                }
+                SeriesDef::Indicator(spec) => spec.compute(cols).into_iter().next().map(|(_, v)| v).unwrap_or_default(),
+            })
+            .collect();
+
+        let n = cols.close.len();
+        let mut out = vec![None; n];
+        let mut in_position = false;
+        for (i, slot) in out.iter_mut().enumerate().skip(warmup) {
+            if in_position {
+                if self.sell.iter().any(|c| eval(c, &series, i)) {
+                    *slot = Some(0.0);
+                    in_position = false;
+                }
+            } else if self.buy.iter().any(|c| eval(c, &series, i)) {
+                *slot = Some(size);
+                in_position = true;
+            }
+        }
+        out
+    }
+}
+
+fn value_at(v: Value, series: &[Vec<Option<f64>>], i: usize) -> Option<f64> {
+    match v {
+        Value::Const(c) => Some(c),
+        Value::Series(k) => series[k].get(i).copied().flatten(),
+    }
+}
+
+fn eval(c: &Cond, series: &[Vec<Option<f64>>], i: usize) -> bool {
+    match c {
+        Cond::And(a, b) => eval(a, series, i) && eval(b, series, i),
+        Cond::Or(a, b) => eval(a, series, i) || eval(b, series, i),
+        Cond::Not(a) => !eval(a, series, i),
+        Cond::Cmp(a, op, b) => match (value_at(*a, series, i), value_at(*b, series, i)) {
+            (Some(x), Some(y)) => match op {
+                CmpOp::Gt => x > y,
+                CmpOp::Lt => x < y,
+                CmpOp::Ge => x >= y,
+                CmpOp::Le => x <= y,
+                CmpOp::Eq => x == y,
+                CmpOp::Ne => x != y,
+            },
+            _ => false,
+        },
+        Cond::Cross { a, b, above } => {
+            if i == 0 {
+                return false;
+            }
+            match (
+                value_at(*a, series, i - 1),
+                value_at(*b, series, i - 1),
+                value_at(*a, series, i),
+                value_at(*b, series, i),
+            ) {
+                (Some(pa), Some(pb), Some(ca), Some(cb)) => {
+                    if *above { pa <= pb && ca > cb } else { pa >= pb && ca < cb }
+                }
+                _ => false,
+            }
+        }
+    }
+}
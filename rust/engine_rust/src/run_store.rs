@@ -0,0 +1,470 @@
+//! 回测结果持久化模块
+//!
+//! 参数扫描动辄产生成千上万个回测结果，逐个 pickle 到磁盘既难以比较也无法筛选。
+//! 这个模块把回测结果（汇总指标、净值曲线、成交记录）保存到 DuckDB，
+//! 之后可以直接用 SQL 在所有结果中筛选、排序，需要细节时再按 `run_id` 读回完整结果。
+//!
+//! ## 存储结构
+//!
+//! | 表 | 内容 |
+//! |----|------|
+//! | `runs` | 每个结果一行：`run_id`（主键）、`saved_at`、`params`（JSON 文本）、`cash`、`position`、`avg_cost`、`equity`、`realized_pnl`、常用指标列（`total_return`、`annualized_return`、`sharpe`、`max_drawdown`、`win_rate`）和完整的 `stats`（JSON 文本） |
+//! | `run_equity` | 净值曲线：`run_id`、`bar_index`、`datetime`、`equity` |
+//! | `run_trades` | 成交记录：`run_id`、`trade_index`、`order_id`、`side`、`price`、`size`、`datetime`、`symbol`、`commission`、`slippage`、`position`、`cash` |
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import optimize_grid, save_backtest_result, load_backtest_result
+//!
+//! for i, trial in enumerate(optimize_grid(cfg, factory, bars, {"fast": [5, 10], "slow": [30, 60]})):
+//!     save_backtest_result("data/results.db", f"ma_sweep_{i}", trial)  # trial 中的 params 一并保存
+//!
+//! import duckdb
+//! best = duckdb.connect("data/results.db").execute(
+//!     "SELECT run_id, params, sharpe FROM runs WHERE run_id LIKE 'ma_sweep_%' ORDER BY sharpe DESC LIMIT 10"
+//! ).fetchall()
+//! result = load_backtest_result("data/results.db", best[0][0])
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 同一 `run_id` 再次保存时覆盖旧结果（三张表在一个事务中更新）
+//! - 只保存上表中的字段，结果中的其他字段（如 `results_db`）不保存
+//! - 结果中没有 `equity_curve` / `trades`（如使用了 `results_db` 的回测）时只保存汇总行
+
+use duckdb::Connection;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::database::open_connection;
+use crate::frames;
+
+/// 回测结果汇总表名
+const RUNS_TABLE: &str = "runs";
+/// 净值曲线表名
+const RUN_EQUITY_TABLE: &str = "run_equity";
+/// 成交记录表名
+const RUN_TRADES_TABLE: &str = "run_trades";
+/// 从 `stats` 中单独存为列的常用指标（便于直接用 SQL 排序筛选）
+const STAT_COLUMNS: [&str; 5] = ["total_return", "annualized_return", "sharpe", "max_drawdown", "win_rate"];
+/// 结果中的账户字段
+const ACCOUNT_COLUMNS: [&str; 5] = ["cash", "position", "avg_cost", "equity", "realized_pnl"];
+/// 成交记录中的可选数值字段（早期版本的结果中没有，保存为 NULL）
+const TRADE_DETAIL_COLUMNS: [&str; 4] = ["commission", "slippage", "position", "cash"];
+
+fn runtime_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(msg)
+}
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 从 Python 结果字典中取出的待保存数据
+struct StoredRun {
+    params: Option<String>,
+    account: Vec<Option<f64>>,
+    stat_columns: Vec<Option<f64>>,
+    stats: Option<String>,
+    equity: Vec<(Option<String>, f64)>,
+    trades: Vec<StoredTrade>,
+}
+
+/// 一笔待保存/读出的成交
+struct StoredTrade {
+    order_id: u64,
+    side: String,
+    price: f64,
+    size: f64,
+    datetime: Option<String>,
+    symbol: Option<String>,
+    /// 依次对应 `TRADE_DETAIL_COLUMNS`
+    details: Vec<Option<f64>>,
+}
+
+fn ensure_run_tables(conn: &Connection) -> PyResult<()> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+             run_id VARCHAR PRIMARY KEY,
+             saved_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+             params VARCHAR,
+             {},
+             {},
+             stats VARCHAR
+         );
+         CREATE TABLE IF NOT EXISTS {} (
+             run_id VARCHAR NOT NULL,
+             bar_index BIGINT NOT NULL,
+             datetime VARCHAR,
+             equity DOUBLE NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS {} (
+             run_id VARCHAR NOT NULL,
+             trade_index BIGINT NOT NULL,
+             order_id UBIGINT NOT NULL,
+             side VARCHAR NOT NULL,
+             price DOUBLE NOT NULL,
+             size DOUBLE NOT NULL,
+             datetime VARCHAR,
+             symbol VARCHAR,
+             {}
+         );",
+        RUNS_TABLE,
+        ACCOUNT_COLUMNS.map(|c| format!("{} DOUBLE", c)).join(",\n             "),
+        STAT_COLUMNS.map(|c| format!("{} DOUBLE", c)).join(",\n             "),
+        RUN_EQUITY_TABLE,
+        RUN_TRADES_TABLE,
+        TRADE_DETAIL_COLUMNS.map(|c| format!("{} DOUBLE", c)).join(",\n             ")
+    ))
+    .map_err(|e| runtime_error(format!("Failed to create result tables: {}", e)))?;
+
+    // 早期版本建的成交表只有前六列，补齐成交明细列
+    let mut upgrade = vec![
+        format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS datetime VARCHAR;", RUN_TRADES_TABLE),
+        format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS symbol VARCHAR;", RUN_TRADES_TABLE),
+    ];
+    upgrade.extend(
+        TRADE_DETAIL_COLUMNS.map(|c| format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} DOUBLE;", RUN_TRADES_TABLE, c)),
+    );
+    conn.execute_batch(&upgrade.join("\n"))
+        .map_err(|e| runtime_error(format!("Failed to upgrade {}: {}", RUN_TRADES_TABLE, e)))
+}
+
+/// 读取可选的数值字段（缺失或为 `None` 时返回 `None`）
+fn optional_f64(dict: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<f64>> {
+    match dict.get_item(key)? {
+        Some(v) if !v.is_none() => v
+            .extract()
+            .map(Some)
+            .map_err(|_| value_error(format!("result field '{}' must be a number", key))),
+        _ => Ok(None),
+    }
+}
+
+/// 用 Python 的 `json.dumps` 序列化（键排序，相同参数得到相同文本，便于 SQL 比较）
+fn to_json(py: Python<'_>, obj: &Bound<'_, PyAny>) -> PyResult<String> {
+    let kwargs = PyDict::new_bound(py);
+    kwargs.set_item("sort_keys", true)?;
+    py.import_bound("json")?
+        .call_method("dumps", (obj,), Some(&kwargs))?
+        .extract()
+}
+
+fn from_json(py: Python<'_>, text: Option<String>) -> PyResult<PyObject> {
+    match text {
+        Some(text) => Ok(py.import_bound("json")?.call_method1("loads", (text,))?.into()),
+        None => Ok(py.None()),
+    }
+}
+
+/// 把 Python 结果字典转换为待保存的数据
+fn extract_run(py: Python<'_>, result: &Bound<'_, PyDict>, params: Option<&Bound<'_, PyAny>>) -> PyResult<StoredRun> {
+    let params = match params {
+        Some(p) if !p.is_none() => Some(to_json(py, p)?),
+        _ => match result.get_item("params")? {
+            Some(p) if !p.is_none() => Some(to_json(py, &p)?),
+            _ => None,
+        },
+    };
+
+    let account = ACCOUNT_COLUMNS.iter().map(|c| optional_f64(result, c)).collect::<PyResult<Vec<_>>>()?;
+
+    let (stat_columns, stats) = match result.get_item("stats")? {
+        Some(stats) if !stats.is_none() => {
+            let dict = stats
+                .downcast::<PyDict>()
+                .map_err(|_| value_error("result field 'stats' must be a dict".to_string()))?;
+            let columns = STAT_COLUMNS.iter().map(|c| optional_f64(dict, c)).collect::<PyResult<Vec<_>>>()?;
+            (columns, Some(to_json(py, &stats)?))
+        }
+        _ => (vec![None; STAT_COLUMNS.len()], None),
+    };
+
+    let mut equity = Vec::new();
+    if let Some(curve) = result.get_item("equity_curve")?.filter(|v| !v.is_none()) {
+        for (index, item) in curve.iter()?.enumerate() {
+            let item = item?;
+            let row = item
+                .downcast::<PyDict>()
+                .map_err(|_| value_error(format!("equity_curve[{}] must be a dict", index)))?;
+            let datetime = match row.get_item("datetime")? {
+                Some(v) if !v.is_none() => Some(v.str()?.to_string()),
+                _ => None,
+            };
+            let value = optional_f64(row, "equity")?
+                .ok_or_else(|| value_error(format!("equity_curve[{}] is missing 'equity'", index)))?;
+            equity.push((datetime, value));
+        }
+    }
+
+    let mut trades = Vec::new();
+    if let Some(list) = result.get_item("trades")?.filter(|v| !v.is_none()) {
+        for (index, item) in list.iter()?.enumerate() {
+            let item = item?;
+            let row = item
+                .downcast::<PyDict>()
+                .map_err(|_| value_error(format!("trades[{}] must be a dict", index)))?;
+            let field = |key: &str| {
+                row.get_item(key)?
+                    .filter(|v| !v.is_none())
+                    .ok_or_else(|| value_error(format!("trades[{}] is missing '{}'", index, key)))
+            };
+            let text = |key: &str| -> PyResult<Option<String>> {
+                row.get_item(key)?.filter(|v| !v.is_none()).map(|v| v.str().map(|s| s.to_string())).transpose()
+            };
+            trades.push(StoredTrade {
+                order_id: field("order_id")?.extract::<u64>()?,
+                side: field("side")?.extract::<String>()?,
+                price: field("price")?.extract::<f64>()?,
+                size: field("size")?.extract::<f64>()?,
+                datetime: text("datetime")?,
+                symbol: text("symbol")?,
+                details: TRADE_DETAIL_COLUMNS
+                    .iter()
+                    .map(|c| {
+                        optional_f64(row, c).map_err(|_| value_error(format!("trades[{}].{} must be a number", index, c)))
+                    })
+                    .collect::<PyResult<_>>()?,
+            });
+        }
+    }
+
+    Ok(StoredRun { params, account, stat_columns, stats, equity, trades })
+}
+
+/// 在事务中删除同一 `run_id` 的旧结果并写入新结果
+fn write_run(conn: &Connection, run_id: &str, run: &StoredRun) -> PyResult<()> {
+    for table in [RUNS_TABLE, RUN_EQUITY_TABLE, RUN_TRADES_TABLE] {
+        conn.execute(&format!("DELETE FROM {} WHERE run_id = ?", table), duckdb::params![run_id])
+            .map_err(|e| runtime_error(format!("Failed to clear previous results in {}: {}", table, e)))?;
+    }
+
+    let columns: Vec<&str> = ACCOUNT_COLUMNS.iter().chain(STAT_COLUMNS.iter()).copied().collect();
+    let mut values: Vec<Box<dyn duckdb::ToSql>> = vec![Box::new(run_id.to_string()), Box::new(run.params.clone())];
+    values.extend(run.account.iter().chain(run.stat_columns.iter()).map(|v| Box::new(*v) as Box<dyn duckdb::ToSql>));
+    values.push(Box::new(run.stats.clone()));
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (run_id, params, {}, stats) VALUES (?, ?, {}, ?)",
+            RUNS_TABLE,
+            columns.join(", "),
+            vec!["?"; columns.len()].join(", ")
+        ),
+        duckdb::params_from_iter(values.iter()),
+    )
+    .map_err(|e| runtime_error(format!("Failed to save run {}: {}", run_id, e)))?;
+
+    let mut appender = conn
+        .appender(RUN_EQUITY_TABLE)
+        .map_err(|e| runtime_error(format!("Failed to create appender for {}: {}", RUN_EQUITY_TABLE, e)))?;
+    for (index, (datetime, equity)) in run.equity.iter().enumerate() {
+        appender
+            .append_row(duckdb::params![run_id, index as i64, datetime, equity])
+            .map_err(|e| runtime_error(format!("Failed to write equity curve: {}", e)))?;
+    }
+    appender.flush().map_err(|e| runtime_error(format!("Failed to write equity curve: {}", e)))?;
+
+    let mut appender = conn
+        .appender(RUN_TRADES_TABLE)
+        .map_err(|e| runtime_error(format!("Failed to create appender for {}: {}", RUN_TRADES_TABLE, e)))?;
+    for (index, t) in run.trades.iter().enumerate() {
+        appender
+            .append_row(duckdb::params![
+                run_id,
+                index as i64,
+                t.order_id,
+                t.side,
+                t.price,
+                t.size,
+                t.datetime,
+                t.symbol,
+                t.details[0],
+                t.details[1],
+                t.details[2],
+                t.details[3]
+            ])
+            .map_err(|e| runtime_error(format!("Failed to write trades: {}", e)))?;
+    }
+    appender.flush().map_err(|e| runtime_error(format!("Failed to write trades: {}", e)))
+}
+
+/// 保存一个回测结果
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import save_backtest_result
+///
+/// result = engine.run(MyStrategy(fast=5, slow=20), bars)
+/// save_backtest_result("data/results.db", "ma_5_20", result, params={"fast": 5, "slow": 20})
+/// ```
+///
+/// # 参数
+///
+/// - `db_path`: 数据库文件路径
+/// - `run_id`: 结果标识（同一 `run_id` 再次保存时覆盖）
+/// - `result`: `run()` / `run_from_db()` 等返回的结果字典，或 `optimize_grid()` 等返回的试验字典（含 `params`、`stats`）
+/// - `params`: 可选的参数字典，序列化为 JSON 保存；未传入时使用 `result` 中的 `params` 字段（如果有）
+///
+/// # 返回值
+///
+/// 字典：`run_id`、`equity_rows`（保存的净值点数）、`trades`（保存的成交数）
+///
+/// # 注意事项
+///
+/// - `params` 和 `stats` 必须能被 `json.dumps()` 序列化
+/// - 字段类型不对（如 `equity_curve` 的元素不是字典）时返回 `ValueError`，数据库不做任何修改
+#[pyfunction]
+#[pyo3(signature = (db_path, run_id, result, params=None))]
+pub fn save_backtest_result(
+    py: Python<'_>,
+    db_path: String,
+    run_id: String,
+    result: &Bound<'_, PyDict>,
+    params: Option<&Bound<'_, PyAny>>,
+) -> PyResult<PyObject> {
+    let run = extract_run(py, result, params)?;
+
+    let conn = open_connection(&db_path)?;
+    ensure_run_tables(&conn)?;
+    conn.execute("BEGIN TRANSACTION", [])
+        .map_err(|e| runtime_error(format!("Failed to begin transaction: {}", e)))?;
+    if let Err(e) = write_run(&conn, &run_id, &run) {
+        let _ = conn.execute("ROLLBACK", []);
+        return Err(e);
+    }
+    conn.execute("COMMIT", []).map_err(|e| runtime_error(format!("Failed to commit transaction: {}", e)))?;
+
+    let summary = PyDict::new_bound(py);
+    summary.set_item("run_id", run_id)?;
+    summary.set_item("equity_rows", run.equity.len())?;
+    summary.set_item("trades", run.trades.len())?;
+    Ok(summary.into())
+}
+
+/// 读取一个已保存的回测结果
+///
+/// ## 实际使用场景
+///
+/// ```python
+/// from engine_rust import load_backtest_result
+///
+/// result = load_backtest_result("data/results.db", "ma_5_20")
+/// print(result["params"], result["stats"]["sharpe"], len(result["equity_curve"]))
+/// ```
+///
+/// # 返回值
+///
+/// 与 `run()` 结果格式相同的字典（`cash`、`position`、`avg_cost`、`equity`、`realized_pnl`、
+/// `equity_curve`、`trades`、`stats`），另加 `run_id`、`saved_at` 和 `params`（未保存参数时为 `None`）；
+/// `run_id` 不存在时返回 `None`。结果同样是 `BacktestResult`，可以调用 `equity_curve_df()` / `trades_df()`
+#[pyfunction]
+pub fn load_backtest_result(py: Python<'_>, db_path: String, run_id: String) -> PyResult<Option<PyObject>> {
+    let conn = open_connection(&db_path)?;
+    ensure_run_tables(&conn)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT strftime(saved_at, '%Y-%m-%d %H:%M:%S'), params, {}, stats FROM {} WHERE run_id = ?",
+            ACCOUNT_COLUMNS.join(", "),
+            RUNS_TABLE
+        ))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let row = stmt
+        .query_map(duckdb::params![run_id], |row| {
+            let mut account = Vec::with_capacity(ACCOUNT_COLUMNS.len());
+            for k in 0..ACCOUNT_COLUMNS.len() {
+                account.push(row.get::<_, Option<f64>>(2 + k)?);
+            }
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                account,
+                row.get::<_, Option<String>>(2 + ACCOUNT_COLUMNS.len())?,
+            ))
+        })
+        .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?
+        .next()
+        .transpose()
+        .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+    let Some((saved_at, params, account, stats)) = row else { return Ok(None) };
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT datetime, equity FROM {} WHERE run_id = ? ORDER BY bar_index",
+            RUN_EQUITY_TABLE
+        ))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let equity = stmt
+        .query_map(duckdb::params![run_id], |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, f64>(1)?)))
+        .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT order_id, side, price, size, datetime, symbol, {} FROM {} WHERE run_id = ? ORDER BY trade_index",
+            TRADE_DETAIL_COLUMNS.join(", "),
+            RUN_TRADES_TABLE
+        ))
+        .map_err(|e| runtime_error(format!("Failed to prepare query: {}", e)))?;
+    let trades = stmt
+        .query_map(duckdb::params![run_id], |row| {
+            Ok(StoredTrade {
+                order_id: row.get(0)?,
+                side: row.get(1)?,
+                price: row.get(2)?,
+                size: row.get(3)?,
+                datetime: row.get(4)?,
+                symbol: row.get(5)?,
+                details: (0..TRADE_DETAIL_COLUMNS.len())
+                    .map(|k| row.get::<_, Option<f64>>(6 + k))
+                    .collect::<Result<_, _>>()?,
+            })
+        })
+        .map_err(|e| runtime_error(format!("Failed to execute query: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| runtime_error(format!("Failed to read row: {}", e)))?;
+
+    // 与 run() 的结果类型相同，equity_curve_df() / trades_df() 从读出的列表构建表格
+    let result = frames::new_result(py, Vec::new(), Vec::new())?;
+    result.set_item("run_id", &run_id)?;
+    result.set_item("saved_at", saved_at)?;
+    result.set_item("params", from_json(py, params)?)?;
+    for (column, value) in ACCOUNT_COLUMNS.iter().zip(account) {
+        result.set_item(*column, value)?;
+    }
+
+    let eq_list = PyList::empty_bound(py);
+    for (datetime, value) in equity {
+        let row = PyDict::new_bound(py);
+        row.set_item("datetime", datetime)?;
+        row.set_item("equity", value)?;
+        eq_list.append(row)?;
+    }
+    result.set_item("equity_curve", eq_list)?;
+
+    let tr_list = PyList::empty_bound(py);
+    for trade in trades {
+        let t = PyDict::new_bound(py);
+        t.set_item("order_id", trade.order_id)?;
+        t.set_item("side", trade.side)?;
+        t.set_item("price", trade.price)?;
+        t.set_item("size", trade.size)?;
+        t.set_item("datetime", trade.datetime)?;
+        t.set_item("symbol", trade.symbol)?;
+        for (column, value) in TRADE_DETAIL_COLUMNS.iter().zip(trade.details) {
+            t.set_item(*column, value)?;
+        }
+        tr_list.append(t)?;
+    }
+    result.set_item("trades", tr_list)?;
+
+    let stats = match stats {
+        Some(text) => from_json(py, Some(text))?,
+        None => PyDict::new_bound(py).into(),
+    };
+    result.set_item("stats", stats)?;
+    Ok(Some(result.into()))
+}
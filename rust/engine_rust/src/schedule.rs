@@ -0,0 +1,173 @@
+//! 日历调度模块
+//!
+//! 这个模块负责回测中的日历逻辑：判断某根 bar 是否是一个交易日/周/月/季度的最后一根，
+//! 以及是否到达用户指定的调仓日期。引擎据此调用策略的 `on_rebalance(ctx)` 和
+//! `on_session_end(ctx)`，策略无需再在 Python 中解析 datetime 字符串判断"是不是月末"。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 把每根 bar 的 `datetime` 解析为日期
+//! 2. 按频率把日期映射为"周期键"（如月度为 `(年, 月)`）
+//! 3. 如果下一根 bar 的周期键不同（或已经是最后一根 bar），当前 bar 就是周期末
+//!
+//! 回测时整段数据已知，因此可以直接用下一根 bar 的日期判断周期末，
+//! 这与"在当天收盘时知道今天是否是本月最后一个交易日"的交易日历假设一致。
+//!
+//! 配置了交易日历（`BacktestConfig(calendar=...)`）时，"下一个日期"改为日历上的下一个交易日：
+//! 数据缺少周期内最后几个交易日时仍在最后一根 bar 调仓，数据在周期中间结束时最后一根 bar 不会被误当作周期末。
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::calendar::TradingCalendar;
+use crate::database::parse_datetime;
+
+/// 调仓频率
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RebalanceFreq {
+    /// 每个交易日的最后一根 bar
+    Daily,
+    /// 每周（ISO 周）的最后一根 bar
+    Weekly,
+    /// 每月的最后一根 bar
+    Monthly,
+    /// 每季度的最后一根 bar
+    Quarterly,
+}
+
+impl RebalanceFreq {
+    /// 从字符串解析频率（不区分大小写），支持 `daily`/`weekly`/`monthly`/`quarterly`
+    /// 以及别名 `day`/`week`/`month`/`month_end`/`quarter`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "daily" | "day" => Some(RebalanceFreq::Daily),
+            "weekly" | "week" => Some(RebalanceFreq::Weekly),
+            "monthly" | "month" | "month_end" => Some(RebalanceFreq::Monthly),
+            "quarterly" | "quarter" => Some(RebalanceFreq::Quarterly),
+            _ => None,
+        }
+    }
+
+    /// 当前日期是否为所在周期的最后一根 bar（`next` 为下一根 bar 的日期，`None` 表示没有下一根）
+    pub fn is_period_end(self, cur: NaiveDate, next: Option<NaiveDate>) -> bool {
+        next.is_none_or(|n| self.period_key(n) != self.period_key(cur))
+    }
+
+    /// 日期所属周期的键，键相同表示属于同一周期
+    fn period_key(self, d: NaiveDate) -> (i32, u32) {
+        match self {
+            RebalanceFreq::Daily => (d.year(), d.ordinal()),
+            RebalanceFreq::Weekly => {
+                let w = d.iso_week();
+                (w.year(), w.week())
+            }
+            RebalanceFreq::Monthly => (d.year(), d.month()),
+            RebalanceFreq::Quarterly => (d.year(), (d.month() - 1) / 3),
+        }
+    }
+}
+
+/// 解析 bar 的日期（无法解析时为 `None`）
+pub(crate) fn bar_dates<'a, I>(datetimes: I) -> Vec<Option<NaiveDate>>
+where
+    I: IntoIterator<Item = Option<&'a str>>,
+{
+    datetimes.into_iter().map(|dt| dt.and_then(parse_datetime).map(|d| d.date())).collect()
+}
+
+/// 每根 bar 之后下一个有效日期；`lookahead` 是这批 bar 之后的第一个有效日期（流式数据的下一批）
+pub(crate) fn next_dates(dates: &[Option<NaiveDate>], lookahead: Option<NaiveDate>) -> Vec<Option<NaiveDate>> {
+    let mut next = lookahead;
+    let mut next_of = vec![None; dates.len()];
+    for i in (0..dates.len()).rev() {
+        next_of[i] = next;
+        if dates[i].is_some() {
+            next = dates[i];
+        }
+    }
+    next_of
+}
+
+/// 标记每根 bar 是否为交易时段（交易日）的最后一根
+///
+/// 用于 `on_session_end(ctx)`：下一根可解析日期的 bar 落在不同日期，或已是最后一根时为 `true`；
+/// 日期无法解析的 bar 为 `false`。
+pub fn session_end_flags(dates: &[Option<NaiveDate>]) -> Vec<bool> {
+    RebalanceSchedule { freq: Some(RebalanceFreq::Daily), ..Default::default() }.flags(dates)
+}
+
+/// 回测中使用的调仓计划
+///
+/// 周期性频率与自定义日期可以同时配置，两者任一触发即调仓。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RebalanceSchedule {
+    /// 周期性调仓频率
+    pub freq: Option<RebalanceFreq>,
+    /// 自定义调仓日期（已排序、去重）
+    pub dates: Vec<NaiveDate>,
+    /// 交易日历；配置后按日历上的下一个交易日判断周期末
+    pub calendar: Option<TradingCalendar>,
+}
+
+impl RebalanceSchedule {
+    /// 由配置解析调仓计划，频率或日期无法识别时返回错误信息
+    pub fn from_config(freq: Option<&str>, dates: Option<&[String]>, calendar: Option<TradingCalendar>) -> Result<Self, String> {
+        let freq = match freq {
+            Some(f) => Some(RebalanceFreq::parse(f).ok_or_else(|| {
+                format!("Unsupported rebalance frequency: {} (expected daily/weekly/monthly/quarterly)", f)
+            })?),
+            None => None,
+        };
+        let mut parsed = Vec::new();
+        for s in dates.unwrap_or(&[]) {
+            let d = parse_datetime(s).ok_or_else(|| format!("Invalid rebalance date: {}", s))?;
+            parsed.push(d.date());
+        }
+        parsed.sort();
+        parsed.dedup();
+        Ok(Self { freq, dates: parsed, calendar })
+    }
+
+    /// 是否配置了任何调仓
+    pub fn is_active(&self) -> bool {
+        self.freq.is_some() || !self.dates.is_empty()
+    }
+
+    /// 逐 bar 判断是否需要调仓
+    ///
+    /// - `cur`: 当前 bar 的日期
+    /// - `next`: 下一根（可解析日期的）bar 的日期，`None` 表示当前已是最后一根
+    /// - `cursor`: 自定义日期的游标，调用方在整个回测中持有并传入同一个变量
+    ///
+    /// 自定义日期落在非交易日时，顺延到之后的第一根 bar；同一根 bar 覆盖多个日期时只触发一次。
+    /// 配置了交易日历时，`next` 与当前日期不同（包括没有下一根）的情况下改用日历上的下一个交易日判断周期末。
+    pub fn is_due(&self, cur: Option<NaiveDate>, next: Option<NaiveDate>, cursor: &mut usize) -> bool {
+        let Some(cur) = cur else { return false };
+        let mut due = false;
+        while *cursor < self.dates.len() && self.dates[*cursor] <= cur {
+            due = true;
+            *cursor += 1;
+        }
+        if let Some(freq) = self.freq {
+            let next = match &self.calendar {
+                Some(cal) if next != Some(cur) => Some(cal.next_trading_day(cur)),
+                _ => next,
+            };
+            due |= freq.is_period_end(cur, next);
+        }
+        due
+    }
+
+    /// 标记每根 bar 是否需要调仓（单资产回测可一次性预计算）
+    pub fn flags(&self, dates: &[Option<NaiveDate>]) -> Vec<bool> {
+        let mut flags = vec![false; dates.len()];
+        if !self.is_active() {
+            return flags;
+        }
+        let mut cursor = 0;
+        let next_of = next_dates(dates, None);
+        for (i, d) in dates.iter().enumerate() {
+            flags[i] = self.is_due(*d, next_of[i], &mut cursor);
+        }
+        flags
+    }
+}
@@ -0,0 +1,100 @@
+//! 交易时段过滤模块
+//!
+//! A 股、期货等市场只在固定时段内撮合（如 09:30–11:30、13:00–15:00），而回测数据里常常混有
+//! 集合竞价、午休或盘后的 bar。策略在这些 bar 上下单，回测会按一个实盘根本成交不了的价格成交。
+//! 配置交易时段后，引擎对时段外提交的订单按策略拒绝或顺延到下一个时段开盘。
+//!
+//! ## 配置方式
+//!
+//! ```python
+//! cfg = BacktestConfig("2024-01-01", "2024-12-31", 100000.0,
+//!                      sessions=["09:30-11:30", "13:00-15:00"],
+//!                      session_policy="defer")
+//! ```
+//!
+//! - 时段格式为 `"HH:MM-HH:MM"`（也接受 `"HH:MM:SS"`），两端都包含在时段内
+//! - 开始时间晚于结束时间表示跨越午夜的夜盘，如 `"21:00-02:30"`
+//! - `session_policy="reject"`（默认）：时段外的订单被拒绝，`on_order` 收到 `event="rejected"`、
+//!   `reason="outside_session"`（事件中的 `message` 为说明文字，见 `order_events.rs`）
+//! - `session_policy="defer"`：时段外的订单暂存，`on_order` 收到 `event="deferred"`；
+//!   在下一根时段内的 bar 开始时（调用 `next()` 之前）按该 bar 的开盘价撮合
+//!
+//! # 注意事项
+//!
+//! - 只有日期、没有时间的 bar（如日线 `"2024-01-02"`）以及没有 `datetime` 的 bar 视为在时段内
+//! - 预热期的 bar 本来就不执行订单，暂存的订单会等到预热结束后的第一根时段内 bar
+//! - 回测结束时仍未执行的暂存订单被丢弃，`on_order` 收到 `event="cancelled"`、`reason="run_ended"`
+//! - `run_multi` 同样按时段拒绝或暂存订单并触发相同的 `on_order` 事件，
+//!   暂存的订单按各标的在时段内第一个时间点的开盘价撮合
+
+use chrono::{NaiveTime, Timelike};
+
+use crate::database::parse_datetime;
+
+/// 时段外订单的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SessionPolicy {
+    /// 拒绝
+    Reject,
+    /// 顺延到下一个时段开盘
+    Defer,
+}
+
+impl SessionPolicy {
+    pub(crate) fn parse(policy: &str) -> Result<Self, String> {
+        match policy.to_lowercase().as_str() {
+            "reject" => Ok(SessionPolicy::Reject),
+            "defer" => Ok(SessionPolicy::Defer),
+            other => Err(format!("Unsupported session_policy '{}': expected 'reject' or 'defer'", other)),
+        }
+    }
+}
+
+/// 允许交易的时段集合
+#[derive(Clone, Debug)]
+pub(crate) struct TradingSessions {
+    windows: Vec<(NaiveTime, NaiveTime)>,
+}
+
+impl TradingSessions {
+    /// 解析时段列表（如 `["09:30-11:30", "13:00-15:00"]`）
+    pub(crate) fn parse(sessions: &[String]) -> Result<Self, String> {
+        if sessions.is_empty() {
+            return Err("sessions must contain at least one window".to_string());
+        }
+        let windows = sessions
+            .iter()
+            .map(|s| {
+                let invalid = || format!("Invalid session '{}': expected 'HH:MM-HH:MM'", s);
+                let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+                let start = parse_time(start.trim()).ok_or_else(invalid)?;
+                let end = parse_time(end.trim()).ok_or_else(invalid)?;
+                Ok((start, end))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { windows })
+    }
+
+    /// bar 时间是否在交易时段外；没有时间部分或无法解析的 `datetime` 视为在时段内
+    pub(crate) fn outside(&self, datetime: Option<&str>) -> bool {
+        let Some(dt) = datetime.filter(|s| s.len() > 10).and_then(parse_datetime) else { return false };
+        !self.contains(dt.time())
+    }
+
+    /// 时间是否落在任一时段内
+    pub(crate) fn contains(&self, t: NaiveTime) -> bool {
+        let t = t.with_nanosecond(0).unwrap_or(t);
+        self.windows.iter().any(|&(start, end)| {
+            if start <= end {
+                start <= t && t <= end
+            } else {
+                // 跨越午夜的夜盘
+                t >= start || t <= end
+            }
+        })
+    }
+}
+
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(s, "%H:%M").or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S")).ok()
+}
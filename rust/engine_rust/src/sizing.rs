@@ -0,0 +1,221 @@
+//! 订单数量换算模块
+//!
+//! 策略想表达的往往是"用一成资金买入"或"这笔交易最多亏净值的 1%"，原先只能在 `next()` 里
+//! 自己用 `ctx` 的净值和价格算股数、再处理整手。这个模块让订单字典直接给出仓位意图，
+//! 由引擎按下单时的净值和价格换算为数量，并按整手和最小变动价位取整。
+//!
+//! ## 换算方式
+//!
+//! | `sizing` | 需要的字段 | 数量 |
+//! |----------|-----------|------|
+//! | `"percent_equity"` | `value`（净值比例，如 `0.1`） | `value × 净值 ÷ 价格` |
+//! | `"fixed_risk"` | `value`（单笔风险占净值比例，如 `0.01`），`stop_distance` 或 `atr`（可选 `atr_multiple`，默认 1） | `value × 净值 ÷ 止损距离` |
+//! | `"kelly"` | `value`（Kelly 比例，如 `0.5` 为半凯利），可选 `min_trades`（默认 20）、`fallback`（默认 `0.01`） | `value × f* × 净值 ÷ 价格` |
+//!
+//! `kelly` 的 `f* = p - (1 - p) / b` 由回测至今的平仓成交实时估计：`p` 为胜率，`b` 为盈亏比
+//! （平均盈利收益率 ÷ 平均亏损收益率，收益率按平仓价相对持仓成本计算）。平仓成交不足 `min_trades` 笔时
+//! 按 `fallback`（净值比例）试探建仓；`f*` 不为正（没有优势）时数量为 0。
+//!
+//! 换算后的数量按 `lot`（默认 1，即整数股；多资产回测默认取 `run_multi(lot_size=...)`）向下取整；
+//! 给出 `tick` 时限价按最小变动价位取整（买单向下、卖单向上，不会比给定限价更差）。
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! class MyStrategy(Strategy):
+//!     def next(self, bar, ctx):
+//!         if self.entry_signal(bar):
+//!             # 单笔风险为净值的 1%，止损放在 2 倍 ATR 处，按 100 股一手下单
+//!             return {"action": "BUY", "sizing": "fixed_risk", "value": 0.01, "atr": bar["atr_14"], "atr_multiple": 2, "lot": 100}
+//!         if self.add_signal(bar):
+//!             return {"action": "BUY", "sizing": "percent_equity", "value": 0.1}
+//!         if self.trend_signal(bar):
+//!             # 半凯利：胜率与盈亏比随平仓成交累积不断更新
+//!             return {"action": "BUY", "sizing": "kelly", "value": 0.5, "min_trades": 30}
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 净值与价格取下单时的最新值（现金 + 持仓市值，价格为限价单的限价或最新价），不考虑手续费和滑点
+//! - 换算只决定数量，不检查现金是否足够；需要限制杠杆时配合敞口限制（`max_gross_exposure` 等）
+//! - `sizing` 与 `size` 不能同时给出；取整后数量为 0 的订单在单资产回测中按 `invalid_size` 拒绝，多资产回测中忽略
+//! - `kelly` 只统计多头平仓（卖出减少多头持仓）的成交，与已实现盈亏的口径一致；多资产回测按组合统一统计
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::{Deserialize, Serialize};
+
+use crate::weights::LotSizes;
+use crate::OrderSide;
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 读取可选的正数字段
+fn positive_field(d: &PyDict, key: &str) -> PyResult<Option<f64>> {
+    let Some(value) = d.get_item(key)?.filter(|v| !v.is_none()) else { return Ok(None) };
+    let number: f64 = value
+        .extract()
+        .map_err(|_| value_error(format!("Order field '{}' must be a number", key)))?;
+    if !(number.is_finite() && number > 0.0) {
+        return Err(value_error(format!("Order field '{}' must be a positive number, got {}", key, number)));
+    }
+    Ok(Some(number))
+}
+
+/// 换算数量所需的账户状态
+pub(crate) struct SizingBasis<'a> {
+    /// 下单时的组合净值
+    pub(crate) equity: f64,
+    /// 多资产回测的每手数量（`run_multi(lot_size=...)`）
+    pub(crate) lots: Option<&'a LotSizes>,
+    /// 回测至今的平仓胜率与盈亏比（`kelly` 使用）
+    pub(crate) kelly: &'a KellyStats,
+}
+
+/// 平仓成交的胜负统计（收益率按平仓价相对持仓成本计算）
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct KellyStats {
+    wins: usize,
+    losses: usize,
+    /// 盈利成交的收益率之和
+    win_sum: f64,
+    /// 亏损成交的收益率绝对值之和
+    loss_sum: f64,
+}
+
+impl KellyStats {
+    /// 记录一笔平仓成交（持仓成本不为正时忽略）
+    pub(crate) fn record(&mut self, exec_price: f64, avg_cost: f64) {
+        if avg_cost <= 0.0 {
+            return;
+        }
+        let ret = (exec_price - avg_cost) / avg_cost;
+        if ret > 0.0 {
+            self.wins += 1;
+            self.win_sum += ret;
+        } else if ret < 0.0 {
+            self.losses += 1;
+            self.loss_sum -= ret;
+        }
+    }
+
+    /// 完整 Kelly 比例 `f* = p - (1 - p) / b`；统计的成交不足 `min_trades` 笔时返回 `None`
+    fn fraction(&self, min_trades: usize) -> Option<f64> {
+        let n = self.wins + self.losses;
+        if n == 0 || n < min_trades {
+            return None;
+        }
+        let p = self.wins as f64 / n as f64;
+        if self.losses == 0 {
+            return Some(1.0);
+        }
+        if self.wins == 0 {
+            return Some(0.0);
+        }
+        let payoff = (self.win_sum / self.wins as f64) / (self.loss_sum / self.losses as f64);
+        Some(p - (1.0 - p) / payoff)
+    }
+}
+
+/// 仓位意图
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SizingMode {
+    /// 净值比例
+    PercentEquity,
+    /// 固定风险：止损距离（价格单位）
+    FixedRisk { distance: f64 },
+    /// Kelly 比例：统计不足 `min_trades` 笔时按 `fallback`（净值比例）
+    Kelly { min_trades: usize, fallback: f64 },
+}
+
+/// 订单字典中的数量换算设置
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct OrderSizing {
+    mode: SizingMode,
+    value: f64,
+    lot: Option<f64>,
+}
+
+impl OrderSizing {
+    /// 解析订单字典中的 `sizing` 相关字段；没有 `sizing` 时返回 `None`
+    pub(crate) fn parse(d: &PyDict) -> PyResult<Option<Self>> {
+        let Some(mode) = d.get_item("sizing")?.filter(|v| !v.is_none()) else { return Ok(None) };
+        let mode: String = mode.extract()?;
+        if d.contains("size")? {
+            return Err(value_error("Order cannot have both 'size' and 'sizing'".to_string()));
+        }
+        let Some(value) = positive_field(d, "value")? else {
+            return Err(value_error(format!("sizing='{}' requires a positive 'value'", mode)));
+        };
+        let mode = match mode.to_lowercase().as_str() {
+            "percent_equity" => SizingMode::PercentEquity,
+            "fixed_risk" => {
+                let distance = match (positive_field(d, "stop_distance")?, positive_field(d, "atr")?) {
+                    (Some(distance), _) => distance,
+                    (None, Some(atr)) => atr * positive_field(d, "atr_multiple")?.unwrap_or(1.0),
+                    (None, None) => {
+                        return Err(value_error("sizing='fixed_risk' requires 'stop_distance' or 'atr'".to_string()))
+                    }
+                };
+                SizingMode::FixedRisk { distance }
+            }
+            "kelly" => {
+                let min_trades = match d.get_item("min_trades")?.filter(|v| !v.is_none()) {
+                    Some(v) => v
+                        .extract::<usize>()
+                        .map_err(|_| value_error("Order field 'min_trades' must be a non-negative integer".to_string()))?,
+                    None => 20,
+                };
+                SizingMode::Kelly { min_trades, fallback: positive_field(d, "fallback")?.unwrap_or(0.01) }
+            }
+            other => {
+                return Err(value_error(format!(
+                    "Unsupported sizing '{}': expected 'percent_equity', 'fixed_risk' or 'kelly'",
+                    other
+                )))
+            }
+        };
+        Ok(Some(Self { mode, value, lot: positive_field(d, "lot")? }))
+    }
+
+    /// 按净值与价格换算数量，并向下取整到整手
+    pub(crate) fn size(&self, symbol: &str, price: f64, basis: &SizingBasis<'_>) -> f64 {
+        let raw = match self.mode {
+            SizingMode::PercentEquity if price > 0.0 => self.value * basis.equity / price,
+            SizingMode::PercentEquity => 0.0,
+            SizingMode::FixedRisk { distance } => self.value * basis.equity / distance,
+            SizingMode::Kelly { min_trades, fallback } if price > 0.0 => {
+                let fraction = match basis.kelly.fraction(min_trades) {
+                    Some(kelly) => self.value * kelly,
+                    None => fallback,
+                };
+                fraction * basis.equity / price
+            }
+            SizingMode::Kelly { .. } => 0.0,
+        };
+        if !(raw.is_finite() && raw > 0.0) {
+            return 0.0;
+        }
+        let lot = self.lot.or_else(|| basis.lots.and_then(|lots| lots.get(symbol))).unwrap_or(1.0);
+        // 容忍浮点误差：2.9999999 手视为 3 手
+        (raw / lot + 1e-9).floor() * lot
+    }
+}
+
+/// 订单字典中的最小变动价位（`tick`）
+pub(crate) fn tick_size(d: &PyDict) -> PyResult<Option<f64>> {
+    positive_field(d, "tick")
+}
+
+/// 限价按最小变动价位取整：买单向下、卖单向上
+pub(crate) fn round_to_tick(price: f64, tick: f64, side: OrderSide) -> f64 {
+    let ticks = price / tick;
+    let ticks = match side {
+        OrderSide::Buy => (ticks + 1e-9).floor(),
+        OrderSide::Sell => (ticks - 1e-9).ceil(),
+    };
+    // 去掉乘法带来的浮点尾差（10.200000000000001 → 10.2）
+    (ticks * tick * 1e12).round() / 1e12
+}
@@ -0,0 +1,165 @@
+//! 策略基类模块
+//!
+//! 引擎对每根 bar、每个订单事件都会调用策略的 `next` / `on_order` / `on_trade`，即使策略里只是
+//! `pass`：一次 Python 方法调用本身就要构造参数、查找属性，成交频繁的回测里这部分开销相当可观。
+//! 这个模块提供扩展里的 `Strategy` 基类，所有钩子都有默认的空实现；策略继承它时，引擎在回测开始时
+//! 检测哪些钩子被子类重写，没有重写的钩子直接跳过，不再进入 Python。
+//! 可选钩子（`on_rebalance`、`on_bar_end`、`on_session_end`、`get_state`/`set_state`）同样如此：
+//! 它们每根 bar 或每个检查点都可能被调用，基类里的默认实现不会让所有子类都付出这份开销。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. `Strategy` 是可被 Python 继承的 Rust 类，`on_start/next/on_order/on_trade/on_stop` 以及可选钩子都是空实现
+//! 2. 回测开始时比较 `type(strategy).<钩子>` 与 `Strategy.<钩子>`：两者是同一个对象（且实例上也没有同名属性）
+//!    说明子类没有重写
+//! 3. 没有重写的钩子在整个回测中都不调用（跳过的同时也省去了构造事件字典的开销）
+//! 4. 不继承 `Strategy` 的策略（鸭子类型）行为不变：基础钩子照常调用，可选钩子在定义了同名属性时调用
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! from engine_rust import Strategy
+//!
+//! class SmaCross(Strategy):
+//!     def __init__(self, window=20):
+//!         self.window = window
+//!
+//!     def next(self, bar, ctx=None):
+//!         ...                        # 只重写 next：on_order/on_trade 等不会被调用
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 检测只在回测（或 `PaperTrader` / `Replayer` 创建时）进行一次，回测开始后再给实例或类挂上的钩子不会被调用
+//! - `pyrust_bt.Strategy` 继承自这个类，原有的策略写法不需要修改
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+
+/// 策略基类：所有钩子默认不做任何事
+///
+/// 继承后只重写需要的钩子，引擎会跳过没有重写的钩子。
+#[pyclass(subclass, module = "engine_rust")]
+pub struct Strategy;
+
+#[pymethods]
+impl Strategy {
+    /// 接受任意参数，子类可以自由定义 `__init__`
+    #[new]
+    #[pyo3(signature = (*_args, **_kwargs))]
+    fn new(_args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>) -> Self {
+        Strategy
+    }
+
+    /// 回测开始时调用，`ctx` 为初始账户快照（`EngineContext`；多资产回测为字典）
+    #[pyo3(signature = (ctx))]
+    fn on_start(&self, ctx: &Bound<'_, PyAny>) {
+        let _ = ctx;
+    }
+
+    /// 每根 bar 调用，返回 `"BUY"`/`"SELL"`、订单字典（多资产时可为列表）或 `None`（不下单）
+    ///
+    /// `ctx` 为本 bar 的账户快照；只接受 `bar` 一个参数的重写同样可用。
+    #[pyo3(signature = (bar, ctx=None))]
+    fn next(&self, bar: &Bound<'_, PyAny>, ctx: Option<&Bound<'_, PyAny>>) -> Option<PyObject> {
+        let _ = (bar, ctx);
+        None
+    }
+
+    /// 订单事件：`submitted`/`filled`/`rejected`/`deferred` 等，`event` 为字典
+    #[pyo3(signature = (event))]
+    fn on_order(&self, event: &Bound<'_, PyAny>) {
+        let _ = event;
+    }
+
+    /// 成交事件：`event` 为成交字典（`order_id`、`side`、`price`、`size` 等）
+    #[pyo3(signature = (event))]
+    fn on_trade(&self, event: &Bound<'_, PyAny>) {
+        let _ = event;
+    }
+
+    /// 回测结束时调用
+    fn on_stop(&self) {}
+
+    /// 调仓回调：仅在配置了 `rebalance` / `rebalance_dates` 时于调仓点（`next` 之后）调用，返回值格式与 `next` 相同
+    #[pyo3(signature = (ctx))]
+    fn on_rebalance(&self, ctx: &Bound<'_, PyAny>) -> Option<PyObject> {
+        let _ = ctx;
+        None
+    }
+
+    /// 每根 bar 的成交全部处理完毕后调用（预热期除外），`ctx` 为成交后的账户快照
+    #[pyo3(signature = (ctx))]
+    fn on_bar_end(&self, ctx: &Bound<'_, PyAny>) {
+        let _ = ctx;
+    }
+
+    /// 交易日最后一根 bar 处理完毕后调用（在 `on_bar_end` 之后）
+    #[pyo3(signature = (ctx))]
+    fn on_session_end(&self, ctx: &Bound<'_, PyAny>) {
+        let _ = ctx;
+    }
+
+    /// 返回需要随检查点保存的策略状态（须可被 `json.dumps` 序列化），默认不保存
+    fn get_state(&self) -> Option<PyObject> {
+        None
+    }
+
+    /// 从检查点恢复时调用（在 `on_start` 之后），`state` 为 `get_state` 返回值的 JSON 往返结果
+    #[pyo3(signature = (state))]
+    fn set_state(&self, state: &Bound<'_, PyAny>) {
+        let _ = state;
+    }
+}
+
+/// 继承 `Strategy` 的策略是否重写了钩子 `name`
+///
+/// 实例上直接赋值的钩子（如在 __init__ 中 self.on_trade = ...）同样算作重写。
+fn overrides(obj: &Bound<'_, PyAny>, name: &str) -> PyResult<bool> {
+    if let Ok(attrs) = obj.getattr("__dict__") {
+        if attrs.contains(name)? {
+            return Ok(true);
+        }
+    }
+    let base = obj.py().get_type_bound::<Strategy>();
+    Ok(!obj.get_type().getattr(name)?.is(&base.getattr(name)?))
+}
+
+/// 策略需要调用的钩子（回测开始时检测一次）
+///
+/// 继承 `Strategy` 时只调用子类重写过的钩子；鸭子类型的策略照常调用基础钩子，
+/// 可选钩子在定义了同名属性时调用。
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StrategyHooks {
+    pub(crate) on_start: bool,
+    pub(crate) next: bool,
+    pub(crate) on_order: bool,
+    pub(crate) on_trade: bool,
+    pub(crate) on_stop: bool,
+    pub(crate) on_rebalance: bool,
+    pub(crate) on_bar_end: bool,
+    pub(crate) on_session_end: bool,
+    pub(crate) get_state: bool,
+    pub(crate) set_state: bool,
+}
+
+impl StrategyHooks {
+    pub(crate) fn detect(py: Python<'_>, strategy: &PyObject) -> PyResult<Self> {
+        let obj = strategy.bind(py);
+        let subclass = obj.is_instance_of::<Strategy>();
+        let base = |name: &str| if subclass { overrides(obj, name) } else { Ok(true) };
+        let optional = |name: &str| if subclass { overrides(obj, name) } else { obj.hasattr(name) };
+        Ok(Self {
+            on_start: base("on_start")?,
+            next: base("next")?,
+            on_order: base("on_order")?,
+            on_trade: base("on_trade")?,
+            on_stop: base("on_stop")?,
+            on_rebalance: optional("on_rebalance")?,
+            on_bar_end: optional("on_bar_end")?,
+            on_session_end: optional("on_session_end")?,
+            get_state: optional("get_state")?,
+            set_state: optional("set_state")?,
+        })
+    }
+}
@@ -0,0 +1,174 @@
+//! 流式数据源模块
+//!
+//! `run()` 原本需要一次性拿到全部 K 线：字典列表、列式数据都要完整放进内存。
+//! 多年的 tick/分钟数据可能比内存还大，这个模块让引擎按需逐批拉取数据，
+//! 内存中只保留当前批次和预读的下一批。
+//!
+//! ## 支持的数据源
+//!
+//! - **Python 迭代器/生成器**：每次产出一根 bar 字典，或者一批数据
+//!   （字典列表、列字典、pyarrow `RecordBatch`、DataFrame 等 `run()` 接受的任意格式）
+//! - **DB-API 游标**（如 DuckDB/sqlite3 执行查询后的游标）：通过 `fetchmany(batch_size)` 分批读取，
+//!   列名取自 `cursor.description`
+//! - **pyarrow `RecordBatchReader`**：如 DuckDB 的 `fetch_record_batch()`，逐个 `RecordBatch` 读取
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 引擎每次向数据源请求一批数据（逐根产出的 bar 会攒够 `batch_size` 根再交给引擎）
+//! 2. 处理当前批次时已预读下一批，因此调仓、交易日结束等需要"下一根 bar 日期"的判断与非流式一致
+//! 3. 数据源耗尽后照常调用 `on_stop()` 并返回结果
+//!
+//! # 注意事项
+//!
+//! - 数据源只能被消费一次
+//! - 流式模式下不支持 `indicators`（预计算需要完整数据）和检查点
+//! - 总 bar 数事先未知，进度回调中的 `total` 为 0，直到数据源耗尽
+
+use pyo3::exceptions::PyStopIteration;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple};
+
+use crate::bar_fields::{self, FieldNames, FieldValue};
+use crate::columnar::extract_bars_any;
+use crate::validation::ParsedFields;
+use crate::{extract_bar, extract_datetime, BarData};
+
+/// 数据源类型
+enum Source {
+    /// 迭代器：每个元素是一根 bar 或一批数据
+    Iter(PyObject),
+    /// DB-API 游标：`fetchmany()` 返回的行按列名映射
+    Cursor { cursor: PyObject, columns: Vec<String> },
+}
+
+/// 分批拉取 bar 的流式数据源
+pub(crate) struct BarStream {
+    source: Source,
+    batch_size: usize,
+    /// 额外字段名缓存（各批次共享）
+    names: FieldNames,
+}
+
+impl BarStream {
+    /// 识别流式数据源；不是流式数据（列表、列字典、Table、DataFrame 等）时返回 `None`
+    pub(crate) fn detect(data: &Bound<'_, PyAny>, batch_size: usize) -> PyResult<Option<Self>> {
+        let batch_size = batch_size.max(1);
+        if data.is_instance_of::<PyList>() || data.is_instance_of::<PyDict>() {
+            return Ok(None);
+        }
+        if data.hasattr("fetchmany")? && data.hasattr("description")? {
+            let description = data.getattr("description")?;
+            if description.is_none() {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    "Cursor has no result set; execute a query before passing it to run()",
+                ));
+            }
+            let columns = description
+                .iter()?
+                .map(|col| col?.get_item(0)?.extract::<String>())
+                .collect::<PyResult<Vec<_>>>()?;
+            return Ok(Some(Self { source: Source::Cursor { cursor: data.clone().unbind(), columns }, batch_size, names: FieldNames::default() }));
+        }
+        if data.hasattr("__next__")? {
+            return Ok(Some(Self { source: Source::Iter(data.clone().unbind()), batch_size, names: FieldNames::default() }));
+        }
+        if data.hasattr("read_next_batch")? {
+            let iter = data.iter()?;
+            return Ok(Some(Self { source: Source::Iter(iter.into_any().unbind()), batch_size, names: FieldNames::default() }));
+        }
+        Ok(None)
+    }
+
+    /// 拉取下一批 bar；数据源耗尽时返回 `None`（空批次会被跳过）
+    pub(crate) fn next_chunk(&mut self, py: Python<'_>) -> PyResult<Option<Vec<BarData>>> {
+        match &self.source {
+            Source::Iter(iter) => {
+                let mut chunk: Vec<BarData> = Vec::new();
+                loop {
+                    let item = match iter.call_method0(py, "__next__") {
+                        Ok(item) => item.into_bound(py),
+                        Err(err) if err.is_instance_of::<PyStopIteration>(py) => break,
+                        Err(err) => return Err(err),
+                    };
+                    match single_bar(&item, &mut self.names)? {
+                        Some(bar) => {
+                            chunk.push(bar);
+                            if chunk.len() >= self.batch_size {
+                                break;
+                            }
+                        }
+                        None => {
+                            chunk.extend(extract_bars_any(item.as_gil_ref())?);
+                            if !chunk.is_empty() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                Ok(if chunk.is_empty() { None } else { Some(chunk) })
+            }
+            Source::Cursor { cursor, columns } => {
+                let rows = cursor.call_method1(py, "fetchmany", (self.batch_size,))?;
+                let mut chunk = Vec::new();
+                for row in rows.bind(py).iter()? {
+                    chunk.push(row_bar(columns, &row?, &mut self.names)?);
+                }
+                Ok(if chunk.is_empty() { None } else { Some(chunk) })
+            }
+        }
+    }
+}
+
+/// 迭代器元素是单根 bar 字典（`close` 为标量）时提取它，否则返回 `None`（视为一批数据）
+fn single_bar(item: &Bound<'_, PyAny>, names: &mut FieldNames) -> PyResult<Option<BarData>> {
+    let Ok(dict) = item.downcast::<PyDict>() else { return Ok(None) };
+    match dict.get_item("close")? {
+        Some(close) if close.extract::<f64>().is_ok() => Ok(Some(extract_bar(dict.as_gil_ref(), names)?)),
+        _ => Ok(None),
+    }
+}
+
+/// 把游标的一行映射为 bar；数值列无法转换时为 0，整数时间视为 epoch 毫秒，其他非字符串的时间值取 `str()`，
+/// 其他列中的数值与字符串保存为额外字段
+fn row_bar(columns: &[String], row: &Bound<'_, PyAny>, names: &mut FieldNames) -> PyResult<BarData> {
+    let row = match row.downcast::<PyTuple>() {
+        Ok(t) => t.clone(),
+        Err(_) => PyTuple::new_bound(row.py(), row.iter()?.collect::<PyResult<Vec<_>>>()?),
+    };
+    let mut bar = BarData {
+        datetime: None,
+        timestamp: None,
+        open: 0.0,
+        high: 0.0,
+        low: 0.0,
+        close: 0.0,
+        volume: 0.0,
+        symbol: None,
+        extra: Vec::new(),
+        parsed: ParsedFields::default(),
+    };
+    for (name, value) in columns.iter().zip(row.iter()) {
+        let value = value.into_gil_ref();
+        match name.as_str() {
+            "datetime" if !value.is_none() => {
+                (bar.datetime, bar.timestamp) = match extract_datetime(value) {
+                    (Some(dt), ts) => (Some(dt), ts),
+                    (None, _) => (Some(value.str()?.to_string()), None),
+                };
+            }
+            "open" => bar.open = bar.parsed.number(name, value),
+            "high" => bar.high = bar.parsed.number(name, value),
+            "low" => bar.low = bar.parsed.number(name, value),
+            "close" => bar.close = bar.parsed.number(name, value),
+            "volume" => bar.volume = bar.parsed.number(name, value),
+            "symbol" => bar.symbol = value.extract::<String>().ok(),
+            other if bar_fields::is_extra(other) => {
+                if let Some(v) = FieldValue::extract(value) {
+                    bar.extra.push((names.intern(other), v));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(bar)
+}
@@ -0,0 +1,204 @@
+//! 引擎内多周期重采样模块
+//!
+//! 多周期策略（如 1 分钟入场、15 分钟和日线判断趋势）以前只能先用 `resample_klines`
+//! 生成各周期数据，再交给 `run_multi` 按时间对齐；对齐规则稍有不慎就会引入未来数据。
+//! 这个模块让单数据源的 `run()` 在主循环中同步维护更高周期的 K 线，逐根增量更新后传给策略。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 每个周期维护一根"正在形成"的 K 线：主周期 bar 到来时，按其时间向下取整到周期边界
+//! 2. 仍在同一周期内则合并 OHLCV（Open 不变、High/Low 取极值、Close 取最新、Volume 累加），
+//!    进入新周期则开始新的一根
+//! 3. 策略在 `next(bar, ctx)` 中通过 `bar["timeframes"]["15m"]` 读取截至当前 bar 的高周期 K 线，
+//!    其中只包含已经发生的主周期 bar，不含未来数据
+//! 4. `complete` 表示当前 bar 是否为该周期的最后一根（与交易日结束判断相同，只看下一根 bar 的时间）
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! class MyStrategy:
+//!     def next(self, bar, ctx):
+//!         daily = bar["timeframes"]["1d"]
+//!         m15 = bar["timeframes"]["15m"]
+//!         if m15["complete"] and m15["close"] > daily["open"]:
+//!             return "BUY"
+//!
+//! result = engine.run(MyStrategy(), bars_1m, timeframes=["15m", "1d"])
+//! ```
+//!
+//! ## 支持的周期格式
+//!
+//! 与 `resample_klines` 相同：`"15m"`、`"1h"`、`"1d"`、`"1w"`、`"1mo"`（或 `"1M"`）、`"1y"`。
+//! 日内周期在每天 00:00 对齐；周线以周一为起点，月线、年线以自然月、自然年为起点。
+//!
+//! # 注意事项
+//!
+//! - 所有 bar 的 `datetime` 必须可解析，否则返回 `ValueError`
+//! - 高周期 K 线的 `datetime` 为周期起始时间
+//! - 从检查点恢复时，引擎会用已处理的 bar 重建各周期状态
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::database::parse_datetime;
+use crate::BarData;
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 周期划分方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bucket {
+    /// 日内分钟周期（在每天 00:00 对齐）
+    Minutes(u32),
+    Days(i64),
+    Weeks(i64),
+    Months(i32),
+    Years(i32),
+}
+
+impl Bucket {
+    /// 解析周期字符串（如 "15m"、"1h"、"1d"、"1w"、"1mo"、"1y"）
+    fn parse(period: &str) -> Option<Self> {
+        let split = period.find(|c: char| !c.is_ascii_digit()).unwrap_or(period.len());
+        let n: i64 = period[..split].parse().ok().filter(|n| *n > 0)?;
+        let bucket = match &period[split..] {
+            "m" => Self::minutes(n)?,
+            "h" | "H" => Self::minutes(n * 60)?,
+            "d" | "D" => Self::Days(n),
+            "w" | "W" => Self::Weeks(n),
+            "mo" | "M" => Self::Months(n as i32),
+            "y" | "Y" => Self::Years(n as i32),
+            _ => return None,
+        };
+        Some(bucket)
+    }
+
+    /// 日内周期必须能整除一天；整天的分钟数按日周期处理
+    fn minutes(n: i64) -> Option<Self> {
+        if n % 1440 == 0 {
+            Some(Self::Days(n / 1440))
+        } else if n < 1440 {
+            Some(Self::Minutes(n as u32))
+        } else {
+            None
+        }
+    }
+
+    /// 时间所在周期的起始时间
+    fn start(self, dt: NaiveDateTime) -> NaiveDateTime {
+        let date = dt.date();
+        let midnight = |d: NaiveDate| d.and_hms_opt(0, 0, 0).unwrap_or(dt);
+        // 公元元年 1 月 1 日为第 1 天（周一）
+        let day_number = date.num_days_from_ce() as i64 - 1;
+        let from_day_number = |days: i64| NaiveDate::from_num_days_from_ce_opt((days + 1) as i32).unwrap_or(date);
+        match self {
+            Bucket::Minutes(n) => {
+                let minutes = (dt.hour() * 60 + dt.minute()) / n * n;
+                date.and_hms_opt(minutes / 60, minutes % 60, 0).unwrap_or(dt)
+            }
+            Bucket::Days(n) => midnight(from_day_number(day_number.div_euclid(n) * n)),
+            Bucket::Weeks(n) => {
+                let week = day_number.div_euclid(7);
+                midnight(from_day_number(week.div_euclid(n) * n * 7))
+            }
+            Bucket::Months(n) => {
+                let month = (date.year() * 12 + date.month0() as i32).div_euclid(n) * n;
+                midnight(NaiveDate::from_ymd_opt(month.div_euclid(12), month.rem_euclid(12) as u32 + 1, 1).unwrap_or(date))
+            }
+            Bucket::Years(n) => midnight(NaiveDate::from_ymd_opt(date.year().div_euclid(n) * n, 1, 1).unwrap_or(date)),
+        }
+    }
+}
+
+/// 单个周期的增量重采样状态
+#[derive(Clone, Debug)]
+struct Frame {
+    period: String,
+    bucket: Bucket,
+    /// 正在形成的 K 线的周期起始时间
+    start: Option<NaiveDateTime>,
+    bar: Option<BarData>,
+    complete: bool,
+}
+
+/// 单数据源回测中维护的一组高周期 K 线
+#[derive(Clone, Debug)]
+pub(crate) struct Timeframes {
+    frames: Vec<Frame>,
+}
+
+impl Timeframes {
+    /// 解析周期列表；无法识别或重复的周期返回 `ValueError`
+    pub(crate) fn new(periods: &[String]) -> PyResult<Self> {
+        let mut frames: Vec<Frame> = Vec::with_capacity(periods.len());
+        for period in periods {
+            let bucket = Bucket::parse(period).ok_or_else(|| value_error(format!("Unsupported timeframe: {}", period)))?;
+            if frames.iter().any(|f| &f.period == period) {
+                return Err(value_error(format!("Duplicate timeframe: {}", period)));
+            }
+            frames.push(Frame { period: period.clone(), bucket, start: None, bar: None, complete: false });
+        }
+        Ok(Self { frames })
+    }
+
+    /// 用一根主周期 bar 更新所有周期；`next` 为下一根 bar（`None` 表示数据结束），用于判断周期是否结束
+    pub(crate) fn update(&mut self, bar: &BarData, next: Option<&BarData>) -> PyResult<()> {
+        let dt = bar_datetime(bar)?;
+        let next_dt = match next {
+            Some(next) => Some(bar_datetime(next)?),
+            None => None,
+        };
+        for frame in &mut self.frames {
+            let start = frame.bucket.start(dt);
+            match (&mut frame.bar, frame.start == Some(start)) {
+                (Some(agg), true) => {
+                    agg.high = agg.high.max(bar.high);
+                    agg.low = agg.low.min(bar.low);
+                    agg.close = bar.close;
+                    agg.volume += bar.volume;
+                }
+                _ => {
+                    frame.start = Some(start);
+                    frame.bar = Some(BarData {
+                        datetime: Some(start.format("%Y-%m-%d %H:%M:%S").to_string()),
+                        timestamp: bar.timestamp.map(|_| start.and_utc().timestamp_millis()),
+                        ..bar.clone()
+                    });
+                }
+            }
+            frame.complete = next_dt.is_none_or(|n| frame.bucket.start(n) != start);
+        }
+        Ok(())
+    }
+
+    /// 转换为策略看到的 `bar["timeframes"]` 字典：`{周期: {datetime, open, high, low, close, volume, complete}}`
+    pub(crate) fn to_py<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let out = PyDict::new_bound(py);
+        for frame in &self.frames {
+            let Some(bar) = &frame.bar else {
+                out.set_item(&frame.period, py.None())?;
+                continue;
+            };
+            let d = PyDict::new_bound(py);
+            d.set_item("datetime", &bar.datetime)?;
+            d.set_item("open", bar.open)?;
+            d.set_item("high", bar.high)?;
+            d.set_item("low", bar.low)?;
+            d.set_item("close", bar.close)?;
+            d.set_item("volume", bar.volume)?;
+            d.set_item("complete", frame.complete)?;
+            out.set_item(&frame.period, d)?;
+        }
+        Ok(out)
+    }
+}
+
+fn bar_datetime(bar: &BarData) -> PyResult<NaiveDateTime> {
+    bar.datetime
+        .as_deref()
+        .and_then(parse_datetime)
+        .ok_or_else(|| value_error(format!("timeframes require a parseable datetime on every bar, got {:?}", bar.datetime)))
+}
@@ -0,0 +1,170 @@
+//! 波动率目标模块
+//!
+//! 固定仓位的策略在市场平静时风险偏低、剧烈波动时风险骤增，收益曲线的波动随行情大起大落。
+//! 波动率目标（volatility targeting）按近期波动动态缩放仓位，让组合的年化波动率保持在目标附近。
+//! `BacktestConfig(vol_target=0.10)` 开启后，引擎在策略信号与撮合之间按目标缩放持仓，策略本身不需要改动。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. 引擎记录每个标的最近 `vol_window` 根 bar（多资产回测为联合时间线的时间点）的收益率
+//! 2. 订单（或目标权重）给出成交后的目标持仓，按目标持仓的净值权重 `w` 与收益率的样本协方差 `Σ`
+//!    估计组合年化波动率 `σ = sqrt(wᵀΣw × 年化周期数)`（年化周期数与统计指标相同，取交易日历）
+//! 3. 缩放系数 `k = vol_target ÷ σ`，不超过 `vol_max_scale`；目标持仓乘以 `k` 后与当前持仓的差额作为实际订单
+//! 4. 目标权重（`run_multi()` 返回权重时）整体乘以 `k` 后再换算为订单
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! # 年化波动率目标 10%，用最近 60 根 bar 估计，最多放大到 1.5 倍
+//! cfg = BacktestConfig("2020-01-01", "2024-12-31", 1_000_000, vol_target=0.10, vol_window=60, vol_max_scale=1.5)
+//! result = BacktestEngine(cfg).run(MyStrategy(), bars)
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 波动率由标的的历史收益率估计，而不是回测净值：净值本身已经被缩放过，用它估计会自我反馈
+//! - 收益率不足 `vol_window` 个时不缩放，订单按原数量执行；预热 bar 同样计入收益率
+//! - 只缩放增加持仓的订单：不跨过 0 的减仓、平仓订单按原数量执行；缩放后方向与原订单相反
+//!   （当前持仓已经超过缩放后的目标）时订单被拒绝，`on_order` 收到 `reason="vol_target"`
+//! - 缩放在敞口限制（`max_gross_exposure` 等）之前进行，超限时仍会被截断或拒绝
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::OrderSide;
+
+/// 各标的最近若干根 bar 的收益率
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ReturnWindow {
+    /// 上一次的价格
+    last: HashMap<String, f64>,
+    /// 最近的收益率（最多 `window` 个）
+    returns: HashMap<String, VecDeque<f64>>,
+}
+
+impl ReturnWindow {
+    /// 记录一个时间点的价格（每个时间点对所有已知标的调用一次，价格未更新的标的收益率为 0）
+    pub(crate) fn push(&mut self, symbol: &str, price: f64, window: usize) {
+        if !(price.is_finite() && price > 0.0) {
+            return;
+        }
+        if let Some(last) = self.last.insert(symbol.to_string(), price) {
+            let series = self.returns.entry(symbol.to_string()).or_default();
+            series.push_back(price / last - 1.0);
+            while series.len() > window {
+                series.pop_front();
+            }
+        }
+    }
+
+    /// 两个标的最近 `n` 个收益率的样本协方差
+    fn covariance(&self, a: &str, b: &str, n: usize) -> Option<f64> {
+        let (ra, rb) = (self.returns.get(a)?, self.returns.get(b)?);
+        if n < 2 || ra.len() < n || rb.len() < n {
+            return None;
+        }
+        let ra = ra.iter().skip(ra.len() - n);
+        let rb = rb.iter().skip(rb.len() - n);
+        let (ma, mb) = (
+            ra.clone().sum::<f64>() / n as f64,
+            rb.clone().sum::<f64>() / n as f64,
+        );
+        let cov = ra.zip(rb).map(|(x, y)| (x - ma) * (y - mb)).sum::<f64>() / (n - 1) as f64;
+        Some(cov)
+    }
+}
+
+/// 波动率目标设置
+#[derive(Clone, Debug)]
+pub(crate) struct VolTarget {
+    /// 年化波动率目标
+    target: f64,
+    /// 估计波动率的收益率个数
+    window: usize,
+    /// 缩放系数上限
+    max_scale: f64,
+    /// 年化周期数
+    periods_per_year: f64,
+}
+
+impl VolTarget {
+    /// 由配置构造；未配置 `vol_target` 时返回 `None`
+    pub(crate) fn from_config(
+        target: Option<f64>,
+        window: usize,
+        max_scale: f64,
+        periods_per_year: f64,
+    ) -> Result<Option<Self>, String> {
+        let Some(target) = target else { return Ok(None) };
+        if !(target.is_finite() && target > 0.0) {
+            return Err(format!("vol_target must be a positive number, got {}", target));
+        }
+        if window < 2 {
+            return Err(format!("vol_window must be at least 2, got {}", window));
+        }
+        if !(max_scale.is_finite() && max_scale > 0.0) {
+            return Err(format!("vol_max_scale must be a positive number, got {}", max_scale));
+        }
+        Ok(Some(Self { target, window, max_scale, periods_per_year }))
+    }
+
+    /// 收益率窗口长度
+    pub(crate) fn window(&self) -> usize {
+        self.window
+    }
+
+    /// 目标持仓（净值权重）的缩放系数；有标的收益率不足 `window` 个时返回 `None`
+    pub(crate) fn scale(&self, returns: &ReturnWindow, weights: &[(&str, f64)]) -> Option<f64> {
+        let mut variance = 0.0;
+        for (a, wa) in weights.iter().filter(|(_, w)| *w != 0.0) {
+            for (b, wb) in weights.iter().filter(|(_, w)| *w != 0.0) {
+                variance += wa * wb * returns.covariance(a, b, self.window)?;
+            }
+        }
+        let vol = (variance.max(0.0) * self.periods_per_year).sqrt();
+        if vol <= 1e-12 {
+            // 没有持仓或价格不动：按上限放大（没有持仓时缩放不影响结果）
+            return Some(self.max_scale);
+        }
+        Some((self.target / vol).min(self.max_scale))
+    }
+
+    /// 按缩放后的目标持仓调整订单数量；返回 0 表示订单应被拒绝
+    ///
+    /// `others` 为其他标的当前的净值权重。
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn adjust_order(
+        &self,
+        returns: &ReturnWindow,
+        side: OrderSide,
+        size: f64,
+        symbol: &str,
+        price: f64,
+        position: f64,
+        equity: f64,
+        others: &[(&str, f64)],
+    ) -> f64 {
+        let signed = match side { OrderSide::Buy => size, OrderSide::Sell => -size };
+        let target = position + signed;
+        // 不跨过 0 的减仓按原数量执行
+        if target * position >= 0.0 && target.abs() < position.abs() {
+            return size;
+        }
+        if !(equity > 0.0 && price > 0.0) {
+            return size;
+        }
+        let mut weights = others.to_vec();
+        weights.push((symbol, target * price / equity));
+        let Some(scale) = self.scale(returns, &weights) else { return size };
+        let delta = scale * target - position;
+        if delta * signed <= 0.0 {
+            return 0.0;
+        }
+        // 原数量为整数时缩放后向下取整
+        if size.fract() == 0.0 {
+            return (delta.abs() + 1e-9).floor();
+        }
+        delta.abs()
+    }
+}
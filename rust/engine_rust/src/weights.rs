@@ -0,0 +1,223 @@
+//! 组合目标权重模块
+//!
+//! 多资产策略往往想表达的是"每个标的占净值多少"，而不是"买卖多少股"：原先 `next_multi()` 只能返回
+//! 订单，策略得自己用 `ctx` 里的净值和价格把权重换算成数量、处理整手、判断偏离多少才值得调仓。
+//! 这个模块让 `run_multi()` 的策略直接返回目标权重，由引擎按当前净值和最新价格换算成订单。
+//!
+//! ## 工作原理（简单理解）
+//!
+//! 1. `next_multi()`（或 `on_rebalance()`）返回 `{symbol: 权重}` 字典，或与 `feeds` 顺序一致的权重列表 / numpy 数组
+//! 2. 引擎按本时间点的价格计算组合净值，目标数量 = 权重 × 净值 ÷ 最新价格，再按每手数量向零取整
+//! 3. 当前权重与目标权重之差不超过 `rebalance_band` 的标的不调仓，避免为微小偏离反复交易
+//! 4. 目标与当前持仓的差额生成市价单，先卖后买，按普通订单撮合（滑点、手续费、交易时段都照常生效）
+//!
+//! ## 实际使用场景
+//!
+//! ```python
+//! class RiskParity:
+//!     def next_multi(self, update_slice, ctx):
+//!         return {"510300": 0.6, "511010": 0.4}   # 60% 股票 ETF，40% 债券 ETF
+//!
+//! result = engine.run_multi(RiskParity(), feeds, lot_size={"510300": 100, "511010": 10}, rebalance_band=0.02)
+//! ```
+//!
+//! # 注意事项
+//!
+//! - 字典只调整其中列出的标的，未列出的持仓保持不变；清仓需要显式给出 `0`。列表 / 数组覆盖全部 feed
+//! - 权重可以为负（空头），合计可以不等于 1（超过 1 即为加杠杆）；换算时不考虑手续费
+//! - 还没有收到过价格的标的本时间点跳过
+//! - 同一时间点 `next_multi()` 与 `on_rebalance()` 都返回权重时合并处理，同一标的以 `on_rebalance()` 为准
+
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyFloat, PyList, PyLong, PyTuple};
+
+use crate::{Order, OrderSide, OrderType};
+
+fn value_error(msg: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(msg)
+}
+
+/// 是否为数值（布尔值除外）
+fn is_number(value: &Bound<'_, PyAny>) -> bool {
+    (value.is_instance_of::<PyFloat>() || value.is_instance_of::<PyLong>())
+        && !value.is_instance_of::<pyo3::types::PyBool>()
+}
+
+fn weight_value(symbol: &str, value: &Bound<'_, PyAny>) -> PyResult<f64> {
+    let weight: f64 = value.extract()?;
+    if !weight.is_finite() {
+        return Err(value_error(format!("Target weight for '{}' must be a finite number, got {}", symbol, weight)));
+    }
+    Ok(weight)
+}
+
+/// 识别策略返回的目标权重；不是权重（订单、订单列表、`None` 等）时返回 `None`
+///
+/// `feed_symbols` 为各 feed 对应的标的，列表 / 数组形式的权重按位置对应。
+pub(crate) fn parse_weights(action: &Bound<'_, PyAny>, feed_symbols: &[String]) -> PyResult<Option<Vec<(String, f64)>>> {
+    // 订单字典带有 action 字段；权重字典的值全部是数值
+    if let Ok(dict) = action.downcast::<PyDict>() {
+        if dict.is_empty() || dict.contains("action")? || !dict.values().iter().all(|v| is_number(&v)) {
+            return Ok(None);
+        }
+        return dict
+            .iter()
+            .map(|(k, v)| {
+                let symbol: String = k.extract()?;
+                let weight = weight_value(&symbol, &v)?;
+                Ok((symbol, weight))
+            })
+            .collect::<PyResult<Vec<_>>>()
+            .map(Some);
+    }
+
+    // numpy 数组等：先转换为列表
+    let list = if action.is_instance_of::<PyList>() || action.is_instance_of::<PyTuple>() {
+        action.clone()
+    } else if action.hasattr("tolist")? && action.hasattr("dtype")? {
+        action.call_method0("tolist")?
+    } else {
+        return Ok(None);
+    };
+    let items = list.iter()?.collect::<PyResult<Vec<_>>>()?;
+    if items.is_empty() || !items.iter().all(is_number) {
+        return Ok(None);
+    }
+    if items.len() != feed_symbols.len() {
+        return Err(value_error(format!(
+            "Target weight list has {} entries but run_multi has {} feeds",
+            items.len(),
+            feed_symbols.len()
+        )));
+    }
+    feed_symbols
+        .iter()
+        .zip(items)
+        .map(|(symbol, v)| Ok((symbol.clone(), weight_value(symbol, &v)?)))
+        .collect::<PyResult<Vec<_>>>()
+        .map(Some)
+}
+
+/// 把同一时间点的多组权重合并（后出现的覆盖先出现的同一标的）
+pub(crate) fn merge_weights(targets: &mut Vec<(String, f64)>, more: Vec<(String, f64)>) {
+    for (symbol, weight) in more {
+        match targets.iter_mut().find(|(s, _)| *s == symbol) {
+            Some(slot) => slot.1 = weight,
+            None => targets.push((symbol, weight)),
+        }
+    }
+}
+
+/// 每手数量：统一的数值或按标的设置
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LotSizes {
+    default: Option<f64>,
+    by_symbol: HashMap<String, f64>,
+}
+
+impl LotSizes {
+    /// 解析 `run_multi()` 的 `lot_size` 参数（数值或 `{symbol: 数值}`，必须为正数）
+    pub(crate) fn parse(value: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let positive = |name: &str, v: &Bound<'_, PyAny>| -> PyResult<f64> {
+            let lot: f64 = v.extract()?;
+            if !(lot.is_finite() && lot > 0.0) {
+                return Err(value_error(format!("lot_size for {} must be a positive number, got {}", name, lot)));
+            }
+            Ok(lot)
+        };
+        let Some(value) = value.filter(|v| !v.is_none()) else { return Ok(Self::default()) };
+        if let Ok(dict) = value.downcast::<PyDict>() {
+            let mut by_symbol = HashMap::with_capacity(dict.len());
+            for (k, v) in dict.iter() {
+                let symbol: String = k.extract()?;
+                let lot = positive(&format!("'{}'", symbol), &v)?;
+                by_symbol.insert(symbol, lot);
+            }
+            return Ok(Self { default: None, by_symbol });
+        }
+        Ok(Self { default: Some(positive("all symbols", value)?), by_symbol: HashMap::new() })
+    }
+
+    pub(crate) fn get(&self, symbol: &str) -> Option<f64> {
+        self.by_symbol.get(symbol).copied().or(self.default)
+    }
+}
+
+/// 目标权重换算为订单
+pub(crate) struct WeightSizer {
+    lots: LotSizes,
+    /// 权重偏离不超过该值时不调仓
+    band: f64,
+}
+
+impl WeightSizer {
+    pub(crate) fn new(lots: LotSizes, band: f64) -> PyResult<Self> {
+        if !(band.is_finite() && band >= 0.0) {
+            return Err(value_error(format!("rebalance_band must be a non-negative number, got {}", band)));
+        }
+        Ok(Self { lots, band })
+    }
+
+    /// 每手数量（订单字典的 `sizing` 换算同样使用）
+    pub(crate) fn lots(&self) -> &LotSizes {
+        &self.lots
+    }
+
+    /// 按当前净值与最新价格生成调仓订单（先卖后买）
+    pub(crate) fn orders(
+        &self,
+        targets: &[(String, f64)],
+        cash: f64,
+        positions: &HashMap<String, (f64, f64)>,
+        last_prices: &HashMap<String, f64>,
+        order_seq: &mut u64,
+    ) -> Vec<Order> {
+        let mut equity = cash;
+        for (sym, (p, _)) in positions.iter() {
+            if let Some(lp) = last_prices.get(sym) {
+                equity += p * lp;
+            }
+        }
+
+        let mut deltas: Vec<(&str, f64)> = Vec::with_capacity(targets.len());
+        for (symbol, weight) in targets {
+            let Some(&price) = last_prices.get(symbol).filter(|p| **p > 0.0) else { continue };
+            let current = positions.get(symbol).map_or(0.0, |(p, _)| *p);
+            if equity > 0.0 && (current * price / equity - weight).abs() <= self.band {
+                continue;
+            }
+            let mut target = weight * equity / price;
+            if let Some(lot) = self.lots.get(symbol) {
+                // 向零取整到整手（容忍浮点误差，2.9999999 手视为 3 手）
+                let lots = target / lot;
+                target = (lots + lots.signum() * 1e-9).trunc() * lot;
+            }
+            let delta = target - current;
+            if delta.abs() > 1e-9 {
+                deltas.push((symbol, delta));
+            }
+        }
+        // 先卖后买：卖出释放的现金先到账
+        deltas.sort_by_key(|(_, delta)| *delta > 0.0);
+
+        deltas
+            .into_iter()
+            .map(|(symbol, delta)| {
+                let id = *order_seq;
+                *order_seq += 1;
+                Order {
+                    id,
+                    side: if delta > 0.0 { OrderSide::Buy } else { OrderSide::Sell },
+                    otype: OrderType::Market,
+                    size: delta.abs(),
+                    limit_price: None,
+                    status: "submitted",
+                    symbol: symbol.to_string(),
+                    submitted_bar: 0,
+                }
+            })
+            .collect()
+    }
+}